@@ -9,8 +9,10 @@ use solana_sdk::{
 use raydium_amm_v3::accounts as raydium_accounts;
 use raydium_amm_v3::instruction as raydium_instruction;
 use raydium_amm_v3::states::{
-    AMM_CONFIG_SEED, OBSERVATION_SEED, OPERATION_SEED, POOL_SEED, POOL_VAULT_SEED, POSITION_SEED,
-    TICK_ARRAY_SEED,
+    tick_array_key, PoolState, TickArrayState, AMM_CONFIG_SEED, CONFIG_OWNER_PROPOSAL_SEED,
+    LOCKED_POSITION_SEED, OBSERVATION_SEED, OPERATION_SEED, POOL_SEED, POOL_STATS_SEED,
+    POOL_VAULT_SEED, POSITION_SEED, PROTOCOL_FEE_SPLIT_SEED, REWARD_MERKLE_CLAIM_SEED,
+    REWARD_MERKLE_SEED, TICK_ARRAY_SEED,
 };
 use std::rc::Rc;
 
@@ -76,6 +78,124 @@ pub fn update_amm_config_instr(
     Ok(instructions)
 }
 
+pub fn update_pool_creation_fee_instr(
+    config: &ClientConfig,
+    amm_config: Pubkey,
+    pool_creation_fee: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let admin = read_keypair_file(&config.admin_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::UpdateAmmConfig {
+            owner: admin.pubkey(),
+            amm_config,
+        })
+        .args(raydium_instruction::UpdatePoolCreationFee { pool_creation_fee })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn propose_config_owner_instr(
+    config: &ClientConfig,
+    amm_config: Pubkey,
+    new_owner: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let admin = read_keypair_file(&config.admin_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (config_owner_proposal, __bump) = Pubkey::find_program_address(
+        &[
+            CONFIG_OWNER_PROPOSAL_SEED.as_bytes(),
+            amm_config.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::ProposeConfigOwner {
+            owner: admin.pubkey(),
+            amm_config,
+            config_owner_proposal,
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::ProposeConfigOwner { new_owner })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn set_protocol_fee_split_instr(
+    config: &ClientConfig,
+    amm_config: Pubkey,
+    primary_destination: Pubkey,
+    secondary_destination: Pubkey,
+    protocol_fee_split_rate: u32,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let admin = read_keypair_file(&config.admin_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (protocol_fee_split, __bump) = Pubkey::find_program_address(
+        &[
+            PROTOCOL_FEE_SPLIT_SEED.as_bytes(),
+            amm_config.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::SetProtocolFeeSplit {
+            owner: admin.pubkey(),
+            amm_config,
+            protocol_fee_split,
+            primary_destination,
+            secondary_destination,
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::SetProtocolFeeSplit {
+            protocol_fee_split_rate,
+        })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn accept_config_owner_instr(
+    config: &ClientConfig,
+    amm_config: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (config_owner_proposal, __bump) = Pubkey::find_program_address(
+        &[
+            CONFIG_OWNER_PROPOSAL_SEED.as_bytes(),
+            amm_config.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::AcceptConfigOwner {
+            new_owner: program.payer(),
+            amm_config,
+            config_owner_proposal,
+        })
+        .args(raydium_instruction::AcceptConfigOwner)
+        .instructions()?;
+    Ok(instructions)
+}
+
 pub fn create_operation_account_instr(config: &ClientConfig) -> Result<Vec<Instruction>> {
     let payer = read_keypair_file(&config.admin_path)?;
     let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
@@ -120,6 +240,29 @@ pub fn update_operation_account_instr(
     Ok(instructions)
 }
 
+pub fn set_pool_status_bit_instr(
+    config: &ClientConfig,
+    pool_state: Pubkey,
+    bit: u8,
+    enable: bool,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let admin = read_keypair_file(&config.admin_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::SetPoolStatusBit {
+            authority: admin.pubkey(),
+            pool_state,
+        })
+        .args(raydium_instruction::SetPoolStatusBit { bit, enable })
+        .instructions()?;
+    Ok(instructions)
+}
+
 pub fn create_pool_instr(
     config: &ClientConfig,
     amm_config: Pubkey,
@@ -128,6 +271,7 @@ pub fn create_pool_instr(
     token_program_0: Pubkey,
     token_program_1: Pubkey,
     tick_array_bitmap: Pubkey,
+    pool_creation_fee_receiver: Option<Pubkey>,
     sqrt_price_x64: u128,
     open_time: u64,
 ) -> Result<Vec<Instruction>> {
@@ -185,6 +329,13 @@ pub fn create_pool_instr(
             system_program: system_program::id(),
             rent: sysvar::rent::id(),
         })
+        // the fund owner of the config, if it charges a pool creation fee
+        .accounts(
+            pool_creation_fee_receiver
+                .map(|key| AccountMeta::new(key, false))
+                .into_iter()
+                .collect::<Vec<_>>(),
+        )
         .args(raydium_instruction::CreatePool {
             sqrt_price_x64,
             open_time,
@@ -401,6 +552,108 @@ pub fn open_position_with_token22_nft_instr(
     Ok(instructions)
 }
 
+pub fn open_position_with_token_amounts_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+    token_vault_0: Pubkey,
+    token_vault_1: Pubkey,
+    token_mint_0: Pubkey,
+    token_mint_1: Pubkey,
+    nft_mint_key: Pubkey,
+    nft_to_owner: Pubkey,
+    user_token_account_0: Pubkey,
+    user_token_account_1: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    amount_0_desired: u64,
+    amount_1_desired: u64,
+    amount_0_min: u64,
+    amount_1_min: u64,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+    with_metadata: bool,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let nft_ata_token_account =
+        spl_associated_token_account::get_associated_token_address_with_program_id(
+            &program.payer(),
+            &nft_mint_key,
+            &spl_token_2022::id(),
+        );
+    let (protocol_position_key, __bump) = Pubkey::find_program_address(
+        &[
+            POSITION_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_lower, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_lower_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_upper, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_upper_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::OpenPositionWithToken22Nft {
+            payer: program.payer(),
+            position_nft_owner: nft_to_owner,
+            position_nft_mint: nft_mint_key,
+            position_nft_account: nft_ata_token_account,
+            pool_state: pool_account_key,
+            protocol_position: protocol_position_key,
+            tick_array_lower,
+            tick_array_upper,
+            personal_position: personal_position_key,
+            token_account_0: user_token_account_0,
+            token_account_1: user_token_account_1,
+            token_vault_0,
+            token_vault_1,
+            rent: sysvar::rent::id(),
+            system_program: system_program::id(),
+            token_program: spl_token::id(),
+            associated_token_program: spl_associated_token_account::id(),
+            token_program_2022: spl_token_2022::id(),
+            vault_0_mint: token_mint_0,
+            vault_1_mint: token_mint_1,
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::OpenPositionWithTokenAmounts {
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            amount_0_desired,
+            amount_1_desired,
+            amount_0_min,
+            amount_1_min,
+            with_metadata,
+        })
+        .instructions()?;
+    Ok(instructions)
+}
+
 pub fn increase_liquidity_instr(
     config: &ClientConfig,
     pool_account_key: Pubkey,
@@ -570,6 +823,117 @@ pub fn decrease_liquidity_instr(
     Ok(instructions)
 }
 
+/// The accounts of one side of `migrate_position`: the position, its pool and its nft account
+pub struct MigrationSide<'a> {
+    pub pool_account_key: Pubkey,
+    pub pool_state: &'a PoolState,
+    pub nft_mint_key: Pubkey,
+    pub nft_token_key: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+}
+
+impl MigrationSide<'_> {
+    fn position_keys(&self, program_id: &Pubkey) -> (Pubkey, Pubkey, Pubkey, Pubkey) {
+        let (personal_position_key, __bump) = Pubkey::find_program_address(
+            &[
+                POSITION_SEED.as_bytes(),
+                self.nft_mint_key.to_bytes().as_ref(),
+            ],
+            program_id,
+        );
+        let (protocol_position_key, __bump) = Pubkey::find_program_address(
+            &[
+                POSITION_SEED.as_bytes(),
+                self.pool_account_key.to_bytes().as_ref(),
+                &self.tick_lower_index.to_be_bytes(),
+                &self.tick_upper_index.to_be_bytes(),
+            ],
+            program_id,
+        );
+        let tick_array = |tick_index: i32| {
+            tick_array_key(
+                program_id,
+                &self.pool_account_key,
+                TickArrayState::get_array_start_index(tick_index, self.pool_state.tick_spacing),
+            )
+        };
+        (
+            personal_position_key,
+            protocol_position_key,
+            tick_array(self.tick_lower_index),
+            tick_array(self.tick_upper_index),
+        )
+    }
+}
+
+pub fn migrate_position_instr(
+    config: &ClientConfig,
+    source: &MigrationSide,
+    target: &MigrationSide,
+    user_token_account_0: Pubkey,
+    user_token_account_1: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    liquidity: u128,
+    amount_0_min: u64,
+    amount_1_min: u64,
+    target_liquidity_min: u128,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (
+        source_personal_position,
+        source_protocol_position,
+        source_tick_array_lower,
+        source_tick_array_upper,
+    ) = source.position_keys(&program.id());
+    let (
+        target_personal_position,
+        target_protocol_position,
+        target_tick_array_lower,
+        target_tick_array_upper,
+    ) = target.position_keys(&program.id());
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::MigratePosition {
+            nft_owner: program.payer(),
+            source_nft_account: source.nft_token_key,
+            source_personal_position,
+            source_pool_state: source.pool_account_key,
+            source_protocol_position,
+            source_token_vault_0: source.pool_state.token_vault_0,
+            source_token_vault_1: source.pool_state.token_vault_1,
+            source_tick_array_lower,
+            source_tick_array_upper,
+            target_nft_account: target.nft_token_key,
+            target_personal_position,
+            target_pool_state: target.pool_account_key,
+            target_protocol_position,
+            target_token_vault_0: target.pool_state.token_vault_0,
+            target_token_vault_1: target.pool_state.token_vault_1,
+            target_tick_array_lower,
+            target_tick_array_upper,
+            token_account_0: user_token_account_0,
+            token_account_1: user_token_account_1,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            vault_0_mint: source.pool_state.token_mint_0,
+            vault_1_mint: source.pool_state.token_mint_1,
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::MigratePosition {
+            liquidity,
+            amount_0_min,
+            amount_1_min,
+            target_liquidity_min,
+        })
+        .instructions()?;
+    Ok(instructions)
+}
+
 pub fn close_personal_position_instr(
     config: &ClientConfig,
     nft_mint_key: Pubkey,
@@ -600,29 +964,539 @@ pub fn close_personal_position_instr(
     Ok(instructions)
 }
 
-pub fn swap_instr(
+/// Close the position, or with `close_if_empty` leave it open if it isn't empty once the
+/// preceding instructions of the transaction ran
+pub fn close_personal_position_v2_instr(
     config: &ClientConfig,
-    amm_config: Pubkey,
-    pool_account_key: Pubkey,
-    input_vault: Pubkey,
-    output_vault: Pubkey,
-    observation_state: Pubkey,
-    user_input_token: Pubkey,
-    user_out_put_token: Pubkey,
-    tick_array: Pubkey,
-    remaining_accounts: Vec<AccountMeta>,
-    amount: u64,
-    other_amount_threshold: u64,
-    sqrt_price_limit_x64: Option<u128>,
-    is_base_input: bool,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    nft_token_program: Pubkey,
+    close_if_empty: bool,
 ) -> Result<Vec<Instruction>> {
     let payer = read_keypair_file(&config.payer_path)?;
     let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
     // Client.
     let client = Client::new(url, Rc::new(payer));
     let program = client.program(config.raydium_v3_program)?;
-    let instructions = program
-        .request()
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::ClosePositionV2 {
+            nft_owner: program.payer(),
+            position_nft_mint: nft_mint_key,
+            position_nft_account: nft_token_key,
+            personal_position: personal_position_key,
+            system_program: system_program::id(),
+            token_program: nft_token_program,
+        })
+        .args(raydium_instruction::ClosePositionV2 { close_if_empty })
+        .instructions()?;
+    Ok(instructions)
+}
+
+/// Set the fee recipient of the position, or clear it if `fee_recipient` is None
+/// Collect the fees and rewards of a position without changing its liquidity, a decrease of zero
+/// liquidity which skips the slippage check
+pub fn collect_fees_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+    token_vault_0: Pubkey,
+    token_vault_1: Pubkey,
+    token_mint_0: Pubkey,
+    token_mint_1: Pubkey,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    user_token_account_0: Pubkey,
+    user_token_account_1: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+) -> Result<Vec<Instruction>> {
+    decrease_liquidity_instr(
+        config,
+        pool_account_key,
+        token_vault_0,
+        token_vault_1,
+        token_mint_0,
+        token_mint_1,
+        nft_mint_key,
+        nft_token_key,
+        user_token_account_0,
+        user_token_account_1,
+        remaining_accounts,
+        0,
+        0,
+        0,
+        tick_lower_index,
+        tick_upper_index,
+        tick_array_lower_start_index,
+        tick_array_upper_start_index,
+    )
+}
+
+pub fn collect_reward_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let (protocol_position_key, __bump) = Pubkey::find_program_address(
+        &[
+            POSITION_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_lower, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_lower_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_upper, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_upper_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::CollectReward {
+            nft_owner: program.payer(),
+            nft_account: nft_token_key,
+            personal_position: personal_position_key,
+            pool_state: pool_account_key,
+            protocol_position: protocol_position_key,
+            tick_array_lower,
+            tick_array_upper,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::CollectReward)
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn compound_fees_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+    liquidity_min: u128,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let (protocol_position_key, __bump) = Pubkey::find_program_address(
+        &[
+            POSITION_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_lower, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_lower_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_upper, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_upper_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::CompoundFees {
+            nft_owner: program.payer(),
+            nft_account: nft_token_key,
+            pool_state: pool_account_key,
+            protocol_position: protocol_position_key,
+            personal_position: personal_position_key,
+            tick_array_lower,
+            tick_array_upper,
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::CompoundFees { liquidity_min })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn set_fee_recipient_instr(
+    config: &ClientConfig,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    fee_recipient: Option<Pubkey>,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let request = program
+        .request()
+        .accounts(raydium_accounts::SetFeeRecipient {
+            nft_owner: program.payer(),
+            nft_account: nft_token_key,
+            personal_position: personal_position_key,
+        });
+    let instructions = match fee_recipient {
+        Some(fee_recipient) => request
+            .args(raydium_instruction::SetFeeRecipient { fee_recipient })
+            .instructions()?,
+        None => request
+            .args(raydium_instruction::ClearFeeRecipient)
+            .instructions()?,
+    };
+    Ok(instructions)
+}
+
+pub fn transfer_position_instr(
+    config: &ClientConfig,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    nft_token_program: Pubkey,
+    new_owner: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::TransferPosition {
+            nft_owner: program.payer(),
+            new_owner,
+            position_nft_mint: nft_mint_key,
+            nft_account: nft_token_key,
+            recipient_nft_account:
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &new_owner,
+                    &nft_mint_key,
+                    &nft_token_program,
+                ),
+            personal_position: personal_position_key,
+            token_program: nft_token_program,
+            associated_token_program: spl_associated_token_account::id(),
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::TransferPosition)
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn lock_position_instr(
+    config: &ClientConfig,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    nft_token_program: Pubkey,
+    unlock_time: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let (locked_position_key, __bump) = Pubkey::find_program_address(
+        &[
+            LOCKED_POSITION_SEED.as_bytes(),
+            nft_mint_key.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::LockPosition {
+            nft_owner: program.payer(),
+            position_nft_mint: nft_mint_key,
+            nft_account: nft_token_key,
+            personal_position: personal_position_key,
+            locked_position: locked_position_key,
+            locked_nft_account:
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &locked_position_key,
+                    &nft_mint_key,
+                    &nft_token_program,
+                ),
+            token_program: nft_token_program,
+            associated_token_program: spl_associated_token_account::id(),
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::LockPosition { unlock_time })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn unlock_position_instr(
+    config: &ClientConfig,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    nft_token_program: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (locked_position_key, __bump) = Pubkey::find_program_address(
+        &[
+            LOCKED_POSITION_SEED.as_bytes(),
+            nft_mint_key.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::UnlockPosition {
+            nft_owner: program.payer(),
+            position_nft_mint: nft_mint_key,
+            nft_account: nft_token_key,
+            locked_position: locked_position_key,
+            locked_nft_account:
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &locked_position_key,
+                    &nft_mint_key,
+                    &nft_token_program,
+                ),
+            token_program: nft_token_program,
+        })
+        .args(raydium_instruction::UnlockPosition)
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn collect_locked_position_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+    token_vault_0: Pubkey,
+    token_vault_1: Pubkey,
+    token_mint_0: Pubkey,
+    token_mint_1: Pubkey,
+    nft_mint_key: Pubkey,
+    user_token_account_0: Pubkey,
+    user_token_account_1: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let (locked_position_key, __bump) = Pubkey::find_program_address(
+        &[
+            LOCKED_POSITION_SEED.as_bytes(),
+            nft_mint_key.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+    let (protocol_position_key, __bump) = Pubkey::find_program_address(
+        &[
+            POSITION_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_lower, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_lower_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let (tick_array_upper, __bump) = Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &tick_array_upper_start_index.to_be_bytes(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::CollectLockedPosition {
+            nft_owner: program.payer(),
+            locked_position: locked_position_key,
+            personal_position: personal_position_key,
+            pool_state: pool_account_key,
+            protocol_position: protocol_position_key,
+            token_vault_0,
+            token_vault_1,
+            tick_array_lower,
+            tick_array_upper,
+            recipient_token_account_0: user_token_account_0,
+            recipient_token_account_1: user_token_account_1,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            vault_0_mint: token_mint_0,
+            vault_1_mint: token_mint_1,
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::CollectLockedPosition)
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn update_position_metadata_instr(
+    config: &ClientConfig,
+    nft_mint_key: Pubkey,
+    nft_token_key: Pubkey,
+    pool_account_key: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let (metadata_account_key, _bump) = Pubkey::find_program_address(
+        &[
+            MPL_PREFIX.as_bytes(),
+            mpl_token_metadata::id().to_bytes().as_ref(),
+            nft_mint_key.to_bytes().as_ref(),
+        ],
+        &mpl_token_metadata::id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::UpdatePositionMetadata {
+            nft_owner: program.payer(),
+            nft_account: nft_token_key,
+            position_nft_mint: nft_mint_key,
+            personal_position: personal_position_key,
+            pool_state: pool_account_key,
+            metadata_account: metadata_account_key,
+            metadata_program: mpl_token_metadata::id(),
+            token_program_2022: spl_token_2022::id(),
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::UpdatePositionMetadata)
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn get_position_amounts_instr(
+    config: &ClientConfig,
+    nft_mint_key: Pubkey,
+    pool_account_key: Pubkey,
+    tick_array_lower: Pubkey,
+    tick_array_upper: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (personal_position_key, __bump) = Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint_key.to_bytes().as_ref()],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::GetPositionAmounts {
+            pool_state: pool_account_key,
+            personal_position: personal_position_key,
+            tick_array_lower,
+            tick_array_upper,
+        })
+        .args(raydium_instruction::GetPositionAmounts)
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn swap_instr(
+    config: &ClientConfig,
+    amm_config: Pubkey,
+    pool_account_key: Pubkey,
+    input_vault: Pubkey,
+    output_vault: Pubkey,
+    observation_state: Pubkey,
+    user_input_token: Pubkey,
+    user_out_put_token: Pubkey,
+    tick_array: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    amount: u64,
+    other_amount_threshold: u64,
+    sqrt_price_limit_x64: Option<u128>,
+    is_base_input: bool,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let instructions = program
+        .request()
         .accounts(raydium_accounts::SwapSingle {
             payer: program.payer(),
             amm_config,
@@ -696,6 +1570,106 @@ pub fn swap_v2_instr(
     Ok(instructions)
 }
 
+pub fn swap_router_base_in_instr(
+    config: &ClientConfig,
+    input_token_account: Pubkey,
+    input_token_mint: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::SwapRouterBaseIn {
+            payer: program.payer(),
+            input_token_account,
+            input_token_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            memo_program: spl_memo::id(),
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::SwapRouterBaseIn {
+            amount_in,
+            amount_out_minimum,
+        })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn swap_router_base_out_instr(
+    config: &ClientConfig,
+    input_token_account: Pubkey,
+    input_token_mint: Pubkey,
+    output_token_account: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    amount_out: u64,
+    amount_in_maximum: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::SwapRouterBaseOut {
+            payer: program.payer(),
+            input_token_account,
+            input_token_mint,
+            output_token_account,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            memo_program: spl_memo::id(),
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::SwapRouterBaseOut {
+            amount_out,
+            amount_in_maximum,
+        })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn swap_router_resume_base_in_instr(
+    config: &ClientConfig,
+    input_token_account: Pubkey,
+    input_token_mint: Pubkey,
+    remaining_accounts: Vec<AccountMeta>,
+    input_balance_before: u64,
+    amount_in_minimum: u64,
+    amount_out_minimum: u64,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::SwapRouterBaseIn {
+            payer: program.payer(),
+            input_token_account,
+            input_token_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            memo_program: spl_memo::id(),
+        })
+        .accounts(remaining_accounts)
+        .args(raydium_instruction::SwapRouterResumeBaseIn {
+            input_balance_before,
+            amount_in_minimum,
+            amount_out_minimum,
+        })
+        .instructions()?;
+    Ok(instructions)
+}
+
 pub fn initialize_reward_instr(
     config: &ClientConfig,
     pool_account_key: Pubkey,
@@ -812,3 +1786,144 @@ pub fn transfer_reward_owner(
         .instructions()?;
     Ok(instructions)
 }
+
+pub fn set_reward_merkle_root_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+    reward_token_vault: Pubkey,
+    reward_index: u8,
+    merkle_root: [u8; 32],
+    total_amount: u64,
+    snapshot_reward_claimed: u64,
+) -> Result<Vec<Instruction>> {
+    let admin = read_keypair_file(&config.admin_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(admin));
+    let program = client.program(config.raydium_v3_program)?;
+    let (operation_account_key, __bump) =
+        Pubkey::find_program_address(&[OPERATION_SEED.as_bytes()], &program.id());
+    let (reward_merkle_key, __bump) = Pubkey::find_program_address(
+        &[
+            REWARD_MERKLE_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &[reward_index],
+        ],
+        &program.id(),
+    );
+
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::SetRewardMerkleRoot {
+            authority: program.payer(),
+            operation_state: operation_account_key,
+            pool_state: pool_account_key,
+            reward_merkle_state: reward_merkle_key,
+            reward_token_vault,
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::SetRewardMerkleRoot {
+            reward_index,
+            merkle_root,
+            total_amount,
+            snapshot_reward_claimed,
+        })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn claim_merkle_reward_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+    reward_index: u8,
+    reward_token_vault: Pubkey,
+    reward_vault_mint: Pubkey,
+    recipient_token_account: Pubkey,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (reward_merkle_key, __bump) = Pubkey::find_program_address(
+        &[
+            REWARD_MERKLE_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+            &[reward_index],
+        ],
+        &program.id(),
+    );
+    let (claim_state_key, __bump) = Pubkey::find_program_address(
+        &[
+            REWARD_MERKLE_CLAIM_SEED.as_bytes(),
+            reward_merkle_key.to_bytes().as_ref(),
+            program.payer().to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::ClaimMerkleReward {
+            claimant: program.payer(),
+            pool_state: pool_account_key,
+            reward_merkle_state: reward_merkle_key,
+            claim_state: claim_state_key,
+            reward_token_vault,
+            recipient_token_account,
+            reward_vault_mint,
+            token_program: spl_token::id(),
+            token_program_2022: spl_token_2022::id(),
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::ClaimMerkleReward { amount, proof })
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn create_pool_stats_instr(
+    config: &ClientConfig,
+    pool_account_key: Pubkey,
+) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let (pool_stats_key, __bump) = Pubkey::find_program_address(
+        &[
+            POOL_STATS_SEED.as_bytes(),
+            pool_account_key.to_bytes().as_ref(),
+        ],
+        &program.id(),
+    );
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::CreatePoolStats {
+            payer: program.payer(),
+            pool_state: pool_account_key,
+            pool_stats: pool_stats_key,
+            system_program: system_program::id(),
+        })
+        .args(raydium_instruction::CreatePoolStats {})
+        .instructions()?;
+    Ok(instructions)
+}
+
+pub fn roll_stats_instr(config: &ClientConfig, pool_stats_key: Pubkey) -> Result<Vec<Instruction>> {
+    let payer = read_keypair_file(&config.payer_path)?;
+    let url = Cluster::Custom(config.http_url.clone(), config.ws_url.clone());
+    // Client.
+    let client = Client::new(url, Rc::new(payer));
+    let program = client.program(config.raydium_v3_program)?;
+    let instructions = program
+        .request()
+        .accounts(raydium_accounts::RollStats {
+            pool_stats: pool_stats_key,
+        })
+        .args(raydium_instruction::RollStats {})
+        .instructions()?;
+    Ok(instructions)
+}
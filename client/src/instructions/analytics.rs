@@ -0,0 +1,229 @@
+use anchor_lang::Discriminator;
+use anyhow::{format_err, Result};
+use raydium_amm_v3::libraries::{fixed_point_64, tick_math};
+use raydium_amm_v3::states::{AmmConfig, ObservationState, SwapEvent, FEE_RATE_DENOMINATOR_VALUE};
+use serde::Serialize;
+use solana_client::{rpc_client::RpcClient, rpc_config::RpcTransactionConfig};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
+use std::str::FromStr;
+
+use super::events_instructions_parse::{decode_event, handle_system_log, Execution};
+use super::position_history::owner_signatures;
+use super::utils::{from_x64_price, multipler};
+
+const PROGRAM_DATA: &str = "Program data: ";
+pub const SECONDS_PER_YEAR: f64 = 365.0 * 24.0 * 3600.0;
+/// The windows the APRs are estimated over
+pub const APR_WINDOWS: [(&str, u32); 2] = [("24h", 24 * 3600), ("7d", 7 * 24 * 3600)];
+
+/// The value in raw token_1 of one unit of liquidity over [tick_lower, tick_upper] at the price
+/// `sqrt_price_x64`, the amounts it holds with the token_0 part valued at that price
+pub fn liquidity_unit_value(sqrt_price_x64: u128, tick_lower: i32, tick_upper: i32) -> Result<f64> {
+    let sqrt_price = from_x64_price(sqrt_price_x64);
+    let sqrt_price_lower = from_x64_price(tick_math::get_sqrt_price_at_tick(tick_lower)?);
+    let sqrt_price_upper = from_x64_price(tick_math::get_sqrt_price_at_tick(tick_upper)?);
+    let sqrt_price_in_range = sqrt_price.clamp(sqrt_price_lower, sqrt_price_upper);
+    let amount_0 = 1.0 / sqrt_price_in_range - 1.0 / sqrt_price_upper;
+    let amount_1 = sqrt_price_in_range - sqrt_price_lower;
+    Ok(amount_0 * sqrt_price * sqrt_price + amount_1)
+}
+
+/// The price of a raw unit of a token in raw units of token_1, from its price in whole token_1
+/// per whole token
+pub fn raw_price(price: f64, decimals: u8, decimals_1: u8) -> f64 {
+    price * multipler(decimals_1) / multipler(decimals)
+}
+
+/// The reward tokens emitted per second to each unit of the liquidity in range
+pub fn reward_per_liquidity_per_second(emissions_per_second_x64: u128, liquidity: u128) -> f64 {
+    if liquidity == 0 {
+        return 0.0;
+    }
+    emissions_per_second_x64 as f64 / fixed_point_64::Q64 as f64 / liquidity as f64
+}
+
+/// The part of the trade fee of a swap paying `amount_in` that goes to the liquidity providers,
+/// the protocol and fund fees are taken out of the trade fee
+pub fn swap_lp_fee(amount_in: u64, amm_config: &AmmConfig) -> f64 {
+    let denominator = FEE_RATE_DENOMINATOR_VALUE as f64;
+    let trade_fee = amount_in as f64 * amm_config.trade_fee_rate as f64 / denominator;
+    let kept_rate = amm_config.protocol_fee_rate as f64 + amm_config.fund_fee_rate as f64;
+    trade_fee * (1.0 - kept_rate.min(denominator) / denominator)
+}
+
+/// The APR of earning `earned_per_liquidity` raw token_1 per unit of liquidity over `seconds`,
+/// against the value of a unit of liquidity
+pub fn annualized_rate(earned_per_liquidity: f64, seconds: u64, unit_value: f64) -> Option<f64> {
+    if seconds == 0 || unit_value <= 0.0 {
+        return None;
+    }
+    Some(earned_per_liquidity / unit_value * SECONDS_PER_YEAR / seconds as f64)
+}
+
+/// Parse a `<mint>=<price>` pair, the price of a reward token in token_1
+pub fn parse_reward_price(value: &str) -> Result<(Pubkey, f64)> {
+    let (mint, price) = value
+        .split_once('=')
+        .ok_or(format_err!("expected <mint>=<price>, got {}", value))?;
+    let mint = Pubkey::from_str(mint.trim())?;
+    let price: f64 = price.trim().parse()?;
+    if !price.is_finite() || price < 0.0 {
+        return Err(format_err!("invalid price {} for {}", price, mint));
+    }
+    Ok((mint, price))
+}
+
+/// The price the APRs of a window are valued at, the time weighted average over the window when
+/// the observations cover it and the spot price otherwise
+pub fn window_sqrt_price_x64(
+    observation_state: &ObservationState,
+    block_timestamp: u32,
+    tick_current: i32,
+    sqrt_price_x64: u128,
+    seconds: u32,
+) -> (u128, &'static str) {
+    match observation_state
+        .average_tick(block_timestamp, tick_current, seconds)
+        .and_then(tick_math::get_sqrt_price_at_tick)
+    {
+        Ok(sqrt_price_x64) => (sqrt_price_x64, "twap"),
+        Err(_) => (sqrt_price_x64, "spot"),
+    }
+}
+
+/// The fee a swap paid to the liquidity in range, per unit of that liquidity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwapFeeGrowth {
+    pub block_time: u64,
+    /// The fee per unit of liquidity in raw token_1, the token_0 fees valued at the price after
+    /// the swap
+    pub fee_per_liquidity: f64,
+}
+
+impl SwapFeeGrowth {
+    pub fn new(block_time: u64, event: &SwapEvent, amm_config: &AmmConfig) -> Option<Self> {
+        if event.liquidity == 0 {
+            return None;
+        }
+        let fee_value = if event.zero_for_one {
+            let price = from_x64_price(event.sqrt_price_x64).powi(2);
+            swap_lp_fee(
+                event.amount_0.saturating_sub(event.transfer_fee_0),
+                amm_config,
+            ) * price
+        } else {
+            swap_lp_fee(
+                event.amount_1.saturating_sub(event.transfer_fee_1),
+                amm_config,
+            )
+        };
+        Some(Self {
+            block_time,
+            fee_per_liquidity: fee_value / event.liquidity as f64,
+        })
+    }
+}
+
+/// Decode the swap events of `pool_id` the program emitted in the logs of a transaction
+pub fn decode_swap_events(
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+    logs: &[String],
+) -> Vec<SwapEvent> {
+    let program_str = program_id.to_string();
+    let mut events = Vec::new();
+    let mut logs = logs;
+    if logs.is_empty() {
+        return events;
+    }
+    let mut execution = match Execution::new(&mut logs) {
+        Ok(execution) => execution,
+        Err(_) => return events,
+    };
+    for log in logs {
+        if !execution.is_empty() && program_str == execution.program() {
+            if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+                let bytes = match anchor_lang::__private::base64::decode(data) {
+                    Ok(bytes) if bytes.len() >= 8 => bytes,
+                    _ => continue,
+                };
+                if bytes[..8] == SwapEvent::DISCRIMINATOR {
+                    let mut slice: &[u8] = &bytes[8..];
+                    if let Ok(event) = decode_event::<SwapEvent>(&mut slice) {
+                        if event.pool_state == *pool_id {
+                            events.push(event);
+                        }
+                    }
+                }
+                continue;
+            }
+        }
+        let (new_program, did_pop) = handle_system_log(&program_str, log);
+        if let Some(new_program) = new_program {
+            execution.push(new_program);
+        }
+        if did_pop {
+            execution.pop();
+        }
+    }
+    events
+}
+
+/// The fees paid by the swaps of the pool since `from_time`, oldest first. Only the
+/// `max_transactions` latest transactions are fetched, the time the returned swaps cover from is
+/// returned with them.
+pub fn load_swap_fee_growths(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+    amm_config: &AmmConfig,
+    from_time: u64,
+    max_transactions: usize,
+) -> Result<(Vec<SwapFeeGrowth>, u64)> {
+    let mut signatures = owner_signatures(rpc_client, pool_id, from_time)?;
+    let mut covered_from = from_time;
+    if signatures.len() > max_transactions {
+        signatures.drain(..signatures.len() - max_transactions);
+        covered_from = signatures.first().map_or(from_time, |(_, _, time)| *time);
+    }
+    let mut fee_growths = Vec::new();
+    for (signature, _, block_time) in signatures {
+        let tx = rpc_client.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        let logs = match tx.transaction.meta.map(|meta| meta.log_messages) {
+            Some(OptionSerializer::Some(logs)) => logs,
+            _ => continue,
+        };
+        fee_growths.extend(
+            decode_swap_events(program_id, pool_id, &logs)
+                .iter()
+                .filter_map(|event| SwapFeeGrowth::new(block_time, event, amm_config)),
+        );
+    }
+    Ok((fee_growths, covered_from))
+}
+
+/// An APR estimate of a pool over a window, of the trade fees or of one of the rewards
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AprRecord {
+    pub pool_id: String,
+    pub window: String,
+    /// fee, or reward_<index>
+    pub source: String,
+    /// The reward mint, empty for the fees
+    pub token_mint: String,
+    /// twap or spot, the price the liquidity and the token_0 amounts are valued at
+    pub price_source: String,
+    /// The seconds the estimate is made over, shorter than the window when the fees were only
+    /// fetched for part of it
+    pub seconds: u64,
+    /// none when the reward token has no price
+    pub apr: Option<f64>,
+}
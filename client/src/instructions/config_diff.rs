@@ -0,0 +1,160 @@
+use anyhow::{format_err, Result};
+use raydium_amm_v3::states::{AmmConfig, FEE_RATE_DENOMINATOR_VALUE};
+use solana_sdk::pubkey::Pubkey;
+use std::io::{self, BufRead, Write};
+use tracing::info;
+
+/// A changed field of the config, formatted for reading
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigFieldChange {
+    pub name: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+/// A fee rate in hundredths of a bip with its percent and bps
+pub fn format_fee_rate(rate: u32) -> String {
+    format!(
+        "{} ({}%, {} bps)",
+        rate,
+        rate as f64 * 100.0 / FEE_RATE_DENOMINATOR_VALUE as f64,
+        rate as f64 * 10_000.0 / FEE_RATE_DENOMINATOR_VALUE as f64
+    )
+}
+
+/// A share of the trade fee in hundredths of a bip with its percent
+pub fn format_fee_share(rate: u32) -> String {
+    format!(
+        "{} ({}% of the trade fee)",
+        rate,
+        rate as f64 * 100.0 / FEE_RATE_DENOMINATOR_VALUE as f64
+    )
+}
+
+/// The fields an update can change, formatted for reading
+pub fn amm_config_fields(config: &AmmConfig) -> Vec<(&'static str, String)> {
+    vec![
+        ("trade_fee_rate", format_fee_rate(config.trade_fee_rate)),
+        (
+            "protocol_fee_rate",
+            format_fee_share(config.protocol_fee_rate),
+        ),
+        ("fund_fee_rate", format_fee_share(config.fund_fee_rate)),
+        ("owner", config.owner.to_string()),
+        ("fund_owner", config.fund_owner.to_string()),
+        (
+            "max_initial_tick_deviation",
+            if config.max_initial_tick_deviation == 0 {
+                "0 (disabled)".to_string()
+            } else {
+                format!(
+                    "{} ticks (~{:.2}%)",
+                    config.max_initial_tick_deviation,
+                    (1.0001f64.powi(config.max_initial_tick_deviation as i32) - 1.0) * 100.0
+                )
+            },
+        ),
+        (
+            "swap_delay_after_create",
+            if config.swap_delay_after_create == 0 {
+                "0 (disabled)".to_string()
+            } else {
+                format!("{}s", config.swap_delay_after_create)
+            },
+        ),
+        (
+            "pool_creation_fee",
+            if config.pool_creation_fee == 0 {
+                "0 (disabled)".to_string()
+            } else {
+                format!(
+                    "{} lamports ({} SOL)",
+                    config.pool_creation_fee,
+                    config.pool_creation_fee as f64 / 1_000_000_000.0
+                )
+            },
+        ),
+    ]
+}
+
+/// Apply an `update_amm_config` to a copy of the config the way the program does, rejecting the
+/// values the program would reject
+pub fn apply_amm_config_update(
+    config: &AmmConfig,
+    param: u8,
+    value: u32,
+    remaining: Option<Pubkey>,
+) -> Result<AmmConfig> {
+    let mut updated = config.clone();
+    match param {
+        0 => {
+            if value >= FEE_RATE_DENOMINATOR_VALUE {
+                return Err(format_err!("trade_fee_rate must be less than 100%"));
+            }
+            updated.trade_fee_rate = value;
+        }
+        1 | 2 => {
+            let other = if param == 1 {
+                config.fund_fee_rate
+            } else {
+                config.protocol_fee_rate
+            };
+            if value + other > FEE_RATE_DENOMINATOR_VALUE {
+                return Err(format_err!(
+                    "protocol_fee_rate and fund_fee_rate together can't exceed 100% of the trade fee"
+                ));
+            }
+            if param == 1 {
+                updated.protocol_fee_rate = value;
+            } else {
+                updated.fund_fee_rate = value;
+            }
+        }
+        3 | 4 => {
+            let key = remaining.ok_or(format_err!("param {} needs the new key", param))?;
+            if param == 3 {
+                if config.two_step_owner_transfer {
+                    return Err(format_err!(
+                        "the owner can only be changed by propose-config-owner and accept-config-owner"
+                    ));
+                }
+                updated.owner = key;
+            } else {
+                updated.fund_owner = key;
+            }
+        }
+        5 => updated.max_initial_tick_deviation = value,
+        6 => updated.swap_delay_after_create = value,
+        _ => return Err(format_err!("unknown param {}", param)),
+    }
+    Ok(updated)
+}
+
+/// The fields that differ between the configs
+pub fn amm_config_diff(old: &AmmConfig, new: &AmmConfig) -> Vec<ConfigFieldChange> {
+    amm_config_fields(old)
+        .into_iter()
+        .zip(amm_config_fields(new))
+        .filter(|((_, old), (_, new))| old != new)
+        .map(|((name, old), (_, new))| ConfigFieldChange { name, old, new })
+        .collect()
+}
+
+/// Print the diff and ask for an explicit yes, unless `assume_yes`
+pub fn confirm_config_diff(changes: &[ConfigFieldChange], assume_yes: bool) -> Result<bool> {
+    if changes.is_empty() {
+        info!("the update doesn't change the config");
+        return Ok(false);
+    }
+    for change in changes {
+        info!("{}: {} -> {}", change.name, change.old, change.new);
+    }
+    if assume_yes {
+        return Ok(true);
+    }
+    print!("send the update? type yes to confirm: ");
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().lock().read_line(&mut line)?;
+    Ok(matches!(line.trim().to_lowercase().as_str(), "y" | "yes"))
+}
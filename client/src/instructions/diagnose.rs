@@ -0,0 +1,145 @@
+use anyhow::{format_err, Result};
+use solana_client::{pubsub_client::PubsubClient, rpc_client::RpcClient};
+use solana_sdk::commitment_config::CommitmentConfig;
+use std::time::{Duration, Instant};
+
+/// Average latency above which a request is reported as slow
+pub const SLOW_LATENCY: Duration = Duration::from_millis(1000);
+/// Slots the websocket can be behind the rpc before it is reported as stale
+pub const STALE_SLOT_LAG: u64 = 10;
+
+/// Latency of one kind of rpc request over several samples
+#[derive(Debug, Default)]
+pub struct LatencyStats {
+    pub name: String,
+    pub samples: Vec<Duration>,
+    pub errors: Vec<String>,
+}
+
+impl LatencyStats {
+    /// Run the request `samples` times and record the latency of the successful ones
+    pub fn measure<T, F>(name: &str, samples: u32, mut request: F) -> Self
+    where
+        F: FnMut() -> Result<T>,
+    {
+        let mut stats = LatencyStats {
+            name: name.to_string(),
+            ..Default::default()
+        };
+        for _ in 0..samples {
+            let start = Instant::now();
+            match request() {
+                Ok(_) => stats.samples.push(start.elapsed()),
+                Err(err) => stats.errors.push(err.to_string()),
+            }
+        }
+        stats
+    }
+
+    pub fn min(&self) -> Option<Duration> {
+        self.samples.iter().min().copied()
+    }
+
+    pub fn max(&self) -> Option<Duration> {
+        self.samples.iter().max().copied()
+    }
+
+    pub fn avg(&self) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<Duration>() / self.samples.len() as u32)
+    }
+
+    pub fn status(&self) -> &'static str {
+        match self.avg() {
+            None => "FAILING",
+            Some(_) if !self.errors.is_empty() => "FLAKY",
+            Some(avg) if avg > SLOW_LATENCY => "SLOW",
+            Some(_) => "OK",
+        }
+    }
+
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "{:<24} {:<8} ok:{}/{}",
+            self.name,
+            self.status(),
+            self.samples.len(),
+            self.samples.len() + self.errors.len()
+        );
+        if let (Some(min), Some(avg), Some(max)) = (self.min(), self.avg(), self.max()) {
+            report += &format!(
+                ", min:{}ms, avg:{}ms, max:{}ms",
+                min.as_millis(),
+                avg.as_millis(),
+                max.as_millis()
+            );
+        }
+        if let Some(err) = self.errors.first() {
+            report += &format!(", first error:{}", err);
+        }
+        report
+    }
+}
+
+/// Lag of the websocket slot notifications behind the rpc node
+#[derive(Debug)]
+pub struct WsSlotLag {
+    /// Time from subscribing to the first slot notification
+    pub first_notification: Duration,
+    /// The slot notified by the websocket
+    pub ws_slot: u64,
+    /// The processed slot of the rpc node when the notification is received
+    pub rpc_slot: u64,
+}
+
+impl WsSlotLag {
+    pub fn slot_lag(&self) -> u64 {
+        self.rpc_slot.saturating_sub(self.ws_slot)
+    }
+
+    pub fn status(&self) -> &'static str {
+        if self.slot_lag() > STALE_SLOT_LAG {
+            "STALE"
+        } else if self.first_notification > SLOW_LATENCY {
+            "SLOW"
+        } else {
+            "OK"
+        }
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "{:<24} {:<8} first notification:{}ms, ws_slot:{}, rpc_slot:{}, lag:{} slots",
+            "slotSubscribe",
+            self.status(),
+            self.first_notification.as_millis(),
+            self.ws_slot,
+            self.rpc_slot,
+            self.slot_lag()
+        )
+    }
+}
+
+/// Subscribe to slot notifications and compare the first notified slot with the rpc slot
+pub fn measure_ws_slot_lag(
+    rpc_client: &RpcClient,
+    ws_url: &str,
+    timeout: Duration,
+) -> Result<WsSlotLag> {
+    let start = Instant::now();
+    let (mut subscription, receiver) =
+        PubsubClient::slot_subscribe(ws_url).map_err(|err| format_err!("{}", err))?;
+    let slot_info = receiver.recv_timeout(timeout);
+    let first_notification = start.elapsed();
+    let _ = subscription.shutdown();
+    let slot_info = slot_info
+        .map_err(|_| format_err!("no slot notification within {}ms", timeout.as_millis()))?;
+    let rpc_slot = rpc_client.get_slot_with_commitment(CommitmentConfig::processed())?;
+    Ok(WsSlotLag {
+        first_notification,
+        ws_slot: slot_info.slot,
+        rpc_slot,
+    })
+}
@@ -11,6 +11,7 @@ use regex::Regex;
 use solana_transaction_status::{
     option_serializer::OptionSerializer, EncodedTransaction, UiTransactionStatusMeta,
 };
+use tracing::info;
 
 const PROGRAM_LOG: &str = "Program log: ";
 const PROGRAM_DATA: &str = "Program data: ";
@@ -42,7 +43,7 @@ pub fn parse_program_event(
                 let (new_program, did_pop) =
                     if !execution.is_empty() && self_program_str == execution.program() {
                         handle_program_log(self_program_str, &l, true).unwrap_or_else(|e| {
-                            println!("Unable to parse log: {e}");
+                            info!("Unable to parse log: {e}");
                             std::process::exit(1);
                         })
                     } else {
@@ -60,12 +61,12 @@ pub fn parse_program_event(
             }
         }
     } else {
-        println!("log is empty");
+        info!("log is empty");
     }
     Ok(())
 }
 
-struct Execution {
+pub(crate) struct Execution {
     stack: Vec<String>,
 }
 
@@ -126,7 +127,7 @@ pub fn handle_program_log(
         let borsh_bytes = match anchor_lang::__private::base64::decode(log) {
             Ok(borsh_bytes) => borsh_bytes,
             _ => {
-                println!("Could not base64 decode log: {}", log);
+                info!("Could not base64 decode log: {}", log);
                 return Ok((None, false));
             }
         };
@@ -140,52 +141,85 @@ pub fn handle_program_log(
         };
         match disc {
             ConfigChangeEvent::DISCRIMINATOR => {
-                println!("{:#?}", decode_event::<ConfigChangeEvent>(&mut slice)?);
+                info!("{:#?}", decode_event::<ConfigChangeEvent>(&mut slice)?);
             }
             CollectPersonalFeeEvent::DISCRIMINATOR => {
-                println!(
+                info!(
                     "{:#?}",
                     decode_event::<CollectPersonalFeeEvent>(&mut slice)?
                 );
             }
             CollectProtocolFeeEvent::DISCRIMINATOR => {
-                println!(
+                info!(
                     "{:#?}",
                     decode_event::<CollectProtocolFeeEvent>(&mut slice)?
                 );
             }
             CreatePersonalPositionEvent::DISCRIMINATOR => {
-                println!(
+                info!(
                     "{:#?}",
                     decode_event::<CreatePersonalPositionEvent>(&mut slice)?
                 );
             }
             DecreaseLiquidityEvent::DISCRIMINATOR => {
-                println!("{:#?}", decode_event::<DecreaseLiquidityEvent>(&mut slice)?);
+                info!("{:#?}", decode_event::<DecreaseLiquidityEvent>(&mut slice)?);
             }
             IncreaseLiquidityEvent::DISCRIMINATOR => {
-                println!("{:#?}", decode_event::<IncreaseLiquidityEvent>(&mut slice)?);
+                info!("{:#?}", decode_event::<IncreaseLiquidityEvent>(&mut slice)?);
             }
             LiquidityCalculateEvent::DISCRIMINATOR => {
-                println!(
+                info!(
                     "{:#?}",
                     decode_event::<LiquidityCalculateEvent>(&mut slice)?
                 );
             }
             LiquidityChangeEvent::DISCRIMINATOR => {
-                println!("{:#?}", decode_event::<LiquidityChangeEvent>(&mut slice)?);
+                info!("{:#?}", decode_event::<LiquidityChangeEvent>(&mut slice)?);
             }
             // PriceChangeEvent::DISCRIMINATOR => {
-            //     println!("{:#?}", decode_event::<PriceChangeEvent>(&mut slice)?);
+            //     info!("{:#?}", decode_event::<PriceChangeEvent>(&mut slice)?);
             // }
             SwapEvent::DISCRIMINATOR => {
-                println!("{:#?}", decode_event::<SwapEvent>(&mut slice)?);
+                info!("{:#?}", decode_event::<SwapEvent>(&mut slice)?);
+            }
+            FlashEvent::DISCRIMINATOR => {
+                info!("{:#?}", decode_event::<FlashEvent>(&mut slice)?);
+            }
+            TransferPositionEvent::DISCRIMINATOR => {
+                info!("{:#?}", decode_event::<TransferPositionEvent>(&mut slice)?);
+            }
+            CompoundFeesEvent::DISCRIMINATOR => {
+                info!("{:#?}", decode_event::<CompoundFeesEvent>(&mut slice)?);
             }
             PoolCreatedEvent::DISCRIMINATOR => {
-                println!("{:#?}", decode_event::<PoolCreatedEvent>(&mut slice)?);
+                info!("{:#?}", decode_event::<PoolCreatedEvent>(&mut slice)?);
+            }
+            PoolStatusChangedEvent::DISCRIMINATOR => {
+                info!("{:#?}", decode_event::<PoolStatusChangedEvent>(&mut slice)?);
+            }
+            LockPositionEvent::DISCRIMINATOR => {
+                info!("{:#?}", decode_event::<LockPositionEvent>(&mut slice)?);
+            }
+            UnlockPositionEvent::DISCRIMINATOR => {
+                info!("{:#?}", decode_event::<UnlockPositionEvent>(&mut slice)?);
+            }
+            ConfigOwnerProposedEvent::DISCRIMINATOR => {
+                info!(
+                    "{:#?}",
+                    decode_event::<ConfigOwnerProposedEvent>(&mut slice)?
+                );
+            }
+            CollectRewardEvent::DISCRIMINATOR => {
+                info!("{:#?}", decode_event::<CollectRewardEvent>(&mut slice)?);
+            }
+            SetProtocolFeeSplitEvent::DISCRIMINATOR => {
+                info!(
+                    "{:#?}",
+                    decode_event::<SetProtocolFeeSplitEvent>(&mut slice)?
+                );
             }
             _ => {
-                println!("unknow event: {}", l);
+                info!("unknow event: {}", l);
             }
         }
         return Ok((None, false));
@@ -195,7 +229,7 @@ pub fn handle_program_log(
     }
 }
 
-fn handle_system_log(this_program_str: &str, log: &str) -> (Option<String>, bool) {
+pub(crate) fn handle_system_log(this_program_str: &str, log: &str) -> (Option<String>, bool) {
     if log.starts_with(&format!("Program {this_program_str} invoke")) {
         (Some(this_program_str.to_string()), false)
     } else if log.contains("invoke") {
@@ -210,7 +244,7 @@ fn handle_system_log(this_program_str: &str, log: &str) -> (Option<String>, bool
     }
 }
 
-fn decode_event<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
+pub(crate) fn decode_event<T: anchor_lang::Event + anchor_lang::AnchorDeserialize>(
     slice: &mut &[u8],
 ) -> Result<T, ClientError> {
     let event: T = anchor_lang::AnchorDeserialize::deserialize(slice)
@@ -226,7 +260,7 @@ pub fn parse_program_instruction(
     let ui_raw_msg = match encoded_transaction {
         solana_transaction_status::EncodedTransaction::Json(ui_tx) => {
             let ui_message = ui_tx.message;
-            // println!("{:#?}", ui_message);
+            // info!("{:#?}", ui_message);
             match ui_message {
                 solana_transaction_status::UiMessage::Raw(ui_raw_msg) => ui_raw_msg,
                 _ => solana_transaction_status::UiRawMessage {
@@ -263,12 +297,12 @@ pub fn parse_program_instruction(
             .iter()
             .position(|r| r == self_program_str)
             .unwrap();
-        // println!("{}", program_index);
-        // println!("{:#?}", account_keys);
+        // info!("{}", program_index);
+        // info!("{:#?}", account_keys);
         for (i, ui_compiled_instruction) in ui_raw_msg.instructions.iter().enumerate() {
             if (ui_compiled_instruction.program_id_index as usize) == program_index {
                 let out_put = format!("instruction #{}", i + 1);
-                println!("{}", out_put.gradient(Color::Green));
+                info!("{}", out_put.gradient(Color::Green));
                 handle_program_instruction(
                     &ui_compiled_instruction.data,
                     InstructionDecodeType::Base58,
@@ -289,7 +323,7 @@ pub fn parse_program_instruction(
                                 {
                                     let out_put =
                                         format!("inner_instruction #{}.{}", inner.index + 1, i + 1);
-                                    println!("{}", out_put.gradient(Color::Green));
+                                    info!("{}", out_put.gradient(Color::Green));
                                     handle_program_instruction(
                                         &ui_compiled_instruction.data,
                                         InstructionDecodeType::Base58,
@@ -320,7 +354,7 @@ pub fn handle_program_instruction(
             let borsh_bytes = match anchor_lang::__private::base64::decode(instr_data) {
                 Ok(borsh_bytes) => borsh_bytes,
                 _ => {
-                    println!("Could not base64 decode instruction: {}", instr_data);
+                    info!("Could not base64 decode instruction: {}", instr_data);
                     return Ok(());
                 }
             };
@@ -330,7 +364,7 @@ pub fn handle_program_instruction(
             let borsh_bytes = match bs58::decode(instr_data).into_vec() {
                 Ok(borsh_bytes) => borsh_bytes,
                 _ => {
-                    println!("Could not base58 decode instruction: {}", instr_data);
+                    info!("Could not base58 decode instruction: {}", instr_data);
                     return Ok(());
                 }
             };
@@ -345,7 +379,7 @@ pub fn handle_program_instruction(
         ix_data = &ix_data[8..];
         disc
     };
-    // println!("{:?}", disc);
+    // info!("{:?}", disc);
 
     match disc {
         instruction::CreateAmmConfig::DISCRIMINATOR => {
@@ -369,7 +403,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", CreateAmmConfig::from(ix));
+            info!("{:#?}", CreateAmmConfig::from(ix));
         }
         instruction::UpdateAmmConfig::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::UpdateAmmConfig>(&mut ix_data).unwrap();
@@ -386,7 +420,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", UpdateAmmConfig::from(ix));
+            info!("{:#?}", UpdateAmmConfig::from(ix));
         }
         instruction::CreatePool::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::CreatePool>(&mut ix_data).unwrap();
@@ -403,7 +437,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", CreatePool::from(ix));
+            info!("{:#?}", CreatePool::from(ix));
         }
         instruction::UpdatePoolStatus::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::UpdatePoolStatus>(&mut ix_data).unwrap();
@@ -418,7 +452,24 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", UpdatePoolStatus::from(ix));
+            info!("{:#?}", UpdatePoolStatus::from(ix));
+        }
+        instruction::SetPoolStatusBit::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::SetPoolStatusBit>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct SetPoolStatusBit {
+                pub bit: u8,
+                pub enable: bool,
+            }
+            impl From<instruction::SetPoolStatusBit> for SetPoolStatusBit {
+                fn from(instr: instruction::SetPoolStatusBit) -> SetPoolStatusBit {
+                    SetPoolStatusBit {
+                        bit: instr.bit,
+                        enable: instr.enable,
+                    }
+                }
+            }
+            info!("{:#?}", SetPoolStatusBit::from(ix));
         }
         instruction::CreateOperationAccount::DISCRIMINATOR => {
             let ix =
@@ -430,7 +481,7 @@ pub fn handle_program_instruction(
                     CreateOperationAccount
                 }
             }
-            println!("{:#?}", CreateOperationAccount::from(ix));
+            info!("{:#?}", CreateOperationAccount::from(ix));
         }
         instruction::UpdateOperationAccount::DISCRIMINATOR => {
             let ix =
@@ -448,7 +499,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", UpdateOperationAccount::from(ix));
+            info!("{:#?}", UpdateOperationAccount::from(ix));
         }
         instruction::TransferRewardOwner::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::TransferRewardOwner>(&mut ix_data).unwrap();
@@ -463,7 +514,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", TransferRewardOwner::from(ix));
+            info!("{:#?}", TransferRewardOwner::from(ix));
         }
         instruction::InitializeReward::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::InitializeReward>(&mut ix_data).unwrap();
@@ -476,7 +527,7 @@ pub fn handle_program_instruction(
                     InitializeReward { param: instr.param }
                 }
             }
-            println!("{:#?}", InitializeReward::from(ix));
+            info!("{:#?}", InitializeReward::from(ix));
         }
         instruction::CollectRemainingRewards::DISCRIMINATOR => {
             let ix =
@@ -492,7 +543,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", CollectRemainingRewards::from(ix));
+            info!("{:#?}", CollectRemainingRewards::from(ix));
         }
         instruction::UpdateRewardInfos::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::UpdateRewardInfos>(&mut ix_data).unwrap();
@@ -503,7 +554,7 @@ pub fn handle_program_instruction(
                     UpdateRewardInfos
                 }
             }
-            println!("{:#?}", UpdateRewardInfos::from(ix));
+            info!("{:#?}", UpdateRewardInfos::from(ix));
         }
         instruction::SetRewardParams::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::SetRewardParams>(&mut ix_data).unwrap();
@@ -524,7 +575,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", SetRewardParams::from(ix));
+            info!("{:#?}", SetRewardParams::from(ix));
         }
         instruction::CollectProtocolFee::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::CollectProtocolFee>(&mut ix_data).unwrap();
@@ -541,7 +592,22 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", CollectProtocolFee::from(ix));
+            info!("{:#?}", CollectProtocolFee::from(ix));
+        }
+        instruction::SetProtocolFeeSplit::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::SetProtocolFeeSplit>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct SetProtocolFeeSplit {
+                pub protocol_fee_split_rate: u32,
+            }
+            impl From<instruction::SetProtocolFeeSplit> for SetProtocolFeeSplit {
+                fn from(instr: instruction::SetProtocolFeeSplit) -> SetProtocolFeeSplit {
+                    SetProtocolFeeSplit {
+                        protocol_fee_split_rate: instr.protocol_fee_split_rate,
+                    }
+                }
+            }
+            info!("{:#?}", SetProtocolFeeSplit::from(ix));
         }
         instruction::CollectFundFee::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::CollectFundFee>(&mut ix_data).unwrap();
@@ -558,7 +624,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", CollectFundFee::from(ix));
+            info!("{:#?}", CollectFundFee::from(ix));
         }
         instruction::OpenPosition::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::OpenPosition>(&mut ix_data).unwrap();
@@ -585,7 +651,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", OpenPosition::from(ix));
+            info!("{:#?}", OpenPosition::from(ix));
         }
         instruction::OpenPositionV2::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::OpenPositionV2>(&mut ix_data).unwrap();
@@ -616,7 +682,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", OpenPositionV2::from(ix));
+            info!("{:#?}", OpenPositionV2::from(ix));
         }
         instruction::ClosePosition::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::ClosePosition>(&mut ix_data).unwrap();
@@ -627,7 +693,22 @@ pub fn handle_program_instruction(
                     ClosePosition
                 }
             }
-            println!("{:#?}", ClosePosition::from(ix));
+            info!("{:#?}", ClosePosition::from(ix));
+        }
+        instruction::ClosePositionV2::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::ClosePositionV2>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct ClosePositionV2 {
+                pub close_if_empty: bool,
+            }
+            impl From<instruction::ClosePositionV2> for ClosePositionV2 {
+                fn from(instr: instruction::ClosePositionV2) -> ClosePositionV2 {
+                    ClosePositionV2 {
+                        close_if_empty: instr.close_if_empty,
+                    }
+                }
+            }
+            info!("{:#?}", ClosePositionV2::from(ix));
         }
         instruction::IncreaseLiquidity::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::IncreaseLiquidity>(&mut ix_data).unwrap();
@@ -646,7 +727,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", IncreaseLiquidity::from(ix));
+            info!("{:#?}", IncreaseLiquidity::from(ix));
         }
         instruction::IncreaseLiquidityV2::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::IncreaseLiquidityV2>(&mut ix_data).unwrap();
@@ -667,7 +748,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", IncreaseLiquidityV2::from(ix));
+            info!("{:#?}", IncreaseLiquidityV2::from(ix));
         }
         instruction::DecreaseLiquidity::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::DecreaseLiquidity>(&mut ix_data).unwrap();
@@ -686,7 +767,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", DecreaseLiquidity::from(ix));
+            info!("{:#?}", DecreaseLiquidity::from(ix));
         }
         instruction::DecreaseLiquidityV2::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::DecreaseLiquidityV2>(&mut ix_data).unwrap();
@@ -705,7 +786,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", DecreaseLiquidityV2::from(ix));
+            info!("{:#?}", DecreaseLiquidityV2::from(ix));
         }
         instruction::Swap::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::Swap>(&mut ix_data).unwrap();
@@ -726,7 +807,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", Swap::from(ix));
+            info!("{:#?}", Swap::from(ix));
         }
         instruction::SwapV2::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::SwapV2>(&mut ix_data).unwrap();
@@ -747,7 +828,7 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", SwapV2::from(ix));
+            info!("{:#?}", SwapV2::from(ix));
         }
         instruction::SwapRouterBaseIn::DISCRIMINATOR => {
             let ix = decode_instruction::<instruction::SwapRouterBaseIn>(&mut ix_data).unwrap();
@@ -764,10 +845,64 @@ pub fn handle_program_instruction(
                     }
                 }
             }
-            println!("{:#?}", SwapRouterBaseIn::from(ix));
+            info!("{:#?}", SwapRouterBaseIn::from(ix));
+        }
+        instruction::SwapRouterResumeBaseIn::DISCRIMINATOR => {
+            let ix =
+                decode_instruction::<instruction::SwapRouterResumeBaseIn>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct SwapRouterResumeBaseIn {
+                pub input_balance_before: u64,
+                pub amount_in_minimum: u64,
+                pub amount_out_minimum: u64,
+            }
+            impl From<instruction::SwapRouterResumeBaseIn> for SwapRouterResumeBaseIn {
+                fn from(instr: instruction::SwapRouterResumeBaseIn) -> SwapRouterResumeBaseIn {
+                    SwapRouterResumeBaseIn {
+                        input_balance_before: instr.input_balance_before,
+                        amount_in_minimum: instr.amount_in_minimum,
+                        amount_out_minimum: instr.amount_out_minimum,
+                    }
+                }
+            }
+            info!("{:#?}", SwapRouterResumeBaseIn::from(ix));
+        }
+        instruction::Flash::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::Flash>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct Flash {
+                pub amount_0: u64,
+                pub amount_1: u64,
+                pub data: Vec<u8>,
+            }
+            impl From<instruction::Flash> for Flash {
+                fn from(instr: instruction::Flash) -> Flash {
+                    Flash {
+                        amount_0: instr.amount_0,
+                        amount_1: instr.amount_1,
+                        data: instr.data,
+                    }
+                }
+            }
+            info!("{:#?}", Flash::from(ix));
+        }
+        instruction::CompoundFees::DISCRIMINATOR => {
+            let ix = decode_instruction::<instruction::CompoundFees>(&mut ix_data).unwrap();
+            #[derive(Debug)]
+            pub struct CompoundFees {
+                pub liquidity_min: u128,
+            }
+            impl From<instruction::CompoundFees> for CompoundFees {
+                fn from(instr: instruction::CompoundFees) -> CompoundFees {
+                    CompoundFees {
+                        liquidity_min: instr.liquidity_min,
+                    }
+                }
+            }
+            info!("{:#?}", CompoundFees::from(ix));
         }
         _ => {
-            println!("unknow instruction: {}", instr_data);
+            info!("unknow instruction: {}", instr_data);
         }
     }
     Ok(())
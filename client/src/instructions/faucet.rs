@@ -0,0 +1,222 @@
+use anyhow::{format_err, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    native_token::{lamports_to_sol, sol_to_lamports},
+    program_option::COption,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account, Mint},
+};
+use std::str::FromStr;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use super::super::ClientConfig;
+use super::rpc::{send_txn, with_compute_budget};
+use super::telemetry::txn_span;
+use super::token_instructions::{create_ata_token_account_instr, spl_token_mint_to_instr};
+
+/// Genesis hash of mainnet-beta, the faucet refuses to run against it
+pub const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+/// Seconds waited for the confirmation of an airdrop
+const AIRDROP_CONFIRM_SECS: u64 = 30;
+
+/// Funding of the payer in devnet workflows, read from the [Faucet] section
+#[derive(Clone, Debug, PartialEq)]
+pub struct FaucetConfig {
+    /// Fund the payer before the commands that spend tokens
+    pub auto: bool,
+    /// An airdrop is requested when the payer holds fewer lamports
+    pub min_lamports: u64,
+    /// Lamports requested per airdrop, the devnet faucet grants at most 2 sol a request
+    pub airdrop_lamports: u64,
+    /// Whole tokens the payer's account of each test mint is topped up to, 0 disables minting
+    pub test_token_amount: u64,
+    /// The mints to top up, the mints of [Pool] when empty
+    pub test_mints: Vec<Pubkey>,
+}
+
+impl Default for FaucetConfig {
+    fn default() -> Self {
+        Self {
+            auto: false,
+            min_lamports: sol_to_lamports(1.0),
+            airdrop_lamports: sol_to_lamports(1.0),
+            test_token_amount: 1_000_000,
+            test_mints: Vec::new(),
+        }
+    }
+}
+
+impl FaucetConfig {
+    /// The lamports to request for a payer holding `balance`, none above the threshold
+    pub fn airdrop_amount(&self, balance: u64) -> Option<u64> {
+        if balance < self.min_lamports && self.airdrop_lamports > 0 {
+            Some(self.airdrop_lamports)
+        } else {
+            None
+        }
+    }
+
+    /// The raw amount to mint to a token account holding `balance` of a mint with `decimals`
+    pub fn mint_amount(&self, balance: u64, decimals: u8) -> Option<u64> {
+        let target = self
+            .test_token_amount
+            .saturating_mul(10u64.saturating_pow(decimals.into()));
+        match target.saturating_sub(balance) {
+            0 => None,
+            amount => Some(amount),
+        }
+    }
+}
+
+/// Fail on mainnet, where there is no faucet and no test token to mint
+pub fn check_not_mainnet(rpc_client: &RpcClient) -> Result<()> {
+    if rpc_client.get_genesis_hash()? == Hash::from_str(MAINNET_GENESIS_HASH)? {
+        return Err(format_err!("the faucet doesn't run on mainnet"));
+    }
+    Ok(())
+}
+
+/// Airdrop sol to the payer when its balance is below the threshold and wait for the airdrop to
+/// be confirmed. Returns the lamports airdropped.
+pub fn ensure_sol_balance(
+    rpc_client: &RpcClient,
+    payer: &Pubkey,
+    faucet: &FaucetConfig,
+) -> Result<u64> {
+    let balance = rpc_client.get_balance(payer)?;
+    let lamports = match faucet.airdrop_amount(balance) {
+        Some(lamports) => lamports,
+        None => {
+            debug!(
+                "payer holds {} sol, no airdrop needed",
+                lamports_to_sol(balance)
+            );
+            return Ok(0);
+        }
+    };
+    info!(
+        "payer holds {} sol, requesting an airdrop of {} sol",
+        lamports_to_sol(balance),
+        lamports_to_sol(lamports)
+    );
+    let signature = rpc_client.request_airdrop(payer, lamports)?;
+    for _ in 0..AIRDROP_CONFIRM_SECS {
+        if rpc_client
+            .confirm_transaction_with_commitment(&signature, CommitmentConfig::confirmed())?
+            .value
+        {
+            info!("airdrop confirmed: {}", signature);
+            return Ok(lamports);
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+    Err(format_err!(
+        "airdrop {} wasn't confirmed, the faucet may be rate limiting the payer",
+        signature
+    ))
+}
+
+/// Top up the payer's token account of each mint to the test amount, creating the account if
+/// needed. Mints whose authority isn't the payer are skipped, create test mints with `new-mint`.
+pub fn ensure_test_tokens(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    mints: &[Pubkey],
+) -> Result<()> {
+    let mut instructions = Vec::new();
+    for mint in mints {
+        let mint_account = match rpc_client
+            .get_account_with_commitment(mint, CommitmentConfig::confirmed())?
+            .value
+        {
+            Some(account) => account,
+            None => {
+                warn!("test mint {} doesn't exist, skipped", mint);
+                continue;
+            }
+        };
+        let mint_state = StateWithExtensions::<Mint>::unpack(&mint_account.data)?;
+        if mint_state.base.mint_authority != COption::Some(payer.pubkey()) {
+            warn!("the payer is not the mint authority of {}, skipped", mint);
+            continue;
+        }
+        let token_program = mint_account.owner;
+        let token_account =
+            get_associated_token_address_with_program_id(&payer.pubkey(), mint, &token_program);
+        let balance = match rpc_client
+            .get_account_with_commitment(&token_account, CommitmentConfig::confirmed())?
+            .value
+        {
+            Some(account) => {
+                StateWithExtensions::<Account>::unpack(&account.data)?
+                    .base
+                    .amount
+            }
+            None => {
+                instructions.extend(create_ata_token_account_instr(
+                    config,
+                    token_program,
+                    mint,
+                    &payer.pubkey(),
+                )?);
+                0
+            }
+        };
+        if let Some(amount) = config.faucet.mint_amount(balance, mint_state.base.decimals) {
+            info!("minting {} of {} to {}", amount, mint, token_account);
+            instructions.extend(spl_token_mint_to_instr(
+                config,
+                token_program,
+                mint,
+                &token_account,
+                amount,
+                payer,
+            )?);
+        }
+    }
+    if instructions.is_empty() {
+        return Ok(());
+    }
+    let span = txn_span("faucet");
+    let _enter = span.enter();
+    let recent_hash = rpc_client.get_latest_blockhash()?;
+    let txn = Transaction::new_signed_with_payer(
+        &with_compute_budget(
+            rpc_client,
+            &config.compute_budget,
+            &payer.pubkey(),
+            &instructions,
+        )?,
+        Some(&payer.pubkey()),
+        &[payer],
+        recent_hash,
+    );
+    send_txn(rpc_client, &txn, true)?;
+    Ok(())
+}
+
+/// Fund the payer for a devnet test scenario: sol for the fees and rents, then the test tokens
+/// of the configured mints
+pub fn run_faucet(rpc_client: &RpcClient, config: &ClientConfig, payer: &Keypair) -> Result<()> {
+    check_not_mainnet(rpc_client)?;
+    ensure_sol_balance(rpc_client, &payer.pubkey(), &config.faucet)?;
+    if config.faucet.test_token_amount == 0 {
+        return Ok(());
+    }
+    let mints: Vec<Pubkey> = if config.faucet.test_mints.is_empty() {
+        [config.mint0, config.mint1].into_iter().flatten().collect()
+    } else {
+        config.faucet.test_mints.clone()
+    };
+    ensure_test_tokens(rpc_client, config, payer, &mints)
+}
@@ -0,0 +1,701 @@
+use anchor_lang::prelude::AccountMeta;
+use anyhow::{format_err, Result};
+use raydium_amm_v3::libraries::{liquidity_math, tick_math};
+use raydium_amm_v3::states::{
+    AmmConfig, OperationState, PersonalPositionState, PoolState, TickArrayBitmapExtension,
+    TickArrayState, AMM_CONFIG_SEED, OPERATION_SEED, POOL_SEED, POOL_TICK_ARRAY_BITMAP_SEED,
+    POSITION_SEED, TICK_ARRAY_SEED,
+};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    native_token::LAMPORTS_PER_SOL,
+    pubkey::Pubkey,
+    signature::{Keypair, Signature, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_token_2022::{
+    extension::StateWithExtensions,
+    state::{Account, Mint},
+};
+use std::fmt::Display;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+use tracing::info;
+
+use super::super::{load_cur_and_next_five_tick_array, ClientConfig};
+use super::amm_instructions::*;
+use super::rpc::{send_txn, with_compute_budget};
+use super::token_instructions::*;
+use super::utils::{
+    amount_with_slippage, deserialize_anchor_account, get_out_put_amount_and_remaining_accounts,
+    price_to_sqrt_price_x64, slippage_to_tolerance_rate, CanonicalPair,
+};
+
+/// Genesis hash of mainnet-beta, test mints are never created there
+pub const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+/// The payer balance below which the wizard asks before going on
+pub const MIN_PAYER_LAMPORTS: u64 = LAMPORTS_PER_SOL;
+
+/// Parameters of the wizard, every one is asked again unless the defaults are accepted
+#[derive(Debug, Clone)]
+pub struct WizardOptions {
+    pub config_index: u16,
+    pub tick_spacing: u16,
+    pub trade_fee_rate: u32,
+    pub protocol_fee_rate: u32,
+    pub fund_fee_rate: u32,
+    /// Existing mints of the pool, new test mints are created if not set
+    pub mint0: Option<Pubkey>,
+    pub mint1: Option<Pubkey>,
+    pub mint_decimals: u8,
+    /// The amount minted to the payer for each new test mint
+    pub mint_amount: u64,
+    /// The price of token_1 in token_0 of the new pool
+    pub price: f64,
+    /// The token_0 amount deposited into the full range position
+    pub position_amount_0: u64,
+    /// The token_0 amount of the test swap
+    pub swap_amount_0: u64,
+    /// Accept all defaults without asking
+    pub assume_yes: bool,
+}
+
+/// Reads the answers of the operator from stdin
+pub struct Prompter {
+    assume_yes: bool,
+}
+
+impl Prompter {
+    pub fn new(assume_yes: bool) -> Self {
+        Self { assume_yes }
+    }
+
+    fn read_line(&self, question: &str) -> Result<String> {
+        print!("{} ", question);
+        io::stdout().flush()?;
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        Ok(line.trim().to_string())
+    }
+
+    /// Ask for a value, an empty answer keeps the default
+    pub fn ask<T>(&self, question: &str, default: T) -> Result<T>
+    where
+        T: FromStr + Display,
+        T::Err: Display,
+    {
+        if self.assume_yes {
+            return Ok(default);
+        }
+        loop {
+            let line = self.read_line(&format!("{} [{}]:", question, default))?;
+            if line.is_empty() {
+                return Ok(default);
+            }
+            match line.parse::<T>() {
+                Ok(value) => return Ok(value),
+                Err(err) => info!("invalid value {}: {}", line, err),
+            }
+        }
+    }
+
+    /// Ask for a yes or no, an empty answer is yes
+    pub fn confirm(&self, question: &str) -> Result<bool> {
+        if self.assume_yes {
+            return Ok(true);
+        }
+        let line = self.read_line(&format!("{} [Y/n]:", question))?;
+        Ok(!matches!(line.to_lowercase().as_str(), "n" | "no"))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WizardStepStatus {
+    /// The step created the account or sent the transaction
+    Done,
+    /// The account already exists and matches the parameters
+    Existing,
+    /// The operator skipped the step
+    Skipped,
+}
+
+/// Outcome of one step of the wizard
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WizardStepReport {
+    pub name: String,
+    pub status: WizardStepStatus,
+    pub detail: String,
+    pub signature: Option<String>,
+}
+
+/// Written to the summary file when the wizard ends
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WizardSummary {
+    pub genesis_hash: String,
+    pub payer: String,
+    pub amm_config: String,
+    pub operation_account: String,
+    pub mint0: String,
+    pub mint1: String,
+    pub pool_id: String,
+    pub tick_current: i32,
+    pub position_nft_mint: Option<String>,
+    pub personal_position: Option<String>,
+    pub position_liquidity: u128,
+    pub swap_amount_in: u64,
+    pub swap_amount_out: u64,
+    pub steps: Vec<WizardStepReport>,
+}
+
+impl WizardSummary {
+    fn record(
+        &mut self,
+        name: &str,
+        status: WizardStepStatus,
+        detail: String,
+        signature: Option<Signature>,
+    ) {
+        info!("[{:?}] {}: {}", status, name, detail);
+        self.steps.push(WizardStepReport {
+            name: name.to_string(),
+            status,
+            detail,
+            signature: signature.map(|signature| signature.to_string()),
+        });
+    }
+
+    pub fn write(&self, path: &str) -> Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// The widest position range of the tick spacing
+pub fn full_range_ticks(tick_spacing: u16) -> (i32, i32) {
+    let tick_spacing = i32::from(tick_spacing);
+    // the division truncates toward zero, so both ticks stay within the tick boundaries
+    (
+        tick_math::MIN_TICK / tick_spacing * tick_spacing,
+        tick_math::MAX_TICK / tick_spacing * tick_spacing,
+    )
+}
+
+fn send_instructions(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<Signature> {
+    let mut all_signers = vec![payer];
+    all_signers.extend_from_slice(signers);
+    let recent_hash = rpc_client.get_latest_blockhash()?;
+    let txn = Transaction::new_signed_with_payer(
+        &with_compute_budget(
+            rpc_client,
+            &config.compute_budget,
+            &payer.pubkey(),
+            instructions,
+        )?,
+        Some(&payer.pubkey()),
+        &all_signers,
+        recent_hash,
+    );
+    send_txn(rpc_client, &txn, true)
+}
+
+fn token_amount(rpc_client: &RpcClient, token_account: &Pubkey) -> Result<u64> {
+    let data = rpc_client.get_account_data(token_account)?;
+    Ok(StateWithExtensions::<Account>::unpack(&data)?.base.amount)
+}
+
+/// The client config of the pool created by the wizard, so the instruction builders derive the
+/// pool accounts from it
+fn pool_client_config(
+    config: &ClientConfig,
+    config_index: u16,
+    amm_config: Pubkey,
+    mint0: Pubkey,
+    mint1: Pubkey,
+) -> ClientConfig {
+    let mut pool_config = config.clone();
+    let pool_id = Pubkey::find_program_address(
+        &[
+            POOL_SEED.as_bytes(),
+            amm_config.as_ref(),
+            mint0.as_ref(),
+            mint1.as_ref(),
+        ],
+        &config.raydium_v3_program,
+    )
+    .0;
+    pool_config.amm_config_index = config_index;
+    pool_config.amm_config_key = amm_config;
+    pool_config.mint0 = Some(mint0);
+    pool_config.mint1 = Some(mint1);
+    pool_config.pool_id_account = Some(pool_id);
+    pool_config.tickarray_bitmap_extension = Some(
+        Pubkey::find_program_address(
+            &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+            &config.raydium_v3_program,
+        )
+        .0,
+    );
+    pool_config
+}
+
+/// Walk through creating the config, the operation account, the mints, a pool, a full range
+/// position and a test swap. Every step is validated against the chain before the next one, the
+/// accounts that already exist are reused.
+pub fn run_init_wizard(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    admin: &Keypair,
+    options: &WizardOptions,
+    summary: &mut WizardSummary,
+) -> Result<()> {
+    let prompter = Prompter::new(options.assume_yes);
+    let program_id = config.raydium_v3_program;
+
+    // cluster and payer
+    let genesis_hash = rpc_client.get_genesis_hash()?.to_string();
+    let payer_lamports = rpc_client.get_balance(&payer.pubkey())?;
+    summary.genesis_hash = genesis_hash.clone();
+    summary.payer = payer.pubkey().to_string();
+    summary.record(
+        "cluster",
+        WizardStepStatus::Done,
+        format!(
+            "genesis_hash:{}, payer:{}, balance:{} SOL",
+            genesis_hash,
+            payer.pubkey(),
+            payer_lamports as f64 / LAMPORTS_PER_SOL as f64
+        ),
+        None,
+    );
+    if payer_lamports < MIN_PAYER_LAMPORTS
+        && !prompter.confirm("The payer holds less than 1 SOL, continue?")?
+    {
+        return Err(format_err!("payer balance too low"));
+    }
+
+    // amm config
+    let config_index = prompter.ask("amm config index", options.config_index)?;
+    let amm_config = Pubkey::find_program_address(
+        &[AMM_CONFIG_SEED.as_bytes(), &config_index.to_be_bytes()],
+        &program_id,
+    )
+    .0;
+    summary.amm_config = amm_config.to_string();
+    let tick_spacing = match rpc_client.get_account(&amm_config) {
+        Ok(account) => {
+            let amm_config_state = deserialize_anchor_account::<AmmConfig>(&account)?;
+            summary.record(
+                "amm_config",
+                WizardStepStatus::Existing,
+                format!(
+                    "{} tick_spacing:{}, trade_fee_rate:{}",
+                    amm_config, amm_config_state.tick_spacing, amm_config_state.trade_fee_rate
+                ),
+                None,
+            );
+            amm_config_state.tick_spacing
+        }
+        Err(_) => {
+            let tick_spacing = prompter.ask("tick spacing", options.tick_spacing)?;
+            let trade_fee_rate = prompter.ask("trade fee rate", options.trade_fee_rate)?;
+            let protocol_fee_rate = prompter.ask("protocol fee rate", options.protocol_fee_rate)?;
+            let fund_fee_rate = prompter.ask("fund fee rate", options.fund_fee_rate)?;
+            if !prompter.confirm(&format!("Create amm config {}?", amm_config))? {
+                summary.record(
+                    "amm_config",
+                    WizardStepStatus::Skipped,
+                    amm_config.to_string(),
+                    None,
+                );
+                return Err(format_err!("the pool requires the amm config"));
+            }
+            let instructions = create_amm_config_instr(
+                config,
+                config_index,
+                tick_spacing,
+                trade_fee_rate,
+                protocol_fee_rate,
+                fund_fee_rate,
+            )?;
+            let signature = send_instructions(rpc_client, config, payer, &instructions, &[admin])?;
+            let amm_config_state =
+                deserialize_anchor_account::<AmmConfig>(&rpc_client.get_account(&amm_config)?)?;
+            if amm_config_state.tick_spacing != tick_spacing
+                || amm_config_state.trade_fee_rate != trade_fee_rate
+            {
+                return Err(format_err!("amm config {} doesn't match", amm_config));
+            }
+            summary.record(
+                "amm_config",
+                WizardStepStatus::Done,
+                format!("{} tick_spacing:{}", amm_config, tick_spacing),
+                Some(signature),
+            );
+            tick_spacing
+        }
+    };
+
+    // operation account
+    let operation_account =
+        Pubkey::find_program_address(&[OPERATION_SEED.as_bytes()], &program_id).0;
+    summary.operation_account = operation_account.to_string();
+    if rpc_client.get_account(&operation_account).is_ok() {
+        summary.record(
+            "operation_account",
+            WizardStepStatus::Existing,
+            operation_account.to_string(),
+            None,
+        );
+    } else if prompter.confirm(&format!("Create operation account {}?", operation_account))? {
+        let instructions = create_operation_account_instr(config)?;
+        let signature = send_instructions(rpc_client, config, payer, &instructions, &[admin])?;
+        deserialize_anchor_account::<OperationState>(&rpc_client.get_account(&operation_account)?)?;
+        summary.record(
+            "operation_account",
+            WizardStepStatus::Done,
+            operation_account.to_string(),
+            Some(signature),
+        );
+    } else {
+        summary.record(
+            "operation_account",
+            WizardStepStatus::Skipped,
+            operation_account.to_string(),
+            None,
+        );
+    }
+
+    // mints
+    let (mint0, mint1) = match (options.mint0, options.mint1) {
+        (Some(mint0), Some(mint1)) => {
+            summary.record(
+                "mints",
+                WizardStepStatus::Existing,
+                format!("{}, {}", mint0, mint1),
+                None,
+            );
+            (mint0, mint1)
+        }
+        _ => {
+            if genesis_hash == MAINNET_GENESIS_HASH {
+                return Err(format_err!(
+                    "test mints are not created on mainnet, set mint0 and mint1"
+                ));
+            }
+            let decimals = prompter.ask("decimals of the test mints", options.mint_decimals)?;
+            let mint_amount = prompter.ask("amount minted to the payer", options.mint_amount)?;
+            let mut mints = Vec::new();
+            let mut signatures = Vec::new();
+            for _ in 0..2 {
+                let mint = Keypair::new();
+                let user_token = get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &mint.pubkey(),
+                    &spl_token::id(),
+                );
+                let mut instructions = create_and_init_mint_instr(
+                    config,
+                    spl_token::id(),
+                    &mint.pubkey(),
+                    &payer.pubkey(),
+                    None,
+                    Vec::new(),
+                    decimals,
+                )?;
+                instructions.extend(create_ata_token_account_instr(
+                    config,
+                    spl_token::id(),
+                    &mint.pubkey(),
+                    &payer.pubkey(),
+                )?);
+                instructions.extend(spl_token_mint_to_instr(
+                    config,
+                    spl_token::id(),
+                    &mint.pubkey(),
+                    &user_token,
+                    mint_amount,
+                    payer,
+                )?);
+                signatures.push(send_instructions(
+                    rpc_client,
+                    config,
+                    payer,
+                    &instructions,
+                    &[&mint],
+                )?);
+                if token_amount(rpc_client, &user_token)? != mint_amount {
+                    return Err(format_err!("mint {} balance doesn't match", mint.pubkey()));
+                }
+                mints.push(mint.pubkey());
+            }
+            summary.record(
+                "mints",
+                WizardStepStatus::Done,
+                format!("{}, {} minted {} each", mints[0], mints[1], mint_amount),
+                signatures.last().copied(),
+            );
+            (mints[0], mints[1])
+        }
+    };
+    let price = prompter.ask("price of token_1 in token_0", options.price)?;
+    let pair = CanonicalPair::new(mint0, mint1);
+    let (mint0, mint1, price) = (pair.mint0, pair.mint1, pair.pool_price(price));
+    summary.mint0 = mint0.to_string();
+    summary.mint1 = mint1.to_string();
+
+    // pool
+    let pool_config = pool_client_config(config, config_index, amm_config, mint0, mint1);
+    let pool_id = pool_config.pool_id_account.unwrap();
+    summary.pool_id = pool_id.to_string();
+    let rsps = rpc_client.get_multiple_accounts(&[mint0, mint1])?;
+    let mint0_account = rsps[0]
+        .as_ref()
+        .ok_or(format_err!("mint {} not found", mint0))?;
+    let mint1_account = rsps[1]
+        .as_ref()
+        .ok_or(format_err!("mint {} not found", mint1))?;
+    let token_program_0 = mint0_account.owner;
+    let token_program_1 = mint1_account.owner;
+    let decimals_0 = StateWithExtensions::<Mint>::unpack(&mint0_account.data)?
+        .base
+        .decimals;
+    let decimals_1 = StateWithExtensions::<Mint>::unpack(&mint1_account.data)?
+        .base
+        .decimals;
+    if rpc_client.get_account(&pool_id).is_ok() {
+        summary.record(
+            "pool",
+            WizardStepStatus::Existing,
+            pool_id.to_string(),
+            None,
+        );
+    } else {
+        let sqrt_price_x64 = price_to_sqrt_price_x64(price, decimals_0, decimals_1);
+        if !prompter.confirm(&format!("Create pool {} at price {}?", pool_id, price))? {
+            summary.record("pool", WizardStepStatus::Skipped, pool_id.to_string(), None);
+            return Ok(());
+        }
+        let amm_config_state =
+            deserialize_anchor_account::<AmmConfig>(&rpc_client.get_account(&amm_config)?)?;
+        let instructions = create_pool_instr(
+            &pool_config,
+            amm_config,
+            mint0,
+            mint1,
+            token_program_0,
+            token_program_1,
+            pool_config.tickarray_bitmap_extension.unwrap(),
+            (amm_config_state.pool_creation_fee > 0).then_some(amm_config_state.fund_owner),
+            sqrt_price_x64,
+            0,
+        )?;
+        let signature = send_instructions(rpc_client, config, payer, &instructions, &[])?;
+        let pool_state =
+            deserialize_anchor_account::<PoolState>(&rpc_client.get_account(&pool_id)?)?;
+        if pool_state.sqrt_price_x64 != sqrt_price_x64 {
+            return Err(format_err!("pool {} price doesn't match", pool_id));
+        }
+        summary.record(
+            "pool",
+            WizardStepStatus::Done,
+            format!("{} sqrt_price_x64:{}", pool_id, sqrt_price_x64),
+            Some(signature),
+        );
+    }
+    let pool_state = deserialize_anchor_account::<PoolState>(&rpc_client.get_account(&pool_id)?)?;
+    summary.tick_current = pool_state.tick_current;
+    let user_token_0 =
+        get_associated_token_address_with_program_id(&payer.pubkey(), &mint0, &token_program_0);
+    let user_token_1 =
+        get_associated_token_address_with_program_id(&payer.pubkey(), &mint1, &token_program_1);
+
+    // full range position
+    let position_amount_0 = prompter.ask(
+        "token_0 amount of the full range position, 0 to skip",
+        options.position_amount_0,
+    )?;
+    if position_amount_0 == 0 {
+        summary.record(
+            "position",
+            WizardStepStatus::Skipped,
+            "no amount".to_string(),
+            None,
+        );
+    } else {
+        let (tick_lower_index, tick_upper_index) = full_range_ticks(tick_spacing);
+        let (liquidity, amount_0, amount_1) =
+            liquidity_math::get_liquidity_and_amounts_from_single_amount(
+                pool_state.tick_current,
+                pool_state.sqrt_price_x64,
+                tick_lower_index,
+                tick_upper_index,
+                position_amount_0,
+                true,
+            )?;
+        let tolerance_rate = slippage_to_tolerance_rate(config.slippage);
+        let nft_mint = Keypair::new();
+        let instructions = open_position_with_token22_nft_instr(
+            &pool_config,
+            pool_id,
+            pool_state.token_vault_0,
+            pool_state.token_vault_1,
+            mint0,
+            mint1,
+            nft_mint.pubkey(),
+            payer.pubkey(),
+            user_token_0,
+            user_token_1,
+            vec![AccountMeta::new(
+                pool_config.tickarray_bitmap_extension.unwrap(),
+                false,
+            )],
+            liquidity,
+            liquidity_math::amount_with_tolerance(amount_0, tolerance_rate),
+            liquidity_math::amount_with_tolerance(amount_1, tolerance_rate),
+            tick_lower_index,
+            tick_upper_index,
+            TickArrayState::get_array_start_index(tick_lower_index, tick_spacing),
+            TickArrayState::get_array_start_index(tick_upper_index, tick_spacing),
+            false,
+        )?;
+        let signature = send_instructions(rpc_client, config, payer, &instructions, &[&nft_mint])?;
+        let personal_position = Pubkey::find_program_address(
+            &[POSITION_SEED.as_bytes(), nft_mint.pubkey().as_ref()],
+            &program_id,
+        )
+        .0;
+        let personal_position_state = deserialize_anchor_account::<PersonalPositionState>(
+            &rpc_client.get_account(&personal_position)?,
+        )?;
+        if personal_position_state.liquidity != liquidity {
+            return Err(format_err!(
+                "position {} liquidity doesn't match",
+                personal_position
+            ));
+        }
+        summary.position_nft_mint = Some(nft_mint.pubkey().to_string());
+        summary.personal_position = Some(personal_position.to_string());
+        summary.position_liquidity = liquidity;
+        summary.record(
+            "position",
+            WizardStepStatus::Done,
+            format!(
+                "{} ticks:[{}, {}], liquidity:{}, amount_0:{}, amount_1:{}",
+                personal_position,
+                tick_lower_index,
+                tick_upper_index,
+                liquidity,
+                amount_0,
+                amount_1
+            ),
+            Some(signature),
+        );
+    }
+
+    // test swap
+    let swap_amount_0 = prompter.ask(
+        "token_0 amount of the test swap, 0 to skip",
+        options.swap_amount_0,
+    )?;
+    if swap_amount_0 == 0 {
+        summary.record(
+            "swap",
+            WizardStepStatus::Skipped,
+            "no amount".to_string(),
+            None,
+        );
+        return Ok(());
+    }
+    let pool_state = deserialize_anchor_account::<PoolState>(&rpc_client.get_account(&pool_id)?)?;
+    let amm_config_state =
+        deserialize_anchor_account::<AmmConfig>(&rpc_client.get_account(&amm_config)?)?;
+    let tickarray_bitmap_extension = deserialize_anchor_account::<TickArrayBitmapExtension>(
+        &rpc_client.get_account(&pool_config.tickarray_bitmap_extension.unwrap())?,
+    )?;
+    let mut tick_arrays = load_cur_and_next_five_tick_array(
+        rpc_client,
+        &pool_config,
+        &pool_state,
+        &tickarray_bitmap_extension,
+        true,
+    )?;
+    let (amount_out, tick_array_indexs) = get_out_put_amount_and_remaining_accounts(
+        swap_amount_0,
+        None,
+        true,
+        true,
+        &amm_config_state,
+        &pool_state,
+        &tickarray_bitmap_extension,
+        &mut tick_arrays,
+    )?;
+    let amount_out_minimum = amount_with_slippage(amount_out, config.slippage, false);
+    let mut remaining_accounts = vec![AccountMeta::new_readonly(
+        pool_config.tickarray_bitmap_extension.unwrap(),
+        false,
+    )];
+    remaining_accounts.extend(tick_array_indexs.into_iter().map(|index| {
+        AccountMeta::new(
+            Pubkey::find_program_address(
+                &[
+                    TICK_ARRAY_SEED.as_bytes(),
+                    pool_id.as_ref(),
+                    &index.to_be_bytes(),
+                ],
+                &program_id,
+            )
+            .0,
+            false,
+        )
+    }));
+    let balance_before = token_amount(rpc_client, &user_token_1)?;
+    let instructions = swap_v2_instr(
+        &pool_config,
+        amm_config,
+        pool_id,
+        pool_state.token_vault_0,
+        pool_state.token_vault_1,
+        pool_state.observation_key,
+        user_token_0,
+        user_token_1,
+        mint0,
+        mint1,
+        remaining_accounts,
+        swap_amount_0,
+        amount_out_minimum,
+        None,
+        true,
+    )?;
+    let signature = send_instructions(rpc_client, config, payer, &instructions, &[])?;
+    let amount_received = token_amount(rpc_client, &user_token_1)?.saturating_sub(balance_before);
+    if amount_received < amount_out_minimum {
+        return Err(format_err!(
+            "swap received {}, below the minimum {}",
+            amount_received,
+            amount_out_minimum
+        ));
+    }
+    summary.swap_amount_in = swap_amount_0;
+    summary.swap_amount_out = amount_received;
+    summary.record(
+        "swap",
+        WizardStepStatus::Done,
+        format!(
+            "{} token_0 in, {} token_1 out, quote:{}",
+            swap_amount_0, amount_received, amount_out
+        ),
+        Some(signature),
+    );
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use anchor_lang::prelude::AccountMeta;
+use anyhow::{format_err, Result};
+use rand::rngs::OsRng;
+use raydium_amm_v3::libraries::liquidity_math;
+use raydium_amm_v3::states::{PoolState, TickArrayState, POOL_TICK_ARRAY_BITMAP_SEED};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use std::ops::Range;
+use tracing::info;
+
+use super::super::ClientConfig;
+use super::amm_instructions::open_position_with_token22_nft_instr;
+use super::analytics::liquidity_unit_value;
+use super::route_planner::versioned_tx_size;
+use super::rpc::{send_or_simulate_txn, with_compute_budget, MAX_COMPUTE_UNIT_LIMIT};
+use super::utils::{
+    deserialize_anchor_account, get_pool_mints_inverse_fee, slippage_to_tolerance_rate,
+};
+
+/// One position of a ladder, with the amounts its liquidity deposits at the current price
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LadderBucket {
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+    pub amount_0: u64,
+    pub amount_1: u64,
+}
+
+/// Split [tick_lower, tick_upper] into `num_buckets` contiguous ranges on the tick spacing, as
+/// even as the spacing allows
+pub fn ladder_ticks(
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_spacing: u16,
+    num_buckets: usize,
+) -> Result<Vec<(i32, i32)>> {
+    let tick_spacing = i32::from(tick_spacing);
+    if tick_lower % tick_spacing != 0 || tick_upper % tick_spacing != 0 {
+        return Err(format_err!(
+            "the ticks {} and {} are not on the spacing {}",
+            tick_lower,
+            tick_upper,
+            tick_spacing
+        ));
+    }
+    let steps = i64::from((tick_upper - tick_lower) / tick_spacing);
+    if num_buckets == 0 || steps < num_buckets as i64 {
+        return Err(format_err!(
+            "can't split {} tick spacings into {} buckets",
+            steps.max(0),
+            num_buckets
+        ));
+    }
+    let boundary =
+        |i: usize| tick_lower + (steps * i as i64 / num_buckets as i64) as i32 * tick_spacing;
+    Ok((0..num_buckets)
+        .map(|i| (boundary(i), boundary(i + 1)))
+        .collect())
+}
+
+/// The same liquidity in every bucket, as much as `total_value` raw token_1 is worth at the price
+/// of the pool. Only the buckets around the price hold both tokens.
+pub fn plan_ladder(
+    pool_state: &PoolState,
+    ticks: &[(i32, i32)],
+    total_value: f64,
+) -> Result<Vec<LadderBucket>> {
+    let mut unit_value = 0.0;
+    for (tick_lower, tick_upper) in ticks {
+        unit_value += liquidity_unit_value(pool_state.sqrt_price_x64, *tick_lower, *tick_upper)?;
+    }
+    let liquidity = (total_value / unit_value).floor();
+    if !liquidity.is_finite() || liquidity < 1.0 {
+        return Err(format_err!(
+            "{} is worth no liquidity in each of {} buckets",
+            total_value,
+            ticks.len()
+        ));
+    }
+    let liquidity = liquidity as u128;
+    ticks
+        .iter()
+        .map(|(tick_lower, tick_upper)| {
+            let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+                pool_state.tick_current,
+                pool_state.sqrt_price_x64,
+                *tick_lower,
+                *tick_upper,
+                i128::try_from(liquidity)?,
+            )?;
+            Ok(LadderBucket {
+                tick_lower_index: *tick_lower,
+                tick_upper_index: *tick_upper,
+                liquidity,
+                amount_0,
+                amount_1,
+            })
+        })
+        .collect()
+}
+
+/// Split `count` items into as few contiguous groups as possible, `fits` reports whether a group
+/// fits in one transaction
+pub fn group_by_tx_size<F>(count: usize, mut fits: F) -> Result<Vec<Range<usize>>>
+where
+    F: FnMut(Range<usize>) -> Result<bool>,
+{
+    let mut groups = Vec::new();
+    let mut start = 0;
+    while start < count {
+        if !fits(start..start + 1)? {
+            return Err(format_err!("item {} does not fit in a transaction", start));
+        }
+        let mut end = start + 1;
+        while end < count && fits(start..end + 1)? {
+            end += 1;
+        }
+        groups.push(start..end);
+        start = end;
+    }
+    Ok(groups)
+}
+
+/// Open a position per bucket in the pool of the config, the buckets next to each other share
+/// their tick arrays and are packed in as few transactions as fit
+pub fn ladder_open(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    buckets: &[LadderBucket],
+    with_metadata: bool,
+    simulate: bool,
+) -> Result<Vec<Pubkey>> {
+    let pool_id = config.pool_id_account.unwrap();
+    let pool_state: PoolState = deserialize_anchor_account(&rpc_client.get_account(&pool_id)?)?;
+    let tickarray_bitmap_extension = Pubkey::find_program_address(
+        &[
+            POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+        ],
+        &config.raydium_v3_program,
+    )
+    .0;
+    let tolerance_rate = slippage_to_tolerance_rate(config.slippage);
+    let nft_mints: Vec<Keypair> = buckets
+        .iter()
+        .map(|_| Keypair::generate(&mut OsRng))
+        .collect();
+    let mut bucket_instructions = Vec::new();
+    for (bucket, nft_mint) in buckets.iter().zip(nft_mints.iter()) {
+        let amount_0 = liquidity_math::amount_with_tolerance(bucket.amount_0, tolerance_rate);
+        let amount_1 = liquidity_math::amount_with_tolerance(bucket.amount_1, tolerance_rate);
+        let transfer_fee = get_pool_mints_inverse_fee(
+            rpc_client,
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+            amount_0,
+            amount_1,
+        );
+        bucket_instructions.push(open_position_with_token22_nft_instr(
+            config,
+            pool_id,
+            pool_state.token_vault_0,
+            pool_state.token_vault_1,
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+            nft_mint.pubkey(),
+            payer.pubkey(),
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                &payer.pubkey(),
+                &pool_state.token_mint_0,
+                &transfer_fee.0.owner,
+            ),
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                &payer.pubkey(),
+                &pool_state.token_mint_1,
+                &transfer_fee.1.owner,
+            ),
+            vec![AccountMeta::new(tickarray_bitmap_extension, false)],
+            bucket.liquidity,
+            amount_0.checked_add(transfer_fee.0.transfer_fee).unwrap(),
+            amount_1.checked_add(transfer_fee.1.transfer_fee).unwrap(),
+            bucket.tick_lower_index,
+            bucket.tick_upper_index,
+            TickArrayState::get_array_start_index(bucket.tick_lower_index, pool_state.tick_spacing),
+            TickArrayState::get_array_start_index(bucket.tick_upper_index, pool_state.tick_spacing),
+            with_metadata,
+        )?);
+    }
+
+    let group_instructions =
+        |group: Range<usize>| -> Vec<Instruction> { bucket_instructions[group].concat() };
+    let groups = group_by_tx_size(buckets.len(), |group| {
+        let mut instructions = config.compute_budget.instructions(MAX_COMPUTE_UNIT_LIMIT);
+        instructions.extend(group_instructions(group));
+        Ok(versioned_tx_size(&payer.pubkey(), &instructions, &[])
+            .map_or(false, |size| size <= PACKET_DATA_SIZE))
+    })?;
+    info!(
+        "{} positions in {} transactions",
+        buckets.len(),
+        groups.len()
+    );
+    for group in groups {
+        let mut signers = vec![payer];
+        signers.extend(nft_mints[group.clone()].iter());
+        let instructions = with_compute_budget(
+            rpc_client,
+            &config.compute_budget,
+            &payer.pubkey(),
+            &group_instructions(group.clone()),
+        )?;
+        let txn = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&payer.pubkey()),
+            &signers,
+            rpc_client.get_latest_blockhash()?,
+        );
+        info!(
+            "positions {:?}",
+            nft_mints[group]
+                .iter()
+                .map(|nft_mint| nft_mint.pubkey())
+                .collect::<Vec<_>>()
+        );
+        send_or_simulate_txn(rpc_client, &txn, simulate)?;
+    }
+    Ok(nft_mints.iter().map(|nft_mint| nft_mint.pubkey()).collect())
+}
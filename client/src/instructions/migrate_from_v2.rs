@@ -0,0 +1,541 @@
+use anchor_lang::prelude::AccountMeta;
+use anyhow::{format_err, Result};
+use arrayref::array_ref;
+use rand::rngs::OsRng;
+use raydium_amm_v3::libraries::{liquidity_math, tick_math};
+use raydium_amm_v3::states::{
+    PoolState, PoolStatusBitIndex, TickArrayState, POOL_TICK_ARRAY_BITMAP_SEED,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address, instruction::create_associated_token_account_idempotent,
+};
+use std::io::{self, BufRead, Write};
+use tracing::info;
+
+use super::super::ClientConfig;
+use super::amm_instructions::open_position_with_token_amounts_instr;
+use super::rpc::{send_txn, with_compute_budget};
+use super::utils::{
+    amount_with_slippage, deserialize_anchor_account, from_x64_price, price_to_tick,
+    tick_with_spacing,
+};
+
+/// The seed of the authority of the legacy AMM pools, bumped by their nonce
+const LEGACY_AMM_AUTHORITY_SEED: &[u8] = b"amm authority";
+/// The size of the state of a legacy AMM pool
+pub const LEGACY_AMM_INFO_LEN: usize = 752;
+/// The tag of the withdraw instruction of the legacy AMM program
+const LEGACY_AMM_WITHDRAW_TAG: u8 = 4;
+/// The size of the state of an order book market, padding included
+const MARKET_STATE_MIN_LEN: usize = 349;
+
+/// The keys and amounts of a legacy constant product pool its withdraw instruction needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LegacyAmm {
+    pub nonce: u64,
+    /// The pool's share of the profits of its market orders, held by the vaults but not by the
+    /// liquidity providers
+    pub need_take_pnl_coin: u64,
+    pub need_take_pnl_pc: u64,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub coin_mint: Pubkey,
+    pub pc_mint: Pubkey,
+    pub lp_mint: Pubkey,
+    pub open_orders: Pubkey,
+    pub market: Pubkey,
+    pub market_program: Pubkey,
+    pub target_orders: Pubkey,
+    /// The supply of lp tokens as tracked by the pool
+    pub lp_amount: u64,
+}
+
+impl LegacyAmm {
+    pub fn unpack(data: &[u8]) -> Result<Self> {
+        if data.len() != LEGACY_AMM_INFO_LEN {
+            return Err(format_err!(
+                "not a legacy amm pool, the account holds {} bytes instead of {}",
+                data.len(),
+                LEGACY_AMM_INFO_LEN
+            ));
+        }
+        let u64_at = |offset: usize| u64::from_le_bytes(*array_ref![data, offset, 8]);
+        let pubkey_at = |offset: usize| Pubkey::new_from_array(*array_ref![data, offset, 32]);
+        // 16 u64 parameters, the 8 u64 fees, then the state data starting with the pnl to take
+        Ok(Self {
+            nonce: u64_at(8),
+            need_take_pnl_coin: u64_at(192),
+            need_take_pnl_pc: u64_at(200),
+            coin_vault: pubkey_at(336),
+            pc_vault: pubkey_at(368),
+            coin_mint: pubkey_at(400),
+            pc_mint: pubkey_at(432),
+            lp_mint: pubkey_at(464),
+            open_orders: pubkey_at(496),
+            market: pubkey_at(528),
+            market_program: pubkey_at(560),
+            target_orders: pubkey_at(592),
+            lp_amount: u64_at(720),
+        })
+    }
+
+    pub fn authority(&self, program_id: &Pubkey) -> Result<Pubkey> {
+        Ok(Pubkey::create_program_address(
+            &[LEGACY_AMM_AUTHORITY_SEED, &[self.nonce as u8]],
+            program_id,
+        )?)
+    }
+}
+
+/// The keys of the order book market of a legacy pool its withdraw instruction needs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LegacyMarket {
+    pub vault_signer_nonce: u64,
+    pub coin_vault: Pubkey,
+    pub pc_vault: Pubkey,
+    pub event_queue: Pubkey,
+    pub bids: Pubkey,
+    pub asks: Pubkey,
+}
+
+impl LegacyMarket {
+    pub fn unpack(data: &[u8]) -> Result<Self> {
+        if data.len() < MARKET_STATE_MIN_LEN {
+            return Err(format_err!(
+                "not a market, the account holds {} bytes",
+                data.len()
+            ));
+        }
+        let pubkey_at = |offset: usize| Pubkey::new_from_array(*array_ref![data, offset, 32]);
+        // after the 5 bytes of head padding, the account flags and the market address
+        Ok(Self {
+            vault_signer_nonce: u64::from_le_bytes(*array_ref![data, 45, 8]),
+            coin_vault: pubkey_at(117),
+            pc_vault: pubkey_at(165),
+            event_queue: pubkey_at(253),
+            bids: pubkey_at(285),
+            asks: pubkey_at(317),
+        })
+    }
+
+    pub fn vault_signer(&self, market: &Pubkey, market_program: &Pubkey) -> Result<Pubkey> {
+        Ok(Pubkey::create_program_address(
+            &[market.as_ref(), &self.vault_signer_nonce.to_le_bytes()],
+            market_program,
+        )?)
+    }
+}
+
+/// Burn `lp_amount` lp tokens of `owner` for its share of the pool vaults
+pub fn legacy_withdraw_instr(
+    program_id: &Pubkey,
+    amm_id: &Pubkey,
+    amm: &LegacyAmm,
+    market: &LegacyMarket,
+    owner: &Pubkey,
+    lp_amount: u64,
+) -> Result<Instruction> {
+    let mut data = vec![LEGACY_AMM_WITHDRAW_TAG];
+    data.extend_from_slice(&lp_amount.to_le_bytes());
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(*amm_id, false),
+            AccountMeta::new_readonly(amm.authority(program_id)?, false),
+            AccountMeta::new(amm.open_orders, false),
+            AccountMeta::new(amm.target_orders, false),
+            AccountMeta::new(amm.lp_mint, false),
+            AccountMeta::new(amm.coin_vault, false),
+            AccountMeta::new(amm.pc_vault, false),
+            AccountMeta::new_readonly(amm.market_program, false),
+            AccountMeta::new(amm.market, false),
+            AccountMeta::new(market.coin_vault, false),
+            AccountMeta::new(market.pc_vault, false),
+            AccountMeta::new_readonly(
+                market.vault_signer(&amm.market, &amm.market_program)?,
+                false,
+            ),
+            AccountMeta::new(get_associated_token_address(owner, &amm.lp_mint), false),
+            AccountMeta::new(get_associated_token_address(owner, &amm.coin_mint), false),
+            AccountMeta::new(get_associated_token_address(owner, &amm.pc_mint), false),
+            AccountMeta::new_readonly(*owner, true),
+            AccountMeta::new(market.event_queue, false),
+            AccountMeta::new(market.bids, false),
+            AccountMeta::new(market.asks, false),
+        ],
+        data,
+    })
+}
+
+/// The share of the pool amounts `lp_amount` lp tokens withdraw, rounded down as the program does
+pub fn legacy_withdraw_amounts(
+    lp_amount: u64,
+    lp_supply: u64,
+    coin_amount: u64,
+    pc_amount: u64,
+) -> (u64, u64) {
+    if lp_supply == 0 {
+        return (0, 0);
+    }
+    let share =
+        |amount: u64| (u128::from(amount) * u128::from(lp_amount) / u128::from(lp_supply)) as u64;
+    (share(coin_amount), share(pc_amount))
+}
+
+/// The ticks of a position over the price `sqrt_price_x64` moved by `range_percent` either way,
+/// or the full range like the legacy liquidity if not set
+pub fn migration_ticks(
+    sqrt_price_x64: u128,
+    range_percent: Option<f64>,
+    tick_spacing: u16,
+) -> Result<(i32, i32)> {
+    let tick_spacing = i32::from(tick_spacing);
+    let min_tick = tick_math::MIN_TICK / tick_spacing * tick_spacing;
+    let max_tick = tick_math::MAX_TICK / tick_spacing * tick_spacing;
+    let range_percent = match range_percent {
+        None => return Ok((min_tick, max_tick)),
+        Some(range_percent) if range_percent > 0.0 && range_percent < 100.0 => range_percent,
+        Some(range_percent) => {
+            return Err(format_err!(
+                "the range must be within (0, 100) percent, got {}",
+                range_percent
+            ))
+        }
+    };
+    let price = from_x64_price(sqrt_price_x64).powi(2);
+    let tick_lower = tick_with_spacing(
+        price_to_tick(price * (1.0 - range_percent / 100.0)),
+        tick_spacing,
+    )
+    .max(min_tick);
+    // rounded up, the range covers at least the requested prices
+    let tick_upper = (tick_with_spacing(
+        price_to_tick(price * (1.0 + range_percent / 100.0)),
+        tick_spacing,
+    ) + tick_spacing)
+        .min(max_tick);
+    Ok((tick_lower, tick_upper))
+}
+
+/// How far apart two prices are, relative to the second
+pub fn price_deviation(price: f64, reference_price: f64) -> f64 {
+    (price / reference_price - 1.0).abs()
+}
+
+/// What a migration withdraws and deposits, the amounts in the order of the tokens of the pool
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MigrationPlan {
+    pub lp_amount: u64,
+    /// Whether token_0 of the pool is the coin of the legacy pool
+    pub zero_is_coin: bool,
+    /// The amounts the lp tokens are expected to withdraw
+    pub amount_0: u64,
+    pub amount_1: u64,
+    /// The amounts the withdraw must pay at least, within the slippage budget
+    pub amount_0_min: u64,
+    pub amount_1_min: u64,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    /// The price of token_0 in token_1 of each pool, in raw units
+    pub legacy_price: f64,
+    pub price: f64,
+}
+
+impl MigrationPlan {
+    pub fn new(
+        amm: &LegacyAmm,
+        coin_vault_amount: u64,
+        pc_vault_amount: u64,
+        pool_state: &PoolState,
+        lp_amount: u64,
+        range_percent: Option<f64>,
+        slippage: f64,
+    ) -> Result<Self> {
+        let zero_is_coin = if pool_state.token_mint_0 == amm.coin_mint
+            && pool_state.token_mint_1 == amm.pc_mint
+        {
+            true
+        } else if pool_state.token_mint_0 == amm.pc_mint && pool_state.token_mint_1 == amm.coin_mint
+        {
+            false
+        } else {
+            return Err(format_err!(
+                "the pools are of different pairs, {}/{} and {}/{}",
+                amm.coin_mint,
+                amm.pc_mint,
+                pool_state.token_mint_0,
+                pool_state.token_mint_1
+            ));
+        };
+        // the vaults also hold the pnl owed to the pool, which is not withdrawn. The amounts in the
+        // open orders of the pool are left out, the withdraw pays at least the expected amounts
+        let (coin_total, pc_total) = (
+            coin_vault_amount.saturating_sub(amm.need_take_pnl_coin),
+            pc_vault_amount.saturating_sub(amm.need_take_pnl_pc),
+        );
+        if coin_total == 0 || pc_total == 0 {
+            return Err(format_err!("the legacy pool is empty"));
+        }
+        let (coin_amount, pc_amount) =
+            legacy_withdraw_amounts(lp_amount, amm.lp_amount, coin_total, pc_total);
+        let (amount_0, amount_1, total_0, total_1) = if zero_is_coin {
+            (coin_amount, pc_amount, coin_total, pc_total)
+        } else {
+            (pc_amount, coin_amount, pc_total, coin_total)
+        };
+        if amount_0 == 0 && amount_1 == 0 {
+            return Err(format_err!("{} lp tokens withdraw nothing", lp_amount));
+        }
+        let (tick_lower_index, tick_upper_index) = migration_ticks(
+            pool_state.sqrt_price_x64,
+            range_percent,
+            pool_state.tick_spacing,
+        )?;
+        Ok(Self {
+            lp_amount,
+            zero_is_coin,
+            amount_0,
+            amount_1,
+            amount_0_min: amount_with_slippage(amount_0, slippage, false),
+            amount_1_min: amount_with_slippage(amount_1, slippage, false),
+            tick_lower_index,
+            tick_upper_index,
+            legacy_price: total_1 as f64 / total_0 as f64,
+            price: from_x64_price(pool_state.sqrt_price_x64).powi(2),
+        })
+    }
+
+    /// The most liquidity the amounts provide at the price of the pool, and what it deposits
+    pub fn deposit(
+        &self,
+        pool_state: &PoolState,
+        amount_0: u64,
+        amount_1: u64,
+    ) -> Result<(u128, u64, u64)> {
+        let liquidity = liquidity_math::get_liquidity_from_amounts(
+            pool_state.sqrt_price_x64,
+            tick_math::get_sqrt_price_at_tick(self.tick_lower_index)?,
+            tick_math::get_sqrt_price_at_tick(self.tick_upper_index)?,
+            amount_0,
+            amount_1,
+        )?;
+        let (deposit_0, deposit_1) = liquidity_math::get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            self.tick_lower_index,
+            self.tick_upper_index,
+            i128::try_from(liquidity)?,
+        )?;
+        Ok((liquidity, deposit_0, deposit_1))
+    }
+}
+
+/// Token balance of the account, 0 if it doesn't exist yet
+fn token_balance(rpc_client: &RpcClient, token_account: &Pubkey) -> u64 {
+    rpc_client
+        .get_token_account_balance(token_account)
+        .ok()
+        .and_then(|balance| balance.amount.parse().ok())
+        .unwrap_or(0)
+}
+
+fn send_instructions(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    signers: Vec<&Keypair>,
+    instructions: &[Instruction],
+) -> Result<()> {
+    let txn = Transaction::new_signed_with_payer(
+        &with_compute_budget(
+            rpc_client,
+            &config.compute_budget,
+            &payer.pubkey(),
+            instructions,
+        )?,
+        Some(&payer.pubkey()),
+        &signers,
+        rpc_client.get_latest_blockhash()?,
+    );
+    let signature = send_txn(rpc_client, &txn, true)?;
+    info!("{}", signature);
+    Ok(())
+}
+
+/// Move the liquidity of the payer in the legacy constant product pool `amm_id` into a new
+/// position in `pool_id`. The lp tokens are withdrawn first, then the received amounts open the
+/// position at the price of the pool, the part the range doesn't use stays in the wallet.
+///
+/// The withdraw is only checked against the amounts expected from the vaults, and the migration
+/// stops before opening the position if the prices of the two pools are further apart than the
+/// slippage, depositing at a price off the market would lose the difference to arbitrage.
+pub fn migrate_from_v2(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    amm_id: &Pubkey,
+    pool_id: &Pubkey,
+    lp_amount: Option<u64>,
+    range_percent: Option<f64>,
+    slippage: f64,
+    assume_yes: bool,
+) -> Result<Option<Pubkey>> {
+    let owner = payer.pubkey();
+    let amm_account = rpc_client.get_account(amm_id)?;
+    // the legacy program is the owner of the pool, no need to configure it
+    let legacy_program = amm_account.owner;
+    let amm = LegacyAmm::unpack(&amm_account.data)?;
+    let market = LegacyMarket::unpack(&rpc_client.get_account_data(&amm.market)?)?;
+    let pool_state = deserialize_anchor_account::<PoolState>(&rpc_client.get_account(pool_id)?)?;
+    if !pool_state.get_status_by_bit(PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity) {
+        return Err(format_err!("opening positions is disabled in {}", pool_id));
+    }
+
+    let user_lp = get_associated_token_address(&owner, &amm.lp_mint);
+    let lp_balance = token_balance(rpc_client, &user_lp);
+    let lp_amount = lp_amount.unwrap_or(lp_balance);
+    if lp_amount == 0 || lp_amount > lp_balance {
+        return Err(format_err!(
+            "{} holds {} lp tokens of {}, can't withdraw {}",
+            owner,
+            lp_balance,
+            amm_id,
+            lp_amount
+        ));
+    }
+    let plan = MigrationPlan::new(
+        &amm,
+        token_balance(rpc_client, &amm.coin_vault),
+        token_balance(rpc_client, &amm.pc_vault),
+        &pool_state,
+        lp_amount,
+        range_percent,
+        slippage,
+    )?;
+    let deviation = price_deviation(plan.legacy_price, plan.price);
+    if deviation > slippage {
+        return Err(format_err!(
+            "the price of {} is {:.4}% off the price of {}, above the slippage of {}%",
+            amm_id,
+            deviation * 100.0,
+            pool_id,
+            slippage * 100.0
+        ));
+    }
+    let (liquidity, deposit_0, deposit_1) =
+        plan.deposit(&pool_state, plan.amount_0, plan.amount_1)?;
+    info!(
+        "withdraw {} lp tokens for amount_0:{} (min {}), amount_1:{} (min {}), open ticks:[{}, {}] with liquidity:{}, deposit_0:{}, deposit_1:{}, price deviation:{:.4}%",
+        plan.lp_amount,
+        plan.amount_0,
+        plan.amount_0_min,
+        plan.amount_1,
+        plan.amount_1_min,
+        plan.tick_lower_index,
+        plan.tick_upper_index,
+        liquidity,
+        deposit_0,
+        deposit_1,
+        deviation * 100.0
+    );
+    if !assume_yes {
+        print!(
+            "migrate with a slippage of {}%? type yes to confirm: ",
+            slippage * 100.0
+        );
+        io::stdout().flush()?;
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        if !matches!(line.trim().to_lowercase().as_str(), "y" | "yes") {
+            info!("aborted");
+            return Ok(None);
+        }
+    }
+
+    // the legacy pools only hold spl token mints
+    let user_token_0 = get_associated_token_address(&owner, &pool_state.token_mint_0);
+    let user_token_1 = get_associated_token_address(&owner, &pool_state.token_mint_1);
+    let balance_0_before = token_balance(rpc_client, &user_token_0);
+    let balance_1_before = token_balance(rpc_client, &user_token_1);
+    let mut instructions = Vec::new();
+    for mint in [amm.coin_mint, amm.pc_mint] {
+        instructions.push(create_associated_token_account_idempotent(
+            &owner,
+            &owner,
+            &mint,
+            &spl_token::id(),
+        ));
+    }
+    instructions.push(legacy_withdraw_instr(
+        &legacy_program,
+        amm_id,
+        &amm,
+        &market,
+        &owner,
+        plan.lp_amount,
+    )?);
+    info!("withdraw from {}", amm_id);
+    send_instructions(rpc_client, config, payer, vec![payer], &instructions)?;
+
+    let received_0 = token_balance(rpc_client, &user_token_0).saturating_sub(balance_0_before);
+    let received_1 = token_balance(rpc_client, &user_token_1).saturating_sub(balance_1_before);
+    if received_0 < plan.amount_0_min || received_1 < plan.amount_1_min {
+        return Err(format_err!(
+            "the withdraw paid amount_0:{}, amount_1:{}, below the minimums, the tokens stay in the wallet",
+            received_0,
+            received_1
+        ));
+    }
+
+    // the price may have moved since the plan, the deposit is recomputed from the pool
+    let pool_state = deserialize_anchor_account::<PoolState>(&rpc_client.get_account(pool_id)?)?;
+    let (_, deposit_0, deposit_1) = plan.deposit(&pool_state, received_0, received_1)?;
+    let tickarray_bitmap_extension = Pubkey::find_program_address(
+        &[
+            POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+        ],
+        &config.raydium_v3_program,
+    )
+    .0;
+    let nft_mint = Keypair::generate(&mut OsRng);
+    let instructions = open_position_with_token_amounts_instr(
+        config,
+        *pool_id,
+        pool_state.token_vault_0,
+        pool_state.token_vault_1,
+        pool_state.token_mint_0,
+        pool_state.token_mint_1,
+        nft_mint.pubkey(),
+        owner,
+        user_token_0,
+        user_token_1,
+        vec![AccountMeta::new(tickarray_bitmap_extension, false)],
+        received_0,
+        received_1,
+        amount_with_slippage(deposit_0, slippage, false),
+        amount_with_slippage(deposit_1, slippage, false),
+        plan.tick_lower_index,
+        plan.tick_upper_index,
+        TickArrayState::get_array_start_index(plan.tick_lower_index, pool_state.tick_spacing),
+        TickArrayState::get_array_start_index(plan.tick_upper_index, pool_state.tick_spacing),
+        true,
+    )?;
+    info!("open position {} in {}", nft_mint.pubkey(), pool_id);
+    send_instructions(
+        rpc_client,
+        config,
+        payer,
+        vec![payer, &nft_mint],
+        &instructions,
+    )?;
+    Ok(Some(nft_mint.pubkey()))
+}
@@ -1,5 +1,26 @@
 pub mod amm_instructions;
+pub mod analytics;
+pub mod config_diff;
+pub mod diagnose;
 pub mod events_instructions_parse;
+pub mod faucet;
+pub mod init_wizard;
+pub mod ladder;
+pub mod migrate_from_v2;
+pub mod output;
+pub mod pool_manifest;
+pub mod pool_quoter;
+pub mod pool_snapshot;
+pub mod portfolio;
+pub mod position_closer;
+pub mod position_history;
+pub mod position_scanner;
+pub mod replay;
+pub mod route_planner;
 pub mod rpc;
+pub mod scheduler;
+pub mod telemetry;
 pub mod token_instructions;
 pub mod utils;
+pub mod verify_build;
+pub mod watch;
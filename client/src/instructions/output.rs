@@ -0,0 +1,198 @@
+use anyhow::Result;
+use clap::Args;
+use raydium_amm_v3::states::{AmmConfig, PoolState};
+use serde::Serialize;
+use solana_sdk::pubkey::Pubkey;
+
+use super::position_scanner::UnifiedPosition;
+use super::utils::{serialize_csv, sqrt_price_x64_to_price};
+
+/// The output of a read command, printed for a terminal by default or as json or csv to be piped
+/// into other tools
+#[derive(Args, Debug, Clone, Copy, Default)]
+pub struct OutputArgs {
+    /// Print the result as json
+    #[arg(long, conflicts_with = "csv")]
+    pub json: bool,
+    /// Print the result as csv, one row per record
+    #[arg(long)]
+    pub csv: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+impl OutputArgs {
+    pub fn format(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else if self.csv {
+            OutputFormat::Csv
+        } else {
+            OutputFormat::Text
+        }
+    }
+}
+
+/// Print the records to stdout as json or csv, nothing is printed for the text format
+pub fn print_records<T: Serialize>(format: OutputFormat, records: &[T]) -> Result<()> {
+    match format {
+        OutputFormat::Text => {}
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(records)?),
+        OutputFormat::Csv => print!("{}", serialize_csv(records)?),
+    }
+    Ok(())
+}
+
+/// An amm config as written by `pcfg`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AmmConfigRecord {
+    pub amm_config: String,
+    pub index: u16,
+    pub owner: String,
+    pub protocol_fee_rate: u32,
+    pub trade_fee_rate: u32,
+    pub tick_spacing: u16,
+    pub fund_fee_rate: u32,
+    pub fund_owner: String,
+    pub max_initial_tick_deviation: u32,
+    pub swap_delay_after_create: u32,
+    pub pool_creation_fee: u64,
+    pub protocol_fee_split_rate: u32,
+}
+
+impl AmmConfigRecord {
+    pub fn new(key: &Pubkey, amm_config: &AmmConfig) -> Self {
+        AmmConfigRecord {
+            amm_config: key.to_string(),
+            index: amm_config.index,
+            owner: amm_config.owner.to_string(),
+            protocol_fee_rate: amm_config.protocol_fee_rate,
+            trade_fee_rate: amm_config.trade_fee_rate,
+            tick_spacing: amm_config.tick_spacing,
+            fund_fee_rate: amm_config.fund_fee_rate,
+            fund_owner: amm_config.fund_owner.to_string(),
+            max_initial_tick_deviation: amm_config.max_initial_tick_deviation,
+            swap_delay_after_create: amm_config.swap_delay_after_create,
+            pool_creation_fee: amm_config.pool_creation_fee,
+            protocol_fee_split_rate: amm_config.protocol_fee_split_rate,
+        }
+    }
+}
+
+/// A pool as written by `ppool`. The u128 values are strings so they survive json parsers
+/// limited to f64.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PoolRecord {
+    pub pool_id: String,
+    pub amm_config: String,
+    pub owner: String,
+    pub token_mint_0: String,
+    pub token_mint_1: String,
+    pub token_vault_0: String,
+    pub token_vault_1: String,
+    pub observation_key: String,
+    pub mint_decimals_0: u8,
+    pub mint_decimals_1: u8,
+    pub tick_spacing: u16,
+    pub liquidity: String,
+    pub sqrt_price_x64: String,
+    pub price: f64,
+    pub tick_current: i32,
+    pub fee_growth_global_0_x64: String,
+    pub fee_growth_global_1_x64: String,
+    pub protocol_fees_token_0: u64,
+    pub protocol_fees_token_1: u64,
+    pub fund_fees_token_0: u64,
+    pub fund_fees_token_1: u64,
+    pub total_fees_token_0: u64,
+    pub total_fees_token_1: u64,
+    pub status: u8,
+    pub open_time: u64,
+    pub min_swap_amount_in: u64,
+    /// The mints of the initialized rewards
+    pub reward_mints: Vec<String>,
+}
+
+impl PoolRecord {
+    pub fn new(key: &Pubkey, pool_state: &PoolState) -> Self {
+        let liquidity = pool_state.liquidity;
+        let sqrt_price_x64 = pool_state.sqrt_price_x64;
+        let fee_growth_global_0_x64 = pool_state.fee_growth_global_0_x64;
+        let fee_growth_global_1_x64 = pool_state.fee_growth_global_1_x64;
+        PoolRecord {
+            pool_id: key.to_string(),
+            amm_config: pool_state.amm_config.to_string(),
+            owner: pool_state.owner.to_string(),
+            token_mint_0: pool_state.token_mint_0.to_string(),
+            token_mint_1: pool_state.token_mint_1.to_string(),
+            token_vault_0: pool_state.token_vault_0.to_string(),
+            token_vault_1: pool_state.token_vault_1.to_string(),
+            observation_key: pool_state.observation_key.to_string(),
+            mint_decimals_0: pool_state.mint_decimals_0,
+            mint_decimals_1: pool_state.mint_decimals_1,
+            tick_spacing: pool_state.tick_spacing,
+            liquidity: liquidity.to_string(),
+            sqrt_price_x64: sqrt_price_x64.to_string(),
+            price: sqrt_price_x64_to_price(
+                sqrt_price_x64,
+                pool_state.mint_decimals_0,
+                pool_state.mint_decimals_1,
+            ),
+            tick_current: pool_state.tick_current,
+            fee_growth_global_0_x64: fee_growth_global_0_x64.to_string(),
+            fee_growth_global_1_x64: fee_growth_global_1_x64.to_string(),
+            protocol_fees_token_0: pool_state.protocol_fees_token_0,
+            protocol_fees_token_1: pool_state.protocol_fees_token_1,
+            fund_fees_token_0: pool_state.fund_fees_token_0,
+            fund_fees_token_1: pool_state.fund_fees_token_1,
+            total_fees_token_0: pool_state.total_fees_token_0,
+            total_fees_token_1: pool_state.total_fees_token_1,
+            status: pool_state.status,
+            open_time: pool_state.open_time,
+            min_swap_amount_in: pool_state.min_swap_amount_in,
+            reward_mints: pool_state
+                .reward_infos
+                .iter()
+                .filter(|reward_info| reward_info.initialized())
+                .map(|reward_info| reward_info.token_mint.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// A position of a pool as written by `ppersonal-position-by-pool`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PositionRecord {
+    pub position: String,
+    pub layout: String,
+    pub nft_mint: Option<String>,
+    pub pool_id: String,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: String,
+    pub token_fees_owed_0: u64,
+    pub token_fees_owed_1: u64,
+    pub reward_amounts_owed: Vec<u64>,
+}
+
+impl From<&UnifiedPosition> for PositionRecord {
+    fn from(position: &UnifiedPosition) -> Self {
+        PositionRecord {
+            position: position.key.to_string(),
+            layout: format!("{:?}", position.layout),
+            nft_mint: position.nft_mint.map(|nft_mint| nft_mint.to_string()),
+            pool_id: position.pool_id.to_string(),
+            tick_lower_index: position.tick_lower_index,
+            tick_upper_index: position.tick_upper_index,
+            liquidity: position.liquidity.to_string(),
+            token_fees_owed_0: position.token_fees_owed_0,
+            token_fees_owed_1: position.token_fees_owed_1,
+            reward_amounts_owed: position.reward_amounts_owed.to_vec(),
+        }
+    }
+}
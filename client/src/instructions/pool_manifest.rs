@@ -0,0 +1,401 @@
+use anyhow::{format_err, Result};
+use raydium_amm_v3::libraries::{liquidity_math, tick_math};
+use raydium_amm_v3::states::{
+    tick_array_keys_for_range, AmmConfig, ObservationState, PoolState, TickArrayState,
+    FEE_RATE_DENOMINATOR_VALUE, OBSERVATION_NUM, OBSERVATION_UPDATE_DURATION_DEFAULT,
+    POOL_TICK_ARRAY_BITMAP_SEED,
+};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+
+use super::rpc::RpcScanner;
+use super::utils::{deserialize_anchor_account, sqrt_price_x64_to_price};
+
+/// Bumped whenever a field of the manifest is renamed, removed or changes meaning. Fields are
+/// only ever added within a version.
+pub const POOL_MANIFEST_SCHEMA_VERSION: u32 = 1;
+/// The price moves, in bps, the depth of the pool is measured at
+pub const STANDARD_DEPTH_BANDS_BPS: [u32; 5] = [10, 50, 100, 200, 500];
+
+/// Everything an integrator needs to route through a pool, written by `pool-manifest`. Amounts,
+/// liquidity and prices in x64 are strings so they survive json parsers limited to f64.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolManifest {
+    pub schema_version: u32,
+    pub program_id: String,
+    pub pool_id: String,
+    /// The minimum slot the accounts were loaded at
+    pub slot: u64,
+    /// The status bits of the pool, see `PoolStatusBitIndex`
+    pub status: u8,
+    /// The pool can't be swapped before this timestamp
+    pub open_time: u64,
+    pub accounts: PoolManifestAccounts,
+    pub mint_0: PoolManifestMint,
+    pub mint_1: PoolManifestMint,
+    pub fee: PoolManifestFee,
+    pub tick_spacing: u16,
+    pub price: PoolManifestPrice,
+    /// The liquidity within `STANDARD_DEPTH_BANDS_BPS` of the current price
+    pub depth: Vec<PoolManifestDepthBand>,
+    pub observation: PoolManifestObservation,
+    pub rewards: Vec<PoolManifestReward>,
+}
+
+/// The accounts of the pool, all of them are PDAs of the program
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolManifestAccounts {
+    pub amm_config: String,
+    pub observation: String,
+    pub tick_array_bitmap_extension: String,
+    pub token_vault_0: String,
+    pub token_vault_1: String,
+    /// The initialized tick arrays within the widest depth band, sorted by start index
+    pub tick_arrays: Vec<PoolManifestTickArray>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolManifestTickArray {
+    pub start_tick_index: i32,
+    pub address: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolManifestMint {
+    pub mint: String,
+    pub decimals: u8,
+    /// spl-token or token-2022 program of the mint
+    pub token_program: String,
+}
+
+/// Fee rates in hundredths of a bip, the protocol and fund rates are shares of the trade fee
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolManifestFee {
+    pub amm_config_index: u16,
+    pub trade_fee_rate: u32,
+    pub protocol_fee_rate: u32,
+    pub fund_fee_rate: u32,
+    pub fee_rate_denominator: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolManifestPrice {
+    pub sqrt_price_x64: String,
+    pub tick_current: i32,
+    /// Price of token_0 in token_1, adjusted by the decimals
+    pub price: f64,
+    /// The liquidity in range at the current price
+    pub liquidity: String,
+}
+
+/// The amounts to swap in, before the trade fee, to move the price by `bps` either way
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PoolManifestDepthBand {
+    pub bps: u32,
+    pub price_lower: f64,
+    pub price_upper: f64,
+    /// Token_0 swapped in to move the price down to `price_lower`
+    pub amount_0_to_lower: String,
+    /// Token_1 swapped in to move the price up to `price_upper`
+    pub amount_1_to_upper: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolManifestObservation {
+    pub initialized: bool,
+    /// The observations the ring buffer holds
+    pub capacity: u32,
+    /// Min seconds between two observations
+    pub update_duration: u32,
+    pub observation_index: u16,
+    /// Timestamp of the oldest observation, zero if none is recorded
+    pub oldest_timestamp: u32,
+    pub newest_timestamp: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolManifestReward {
+    pub index: u8,
+    pub mint: String,
+    pub vault: String,
+    pub authority: String,
+    pub reward_state: u8,
+    pub open_time: u64,
+    pub end_time: u64,
+}
+
+/// The sqrt price `bps` away from `sqrt_price_x64`, down if `down`, clamped to the price range
+pub fn band_sqrt_price_x64(sqrt_price_x64: u128, bps: u32, down: bool) -> u128 {
+    let ratio = if down {
+        1.0 - bps as f64 / 10_000.0
+    } else {
+        1.0 + bps as f64 / 10_000.0
+    };
+    ((sqrt_price_x64 as f64 * ratio.sqrt()) as u128).clamp(
+        tick_math::MIN_SQRT_PRICE_X64,
+        tick_math::MAX_SQRT_PRICE_X64 - 1,
+    )
+}
+
+/// The amount swapped in, before the trade fee, to move the price from the current one to
+/// `sqrt_price_limit_x64`. `ticks` holds the initialized ticks around the current price as
+/// `(tick, liquidity_net)`, sorted by tick.
+pub fn depth_to_sqrt_price(
+    sqrt_price_x64: u128,
+    tick_current: i32,
+    liquidity: u128,
+    ticks: &[(i32, i128)],
+    sqrt_price_limit_x64: u128,
+) -> Result<u128> {
+    let zero_for_one = sqrt_price_limit_x64 < sqrt_price_x64;
+    let delta_amount = |sqrt_price_a_x64: u128, sqrt_price_b_x64: u128, liquidity: u128| {
+        if zero_for_one {
+            liquidity_math::get_delta_amount_0_unsigned(
+                sqrt_price_a_x64,
+                sqrt_price_b_x64,
+                liquidity,
+                true,
+            )
+        } else {
+            liquidity_math::get_delta_amount_1_unsigned(
+                sqrt_price_a_x64,
+                sqrt_price_b_x64,
+                liquidity,
+                true,
+            )
+        }
+    };
+    let crossed: Vec<&(i32, i128)> = if zero_for_one {
+        ticks
+            .iter()
+            .rev()
+            .filter(|(tick, _)| *tick <= tick_current)
+            .collect()
+    } else {
+        ticks
+            .iter()
+            .filter(|(tick, _)| *tick > tick_current)
+            .collect()
+    };
+    let mut amount = 0u128;
+    let mut sqrt_price_x64 = sqrt_price_x64;
+    let mut liquidity = liquidity;
+    for (tick, liquidity_net) in crossed {
+        let sqrt_price_tick_x64 = tick_math::get_sqrt_price_at_tick(*tick)?;
+        if (zero_for_one && sqrt_price_tick_x64 <= sqrt_price_limit_x64)
+            || (!zero_for_one && sqrt_price_tick_x64 >= sqrt_price_limit_x64)
+        {
+            break;
+        }
+        amount += u128::from(delta_amount(
+            sqrt_price_x64,
+            sqrt_price_tick_x64,
+            liquidity,
+        )?);
+        sqrt_price_x64 = sqrt_price_tick_x64;
+        let liquidity_net = if zero_for_one {
+            -*liquidity_net
+        } else {
+            *liquidity_net
+        };
+        liquidity = liquidity_math::add_delta(liquidity, liquidity_net)?;
+    }
+    amount += u128::from(delta_amount(
+        sqrt_price_x64,
+        sqrt_price_limit_x64,
+        liquidity,
+    )?);
+    Ok(amount)
+}
+
+/// Load the pool with its config, observation and the tick arrays within the widest depth band
+/// and build the manifest
+pub fn load_pool_manifest(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+) -> Result<PoolManifest> {
+    let pool_rsp = rpc_client
+        .get_multiple_accounts_with_commitment(&[*pool_id], CommitmentConfig::confirmed())?;
+    let pool_state = deserialize_anchor_account::<PoolState>(
+        pool_rsp.value[0]
+            .as_ref()
+            .ok_or(format_err!("pool {} not found", pool_id))?,
+    )?;
+    let tickarray_bitmap_extension = Pubkey::find_program_address(
+        &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+        program_id,
+    )
+    .0;
+    let rsp = rpc_client.get_multiple_accounts_with_commitment(
+        &[
+            pool_state.amm_config,
+            pool_state.observation_key,
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+        ],
+        CommitmentConfig::confirmed(),
+    )?;
+    let mut slot = pool_rsp.context.slot.min(rsp.context.slot);
+    let amm_config = deserialize_anchor_account::<AmmConfig>(rsp.value[0].as_ref().ok_or(
+        format_err!("amm config {} not found", pool_state.amm_config),
+    )?)?;
+    let observation_state =
+        deserialize_anchor_account::<ObservationState>(rsp.value[1].as_ref().ok_or(
+            format_err!("observation {} not found", pool_state.observation_key),
+        )?)?;
+    let mut token_programs = Vec::new();
+    for (mint, account) in [
+        (pool_state.token_mint_0, &rsp.value[2]),
+        (pool_state.token_mint_1, &rsp.value[3]),
+    ] {
+        token_programs.push(
+            account
+                .as_ref()
+                .ok_or(format_err!("mint {} not found", mint))?
+                .owner,
+        );
+    }
+
+    let sqrt_price_x64 = pool_state.sqrt_price_x64;
+    let tick_current = pool_state.tick_current;
+    let liquidity = pool_state.liquidity;
+    let tick_spacing = pool_state.tick_spacing;
+    let widest_band = STANDARD_DEPTH_BANDS_BPS[STANDARD_DEPTH_BANDS_BPS.len() - 1];
+    let tick_lower =
+        tick_math::get_tick_at_sqrt_price(band_sqrt_price_x64(sqrt_price_x64, widest_band, true))?;
+    let tick_upper =
+        tick_math::get_tick_at_sqrt_price(band_sqrt_price_x64(sqrt_price_x64, widest_band, false))?;
+    let tick_array_keys =
+        tick_array_keys_for_range(program_id, pool_id, tick_spacing, tick_lower, tick_upper);
+    let mut tick_arrays = Vec::new();
+    let mut ticks = Vec::new();
+    let keys: Vec<Pubkey> = tick_array_keys.iter().map(|(_, key)| *key).collect();
+    let (tick_arrays_slot, tick_array_accounts) = scanner.get_multiple_accounts_with_commitment(
+        rpc_client,
+        &keys,
+        CommitmentConfig::confirmed(),
+    )?;
+    slot = slot.min(tick_arrays_slot);
+    for ((start_tick_index, key), account) in tick_array_keys.iter().zip(tick_array_accounts) {
+        // tick arrays without initialized ticks don't exist
+        let account = match account {
+            Some(account) => account,
+            None => continue,
+        };
+        let tick_array = deserialize_anchor_account::<TickArrayState>(&account)?;
+        for tick_state in tick_array.ticks {
+            if tick_state.is_initialized() {
+                ticks.push((tick_state.tick, tick_state.liquidity_net));
+            }
+        }
+        tick_arrays.push(PoolManifestTickArray {
+            start_tick_index: *start_tick_index,
+            address: key.to_string(),
+        });
+    }
+    ticks.sort_by_key(|(tick, _)| *tick);
+
+    let (decimals_0, decimals_1) = (pool_state.mint_decimals_0, pool_state.mint_decimals_1);
+    let mut depth = Vec::new();
+    for bps in STANDARD_DEPTH_BANDS_BPS {
+        let sqrt_price_lower_x64 = band_sqrt_price_x64(sqrt_price_x64, bps, true);
+        let sqrt_price_upper_x64 = band_sqrt_price_x64(sqrt_price_x64, bps, false);
+        depth.push(PoolManifestDepthBand {
+            bps,
+            price_lower: sqrt_price_x64_to_price(sqrt_price_lower_x64, decimals_0, decimals_1),
+            price_upper: sqrt_price_x64_to_price(sqrt_price_upper_x64, decimals_0, decimals_1),
+            amount_0_to_lower: depth_to_sqrt_price(
+                sqrt_price_x64,
+                tick_current,
+                liquidity,
+                &ticks,
+                sqrt_price_lower_x64,
+            )?
+            .to_string(),
+            amount_1_to_upper: depth_to_sqrt_price(
+                sqrt_price_x64,
+                tick_current,
+                liquidity,
+                &ticks,
+                sqrt_price_upper_x64,
+            )?
+            .to_string(),
+        });
+    }
+
+    let observations = observation_state.observations;
+    let recorded: Vec<u32> = observations
+        .iter()
+        .map(|observation| observation.block_timestamp)
+        .filter(|block_timestamp| *block_timestamp != 0)
+        .collect();
+    let reward_infos = pool_state.reward_infos;
+    let rewards = reward_infos
+        .iter()
+        .enumerate()
+        .filter(|(_, reward_info)| reward_info.initialized())
+        .map(|(index, reward_info)| PoolManifestReward {
+            index: index as u8,
+            mint: reward_info.token_mint.to_string(),
+            vault: reward_info.token_vault.to_string(),
+            authority: reward_info.authority.to_string(),
+            reward_state: reward_info.reward_state,
+            open_time: reward_info.open_time,
+            end_time: reward_info.end_time,
+        })
+        .collect();
+
+    Ok(PoolManifest {
+        schema_version: POOL_MANIFEST_SCHEMA_VERSION,
+        program_id: program_id.to_string(),
+        pool_id: pool_id.to_string(),
+        slot,
+        status: pool_state.status,
+        open_time: pool_state.open_time,
+        accounts: PoolManifestAccounts {
+            amm_config: pool_state.amm_config.to_string(),
+            observation: pool_state.observation_key.to_string(),
+            tick_array_bitmap_extension: tickarray_bitmap_extension.to_string(),
+            token_vault_0: pool_state.token_vault_0.to_string(),
+            token_vault_1: pool_state.token_vault_1.to_string(),
+            tick_arrays,
+        },
+        mint_0: PoolManifestMint {
+            mint: pool_state.token_mint_0.to_string(),
+            decimals: decimals_0,
+            token_program: token_programs[0].to_string(),
+        },
+        mint_1: PoolManifestMint {
+            mint: pool_state.token_mint_1.to_string(),
+            decimals: decimals_1,
+            token_program: token_programs[1].to_string(),
+        },
+        fee: PoolManifestFee {
+            amm_config_index: amm_config.index,
+            trade_fee_rate: amm_config.trade_fee_rate,
+            protocol_fee_rate: amm_config.protocol_fee_rate,
+            fund_fee_rate: amm_config.fund_fee_rate,
+            fee_rate_denominator: FEE_RATE_DENOMINATOR_VALUE,
+        },
+        tick_spacing,
+        price: PoolManifestPrice {
+            sqrt_price_x64: sqrt_price_x64.to_string(),
+            tick_current,
+            price: sqrt_price_x64_to_price(sqrt_price_x64, decimals_0, decimals_1),
+            liquidity: liquidity.to_string(),
+        },
+        depth,
+        observation: PoolManifestObservation {
+            initialized: observation_state.initialized,
+            capacity: OBSERVATION_NUM as u32,
+            update_duration: OBSERVATION_UPDATE_DURATION_DEFAULT,
+            observation_index: observation_state.observation_index,
+            oldest_timestamp: recorded.iter().copied().min().unwrap_or_default(),
+            newest_timestamp: recorded.iter().copied().max().unwrap_or_default(),
+        },
+        rewards,
+    })
+}
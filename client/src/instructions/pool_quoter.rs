@@ -0,0 +1,271 @@
+use anchor_lang::Discriminator;
+use anyhow::{format_err, Result};
+use raydium_amm_v3::states::{
+    AmmConfig, PoolState, TickArrayBitmapExtension, TickArrayState, POOL_SEED,
+    POOL_TICK_ARRAY_BITMAP_SEED, TICK_ARRAY_SEED,
+};
+use rayon::prelude::*;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::VecDeque;
+
+use super::rpc::RpcScanner;
+use super::utils::{
+    deserialize_anchor_account, get_first_initialized_tick_array, quote_swap, CanonicalPair,
+    QuoteError,
+};
+
+/// Tick arrays loaded after the first initialized one in the swap direction
+pub const NEXT_TICK_ARRAY_COUNT: usize = 5;
+
+/// The accounts a quote of the pool reads, loaded once so quoting doesn't touch the rpc
+#[derive(Clone)]
+pub struct PoolQuoteAccounts {
+    pub pool_id: Pubkey,
+    pub pool_state: PoolState,
+    pub amm_config: AmmConfig,
+    pub tickarray_bitmap_extension: TickArrayBitmapExtension,
+    pub zero_for_one: bool,
+    pub tick_arrays: VecDeque<TickArrayState>,
+}
+
+/// Quote of swapping an exact input amount through a pool
+#[derive(Debug, Clone)]
+pub struct PoolQuote {
+    pub pool_id: Pubkey,
+    pub amm_config: Pubkey,
+    pub trade_fee_rate: u32,
+    pub zero_for_one: bool,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub tick_array_start_indexes: Vec<i32>,
+}
+
+impl PoolQuoteAccounts {
+    /// Quote swapping `amount_in` of the input token, only reads the loaded accounts
+    pub fn quote(&self, amount_in: u64) -> Result<PoolQuote, QuoteError> {
+        let (amount_out, tick_array_start_indexes) = quote_swap(
+            amount_in,
+            None,
+            self.zero_for_one,
+            true,
+            &self.amm_config,
+            &self.pool_state,
+            &self.tickarray_bitmap_extension,
+            &self.tick_arrays,
+        )?;
+        Ok(PoolQuote {
+            pool_id: self.pool_id,
+            amm_config: self.pool_state.amm_config,
+            trade_fee_rate: self.amm_config.trade_fee_rate,
+            zero_for_one: self.zero_for_one,
+            amount_in,
+            amount_out,
+            tick_array_start_indexes: tick_array_start_indexes.into(),
+        })
+    }
+}
+
+/// Load the first initialized tick array in the swap direction and the next ones
+pub fn load_tick_arrays(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    zero_for_one: bool,
+) -> Result<VecDeque<TickArrayState>, QuoteError> {
+    let tick_array_key = |start_index: i32| {
+        Pubkey::find_program_address(
+            &[
+                TICK_ARRAY_SEED.as_bytes(),
+                pool_id.as_ref(),
+                &start_index.to_be_bytes(),
+            ],
+            program_id,
+        )
+        .0
+    };
+    let (_, mut start_index) =
+        get_first_initialized_tick_array(pool_state, tickarray_bitmap_extension, zero_for_one)?;
+    let mut tick_array_keys = vec![tick_array_key(start_index)];
+    for _ in 0..NEXT_TICK_ARRAY_COUNT {
+        match pool_state
+            .next_initialized_tick_array_start_index(
+                &Some(*tickarray_bitmap_extension),
+                start_index,
+                zero_for_one,
+            )
+            .map_err(|_| QuoteError::Compute("failed to find the next tick array"))?
+        {
+            Some(next_start_index) => start_index = next_start_index,
+            None => break,
+        }
+        tick_array_keys.push(tick_array_key(start_index));
+    }
+    let mut tick_arrays = VecDeque::new();
+    for account in rpc_client
+        .get_multiple_accounts(&tick_array_keys)
+        .map_err(|_| QuoteError::Compute("failed to load the tick arrays"))?
+    {
+        let account = account.ok_or(QuoteError::Compute("tick array not found"))?;
+        tick_arrays.push_back(
+            deserialize_anchor_account::<TickArrayState>(&account)
+                .map_err(|_| QuoteError::Compute("failed to decode the tick array"))?,
+        );
+    }
+    Ok(tick_arrays)
+}
+
+/// Load the accounts to quote swapping `input_mint` through the pool
+pub fn load_pool_quote_accounts(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    pool_id: Pubkey,
+    input_mint: Pubkey,
+) -> Result<PoolQuoteAccounts> {
+    let tickarray_bitmap_extension_key = Pubkey::find_program_address(
+        &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+        program_id,
+    )
+    .0;
+    let rsps = rpc_client.get_multiple_accounts(&[pool_id, tickarray_bitmap_extension_key])?;
+    let pool_state = deserialize_anchor_account::<PoolState>(
+        rsps[0]
+            .as_ref()
+            .ok_or(format_err!("pool {} not found", pool_id))?,
+    )?;
+    let tickarray_bitmap_extension = deserialize_anchor_account::<TickArrayBitmapExtension>(
+        rsps[1]
+            .as_ref()
+            .ok_or(format_err!("pool {} has no bitmap extension", pool_id))?,
+    )?;
+    let zero_for_one = input_mint == pool_state.token_mint_0;
+    if !zero_for_one && input_mint != pool_state.token_mint_1 {
+        return Err(format_err!(
+            "pool {} doesn't trade mint {}",
+            pool_id,
+            input_mint
+        ));
+    }
+    let amm_config =
+        deserialize_anchor_account::<AmmConfig>(&rpc_client.get_account(&pool_state.amm_config)?)?;
+    let tick_arrays = load_tick_arrays(
+        rpc_client,
+        program_id,
+        &pool_id,
+        &pool_state,
+        &tickarray_bitmap_extension,
+        zero_for_one,
+    )?;
+    Ok(PoolQuoteAccounts {
+        pool_id,
+        pool_state,
+        amm_config,
+        tickarray_bitmap_extension,
+        zero_for_one,
+        tick_arrays,
+    })
+}
+
+/// Quote the pools in parallel, loading their accounts concurrently. The quotes are ranked by
+/// the output amount, the pools that failed to quote come last.
+pub fn quote_pools(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    pool_ids: &[Pubkey],
+    input_mint: Pubkey,
+    amount_in: u64,
+) -> Vec<(Pubkey, Result<PoolQuote>)> {
+    let mut quotes: Vec<(Pubkey, Result<PoolQuote>)> = pool_ids
+        .par_iter()
+        .map(|pool_id| {
+            let quote = load_pool_quote_accounts(rpc_client, program_id, *pool_id, input_mint)
+                .and_then(|accounts| {
+                    accounts
+                        .quote(amount_in)
+                        .map_err(|err| format_err!("{}", err))
+                });
+            (*pool_id, quote)
+        })
+        .collect();
+    quotes.sort_by_key(|(_, quote)| match quote {
+        Ok(quote) => (0, u64::MAX - quote.amount_out),
+        Err(_) => (1, 0),
+    });
+    quotes
+}
+
+/// Every amm config of the program ordered by index. Configs are only unique by index, several
+/// can share a tick spacing or a trade fee rate
+pub fn list_amm_configs(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    program_id: &Pubkey,
+) -> Result<Vec<(Pubkey, AmmConfig)>> {
+    let accounts = scanner.get_program_accounts_with_config(
+        rpc_client,
+        program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                    0,
+                    AmmConfig::discriminator().to_vec(),
+                )),
+                RpcFilterType::DataSize(AmmConfig::LEN as u64),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                ..RpcAccountInfoConfig::default()
+            },
+            with_context: Some(false),
+        },
+    )?;
+    let mut amm_configs = accounts
+        .iter()
+        .map(|(key, account)| Ok((*key, deserialize_anchor_account::<AmmConfig>(account)?)))
+        .collect::<Result<Vec<_>>>()?;
+    amm_configs.sort_by_key(|(_, amm_config)| amm_config.index);
+    Ok(amm_configs)
+}
+
+/// The pools of the mint pair under every amm config of the program
+pub fn find_pair_pools(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    program_id: &Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+) -> Result<Vec<Pubkey>> {
+    let CanonicalPair { mint0, mint1, .. } = CanonicalPair::new(mint_a, mint_b);
+    let candidates: Vec<Pubkey> = list_amm_configs(rpc_client, scanner, program_id)?
+        .iter()
+        .map(|(amm_config, _)| {
+            Pubkey::find_program_address(
+                &[
+                    POOL_SEED.as_bytes(),
+                    amm_config.as_ref(),
+                    mint0.as_ref(),
+                    mint1.as_ref(),
+                ],
+                program_id,
+            )
+            .0
+        })
+        .collect();
+    let mut pools = Vec::new();
+    for (pool_id, account) in candidates
+        .iter()
+        .zip(scanner.get_multiple_accounts(rpc_client, &candidates)?)
+    {
+        if account.is_some() {
+            pools.push(*pool_id);
+        }
+    }
+    Ok(pools)
+}
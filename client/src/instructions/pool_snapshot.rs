@@ -0,0 +1,468 @@
+use anchor_lang::AccountDeserialize;
+use anyhow::{format_err, Result};
+use raydium_amm_v3::states::{
+    PersonalPositionState, PoolState, ProtocolPositionState, TickArrayState,
+    POOL_TICK_ARRAY_BITMAP_SEED,
+};
+use serde::{Deserialize, Serialize};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, keccak, pubkey::Pubkey};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+use super::rpc::RpcScanner;
+use super::utils::deserialize_anchor_account;
+
+/// One account of the pool snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSnapshotAccount {
+    /// pool, observation, tick_array_bitmap_extension, tick_array, protocol_position or personal_position
+    pub kind: String,
+    pub pubkey: String,
+    pub owner: String,
+    /// keccak of the account data
+    pub data_hash: String,
+}
+
+impl PoolSnapshotAccount {
+    pub fn new(kind: &str, pubkey: &Pubkey, account: &Account) -> Self {
+        Self {
+            kind: kind.to_string(),
+            pubkey: pubkey.to_string(),
+            owner: account.owner.to_string(),
+            data_hash: hex::encode(keccak::hash(&account.data).0),
+        }
+    }
+
+    /// Leaf of the snapshot merkle tree, `keccak(pubkey, owner, keccak(data))`
+    pub fn leaf(&self) -> Result<[u8; 32]> {
+        let pubkey = Pubkey::from_str(&self.pubkey)?;
+        let owner = Pubkey::from_str(&self.owner)?;
+        let data_hash = hex::decode(&self.data_hash)?;
+        Ok(keccak::hashv(&[pubkey.as_ref(), owner.as_ref(), &data_hash]).0)
+    }
+}
+
+/// All accounts belonging to a pool at a slot, written by `export-snapshot`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolSnapshot {
+    pub pool_id: String,
+    /// The minimum slot the accounts were loaded at
+    pub slot: u64,
+    pub merkle_root: String,
+    /// Sorted by pubkey
+    pub accounts: Vec<PoolSnapshotAccount>,
+}
+
+impl PoolSnapshot {
+    /// Canonicalize the accounts by sorting them by pubkey and compute the merkle root
+    pub fn new(
+        pool_id: &Pubkey,
+        slot: u64,
+        mut accounts: Vec<PoolSnapshotAccount>,
+    ) -> Result<Self> {
+        accounts.sort_by_key(|account| Pubkey::from_str(&account.pubkey).unwrap_or_default());
+        let merkle_root = hex::encode(snapshot_merkle_root(&accounts)?);
+        Ok(Self {
+            pool_id: pool_id.to_string(),
+            slot,
+            merkle_root,
+            accounts,
+        })
+    }
+
+    /// Returns the differences to `other` by account, empty if both hold the same accounts
+    pub fn diff(&self, other: &PoolSnapshot) -> Vec<String> {
+        let accounts: BTreeMap<&String, &PoolSnapshotAccount> = self
+            .accounts
+            .iter()
+            .map(|account| (&account.pubkey, account))
+            .collect();
+        let other_accounts: BTreeMap<&String, &PoolSnapshotAccount> = other
+            .accounts
+            .iter()
+            .map(|account| (&account.pubkey, account))
+            .collect();
+        let mut diffs = Vec::new();
+        for (pubkey, account) in accounts.iter() {
+            match other_accounts.get(pubkey) {
+                None => diffs.push(format!("{} {} missing", account.kind, pubkey)),
+                Some(other_account) if other_account != account => diffs.push(format!(
+                    "{} {} data_hash:{}, other data_hash:{}",
+                    account.kind, pubkey, account.data_hash, other_account.data_hash
+                )),
+                Some(_) => {}
+            }
+        }
+        for (pubkey, account) in other_accounts.iter() {
+            if !accounts.contains_key(pubkey) {
+                diffs.push(format!("{} {} unexpected", account.kind, pubkey));
+            }
+        }
+        diffs
+    }
+}
+
+/// Merkle root of the snapshot accounts in their order, a node without sibling is promoted to the
+/// next level unchanged
+pub fn snapshot_merkle_root(accounts: &[PoolSnapshotAccount]) -> Result<[u8; 32]> {
+    let mut layer = accounts
+        .iter()
+        .map(|account| account.leaf())
+        .collect::<Result<Vec<[u8; 32]>>>()?;
+    if layer.is_empty() {
+        return Ok([0u8; 32]);
+    }
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                if pair.len() == 2 {
+                    keccak::hashv(&[&pair[0], &pair[1]]).0
+                } else {
+                    pair[0]
+                }
+            })
+            .collect();
+    }
+    Ok(layer[0])
+}
+
+fn get_program_accounts_by_pool(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+    pool_id_offset: usize,
+    data_size: usize,
+    min_context_slot: u64,
+) -> Result<Vec<(Pubkey, Account)>> {
+    scanner.get_program_accounts_with_config(
+        rpc_client,
+        program_id,
+        RpcProgramAccountsConfig {
+            filters: Some(vec![
+                RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                    pool_id_offset,
+                    &pool_id.to_bytes(),
+                )),
+                RpcFilterType::DataSize(data_size as u64),
+            ]),
+            account_config: RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                min_context_slot: Some(min_context_slot),
+                ..RpcAccountInfoConfig::default()
+            },
+            with_context: Some(false),
+        },
+    )
+}
+
+/// Load the pool, its observation, bitmap extension, tick arrays and positions and build the snapshot
+pub fn load_pool_snapshot(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+) -> Result<PoolSnapshot> {
+    let pool_account = rpc_client
+        .get_account_with_commitment(pool_id, CommitmentConfig::confirmed())?
+        .value
+        .ok_or(format_err!("pool {} not found", pool_id))?;
+    let pool_state = deserialize_anchor_account::<PoolState>(&pool_account)?;
+    let tickarray_bitmap_extension = Pubkey::find_program_address(
+        &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+        program_id,
+    )
+    .0;
+    let rsp = rpc_client.get_multiple_accounts_with_commitment(
+        &[
+            *pool_id,
+            pool_state.observation_key,
+            tickarray_bitmap_extension,
+        ],
+        CommitmentConfig::confirmed(),
+    )?;
+    let slot = rsp.context.slot;
+
+    let mut accounts = Vec::new();
+    for (kind, pubkey, account) in [
+        ("pool", pool_id, &rsp.value[0]),
+        ("observation", &pool_state.observation_key, &rsp.value[1]),
+        (
+            "tick_array_bitmap_extension",
+            &tickarray_bitmap_extension,
+            &rsp.value[2],
+        ),
+    ] {
+        if let Some(account) = account {
+            accounts.push(PoolSnapshotAccount::new(kind, pubkey, account));
+        }
+    }
+    for (kind, pool_id_offset, data_size) in [
+        ("tick_array", 8, TickArrayState::LEN),
+        ("protocol_position", 8 + 1, ProtocolPositionState::LEN),
+        ("personal_position", 8 + 1 + 32, PersonalPositionState::LEN),
+    ] {
+        for (pubkey, account) in get_program_accounts_by_pool(
+            rpc_client,
+            scanner,
+            program_id,
+            pool_id,
+            pool_id_offset,
+            data_size,
+            slot,
+        )? {
+            accounts.push(PoolSnapshotAccount::new(kind, &pubkey, &account));
+        }
+    }
+    PoolSnapshot::new(pool_id, slot, accounts)
+}
+
+/// One account of the liquidity snapshot with its raw data, enough to rebuild the pool locally
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquiditySnapshotAccount {
+    /// pool, amm_config, observation, tick_array_bitmap_extension, tick_array or personal_position
+    pub kind: String,
+    pub pubkey: String,
+    pub owner: String,
+    /// base64 of the account data
+    pub data: String,
+}
+
+impl LiquiditySnapshotAccount {
+    pub fn new(kind: &str, pubkey: &Pubkey, account: &Account) -> Self {
+        Self {
+            kind: kind.to_string(),
+            pubkey: pubkey.to_string(),
+            owner: account.owner.to_string(),
+            data: anchor_lang::__private::base64::encode(&account.data),
+        }
+    }
+
+    pub fn decode<T: AccountDeserialize>(&self) -> Result<T> {
+        let data = anchor_lang::__private::base64::decode(&self.data)?;
+        T::try_deserialize(&mut data.as_slice()).map_err(Into::into)
+    }
+}
+
+/// An initialized tick of the liquidity snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquiditySnapshotTick {
+    pub tick: i32,
+    pub liquidity_net: String,
+    pub liquidity_gross: String,
+}
+
+/// A position of the liquidity snapshot
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LiquiditySnapshotPosition {
+    pub personal_position: String,
+    pub nft_mint: String,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: String,
+}
+
+/// The pool state, initialized ticks and positions of a pool at a slot with the raw accounts
+/// behind them, written by `snapshot-pool`. Every list is sorted so two snapshots of the same
+/// state are byte for byte equal. u128 values are strings.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PoolLiquiditySnapshot {
+    pub pool_id: String,
+    /// The minimum slot the accounts were loaded at
+    pub slot: u64,
+    pub amm_config: String,
+    pub tick_spacing: u16,
+    pub tick_current: i32,
+    pub sqrt_price_x64: String,
+    pub liquidity: String,
+    /// Sorted by tick
+    pub ticks: Vec<LiquiditySnapshotTick>,
+    /// Sorted by personal position
+    pub positions: Vec<LiquiditySnapshotPosition>,
+    /// Sorted by pubkey
+    pub accounts: Vec<LiquiditySnapshotAccount>,
+}
+
+impl PoolLiquiditySnapshot {
+    pub fn new(
+        pool_id: &Pubkey,
+        slot: u64,
+        pool_state: &PoolState,
+        tick_arrays: &[TickArrayState],
+        positions: &[(Pubkey, PersonalPositionState)],
+        mut accounts: Vec<LiquiditySnapshotAccount>,
+    ) -> Self {
+        let mut ticks = Vec::new();
+        for tick_array in tick_arrays {
+            for tick_state in tick_array.ticks.iter() {
+                if !tick_state.is_initialized() {
+                    continue;
+                }
+                let liquidity_net = tick_state.liquidity_net;
+                let liquidity_gross = tick_state.liquidity_gross;
+                ticks.push(LiquiditySnapshotTick {
+                    tick: tick_state.tick,
+                    liquidity_net: liquidity_net.to_string(),
+                    liquidity_gross: liquidity_gross.to_string(),
+                });
+            }
+        }
+        ticks.sort_by_key(|tick| tick.tick);
+
+        let mut positions: Vec<(Pubkey, LiquiditySnapshotPosition)> = positions
+            .iter()
+            .map(|(key, position)| {
+                (
+                    *key,
+                    LiquiditySnapshotPosition {
+                        personal_position: key.to_string(),
+                        nft_mint: position.nft_mint.to_string(),
+                        tick_lower_index: position.tick_lower_index,
+                        tick_upper_index: position.tick_upper_index,
+                        liquidity: { position.liquidity }.to_string(),
+                    },
+                )
+            })
+            .collect();
+        positions.sort_by_key(|(key, _)| *key);
+        accounts.sort_by_key(|account| Pubkey::from_str(&account.pubkey).unwrap_or_default());
+
+        let sqrt_price_x64 = pool_state.sqrt_price_x64;
+        let liquidity = pool_state.liquidity;
+        Self {
+            pool_id: pool_id.to_string(),
+            slot,
+            amm_config: pool_state.amm_config.to_string(),
+            tick_spacing: pool_state.tick_spacing,
+            tick_current: pool_state.tick_current,
+            sqrt_price_x64: sqrt_price_x64.to_string(),
+            liquidity: liquidity.to_string(),
+            ticks,
+            positions: positions
+                .into_iter()
+                .map(|(_, position)| position)
+                .collect(),
+            accounts,
+        }
+    }
+
+    /// The first raw account of a kind, for the kinds the pool has at most one of
+    pub fn account(&self, kind: &str) -> Option<&LiquiditySnapshotAccount> {
+        self.accounts.iter().find(|account| account.kind == kind)
+    }
+
+    /// Decode the raw accounts of a kind, in pubkey order
+    pub fn decode_accounts<T: AccountDeserialize>(&self, kind: &str) -> Result<Vec<T>> {
+        self.accounts
+            .iter()
+            .filter(|account| account.kind == kind)
+            .map(|account| account.decode())
+            .collect()
+    }
+}
+
+/// Load the pool, its amm config, observation, bitmap extension, tick arrays and positions with
+/// their data to build the liquidity snapshot
+pub fn load_pool_liquidity_snapshot(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+) -> Result<PoolLiquiditySnapshot> {
+    let pool_account = rpc_client
+        .get_account_with_commitment(pool_id, CommitmentConfig::confirmed())?
+        .value
+        .ok_or(format_err!("pool {} not found", pool_id))?;
+    let pool_state = deserialize_anchor_account::<PoolState>(&pool_account)?;
+    let tickarray_bitmap_extension = Pubkey::find_program_address(
+        &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+        program_id,
+    )
+    .0;
+    let rsp = rpc_client.get_multiple_accounts_with_commitment(
+        &[
+            *pool_id,
+            pool_state.amm_config,
+            pool_state.observation_key,
+            tickarray_bitmap_extension,
+        ],
+        CommitmentConfig::confirmed(),
+    )?;
+    let slot = rsp.context.slot;
+    // the pool is read again at the slot of the other accounts
+    let pool_state = deserialize_anchor_account::<PoolState>(
+        rsp.value[0]
+            .as_ref()
+            .ok_or(format_err!("pool {} not found", pool_id))?,
+    )?;
+
+    let mut accounts = Vec::new();
+    for (kind, pubkey, account) in [
+        ("pool", pool_id, &rsp.value[0]),
+        ("amm_config", &pool_state.amm_config, &rsp.value[1]),
+        ("observation", &pool_state.observation_key, &rsp.value[2]),
+        (
+            "tick_array_bitmap_extension",
+            &tickarray_bitmap_extension,
+            &rsp.value[3],
+        ),
+    ] {
+        if let Some(account) = account {
+            accounts.push(LiquiditySnapshotAccount::new(kind, pubkey, account));
+        }
+    }
+
+    let mut tick_arrays = Vec::new();
+    for (pubkey, account) in get_program_accounts_by_pool(
+        rpc_client,
+        scanner,
+        program_id,
+        pool_id,
+        8,
+        TickArrayState::LEN,
+        slot,
+    )? {
+        tick_arrays.push(deserialize_anchor_account::<TickArrayState>(&account)?);
+        accounts.push(LiquiditySnapshotAccount::new(
+            "tick_array",
+            &pubkey,
+            &account,
+        ));
+    }
+    let mut positions = Vec::new();
+    for (pubkey, account) in get_program_accounts_by_pool(
+        rpc_client,
+        scanner,
+        program_id,
+        pool_id,
+        8 + 1 + 32,
+        PersonalPositionState::LEN,
+        slot,
+    )? {
+        positions.push((
+            pubkey,
+            deserialize_anchor_account::<PersonalPositionState>(&account)?,
+        ));
+        accounts.push(LiquiditySnapshotAccount::new(
+            "personal_position",
+            &pubkey,
+            &account,
+        ));
+    }
+    Ok(PoolLiquiditySnapshot::new(
+        pool_id,
+        slot,
+        &pool_state,
+        &tick_arrays,
+        &positions,
+        accounts,
+    ))
+}
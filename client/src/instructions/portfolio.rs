@@ -0,0 +1,245 @@
+use anyhow::{format_err, Result};
+use raydium_amm_v3::libraries::liquidity_math;
+use raydium_amm_v3::states::{tick_array_key, PersonalPositionState, PoolState, TickArrayState};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+
+use super::super::{get_all_nft_and_position_by_owner, ClientConfig};
+use super::rpc::RpcScanner;
+use super::utils::{
+    deserialize_anchor_account, position_claimable_fees, position_claimable_rewards,
+};
+
+/// A position of the owner valued at the current price of its pool. Amounts and liquidity are
+/// strings so they survive json parsers limited to f64.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortfolioPosition {
+    pub nft_mint: String,
+    pub position: String,
+    pub pool_id: String,
+    pub token_mint_0: String,
+    pub token_mint_1: String,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub in_range: bool,
+    pub liquidity: String,
+    /// The amounts removing all of the liquidity would withdraw
+    pub amount_0: String,
+    pub amount_1: String,
+    /// The fees collectable now, the owed fees plus the accrual since the last update
+    pub fees_0: String,
+    pub fees_1: String,
+    /// The rewards collectable now of the initialized rewards of the pool
+    pub rewards: Vec<PortfolioReward>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortfolioReward {
+    pub mint: String,
+    pub amount: String,
+}
+
+/// The sum over all positions of a mint, as liquidity, fees or rewards
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortfolioMintTotal {
+    pub mint: String,
+    pub liquidity_amount: String,
+    pub fees: String,
+    pub rewards: String,
+}
+
+/// Every position nft held by the owner, written by `portfolio`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Portfolio {
+    pub owner: String,
+    pub positions: Vec<PortfolioPosition>,
+    pub totals: Vec<PortfolioMintTotal>,
+}
+
+impl Portfolio {
+    /// One row per position followed by the totals per mint
+    pub fn table(&self) -> String {
+        let mut table = format!(
+            "{:<44} {:<44} {:>8} {:>8} {:>5} {:>20} {:>20} {:>20} {:>20} rewards\n",
+            "nft_mint",
+            "pool_id",
+            "lower",
+            "upper",
+            "range",
+            "amount_0",
+            "amount_1",
+            "fees_0",
+            "fees_1"
+        );
+        for position in self.positions.iter() {
+            let rewards: Vec<String> = position
+                .rewards
+                .iter()
+                .map(|reward| format!("{}:{}", reward.mint, reward.amount))
+                .collect();
+            table.push_str(&format!(
+                "{:<44} {:<44} {:>8} {:>8} {:>5} {:>20} {:>20} {:>20} {:>20} {}\n",
+                position.nft_mint,
+                position.pool_id,
+                position.tick_lower_index,
+                position.tick_upper_index,
+                if position.in_range { "in" } else { "out" },
+                position.amount_0,
+                position.amount_1,
+                position.fees_0,
+                position.fees_1,
+                rewards.join(", ")
+            ));
+        }
+        table.push_str(&format!(
+            "\n{:<44} {:>20} {:>20} {:>20}\n",
+            "mint", "liquidity_amount", "fees", "rewards"
+        ));
+        for total in self.totals.iter() {
+            table.push_str(&format!(
+                "{:<44} {:>20} {:>20} {:>20}\n",
+                total.mint, total.liquidity_amount, total.fees, total.rewards
+            ));
+        }
+        table
+    }
+}
+
+/// Value every position nft of the owner across all pools. The pending fees and rewards are
+/// computed from the tick arrays of the position bounds, the way the program accrues them.
+pub fn load_portfolio(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    scanner: &RpcScanner,
+    owner: &Pubkey,
+) -> Result<Portfolio> {
+    let nfts: Vec<_> =
+        get_all_nft_and_position_by_owner(rpc_client, owner, &config.raydium_v3_program)
+            .into_iter()
+            .filter(|nft| nft.amount == 1)
+            .collect();
+    let position_keys: Vec<Pubkey> = nfts.iter().map(|nft| nft.position).collect();
+    let mut positions = Vec::new();
+    for (key, account) in position_keys
+        .iter()
+        .zip(scanner.get_multiple_accounts(rpc_client, &position_keys)?)
+    {
+        // nfts of other programs have no position
+        if let Some(account) = account {
+            positions.push((
+                *key,
+                deserialize_anchor_account::<PersonalPositionState>(&account)?,
+            ));
+        }
+    }
+
+    let mut pool_ids: Vec<Pubkey> = positions
+        .iter()
+        .map(|(_, position)| position.pool_id)
+        .collect();
+    pool_ids.sort();
+    pool_ids.dedup();
+    let mut pools = HashMap::new();
+    for (pool_id, account) in pool_ids
+        .iter()
+        .zip(scanner.get_multiple_accounts(rpc_client, &pool_ids)?)
+    {
+        let account = account.ok_or(format_err!("pool {} not found", pool_id))?;
+        pools.insert(*pool_id, deserialize_anchor_account::<PoolState>(&account)?);
+    }
+
+    let mut portfolio_positions = Vec::new();
+    let mut totals: BTreeMap<Pubkey, (u64, u64, u64)> = BTreeMap::new();
+    for (key, position) in positions.iter() {
+        let pool_state = &pools[&position.pool_id];
+        let tick_array_keys: Vec<Pubkey> = [position.tick_lower_index, position.tick_upper_index]
+            .iter()
+            .map(|tick_index| {
+                tick_array_key(
+                    &config.raydium_v3_program,
+                    &position.pool_id,
+                    TickArrayState::get_array_start_index(*tick_index, pool_state.tick_spacing),
+                )
+            })
+            .collect();
+        let rsps = rpc_client.get_multiple_accounts(&tick_array_keys)?;
+        let mut tick_arrays = Vec::new();
+        for (tick_array_key, rsp) in tick_array_keys.iter().zip(rsps) {
+            let account = rsp.ok_or(format_err!("tick array {} not found", tick_array_key))?;
+            tick_arrays.push(deserialize_anchor_account::<TickArrayState>(&account)?);
+        }
+        let tick_lower = *tick_arrays[0]
+            .get_tick_state_mut(position.tick_lower_index, pool_state.tick_spacing)?;
+        let tick_upper = *tick_arrays[1]
+            .get_tick_state_mut(position.tick_upper_index, pool_state.tick_spacing)?;
+
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            position.tick_lower_index,
+            position.tick_upper_index,
+            -(position.liquidity as i128),
+        )?;
+        let (fees_0, fees_1) =
+            position_claimable_fees(pool_state, position, &tick_lower, &tick_upper);
+        let reward_amounts =
+            position_claimable_rewards(pool_state, position, &tick_lower, &tick_upper);
+
+        for (mint, amount, fees) in [
+            (pool_state.token_mint_0, amount_0, fees_0),
+            (pool_state.token_mint_1, amount_1, fees_1),
+        ] {
+            let total = totals.entry(mint).or_default();
+            total.0 = total.0.checked_add(amount).unwrap();
+            total.1 = total.1.checked_add(fees).unwrap();
+        }
+        let mut rewards = Vec::new();
+        for (reward_info, amount) in pool_state.reward_infos.iter().zip(reward_amounts) {
+            if !reward_info.initialized() {
+                continue;
+            }
+            let total = totals.entry(reward_info.token_mint).or_default();
+            total.2 = total.2.checked_add(amount).unwrap();
+            rewards.push(PortfolioReward {
+                mint: reward_info.token_mint.to_string(),
+                amount: amount.to_string(),
+            });
+        }
+
+        portfolio_positions.push(PortfolioPosition {
+            nft_mint: position.nft_mint.to_string(),
+            position: key.to_string(),
+            pool_id: position.pool_id.to_string(),
+            token_mint_0: pool_state.token_mint_0.to_string(),
+            token_mint_1: pool_state.token_mint_1.to_string(),
+            tick_lower_index: position.tick_lower_index,
+            tick_upper_index: position.tick_upper_index,
+            in_range: position.tick_lower_index <= pool_state.tick_current
+                && pool_state.tick_current < position.tick_upper_index,
+            liquidity: { position.liquidity }.to_string(),
+            amount_0: amount_0.to_string(),
+            amount_1: amount_1.to_string(),
+            fees_0: fees_0.to_string(),
+            fees_1: fees_1.to_string(),
+            rewards,
+        });
+    }
+
+    Ok(Portfolio {
+        owner: owner.to_string(),
+        positions: portfolio_positions,
+        totals: totals
+            .into_iter()
+            .map(
+                |(mint, (liquidity_amount, fees, rewards))| PortfolioMintTotal {
+                    mint: mint.to_string(),
+                    liquidity_amount: liquidity_amount.to_string(),
+                    fees: fees.to_string(),
+                    rewards: rewards.to_string(),
+                },
+            )
+            .collect(),
+    })
+}
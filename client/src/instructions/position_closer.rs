@@ -0,0 +1,378 @@
+use anchor_lang::prelude::AccountMeta;
+use anyhow::{format_err, Result};
+use raydium_amm_v3::libraries::liquidity_math;
+use raydium_amm_v3::states::{
+    PersonalPositionState, PoolState, PoolStatusBitIndex, TickArrayState,
+    POOL_TICK_ARRAY_BITMAP_SEED,
+};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    transaction::Transaction,
+};
+use spl_associated_token_account::{
+    get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, Write};
+use tracing::{info, warn};
+
+use super::super::{get_all_nft_and_position_by_owner, ClientConfig};
+use super::amm_instructions::{close_personal_position_instr, decrease_liquidity_instr};
+use super::rpc::{send_txn, simulate_transaction, with_compute_budget, RpcScanner};
+use super::telemetry::txn_span;
+use super::utils::{
+    amount_with_slippage, deserialize_anchor_account, get_pool_mints_transfer_fee,
+    reward_remaining_accounts,
+};
+
+/// A position of the owner in the pool and the minimums its liquidity is removed with
+#[derive(Debug, Clone)]
+pub struct PositionClosure {
+    pub nft_mint: Pubkey,
+    pub nft_account: Pubkey,
+    pub nft_token_program: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+    /// The amounts the liquidity is worth at the current price
+    pub amount_0: u64,
+    pub amount_1: u64,
+    /// The amounts after the slippage budget and the transfer fees
+    pub amount_0_min: u64,
+    pub amount_1_min: u64,
+    /// Fees and rewards owed as of the last update of the position, the accrual since is
+    /// collected as well
+    pub fees_owed_0: u64,
+    pub fees_owed_1: u64,
+    pub rewards_owed: [u64; 3],
+}
+
+/// The outcome of closing the positions, the proceeds are the balance changes of the owner's
+/// token accounts by mint
+#[derive(Debug, Clone, Default)]
+pub struct ClosureSummary {
+    pub closed: Vec<Pubkey>,
+    pub failed: Vec<(Pubkey, String)>,
+    pub proceeds: BTreeMap<Pubkey, u64>,
+}
+
+/// The positions of the owner in the pool with the minimums of removing all of their liquidity
+pub fn plan_position_closures(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    owner: &Pubkey,
+    pool_id: &Pubkey,
+    pool_state: &PoolState,
+    slippage: f64,
+) -> Result<Vec<PositionClosure>> {
+    let nfts: Vec<_> =
+        get_all_nft_and_position_by_owner(rpc_client, owner, &config.raydium_v3_program)
+            .into_iter()
+            .filter(|nft| nft.amount == 1)
+            .collect();
+    let positions: Vec<Pubkey> = nfts.iter().map(|nft| nft.position).collect();
+    let mut closures = Vec::new();
+    for (nft, account) in nfts
+        .iter()
+        .zip(RpcScanner::new(config.rpc_scan).get_multiple_accounts(rpc_client, &positions)?)
+    {
+        let account = match account {
+            Some(account) => account,
+            None => continue,
+        };
+        let position = deserialize_anchor_account::<PersonalPositionState>(&account)?;
+        if position.pool_id != *pool_id {
+            continue;
+        }
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            position.tick_lower_index,
+            position.tick_upper_index,
+            -(position.liquidity as i128),
+        )?;
+        let amount_0_with_slippage = amount_with_slippage(amount_0, slippage, false);
+        let amount_1_with_slippage = amount_with_slippage(amount_1, slippage, false);
+        let transfer_fee = get_pool_mints_transfer_fee(
+            rpc_client,
+            pool_state.token_mint_0,
+            pool_state.token_mint_1,
+            amount_0_with_slippage,
+            amount_1_with_slippage,
+        );
+        closures.push(PositionClosure {
+            nft_mint: nft.mint,
+            nft_account: nft.key,
+            nft_token_program: nft.program,
+            tick_lower_index: position.tick_lower_index,
+            tick_upper_index: position.tick_upper_index,
+            liquidity: position.liquidity,
+            amount_0,
+            amount_1,
+            amount_0_min: amount_0_with_slippage.saturating_sub(transfer_fee.0.transfer_fee),
+            amount_1_min: amount_1_with_slippage.saturating_sub(transfer_fee.1.transfer_fee),
+            fees_owed_0: position.token_fees_owed_0,
+            fees_owed_1: position.token_fees_owed_1,
+            rewards_owed: [
+                position.reward_infos[0].reward_amount_owed,
+                position.reward_infos[1].reward_amount_owed,
+                position.reward_infos[2].reward_amount_owed,
+            ],
+        });
+    }
+    closures.sort_by_key(|closure| (closure.tick_lower_index, closure.tick_upper_index));
+    Ok(closures)
+}
+
+/// The token program owning each mint
+pub fn mint_token_programs(
+    rpc_client: &RpcClient,
+    mints: &[Pubkey],
+) -> Result<HashMap<Pubkey, Pubkey>> {
+    let mut token_programs = HashMap::new();
+    for (mint, account) in mints.iter().zip(rpc_client.get_multiple_accounts(mints)?) {
+        let account = account.ok_or(format_err!("mint {} not found", mint))?;
+        token_programs.insert(*mint, account.owner);
+    }
+    Ok(token_programs)
+}
+
+/// Create the missing token accounts of the owner, remove all liquidity collecting the fees and
+/// rewards, then close the position
+pub fn close_position_instructions(
+    config: &ClientConfig,
+    owner: &Pubkey,
+    pool_id: &Pubkey,
+    pool_state: &PoolState,
+    token_programs: &HashMap<Pubkey, Pubkey>,
+    closure: &PositionClosure,
+) -> Result<Vec<Instruction>> {
+    let mut instructions = Vec::new();
+    // the fee rebate rewards are paid in the pool tokens, each account is created once
+    let mut mints = vec![pool_state.token_mint_0, pool_state.token_mint_1];
+    for reward_info in pool_state.reward_infos.iter() {
+        if reward_info.initialized() && !mints.contains(&reward_info.token_mint) {
+            mints.push(reward_info.token_mint);
+        }
+    }
+    for mint in mints.iter() {
+        instructions.push(create_associated_token_account_idempotent(
+            owner,
+            owner,
+            mint,
+            &token_programs[mint],
+        ));
+    }
+
+    let tickarray_bitmap_extension = Pubkey::find_program_address(
+        &[
+            POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+            pool_id.to_bytes().as_ref(),
+        ],
+        &config.raydium_v3_program,
+    )
+    .0;
+    let mut remaining_accounts = vec![AccountMeta::new(tickarray_bitmap_extension, false)];
+    remaining_accounts.extend(reward_remaining_accounts(
+        pool_state,
+        owner,
+        token_programs,
+    )?);
+    instructions.extend(decrease_liquidity_instr(
+        config,
+        *pool_id,
+        pool_state.token_vault_0,
+        pool_state.token_vault_1,
+        pool_state.token_mint_0,
+        pool_state.token_mint_1,
+        closure.nft_mint,
+        closure.nft_account,
+        get_associated_token_address_with_program_id(
+            owner,
+            &pool_state.token_mint_0,
+            &token_programs[&pool_state.token_mint_0],
+        ),
+        get_associated_token_address_with_program_id(
+            owner,
+            &pool_state.token_mint_1,
+            &token_programs[&pool_state.token_mint_1],
+        ),
+        remaining_accounts,
+        closure.liquidity,
+        closure.amount_0_min,
+        closure.amount_1_min,
+        closure.tick_lower_index,
+        closure.tick_upper_index,
+        TickArrayState::get_array_start_index(closure.tick_lower_index, pool_state.tick_spacing),
+        TickArrayState::get_array_start_index(closure.tick_upper_index, pool_state.tick_spacing),
+    )?);
+    instructions.extend(close_personal_position_instr(
+        config,
+        closure.nft_mint,
+        closure.nft_account,
+        closure.nft_token_program,
+    )?);
+    Ok(instructions)
+}
+
+/// Token balance of the account, 0 if it doesn't exist yet
+fn token_balance(rpc_client: &RpcClient, token_account: &Pubkey) -> u64 {
+    rpc_client
+        .get_token_account_balance(token_account)
+        .ok()
+        .and_then(|balance| balance.amount.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Close every position of the payer in the pool, one transaction each. A position whose
+/// simulation fails is reported and skipped, the others are still closed.
+pub fn close_all_positions(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    pool_id: &Pubkey,
+    slippage: f64,
+    assume_yes: bool,
+    simulate: bool,
+) -> Result<ClosureSummary> {
+    let owner = payer.pubkey();
+    let pool_state = deserialize_anchor_account::<PoolState>(&rpc_client.get_account(pool_id)?)?;
+    if !pool_state.get_status_by_bit(PoolStatusBitIndex::DecreaseLiquidity) {
+        return Err(format_err!(
+            "decreasing liquidity is disabled in {}",
+            pool_id
+        ));
+    }
+    let closures =
+        plan_position_closures(rpc_client, config, &owner, pool_id, &pool_state, slippage)?;
+    let mut summary = ClosureSummary::default();
+    if closures.is_empty() {
+        info!("no position of {} in {}", owner, pool_id);
+        return Ok(summary);
+    }
+
+    let mut mints = vec![pool_state.token_mint_0, pool_state.token_mint_1];
+    for (i, reward_info) in pool_state.reward_infos.iter().enumerate() {
+        if !reward_info.initialized() {
+            continue;
+        }
+        if reward_info.consolidated() {
+            info!(
+                "reward {} is consolidated, its owed amounts are claimed with claim-merkle-reward",
+                i
+            );
+        }
+        if !mints.contains(&reward_info.token_mint) {
+            mints.push(reward_info.token_mint);
+        }
+    }
+    let token_programs = mint_token_programs(rpc_client, &mints)?;
+
+    for closure in closures.iter() {
+        info!(
+            "position:{}, ticks:[{}, {}], liquidity:{}, amount_0:{} (min {}), amount_1:{} (min {}), fees_owed:[{}, {}], rewards_owed:{:?}",
+            closure.nft_mint,
+            closure.tick_lower_index,
+            closure.tick_upper_index,
+            closure.liquidity,
+            closure.amount_0,
+            closure.amount_0_min,
+            closure.amount_1,
+            closure.amount_1_min,
+            closure.fees_owed_0,
+            closure.fees_owed_1,
+            closure.rewards_owed
+        );
+    }
+    if !assume_yes {
+        print!(
+            "close {} positions with a slippage of {}%? type yes to confirm: ",
+            closures.len(),
+            slippage * 100.0
+        );
+        io::stdout().flush()?;
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        if !matches!(line.trim().to_lowercase().as_str(), "y" | "yes") {
+            info!("aborted");
+            return Ok(summary);
+        }
+    }
+
+    let token_accounts: Vec<(Pubkey, Pubkey)> = mints
+        .iter()
+        .map(|mint| {
+            (
+                *mint,
+                get_associated_token_address_with_program_id(&owner, mint, &token_programs[mint]),
+            )
+        })
+        .collect();
+    let balances_before: Vec<u64> = token_accounts
+        .iter()
+        .map(|(_, token_account)| token_balance(rpc_client, token_account))
+        .collect();
+    for closure in closures.iter() {
+        let span = txn_span(&closure.nft_mint.to_string());
+        let _enter = span.enter();
+        let instructions = with_compute_budget(
+            rpc_client,
+            &config.compute_budget,
+            &owner,
+            &close_position_instructions(
+                config,
+                &owner,
+                pool_id,
+                &pool_state,
+                &token_programs,
+                closure,
+            )?,
+        )?;
+        let txn = Transaction::new_signed_with_payer(
+            &instructions,
+            Some(&owner),
+            &[payer],
+            rpc_client.get_latest_blockhash()?,
+        );
+        let simulation =
+            simulate_transaction(rpc_client, &txn, true, CommitmentConfig::confirmed())?.value;
+        if let Some(err) = simulation.err {
+            let reason = format!(
+                "simulation failed: {}, logs: {}",
+                err,
+                simulation.logs.unwrap_or_default().join(" | ")
+            );
+            warn!("{}: {}", closure.nft_mint, reason);
+            summary.failed.push((closure.nft_mint, reason));
+            continue;
+        }
+        if simulate {
+            info!(
+                "{} simulated, logs: {}",
+                closure.nft_mint,
+                simulation.logs.unwrap_or_default().join(" | ")
+            );
+            continue;
+        }
+        match send_txn(rpc_client, &txn, true) {
+            Ok(signature) => {
+                info!("{} closed: {}", closure.nft_mint, signature);
+                summary.closed.push(closure.nft_mint);
+            }
+            Err(err) => {
+                warn!("{}: {}", closure.nft_mint, err);
+                summary.failed.push((closure.nft_mint, err.to_string()));
+            }
+        }
+    }
+    for ((mint, token_account), before) in token_accounts.iter().zip(balances_before) {
+        let after = token_balance(rpc_client, token_account);
+        summary.proceeds.insert(*mint, after.saturating_sub(before));
+    }
+    Ok(summary)
+}
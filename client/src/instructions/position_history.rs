@@ -0,0 +1,607 @@
+use anchor_lang::Discriminator;
+use anyhow::Result;
+use raydium_amm_v3::states::{
+    ClaimMerkleRewardEvent, CreatePersonalPositionEvent, DecreaseLiquidityEvent,
+    IncreaseLiquidityEvent, LiquidityChangeEvent, PersonalPositionState, PoolState, POSITION_SEED,
+    REWARD_NUM,
+};
+use solana_client::{
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::RpcTransactionConfig,
+};
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    option_serializer::OptionSerializer, EncodedTransaction, UiMessage, UiTransactionEncoding,
+};
+use spl_token_2022::{extension::StateWithExtensions, state::Mint};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use super::events_instructions_parse::{decode_event, handle_system_log, Execution};
+use super::utils::{deserialize_anchor_account, format_unix_timestamp};
+
+const PROGRAM_DATA: &str = "Program data: ";
+/// Signatures per getSignaturesForAddress request
+const SIGNATURES_PAGE_LIMIT: usize = 1000;
+
+pub const POSITION_HISTORY_CSV_HEADER: &str = "timestamp,date,signature,slot,event,direction,position_nft_mint,pool,token_mint,amount,raw_amount,transfer_fee,liquidity,tick_lower,tick_upper";
+
+/// A lifecycle event of a position decoded from the logs of a transaction. The pool is taken from
+/// the `LiquidityChangeEvent` the program emits before the increase and decrease events, it is
+/// none when the program didn't emit one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PositionEvent {
+    Open {
+        pool: Pubkey,
+        position_nft_mint: Option<Pubkey>,
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+        amount_0: u64,
+        amount_1: u64,
+        transfer_fee_0: u64,
+        transfer_fee_1: u64,
+    },
+    Increase {
+        pool: Option<Pubkey>,
+        position_nft_mint: Pubkey,
+        liquidity: u128,
+        amount_0: u64,
+        amount_1: u64,
+        transfer_fee_0: u64,
+        transfer_fee_1: u64,
+    },
+    /// A decrease also collects the fees and rewards of the position, a collect is a decrease of
+    /// zero liquidity
+    Decrease {
+        pool: Option<Pubkey>,
+        position_nft_mint: Pubkey,
+        liquidity: u128,
+        amount_0: u64,
+        amount_1: u64,
+        fee_amount_0: u64,
+        fee_amount_1: u64,
+        reward_amounts: [u64; REWARD_NUM],
+        transfer_fee_0: u64,
+        transfer_fee_1: u64,
+    },
+    ClaimMerkleReward {
+        pool: Pubkey,
+        reward_index: u8,
+        amount: u64,
+    },
+}
+
+/// The position NFT mints among the accounts of a transaction, found by their personal position
+/// PDA being one of the accounts too
+pub fn find_position_nft_mints(program_id: &Pubkey, account_keys: &[Pubkey]) -> Vec<Pubkey> {
+    account_keys
+        .iter()
+        .filter(|key| {
+            let personal_position =
+                Pubkey::find_program_address(&[POSITION_SEED.as_bytes(), key.as_ref()], program_id)
+                    .0;
+            account_keys.contains(&personal_position)
+        })
+        .copied()
+        .collect()
+}
+
+/// Decode the position events the program emitted in the logs of a transaction, in order
+pub fn decode_position_events(
+    program_id: &Pubkey,
+    logs: &[String],
+    account_keys: &[Pubkey],
+) -> Vec<PositionEvent> {
+    let program_str = program_id.to_string();
+    let position_nft_mints = find_position_nft_mints(program_id, account_keys);
+    let mut events = Vec::new();
+    let mut logs = logs;
+    if logs.is_empty() {
+        return events;
+    }
+    let mut execution = match Execution::new(&mut logs) {
+        Ok(execution) => execution,
+        Err(_) => return events,
+    };
+    let mut last_pool = None;
+    let mut opened = 0;
+    for log in logs {
+        if !execution.is_empty() && program_str == execution.program() {
+            if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+                let bytes = match anchor_lang::__private::base64::decode(data) {
+                    Ok(bytes) if bytes.len() >= 8 => bytes,
+                    _ => continue,
+                };
+                let mut slice: &[u8] = &bytes[8..];
+                match <[u8; 8]>::try_from(&bytes[..8]).unwrap() {
+                    LiquidityChangeEvent::DISCRIMINATOR => {
+                        if let Ok(event) = decode_event::<LiquidityChangeEvent>(&mut slice) {
+                            last_pool = Some(event.pool_state);
+                        }
+                    }
+                    CreatePersonalPositionEvent::DISCRIMINATOR => {
+                        if let Ok(event) = decode_event::<CreatePersonalPositionEvent>(&mut slice) {
+                            events.push(PositionEvent::Open {
+                                pool: event.pool_state,
+                                // the mints are matched to the opens in order
+                                position_nft_mint: position_nft_mints.get(opened).copied(),
+                                tick_lower: event.tick_lower_index,
+                                tick_upper: event.tick_upper_index,
+                                liquidity: event.liquidity,
+                                amount_0: event.deposit_amount_0,
+                                amount_1: event.deposit_amount_1,
+                                transfer_fee_0: event.deposit_amount_0_transfer_fee,
+                                transfer_fee_1: event.deposit_amount_1_transfer_fee,
+                            });
+                            opened += 1;
+                        }
+                    }
+                    IncreaseLiquidityEvent::DISCRIMINATOR => {
+                        if let Ok(event) = decode_event::<IncreaseLiquidityEvent>(&mut slice) {
+                            events.push(PositionEvent::Increase {
+                                pool: last_pool.take(),
+                                position_nft_mint: event.position_nft_mint,
+                                liquidity: event.liquidity,
+                                amount_0: event.amount_0,
+                                amount_1: event.amount_1,
+                                transfer_fee_0: event.amount_0_transfer_fee,
+                                transfer_fee_1: event.amount_1_transfer_fee,
+                            });
+                        }
+                    }
+                    DecreaseLiquidityEvent::DISCRIMINATOR => {
+                        if let Ok(event) = decode_event::<DecreaseLiquidityEvent>(&mut slice) {
+                            events.push(PositionEvent::Decrease {
+                                pool: last_pool.take(),
+                                position_nft_mint: event.position_nft_mint,
+                                liquidity: event.liquidity,
+                                amount_0: event.decrease_amount_0,
+                                amount_1: event.decrease_amount_1,
+                                fee_amount_0: event.fee_amount_0,
+                                fee_amount_1: event.fee_amount_1,
+                                reward_amounts: event.reward_amounts,
+                                transfer_fee_0: event.transfer_fee_0,
+                                transfer_fee_1: event.transfer_fee_1,
+                            });
+                        }
+                    }
+                    ClaimMerkleRewardEvent::DISCRIMINATOR => {
+                        if let Ok(event) = decode_event::<ClaimMerkleRewardEvent>(&mut slice) {
+                            events.push(PositionEvent::ClaimMerkleReward {
+                                pool: event.pool_state,
+                                reward_index: event.reward_index,
+                                amount: event.amount,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+        }
+        let (new_program, did_pop) = handle_system_log(&program_str, log);
+        if let Some(new_program) = new_program {
+            execution.push(new_program);
+        }
+        if did_pop {
+            execution.pop();
+        }
+    }
+    events
+}
+
+/// One token movement of a position, a row of the exported csv
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PositionHistoryRow {
+    pub block_time: u64,
+    pub signature: String,
+    pub slot: u64,
+    /// open, increase, decrease, collect_fee, collect_reward or claim_reward
+    pub event: &'static str,
+    /// deposit to the pool, withdraw of the principal or income of fees and rewards
+    pub direction: &'static str,
+    pub position_nft_mint: Option<Pubkey>,
+    pub pool: Option<Pubkey>,
+    pub token_mint: Option<Pubkey>,
+    pub decimals: Option<u8>,
+    pub raw_amount: u64,
+    pub transfer_fee: u64,
+    pub liquidity: u128,
+    pub tick_lower: Option<i32>,
+    pub tick_upper: Option<i32>,
+}
+
+impl PositionHistoryRow {
+    /// The amount in ui units, the raw amount if the decimals are unknown
+    pub fn amount(&self) -> String {
+        match self.decimals {
+            Some(decimals) if decimals > 0 => {
+                let raw = format!(
+                    "{:0>width$}",
+                    self.raw_amount,
+                    width = decimals as usize + 1
+                );
+                let (int, frac) = raw.split_at(raw.len() - decimals as usize);
+                format!("{}.{}", int, frac)
+            }
+            _ => self.raw_amount.to_string(),
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let optional = |key: Option<Pubkey>| key.map_or(String::new(), |key| key.to_string());
+        let tick = |tick: Option<i32>| tick.map_or(String::new(), |tick| tick.to_string());
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}",
+            self.block_time,
+            format_unix_timestamp(self.block_time),
+            self.signature,
+            self.slot,
+            self.event,
+            self.direction,
+            optional(self.position_nft_mint),
+            optional(self.pool),
+            optional(self.token_mint),
+            self.amount(),
+            self.raw_amount,
+            self.transfer_fee,
+            self.liquidity,
+            tick(self.tick_lower),
+            tick(self.tick_upper)
+        )
+    }
+}
+
+/// Resolves the pools of positions and the mints and decimals of pools, caching the accounts
+pub struct PoolResolver<'a> {
+    rpc_client: &'a RpcClient,
+    program_id: Pubkey,
+    pools: HashMap<Pubkey, Option<PoolState>>,
+    position_pools: HashMap<Pubkey, Option<Pubkey>>,
+    decimals: HashMap<Pubkey, Option<u8>>,
+}
+
+impl<'a> PoolResolver<'a> {
+    pub fn new(rpc_client: &'a RpcClient, program_id: Pubkey) -> Self {
+        Self {
+            rpc_client,
+            program_id,
+            pools: HashMap::new(),
+            position_pools: HashMap::new(),
+            decimals: HashMap::new(),
+        }
+    }
+
+    pub fn pool(&mut self, pool_id: &Pubkey) -> Option<PoolState> {
+        let rpc_client = self.rpc_client;
+        *self.pools.entry(*pool_id).or_insert_with(|| {
+            rpc_client
+                .get_account(pool_id)
+                .ok()
+                .and_then(|account| deserialize_anchor_account::<PoolState>(&account).ok())
+        })
+    }
+
+    /// The pool of a position, learned from its earlier events or read from the personal
+    /// position, none if the position was closed before it was seen
+    pub fn position_pool(
+        &mut self,
+        position_nft_mint: &Pubkey,
+        pool: Option<Pubkey>,
+    ) -> Option<Pubkey> {
+        if let Some(pool) = pool {
+            self.position_pools.insert(*position_nft_mint, Some(pool));
+            return Some(pool);
+        }
+        let rpc_client = self.rpc_client;
+        let program_id = self.program_id;
+        *self
+            .position_pools
+            .entry(*position_nft_mint)
+            .or_insert_with(|| {
+                let personal_position = Pubkey::find_program_address(
+                    &[POSITION_SEED.as_bytes(), position_nft_mint.as_ref()],
+                    &program_id,
+                )
+                .0;
+                rpc_client
+                    .get_account(&personal_position)
+                    .ok()
+                    .and_then(|account| {
+                        deserialize_anchor_account::<PersonalPositionState>(&account).ok()
+                    })
+                    .map(|position| position.pool_id)
+            })
+    }
+
+    pub fn decimals(&mut self, mint: &Pubkey) -> Option<u8> {
+        let rpc_client = self.rpc_client;
+        *self.decimals.entry(*mint).or_insert_with(|| {
+            rpc_client.get_account(mint).ok().and_then(|account| {
+                StateWithExtensions::<Mint>::unpack(&account.data)
+                    .ok()
+                    .map(|mint| mint.base.decimals)
+            })
+        })
+    }
+}
+
+/// Turn the events of a transaction into one row per token moved, zero amounts are skipped
+pub fn position_history_rows(
+    resolver: &mut PoolResolver,
+    signature: &str,
+    slot: u64,
+    block_time: u64,
+    events: &[PositionEvent],
+) -> Vec<PositionHistoryRow> {
+    let mut rows = Vec::new();
+    for event in events {
+        let (position_nft_mint, pool, liquidity, ticks) = match event {
+            PositionEvent::Open {
+                pool,
+                position_nft_mint,
+                liquidity,
+                tick_lower,
+                tick_upper,
+                ..
+            } => {
+                if let Some(position_nft_mint) = position_nft_mint {
+                    resolver.position_pool(position_nft_mint, Some(*pool));
+                }
+                (
+                    *position_nft_mint,
+                    Some(*pool),
+                    *liquidity,
+                    (Some(*tick_lower), Some(*tick_upper)),
+                )
+            }
+            PositionEvent::Increase {
+                pool,
+                position_nft_mint,
+                liquidity,
+                ..
+            }
+            | PositionEvent::Decrease {
+                pool,
+                position_nft_mint,
+                liquidity,
+                ..
+            } => (
+                Some(*position_nft_mint),
+                resolver.position_pool(position_nft_mint, *pool),
+                *liquidity,
+                (None, None),
+            ),
+            PositionEvent::ClaimMerkleReward { pool, .. } => (None, Some(*pool), 0, (None, None)),
+        };
+        let pool_state = pool.and_then(|pool| resolver.pool(&pool));
+        let mut push = |resolver: &mut PoolResolver,
+                        event: &'static str,
+                        direction: &'static str,
+                        token_mint: Option<Pubkey>,
+                        raw_amount: u64,
+                        transfer_fee: u64| {
+            if raw_amount == 0 {
+                return;
+            }
+            rows.push(PositionHistoryRow {
+                block_time,
+                signature: signature.to_string(),
+                slot,
+                event,
+                direction,
+                position_nft_mint,
+                pool,
+                token_mint,
+                decimals: token_mint.and_then(|mint| resolver.decimals(&mint)),
+                raw_amount,
+                transfer_fee,
+                liquidity,
+                tick_lower: ticks.0,
+                tick_upper: ticks.1,
+            });
+        };
+        let mint_0 = pool_state.map(|pool| pool.token_mint_0);
+        let mint_1 = pool_state.map(|pool| pool.token_mint_1);
+        match event {
+            PositionEvent::Open {
+                amount_0,
+                amount_1,
+                transfer_fee_0,
+                transfer_fee_1,
+                ..
+            } => {
+                push(
+                    resolver,
+                    "open",
+                    "deposit",
+                    mint_0,
+                    *amount_0,
+                    *transfer_fee_0,
+                );
+                push(
+                    resolver,
+                    "open",
+                    "deposit",
+                    mint_1,
+                    *amount_1,
+                    *transfer_fee_1,
+                );
+            }
+            PositionEvent::Increase {
+                amount_0,
+                amount_1,
+                transfer_fee_0,
+                transfer_fee_1,
+                ..
+            } => {
+                push(
+                    resolver,
+                    "increase",
+                    "deposit",
+                    mint_0,
+                    *amount_0,
+                    *transfer_fee_0,
+                );
+                push(
+                    resolver,
+                    "increase",
+                    "deposit",
+                    mint_1,
+                    *amount_1,
+                    *transfer_fee_1,
+                );
+            }
+            PositionEvent::Decrease {
+                amount_0,
+                amount_1,
+                fee_amount_0,
+                fee_amount_1,
+                reward_amounts,
+                transfer_fee_0,
+                transfer_fee_1,
+                ..
+            } => {
+                push(
+                    resolver,
+                    "decrease",
+                    "withdraw",
+                    mint_0,
+                    *amount_0,
+                    *transfer_fee_0,
+                );
+                push(
+                    resolver,
+                    "decrease",
+                    "withdraw",
+                    mint_1,
+                    *amount_1,
+                    *transfer_fee_1,
+                );
+                push(resolver, "collect_fee", "income", mint_0, *fee_amount_0, 0);
+                push(resolver, "collect_fee", "income", mint_1, *fee_amount_1, 0);
+                for (index, reward_amount) in reward_amounts.iter().enumerate() {
+                    let reward_mint = pool_state
+                        .map(|pool| pool.reward_infos[index].token_mint)
+                        .filter(|mint| *mint != Pubkey::default());
+                    push(
+                        resolver,
+                        "collect_reward",
+                        "income",
+                        reward_mint,
+                        *reward_amount,
+                        0,
+                    );
+                }
+            }
+            PositionEvent::ClaimMerkleReward {
+                reward_index,
+                amount,
+                ..
+            } => {
+                let reward_mint = pool_state
+                    .and_then(|pool| pool.reward_infos.get(*reward_index as usize).copied())
+                    .map(|reward_info| reward_info.token_mint);
+                push(resolver, "claim_reward", "income", reward_mint, *amount, 0);
+            }
+        }
+    }
+    rows
+}
+
+/// The successful transactions of `owner` from `from_time` on, oldest first, with their slot and
+/// block time
+pub fn owner_signatures(
+    rpc_client: &RpcClient,
+    owner: &Pubkey,
+    from_time: u64,
+) -> Result<Vec<(Signature, u64, u64)>> {
+    let mut signatures = Vec::new();
+    let mut before = None;
+    'pages: loop {
+        let page = rpc_client.get_signatures_for_address_with_config(
+            owner,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(SIGNATURES_PAGE_LIMIT),
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )?;
+        let page_len = page.len();
+        for status in page {
+            let signature = Signature::from_str(&status.signature)?;
+            before = Some(signature);
+            let block_time = status.block_time.unwrap_or_default().max(0) as u64;
+            if block_time < from_time {
+                break 'pages;
+            }
+            if status.err.is_none() {
+                signatures.push((signature, status.slot, block_time));
+            }
+        }
+        if page_len < SIGNATURES_PAGE_LIMIT {
+            break;
+        }
+    }
+    signatures.reverse();
+    Ok(signatures)
+}
+
+/// Reconstruct the position history of `owner` from its transactions since `from_time`
+pub fn export_position_history(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    from_time: u64,
+) -> Result<Vec<PositionHistoryRow>> {
+    let mut resolver = PoolResolver::new(rpc_client, *program_id);
+    let mut rows = Vec::new();
+    for (signature, slot, block_time) in owner_signatures(rpc_client, owner, from_time)? {
+        let tx = rpc_client.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Json),
+                commitment: Some(CommitmentConfig::confirmed()),
+                max_supported_transaction_version: Some(0),
+            },
+        )?;
+        let meta = match tx.transaction.meta {
+            Some(meta) => meta,
+            None => continue,
+        };
+        let logs = match meta.log_messages {
+            OptionSerializer::Some(logs) => logs,
+            _ => continue,
+        };
+        let mut account_keys = match tx.transaction.transaction {
+            EncodedTransaction::Json(ui_tx) => match ui_tx.message {
+                UiMessage::Raw(message) => message.account_keys,
+                UiMessage::Parsed(message) => message
+                    .account_keys
+                    .into_iter()
+                    .map(|account| account.pubkey)
+                    .collect(),
+            },
+            _ => Vec::new(),
+        };
+        if let OptionSerializer::Some(addresses) = meta.loaded_addresses {
+            account_keys.extend(addresses.writable);
+            account_keys.extend(addresses.readonly);
+        }
+        let account_keys: Vec<Pubkey> = account_keys
+            .iter()
+            .filter_map(|key| Pubkey::from_str(key).ok())
+            .collect();
+        let events = decode_position_events(program_id, &logs, &account_keys);
+        rows.extend(position_history_rows(
+            &mut resolver,
+            &signature.to_string(),
+            slot,
+            block_time,
+            &events,
+        ));
+    }
+    Ok(rows)
+}
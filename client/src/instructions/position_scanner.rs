@@ -0,0 +1,169 @@
+use anchor_lang::Discriminator;
+use anyhow::Result;
+use raydium_amm_v3::states::{PersonalPositionState, REWARD_NUM};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    rpc_client::RpcClient,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig},
+    rpc_filter::{Memcmp, RpcFilterType},
+};
+use solana_sdk::{account::Account, pubkey::Pubkey};
+
+use super::rpc::RpcScanner;
+use super::utils::deserialize_anchor_account;
+
+/// Position account layouts known by the client.
+/// A layout is identified by the account discriminator and the version byte, layouts without a
+/// version byte are version 0 and identified by the data size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PositionLayout {
+    /// `PersonalPositionState` owned through a position nft
+    PersonalV0,
+}
+
+impl PositionLayout {
+    pub fn all() -> Vec<PositionLayout> {
+        vec![PositionLayout::PersonalV0]
+    }
+
+    pub fn discriminator(&self) -> [u8; 8] {
+        match self {
+            PositionLayout::PersonalV0 => PersonalPositionState::discriminator(),
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        match self {
+            PositionLayout::PersonalV0 => 0,
+        }
+    }
+
+    pub fn data_len(&self) -> usize {
+        match self {
+            PositionLayout::PersonalV0 => PersonalPositionState::LEN,
+        }
+    }
+
+    /// Offset of the pool id, used to filter positions by pool
+    pub fn pool_id_offset(&self) -> usize {
+        match self {
+            PositionLayout::PersonalV0 => 8 + 1 + 32,
+        }
+    }
+
+    /// Detect the layout of the account data
+    pub fn detect(data: &[u8]) -> Option<PositionLayout> {
+        if data.len() < 8 {
+            return None;
+        }
+        PositionLayout::all()
+            .into_iter()
+            .find(|layout| data[..8] == layout.discriminator() && data.len() == layout.data_len())
+    }
+}
+
+/// Position listing shared by all layouts
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnifiedPosition {
+    pub key: Pubkey,
+    pub layout: PositionLayout,
+    /// None if the position is not owned through a nft
+    pub nft_mint: Option<Pubkey>,
+    pub pool_id: Pubkey,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub liquidity: u128,
+    pub token_fees_owed_0: u64,
+    pub token_fees_owed_1: u64,
+    pub reward_amounts_owed: [u64; REWARD_NUM],
+}
+
+/// Decode a position of any known layout, return None if the layout is unknown
+pub fn decode_position(key: Pubkey, account: &Account) -> Result<Option<UnifiedPosition>> {
+    let layout = match PositionLayout::detect(&account.data) {
+        Some(layout) => layout,
+        None => return Ok(None),
+    };
+    let position = match layout {
+        PositionLayout::PersonalV0 => {
+            let position = deserialize_anchor_account::<PersonalPositionState>(account)?;
+            let mut reward_amounts_owed = [0u64; REWARD_NUM];
+            for (i, reward_info) in position.reward_infos.iter().enumerate() {
+                reward_amounts_owed[i] = reward_info.reward_amount_owed;
+            }
+            UnifiedPosition {
+                key,
+                layout,
+                nft_mint: Some(position.nft_mint),
+                pool_id: position.pool_id,
+                tick_lower_index: position.tick_lower_index,
+                tick_upper_index: position.tick_upper_index,
+                liquidity: position.liquidity,
+                token_fees_owed_0: position.token_fees_owed_0,
+                token_fees_owed_1: position.token_fees_owed_1,
+                reward_amounts_owed,
+            }
+        }
+    };
+    Ok(Some(position))
+}
+
+/// Load positions of any known layout by address, missing or unknown accounts are skipped
+pub fn load_positions(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    keys: &[Pubkey],
+) -> Result<Vec<UnifiedPosition>> {
+    let rsps = scanner.get_multiple_accounts(rpc_client, keys)?;
+    let mut positions = Vec::new();
+    for (key, rsp) in keys.iter().zip(rsps) {
+        if let Some(account) = rsp {
+            if let Some(position) = decode_position(*key, &account)? {
+                positions.push(position);
+            }
+        }
+    }
+    Ok(positions)
+}
+
+/// Scan the positions of the pool for every known layout
+pub fn scan_positions_by_pool(
+    rpc_client: &RpcClient,
+    scanner: &RpcScanner,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+) -> Result<Vec<UnifiedPosition>> {
+    let mut positions = Vec::new();
+    for layout in PositionLayout::all() {
+        let accounts = scanner.get_program_accounts_with_config(
+            rpc_client,
+            program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(vec![
+                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(
+                        0,
+                        layout.discriminator().to_vec(),
+                    )),
+                    RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                        layout.pool_id_offset(),
+                        &pool_id.to_bytes(),
+                    )),
+                    RpcFilterType::DataSize(layout.data_len() as u64),
+                ]),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    ..RpcAccountInfoConfig::default()
+                },
+                with_context: Some(false),
+            },
+        )?;
+        for (key, account) in accounts {
+            if let Some(position) = decode_position(key, &account)? {
+                if position.pool_id == *pool_id {
+                    positions.push(position);
+                }
+            }
+        }
+    }
+    Ok(positions)
+}
@@ -0,0 +1,345 @@
+use anyhow::{format_err, Result};
+use raydium_amm_v3::instructions::{swap_internal, PositionAmounts};
+use raydium_amm_v3::libraries::tick_math;
+use raydium_amm_v3::states::{
+    AmmConfig, ObservationState, PersonalPositionState, PoolState, TickArrayBitmapExtension,
+    TickArrayState, TickState,
+};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+
+use super::pool_snapshot::PoolLiquiditySnapshot;
+use super::utils::sqrt_price_x64_to_price;
+
+fn default_base_in() -> bool {
+    true
+}
+
+/// One swap of the replay, read from the swaps file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaySwap {
+    /// The input amount of an exact input swap, the output amount of an exact output one
+    pub amount: u64,
+    pub zero_for_one: bool,
+    #[serde(default = "default_base_in")]
+    pub base_in: bool,
+    /// Seconds passed since the previous swap, accrues the rewards and observations
+    #[serde(default)]
+    pub seconds: u32,
+}
+
+/// The outcome of one replayed swap, the amounts are 0 and the pool unchanged if it failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplaySwapResult {
+    pub swap: ReplaySwap,
+    pub amount_0: u64,
+    pub amount_1: u64,
+    pub tick_after: i32,
+    pub error: Option<String>,
+}
+
+/// The fees a position earned over the replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReplayPositionFees {
+    pub personal_position: String,
+    pub tick_lower_index: i32,
+    pub tick_upper_index: i32,
+    pub fees_0: u64,
+    pub fees_1: u64,
+}
+
+/// A pool rebuilt in memory from a liquidity snapshot, swapped with the swap logic of the
+/// program
+pub struct ReplayPool {
+    pub amm_config: AmmConfig,
+    pub pool_state: PoolState,
+    pub observation_state: ObservationState,
+    pub tickarray_bitmap_extension: Option<TickArrayBitmapExtension>,
+    /// By start index
+    pub tick_arrays: BTreeMap<i32, TickArrayState>,
+    pub positions: Vec<(Pubkey, PersonalPositionState)>,
+    /// The block timestamp of the next swap
+    pub block_timestamp: u32,
+}
+
+impl ReplayPool {
+    pub fn new(
+        amm_config: AmmConfig,
+        pool_state: PoolState,
+        observation_state: ObservationState,
+        tickarray_bitmap_extension: Option<TickArrayBitmapExtension>,
+        tick_arrays: Vec<TickArrayState>,
+        positions: Vec<(Pubkey, PersonalPositionState)>,
+    ) -> Self {
+        // the replay starts after the last update of the rewards and the observations
+        let observation_index = observation_state.observation_index;
+        let mut block_timestamp =
+            observation_state.observations[observation_index as usize].block_timestamp;
+        for reward_info in pool_state.reward_infos.iter() {
+            if reward_info.initialized() {
+                let last_update_time = reward_info.last_update_time;
+                block_timestamp = block_timestamp.max(last_update_time as u32);
+            }
+        }
+        Self {
+            amm_config,
+            pool_state,
+            observation_state,
+            tickarray_bitmap_extension,
+            tick_arrays: tick_arrays
+                .into_iter()
+                .map(|tick_array| (tick_array.start_tick_index, tick_array))
+                .collect(),
+            positions,
+            block_timestamp,
+        }
+    }
+
+    pub fn from_snapshot(snapshot: &PoolLiquiditySnapshot) -> Result<Self> {
+        let single = |kind: &str| {
+            snapshot
+                .account(kind)
+                .ok_or(format_err!("the snapshot has no {} account", kind))
+        };
+        let tickarray_bitmap_extension = match snapshot.account("tick_array_bitmap_extension") {
+            Some(account) => Some(account.decode::<TickArrayBitmapExtension>()?),
+            None => None,
+        };
+        let mut positions = Vec::new();
+        for account in snapshot
+            .accounts
+            .iter()
+            .filter(|account| account.kind == "personal_position")
+        {
+            positions.push((
+                Pubkey::from_str(&account.pubkey)?,
+                account.decode::<PersonalPositionState>()?,
+            ));
+        }
+        Ok(Self::new(
+            single("amm_config")?.decode::<AmmConfig>()?,
+            single("pool")?.decode::<PoolState>()?,
+            single("observation")?.decode::<ObservationState>()?,
+            tickarray_bitmap_extension,
+            snapshot.decode_accounts::<TickArrayState>("tick_array")?,
+            positions,
+        ))
+    }
+
+    /// The loaded tick arrays in the swap direction, starting at the first initialized one
+    fn swap_tick_arrays(&self, zero_for_one: bool) -> Result<Vec<TickArrayState>> {
+        let (_, mut start_index) = self
+            .pool_state
+            .get_first_initialized_tick_array(&self.tickarray_bitmap_extension, zero_for_one)?;
+        let mut tick_arrays = Vec::new();
+        loop {
+            tick_arrays.push(*self.tick_arrays.get(&start_index).ok_or(format_err!(
+                "tick array {} not in the snapshot",
+                start_index
+            ))?);
+            match self.pool_state.next_initialized_tick_array_start_index(
+                &self.tickarray_bitmap_extension,
+                start_index,
+                zero_for_one,
+            )? {
+                Some(next_start_index) => start_index = next_start_index,
+                None => break,
+            }
+        }
+        Ok(tick_arrays)
+    }
+
+    fn try_swap(&mut self, swap: &ReplaySwap) -> Result<(u64, u64)> {
+        let pool_state = RefCell::new(self.pool_state);
+        let observation_state = RefCell::new(self.observation_state);
+        let tick_arrays: Vec<RefCell<TickArrayState>> = self
+            .swap_tick_arrays(swap.zero_for_one)?
+            .into_iter()
+            .map(RefCell::new)
+            .collect();
+        let sqrt_price_limit_x64 = if swap.zero_for_one {
+            tick_math::MIN_SQRT_PRICE_X64 + 1
+        } else {
+            tick_math::MAX_SQRT_PRICE_X64 - 1
+        };
+        let amounts = swap_internal(
+            &self.amm_config,
+            &mut pool_state.borrow_mut(),
+            &mut tick_arrays
+                .iter()
+                .map(|tick_array| tick_array.borrow_mut())
+                .collect::<VecDeque<_>>(),
+            &mut observation_state.borrow_mut(),
+            &self.tickarray_bitmap_extension,
+            swap.amount,
+            sqrt_price_limit_x64,
+            swap.zero_for_one,
+            swap.base_in,
+            self.block_timestamp,
+        )?;
+
+        // only a successful swap is kept, the crossed ticks are written back
+        self.pool_state = pool_state.into_inner();
+        self.observation_state = observation_state.into_inner();
+        for tick_array in tick_arrays {
+            let tick_array = tick_array.into_inner();
+            self.tick_arrays
+                .insert(tick_array.start_tick_index, tick_array);
+        }
+        Ok(amounts)
+    }
+
+    pub fn swap(&mut self, swap: &ReplaySwap) -> ReplaySwapResult {
+        self.block_timestamp = self.block_timestamp.saturating_add(swap.seconds);
+        let (amounts, error) = match self.try_swap(swap) {
+            Ok(amounts) => (amounts, None),
+            Err(err) => ((0, 0), Some(err.to_string())),
+        };
+        ReplaySwapResult {
+            swap: *swap,
+            amount_0: amounts.0,
+            amount_1: amounts.1,
+            tick_after: self.pool_state.tick_current,
+            error,
+        }
+    }
+
+    fn tick_state(&self, tick_index: i32) -> Result<TickState> {
+        let start_index =
+            TickArrayState::get_array_start_index(tick_index, self.pool_state.tick_spacing);
+        let tick_array = self.tick_arrays.get(&start_index).ok_or(format_err!(
+            "tick array {} not in the snapshot",
+            start_index
+        ))?;
+        Ok(tick_array.ticks
+            [tick_array.get_tick_offset_in_array(tick_index, self.pool_state.tick_spacing)?])
+    }
+
+    /// The value and fees owed of every position at the current state of the replay
+    pub fn position_amounts(&self) -> Result<Vec<PositionAmounts>> {
+        self.positions
+            .iter()
+            .map(|(key, position)| {
+                PositionAmounts::new(
+                    &self.pool_state,
+                    position,
+                    *key,
+                    &self.tick_state(position.tick_lower_index)?,
+                    &self.tick_state(position.tick_upper_index)?,
+                )
+                .map_err(Into::into)
+            })
+            .collect()
+    }
+}
+
+/// The state of a pool after replaying swaps and the fees it and its positions earned
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayReport {
+    pub swaps: Vec<ReplaySwapResult>,
+    pub sqrt_price_x64: String,
+    pub price: f64,
+    pub tick_current: i32,
+    pub liquidity: String,
+    /// The fees charged over the replay, by token
+    pub lp_fees: [u64; 2],
+    pub protocol_fees: [u64; 2],
+    pub fund_fees: [u64; 2],
+    pub positions: Vec<ReplayPositionFees>,
+}
+
+/// Replay the swaps in order on the pool, a failed swap is reported and skipped
+pub fn replay_swaps(replay_pool: &mut ReplayPool, swaps: &[ReplaySwap]) -> Result<ReplayReport> {
+    let pool_before = replay_pool.pool_state;
+    let positions_before = replay_pool.position_amounts()?;
+    let results = swaps
+        .iter()
+        .map(|swap| replay_pool.swap(swap))
+        .collect::<Vec<_>>();
+    let positions_after = replay_pool.position_amounts()?;
+
+    let pool_state = &replay_pool.pool_state;
+    let sqrt_price_x64 = pool_state.sqrt_price_x64;
+    let liquidity = pool_state.liquidity;
+    Ok(ReplayReport {
+        swaps: results,
+        sqrt_price_x64: sqrt_price_x64.to_string(),
+        price: sqrt_price_x64_to_price(
+            sqrt_price_x64,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1,
+        ),
+        tick_current: pool_state.tick_current,
+        liquidity: liquidity.to_string(),
+        lp_fees: [
+            pool_state.total_fees_token_0 - pool_before.total_fees_token_0,
+            pool_state.total_fees_token_1 - pool_before.total_fees_token_1,
+        ],
+        protocol_fees: [
+            pool_state.protocol_fees_token_0 - pool_before.protocol_fees_token_0,
+            pool_state.protocol_fees_token_1 - pool_before.protocol_fees_token_1,
+        ],
+        fund_fees: [
+            pool_state.fund_fees_token_0 - pool_before.fund_fees_token_0,
+            pool_state.fund_fees_token_1 - pool_before.fund_fees_token_1,
+        ],
+        positions: positions_before
+            .iter()
+            .zip(positions_after.iter())
+            .zip(replay_pool.positions.iter())
+            .map(|((before, after), (_, position))| ReplayPositionFees {
+                personal_position: after.personal_position.to_string(),
+                tick_lower_index: position.tick_lower_index,
+                tick_upper_index: position.tick_upper_index,
+                fees_0: after.fees_owed_0 - before.fees_owed_0,
+                fees_1: after.fees_owed_1 - before.fees_owed_1,
+            })
+            .collect(),
+    })
+}
+
+/// The lines of the replay report, the pool then one line per failed swap and per position
+/// that earned fees
+pub fn report_replay(report: &ReplayReport) -> Vec<String> {
+    let failed = report
+        .swaps
+        .iter()
+        .filter(|result| result.error.is_some())
+        .count();
+    let mut lines = vec![format!(
+        "swaps:{}, failed:{}, price:{}, tick:{}, liquidity:{}, lp_fees:{:?}, protocol_fees:{:?}, fund_fees:{:?}",
+        report.swaps.len(),
+        failed,
+        report.price,
+        report.tick_current,
+        report.liquidity,
+        report.lp_fees,
+        report.protocol_fees,
+        report.fund_fees
+    )];
+    for (index, result) in report.swaps.iter().enumerate() {
+        if let Some(error) = &result.error {
+            lines.push(format!(
+                "swap {} {:?} failed: {}",
+                index, result.swap, error
+            ));
+        }
+    }
+    for position in report.positions.iter() {
+        if position.fees_0 == 0 && position.fees_1 == 0 {
+            continue;
+        }
+        lines.push(format!(
+            "personal_position:{}, lower:{}, upper:{}, fees_0:{}, fees_1:{}",
+            position.personal_position,
+            position.tick_lower_index,
+            position.tick_upper_index,
+            position.fees_0,
+            position.fees_1
+        ));
+    }
+    lines
+}
@@ -0,0 +1,167 @@
+use anchor_lang::prelude::AccountMeta;
+use anyhow::{format_err, Result};
+use solana_sdk::{
+    address_lookup_table_account::AddressLookupTableAccount,
+    hash::Hash,
+    instruction::Instruction,
+    message::{v0, VersionedMessage},
+    packet::PACKET_DATA_SIZE,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::VersionedTransaction,
+};
+use std::mem::size_of;
+
+use super::utils::amount_with_slippage;
+
+/// Size of the address lookup table metadata preceding the addresses
+pub const LOOKUP_TABLE_META_SIZE: usize = 56;
+
+/// One pool of the route with its quote
+#[derive(Debug, Clone)]
+pub struct RouteHop {
+    pub pool_id: Pubkey,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    /// The token account receiving the output of this hop, the input of the next hop
+    pub output_token_account: Pubkey,
+    /// amm_config, pool_state, output_token_account, input_vault, output_vault, output_mint,
+    /// observation_state, tickarray_bitmap_extension and tick arrays in the order read by the router
+    pub accounts: Vec<AccountMeta>,
+    pub amount_in: u64,
+    pub amount_out: u64,
+}
+
+/// Consecutive hops executed by one transaction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteLeg {
+    /// Index of the first hop
+    pub start: usize,
+    /// Index after the last hop
+    pub end: usize,
+    /// The minimum input of a resumed leg, the minimum output of the previous leg. Zero for the
+    /// first leg.
+    pub amount_in_minimum: u64,
+    pub amount_out_minimum: u64,
+}
+
+impl RouteLeg {
+    pub fn is_first(&self) -> bool {
+        self.start == 0
+    }
+
+    pub fn remaining_accounts(&self, hops: &[RouteHop]) -> Vec<AccountMeta> {
+        hops[self.start..self.end]
+            .iter()
+            .flat_map(|hop| hop.accounts.clone())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RoutePlan {
+    /// All hops fit in one transaction
+    SingleTx(RouteLeg),
+    /// The route is split, every leg after the first resumes from the intermediate balance
+    MultiTx(Vec<RouteLeg>),
+}
+
+impl RoutePlan {
+    pub fn legs(&self) -> Vec<RouteLeg> {
+        match self {
+            RoutePlan::SingleTx(leg) => vec![leg.clone()],
+            RoutePlan::MultiTx(legs) => legs.clone(),
+        }
+    }
+}
+
+/// Decode the addresses of an address lookup table account
+pub fn decode_lookup_table(key: Pubkey, data: &[u8]) -> Result<AddressLookupTableAccount> {
+    if data.len() < LOOKUP_TABLE_META_SIZE
+        || (data.len() - LOOKUP_TABLE_META_SIZE) % size_of::<Pubkey>() != 0
+    {
+        return Err(format_err!("invalid lookup table {}", key));
+    }
+    let addresses = data[LOOKUP_TABLE_META_SIZE..]
+        .chunks(size_of::<Pubkey>())
+        .map(|address| Pubkey::new_from_array(address.try_into().unwrap()))
+        .collect();
+    Ok(AddressLookupTableAccount { key, addresses })
+}
+
+/// Serialized size of the v0 transaction of the instructions, the signatures are not required
+pub fn versioned_tx_size(
+    payer: &Pubkey,
+    instructions: &[Instruction],
+    lookup_tables: &[AddressLookupTableAccount],
+) -> Result<usize> {
+    let message = v0::Message::try_compile(payer, instructions, lookup_tables, Hash::default())?;
+    let transaction = VersionedTransaction {
+        signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+        message: VersionedMessage::V0(message),
+    };
+    Ok(bincode::serialized_size(&transaction)? as usize)
+}
+
+/// Split the hops into as few transactions as possible. `fits` reports whether the transaction of
+/// a leg is within the size limit. The minimum output of every leg is its quote with slippage and
+/// is checked again as the minimum input of the next leg.
+pub fn plan_route<F>(hops: &[RouteHop], slippage: f64, mut fits: F) -> Result<RoutePlan>
+where
+    F: FnMut(&RouteLeg) -> Result<bool>,
+{
+    if hops.is_empty() {
+        return Err(format_err!("empty route"));
+    }
+    let leg = |start: usize, end: usize, amount_in_minimum: u64| RouteLeg {
+        start,
+        end,
+        amount_in_minimum,
+        amount_out_minimum: amount_with_slippage(hops[end - 1].amount_out, slippage, false),
+    };
+
+    let mut legs: Vec<RouteLeg> = Vec::new();
+    let mut start = 0;
+    let mut amount_in_minimum = 0;
+    while start < hops.len() {
+        if !fits(&leg(start, start + 1, amount_in_minimum))? {
+            return Err(format_err!(
+                "hop {} pool {} does not fit in a transaction",
+                start,
+                hops[start].pool_id
+            ));
+        }
+        let mut end = start + 1;
+        while end < hops.len() && fits(&leg(start, end + 1, amount_in_minimum))? {
+            end += 1;
+        }
+        let current = leg(start, end, amount_in_minimum);
+        amount_in_minimum = current.amount_out_minimum;
+        start = end;
+        legs.push(current);
+    }
+    if legs.len() == 1 {
+        Ok(RoutePlan::SingleTx(legs.pop().unwrap()))
+    } else {
+        Ok(RoutePlan::MultiTx(legs))
+    }
+}
+
+/// `plan_route` with the transaction size limit of the built leg instructions
+pub fn plan_route_by_tx_size<F>(
+    payer: &Pubkey,
+    hops: &[RouteHop],
+    slippage: f64,
+    lookup_tables: &[AddressLookupTableAccount],
+    mut build_leg_instructions: F,
+) -> Result<RoutePlan>
+where
+    F: FnMut(&RouteLeg) -> Result<Vec<Instruction>>,
+{
+    plan_route(hops, slippage, |leg| {
+        let instructions = build_leg_instructions(leg)?;
+        // a leg with more accounts than a message can index doesn't fit either
+        Ok(versioned_tx_size(payer, &instructions, lookup_tables)
+            .map_or(false, |size| size <= PACKET_DATA_SIZE))
+    })
+}
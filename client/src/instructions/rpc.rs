@@ -1,15 +1,26 @@
 use anyhow::{anyhow, Result};
 use solana_client::{
+    client_error::{ClientError, ClientErrorKind, Result as ClientResult},
     rpc_client::RpcClient,
-    rpc_config::RpcSendTransactionConfig,
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig},
     rpc_request::RpcRequest,
     rpc_response::{RpcResult, RpcSimulateTransactionResult},
 };
 use solana_sdk::{
-    account::Account, commitment_config::CommitmentConfig, program_pack::Pack as TokenPack,
-    pubkey::Pubkey, signature::Signature, transaction::Transaction,
+    account::Account,
+    commitment_config::CommitmentConfig,
+    compute_budget::{self, ComputeBudgetInstruction},
+    hash::{hashv, Hash},
+    instruction::Instruction,
+    program_pack::Pack as TokenPack,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::Transaction,
 };
+use std::cell::Cell;
 use std::convert::Into;
+use std::time::{Duration, Instant};
+use tracing::{debug, debug_span, info, info_span, warn};
 
 pub fn simulate_transaction(
     client: &RpcClient,
@@ -17,17 +28,43 @@ pub fn simulate_transaction(
     sig_verify: bool,
     cfg: CommitmentConfig,
 ) -> RpcResult<RpcSimulateTransactionResult> {
+    let span = info_span!(
+        "simulate_transaction",
+        signature = %transaction.signatures.get(0).copied().unwrap_or_default()
+    );
+    let _enter = span.enter();
+    let start = Instant::now();
     let serialized_encoded = bs58::encode(bincode::serialize(transaction).unwrap()).into_string();
-    client.send(
+    let result: RpcResult<RpcSimulateTransactionResult> = client.send(
         RpcRequest::SimulateTransaction,
         serde_json::json!([serialized_encoded, {
             "sigVerify": sig_verify, "commitment": cfg.commitment
         }]),
-    )
+    );
+    match &result {
+        Ok(response) => debug!(
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            units_consumed = response.value.units_consumed.unwrap_or_default(),
+            err = ?response.value.err,
+            "simulated"
+        ),
+        Err(err) => warn!(
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "simulation request failed: {}", err
+        ),
+    }
+    result
 }
 
 pub fn send_txn(client: &RpcClient, txn: &Transaction, wait_confirm: bool) -> Result<Signature> {
-    Ok(client.send_and_confirm_transaction_with_spinner_and_config(
+    let span = info_span!(
+        "send_txn",
+        signature = %txn.signatures.get(0).copied().unwrap_or_default(),
+        wait_confirm
+    );
+    let _enter = span.enter();
+    let start = Instant::now();
+    let result = client.send_and_confirm_transaction_with_spinner_and_config(
         txn,
         if wait_confirm {
             CommitmentConfig::confirmed()
@@ -38,7 +75,119 @@ pub fn send_txn(client: &RpcClient, txn: &Transaction, wait_confirm: bool) -> Re
             skip_preflight: true,
             ..RpcSendTransactionConfig::default()
         },
-    )?)
+    );
+    match &result {
+        Ok(signature) => info!(
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "transaction confirmed: {}", signature
+        ),
+        Err(err) => warn!(
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "transaction failed: {}", err
+        ),
+    }
+    Ok(result?)
+}
+
+/// Send the transaction, or with `simulate` only simulate it and print the result with its
+/// logs. The signature is returned when the transaction is sent
+pub fn send_or_simulate_txn(
+    client: &RpcClient,
+    txn: &Transaction,
+    simulate: bool,
+) -> Result<Option<Signature>> {
+    if simulate {
+        let ret = simulate_transaction(client, txn, true, CommitmentConfig::confirmed())?;
+        info!("{:#?}", ret);
+        Ok(None)
+    } else {
+        let signature = send_txn(client, txn, true)?;
+        info!("{}", signature);
+        Ok(Some(signature))
+    }
+}
+
+/// The most compute units a transaction can request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Compute budget of the sent transactions, read from the [ComputeBudget] section
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ComputeBudgetConfig {
+    /// Request the units a simulation consumed, otherwise the maximum is requested
+    pub estimate: bool,
+    /// Percent added to the simulated units, the state can change before the transaction lands
+    pub unit_margin_percent: u32,
+    /// Price of a compute unit in micro lamports, 0 to pay no priority fee
+    pub priority_fee: u64,
+}
+
+impl Default for ComputeBudgetConfig {
+    fn default() -> Self {
+        Self {
+            estimate: true,
+            unit_margin_percent: 20,
+            priority_fee: 0,
+        }
+    }
+}
+
+impl ComputeBudgetConfig {
+    /// The unit limit of a transaction whose simulation consumed `units_consumed`
+    pub fn unit_limit(&self, units_consumed: u64) -> u32 {
+        let units = units_consumed.saturating_mul(100 + u64::from(self.unit_margin_percent)) / 100;
+        units.min(MAX_COMPUTE_UNIT_LIMIT.into()) as u32
+    }
+
+    /// The compute budget instructions heading a transaction
+    pub fn instructions(&self, unit_limit: u32) -> Vec<Instruction> {
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(unit_limit)];
+        if self.priority_fee > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(
+                self.priority_fee,
+            ));
+        }
+        instructions
+    }
+}
+
+/// Replace the compute budget instructions of `instructions` with a unit limit estimated by
+/// simulating them and the configured priority fee. The maximum is requested if the simulation
+/// fails, the send reports the error.
+pub fn with_compute_budget(
+    client: &RpcClient,
+    config: &ComputeBudgetConfig,
+    payer: &Pubkey,
+    instructions: &[Instruction],
+) -> Result<Vec<Instruction>> {
+    let instructions: Vec<Instruction> = instructions
+        .iter()
+        .filter(|instruction| instruction.program_id != compute_budget::id())
+        .cloned()
+        .collect();
+    let mut unit_limit = MAX_COMPUTE_UNIT_LIMIT;
+    if config.estimate {
+        let mut simulated = config.instructions(MAX_COMPUTE_UNIT_LIMIT);
+        simulated.extend(instructions.iter().cloned());
+        let mut txn = Transaction::new_with_payer(&simulated, Some(payer));
+        txn.message.recent_blockhash = client.get_latest_blockhash()?;
+        match simulate_transaction(client, &txn, false, CommitmentConfig::confirmed()) {
+            Ok(response) => match (response.value.err, response.value.units_consumed) {
+                (None, Some(units_consumed)) => {
+                    unit_limit = config.unit_limit(units_consumed);
+                    debug!(units_consumed, unit_limit, "compute units estimated");
+                }
+                (err, _) => warn!(
+                    "compute units not estimated, simulation failed: {:?}, logs: {}",
+                    err,
+                    response.value.logs.unwrap_or_default().join(" | ")
+                ),
+            },
+            Err(err) => warn!("compute units not estimated: {}", err),
+        }
+    }
+    let mut budgeted = config.instructions(unit_limit);
+    budgeted.extend(instructions);
+    Ok(budgeted)
 }
 
 pub fn get_token_account<T: TokenPack>(client: &RpcClient, addr: &Pubkey) -> Result<T> {
@@ -55,3 +204,377 @@ pub fn get_multiple_accounts(
 ) -> Result<Vec<Option<Account>>> {
     Ok(client.get_multiple_accounts(pubkeys)?)
 }
+
+/// The most accounts one getMultipleAccounts request can read
+pub const MAX_MULTIPLE_ACCOUNTS: usize = 100;
+/// The longest wait between two retries of a rate limited request
+pub const MAX_SCAN_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How bulk reads are split and paced, so scans of large pools stay within the limits of public
+/// rpc providers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RpcScanConfig {
+    /// Accounts per getMultipleAccounts request, at most MAX_MULTIPLE_ACCOUNTS
+    pub chunk_size: usize,
+    /// Minimum time between the start of two requests
+    pub request_interval: Duration,
+    /// Retries of a request rejected with 429 before the scan fails
+    pub max_retries: u32,
+    /// Wait before the first retry, doubled at every retry up to MAX_SCAN_BACKOFF
+    pub initial_backoff: Duration,
+}
+
+impl Default for RpcScanConfig {
+    fn default() -> Self {
+        Self {
+            chunk_size: MAX_MULTIPLE_ACCOUNTS,
+            request_interval: Duration::from_millis(100),
+            max_retries: 5,
+            initial_backoff: Duration::from_millis(500),
+        }
+    }
+}
+
+/// Whether the rpc rejected the request for exceeding its rate limit
+pub fn is_rate_limited(err: &ClientError) -> bool {
+    match err.kind() {
+        ClientErrorKind::Reqwest(err) => {
+            err.status().map_or(false, |status| status.as_u16() == 429)
+        }
+        _ => err.to_string().contains("429"),
+    }
+}
+
+/// Runs the requests of the bulk commands: waits the request interval between two requests,
+/// retries the rate limited ones with an exponential backoff and splits account reads in chunks
+pub struct RpcScanner {
+    pub config: RpcScanConfig,
+    last_request: Cell<Option<Instant>>,
+}
+
+impl RpcScanner {
+    pub fn new(config: RpcScanConfig) -> Self {
+        Self {
+            config,
+            last_request: Cell::new(None),
+        }
+    }
+
+    /// The wait before the retry following `attempt` failed attempts
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.config
+            .initial_backoff
+            .checked_mul(factor)
+            .map_or(MAX_SCAN_BACKOFF, |backoff| backoff.min(MAX_SCAN_BACKOFF))
+    }
+
+    fn pace(&self) {
+        if let Some(last_request) = self.last_request.get() {
+            let elapsed = last_request.elapsed();
+            if elapsed < self.config.request_interval {
+                std::thread::sleep(self.config.request_interval - elapsed);
+            }
+        }
+        self.last_request.set(Some(Instant::now()));
+    }
+
+    /// Send the request, retrying it while the rpc rate limits it
+    pub fn request<T>(
+        &self,
+        name: &str,
+        mut request: impl FnMut() -> ClientResult<T>,
+    ) -> Result<T> {
+        let mut attempt = 0;
+        loop {
+            self.pace();
+            match request() {
+                Ok(value) => return Ok(value),
+                Err(err) if is_rate_limited(&err) && attempt < self.config.max_retries => {
+                    attempt += 1;
+                    let backoff = self.backoff(attempt);
+                    warn!(
+                        backoff_ms = backoff.as_millis() as u64,
+                        "{} rate limited, retry {} of {}", name, attempt, self.config.max_retries
+                    );
+                    std::thread::sleep(backoff);
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Read any number of accounts, in order, a request per chunk
+    pub fn get_multiple_accounts(
+        &self,
+        client: &RpcClient,
+        pubkeys: &[Pubkey],
+    ) -> Result<Vec<Option<Account>>> {
+        let span = debug_span!("scan_multiple_accounts", accounts = pubkeys.len());
+        let _enter = span.enter();
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(self.config.chunk_size.clamp(1, MAX_MULTIPLE_ACCOUNTS)) {
+            accounts.extend(self.request("getMultipleAccounts", || {
+                client.get_multiple_accounts(chunk)
+            })?);
+        }
+        Ok(accounts)
+    }
+
+    /// Read the accounts of the chunk with the slot of the response, see get_multiple_accounts
+    pub fn get_multiple_accounts_with_commitment(
+        &self,
+        client: &RpcClient,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> Result<(u64, Vec<Option<Account>>)> {
+        let mut slot = u64::MAX;
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(self.config.chunk_size.clamp(1, MAX_MULTIPLE_ACCOUNTS)) {
+            let rsp = self.request("getMultipleAccounts", || {
+                client.get_multiple_accounts_with_commitment(chunk, commitment)
+            })?;
+            // the oldest slot, every chunk was read at or after it
+            slot = slot.min(rsp.context.slot);
+            accounts.extend(rsp.value);
+        }
+        Ok((slot, accounts))
+    }
+
+    pub fn get_program_accounts_with_config(
+        &self,
+        client: &RpcClient,
+        program_id: &Pubkey,
+        config: RpcProgramAccountsConfig,
+    ) -> Result<Vec<(Pubkey, Account)>> {
+        let span = debug_span!("scan_program_accounts", program = %program_id);
+        let _enter = span.enter();
+        let start = Instant::now();
+        let accounts = self.request("getProgramAccounts", || {
+            client.get_program_accounts_with_config(program_id, config.clone())
+        })?;
+        debug!(
+            accounts = accounts.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "program accounts read"
+        );
+        Ok(accounts)
+    }
+}
+
+/// An rpc endpoint of the pool
+pub struct RpcEndpoint {
+    pub http_url: String,
+    pub ws_url: String,
+    pub client: RpcClient,
+}
+
+/// The accounts read from one endpoint at a slot
+#[derive(Debug)]
+pub struct EndpointAccounts {
+    pub http_url: String,
+    pub slot: u64,
+    pub hash: Hash,
+    pub accounts: Vec<Option<Account>>,
+}
+
+/// Accounts the quorum of endpoints agreed on
+#[derive(Debug)]
+pub struct QuorumAccounts {
+    /// The highest slot among the agreeing endpoints
+    pub slot: u64,
+    pub hash: Hash,
+    pub agreeing: Vec<String>,
+    pub accounts: Vec<Option<Account>>,
+}
+
+/// Several rpc endpoints of the same cluster. Reads can fan out to every endpoint and require a
+/// quorum of them to return the same accounts, writes go to the endpoints in order until one
+/// accepts the transaction, so one degraded endpoint doesn't stop a keeper.
+pub struct RpcPool {
+    pub endpoints: Vec<RpcEndpoint>,
+    /// Endpoints that must return the same accounts for a quorum read
+    pub quorum: usize,
+}
+
+impl RpcPool {
+    /// `http_urls` and `ws_urls` are paired by position, the first pair is the primary endpoint
+    pub fn new(http_urls: &[String], ws_urls: &[String], quorum: usize) -> Result<Self> {
+        if http_urls.is_empty() || http_urls.len() != ws_urls.len() {
+            return Err(anyhow!(
+                "every http url needs a ws url, got {} http and {} ws urls",
+                http_urls.len(),
+                ws_urls.len()
+            ));
+        }
+        if quorum == 0 || quorum > http_urls.len() {
+            return Err(anyhow!(
+                "quorum {} must be between 1 and the {} endpoints",
+                quorum,
+                http_urls.len()
+            ));
+        }
+        Ok(Self {
+            endpoints: http_urls
+                .iter()
+                .zip(ws_urls)
+                .map(|(http_url, ws_url)| RpcEndpoint {
+                    http_url: http_url.clone(),
+                    ws_url: ws_url.clone(),
+                    client: RpcClient::new(http_url.clone()),
+                })
+                .collect(),
+            quorum,
+        })
+    }
+
+    /// The first endpoint answering `getSlot`, in the configured order
+    pub fn first_healthy(&self) -> Result<&RpcEndpoint> {
+        let mut errors = Vec::new();
+        for endpoint in &self.endpoints {
+            match endpoint.client.get_slot() {
+                Ok(_) => return Ok(endpoint),
+                Err(err) => {
+                    warn!(endpoint = %endpoint.http_url, "rpc unhealthy: {}", err);
+                    errors.push(format!("{}: {}", endpoint.http_url, err));
+                }
+            }
+        }
+        Err(anyhow!("no healthy rpc endpoint, {}", errors.join("; ")))
+    }
+
+    /// Read the accounts from every endpoint, the endpoints that fail are skipped
+    pub fn get_multiple_accounts_from_all(
+        &self,
+        pubkeys: &[Pubkey],
+    ) -> (Vec<EndpointAccounts>, Vec<String>) {
+        let span = debug_span!("get_multiple_accounts_from_all", accounts = pubkeys.len());
+        let _enter = span.enter();
+        let mut responses = Vec::new();
+        let mut errors = Vec::new();
+        for endpoint in &self.endpoints {
+            let start = Instant::now();
+            match endpoint.client.get_multiple_accounts_with_config(
+                pubkeys,
+                RpcAccountInfoConfig {
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    ..RpcAccountInfoConfig::default()
+                },
+            ) {
+                Ok(response) => {
+                    debug!(
+                        endpoint = %endpoint.http_url,
+                        slot = response.context.slot,
+                        elapsed_ms = start.elapsed().as_millis() as u64,
+                        "accounts read"
+                    );
+                    responses.push(EndpointAccounts {
+                        http_url: endpoint.http_url.clone(),
+                        slot: response.context.slot,
+                        hash: accounts_hash(&response.value),
+                        accounts: response.value,
+                    })
+                }
+                Err(err) => {
+                    warn!(endpoint = %endpoint.http_url, "accounts read failed: {}", err);
+                    errors.push(format!("{}: {}", endpoint.http_url, err));
+                }
+            }
+        }
+        (responses, errors)
+    }
+
+    /// Read the accounts from every endpoint and return them if at least `quorum` endpoints
+    /// returned the same data. Endpoints behind a recent write of the accounts disagree, the
+    /// error lists the slot and hash every endpoint returned.
+    pub fn get_multiple_accounts_quorum(&self, pubkeys: &[Pubkey]) -> Result<QuorumAccounts> {
+        let (responses, errors) = self.get_multiple_accounts_from_all(pubkeys);
+        select_quorum(responses, self.quorum).map_err(|err| {
+            if errors.is_empty() {
+                err
+            } else {
+                anyhow!("{}, failed endpoints: {}", err, errors.join("; "))
+            }
+        })
+    }
+
+    pub fn get_account_quorum(&self, pubkey: &Pubkey) -> Result<Option<Account>> {
+        Ok(self
+            .get_multiple_accounts_quorum(&[*pubkey])?
+            .accounts
+            .pop()
+            .unwrap())
+    }
+
+    /// Send the transaction to the endpoints in order until one accepts it. The signed transaction
+    /// is the same on every endpoint, so it lands at most once.
+    pub fn send_txn_with_failover(
+        &self,
+        txn: &Transaction,
+        wait_confirm: bool,
+    ) -> Result<Signature> {
+        let mut errors = Vec::new();
+        for endpoint in &self.endpoints {
+            match send_txn(&endpoint.client, txn, wait_confirm) {
+                Ok(signature) => return Ok(signature),
+                Err(err) => {
+                    warn!(endpoint = %endpoint.http_url, "send failed, trying the next endpoint");
+                    errors.push(format!("{}: {}", endpoint.http_url, err));
+                }
+            }
+        }
+        Err(anyhow!("every rpc endpoint failed, {}", errors.join("; ")))
+    }
+}
+
+/// Hash of the owner, lamports and data of the accounts, so responses can be compared cheaply
+pub fn accounts_hash(accounts: &[Option<Account>]) -> Hash {
+    let mut fields: Vec<Vec<u8>> = Vec::new();
+    for account in accounts {
+        match account {
+            Some(account) => {
+                fields.push(vec![1]);
+                fields.push(account.owner.to_bytes().to_vec());
+                fields.push(account.lamports.to_le_bytes().to_vec());
+                fields.push((account.data.len() as u64).to_le_bytes().to_vec());
+                fields.push(account.data.clone());
+            }
+            None => fields.push(vec![0]),
+        }
+    }
+    let slices: Vec<&[u8]> = fields.iter().map(|field| field.as_slice()).collect();
+    hashv(&slices)
+}
+
+/// The accounts returned by the most endpoints, if at least `quorum` of them agree
+pub fn select_quorum(responses: Vec<EndpointAccounts>, quorum: usize) -> Result<QuorumAccounts> {
+    let mut groups: Vec<QuorumAccounts> = Vec::new();
+    let mut summary = Vec::new();
+    for response in responses {
+        summary.push(format!(
+            "{} slot:{} hash:{}",
+            response.http_url, response.slot, response.hash
+        ));
+        match groups.iter_mut().find(|group| group.hash == response.hash) {
+            Some(group) => {
+                group.slot = group.slot.max(response.slot);
+                group.agreeing.push(response.http_url);
+            }
+            None => groups.push(QuorumAccounts {
+                slot: response.slot,
+                hash: response.hash,
+                agreeing: vec![response.http_url],
+                accounts: response.accounts,
+            }),
+        }
+    }
+    groups
+        .into_iter()
+        .max_by_key(|group| (group.agreeing.len(), group.slot))
+        .filter(|group| group.agreeing.len() >= quorum)
+        .ok_or(anyhow!(
+            "no {} endpoints agree on the accounts, {}",
+            quorum,
+            summary.join("; ")
+        ))
+}
@@ -0,0 +1,436 @@
+use anchor_lang::prelude::AccountMeta;
+use anyhow::{format_err, Result};
+use raydium_amm_v3::libraries::fixed_point_64;
+use raydium_amm_v3::states::{
+    AmmConfig, PoolState, AMM_CONFIG_SEED, OPERATION_SEED, POOL_REWARD_VAULT_SEED,
+};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    clock::Clock,
+    commitment_config::CommitmentConfig,
+    instruction::Instruction,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    sysvar,
+    transaction::Transaction,
+};
+use spl_associated_token_account::get_associated_token_address;
+use std::collections::{BTreeMap, HashSet};
+use std::process::Command;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{info, warn};
+
+use super::super::ClientConfig;
+use super::amm_instructions::{set_reward_params_instr, update_amm_config_instr};
+use super::config_diff::{amm_config_diff, apply_amm_config_update};
+use super::rpc::{send_txn, simulate_transaction, with_compute_budget};
+use super::telemetry::txn_span;
+use super::utils::{deserialize_anchor_account, format_unix_timestamp};
+
+/// The admin actions of a schedule plan, tagged by `type` in the plan file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduledAction {
+    /// `update_amm_config` of the config at `config_index`, `remaining` is the new key of the
+    /// params 3 and 4
+    UpdateConfig {
+        config_index: u16,
+        param: u8,
+        #[serde(default)]
+        value: u32,
+        #[serde(default)]
+        remaining: Option<String>,
+    },
+    /// `set_reward_params` of a reward of the pool, funded from the admin's reward token account
+    SetRewardParams {
+        pool_id: String,
+        index: u8,
+        open_time: u64,
+        end_time: u64,
+        /// Reward tokens emitted per second
+        emissions: f64,
+        reward_mint: String,
+    },
+}
+
+/// An action of the plan and the unix timestamp it's executed at
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedAction {
+    /// Names the action in notifications and in the state file, unique within the plan
+    pub label: String,
+    pub at: u64,
+    #[serde(flatten)]
+    pub action: ScheduledAction,
+}
+
+/// The plan file of `run-schedule`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedulePlan {
+    pub actions: Vec<PlannedAction>,
+}
+
+/// The signatures of the executed actions by label, written next to the plan so a restarted
+/// scheduler doesn't execute an action twice
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleState {
+    pub executed: BTreeMap<String, String>,
+}
+
+impl ScheduleState {
+    pub fn path(plan_path: &str) -> String {
+        format!("{}.state", plan_path)
+    }
+
+    pub fn load(plan_path: &str) -> Result<Self> {
+        let path = Self::path(plan_path);
+        if !std::path::Path::new(&path).exists() {
+            return Ok(Self::default());
+        }
+        Ok(serde_json::from_str(&std::fs::read_to_string(&path)?)?)
+    }
+
+    pub fn save(&self, plan_path: &str) -> Result<()> {
+        std::fs::write(Self::path(plan_path), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Read the plan, check every action can be built and sort them by time
+pub fn load_schedule_plan(plan_path: &str) -> Result<SchedulePlan> {
+    let mut plan: SchedulePlan = serde_json::from_str(&std::fs::read_to_string(plan_path)?)
+        .map_err(|e| format_err!("invalid plan {}: {}", plan_path, e))?;
+    let mut labels = HashSet::new();
+    for planned in plan.actions.iter() {
+        if !labels.insert(planned.label.as_str()) {
+            return Err(format_err!("label {} is used twice", planned.label));
+        }
+        match &planned.action {
+            ScheduledAction::UpdateConfig {
+                param, remaining, ..
+            } => {
+                if *param > 6 {
+                    return Err(format_err!("{}: unknown param {}", planned.label, param));
+                }
+                if (*param == 3 || *param == 4) && remaining.is_none() {
+                    return Err(format_err!(
+                        "{}: param {} needs the new key in remaining",
+                        planned.label,
+                        param
+                    ));
+                }
+                if let Some(remaining) = remaining {
+                    parse_key(&planned.label, remaining)?;
+                }
+            }
+            ScheduledAction::SetRewardParams {
+                pool_id,
+                open_time,
+                end_time,
+                emissions,
+                reward_mint,
+                ..
+            } => {
+                parse_key(&planned.label, pool_id)?;
+                parse_key(&planned.label, reward_mint)?;
+                if end_time <= open_time || *emissions <= 0.0 {
+                    return Err(format_err!(
+                        "{}: the reward must end after it opens and emit tokens",
+                        planned.label
+                    ));
+                }
+            }
+        }
+    }
+    plan.actions.sort_by_key(|planned| planned.at);
+    Ok(plan)
+}
+
+fn parse_key(label: &str, key: &str) -> Result<Pubkey> {
+    Pubkey::from_str(key).map_err(|_| format_err!("{}: invalid key {}", label, key))
+}
+
+/// Reports the progress of the schedule on stdout and through the notify command, which is run
+/// by `sh -c` with SCHEDULE_EVENT, SCHEDULE_LABEL and SCHEDULE_MESSAGE set
+pub struct ScheduleNotifier {
+    pub command: Option<String>,
+}
+
+impl ScheduleNotifier {
+    pub fn notify(&self, event: &str, label: &str, message: &str) {
+        info!(
+            "[{}] {} {}: {}",
+            format_unix_timestamp(unix_now()),
+            event,
+            label,
+            message
+        );
+        let command = match &self.command {
+            Some(command) => command,
+            None => return,
+        };
+        // a failing notification must not stop the schedule
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(command)
+            .env("SCHEDULE_EVENT", event)
+            .env("SCHEDULE_LABEL", label)
+            .env("SCHEDULE_MESSAGE", message)
+            .status();
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!("notify command exited with {}", status),
+            Err(e) => warn!("notify command failed: {}", e),
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+/// The unix timestamp of the clock sysvar, the clock the program checks reward times against
+pub fn cluster_time(rpc_client: &RpcClient) -> Result<u64> {
+    let clock: Clock = bincode::deserialize(&rpc_client.get_account(&sysvar::clock::id())?.data)?;
+    Ok(clock.unix_timestamp as u64)
+}
+
+/// Build the instructions of the action from the accounts as they are now. An update of the
+/// config is checked the way the program checks it and its diff is printed.
+pub fn build_scheduled_action(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    admin: &Pubkey,
+    action: &ScheduledAction,
+) -> Result<Vec<Instruction>> {
+    match action {
+        ScheduledAction::UpdateConfig {
+            config_index,
+            param,
+            value,
+            remaining,
+        } => {
+            let amm_config_key = Pubkey::find_program_address(
+                &[AMM_CONFIG_SEED.as_bytes(), &config_index.to_be_bytes()],
+                &config.raydium_v3_program,
+            )
+            .0;
+            let remaining = match remaining {
+                Some(remaining) => Some(Pubkey::from_str(remaining)?),
+                None => None,
+            };
+            let amm_config =
+                deserialize_anchor_account::<AmmConfig>(&rpc_client.get_account(&amm_config_key)?)?;
+            let updated_config = apply_amm_config_update(&amm_config, *param, *value, remaining)?;
+            let changes = amm_config_diff(&amm_config, &updated_config);
+            if changes.is_empty() {
+                return Err(format_err!("the update doesn't change {}", amm_config_key));
+            }
+            for change in changes {
+                info!("{}: {} -> {}", change.name, change.old, change.new);
+            }
+            let remaining_accounts = remaining
+                .map(|key| vec![AccountMeta::new_readonly(key, false)])
+                .unwrap_or_default();
+            update_amm_config_instr(config, amm_config_key, remaining_accounts, *param, *value)
+        }
+        ScheduledAction::SetRewardParams {
+            pool_id,
+            index,
+            open_time,
+            end_time,
+            emissions,
+            reward_mint,
+        } => {
+            let pool_id = Pubkey::from_str(pool_id)?;
+            let reward_mint = Pubkey::from_str(reward_mint)?;
+            let pool_state =
+                deserialize_anchor_account::<PoolState>(&rpc_client.get_account(&pool_id)?)?;
+            let operation_key = Pubkey::find_program_address(
+                &[OPERATION_SEED.as_bytes()],
+                &config.raydium_v3_program,
+            )
+            .0;
+            let reward_token_vault = Pubkey::find_program_address(
+                &[
+                    POOL_REWARD_VAULT_SEED.as_bytes(),
+                    pool_id.as_ref(),
+                    reward_mint.as_ref(),
+                ],
+                &config.raydium_v3_program,
+            )
+            .0;
+            set_reward_params_instr(
+                config,
+                pool_state.amm_config,
+                pool_id,
+                reward_token_vault,
+                get_associated_token_address(admin, &reward_mint),
+                operation_key,
+                *index,
+                *open_time,
+                *end_time,
+                (*emissions * fixed_point_64::Q64 as f64) as u128,
+            )
+        }
+    }
+}
+
+/// Simulate the signed transaction, the error carries the logs of a failed simulation
+pub fn preflight_scheduled_action(rpc_client: &RpcClient, txn: &Transaction) -> Result<()> {
+    let result = simulate_transaction(rpc_client, txn, true, CommitmentConfig::confirmed())?.value;
+    match result.err {
+        None => Ok(()),
+        Some(err) => Err(format_err!(
+            "simulation failed: {}, logs: {}",
+            err,
+            result.logs.unwrap_or_default().join(" | ")
+        )),
+    }
+}
+
+/// Options of `run-schedule`
+#[derive(Debug, Clone)]
+pub struct ScheduleOptions {
+    /// Only build and simulate the pending actions
+    pub dry_run: bool,
+    /// Seconds between checks of the cluster time while waiting
+    pub poll_interval: u64,
+    /// Simulations of an action before it's given up
+    pub attempts: u32,
+}
+
+/// Execute the pending actions of the plan in order, each once the cluster time reaches it and
+/// its simulation succeeds. An action that keeps failing its simulation is reported and left
+/// pending for the next run.
+pub fn run_schedule(
+    rpc_client: &RpcClient,
+    config: &ClientConfig,
+    payer: &Keypair,
+    admin: &Keypair,
+    plan_path: &str,
+    notifier: &ScheduleNotifier,
+    options: &ScheduleOptions,
+) -> Result<()> {
+    let plan = load_schedule_plan(plan_path)?;
+    let mut state = ScheduleState::load(plan_path)?;
+    let pending: Vec<&PlannedAction> = plan
+        .actions
+        .iter()
+        .filter(|planned| !state.executed.contains_key(&planned.label))
+        .collect();
+    info!(
+        "actions:{}, executed:{}, pending:{}",
+        plan.actions.len(),
+        plan.actions.len() - pending.len(),
+        pending.len()
+    );
+    let signers = vec![payer, admin];
+    if options.dry_run {
+        // the checks of the program run against the accounts of now, an action may only become
+        // valid at its time
+        for planned in pending {
+            info!("{} at {}", planned.label, format_unix_timestamp(planned.at));
+            let checked =
+                build_scheduled_action(rpc_client, config, &admin.pubkey(), &planned.action)
+                    .and_then(|instructions| {
+                        let txn = Transaction::new_signed_with_payer(
+                            &with_compute_budget(
+                                rpc_client,
+                                &config.compute_budget,
+                                &payer.pubkey(),
+                                &instructions,
+                            )?,
+                            Some(&payer.pubkey()),
+                            &signers,
+                            rpc_client.get_latest_blockhash()?,
+                        );
+                        preflight_scheduled_action(rpc_client, &txn)
+                    });
+            match checked {
+                Ok(()) => info!("simulation ok"),
+                Err(e) => warn!("{}", e),
+            }
+        }
+        return Ok(());
+    }
+
+    for planned in pending {
+        let span = txn_span(&planned.label);
+        let _enter = span.enter();
+        let now = cluster_time(rpc_client)?;
+        if now > planned.at {
+            notifier.notify(
+                "late",
+                &planned.label,
+                &format!(
+                    "was due at {}, executing now",
+                    format_unix_timestamp(planned.at)
+                ),
+            );
+        } else {
+            notifier.notify(
+                "waiting",
+                &planned.label,
+                &format!("due at {}", format_unix_timestamp(planned.at)),
+            );
+            while cluster_time(rpc_client)? < planned.at {
+                let remaining = planned.at.saturating_sub(unix_now());
+                std::thread::sleep(Duration::from_secs(
+                    remaining.min(options.poll_interval).max(1),
+                ));
+            }
+        }
+
+        let mut last_error = None;
+        for attempt in 1..=options.attempts {
+            let checked =
+                build_scheduled_action(rpc_client, config, &admin.pubkey(), &planned.action)
+                    .and_then(|instructions| {
+                        let txn = Transaction::new_signed_with_payer(
+                            &with_compute_budget(
+                                rpc_client,
+                                &config.compute_budget,
+                                &payer.pubkey(),
+                                &instructions,
+                            )?,
+                            Some(&payer.pubkey()),
+                            &signers,
+                            rpc_client.get_latest_blockhash()?,
+                        );
+                        preflight_scheduled_action(rpc_client, &txn)?;
+                        Ok(txn)
+                    });
+            match checked {
+                Ok(txn) => {
+                    last_error = None;
+                    let signature = send_txn(rpc_client, &txn, true).map_err(|e| {
+                        notifier.notify("failed", &planned.label, &e.to_string());
+                        e
+                    })?;
+                    state
+                        .executed
+                        .insert(planned.label.clone(), signature.to_string());
+                    state.save(plan_path)?;
+                    notifier.notify("executed", &planned.label, &signature.to_string());
+                    break;
+                }
+                Err(e) => {
+                    warn!("attempt {} of {}: {}", attempt, options.attempts, e);
+                    last_error = Some(e);
+                    if attempt < options.attempts {
+                        std::thread::sleep(Duration::from_secs(options.poll_interval));
+                    }
+                }
+            }
+        }
+        if let Some(e) = last_error {
+            notifier.notify("failed", &planned.label, &e.to_string());
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anyhow::{format_err, Result};
+use clap::ValueEnum;
+use tracing::{info_span, Span};
+use tracing_subscriber::EnvFilter;
+
+/// Verbosity used when neither --log-filter nor RUST_LOG is set
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+/// How the log lines are written to stdout
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogFormat {
+    /// One readable line per event, for a terminal
+    Human,
+    /// One json object per event with the fields of its spans, for log pipelines
+    Json,
+}
+
+/// The filter directives to apply, the command line takes precedence over RUST_LOG. Directives
+/// follow the RUST_LOG syntax, e.g. `info,client::instructions::rpc=debug`.
+pub fn log_filter(arg: Option<&str>, env: Option<String>) -> String {
+    match arg {
+        Some(filter) => filter.to_string(),
+        None => env
+            .filter(|filter| !filter.trim().is_empty())
+            .unwrap_or(DEFAULT_LOG_FILTER.to_string()),
+    }
+}
+
+/// Install the global subscriber, called once before any command runs
+pub fn init_telemetry(format: LogFormat, filter: Option<&str>) -> Result<()> {
+    let filter = log_filter(filter, std::env::var("RUST_LOG").ok());
+    let env_filter = EnvFilter::try_new(&filter)
+        .map_err(|e| format_err!("invalid log filter {}: {}", filter, e))?;
+    let builder = tracing_subscriber::fmt().with_env_filter(env_filter);
+    match format {
+        LogFormat::Human => builder.with_target(false).without_time().try_init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init(),
+    }
+    .map_err(|e| format_err!("failed to install the logger: {}", e))
+}
+
+/// A random id joining the log lines of one run or one transaction
+pub fn new_correlation_id() -> String {
+    format!("{:016x}", rand::random::<u64>())
+}
+
+/// Span around the lifecycle of one transaction, from its build through the simulation to the
+/// confirmation, every event inside carries the `txn_id`
+pub fn txn_span(label: &str) -> Span {
+    info_span!("txn", label, txn_id = %new_correlation_id())
+}
@@ -1,8 +1,11 @@
-use anchor_lang::AccountDeserialize;
+use anchor_lang::prelude::AccountMeta;
+use anchor_lang::{AccountDeserialize, AnchorDeserialize};
 use anyhow::Result;
+use clap::ValueEnum;
 use raydium_amm_v3::libraries::fixed_point_64;
 use raydium_amm_v3::libraries::*;
 use raydium_amm_v3::states::*;
+use serde::{Deserialize, Serialize};
 use solana_client::rpc_client::RpcClient;
 use solana_sdk::{account::Account, pubkey::Pubkey};
 use spl_token_2022::{
@@ -22,7 +25,8 @@ use spl_token_2022::{
     state::Mint,
 };
 use std::collections::VecDeque;
-use std::ops::{DerefMut, Mul, Neg};
+use std::ops::Mul;
+use tracing::{debug, warn};
 
 pub fn deserialize_anchor_account<T: AccountDeserialize>(account: &Account) -> Result<T> {
     let mut data: &[u8] = &account.data;
@@ -61,6 +65,22 @@ pub fn amount_with_slippage(amount: u64, slippage: f64, round_up: bool) -> u64 {
     }
 }
 
+/// The slippage rate of a tolerance in basis points, at most 10000 bps
+pub fn slippage_from_bps(slippage_bps: u16) -> Result<f64> {
+    if slippage_bps > 10_000 {
+        return Err(anyhow::format_err!(
+            "slippage {} bps is more than 10000 bps",
+            slippage_bps
+        ));
+    }
+    Ok(slippage_bps as f64 / 10_000.0)
+}
+
+/// Convert the slippage of the config into the tolerance rate of `liquidity_math::amount_with_tolerance`
+pub fn slippage_to_tolerance_rate(slippage: f64) -> u32 {
+    (slippage * FEE_RATE_DENOMINATOR_VALUE as f64).ceil() as u32
+}
+
 pub fn get_pool_mints_inverse_fee(
     rpc_client: &RpcClient,
     token_mint_0: Pubkey,
@@ -159,7 +179,7 @@ pub fn get_account_extensions<'data, S: BaseState>(
 ) -> Vec<ExtensionStruct> {
     let mut extensions: Vec<ExtensionStruct> = Vec::new();
     let extension_types = account_state.get_extension_types().unwrap();
-    println!("extension_types:{:?}", extension_types);
+    debug!("extension_types:{:?}", extension_types);
     for extension_type in extension_types {
         match extension_type {
             ExtensionType::ConfidentialTransferAccount => {
@@ -225,7 +245,7 @@ pub fn get_account_extensions<'data, S: BaseState>(
                 extensions.push(ExtensionStruct::TransferFeeAmount(*extension));
             }
             _ => {
-                println!("unkonwn extension:{:#?}", extension_type);
+                warn!("unkonwn extension:{:#?}", extension_type);
             }
         }
     }
@@ -275,36 +295,844 @@ pub fn sqrt_price_x64_to_price(price: u128, decimals_0: u8, decimals_1: u8) -> f
     from_x64_price(price).powi(2) * multipler(decimals_0) / multipler(decimals_1)
 }
 
-// the top level state of the swap, the results of which are recorded in storage at the end
-#[derive(Debug)]
-pub struct SwapState {
-    // the amount remaining to be swapped in/out of the input/output asset
-    pub amount_specified_remaining: u64,
-    // the amount already swapped out/in of the output/input asset
-    pub amount_calculated: u64,
-    // current sqrt(price)
+/// The price of the other token of the pair, the price must be positive
+pub fn invert_price(price: f64) -> f64 {
+    assert!(price > 0.0, "price must be positive, got {}", price);
+    1.0 / price
+}
+
+/// A mint pair in the order pools store it, mint0 < mint1, and whether it was entered the other
+/// way round. Prices entered for the pair are the price of the second mint in the first one,
+/// pool prices are the price of mint1 in mint0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CanonicalPair {
+    pub mint0: Pubkey,
+    pub mint1: Pubkey,
+    /// The pair was entered as (mint1, mint0)
+    pub inverted: bool,
+}
+
+impl CanonicalPair {
+    pub fn new(mint_a: Pubkey, mint_b: Pubkey) -> Self {
+        if mint_a > mint_b {
+            Self {
+                mint0: mint_b,
+                mint1: mint_a,
+                inverted: true,
+            }
+        } else {
+            Self {
+                mint0: mint_a,
+                mint1: mint_b,
+                inverted: false,
+            }
+        }
+    }
+
+    /// The price entered for the pair as the pool price
+    pub fn pool_price(&self, price: f64) -> f64 {
+        if self.inverted {
+            invert_price(price)
+        } else {
+            price
+        }
+    }
+
+    /// The pool price as the price of the pair in the order it was entered
+    pub fn user_price(&self, pool_price: f64) -> f64 {
+        self.pool_price(pool_price)
+    }
+
+    /// A price range entered for the pair as the pool price range, inverting the prices swaps the
+    /// lower and upper bounds
+    pub fn pool_price_range(&self, price_lower: f64, price_upper: f64) -> (f64, f64) {
+        if self.inverted {
+            (invert_price(price_upper), invert_price(price_lower))
+        } else {
+            (price_lower, price_upper)
+        }
+    }
+
+    /// Amounts entered for the pair as the amounts of (mint0, mint1)
+    pub fn pool_amounts<T>(&self, amount_a: T, amount_b: T) -> (T, T) {
+        if self.inverted {
+            (amount_b, amount_a)
+        } else {
+            (amount_a, amount_b)
+        }
+    }
+}
+
+/// The direction of a swap from `input_mint` to `output_mint` through the pool of
+/// (`mint_0`, `mint_1`), true for token_0 to token_1
+pub fn swap_direction(
+    mint_0: Pubkey,
+    mint_1: Pubkey,
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+) -> Result<bool> {
+    if input_mint == mint_0 && output_mint == mint_1 {
+        Ok(true)
+    } else if input_mint == mint_1 && output_mint == mint_0 {
+        Ok(false)
+    } else {
+        Err(anyhow::format_err!(
+            "the pool of {} and {} doesn't swap {} for {}",
+            mint_0,
+            mint_1,
+            input_mint,
+            output_mint
+        ))
+    }
+}
+
+/// Run the checks the program makes on the accounts of a swap before sending it: the direction
+/// is read from the vaults, and each user token account must hold the mint of its vault. Catches
+/// a wrapped sol or duplicate mint account passed on the wrong side. Returns zero_for_one.
+pub fn check_swap_accounts(
+    pool_state: &PoolState,
+    input_vault: Pubkey,
+    output_vault: Pubkey,
+    input_account_mint: Pubkey,
+    output_account_mint: Pubkey,
+) -> Result<bool> {
+    let zero_for_one = pool_state
+        .swap_direction(&input_vault, &output_vault)
+        .map_err(|_| {
+            anyhow::format_err!(
+                "{} and {} are not the input and output vaults of the pool",
+                input_vault,
+                output_vault
+            )
+        })?;
+    let (input_vault_mint, output_vault_mint) = if zero_for_one {
+        (pool_state.token_mint_0, pool_state.token_mint_1)
+    } else {
+        (pool_state.token_mint_1, pool_state.token_mint_0)
+    };
+    for (side, account_mint, vault_mint) in [
+        ("input", input_account_mint, input_vault_mint),
+        ("output", output_account_mint, output_vault_mint),
+    ] {
+        if account_mint != vault_mint {
+            return Err(anyhow::format_err!(
+                "the {} token account holds {} but its vault holds {}",
+                side,
+                account_mint,
+                vault_mint
+            ));
+        }
+    }
+    Ok(zero_for_one)
+}
+
+/// A tick array the range of a new position lands in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TickArrayOpenCost {
+    pub start_index: i32,
+    pub address: Pubkey,
+    /// The account exists, opening the position reuses it
+    pub exists: bool,
+    /// The pool bitmap marks the tick array as holding initialized ticks, an existing tick array
+    /// whose ticks were all cleared is not marked
+    pub bitmap_initialized: bool,
+    /// The rent paid by the position owner to create the account, zero if it exists
+    pub rent: u64,
+}
+
+/// The tick arrays opening a position creates and their rent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenCostEstimate {
+    pub tick_arrays: Vec<TickArrayOpenCost>,
+    pub rent: u64,
+}
+
+impl OpenCostEstimate {
+    pub fn report(&self) -> String {
+        let mut report = String::new();
+        for tick_array in self.tick_arrays.iter() {
+            report.push_str(&format!(
+                "tick_array:{}, start_index:{}, exists:{}, bitmap_initialized:{}, rent:{}\n",
+                tick_array.address,
+                tick_array.start_index,
+                tick_array.exists,
+                tick_array.bitmap_initialized,
+                tick_array.rent
+            ));
+        }
+        report.push_str(&format!(
+            "tick arrays to create:{}, rent:{} lamports",
+            self.tick_arrays
+                .iter()
+                .filter(|tick_array| !tick_array.exists)
+                .count(),
+            self.rent
+        ));
+        report
+    }
+}
+
+/// Whether the pool bitmap, or its extension beyond the default range, marks the tick array
+/// starting at `start_index` as initialized
+pub fn tick_array_bitmap_initialized(
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    start_index: i32,
+) -> Result<bool> {
+    // the start index of the lowest tick array is below the min tick
+    let tick_index = start_index.max(tick_math::MIN_TICK);
+    if pool_state.is_overflow_default_tickarray_bitmap(vec![tick_index]) {
+        Ok(tickarray_bitmap_extension
+            .check_tick_array_is_initialized(start_index, pool_state.tick_spacing)?
+            .0)
+    } else {
+        Ok(check_current_tick_array_is_initialized(
+            U1024(pool_state.tick_array_bitmap),
+            tick_index,
+            pool_state.tick_spacing,
+        )?
+        .0)
+    }
+}
+
+/// The tick arrays holding `tick_lower` and `tick_upper` of the pool, with the rent to create
+/// those for which `tick_array_exists` is false
+pub fn tick_array_open_costs(
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    tick_lower: i32,
+    tick_upper: i32,
+    tick_array_exists: impl Fn(&Pubkey) -> bool,
+    tick_array_rent: u64,
+) -> Result<OpenCostEstimate> {
+    let mut start_indexes = vec![TickArrayState::get_array_start_index(
+        tick_lower,
+        pool_state.tick_spacing,
+    )];
+    let upper_start_index =
+        TickArrayState::get_array_start_index(tick_upper, pool_state.tick_spacing);
+    if upper_start_index != start_indexes[0] {
+        start_indexes.push(upper_start_index);
+    }
+    let mut estimate = OpenCostEstimate {
+        tick_arrays: Vec::new(),
+        rent: 0,
+    };
+    for start_index in start_indexes {
+        let address = tick_array_key(program_id, pool_id, start_index);
+        let exists = tick_array_exists(&address);
+        let rent = if exists { 0 } else { tick_array_rent };
+        estimate.rent += rent;
+        estimate.tick_arrays.push(TickArrayOpenCost {
+            start_index,
+            address,
+            exists,
+            bitmap_initialized: tick_array_bitmap_initialized(
+                pool_state,
+                tickarray_bitmap_extension,
+                start_index,
+            )?,
+            rent,
+        });
+    }
+    Ok(estimate)
+}
+
+/// Load the pool and the tick arrays of the range and estimate the rent of the tick arrays
+/// opening a position from `tick_lower` to `tick_upper` creates
+pub fn estimate_open_cost(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Result<OpenCostEstimate> {
+    let tickarray_bitmap_extension_key = Pubkey::find_program_address(
+        &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+        program_id,
+    )
+    .0;
+    let rsps = rpc_client.get_multiple_accounts(&[*pool_id, tickarray_bitmap_extension_key])?;
+    let pool_state = deserialize_anchor_account::<PoolState>(
+        rsps[0]
+            .as_ref()
+            .ok_or(anyhow::format_err!("pool {} not found", pool_id))?,
+    )?;
+    let tickarray_bitmap_extension =
+        deserialize_anchor_account::<TickArrayBitmapExtension>(rsps[1].as_ref().ok_or(
+            anyhow::format_err!("pool {} has no bitmap extension", pool_id),
+        )?)?;
+    let tick_array_keys: Vec<Pubkey> = [tick_lower, tick_upper]
+        .iter()
+        .map(|tick| {
+            tick_array_key(
+                program_id,
+                pool_id,
+                TickArrayState::get_array_start_index(*tick, pool_state.tick_spacing),
+            )
+        })
+        .collect();
+    let existing: Vec<Pubkey> = tick_array_keys
+        .iter()
+        .zip(rpc_client.get_multiple_accounts(&tick_array_keys)?)
+        .filter(|(_, account)| account.is_some())
+        .map(|(key, _)| *key)
+        .collect();
+    let tick_array_rent = rpc_client.get_minimum_balance_for_rent_exemption(TickArrayState::LEN)?;
+    tick_array_open_costs(
+        program_id,
+        pool_id,
+        &pool_state,
+        &tickarray_bitmap_extension,
+        tick_lower,
+        tick_upper,
+        |key| existing.contains(key),
+        tick_array_rent,
+    )
+}
+
+/// An account create_pool creates and the rent the creator pays for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CreatePoolAccount {
+    pub name: &'static str,
+    pub address: Pubkey,
+    pub space: usize,
+    pub rent: u64,
+}
+
+/// What create_pool does for a pair of mints, a config and a price, without sending it
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreatePoolEstimate {
+    pub amm_config: Pubkey,
+    pub pool_id: Pubkey,
+    pub mint0: Pubkey,
+    pub mint1: Pubkey,
+    pub token_program_0: Pubkey,
+    pub token_program_1: Pubkey,
+    /// The price of token_1 in token_0 the pool starts at
+    pub pool_price: f64,
     pub sqrt_price_x64: u128,
-    // the tick associated with the current price
     pub tick: i32,
-    // the current liquidity in range
-    pub liquidity: u128,
-}
-#[derive(Default)]
-struct StepComputations {
-    // the price at the beginning of the step
-    sqrt_price_start_x64: u128,
-    // the next tick to swap to from the current tick in the swap direction
-    tick_next: i32,
-    // whether tick_next is initialized or not
-    initialized: bool,
-    // sqrt(price) for the next tick (1/0)
-    sqrt_price_next_x64: u128,
-    // how much is being swapped in in this step
-    amount_in: u64,
-    // how much is being swapped out
-    amount_out: u64,
-    // how much fee is being paid in
-    fee_amount: u64,
+    pub accounts: Vec<CreatePoolAccount>,
+    pub rent: u64,
+    pub warnings: Vec<String>,
+}
+
+impl CreatePoolEstimate {
+    pub fn report(&self) -> String {
+        let mut report = format!(
+            "amm_config:{}, pool_id:{}\nmint0:{} ({}), mint1:{} ({})\nprice:{}, sqrt_price_x64:{}, tick:{}\n",
+            self.amm_config,
+            self.pool_id,
+            self.mint0,
+            self.token_program_0,
+            self.mint1,
+            self.token_program_1,
+            self.pool_price,
+            self.sqrt_price_x64,
+            self.tick
+        );
+        for account in self.accounts.iter() {
+            report.push_str(&format!(
+                "{}:{}, space:{}, rent:{}\n",
+                account.name, account.address, account.space, account.rent
+            ));
+        }
+        report.push_str(&format!("rent:{} lamports", self.rent));
+        for warning in self.warnings.iter() {
+            report.push_str(&format!("\nwarning: {}", warning));
+        }
+        report
+    }
+}
+
+/// What the creator of a pool should know before sending create_pool
+pub fn create_pool_warnings(
+    pair: &CanonicalPair,
+    price: f64,
+    decimals_0: u8,
+    decimals_1: u8,
+    amm_config: &AmmConfig,
+    pool_exists: bool,
+) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if pool_exists {
+        warnings.push("the pool already exists, create_pool would fail".to_string());
+    }
+    if pair.inverted {
+        warnings.push(format!(
+            "the mints are ordered by address, the pool is ({}, {}) and the price {} becomes {}",
+            pair.mint0,
+            pair.mint1,
+            price,
+            pair.pool_price(price)
+        ));
+    }
+    if decimals_0 != decimals_1 {
+        warnings.push(format!(
+            "mint0 has {} decimals and mint1 {}, the price is of whole tokens",
+            decimals_0, decimals_1
+        ));
+    }
+    if amm_config.max_initial_tick_deviation > 0 {
+        warnings.push(format!(
+            "the config requires a price reference signed by its owner, the initial tick can be {} ticks away from it",
+            amm_config.max_initial_tick_deviation
+        ));
+    }
+    if amm_config.swap_delay_after_create > 0 {
+        warnings.push(format!(
+            "swaps open {} seconds after the creation",
+            amm_config.swap_delay_after_create
+        ));
+    }
+    if amm_config.pool_creation_fee > 0 {
+        warnings.push(format!(
+            "the creator pays a creation fee of {} lamports to the fund owner {}",
+            amm_config.pool_creation_fee, amm_config.fund_owner
+        ));
+    }
+    warnings
+}
+
+/// The size of a vault of the mint, with the account extensions the mint requires
+fn token_vault_space(mint_data: &[u8]) -> Result<usize> {
+    let mint = StateWithExtensions::<Mint>::unpack(mint_data)?;
+    let account_extensions =
+        ExtensionType::get_required_init_account_extensions(&mint.get_extension_types()?);
+    Ok(ExtensionType::try_calculate_account_len::<
+        spl_token_2022::state::Account,
+    >(&account_extensions)?)
+}
+
+/// Derive the accounts create_pool creates for the mints in any order and the config at
+/// `config_index`, and estimate their rent and the initial tick at `price`, the price of
+/// `mint_b` in `mint_a`
+pub fn estimate_create_pool(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    config_index: u16,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    price: f64,
+) -> Result<CreatePoolEstimate> {
+    let pair = CanonicalPair::new(mint_a, mint_b);
+    let pool_price = pair.pool_price(price);
+    let amm_config_key = Pubkey::find_program_address(
+        &[AMM_CONFIG_SEED.as_bytes(), &config_index.to_be_bytes()],
+        program_id,
+    )
+    .0;
+    let pool_id = Pubkey::find_program_address(
+        &[
+            POOL_SEED.as_bytes(),
+            amm_config_key.as_ref(),
+            pair.mint0.as_ref(),
+            pair.mint1.as_ref(),
+        ],
+        program_id,
+    )
+    .0;
+    let rsps =
+        rpc_client.get_multiple_accounts(&[amm_config_key, pair.mint0, pair.mint1, pool_id])?;
+    let amm_config = deserialize_anchor_account::<AmmConfig>(rsps[0].as_ref().ok_or(
+        anyhow::format_err!("amm config {} not found", amm_config_key),
+    )?)?;
+    let mint_0_account = rsps[1]
+        .as_ref()
+        .ok_or(anyhow::format_err!("mint {} not found", pair.mint0))?;
+    let mint_1_account = rsps[2]
+        .as_ref()
+        .ok_or(anyhow::format_err!("mint {} not found", pair.mint1))?;
+    let decimals_0 = StateWithExtensions::<Mint>::unpack(&mint_0_account.data)?
+        .base
+        .decimals;
+    let decimals_1 = StateWithExtensions::<Mint>::unpack(&mint_1_account.data)?
+        .base
+        .decimals;
+
+    let sqrt_price_x64 = price_to_sqrt_price_x64(pool_price, decimals_0, decimals_1);
+    let tick = tick_math::get_tick_at_sqrt_price(sqrt_price_x64)
+        .map_err(|_| anyhow::format_err!("price {} is out of the tick range", pool_price))?;
+
+    let pool_account = |seed: &str, key: &Pubkey| {
+        Pubkey::find_program_address(
+            &[seed.as_bytes(), pool_id.as_ref(), key.as_ref()],
+            program_id,
+        )
+        .0
+    };
+    let observation_key =
+        Pubkey::find_program_address(&[OBSERVATION_SEED.as_bytes(), pool_id.as_ref()], program_id)
+            .0;
+    let tick_array_bitmap_key = Pubkey::find_program_address(
+        &[POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(), pool_id.as_ref()],
+        program_id,
+    )
+    .0;
+    let mut accounts = Vec::new();
+    for (name, address, space) in [
+        ("pool_state", pool_id, PoolState::LEN),
+        (
+            "token_vault_0",
+            pool_account(POOL_VAULT_SEED, &pair.mint0),
+            token_vault_space(&mint_0_account.data)?,
+        ),
+        (
+            "token_vault_1",
+            pool_account(POOL_VAULT_SEED, &pair.mint1),
+            token_vault_space(&mint_1_account.data)?,
+        ),
+        ("observation_state", observation_key, ObservationState::LEN),
+        (
+            "tick_array_bitmap",
+            tick_array_bitmap_key,
+            TickArrayBitmapExtension::LEN,
+        ),
+    ] {
+        accounts.push(CreatePoolAccount {
+            name,
+            address,
+            space,
+            rent: rpc_client.get_minimum_balance_for_rent_exemption(space)?,
+        });
+    }
+
+    Ok(CreatePoolEstimate {
+        amm_config: amm_config_key,
+        pool_id,
+        mint0: pair.mint0,
+        mint1: pair.mint1,
+        token_program_0: mint_0_account.owner,
+        token_program_1: mint_1_account.owner,
+        pool_price,
+        sqrt_price_x64,
+        tick,
+        rent: accounts.iter().map(|account| account.rent).sum(),
+        accounts,
+        warnings: create_pool_warnings(
+            &pair,
+            price,
+            decimals_0,
+            decimals_1,
+            &amm_config,
+            rsps[3].is_some(),
+        ),
+    })
+}
+
+/// The cumulative taker flow of a pool by direction. Takers sell token_0 when swapping it for
+/// token_1 and buy token_0 when swapping token_1 for it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolOrderFlow {
+    pub sell_count: u64,
+    /// token_0 sold by the takers
+    pub sell_amount_0: u128,
+    /// token_1 the sellers received
+    pub sell_amount_1: u128,
+    pub buy_count: u64,
+    /// token_0 bought by the takers
+    pub buy_amount_0: u128,
+    /// token_1 the buyers paid
+    pub buy_amount_1: u128,
+}
+
+impl PoolOrderFlow {
+    pub fn new(pool_state: &PoolState) -> Self {
+        Self {
+            sell_count: pool_state.swap_count_0_for_1,
+            sell_amount_0: pool_state.swap_in_amount_token_0,
+            sell_amount_1: pool_state.swap_out_amount_token_1,
+            buy_count: pool_state.swap_count_1_for_0,
+            buy_amount_0: pool_state.swap_out_amount_token_0,
+            buy_amount_1: pool_state.swap_in_amount_token_1,
+        }
+    }
+
+    /// The buy minus the sell volume over the total volume, both in token_1, from -1 when the
+    /// takers only sold to 1 when they only bought. None before the first swap.
+    pub fn imbalance(&self) -> Option<f64> {
+        let total = self.buy_amount_1 as f64 + self.sell_amount_1 as f64;
+        if total == 0.0 {
+            return None;
+        }
+        Some((self.buy_amount_1 as f64 - self.sell_amount_1 as f64) / total)
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "sells:{}, token_0 sold:{}, token_1 received:{}\nbuys:{}, token_0 bought:{}, token_1 paid:{}\nimbalance:{}",
+            self.sell_count,
+            self.sell_amount_0,
+            self.sell_amount_1,
+            self.buy_count,
+            self.buy_amount_0,
+            self.buy_amount_1,
+            self.imbalance()
+                .map_or("-".to_string(), |imbalance| format!("{:.4}", imbalance))
+        )
+    }
+}
+
+/// A unix timestamp as a UTC date, e.g. `2024-03-01 12:00:00 UTC`
+pub fn format_unix_timestamp(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64;
+    let seconds = timestamp % 86400;
+    // civil date of the days since 1970-01-01
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// The unix timestamp of midnight UTC of a `YYYY-MM-DD` date
+pub fn parse_utc_date(date: &str) -> Result<u64> {
+    let parts: Vec<&str> = date.trim().split('-').collect();
+    let (year, month, day): (i64, i64, i64) = match parts.as_slice() {
+        [year, month, day] => (year.parse()?, month.parse()?, day.parse()?),
+        _ => return Err(anyhow::format_err!("{} is not a YYYY-MM-DD date", date)),
+    };
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || year < 1970 {
+        return Err(anyhow::format_err!("{} is not a YYYY-MM-DD date", date));
+    }
+    // days since 1970-01-01 of the civil date
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Ok(days as u64 * 86400)
+}
+
+/// The schedule and funding of a reward slot of a pool, amounts in the reward token's raw units
+#[derive(Debug, Clone, PartialEq)]
+pub struct RewardSchedule {
+    pub index: usize,
+    pub reward_state: u8,
+    pub token_mint: Pubkey,
+    pub token_vault: Pubkey,
+    pub authority: Pubkey,
+    pub decimals: u8,
+    pub vault_balance: u64,
+    pub open_time: u64,
+    pub end_time: u64,
+    pub last_update_time: u64,
+    pub emissions_per_second_x64: u128,
+    pub emitted: u64,
+    pub claimed: u64,
+}
+
+impl RewardSchedule {
+    pub fn new(index: usize, reward_info: &RewardInfo, vault_balance: u64, decimals: u8) -> Self {
+        Self {
+            index,
+            reward_state: reward_info.reward_state,
+            token_mint: reward_info.token_mint,
+            token_vault: reward_info.token_vault,
+            authority: reward_info.authority,
+            decimals,
+            vault_balance,
+            open_time: reward_info.open_time,
+            end_time: reward_info.end_time,
+            last_update_time: reward_info.last_update_time,
+            emissions_per_second_x64: reward_info.emissions_per_second_x64,
+            emitted: reward_info.reward_total_emissioned,
+            claimed: reward_info.reward_claimed,
+        }
+    }
+
+    fn ui_amount(&self, amount: f64) -> f64 {
+        amount / 10f64.powi(self.decimals as i32)
+    }
+
+    /// The tokens emitted per day, in the reward token's ui units
+    pub fn emission_per_day(&self) -> f64 {
+        self.ui_amount(self.emissions_per_second_x64 as f64 / fixed_point_64::Q64 as f64 * 86400.0)
+    }
+
+    /// The vault balance not owed to positions yet, left to fund the emissions to come
+    pub fn unallocated_balance(&self) -> u64 {
+        self.vault_balance
+            .saturating_sub(self.emitted.saturating_sub(self.claimed))
+    }
+
+    /// When the vault runs out at the current emission rate, `None` if nothing is emitted. A
+    /// depletion at or after `end_time` means the reward is funded to its end.
+    pub fn depletion_time(&self) -> Option<u64> {
+        if self.emissions_per_second_x64 == 0 {
+            return None;
+        }
+        let seconds = (U256::from(self.unallocated_balance()) << fixed_point_64::RESOLUTION)
+            / U256::from(self.emissions_per_second_x64);
+        let start = self.last_update_time.max(self.open_time);
+        let seconds = if seconds > U256::from(u64::MAX) {
+            u64::MAX
+        } else {
+            seconds.as_u64()
+        };
+        Some(start.saturating_add(seconds))
+    }
+
+    pub fn report(&self) -> String {
+        let state = match self.reward_state {
+            0 => "uninitialized",
+            1 => "initialized",
+            2 => "opening",
+            3 => "ended",
+            4 => "consolidated",
+            _ => "unknown",
+        };
+        let depletion = match self.depletion_time() {
+            None => "-".to_string(),
+            Some(time) if time >= self.end_time => "funded through the end time".to_string(),
+            Some(time) => format!("{}, before the end time", format_unix_timestamp(time)),
+        };
+        format!(
+            "reward {}: {}\n  mint:{}\n  vault:{}, balance:{}\n  authority:{}\n  open:{}\n  end:{}\n  last_update:{}\n  emission per day:{}\n  emitted:{}, claimed:{}, unclaimed:{}\n  depletion:{}",
+            self.index,
+            state,
+            self.token_mint,
+            self.token_vault,
+            self.ui_amount(self.vault_balance as f64),
+            self.authority,
+            format_unix_timestamp(self.open_time),
+            format_unix_timestamp(self.end_time),
+            format_unix_timestamp(self.last_update_time),
+            self.emission_per_day(),
+            self.ui_amount(self.emitted as f64),
+            self.ui_amount(self.claimed as f64),
+            self.ui_amount(self.emitted.saturating_sub(self.claimed) as f64),
+            depletion
+        )
+    }
+}
+
+/// One claim of a reward merkle snapshot, keys and hashes are encoded in base58 and hex
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardMerkleClaim {
+    pub claimant: String,
+    pub amount: u64,
+    pub proof: Vec<String>,
+}
+
+/// The owed rewards of an ended reward, written by `gen-reward-merkle`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RewardMerkleSnapshot {
+    pub pool_id: String,
+    pub reward_index: u8,
+    pub merkle_root: String,
+    pub total_amount: u64,
+    /// The reward claimed of the pool when the owed rewards were read, the root is rejected if
+    /// positions collected since
+    pub reward_claimed: u64,
+    pub claims: Vec<RewardMerkleClaim>,
+}
+
+/// Builds the merkle tree of the claims, returns the root and the proof of every claim.
+/// A node without sibling is promoted to the next level unchanged.
+pub fn build_reward_merkle_tree(claims: &[(Pubkey, u64)]) -> ([u8; 32], Vec<Vec<[u8; 32]>>) {
+    let mut layer: Vec<[u8; 32]> = claims
+        .iter()
+        .map(|(claimant, amount)| RewardMerkleState::leaf(claimant, *amount))
+        .collect();
+    let mut proofs = vec![Vec::new(); claims.len()];
+    let mut positions: Vec<usize> = (0..claims.len()).collect();
+    if layer.is_empty() {
+        return ([0u8; 32], proofs);
+    }
+    while layer.len() > 1 {
+        for (proof, position) in proofs.iter_mut().zip(positions.iter_mut()) {
+            let sibling = *position ^ 1;
+            if sibling < layer.len() {
+                proof.push(layer[sibling]);
+            }
+            *position /= 2;
+        }
+        layer = layer
+            .chunks(2)
+            .map(|pair| {
+                if pair.len() == 2 {
+                    RewardMerkleState::hash_pair(&pair[0], &pair[1])
+                } else {
+                    pair[0]
+                }
+            })
+            .collect();
+    }
+    (layer[0], proofs)
+}
+
+/// Error of quoting a swap
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteError {
+    /// The pool has no initialized tick array in the swap direction
+    NoLiquidity,
+    Compute(&'static str),
+    /// The swap math of the program failed
+    Program(String),
+}
+
+impl std::fmt::Display for QuoteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuoteError::NoLiquidity => write!(f, "the pool has no liquidity in the swap direction"),
+            QuoteError::Compute(err) => write!(f, "{}", err),
+            QuoteError::Program(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for QuoteError {}
+
+impl From<&'static str> for QuoteError {
+    fn from(err: &'static str) -> Self {
+        QuoteError::Compute(err)
+    }
+}
+
+impl From<anchor_lang::error::Error> for QuoteError {
+    fn from(err: anchor_lang::error::Error) -> Self {
+        if err == raydium_amm_v3::error::ErrorCode::InsufficientLiquidityForDirection.into() {
+            QuoteError::NoLiquidity
+        } else {
+            QuoteError::Program(err.to_string())
+        }
+    }
+}
+
+/// The first initialized tick array in the swap direction, `QuoteError::NoLiquidity` if there is none
+pub fn get_first_initialized_tick_array(
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    zero_for_one: bool,
+) -> Result<(bool, i32), QuoteError> {
+    pool_state
+        .get_first_initialized_tick_array(&Some(*tickarray_bitmap_extension), zero_for_one)
+        .map_err(|err| {
+            if err == raydium_amm_v3::error::ErrorCode::InsufficientLiquidityForDirection.into() {
+                QuoteError::NoLiquidity
+            } else {
+                QuoteError::Compute("failed to find the first initialized tick array")
+            }
+        })
 }
 
 pub fn get_out_put_amount_and_remaining_accounts(
@@ -316,211 +1144,1346 @@ pub fn get_out_put_amount_and_remaining_accounts(
     pool_state: &PoolState,
     tickarray_bitmap_extension: &TickArrayBitmapExtension,
     tick_arrays: &mut VecDeque<TickArrayState>,
-) -> Result<(u64, VecDeque<i32>), &'static str> {
-    let (is_pool_current_tick_array, current_vaild_tick_array_start_index) = pool_state
-        .get_first_initialized_tick_array(&Some(*tickarray_bitmap_extension), zero_for_one)
-        .unwrap();
-
-    let (amount_calculated, tick_array_start_index_vec) = swap_compute(
+) -> Result<(u64, VecDeque<i32>), QuoteError> {
+    let simulation = compute_swap_quote(
+        input_amount,
+        sqrt_price_limit_x64,
         zero_for_one,
         is_base_input,
-        is_pool_current_tick_array,
-        pool_config.trade_fee_rate,
-        input_amount,
-        current_vaild_tick_array_start_index,
-        sqrt_price_limit_x64.unwrap_or(0),
+        pool_config,
         pool_state,
         tickarray_bitmap_extension,
         tick_arrays,
     )?;
-    println!("tick_array_start_index:{:?}", tick_array_start_index_vec);
+    debug!(
+        "tick_array_start_index:{:?}",
+        simulation.tick_array_start_indexes
+    );
 
-    Ok((amount_calculated, tick_array_start_index_vec))
+    Ok((
+        simulation.amount_calculated(is_base_input),
+        simulation.tick_array_start_indexes,
+    ))
 }
 
-fn swap_compute(
+/// Quote a swap without side effects, the tick arrays are not consumed so the same accounts can
+/// be quoted again, from any thread
+pub fn quote_swap(
+    input_amount: u64,
+    sqrt_price_limit_x64: Option<u128>,
     zero_for_one: bool,
     is_base_input: bool,
-    is_pool_current_tick_array: bool,
-    fee: u32,
-    amount_specified: u64,
-    current_vaild_tick_array_start_index: i32,
-    sqrt_price_limit_x64: u128,
+    pool_config: &AmmConfig,
     pool_state: &PoolState,
     tickarray_bitmap_extension: &TickArrayBitmapExtension,
-    tick_arrays: &mut VecDeque<TickArrayState>,
-) -> Result<(u64, VecDeque<i32>), &'static str> {
-    if amount_specified == 0 {
-        return Result::Err("amountSpecified must not be 0");
-    }
-    let sqrt_price_limit_x64 = if sqrt_price_limit_x64 == 0 {
-        if zero_for_one {
-            tick_math::MIN_SQRT_PRICE_X64 + 1
+    tick_arrays: &VecDeque<TickArrayState>,
+) -> Result<(u64, VecDeque<i32>), QuoteError> {
+    let simulation = simulate_swap(
+        input_amount,
+        sqrt_price_limit_x64,
+        zero_for_one,
+        is_base_input,
+        pool_config,
+        pool_state,
+        tickarray_bitmap_extension,
+        tick_arrays,
+    )?;
+    Ok((
+        simulation.amount_calculated(is_base_input),
+        simulation.tick_array_start_indexes,
+    ))
+}
+
+/// Off-chain outcome of a swap, computed with the swap math of the program from loaded accounts
+#[derive(Debug, Clone, PartialEq)]
+pub struct SwapSimulation {
+    pub zero_for_one: bool,
+    /// Amount of the input token paid into the pool, the trade fee included
+    pub amount_in: u64,
+    /// Amount of the output token paid out of the pool
+    pub amount_out: u64,
+    /// Trade fee charged on the input token, split into the protocol, fund and lp shares
+    pub fee_amount: u64,
+    pub protocol_fee: u64,
+    pub fund_fee: u64,
+    pub lp_fee: u64,
+    pub sqrt_price_before_x64: u128,
+    pub sqrt_price_after_x64: u128,
+    pub tick_after: i32,
+    pub tick_array_start_indexes: VecDeque<i32>,
+}
+
+impl SwapSimulation {
+    /// The amount the swap computes, the output of an exact input swap or the input of an exact
+    /// output one
+    pub fn amount_calculated(&self, is_base_input: bool) -> u64 {
+        if is_base_input {
+            self.amount_out
         } else {
-            tick_math::MAX_SQRT_PRICE_X64 - 1
+            self.amount_in
         }
+    }
+
+    /// Relative move of the pool price caused by the swap
+    pub fn price_impact(&self) -> f64 {
+        let ratio =
+            from_x64_price(self.sqrt_price_after_x64) / from_x64_price(self.sqrt_price_before_x64);
+        (ratio * ratio - 1.0).abs()
+    }
+}
+
+/// One line summary of a simulated swap, prices are of token 0 in token 1
+pub fn report_swap_simulation(simulation: &SwapSimulation, pool_state: &PoolState) -> String {
+    format!(
+        "amount_in:{}, amount_out:{}, fee:{} (lp:{}, protocol:{}, fund:{}), price:{} -> {}, price_impact:{:.4}%, tick_after:{}",
+        simulation.amount_in,
+        simulation.amount_out,
+        simulation.fee_amount,
+        simulation.lp_fee,
+        simulation.protocol_fee,
+        simulation.fund_fee,
+        sqrt_price_x64_to_price(
+            simulation.sqrt_price_before_x64,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1
+        ),
+        sqrt_price_x64_to_price(
+            simulation.sqrt_price_after_x64,
+            pool_state.mint_decimals_0,
+            pool_state.mint_decimals_1
+        ),
+        simulation.price_impact() * 100.0,
+        simulation.tick_after
+    )
+}
+
+/// Simulate a swap off-chain without building a transaction, the tick arrays are not consumed
+pub fn simulate_swap(
+    input_amount: u64,
+    sqrt_price_limit_x64: Option<u128>,
+    zero_for_one: bool,
+    is_base_input: bool,
+    pool_config: &AmmConfig,
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    tick_arrays: &VecDeque<TickArrayState>,
+) -> Result<SwapSimulation, QuoteError> {
+    compute_swap_quote(
+        input_amount,
+        sqrt_price_limit_x64,
+        zero_for_one,
+        is_base_input,
+        pool_config,
+        pool_state,
+        tickarray_bitmap_extension,
+        &mut tick_arrays.clone(),
+    )
+}
+
+fn compute_swap_quote(
+    input_amount: u64,
+    sqrt_price_limit_x64: Option<u128>,
+    zero_for_one: bool,
+    is_base_input: bool,
+    pool_config: &AmmConfig,
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    tick_arrays: &mut VecDeque<TickArrayState>,
+) -> Result<SwapSimulation, QuoteError> {
+    let sqrt_price_limit_x64 = match sqrt_price_limit_x64 {
+        Some(sqrt_price_limit_x64) if sqrt_price_limit_x64 != 0 => sqrt_price_limit_x64,
+        _ if zero_for_one => tick_math::MIN_SQRT_PRICE_X64 + 1,
+        _ => tick_math::MAX_SQRT_PRICE_X64 - 1,
+    };
+    // the crossed ticks are only written to the loaded copies of the tick arrays, the rewards
+    // they record don't change the amounts
+    let reward_infos = pool_state.reward_infos;
+    let state = swap_quote::compute_swap(
+        pool_config,
+        pool_state,
+        &mut tick_arrays.iter_mut().collect::<VecDeque<_>>(),
+        &Some(*tickarray_bitmap_extension),
+        &reward_infos,
+        input_amount,
+        sqrt_price_limit_x64,
+        zero_for_one,
+        is_base_input,
+        0,
+    )?;
+
+    let amount_swapped = input_amount - state.amount_specified_remaining;
+    let (amount_in, amount_out) = if is_base_input {
+        (amount_swapped, state.amount_calculated)
     } else {
-        sqrt_price_limit_x64
+        (state.amount_calculated, amount_swapped)
     };
-    if zero_for_one {
-        if sqrt_price_limit_x64 < tick_math::MIN_SQRT_PRICE_X64 {
-            return Result::Err("sqrt_price_limit_x64 must greater than MIN_SQRT_PRICE_X64");
-        }
-        if sqrt_price_limit_x64 >= pool_state.sqrt_price_x64 {
-            return Result::Err("sqrt_price_limit_x64 must smaller than current");
+    Ok(SwapSimulation {
+        zero_for_one,
+        amount_in,
+        amount_out,
+        fee_amount: state.fee_amount + state.protocol_fee + state.fund_fee,
+        protocol_fee: state.protocol_fee,
+        fund_fee: state.fund_fee,
+        lp_fee: state.fee_amount,
+        sqrt_price_before_x64: pool_state.sqrt_price_x64,
+        sqrt_price_after_x64: state.sqrt_price_x64,
+        tick_after: state.tick,
+        tick_array_start_indexes: state.tick_array_start_indexes.into_iter().collect(),
+    })
+}
+
+/// The shortfall a swap returns when its output vault can't pay it, `None` if the return data is
+/// missing or not from the program
+pub fn decode_output_vault_shortfall(
+    return_data: &Option<solana_transaction_status::UiTransactionReturnData>,
+    program_id: &Pubkey,
+) -> Option<raydium_amm_v3::instructions::OutputVaultShortfall> {
+    let return_data = return_data.as_ref()?;
+    if return_data.program_id != program_id.to_string() {
+        return None;
+    }
+    let data = anchor_lang::__private::base64::decode(&return_data.data.0).ok()?;
+    raydium_amm_v3::instructions::OutputVaultShortfall::try_from_slice(&data).ok()
+}
+
+/// The position amounts returned by a simulated `get_position_amounts`, `None` if the return
+/// data is missing or not from the program
+pub fn decode_position_amounts(
+    return_data: &Option<solana_transaction_status::UiTransactionReturnData>,
+    program_id: &Pubkey,
+) -> Option<raydium_amm_v3::instructions::PositionAmounts> {
+    let return_data = return_data.as_ref()?;
+    if return_data.program_id != program_id.to_string() {
+        return None;
+    }
+    let data = anchor_lang::__private::base64::decode(&return_data.data.0).ok()?;
+    raydium_amm_v3::instructions::PositionAmounts::try_from_slice(&data).ok()
+}
+
+/// The amount a swap returns on success: the output amount of `swap_router_base_in` or of an
+/// exact input swap, the input amount paid by an exact output swap. `None` if the return data is
+/// missing, not from the program or not an amount, like the shortfall of a failed swap
+pub fn decode_swap_amount(
+    return_data: &Option<solana_transaction_status::UiTransactionReturnData>,
+    program_id: &Pubkey,
+) -> Option<u64> {
+    let return_data = return_data.as_ref()?;
+    if return_data.program_id != program_id.to_string() {
+        return None;
+    }
+    let data = anchor_lang::__private::base64::decode(&return_data.data.0).ok()?;
+    u64::try_from_slice(&data).ok()
+}
+
+/// The largest swap the output vault can pay: the output amount of an exact output swap or the
+/// input amount of an exact input swap. One below the vault balance, draining the vault freezes
+/// the pool.
+pub fn fillable_swap_amount(
+    shortfall: &raydium_amm_v3::instructions::OutputVaultShortfall,
+    base_in: bool,
+    zero_for_one: bool,
+    pool_config: &AmmConfig,
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    tick_arrays: &VecDeque<TickArrayState>,
+) -> Result<u64, QuoteError> {
+    let max_amount_out = shortfall.max_amount_out.saturating_sub(1);
+    if !base_in || max_amount_out == 0 {
+        return Ok(max_amount_out);
+    }
+    let (amount_in, _) = quote_swap(
+        max_amount_out,
+        None,
+        zero_for_one,
+        false,
+        pool_config,
+        pool_state,
+        tickarray_bitmap_extension,
+        tick_arrays,
+    )?;
+    Ok(amount_in)
+}
+
+/// Explain why the swap can't be paid and the size that can
+pub fn report_output_vault_shortfall(
+    shortfall: &raydium_amm_v3::instructions::OutputVaultShortfall,
+    base_in: bool,
+    zero_for_one: bool,
+    pool_config: &AmmConfig,
+    pool_state: &PoolState,
+    tickarray_bitmap_extension: &TickArrayBitmapExtension,
+    tick_arrays: &VecDeque<TickArrayState>,
+) -> String {
+    let suggestion = match fillable_swap_amount(
+        shortfall,
+        base_in,
+        zero_for_one,
+        pool_config,
+        pool_state,
+        tickarray_bitmap_extension,
+        tick_arrays,
+    ) {
+        Ok(0) => "the output side of the pool is empty".to_string(),
+        Ok(amount) => format!(
+            "retry with an {} amount of at most {}",
+            if base_in { "input" } else { "output" },
+            amount
+        ),
+        Err(err) => format!("no fillable size: {}", err),
+    };
+    format!(
+        "the output vault holds {}, the swap pays out {}, {}",
+        shortfall.max_amount_out, shortfall.amount_out, suggestion
+    )
+}
+
+/// Fees the position can collect now, the owed fees plus the accrual since its last update
+pub fn position_claimable_fees(
+    pool_state: &PoolState,
+    position: &PersonalPositionState,
+    tick_lower: &TickState,
+    tick_upper: &TickState,
+) -> (u64, u64) {
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = get_fee_growth_inside(
+        tick_lower,
+        tick_upper,
+        pool_state.tick_current,
+        pool_state.fee_growth_global_0_x64,
+        pool_state.fee_growth_global_1_x64,
+    )
+    .unwrap();
+    (
+        raydium_amm_v3::instructions::calculate_latest_token_fees(
+            position.token_fees_owed_0,
+            position.fee_growth_inside_0_last_x64,
+            fee_growth_inside_0_x64,
+            position.liquidity,
+        ),
+        raydium_amm_v3::instructions::calculate_latest_token_fees(
+            position.token_fees_owed_1,
+            position.fee_growth_inside_1_last_x64,
+            fee_growth_inside_1_x64,
+            position.liquidity,
+        ),
+    )
+}
+
+/// Rewards the position can collect now by reward index, the owed rewards plus the accrual since
+/// its last update at the reward growths of the pool
+pub fn position_claimable_rewards(
+    pool_state: &PoolState,
+    position: &PersonalPositionState,
+    tick_lower: &TickState,
+    tick_upper: &TickState,
+) -> [u64; REWARD_NUM] {
+    let reward_growths_inside = get_reward_growths_inside(
+        tick_lower,
+        tick_upper,
+        pool_state.tick_current,
+        &pool_state.reward_infos,
+    )
+    .unwrap();
+    let mut rewards = [0u64; REWARD_NUM];
+    for (i, reward_info) in position.reward_infos.iter().enumerate() {
+        let reward_growth_delta =
+            reward_growths_inside[i].wrapping_sub(reward_info.growth_inside_last_x64);
+        let amount_owed_delta = U256::from(reward_growth_delta)
+            .mul_div_floor(
+                U256::from(position.liquidity),
+                U256::from(fixed_point_64::Q64),
+            )
+            .unwrap()
+            .to_underflow_u64();
+        rewards[i] = reward_info
+            .reward_amount_owed
+            .checked_add(amount_owed_delta)
+            .unwrap();
+    }
+    rewards
+}
+
+/// The remaining accounts collecting the rewards of a position by `decrease_liquidity_v2`: for
+/// every initialized reward in index order its vault, the recipient's token account and its mint.
+/// `reward_token_programs` holds the token program owning each reward mint.
+pub fn reward_remaining_accounts(
+    pool_state: &PoolState,
+    recipient: &Pubkey,
+    reward_token_programs: &std::collections::HashMap<Pubkey, Pubkey>,
+) -> Result<Vec<AccountMeta>> {
+    let mut accounts = Vec::new();
+    for reward_info in pool_state.reward_infos.iter() {
+        if !reward_info.initialized() {
+            continue;
         }
-    } else {
-        if sqrt_price_limit_x64 > tick_math::MAX_SQRT_PRICE_X64 {
-            return Result::Err("sqrt_price_limit_x64 must smaller than MAX_SQRT_PRICE_X64");
+        let token_program =
+            reward_token_programs
+                .get(&reward_info.token_mint)
+                .ok_or(anyhow::format_err!(
+                    "no token program of the reward mint {}",
+                    reward_info.token_mint
+                ))?;
+        accounts.push(AccountMeta::new(reward_info.token_vault, false));
+        accounts.push(AccountMeta::new(
+            spl_associated_token_account::get_associated_token_address_with_program_id(
+                recipient,
+                &reward_info.token_mint,
+                token_program,
+            ),
+            false,
+        ));
+        accounts.push(AccountMeta::new_readonly(reward_info.token_mint, false));
+    }
+    Ok(accounts)
+}
+
+/// Write the records as csv with a header line, one column per field sorted by name. Strings are
+/// written as is, nested values as json and missing or null fields as empty cells.
+pub fn serialize_csv<T: Serialize>(records: &[T]) -> Result<String> {
+    let mut rows = Vec::with_capacity(records.len());
+    for record in records {
+        match serde_json::to_value(record)? {
+            serde_json::Value::Object(fields) => rows.push(fields),
+            _ => return Err(anyhow::format_err!("only structs can be written as csv")),
         }
-        if sqrt_price_limit_x64 <= pool_state.sqrt_price_x64 {
-            return Result::Err("sqrt_price_limit_x64 must greater than current");
+    }
+    let columns: Vec<String> = match rows.first() {
+        Some(fields) => fields.keys().cloned().collect(),
+        None => return Ok(String::new()),
+    };
+    let escape = |cell: String| {
+        if cell.contains(|c| c == ',' || c == '"' || c == '\n' || c == '\r') {
+            format!("\"{}\"", cell.replace('"', "\"\""))
+        } else {
+            cell
         }
+    };
+
+    let mut csv = columns
+        .iter()
+        .cloned()
+        .map(escape)
+        .collect::<Vec<_>>()
+        .join(",");
+    csv.push('\n');
+    for fields in rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|column| match fields.get(column) {
+                Some(serde_json::Value::String(value)) => value.clone(),
+                Some(serde_json::Value::Null) | None => String::new(),
+                Some(value) => value.to_string(),
+            })
+            .map(escape)
+            .collect();
+        csv.push_str(&cells.join(","));
+        csv.push('\n');
     }
-    let mut tick_match_current_tick_array = is_pool_current_tick_array;
+    Ok(csv)
+}
 
-    let mut state = SwapState {
-        amount_specified_remaining: amount_specified,
-        amount_calculated: 0,
-        sqrt_price_x64: pool_state.sqrt_price_x64,
-        tick: pool_state.tick_current,
-        liquidity: pool_state.liquidity,
-    };
+/// The side of a range order. A sell of token_0 for token_1 sits above the market and holds
+/// token_0 only, a buy sits below and holds token_1 only.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RangeOrderSide {
+    Sell,
+    Buy,
+}
 
-    let mut tick_array_current = tick_arrays.pop_front().unwrap();
-    if tick_array_current.start_tick_index != current_vaild_tick_array_start_index {
-        return Result::Err("tick array start tick index does not match");
-    }
-    let mut tick_array_start_index_vec = VecDeque::new();
-    tick_array_start_index_vec.push_back(tick_array_current.start_tick_index);
-    let mut loop_count = 0;
-    // loop across ticks until input liquidity is consumed, or the limit price is reached
-    while state.amount_specified_remaining != 0
-        && state.sqrt_price_x64 != sqrt_price_limit_x64
-        && state.tick < tick_math::MAX_TICK
-        && state.tick > tick_math::MIN_TICK
-    {
-        if loop_count > 10 {
-            return Result::Err("loop_count limit");
-        }
-        let mut step = StepComputations::default();
-        step.sqrt_price_start_x64 = state.sqrt_price_x64;
-        // save the bitmap, and the tick account if it is initialized
-        let mut next_initialized_tick = if let Some(tick_state) = tick_array_current
-            .next_initialized_tick(state.tick, pool_state.tick_spacing, zero_for_one)
-            .unwrap()
-        {
-            Box::new(*tick_state)
+/// The one tick spacing wide range containing `order_tick`, it must be entirely on the side of
+/// the market the order sits on so that the position holds a single token
+pub fn range_order_ticks(
+    tick_current: i32,
+    order_tick: i32,
+    tick_spacing: u16,
+    side: RangeOrderSide,
+) -> Result<(i32, i32)> {
+    let tick_lower = tick_with_spacing(order_tick, tick_spacing.into());
+    let tick_upper = tick_lower + i32::from(tick_spacing);
+    if tick_lower < tick_math::MIN_TICK || tick_upper > tick_math::MAX_TICK {
+        return Err(anyhow::format_err!(
+            "tick {} is out of the tick range",
+            order_tick
+        ));
+    }
+    match side {
+        RangeOrderSide::Sell if tick_lower <= tick_current => Err(anyhow::format_err!(
+            "a sell order must be above the market, lower tick {} is not above tick {}",
+            tick_lower,
+            tick_current
+        )),
+        RangeOrderSide::Buy if tick_upper > tick_current => Err(anyhow::format_err!(
+            "a buy order must be below the market, upper tick {} is above tick {}",
+            tick_upper,
+            tick_current
+        )),
+        _ => Ok((tick_lower, tick_upper)),
+    }
+}
+
+/// The share of a range order already converted to the other token, from 0 to 1. Inside the
+/// range the token_1 held grows linearly with the sqrt price.
+pub fn range_order_fill(
+    sqrt_price_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    side: RangeOrderSide,
+) -> Result<f64> {
+    let sqrt_price_lower_x64 = tick_math::get_sqrt_price_at_tick(tick_lower)?;
+    let sqrt_price_upper_x64 = tick_math::get_sqrt_price_at_tick(tick_upper)?;
+    let sqrt_price_x64 = sqrt_price_x64.clamp(sqrt_price_lower_x64, sqrt_price_upper_x64);
+    let token_1_share = (sqrt_price_x64 - sqrt_price_lower_x64) as f64
+        / (sqrt_price_upper_x64 - sqrt_price_lower_x64) as f64;
+    Ok(match side {
+        RangeOrderSide::Sell => token_1_share,
+        RangeOrderSide::Buy => 1.0 - token_1_share,
+    })
+}
+
+#[cfg(test)]
+mod utils_test {
+    use super::*;
+
+    /// Mark the tick array starting at `start_index` initialized in the default pool bitmap
+    fn set_bitmap(pool_state: &mut PoolState, start_index: i32) {
+        let bit_pos =
+            (start_index / TickArrayState::tick_count(pool_state.tick_spacing) + 512) as usize;
+        let mut tick_array_bitmap = pool_state.tick_array_bitmap;
+        tick_array_bitmap[bit_pos / 64] |= 1 << (bit_pos % 64);
+        pool_state.tick_array_bitmap = tick_array_bitmap;
+    }
+
+    #[test]
+    fn tick_array_open_costs_test() {
+        let program_id = Pubkey::new_unique();
+        let pool_id = Pubkey::new_unique();
+        let mut pool_state = PoolState::default();
+        pool_state.tick_spacing = 10;
+        let tickarray_bitmap_extension = TickArrayBitmapExtension::default();
+        set_bitmap(&mut pool_state, -600);
+        let rent = 70_000_000;
+
+        // both ticks in one tick array, it exists
+        let estimate = tick_array_open_costs(
+            &program_id,
+            &pool_id,
+            &pool_state,
+            &tickarray_bitmap_extension,
+            -500,
+            -100,
+            |_| true,
+            rent,
+        )
+        .unwrap();
+        assert_eq!(estimate.tick_arrays.len(), 1);
+        assert_eq!(estimate.tick_arrays[0].start_index, -600);
+        assert!(estimate.tick_arrays[0].bitmap_initialized);
+        assert_eq!(estimate.rent, 0);
+
+        // the upper tick array is created
+        let lower_key = estimate.tick_arrays[0].address;
+        let estimate = tick_array_open_costs(
+            &program_id,
+            &pool_id,
+            &pool_state,
+            &tickarray_bitmap_extension,
+            -500,
+            100,
+            |key| *key == lower_key,
+            rent,
+        )
+        .unwrap();
+        assert_eq!(
+            estimate
+                .tick_arrays
+                .iter()
+                .map(|tick_array| (tick_array.start_index, tick_array.exists))
+                .collect::<Vec<_>>(),
+            vec![(-600, true), (0, false)]
+        );
+        assert!(!estimate.tick_arrays[1].bitmap_initialized);
+        assert_eq!(estimate.tick_arrays[1].rent, rent);
+        assert_eq!(estimate.rent, rent);
+
+        // neither exists
+        let estimate = tick_array_open_costs(
+            &program_id,
+            &pool_id,
+            &pool_state,
+            &tickarray_bitmap_extension,
+            -1200,
+            1200,
+            |_| false,
+            rent,
+        )
+        .unwrap();
+        assert_eq!(estimate.rent, 2 * rent);
+    }
+
+    #[test]
+    fn format_unix_timestamp_test() {
+        assert_eq!(format_unix_timestamp(0), "1970-01-01 00:00:00 UTC");
+        assert_eq!(format_unix_timestamp(951782400), "2000-02-29 00:00:00 UTC");
+        assert_eq!(format_unix_timestamp(1709294400), "2024-03-01 12:00:00 UTC");
+    }
+
+    #[test]
+    fn parse_utc_date_test() {
+        assert_eq!(parse_utc_date("1970-01-01").unwrap(), 0);
+        assert_eq!(parse_utc_date("2000-02-29").unwrap(), 951782400);
+        assert_eq!(parse_utc_date("2024-03-01").unwrap(), 1709251200);
+        assert!(parse_utc_date("2024-13-01").is_err());
+        assert!(parse_utc_date("2024/03/01").is_err());
+    }
+
+    #[test]
+    fn reward_depletion_time_test() {
+        let mut reward_info = RewardInfo::new(Pubkey::new_unique());
+        reward_info.open_time = 1000;
+        reward_info.end_time = 5000;
+        reward_info.last_update_time = 1200;
+        reward_info.emissions_per_second_x64 = fixed_point_64::Q64;
+        reward_info.reward_total_emissioned = 300;
+        reward_info.reward_claimed = 100;
+        // 200 owed to the positions, 800 left at one token a second
+        let schedule = RewardSchedule::new(0, &reward_info, 1000, 0);
+        assert_eq!(schedule.unallocated_balance(), 800);
+        assert_eq!(schedule.depletion_time(), Some(2000));
+        assert_eq!(schedule.emission_per_day(), 86400.0);
+
+        reward_info.emissions_per_second_x64 = 0;
+        let schedule = RewardSchedule::new(0, &reward_info, 1000, 0);
+        assert_eq!(schedule.depletion_time(), None);
+    }
+
+    #[test]
+    fn reward_remaining_accounts_test() {
+        let mut pool_state = PoolState::default();
+        let recipient = Pubkey::new_unique();
+        let mint_0 = Pubkey::new_unique();
+        let mint_2 = Pubkey::new_unique();
+        pool_state.reward_infos[0].token_mint = mint_0;
+        pool_state.reward_infos[0].token_vault = Pubkey::new_unique();
+        pool_state.reward_infos[2].token_mint = mint_2;
+        pool_state.reward_infos[2].token_vault = Pubkey::new_unique();
+        let mut reward_token_programs = std::collections::HashMap::new();
+        reward_token_programs.insert(mint_0, spl_token::id());
+        // the mint of the third reward is unknown
+        assert!(
+            reward_remaining_accounts(&pool_state, &recipient, &reward_token_programs).is_err()
+        );
+
+        reward_token_programs.insert(mint_2, spl_token_2022::id());
+        let accounts =
+            reward_remaining_accounts(&pool_state, &recipient, &reward_token_programs).unwrap();
+        // the uninitialized second reward has no group
+        let keys: Vec<Pubkey> = accounts.iter().map(|account| account.pubkey).collect();
+        assert_eq!(
+            keys,
+            vec![
+                pool_state.reward_infos[0].token_vault,
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &recipient,
+                    &mint_0,
+                    &spl_token::id()
+                ),
+                mint_0,
+                pool_state.reward_infos[2].token_vault,
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &recipient,
+                    &mint_2,
+                    &spl_token_2022::id()
+                ),
+                mint_2,
+            ]
+        );
+        assert!(accounts[0].is_writable && accounts[1].is_writable && !accounts[2].is_writable);
+    }
+
+    #[test]
+    fn create_pool_warnings_test() {
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+        let (low, high) = if mint_a < mint_b {
+            (mint_a, mint_b)
         } else {
-            if !tick_match_current_tick_array {
-                tick_match_current_tick_array = true;
-                Box::new(
-                    *tick_array_current
-                        .first_initialized_tick(zero_for_one)
-                        .unwrap(),
-                )
-            } else {
-                Box::new(TickState::default())
-            }
+            (mint_b, mint_a)
         };
-        if !next_initialized_tick.is_initialized() {
-            let current_vaild_tick_array_start_index = pool_state
-                .next_initialized_tick_array_start_index(
-                    &Some(*tickarray_bitmap_extension),
-                    current_vaild_tick_array_start_index,
-                    zero_for_one,
-                )
-                .unwrap();
-            tick_array_current = tick_arrays.pop_front().unwrap();
-            if current_vaild_tick_array_start_index.is_none() {
-                return Result::Err("tick array start tick index out of range limit");
-            }
-            if tick_array_current.start_tick_index != current_vaild_tick_array_start_index.unwrap()
-            {
-                return Result::Err("tick array start tick index does not match");
-            }
-            tick_array_start_index_vec.push_back(tick_array_current.start_tick_index);
-            let mut first_initialized_tick = tick_array_current
-                .first_initialized_tick(zero_for_one)
-                .unwrap();
+        let amm_config = AmmConfig::default();
+        assert!(create_pool_warnings(
+            &CanonicalPair::new(low, high),
+            2.0,
+            6,
+            6,
+            &amm_config,
+            false
+        )
+        .is_empty());
 
-            next_initialized_tick = Box::new(*first_initialized_tick.deref_mut());
-        }
-        step.tick_next = next_initialized_tick.tick;
-        step.initialized = next_initialized_tick.is_initialized();
-        if step.tick_next < MIN_TICK {
-            step.tick_next = MIN_TICK;
-        } else if step.tick_next > MAX_TICK {
-            step.tick_next = MAX_TICK;
+        let amm_config = AmmConfig {
+            max_initial_tick_deviation: 100,
+            ..Default::default()
+        };
+        let warnings =
+            create_pool_warnings(&CanonicalPair::new(high, low), 2.0, 6, 9, &amm_config, true);
+        assert_eq!(warnings.len(), 4);
+        assert!(warnings[0].contains("already exists"));
+        // the price is flipped with the mints
+        assert!(warnings[1].contains("becomes 0.5"));
+        assert!(warnings[2].contains("6 decimals and mint1 9"));
+        assert!(warnings[3].contains("100 ticks"));
+    }
+
+    #[test]
+    fn scan_backoff_test() {
+        use super::super::rpc::{RpcScanConfig, RpcScanner, MAX_SCAN_BACKOFF};
+        use std::time::Duration;
+
+        let scanner = RpcScanner::new(RpcScanConfig {
+            initial_backoff: Duration::from_millis(500),
+            ..Default::default()
+        });
+        assert_eq!(scanner.backoff(1), Duration::from_millis(500));
+        assert_eq!(scanner.backoff(2), Duration::from_millis(1000));
+        assert_eq!(scanner.backoff(4), Duration::from_millis(4000));
+        // capped, also once the factor overflows
+        assert_eq!(scanner.backoff(7), MAX_SCAN_BACKOFF);
+        assert_eq!(scanner.backoff(40), MAX_SCAN_BACKOFF);
+    }
+
+    #[test]
+    fn swap_simulation_price_impact_test() {
+        let mut simulation = SwapSimulation {
+            zero_for_one: false,
+            amount_in: 1_000,
+            amount_out: 990,
+            fee_amount: 3,
+            protocol_fee: 0,
+            fund_fee: 0,
+            lp_fee: 3,
+            sqrt_price_before_x64: fixed_point_64::Q64,
+            sqrt_price_after_x64: fixed_point_64::Q64 * 11 / 10,
+            tick_after: 953,
+            tick_array_start_indexes: VecDeque::new(),
+        };
+        // the price moves by the square of the sqrt price ratio
+        assert!((simulation.price_impact() - 0.21).abs() < 1e-9);
+        assert_eq!(simulation.amount_calculated(true), 990);
+        assert_eq!(simulation.amount_calculated(false), 1_000);
+        // the impact is the size of the move, in either direction
+        simulation.sqrt_price_after_x64 = fixed_point_64::Q64 * 9 / 10;
+        assert!((simulation.price_impact() - 0.19).abs() < 1e-9);
+    }
+
+    #[test]
+    fn position_claimable_fees_test() {
+        let mut pool_state = PoolState::default();
+        pool_state.tick_current = 0;
+        pool_state.fee_growth_global_0_x64 = 10 * fixed_point_64::Q64;
+        pool_state.fee_growth_global_1_x64 = 4 * fixed_point_64::Q64;
+        let mut tick_lower = TickState::default();
+        tick_lower.tick = -10;
+        tick_lower.fee_growth_outside_0_x64 = 2 * fixed_point_64::Q64;
+        let mut tick_upper = TickState::default();
+        tick_upper.tick = 10;
+        tick_upper.fee_growth_outside_1_x64 = fixed_point_64::Q64;
+        let mut position = PersonalPositionState::default();
+        position.tick_lower_index = -10;
+        position.tick_upper_index = 10;
+        position.liquidity = 100;
+        position.fee_growth_inside_0_last_x64 = 5 * fixed_point_64::Q64;
+        position.token_fees_owed_0 = 7;
+        position.token_fees_owed_1 = 1;
+
+        // inside growths are 8 and 3 per unit of liquidity, the position last saw 5 and 0
+        assert_eq!(
+            position_claimable_fees(&pool_state, &position, &tick_lower, &tick_upper),
+            (7 + 300, 1 + 300)
+        );
+        // above the range the growth inside is what the upper tick recorded when it was crossed,
+        // here what the position last saw
+        pool_state.tick_current = 20;
+        tick_upper.fee_growth_outside_0_x64 = 7 * fixed_point_64::Q64;
+        tick_upper.fee_growth_outside_1_x64 = 0;
+        assert_eq!(
+            position_claimable_fees(&pool_state, &position, &tick_lower, &tick_upper),
+            (7, 1)
+        );
+    }
+
+    #[test]
+    fn position_claimable_rewards_test() {
+        let mut pool_state = PoolState::default();
+        pool_state.tick_current = 0;
+        pool_state.reward_infos[0].token_mint = Pubkey::new_unique();
+        pool_state.reward_infos[0].reward_growth_global_x64 = 6 * fixed_point_64::Q64;
+        let mut tick_lower = TickState::default();
+        tick_lower.tick = -10;
+        tick_lower.reward_growths_outside_x64[0] = fixed_point_64::Q64;
+        let mut tick_upper = TickState::default();
+        tick_upper.tick = 10;
+        let mut position = PersonalPositionState::default();
+        position.tick_lower_index = -10;
+        position.tick_upper_index = 10;
+        position.liquidity = 100;
+        position.reward_infos[0].growth_inside_last_x64 = 2 * fixed_point_64::Q64;
+        position.reward_infos[0].reward_amount_owed = 9;
+        // an uninitialized reward keeps what is owed
+        position.reward_infos[1].reward_amount_owed = 4;
+
+        // the growth inside is 5 per unit of liquidity, the position last saw 2
+        assert_eq!(
+            position_claimable_rewards(&pool_state, &position, &tick_lower, &tick_upper),
+            [9 + 300, 4, 0]
+        );
+    }
+
+    #[test]
+    fn faucet_amounts_test() {
+        use super::super::faucet::FaucetConfig;
+
+        let faucet = FaucetConfig {
+            min_lamports: 1_000_000_000,
+            airdrop_lamports: 2_000_000_000,
+            test_token_amount: 1_000,
+            ..Default::default()
+        };
+        assert_eq!(faucet.airdrop_amount(999_999_999), Some(2_000_000_000));
+        assert_eq!(faucet.airdrop_amount(1_000_000_000), None);
+        // topped up to the whole token amount, never minted above it
+        assert_eq!(faucet.mint_amount(0, 6), Some(1_000_000_000));
+        assert_eq!(faucet.mint_amount(400_000_000, 6), Some(600_000_000));
+        assert_eq!(faucet.mint_amount(1_500_000_000, 6), None);
+        assert_eq!(faucet.mint_amount(0, 0), Some(1_000));
+        // the target saturates instead of overflowing on large decimals
+        assert_eq!(faucet.mint_amount(0, 30), Some(u64::MAX));
+    }
+
+    #[test]
+    fn compute_budget_test() {
+        use super::super::rpc::{ComputeBudgetConfig, MAX_COMPUTE_UNIT_LIMIT};
+        use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+
+        let config = ComputeBudgetConfig {
+            estimate: true,
+            unit_margin_percent: 20,
+            priority_fee: 0,
+        };
+        assert_eq!(config.unit_limit(100_000), 120_000);
+        // never above what a transaction can request
+        assert_eq!(config.unit_limit(1_300_000), MAX_COMPUTE_UNIT_LIMIT);
+        assert_eq!(config.unit_limit(u64::MAX), MAX_COMPUTE_UNIT_LIMIT);
+        // no priority fee, only the limit
+        assert_eq!(
+            config.instructions(120_000),
+            vec![ComputeBudgetInstruction::set_compute_unit_limit(120_000)]
+        );
+
+        let config = ComputeBudgetConfig {
+            priority_fee: 5_000,
+            ..config
+        };
+        let instructions = config.instructions(120_000);
+        assert_eq!(
+            instructions,
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(120_000),
+                ComputeBudgetInstruction::set_compute_unit_price(5_000),
+            ]
+        );
+        assert!(instructions
+            .iter()
+            .all(|instruction| instruction.program_id == compute_budget::id()));
+    }
+
+    #[test]
+    fn check_swap_accounts_test() {
+        let mut pool_state = PoolState::default();
+        pool_state.token_mint_0 = Pubkey::new_unique();
+        pool_state.token_mint_1 = Pubkey::new_unique();
+        pool_state.token_vault_0 = Pubkey::new_unique();
+        pool_state.token_vault_1 = Pubkey::new_unique();
+        let (mint_0, mint_1) = (pool_state.token_mint_0, pool_state.token_mint_1);
+        let (vault_0, vault_1) = (pool_state.token_vault_0, pool_state.token_vault_1);
+
+        assert!(check_swap_accounts(&pool_state, vault_0, vault_1, mint_0, mint_1).unwrap());
+        assert!(!check_swap_accounts(&pool_state, vault_1, vault_0, mint_1, mint_0).unwrap());
+        // the direction comes from the vaults, token accounts on the wrong sides are rejected
+        assert!(check_swap_accounts(&pool_state, vault_0, vault_1, mint_1, mint_0).is_err());
+        // the same mint on both sides
+        assert!(check_swap_accounts(&pool_state, vault_0, vault_1, mint_0, mint_0).is_err());
+        assert!(check_swap_accounts(&pool_state, vault_0, vault_0, mint_0, mint_0).is_err());
+    }
+
+    #[test]
+    fn serialize_csv_test() {
+        #[derive(Serialize)]
+        struct Row {
+            name: String,
+            amount: u64,
+            owner: Option<String>,
+            rewards: Vec<u64>,
         }
 
-        step.sqrt_price_next_x64 = tick_math::get_sqrt_price_at_tick(step.tick_next).unwrap();
+        assert_eq!(serialize_csv::<Row>(&[]).unwrap(), "");
+        let rows = [
+            Row {
+                name: "sol,usdc".to_string(),
+                amount: 10,
+                owner: None,
+                rewards: vec![1, 2],
+            },
+            Row {
+                name: "say \"hi\"".to_string(),
+                amount: 0,
+                owner: Some("owner".to_string()),
+                rewards: vec![],
+            },
+        ];
+        assert_eq!(
+            serialize_csv(&rows).unwrap(),
+            "amount,name,owner,rewards\n\
+             10,\"sol,usdc\",,\"[1,2]\"\n\
+             0,\"say \"\"hi\"\"\",owner,[]\n"
+        );
+        // only structs have columns
+        assert!(serialize_csv(&[1u64, 2]).is_err());
+    }
 
-        let target_price = if (zero_for_one && step.sqrt_price_next_x64 < sqrt_price_limit_x64)
-            || (!zero_for_one && step.sqrt_price_next_x64 > sqrt_price_limit_x64)
-        {
-            sqrt_price_limit_x64
-        } else {
-            step.sqrt_price_next_x64
+    #[test]
+    fn pool_liquidity_snapshot_test() {
+        use super::super::pool_snapshot::{LiquiditySnapshotAccount, PoolLiquiditySnapshot};
+        use anchor_lang::{__private::bytemuck, Discriminator};
+
+        let pool_id = Pubkey::new_unique();
+        let mut pool_state = PoolState::default();
+        pool_state.tick_spacing = 10;
+        pool_state.tick_current = 15;
+        pool_state.liquidity = 300;
+
+        let tick_array = |start_tick_index: i32, ticks: &[(usize, i128, u128)]| {
+            let mut tick_array = TickArrayState::default();
+            tick_array.start_tick_index = start_tick_index;
+            for (offset, liquidity_net, liquidity_gross) in ticks {
+                tick_array.ticks[*offset].tick = start_tick_index + *offset as i32 * 10;
+                tick_array.ticks[*offset].liquidity_net = *liquidity_net;
+                tick_array.ticks[*offset].liquidity_gross = *liquidity_gross;
+            }
+            tick_array
+        };
+        // listed out of order, the snapshot sorts them
+        let tick_arrays = [
+            tick_array(600, &[(0, -100, 100)]),
+            tick_array(-600, &[(59, 300, 300), (30, 7, 7)]),
+        ];
+
+        let position = |nft_mint: Pubkey, liquidity: u128| PersonalPositionState {
+            nft_mint,
+            pool_id,
+            tick_lower_index: -10,
+            tick_upper_index: 600,
+            liquidity,
+            ..Default::default()
+        };
+        let (key_0, key_1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (nft_mint_0, nft_mint_1) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // zero-copy accounts are the discriminator followed by the raw bytes of the state
+        let position_data = [
+            PersonalPositionState::discriminator().to_vec(),
+            bytemuck::bytes_of(&position(nft_mint_1, 200)).to_vec(),
+        ]
+        .concat();
+        let account = Account {
+            data: position_data,
+            ..Default::default()
+        };
+        let accounts = vec![LiquiditySnapshotAccount::new(
+            "personal_position",
+            &key_1,
+            &account,
+        )];
+
+        let snapshot = PoolLiquiditySnapshot::new(
+            &pool_id,
+            5,
+            &pool_state,
+            &tick_arrays,
+            &[
+                (key_1, position(nft_mint_1, 200)),
+                (key_0, position(nft_mint_0, 100)),
+            ],
+            accounts,
+        );
+        let ticks: Vec<(i32, &str)> = snapshot
+            .ticks
+            .iter()
+            .map(|tick| (tick.tick, tick.liquidity_net.as_str()))
+            .collect();
+        assert_eq!(ticks, vec![(-300, "7"), (-10, "300"), (600, "-100")]);
+        let mut position_keys = vec![key_0, key_1];
+        position_keys.sort();
+        assert_eq!(
+            snapshot
+                .positions
+                .iter()
+                .map(|position| position.personal_position.clone())
+                .collect::<Vec<_>>(),
+            position_keys
+                .iter()
+                .map(|key| key.to_string())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(snapshot.liquidity, "300");
+
+        // the raw accounts decode back to the states
+        let decoded = snapshot
+            .decode_accounts::<PersonalPositionState>("personal_position")
+            .unwrap();
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].nft_mint, nft_mint_1);
+        let liquidity = decoded[0].liquidity;
+        assert_eq!(liquidity, 200);
+        assert!(snapshot
+            .decode_accounts::<PersonalPositionState>("tick_array")
+            .unwrap()
+            .is_empty());
+
+        // the json of the same state is the same
+        let json = serde_json::to_string(&snapshot).unwrap();
+        let read: PoolLiquiditySnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(read, snapshot);
+        assert_eq!(serde_json::to_string(&read).unwrap(), json);
+    }
+
+    #[test]
+    fn replay_swaps_test() {
+        use super::super::replay::{replay_swaps, ReplayPool, ReplaySwap};
+
+        let liquidity = 1_000_000_000u128;
+        let amm_config = AmmConfig {
+            trade_fee_rate: 2500,
+            tick_spacing: 10,
+            ..Default::default()
+        };
+        let mut pool_state = PoolState::default();
+        pool_state.amm_config = Pubkey::new_unique();
+        pool_state.token_mint_0 = Pubkey::new_unique();
+        pool_state.token_mint_1 = Pubkey::new_unique();
+        pool_state.bump = [Pubkey::find_program_address(
+            &[
+                POOL_SEED.as_bytes(),
+                pool_state.amm_config.as_ref(),
+                pool_state.token_mint_0.as_ref(),
+                pool_state.token_mint_1.as_ref(),
+            ],
+            &raydium_amm_v3::id(),
+        )
+        .1];
+        pool_state.tick_spacing = 10;
+        pool_state.sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(0).unwrap();
+        pool_state.liquidity = liquidity;
+        set_bitmap(&mut pool_state, -600);
+        set_bitmap(&mut pool_state, 0);
+        let pool_id = pool_state.key();
+
+        // one position over [-300, 300] holds all the liquidity
+        let tick_array = |start_tick_index: i32, tick: i32, liquidity_net: i128| {
+            let mut tick_array = TickArrayState::default();
+            tick_array.pool_id = pool_id;
+            tick_array.start_tick_index = start_tick_index;
+            let offset = ((tick - start_tick_index) / 10) as usize;
+            tick_array.ticks[offset].tick = tick;
+            tick_array.ticks[offset].liquidity_net = liquidity_net;
+            tick_array.ticks[offset].liquidity_gross = liquidity;
+            tick_array
         };
-        let swap_step = swap_math::compute_swap_step(
-            state.sqrt_price_x64,
-            target_price,
-            state.liquidity,
-            state.amount_specified_remaining,
-            fee,
-            is_base_input,
+        let mut observation_state = ObservationState::default();
+        observation_state.pool_id = pool_id;
+        let position_key = Pubkey::new_unique();
+        let mut replay_pool = ReplayPool::new(
+            amm_config,
+            pool_state,
+            observation_state,
+            None,
+            vec![
+                tick_array(-600, -300, liquidity as i128),
+                tick_array(0, 300, -(liquidity as i128)),
+            ],
+            vec![(
+                position_key,
+                PersonalPositionState {
+                    pool_id,
+                    tick_lower_index: -300,
+                    tick_upper_index: 300,
+                    liquidity,
+                    ..Default::default()
+                },
+            )],
+        );
+
+        let swap = |amount: u64, zero_for_one: bool| ReplaySwap {
+            amount,
             zero_for_one,
-            1,
+            base_in: true,
+            seconds: 1,
+        };
+        let report = replay_swaps(
+            &mut replay_pool,
+            &[swap(1_000_000, true), swap(0, true), swap(400_000, false)],
         )
         .unwrap();
-        state.sqrt_price_x64 = swap_step.sqrt_price_next_x64;
-        step.amount_in = swap_step.amount_in;
-        step.amount_out = swap_step.amount_out;
-        step.fee_amount = swap_step.fee_amount;
+        assert_eq!(report.swaps[0].amount_0, 1_000_000);
+        assert!(report.swaps[0].amount_1 > 0 && report.swaps[0].error.is_none());
+        // a failed swap is reported and leaves the pool unchanged
+        assert!(report.swaps[1].error.is_some());
+        assert_eq!(report.swaps[1].tick_after, report.swaps[0].tick_after);
+        assert_eq!(report.swaps[2].amount_1, 400_000);
+        // the fee of each step is rounded up, crossing into the next tick array adds a step
+        assert_eq!(report.lp_fees[0], 2500);
+        assert!(report.lp_fees[1] >= 1000 && report.lp_fees[1] <= 1001);
+        assert_eq!(report.protocol_fees, [0, 0]);
 
-        if is_base_input {
-            state.amount_specified_remaining = state
-                .amount_specified_remaining
-                .checked_sub(step.amount_in + step.fee_amount)
-                .unwrap();
-            state.amount_calculated = state
-                .amount_calculated
-                .checked_add(step.amount_out)
-                .unwrap();
-        } else {
-            state.amount_specified_remaining = state
-                .amount_specified_remaining
-                .checked_sub(step.amount_out)
-                .unwrap();
-            state.amount_calculated = state
-                .amount_calculated
-                .checked_add(step.amount_in + step.fee_amount)
-                .unwrap();
+        // the position owns all the liquidity and earns the fees, rounded down
+        assert_eq!(report.positions.len(), 1);
+        let position = &report.positions[0];
+        assert_eq!(position.personal_position, position_key.to_string());
+        for (fees, lp_fees) in [
+            (position.fees_0, report.lp_fees[0]),
+            (position.fees_1, report.lp_fees[1]),
+        ] {
+            assert!(fees <= lp_fees && fees + 2 >= lp_fees);
         }
+    }
 
-        if state.sqrt_price_x64 == step.sqrt_price_next_x64 {
-            // if the tick is initialized, run the tick transition
-            if step.initialized {
-                let mut liquidity_net = next_initialized_tick.liquidity_net;
-                if zero_for_one {
-                    liquidity_net = liquidity_net.neg();
-                }
-                state.liquidity =
-                    liquidity_math::add_delta(state.liquidity, liquidity_net).unwrap();
-            }
+    #[test]
+    fn range_order_test() {
+        // a sell sits in the spacing above the market, a buy in the one below
+        assert_eq!(
+            range_order_ticks(105, 123, 10, RangeOrderSide::Sell).unwrap(),
+            (120, 130)
+        );
+        assert_eq!(
+            range_order_ticks(105, 97, 10, RangeOrderSide::Buy).unwrap(),
+            (90, 100)
+        );
+        assert_eq!(
+            range_order_ticks(-105, -123, 10, RangeOrderSide::Buy).unwrap(),
+            (-130, -120)
+        );
+        // the range containing the market holds both tokens
+        assert!(range_order_ticks(105, 108, 10, RangeOrderSide::Sell).is_err());
+        assert!(range_order_ticks(105, 101, 10, RangeOrderSide::Buy).is_err());
+        assert!(range_order_ticks(105, 95, 10, RangeOrderSide::Sell).is_err());
+        assert!(range_order_ticks(0, tick_math::MAX_TICK, 10, RangeOrderSide::Sell).is_err());
 
-            state.tick = if zero_for_one {
-                step.tick_next - 1
-            } else {
-                step.tick_next
-            };
-        } else if state.sqrt_price_x64 != step.sqrt_price_start_x64 {
-            // recompute unless we're on a lower tick boundary (i.e. already transitioned ticks), and haven't moved
-            state.tick = tick_math::get_tick_at_sqrt_price(state.sqrt_price_x64).unwrap();
-        }
-        loop_count += 1;
+        let sqrt_price_lower_x64 = tick_math::get_sqrt_price_at_tick(120).unwrap();
+        let sqrt_price_upper_x64 = tick_math::get_sqrt_price_at_tick(130).unwrap();
+        let fill = |sqrt_price_x64, side| range_order_fill(sqrt_price_x64, 120, 130, side).unwrap();
+        assert_eq!(fill(sqrt_price_lower_x64 - 1, RangeOrderSide::Sell), 0.0);
+        assert_eq!(fill(sqrt_price_upper_x64 + 1, RangeOrderSide::Sell), 1.0);
+        assert_eq!(fill(sqrt_price_upper_x64, RangeOrderSide::Buy), 0.0);
+        assert_eq!(fill(sqrt_price_lower_x64, RangeOrderSide::Buy), 1.0);
+        let middle = (sqrt_price_lower_x64 + sqrt_price_upper_x64) / 2;
+        assert!((fill(middle, RangeOrderSide::Sell) - 0.5).abs() < 1e-9);
+        assert!((fill(middle, RangeOrderSide::Buy) - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn slippage_from_bps_test() {
+        assert_eq!(slippage_from_bps(0).unwrap(), 0.0);
+        assert_eq!(slippage_from_bps(50).unwrap(), 0.005);
+        assert_eq!(slippage_from_bps(10_000).unwrap(), 1.0);
+        assert!(slippage_from_bps(10_001).is_err());
+        // 1% of the quote, the minimum out rounds down and the maximum in up
+        let slippage = slippage_from_bps(100).unwrap();
+        assert_eq!(amount_with_slippage(1_001, slippage, false), 990);
+        assert_eq!(amount_with_slippage(1_001, slippage, true), 1_012);
+    }
+
+    #[test]
+    fn reward_apr_math_test() {
+        use crate::instructions::analytics::*;
+        use raydium_amm_v3::states::AmmConfig;
+
+        // at a price of 1 a unit of full range liquidity holds about 1 of each token
+        let unit_value = liquidity_unit_value(
+            fixed_point_64::Q64,
+            tick_math::MIN_TICK,
+            tick_math::MAX_TICK,
+        )
+        .unwrap();
+        assert!((unit_value - 2.0).abs() < 1e-6);
+        // above the range it is all token_1, whatever the price
+        let sqrt_price_lower = tick_to_sqrt_price(-100);
+        let sqrt_price_upper = tick_to_sqrt_price(100);
+        let above = tick_math::get_sqrt_price_at_tick(200).unwrap();
+        let unit_value = liquidity_unit_value(above, -100, 100).unwrap();
+        assert!((unit_value - (sqrt_price_upper - sqrt_price_lower)).abs() < 1e-9);
+
+        // 0.25% trade fee, of which 12% to the protocol and 4% to the fund
+        let amm_config = AmmConfig {
+            trade_fee_rate: 2_500,
+            protocol_fee_rate: 120_000,
+            fund_fee_rate: 40_000,
+            ..Default::default()
+        };
+        assert!((swap_lp_fee(1_000_000, &amm_config) - 2_100.0).abs() < 1e-9);
+
+        // 100 tokens per second shared by 1000 liquidity
+        let per_second = reward_per_liquidity_per_second(100 * fixed_point_64::Q64, 1_000);
+        assert!((per_second - 0.1).abs() < 1e-12);
+        assert_eq!(reward_per_liquidity_per_second(fixed_point_64::Q64, 0), 0.0);
+        // earning 1% of the value in a day
+        let apr = annualized_rate(0.02, 86_400, 2.0).unwrap();
+        assert!((apr - 3.65).abs() < 1e-9);
+        assert_eq!(annualized_rate(0.02, 0, 2.0), None);
+        assert_eq!(annualized_rate(0.02, 86_400, 0.0), None);
+
+        // a 6 decimals reward at 2 token_1 of 9 decimals
+        assert!((raw_price(2.0, 6, 9) - 2_000.0).abs() < 1e-9);
+        let mint = Pubkey::new_unique();
+        assert_eq!(
+            parse_reward_price(&format!("{}=1.5", mint)).unwrap(),
+            (mint, 1.5)
+        );
+        assert!(parse_reward_price(&mint.to_string()).is_err());
+        assert!(parse_reward_price(&format!("{}=-1", mint)).is_err());
+    }
+
+    #[test]
+    fn migrate_from_v2_test() {
+        use crate::instructions::migrate_from_v2::*;
+
+        let (coin_mint, pc_mint, lp_mint) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut data = vec![0u8; LEGACY_AMM_INFO_LEN];
+        data[8..16].copy_from_slice(&254u64.to_le_bytes());
+        data[192..200].copy_from_slice(&10u64.to_le_bytes());
+        data[400..432].copy_from_slice(coin_mint.as_ref());
+        data[432..464].copy_from_slice(pc_mint.as_ref());
+        data[464..496].copy_from_slice(lp_mint.as_ref());
+        data[720..728].copy_from_slice(&1_000u64.to_le_bytes());
+        let amm = LegacyAmm::unpack(&data).unwrap();
+        assert_eq!(
+            (amm.nonce, amm.need_take_pnl_coin, amm.lp_amount),
+            (254, 10, 1_000)
+        );
+        assert_eq!(
+            (amm.coin_mint, amm.pc_mint, amm.lp_mint),
+            (coin_mint, pc_mint, lp_mint)
+        );
+        assert!(LegacyAmm::unpack(&data[..700]).is_err());
+
+        // a quarter of the lp supply, rounded down
+        assert_eq!(
+            legacy_withdraw_amounts(250, 1_000, 4_003, 8_000),
+            (1_000, 2_000)
+        );
+        assert_eq!(legacy_withdraw_amounts(250, 0, 4_003, 8_000), (0, 0));
+
+        // the full range by default, else the price moved by the percent either way
+        assert_eq!(
+            migration_ticks(fixed_point_64::Q64, None, 60).unwrap(),
+            (-443_580, 443_580)
+        );
+        let (tick_lower, tick_upper) =
+            migration_ticks(fixed_point_64::Q64, Some(10.0), 60).unwrap();
+        assert!(tick_to_price(tick_lower) <= 0.9 && tick_to_price(tick_upper) >= 1.1);
+        assert_eq!((tick_lower % 60, tick_upper % 60), (0, 0));
+        assert!(migration_ticks(fixed_point_64::Q64, Some(100.0), 60).is_err());
+
+        // the pc of the legacy pool is token_0 of the pool
+        let pool_state = PoolState {
+            token_mint_0: pc_mint,
+            token_mint_1: coin_mint,
+            sqrt_price_x64: fixed_point_64::Q64,
+            tick_spacing: 60,
+            ..Default::default()
+        };
+        let plan = MigrationPlan::new(&amm, 1_010, 1_000, &pool_state, 500, None, 0.01).unwrap();
+        assert!(!plan.zero_is_coin);
+        // the pnl owed to the pool is not withdrawn
+        assert_eq!((plan.amount_0, plan.amount_1), (500, 500));
+        assert_eq!((plan.amount_0_min, plan.amount_1_min), (495, 495));
+        assert_eq!(price_deviation(plan.legacy_price, plan.price), 0.0);
+        let other_pool = PoolState {
+            token_mint_0: Pubkey::new_unique(),
+            ..pool_state
+        };
+        assert!(MigrationPlan::new(&amm, 1_010, 1_000, &other_pool, 500, None, 0.01).is_err());
     }
 
-    Ok((state.amount_calculated, tick_array_start_index_vec))
+    #[test]
+    fn ladder_test() {
+        use crate::instructions::ladder::*;
+
+        assert_eq!(
+            ladder_ticks(0, 70, 10, 3).unwrap(),
+            vec![(0, 20), (20, 40), (40, 70)]
+        );
+        assert!(ladder_ticks(0, 20, 10, 3).is_err());
+        assert!(ladder_ticks(0, 20, 10, 0).is_err());
+        assert!(ladder_ticks(5, 70, 10, 3).is_err());
+
+        let pool_state = PoolState {
+            sqrt_price_x64: fixed_point_64::Q64,
+            tick_current: 0,
+            tick_spacing: 10,
+            ..Default::default()
+        };
+        let ticks = ladder_ticks(-100, 100, 10, 4).unwrap();
+        assert_eq!(ticks, vec![(-100, -50), (-50, 0), (0, 50), (50, 100)]);
+        let buckets = plan_ladder(&pool_state, &ticks, 1_000_000.0).unwrap();
+        assert!(buckets
+            .iter()
+            .all(|bucket| bucket.liquidity == buckets[0].liquidity));
+        // below the price the buckets only hold token_1, above only token_0
+        assert_eq!((buckets[0].amount_0, buckets[1].amount_0), (0, 0));
+        assert_eq!((buckets[2].amount_1, buckets[3].amount_1), (0, 0));
+        // the deposits are worth the total value at a price of 1
+        let value: u64 = buckets
+            .iter()
+            .map(|bucket| bucket.amount_0 + bucket.amount_1)
+            .sum();
+        assert!((value as f64 - 1_000_000.0).abs() < 10.0);
+        assert!(plan_ladder(&pool_state, &ticks, 0.0).is_err());
+
+        // at most two per transaction
+        let groups = group_by_tx_size(5, |group| Ok(group.len() <= 2)).unwrap();
+        assert_eq!(groups, vec![0..2, 2..4, 4..5]);
+        assert!(group_by_tx_size(5, |_| Ok(false)).is_err());
+    }
+
+    #[test]
+    fn decode_swap_amount_test() {
+        use anchor_lang::AnchorSerialize;
+        use solana_transaction_status::{UiReturnDataEncoding, UiTransactionReturnData};
+        let program_id = Pubkey::new_unique();
+        let return_data = |program_id: &Pubkey, data: Vec<u8>| {
+            Some(UiTransactionReturnData {
+                program_id: program_id.to_string(),
+                data: (
+                    anchor_lang::__private::base64::encode(data),
+                    UiReturnDataEncoding::Base64,
+                ),
+            })
+        };
+
+        let amount = return_data(&program_id, 1_234_567u64.to_le_bytes().to_vec());
+        assert_eq!(decode_swap_amount(&amount, &program_id), Some(1_234_567));
+        assert!(decode_output_vault_shortfall(&amount, &program_id).is_none());
+        assert_eq!(decode_swap_amount(&amount, &Pubkey::new_unique()), None);
+        assert_eq!(decode_swap_amount(&None, &program_id), None);
+
+        // a failed swap returns its shortfall instead
+        let shortfall = raydium_amm_v3::instructions::OutputVaultShortfall {
+            amount_out: 100,
+            max_amount_out: 90,
+        };
+        let shortfall = return_data(&program_id, shortfall.try_to_vec().unwrap());
+        assert_eq!(decode_swap_amount(&shortfall, &program_id), None);
+        assert!(decode_output_vault_shortfall(&shortfall, &program_id).is_some());
+    }
 }
@@ -0,0 +1,181 @@
+use anyhow::{format_err, Result};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    hash::{hashv, Hash},
+    pubkey::Pubkey,
+};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use tracing::info;
+
+/// The program artifact `cargo build-sbf` writes under the workspace target directory
+pub const PROGRAM_ARTIFACT: &str = "target/deploy/raydium_amm_v3.so";
+/// The manifest of the program in the workspace
+pub const PROGRAM_MANIFEST: &str = "programs/amm/Cargo.toml";
+
+/// The binary of a deployed program and where it was read from
+#[derive(Debug)]
+pub struct DeployedProgram {
+    /// The program data account of an upgradeable program
+    pub programdata_address: Option<Pubkey>,
+    /// The slot the program was last deployed at
+    pub deployed_slot: Option<u64>,
+    pub upgrade_authority: Option<Pubkey>,
+    pub binary: Vec<u8>,
+}
+
+/// Outcome of comparing the deployed program with a local build
+#[derive(Debug)]
+pub struct VerifyBuildReport {
+    pub program_id: Pubkey,
+    /// The commit built, none if a prebuilt artifact was compared
+    pub commit: Option<String>,
+    pub onchain_hash: Hash,
+    pub local_hash: Hash,
+}
+
+impl VerifyBuildReport {
+    pub fn matches(&self) -> bool {
+        self.onchain_hash == self.local_hash
+    }
+
+    pub fn report(&self) -> String {
+        format!(
+            "program:{}, commit:{}, onchain_hash:{}, local_hash:{}, result:{}",
+            self.program_id,
+            self.commit.as_deref().unwrap_or("-"),
+            self.onchain_hash,
+            self.local_hash,
+            if self.matches() { "MATCH" } else { "MISMATCH" }
+        )
+    }
+}
+
+/// Read the binary of the deployed program, following the program data account of an upgradeable
+/// program
+pub fn fetch_program_binary(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<DeployedProgram> {
+    let program_account = rpc_client.get_account(program_id)?;
+    if program_account.owner != bpf_loader_upgradeable::id() {
+        // programs of the older loaders store the binary in the program account
+        return Ok(DeployedProgram {
+            programdata_address: None,
+            deployed_slot: None,
+            upgrade_authority: None,
+            binary: program_account.data,
+        });
+    }
+    let programdata_address = match bincode::deserialize(&program_account.data)? {
+        UpgradeableLoaderState::Program {
+            programdata_address,
+        } => programdata_address,
+        _ => return Err(format_err!("{} is not a program account", program_id)),
+    };
+    let programdata_account = rpc_client.get_account(&programdata_address)?;
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    if programdata_account.data.len() < metadata_len {
+        return Err(format_err!(
+            "program data {} is too short",
+            programdata_address
+        ));
+    }
+    match bincode::deserialize(&programdata_account.data[..metadata_len])? {
+        UpgradeableLoaderState::ProgramData {
+            slot,
+            upgrade_authority_address,
+        } => Ok(DeployedProgram {
+            programdata_address: Some(programdata_address),
+            deployed_slot: Some(slot),
+            upgrade_authority: upgrade_authority_address,
+            binary: programdata_account.data[metadata_len..].to_vec(),
+        }),
+        _ => Err(format_err!(
+            "{} is not a program data account",
+            programdata_address
+        )),
+    }
+}
+
+/// The program data account is allocated larger than the binary and zero padded, the padding is
+/// not part of the build
+pub fn trim_trailing_zeros(binary: &[u8]) -> &[u8] {
+    let len = binary
+        .iter()
+        .rposition(|byte| *byte != 0)
+        .map_or(0, |last| last + 1);
+    &binary[..len]
+}
+
+/// The sha256 of the program binary without the trailing zero padding
+pub fn program_hash(binary: &[u8]) -> Hash {
+    hashv(&[trim_trailing_zeros(binary)])
+}
+
+/// Check out `commit` of the repo into a temporary worktree, build the program with
+/// `cargo build-sbf` and return the built binary. The worktree is removed afterwards.
+pub fn build_program_at_commit(repo_dir: &Path, commit: &str) -> Result<Vec<u8>> {
+    let worktree_dir: PathBuf =
+        std::env::temp_dir().join(format!("raydium-clmm-verify-{}", std::process::id()));
+    run(Command::new("git").current_dir(repo_dir).args([
+        "worktree",
+        "add",
+        "--detach",
+        worktree_dir.to_str().unwrap(),
+        commit,
+    ]))?;
+    let binary = run(Command::new("cargo").current_dir(&worktree_dir).args([
+        "build-sbf",
+        "--manifest-path",
+        PROGRAM_MANIFEST,
+    ]))
+    .and_then(|_| Ok(fs::read(worktree_dir.join(PROGRAM_ARTIFACT))?));
+    run(Command::new("git").current_dir(repo_dir).args([
+        "worktree",
+        "remove",
+        "--force",
+        worktree_dir.to_str().unwrap(),
+    ]))?;
+    binary
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    let status = command.status()?;
+    if !status.success() {
+        return Err(format_err!("{:?} failed with {}", command, status));
+    }
+    Ok(())
+}
+
+/// Compare the hash of the deployed program with the hash of `local_binary`
+pub fn verify_build(
+    rpc_client: &RpcClient,
+    program_id: &Pubkey,
+    commit: Option<String>,
+    local_binary: &[u8],
+) -> Result<VerifyBuildReport> {
+    let deployed = fetch_program_binary(rpc_client, program_id)?;
+    info!(
+        "programdata:{}, deployed_slot:{}, upgrade_authority:{}",
+        deployed
+            .programdata_address
+            .map_or("-".to_string(), |key| key.to_string()),
+        deployed
+            .deployed_slot
+            .map_or("-".to_string(), |slot| slot.to_string()),
+        deployed
+            .upgrade_authority
+            .map_or("-".to_string(), |key| key.to_string()),
+    );
+    Ok(VerifyBuildReport {
+        program_id: *program_id,
+        commit,
+        onchain_hash: program_hash(&deployed.binary),
+        local_hash: program_hash(local_binary),
+    })
+}
@@ -0,0 +1,215 @@
+use anyhow::{format_err, Result};
+use raydium_amm_v3::states::{tick_array_key, ObservationState, PoolState, TickArrayState};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    pubsub_client::{PubsubAccountClientSubscription, PubsubClient},
+    rpc_config::RpcAccountInfoConfig,
+};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey};
+use std::sync::mpsc::{channel, Receiver};
+use std::thread;
+
+use super::super::ClientConfig;
+use super::utils::{deserialize_anchor_account, sqrt_price_x64_to_price};
+
+/// The account a notification of `watch_pool` is for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchedAccount {
+    Pool,
+    Observation,
+    /// The tick array of this start index
+    TickArray(i32),
+}
+
+/// An account update of the watched pool
+pub struct WatchNotification {
+    pub account: WatchedAccount,
+    pub slot: u64,
+    pub data: Account,
+}
+
+/// The subscriptions of a watched pool, the notifications stop once it is dropped
+pub struct PoolWatch {
+    pub notifications: Receiver<WatchNotification>,
+    subscriptions: Vec<PubsubAccountClientSubscription>,
+}
+
+impl PoolWatch {
+    pub fn shutdown(self) {
+        for mut subscription in self.subscriptions {
+            let _ = subscription.shutdown();
+        }
+    }
+}
+
+/// The start indexes of the tick array of the current tick and `arrays_around` arrays on each side
+pub fn nearby_tick_array_start_indexes(
+    tick_current: i32,
+    tick_spacing: u16,
+    arrays_around: u8,
+) -> Vec<i32> {
+    let current = TickArrayState::get_array_start_index(tick_current, tick_spacing);
+    let array_span = TickArrayState::tick_count(tick_spacing);
+    (-(arrays_around as i32)..=arrays_around as i32)
+        .map(|offset| current + offset * array_span)
+        .filter(|start_index| {
+            TickArrayState::check_is_valid_start_index(*start_index, tick_spacing)
+        })
+        .collect()
+}
+
+/// Subscribe over the websocket to the pool, its observation and the tick arrays around the
+/// current tick, and forward every account update to one channel.
+pub fn watch_pool(
+    config: &ClientConfig,
+    pool_id: &Pubkey,
+    pool_state: &PoolState,
+    arrays_around: u8,
+) -> Result<PoolWatch> {
+    let mut accounts = vec![
+        (WatchedAccount::Pool, *pool_id),
+        (WatchedAccount::Observation, pool_state.observation_key),
+    ];
+    for start_index in nearby_tick_array_start_indexes(
+        pool_state.tick_current,
+        pool_state.tick_spacing,
+        arrays_around,
+    ) {
+        accounts.push((
+            WatchedAccount::TickArray(start_index),
+            tick_array_key(&config.raydium_v3_program, pool_id, start_index),
+        ));
+    }
+
+    let (sender, notifications) = channel();
+    let mut subscriptions = Vec::new();
+    for (account, key) in accounts {
+        let (subscription, receiver) = PubsubClient::account_subscribe(
+            &config.ws_url,
+            &key,
+            Some(RpcAccountInfoConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                commitment: Some(CommitmentConfig::confirmed()),
+                ..RpcAccountInfoConfig::default()
+            }),
+        )
+        .map_err(|err| format_err!("subscribe {}: {}", key, err))?;
+        subscriptions.push(subscription);
+        let sender = sender.clone();
+        thread::spawn(move || {
+            for response in receiver.iter() {
+                // closed accounts have no data to decode
+                if let Some(data) = response.value.decode::<Account>() {
+                    let notification = WatchNotification {
+                        account,
+                        slot: response.context.slot,
+                        data,
+                    };
+                    if sender.send(notification).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+    Ok(PoolWatch {
+        notifications,
+        subscriptions,
+    })
+}
+
+/// One line of the price, liquidity and current tick of the pool, with the change from the
+/// previous state if there is one
+pub fn describe_pool_update(previous: Option<&PoolState>, pool_state: &PoolState) -> String {
+    let sqrt_price_x64 = pool_state.sqrt_price_x64;
+    let liquidity = pool_state.liquidity;
+    let tick_current = pool_state.tick_current;
+    let price = sqrt_price_x64_to_price(
+        sqrt_price_x64,
+        pool_state.mint_decimals_0,
+        pool_state.mint_decimals_1,
+    );
+    let mut line = format!(
+        "price:{}, liquidity:{}, tick:{}",
+        price, liquidity, tick_current
+    );
+    if let Some(previous) = previous {
+        let previous_liquidity = previous.liquidity;
+        let previous_tick = previous.tick_current;
+        if previous_tick != tick_current {
+            line.push_str(&format!(", tick {} -> {}", previous_tick, tick_current));
+            let previous_start_index =
+                TickArrayState::get_array_start_index(previous_tick, pool_state.tick_spacing);
+            let start_index =
+                TickArrayState::get_array_start_index(tick_current, pool_state.tick_spacing);
+            if previous_start_index != start_index {
+                line.push_str(&format!(
+                    ", tick array {} -> {}",
+                    previous_start_index, start_index
+                ));
+            }
+        }
+        if previous_liquidity != liquidity {
+            line.push_str(&format!(
+                ", liquidity {} -> {}",
+                previous_liquidity, liquidity
+            ));
+        }
+    }
+    line
+}
+
+/// One line of the initialized ticks and liquidity net of a tick array update
+pub fn describe_tick_array_update(tick_array: &TickArrayState) -> String {
+    let start_tick_index = tick_array.start_tick_index;
+    let initialized: Vec<String> = tick_array
+        .ticks
+        .iter()
+        .filter(|tick| tick.is_initialized())
+        .map(|tick| {
+            let tick_index = tick.tick;
+            let liquidity_net = tick.liquidity_net;
+            format!("{}:{}", tick_index, liquidity_net)
+        })
+        .collect();
+    format!(
+        "tick array {}, initialized ticks:{}, liquidity_net [{}]",
+        start_tick_index,
+        tick_array.initialized_tick_count,
+        initialized.join(", ")
+    )
+}
+
+/// One line of the latest observation of the pool
+pub fn describe_observation_update(observation_state: &ObservationState) -> String {
+    let observation_index = observation_state.observation_index;
+    let observation = observation_state.observations[observation_index as usize];
+    let block_timestamp = observation.block_timestamp;
+    let tick_cumulative = observation.tick_cumulative;
+    format!(
+        "observation index:{}, timestamp:{}, tick_cumulative:{}",
+        observation_index, block_timestamp, tick_cumulative
+    )
+}
+
+/// Decode the notification and describe it, the pool state is kept to report its transitions
+pub fn describe_notification(
+    notification: &WatchNotification,
+    last_pool_state: &mut Option<PoolState>,
+) -> Result<String> {
+    let line = match notification.account {
+        WatchedAccount::Pool => {
+            let pool_state = deserialize_anchor_account::<PoolState>(&notification.data)?;
+            let line = describe_pool_update(last_pool_state.as_ref(), &pool_state);
+            *last_pool_state = Some(pool_state);
+            line
+        }
+        WatchedAccount::Observation => describe_observation_update(&deserialize_anchor_account::<
+            ObservationState,
+        >(&notification.data)?),
+        WatchedAccount::TickArray(_) => describe_tick_array_update(&deserialize_anchor_account::<
+            TickArrayState,
+        >(&notification.data)?),
+    };
+    Ok(format!("slot:{}, {}", notification.slot, line))
+}
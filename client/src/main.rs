@@ -19,30 +19,58 @@ use solana_client::{
 use solana_sdk::{
     commitment_config::CommitmentConfig,
     compute_budget::ComputeBudgetInstruction,
-    message::Message,
+    instruction::Instruction,
+    message::{v0, Message, VersionedMessage},
+    native_token::{lamports_to_sol, sol_to_lamports},
     program_pack::Pack,
     pubkey::Pubkey,
     signature::{Keypair, Signature, Signer},
-    transaction::Transaction,
+    transaction::{Transaction, VersionedTransaction},
 };
-use solana_transaction_status::UiTransactionEncoding;
+use solana_transaction_status::{option_serializer::OptionSerializer, UiTransactionEncoding};
 use std::path::Path;
 use std::rc::Rc;
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use std::{collections::VecDeque, convert::identity, mem::size_of};
+use tracing::{info, info_span, warn};
 
 mod instructions;
 use bincode::serialize;
 use instructions::amm_instructions::*;
+use instructions::analytics::*;
+use instructions::config_diff::*;
+use instructions::diagnose::*;
 use instructions::events_instructions_parse::*;
+use instructions::faucet::*;
+use instructions::init_wizard::*;
+use instructions::ladder::*;
+use instructions::migrate_from_v2::*;
+use instructions::output::*;
+use instructions::pool_manifest::*;
+use instructions::pool_quoter::*;
+use instructions::pool_snapshot::*;
+use instructions::portfolio::*;
+use instructions::position_closer::*;
+use instructions::position_history::*;
+use instructions::position_scanner::*;
+use instructions::replay::*;
+use instructions::route_planner::*;
 use instructions::rpc::*;
+use instructions::scheduler::*;
+use instructions::telemetry::*;
 use instructions::token_instructions::*;
 use instructions::utils::*;
+use instructions::verify_build::*;
+use instructions::watch::*;
 use raydium_amm_v3::{
     libraries::{fixed_point_64, liquidity_math, tick_math},
     states::{PoolState, TickArrayBitmapExtension, TickArrayState, POOL_TICK_ARRAY_BITMAP_SEED},
 };
-use spl_associated_token_account::get_associated_token_address;
+use spl_associated_token_account::{
+    get_associated_token_address, get_associated_token_address_with_program_id,
+    instruction::create_associated_token_account_idempotent,
+};
 use spl_token_2022::{
     extension::StateWithExtensions,
     state::Mint,
@@ -55,6 +83,9 @@ use crate::instructions::utils;
 pub struct ClientConfig {
     http_url: String,
     ws_url: String,
+    fallback_http_urls: Vec<String>,
+    fallback_ws_urls: Vec<String>,
+    read_quorum: usize,
     payer_path: String,
     admin_path: String,
     raydium_v3_program: Pubkey,
@@ -66,6 +97,9 @@ pub struct ClientConfig {
     pool_id_account: Option<Pubkey>,
     tickarray_bitmap_extension: Option<Pubkey>,
     amm_config_index: u16,
+    rpc_scan: RpcScanConfig,
+    faucet: FaucetConfig,
+    compute_budget: ComputeBudgetConfig,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Default)]
@@ -78,9 +112,62 @@ pub struct PoolAccounts {
     pool_tick_arrays: Vec<Pubkey>,
 }
 
-fn load_cfg(client_config: &String) -> Result<ClientConfig> {
+/// Keys of a profile that belong to the [Pool] section
+const PROFILE_POOL_KEYS: [&str; 3] = ["mint0", "mint1", "amm_config_index"];
+/// Keys of a profile that replace the [Global] ones
+const PROFILE_GLOBAL_KEYS: [&str; 12] = [
+    "http_url",
+    "ws_url",
+    "fallback_http_urls",
+    "fallback_ws_urls",
+    "read_quorum",
+    "rpc_chunk_size",
+    "rpc_request_interval_ms",
+    "rpc_max_retries",
+    "payer_path",
+    "admin_path",
+    "raydium_v3_program",
+    "slippage",
+];
+
+/// Replace the cluster and pool keys with the ones of the `[profile.<name>]` section,
+/// a key that is neither a pool nor a global one is rejected
+fn apply_profile(config: &mut Ini, profile: &str) -> Result<()> {
+    let section = format!("profile.{}", profile);
+    let keys = match config.get_map_ref().get(&section.to_lowercase()) {
+        Some(keys) => keys.clone(),
+        None => {
+            return Err(format_err!(
+                "profile {} is not defined in [{}]",
+                profile,
+                section
+            ))
+        }
+    };
+    for (key, value) in keys {
+        let target = if PROFILE_POOL_KEYS.contains(&key.as_str()) {
+            "Pool"
+        } else if PROFILE_GLOBAL_KEYS.contains(&key.as_str()) {
+            "Global"
+        } else {
+            return Err(format_err!("unknown key {} in profile {}", key, profile));
+        };
+        config.set(target, &key, value);
+    }
+    Ok(())
+}
+
+fn load_cfg(
+    client_config: &String,
+    profile: Option<String>,
+    wallet: Option<String>,
+    program_id: Option<Pubkey>,
+) -> Result<ClientConfig> {
     let mut config = Ini::new();
     let _map = config.load(client_config).unwrap();
+    if let Some(profile) = profile {
+        apply_profile(&mut config, &profile)?;
+    }
     let http_url = config.get("Global", "http_url").unwrap();
     if http_url.is_empty() {
         panic!("http_url must not be empty");
@@ -89,7 +176,98 @@ fn load_cfg(client_config: &String) -> Result<ClientConfig> {
     if ws_url.is_empty() {
         panic!("ws_url must not be empty");
     }
-    let payer_path = config.get("Global", "payer_path").unwrap();
+    // endpoints tried after the primary one, paired by position
+    let url_list = |key: &str| -> Vec<String> {
+        config
+            .get("Global", key)
+            .unwrap_or_default()
+            .split(',')
+            .map(|url| url.trim().to_string())
+            .filter(|url| !url.is_empty())
+            .collect()
+    };
+    let fallback_http_urls = url_list("fallback_http_urls");
+    let fallback_ws_urls = url_list("fallback_ws_urls");
+    if fallback_http_urls.len() != fallback_ws_urls.len() {
+        panic!("fallback_http_urls and fallback_ws_urls must have the same length");
+    }
+    let read_quorum = config
+        .getuint("Global", "read_quorum")
+        .unwrap()
+        .unwrap_or(1) as usize;
+    // pacing of the bulk reads, the defaults suit public endpoints
+    let default_scan = RpcScanConfig::default();
+    let rpc_scan = RpcScanConfig {
+        chunk_size: config
+            .getuint("Global", "rpc_chunk_size")
+            .unwrap()
+            .map_or(default_scan.chunk_size, |chunk_size| chunk_size as usize),
+        request_interval: config
+            .getuint("Global", "rpc_request_interval_ms")
+            .unwrap()
+            .map_or(default_scan.request_interval, Duration::from_millis),
+        max_retries: config
+            .getuint("Global", "rpc_max_retries")
+            .unwrap()
+            .map_or(default_scan.max_retries, |max_retries| max_retries as u32),
+        ..default_scan
+    };
+    // funding of the payer in devnet workflows, the section is optional
+    let default_faucet = FaucetConfig::default();
+    let faucet = FaucetConfig {
+        auto: config
+            .getbool("Faucet", "auto")
+            .unwrap()
+            .unwrap_or(default_faucet.auto),
+        min_lamports: config
+            .getfloat("Faucet", "min_sol")
+            .unwrap()
+            .map_or(default_faucet.min_lamports, sol_to_lamports),
+        airdrop_lamports: config
+            .getfloat("Faucet", "airdrop_sol")
+            .unwrap()
+            .map_or(default_faucet.airdrop_lamports, sol_to_lamports),
+        test_token_amount: config
+            .getuint("Faucet", "test_token_amount")
+            .unwrap()
+            .unwrap_or(default_faucet.test_token_amount),
+        test_mints: config
+            .get("Faucet", "test_mints")
+            .unwrap_or_default()
+            .split(',')
+            .map(|mint| mint.trim())
+            .filter(|mint| !mint.is_empty())
+            .map(|mint| Pubkey::from_str(mint).unwrap())
+            .collect(),
+    };
+    // compute budget of the sent transactions, the section is optional
+    let default_compute_budget = ComputeBudgetConfig::default();
+    let compute_budget = ComputeBudgetConfig {
+        estimate: config
+            .getbool("ComputeBudget", "estimate")
+            .unwrap()
+            .unwrap_or(default_compute_budget.estimate),
+        unit_margin_percent: config
+            .getuint("ComputeBudget", "unit_margin_percent")
+            .unwrap()
+            .map_or(default_compute_budget.unit_margin_percent, |margin| {
+                margin as u32
+            }),
+        priority_fee: config
+            .getuint("ComputeBudget", "priority_fee")
+            .unwrap()
+            .unwrap_or(default_compute_budget.priority_fee),
+    };
+    // the selected wallet replaces the default payer, so every command signs, derives ATAs
+    // and scans positions with the same keypair
+    let payer_path = if let Some(label) = wallet {
+        match config.get("Wallets", &label) {
+            Some(path) if !path.is_empty() => path,
+            _ => panic!("wallet {} is not defined in [Wallets]", label),
+        }
+    } else {
+        config.get("Global", "payer_path").unwrap()
+    };
     if payer_path.is_empty() {
         panic!("payer_path must not be empty");
     }
@@ -98,11 +276,18 @@ fn load_cfg(client_config: &String) -> Result<ClientConfig> {
         panic!("admin_path must not be empty");
     }
 
-    let raydium_v3_program_str = config.get("Global", "raydium_v3_program").unwrap();
-    if raydium_v3_program_str.is_empty() {
-        panic!("raydium_v3_program must not be empty");
-    }
-    let raydium_v3_program = Pubkey::from_str(&raydium_v3_program_str).unwrap();
+    // the program id override applies before any PDA is derived, so the config, pool and
+    // bitmap extension keys all belong to the chosen deployment
+    let raydium_v3_program = match program_id {
+        Some(program_id) => program_id,
+        None => {
+            let raydium_v3_program_str = config.get("Global", "raydium_v3_program").unwrap();
+            if raydium_v3_program_str.is_empty() {
+                panic!("raydium_v3_program must not be empty");
+            }
+            Pubkey::from_str(&raydium_v3_program_str).unwrap()
+        }
+    };
     let slippage = config.getfloat("Global", "slippage").unwrap().unwrap();
 
     let mut mint0 = None;
@@ -126,11 +311,9 @@ fn load_cfg(client_config: &String) -> Result<ClientConfig> {
     );
 
     let pool_id_account = if mint0 != None && mint1 != None {
-        if mint0.unwrap() > mint1.unwrap() {
-            let temp_mint = mint0;
-            mint0 = mint1;
-            mint1 = temp_mint;
-        }
+        let pair = CanonicalPair::new(mint0.unwrap(), mint1.unwrap());
+        mint0 = Some(pair.mint0);
+        mint1 = Some(pair.mint1);
         Some(
             Pubkey::find_program_address(
                 &[
@@ -164,6 +347,9 @@ fn load_cfg(client_config: &String) -> Result<ClientConfig> {
     Ok(ClientConfig {
         http_url,
         ws_url,
+        fallback_http_urls,
+        fallback_ws_urls,
+        read_quorum,
         payer_path,
         admin_path,
         raydium_v3_program,
@@ -174,6 +360,9 @@ fn load_cfg(client_config: &String) -> Result<ClientConfig> {
         pool_id_account,
         tickarray_bitmap_extension,
         amm_config_index,
+        rpc_scan,
+        faucet,
+        compute_budget,
     })
 }
 fn read_keypair_file(s: &str) -> Result<Keypair> {
@@ -194,59 +383,15 @@ fn load_cur_and_next_five_tick_array(
     pool_state: &PoolState,
     tickarray_bitmap_extension: &TickArrayBitmapExtension,
     zero_for_one: bool,
-) -> VecDeque<TickArrayState> {
-    let (_, mut current_vaild_tick_array_start_index) = pool_state
-        .get_first_initialized_tick_array(&Some(*tickarray_bitmap_extension), zero_for_one)
-        .unwrap();
-    let mut tick_array_keys = Vec::new();
-    tick_array_keys.push(
-        Pubkey::find_program_address(
-            &[
-                raydium_amm_v3::states::TICK_ARRAY_SEED.as_bytes(),
-                pool_config.pool_id_account.unwrap().to_bytes().as_ref(),
-                &current_vaild_tick_array_start_index.to_be_bytes(),
-            ],
-            &pool_config.raydium_v3_program,
-        )
-        .0,
-    );
-    let mut max_array_size = 5;
-    while max_array_size != 0 {
-        let next_tick_array_index = pool_state
-            .next_initialized_tick_array_start_index(
-                &Some(*tickarray_bitmap_extension),
-                current_vaild_tick_array_start_index,
-                zero_for_one,
-            )
-            .unwrap();
-        if next_tick_array_index.is_none() {
-            break;
-        }
-        current_vaild_tick_array_start_index = next_tick_array_index.unwrap();
-        tick_array_keys.push(
-            Pubkey::find_program_address(
-                &[
-                    raydium_amm_v3::states::TICK_ARRAY_SEED.as_bytes(),
-                    pool_config.pool_id_account.unwrap().to_bytes().as_ref(),
-                    &current_vaild_tick_array_start_index.to_be_bytes(),
-                ],
-                &pool_config.raydium_v3_program,
-            )
-            .0,
-        );
-        max_array_size -= 1;
-    }
-    let tick_array_rsps = rpc_client.get_multiple_accounts(&tick_array_keys).unwrap();
-    let mut tick_arrays = VecDeque::new();
-    for tick_array in tick_array_rsps {
-        let tick_array_state =
-            deserialize_anchor_account::<raydium_amm_v3::states::TickArrayState>(
-                &tick_array.unwrap(),
-            )
-            .unwrap();
-        tick_arrays.push_back(tick_array_state);
-    }
-    tick_arrays
+) -> Result<VecDeque<TickArrayState>, QuoteError> {
+    load_tick_arrays(
+        rpc_client,
+        &pool_config.raydium_v3_program,
+        &pool_config.pool_id_account.unwrap(),
+        pool_state,
+        tickarray_bitmap_extension,
+        zero_for_one,
+    )
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -339,8 +484,87 @@ fn get_nft_account_and_position_by_owner(
     position_nft_accounts
 }
 
+/// Owed reward of a position, include the reward accrued since the position was last updated
+fn get_position_reward_owed(
+    rpc_client: &RpcClient,
+    pool_config: &ClientConfig,
+    pool: &PoolState,
+    position: &raydium_amm_v3::states::PersonalPositionState,
+    reward_index: usize,
+) -> Result<u64> {
+    let tick_array_keys: Vec<Pubkey> = [position.tick_lower_index, position.tick_upper_index]
+        .iter()
+        .map(|tick_index| {
+            let start_index = TickArrayState::get_array_start_index(*tick_index, pool.tick_spacing);
+            Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::TICK_ARRAY_SEED.as_bytes(),
+                    position.pool_id.to_bytes().as_ref(),
+                    &start_index.to_be_bytes(),
+                ],
+                &pool_config.raydium_v3_program,
+            )
+            .0
+        })
+        .collect();
+    let rsps = rpc_client.get_multiple_accounts(&tick_array_keys)?;
+    let mut tick_array_lower = deserialize_anchor_account::<TickArrayState>(
+        rsps[0]
+            .as_ref()
+            .ok_or(format_err!("tick array lower not exist"))?,
+    )?;
+    let mut tick_array_upper = deserialize_anchor_account::<TickArrayState>(
+        rsps[1]
+            .as_ref()
+            .ok_or(format_err!("tick array upper not exist"))?,
+    )?;
+    let tick_lower = *tick_array_lower
+        .get_tick_state_mut(position.tick_lower_index, pool.tick_spacing)
+        .unwrap();
+    let tick_upper = *tick_array_upper
+        .get_tick_state_mut(position.tick_upper_index, pool.tick_spacing)
+        .unwrap();
+    Ok(position_claimable_rewards(pool, position, &tick_lower, &tick_upper)[reward_index])
+}
+
+/// The wallet holding the position nft
+fn get_nft_owner(rpc_client: &RpcClient, nft_mint: &Pubkey) -> Result<Option<Pubkey>> {
+    let largest_accounts = rpc_client.get_token_largest_accounts(nft_mint)?;
+    for token_account in largest_accounts {
+        if token_account.amount.amount != "1" {
+            continue;
+        }
+        let token_account_key = Pubkey::from_str(&token_account.address)?;
+        let token_data = rpc_client.get_account_data(&token_account_key)?;
+        let token_state = StateWithExtensions::<Account>::unpack(&token_data)?;
+        return Ok(Some(token_state.base.owner));
+    }
+    Ok(None)
+}
+
 #[derive(Debug, Parser)]
 pub struct Opts {
+    /// Profile defined as a [profile.<name>] section of client_config.ini, its keys replace the
+    /// ones of [Global] and [Pool]
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Wallet label defined in the [Wallets] section of client_config.ini, default is payer_path
+    #[arg(long, global = true)]
+    pub wallet: Option<String>,
+    /// Program id of the deployment to use, default is raydium_v3_program of client_config.ini
+    #[arg(long, global = true)]
+    pub program_id: Option<Pubkey>,
+    /// Format of the log lines
+    #[arg(long, global = true, value_enum, default_value_t = LogFormat::Human)]
+    pub log_format: LogFormat,
+    /// Verbosity per module in the RUST_LOG syntax, e.g. info,client::instructions::rpc=debug,
+    /// default is RUST_LOG or info
+    #[arg(long, global = true)]
+    pub log_filter: Option<String>,
+    /// Simulate the transactions of the command and print their logs instead of sending them.
+    /// A transaction depending on an earlier one of the same command can fail to simulate
+    #[arg(long, global = true)]
+    pub simulate: bool,
     #[clap(subcommand)]
     pub command: CommandsName,
 }
@@ -394,12 +618,48 @@ pub enum CommandsName {
         param: u8,
         value: u32,
         remaining: Option<Pubkey>,
+        /// Send without confirming the diff of the config
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Set the lamports the creator of a pool of the config pays to its fund owner, zero to disable
+    UpdatePoolCreationFee {
+        config_index: u16,
+        pool_creation_fee: u64,
+    },
+    /// Propose a new owner of the config, signed by the admin. The owner changes once the new
+    /// owner runs `accept-config-owner`
+    ProposeConfigOwner {
+        config_index: u16,
+        new_owner: Pubkey,
+    },
+    /// Accept the owner proposal of the config, signed by the payer as the new owner
+    AcceptConfigOwner {
+        config_index: u16,
+    },
+    /// Split the protocol fees of the config, `split_rate` of them go to the token accounts of
+    /// the secondary destination and the rest to the primary one. A zero rate stops splitting
+    SetProtocolFeeSplit {
+        config_index: u16,
+        primary_destination: Pubkey,
+        secondary_destination: Pubkey,
+        split_rate: u32,
     },
     CreateOperation,
     UpdateOperation {
         param: u8,
         keys: Vec<Pubkey>,
     },
+    /// Enable or disable one operation of a pool: 0 open position or increase liquidity,
+    /// 1 decrease liquidity, 2 collect fee, 3 collect reward, 4 swap, 5 flash, which stays
+    /// disabled until enabled
+    SetPoolStatusBit {
+        pool_id: Pubkey,
+        bit: u8,
+        /// Disable the operation instead of enabling it
+        #[arg(long)]
+        disable: bool,
+    },
     CreatePool {
         config_index: u16,
         price: f64,
@@ -408,6 +668,14 @@ pub enum CommandsName {
         #[arg(short, long, default_value_t = 0)]
         open_time: u64,
     },
+    /// Report the accounts, rent and initial tick of create-pool with the same arguments, and
+    /// what could make it fail or surprise, without sending anything
+    EstimateCreatePool {
+        config_index: u16,
+        price: f64,
+        mint0: Pubkey,
+        mint1: Pubkey,
+    },
     InitReward {
         open_time: u64,
         end_time: u64,
@@ -436,6 +704,43 @@ pub enum CommandsName {
         input_amount: u64,
         #[arg(short, long)]
         with_metadata: bool,
+        /// Open the position for this owner, it receives the position nft while the payer funds
+        /// the deposits and the rent
+        #[arg(long)]
+        owner: Option<Pubkey>,
+    },
+    /// Split the price range into contiguous positions of the same liquidity, opened in as few
+    /// transactions as fit
+    LadderOpen {
+        tick_lower_price: f64,
+        tick_upper_price: f64,
+        num_buckets: usize,
+        /// The value of all the positions in raw token_1, or in raw token_0 with --is-base-0
+        total_amount: u64,
+        #[arg(short, long)]
+        is_base_0: bool,
+        #[arg(short, long)]
+        with_metadata: bool,
+    },
+    /// Open a one tick spacing wide position at the price holding only the token sold, it is
+    /// converted to the other token as the market crosses the range
+    PlaceRangeOrder {
+        price: f64,
+        /// Of token_0 for a sell, of token_1 for a buy
+        amount: u64,
+        #[arg(value_enum)]
+        side: RangeOrderSide,
+    },
+    /// Report how much of a range order placed by `place-range-order` has been filled
+    CheckRangeOrder {
+        nft_mint: Pubkey,
+        #[arg(value_enum)]
+        side: RangeOrderSide,
+    },
+    /// Report the tick arrays opening a position in the range creates and their rent
+    EstimateOpenCost {
+        tick_lower_price: f64,
+        tick_upper_price: f64,
     },
     IncreaseLiquidity {
         tick_lower_price: f64,
@@ -444,36 +749,76 @@ pub enum CommandsName {
         is_base_0: bool,
         imput_amount: u64,
     },
+    /// Remove liquidity from the position, all of it by default. The position and its nft are
+    /// closed in the same transaction once nothing is left in it
     DecreaseLiquidity {
         tick_lower_index: i32,
         tick_upper_index: i32,
         liquidity: Option<u128>,
-        #[arg(short, long)]
-        simulate: bool,
+        /// Keep the position and its nft even if the decrease empties it
+        #[arg(long)]
+        keep_position: bool,
     },
     Swap {
         input_token: Pubkey,
         output_token: Pubkey,
         #[arg(short, long)]
         base_in: bool,
-        #[arg(short, long)]
-        simulate: bool,
+        /// Print the expected amounts, fees and price impact computed off-chain, nothing is built
+        /// or sent
+        #[arg(long)]
+        quote_only: bool,
         amount: u64,
         limit_price: Option<f64>,
+        /// Stop the swap at this tick instead of a limit price
+        #[arg(long, conflicts_with = "limit_price")]
+        limit_tick: Option<i32>,
+        /// Tolerance of the quoted other amount in basis points, default is the slippage of
+        /// client_config.ini
+        #[arg(long)]
+        slippage_bps: Option<u16>,
     },
     SwapV2 {
         input_token: Pubkey,
         output_token: Pubkey,
         #[arg(short, long)]
         base_in: bool,
-        #[arg(short, long)]
-        simulate: bool,
+        /// Print the expected amounts, fees and price impact computed off-chain, nothing is built
+        /// or sent
+        #[arg(long)]
+        quote_only: bool,
         amount: u64,
         limit_price: Option<f64>,
+        /// Stop the swap at this tick instead of a limit price
+        #[arg(long, conflicts_with = "limit_price")]
+        limit_tick: Option<i32>,
+        /// Tolerance of the quoted other amount in basis points, default is the slippage of
+        /// client_config.ini
+        #[arg(long)]
+        slippage_bps: Option<u16>,
+    },
+    /// Swap across the pools in order, split into several transactions if the route doesn't fit
+    /// in one
+    SwapRouter {
+        input_token: Pubkey,
+        amount_in: u64,
+        #[arg(short, long, value_delimiter = ',')]
+        pools: Vec<Pubkey>,
+        /// Address lookup table holding the route accounts
+        #[arg(short, long)]
+        lookup_table: Option<Pubkey>,
     },
     PPositionByOwner {
         user_wallet: Pubkey,
     },
+    /// Value every position nft of the owner across all pools: the amounts of the liquidity at the
+    /// current price and the fees and rewards collectable now, with the totals per mint
+    Portfolio {
+        owner: Pubkey,
+        /// Also write the portfolio as json to this file
+        #[arg(long)]
+        json: Option<String>,
+    },
     PTickState {
         tick: i32,
         pool_id: Option<Pubkey>,
@@ -490,8 +835,25 @@ pub enum CommandsName {
     },
     POperation,
     PObservation,
+    /// Print the time weighted average price of the pool over the last seconds, read from the
+    /// observations of the pool
+    Twap {
+        pool_id: Option<Pubkey>,
+        #[arg(short, long, default_value_t = 300)]
+        seconds: u32,
+        /// End the window this many seconds ago instead of now
+        #[arg(long, default_value_t = 0)]
+        until: u32,
+    },
     PConfig {
         config_index: u16,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// List every amm config of the program, several can share a tick spacing or a fee rate
+    ListConfigs {
+        #[command(flatten)]
+        output: OutputArgs,
     },
     PriceToTick {
         price: f64,
@@ -514,6 +876,8 @@ pub enum CommandsName {
     },
     PPersonalPositionByPool {
         pool_id: Option<Pubkey>,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     PProtocolPositionByPool {
         pool_id: Option<Pubkey>,
@@ -523,6 +887,50 @@ pub enum CommandsName {
     },
     PPool {
         pool_id: Option<Pubkey>,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    /// Print the token amounts a position is worth at the current price and the fees and rewards
+    /// owed to it, as computed on chain by a simulated `get_position_amounts`
+    PPositionAmounts {
+        nft_mint: Pubkey,
+    },
+    /// Print the taker buy and sell volume of the pool
+    PPoolFlow {
+        pool_id: Option<Pubkey>,
+    },
+    /// Create the account recording the swap volume and fees of the pool over the last 24 hours
+    CreatePoolStats {
+        pool_id: Option<Pubkey>,
+    },
+    /// Drop the expired buckets of the pool stats, for a pool that hasn't swapped in a while
+    RollStats {
+        pool_id: Option<Pubkey>,
+    },
+    /// Print the swap volume and fees of the pool over the last 24 hours
+    PPoolStats {
+        pool_id: Option<Pubkey>,
+    },
+    /// Print the schedule, funding and projected depletion of each reward of the pool
+    PReward {
+        pool_id: Option<Pubkey>,
+    },
+    /// Estimate the APR of the trade fees and of each reward of the pool over the last 24h and
+    /// 7d, for liquidity over the range, the full range by default
+    RewardApr {
+        pool_id: Option<Pubkey>,
+        #[arg(long, allow_hyphen_values = true)]
+        tick_lower: Option<i32>,
+        #[arg(long, allow_hyphen_values = true)]
+        tick_upper: Option<i32>,
+        /// The price in token_1 of a reward that is neither of the pool tokens, as <mint>=<price>
+        #[arg(long = "reward-price", value_parser = parse_reward_price)]
+        reward_prices: Vec<(Pubkey, f64)>,
+        /// Decode the swaps of at most this many of the latest transactions of the pool
+        #[arg(long, default_value_t = 1000)]
+        max_transactions: usize,
+        #[command(flatten)]
+        output: OutputArgs,
     },
     PBitmapExtension {
         bitmap_extension: Option<Pubkey>,
@@ -533,6 +941,17 @@ pub enum CommandsName {
     PPersonal {
         personal_id: Pubkey,
     },
+    GenRewardMerkle {
+        reward_index: u8,
+        #[arg(short, long)]
+        out_file: Option<String>,
+    },
+    SetRewardMerkleRoot {
+        snapshot_file: String,
+    },
+    ClaimMerkleReward {
+        snapshot_file: String,
+    },
     DecodeInstruction {
         instr_hex_data: String,
     },
@@ -542,17 +961,328 @@ pub enum CommandsName {
     DecodeTxLog {
         tx_id: String,
     },
+    /// Print the events the program emitted in a transaction as typed structs
+    DecodeLogs {
+        signature: Signature,
+    },
+    /// Stream the price, liquidity and tick transitions of the pool over the websocket, with the
+    /// updates of its observation and the tick arrays around the current tick
+    WatchPool {
+        pool_id: Option<Pubkey>,
+        /// Tick arrays watched on each side of the tick array of the current tick
+        #[arg(long, default_value_t = 1)]
+        arrays_around: u8,
+    },
+    /// Measure rpc and websocket latency and simulate a small swap on the configured pool
+    Diagnose {
+        /// Number of samples of every rpc request
+        #[arg(short, long, default_value_t = 5)]
+        samples: u32,
+        /// Amount of token_0 swapped in the simulation, the simulation is skipped if not set
+        #[arg(long)]
+        swap_amount: Option<u64>,
+    },
+    /// Export the pool, observation, tick arrays and positions with their merkle root
+    ExportSnapshot {
+        pool_id: Option<Pubkey>,
+        #[arg(short, long)]
+        out_file: Option<String>,
+    },
+    /// Re-derive the snapshot from chain and compare it with the snapshot file
+    VerifySnapshot {
+        snapshot_file: String,
+    },
+    /// Write the pool state, initialized ticks and positions of the pool with the data of all its
+    /// accounts as deterministic json, to replay swaps locally or compute lp incentives
+    SnapshotPool {
+        pool_id: Pubkey,
+        out_file: String,
+    },
+    /// Replay the swaps of a json file, a list of `{amount, zero_for_one, base_in, seconds}`, on a
+    /// `snapshot-pool` file off-chain with the swap logic of the program. Reports the final price
+    /// and the fees earned by the pool and by each position
+    Replay {
+        snapshot_file: String,
+        swaps_file: String,
+        /// Also write the report as json to this file
+        #[arg(long)]
+        out_file: Option<String>,
+    },
+    /// Quote the pools of a mint pair in parallel and rank them by the output amount
+    BestPool {
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        amount_in: u64,
+        /// Candidate pools, every pool of the pair is quoted if not set
+        #[arg(long)]
+        pools: Vec<Pubkey>,
+    },
+    /// Walk through creating the config, operation account, mints, pool, a full range position
+    /// and a test swap, validating every step and writing a summary file
+    InitWizard {
+        #[arg(long, default_value_t = 0)]
+        config_index: u16,
+        #[arg(long, default_value_t = 10)]
+        tick_spacing: u16,
+        #[arg(long, default_value_t = 2500)]
+        trade_fee_rate: u32,
+        #[arg(long, default_value_t = 120000)]
+        protocol_fee_rate: u32,
+        #[arg(long, default_value_t = 40000)]
+        fund_fee_rate: u32,
+        /// Price of token_1 in token_0
+        #[arg(long, default_value_t = 1.0)]
+        price: f64,
+        /// Existing mint of the pool, test mints are created on devnet if not set
+        #[arg(long)]
+        mint0: Option<Pubkey>,
+        #[arg(long)]
+        mint1: Option<Pubkey>,
+        #[arg(long, default_value_t = 6)]
+        decimals: u8,
+        #[arg(long, default_value_t = 1_000_000_000_000)]
+        mint_amount: u64,
+        /// Token_0 amount of the full range position
+        #[arg(long, default_value_t = 100_000_000_000)]
+        position_amount: u64,
+        /// Token_0 amount of the test swap
+        #[arg(long, default_value_t = 1_000_000)]
+        swap_amount: u64,
+        #[arg(long, default_value = "init_wizard_summary.json")]
+        summary_file: String,
+        /// Accept every default without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Read the accounts from every configured rpc endpoint and check they agree
+    QuorumRead {
+        keys: Vec<Pubkey>,
+    },
+    /// Compare the hash of the deployed program with a build of the repo at a commit
+    VerifyBuild {
+        /// The commit to build
+        #[arg(long, default_value = "HEAD")]
+        commit: String,
+        /// The repo to build from
+        #[arg(long, default_value = ".")]
+        repo: String,
+        /// Compare a prebuilt program instead of building the commit
+        #[arg(long)]
+        so_path: Option<String>,
+    },
+    /// Write the json manifest of the pools integrators route through: accounts, mints, fees,
+    /// depth around the current price and the observation config
+    PoolManifest {
+        /// The configured pool if none is set
+        pool_ids: Vec<Pubkey>,
+        /// Directory the manifests are written to, one `pool_manifest_<pool_id>.json` per pool
+        #[arg(short, long, default_value = ".")]
+        out_dir: String,
+    },
+    /// Export the opens, increases, decreases and fee and reward collections of the positions of
+    /// an owner as csv, one row per token moved
+    ExportHistory {
+        owner: Pubkey,
+        /// Only the transactions from this date on, YYYY-MM-DD in UTC
+        #[arg(long)]
+        from: Option<String>,
+        #[arg(short, long, default_value = "position_history.csv")]
+        out_file: String,
+    },
+    /// Execute the admin actions of a json plan file at their times, simulating each first. The
+    /// executed actions are recorded in <plan_file>.state.
+    RunSchedule {
+        plan_file: String,
+        /// Shell command run on every event with SCHEDULE_EVENT, SCHEDULE_LABEL and
+        /// SCHEDULE_MESSAGE set
+        #[arg(long)]
+        notify_cmd: Option<String>,
+        /// Only build and simulate the pending actions
+        #[arg(long)]
+        dry_run: bool,
+        #[arg(long, default_value_t = 10)]
+        poll_interval: u64,
+        /// Simulations of an action before it's given up
+        #[arg(long, default_value_t = 3)]
+        attempts: u32,
+    },
+    /// Remove all liquidity of every position of the payer in the pool, collecting the fees and
+    /// rewards, then close the positions
+    CloseAllPositions {
+        pool_id: Pubkey,
+        /// Slippage of the withdrawn amounts, the configured one if not set
+        #[arg(long)]
+        slippage: Option<f64>,
+        /// Send without confirming the positions to close
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Collect the fees and rewards of a position of the payer without changing its liquidity,
+    /// the claimable fees are printed first
+    CollectFees {
+        nft_mint: Pubkey,
+    },
+    /// Collect the rewards owed to a position of the payer with `collect_reward`, the liquidity
+    /// and the fees are not touched
+    CollectReward {
+        nft_mint: Pubkey,
+    },
+    /// Add the fees owed to a position of the payer to its liquidity with `compound_fees`, the
+    /// fees the liquidity doesn't use stay owed
+    CompoundFees {
+        nft_mint: Pubkey,
+    },
+    /// Move the liquidity of a position of the payer into another of its positions, of the same
+    /// pair in a pool of another fee tier, in one transaction
+    MigratePosition {
+        source_nft_mint: Pubkey,
+        target_nft_mint: Pubkey,
+        /// The liquidity moved, all of the source position if not set
+        #[arg(long)]
+        liquidity: Option<u128>,
+    },
+    /// Withdraw the lp tokens of the payer from a legacy constant product pool and open a
+    /// position of the same pair with the withdrawn amounts
+    MigrateFromV2 {
+        /// The legacy pool
+        amm_id: Pubkey,
+        /// The pool the position is opened in, the configured one if not set
+        #[arg(long)]
+        pool_id: Option<Pubkey>,
+        /// The lp tokens withdrawn, all of the payer's if not set
+        #[arg(long)]
+        lp_amount: Option<u64>,
+        /// The range of the position, this percent of the price either way, the full range if not
+        /// set
+        #[arg(long)]
+        range_percent: Option<f64>,
+        /// Slippage of the withdrawn and deposited amounts and of the price of the legacy pool, the
+        /// configured one if not set
+        #[arg(long)]
+        slippage: Option<f64>,
+        /// Send without confirming the migration
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Send the fees and rewards of a position of the payer to the token accounts of another
+    /// owner, or clear it when no recipient is given
+    SetFeeRecipient {
+        nft_mint: Pubkey,
+        fee_recipient: Option<Pubkey>,
+    },
+    /// Transfer a position nft of the payer to a new owner, clearing its fee recipient
+    TransferPosition {
+        nft_mint: Pubkey,
+        new_owner: Pubkey,
+    },
+    /// Lock a position nft of the payer in the custody of the program until the unix timestamp
+    LockPosition {
+        nft_mint: Pubkey,
+        unlock_time: u64,
+    },
+    /// Return a locked position nft to the payer once its unlock time is reached
+    UnlockPosition {
+        nft_mint: Pubkey,
+    },
+    /// Collect the fees and rewards of a position locked by the payer, it stays locked
+    CollectLockedPosition {
+        nft_mint: Pubkey,
+    },
+    /// Rewrite the metadata of a position nft of the payer with the current name, symbol and uri
+    UpdatePositionMetadata {
+        nft_mint: Pubkey,
+    },
+    /// Airdrop sol to the payer below the [Faucet] threshold and mint it the test tokens,
+    /// devnet and localnet only
+    Faucet,
+}
+
+impl CommandsName {
+    /// The commands the payer needs sol and test tokens for, funded first when [Faucet] auto is set
+    /// The command sends its transactions through a path honoring --simulate
+    fn can_simulate(&self) -> bool {
+        !matches!(
+            self,
+            CommandsName::InitWizard { .. }
+                | CommandsName::Faucet
+                | CommandsName::MigrateFromV2 { .. }
+        )
+    }
+
+    fn spends_tokens(&self) -> bool {
+        matches!(
+            self,
+            CommandsName::CreatePool { .. }
+                | CommandsName::InitReward { .. }
+                | CommandsName::OpenPosition { .. }
+                | CommandsName::PlaceRangeOrder { .. }
+                | CommandsName::LadderOpen { .. }
+                | CommandsName::IncreaseLiquidity { .. }
+                | CommandsName::Swap { .. }
+                | CommandsName::SwapV2 { .. }
+                | CommandsName::SwapRouter { .. }
+                | CommandsName::RunSchedule { .. }
+        )
+    }
 }
 // #[cfg(not(feature = "async"))]
 fn main() -> Result<()> {
-    println!("Starting...");
+    let opts = Opts::parse();
+    init_telemetry(opts.log_format, opts.log_filter.as_deref())?;
+    // every line of the run carries the command and the run id
+    let command_name = format!("{:?}", opts.command);
+    let command_span = info_span!(
+        "command",
+        name = command_name
+            .split(|c: char| !c.is_alphanumeric())
+            .next()
+            .unwrap_or_default(),
+        run_id = %new_correlation_id()
+    );
+    let _enter = command_span.enter();
+    info!("Starting...");
     let client_config = "client_config.ini";
-    let pool_config = load_cfg(&client_config.to_string()).unwrap();
+    let pool_config = load_cfg(
+        &client_config.to_string(),
+        opts.profile.clone(),
+        opts.wallet.clone(),
+        opts.program_id,
+    )
+    .unwrap();
     // Admin and cluster params.
     let payer = read_keypair_file(&pool_config.payer_path)?;
     let admin = read_keypair_file(&pool_config.admin_path)?;
+    // the primary endpoint first, then the fallbacks
+    let rpc_pool = RpcPool::new(
+        &[
+            vec![pool_config.http_url.clone()],
+            pool_config.fallback_http_urls.clone(),
+        ]
+        .concat(),
+        &[
+            vec![pool_config.ws_url.clone()],
+            pool_config.fallback_ws_urls.clone(),
+        ]
+        .concat(),
+        pool_config.read_quorum,
+    )?;
+    // commands talk to the first healthy endpoint, so a degraded primary doesn't stop them
+    let endpoint = rpc_pool.first_healthy()?;
+    if endpoint.http_url != pool_config.http_url {
+        warn!(
+            "rpc {} is unavailable, failing over to {}",
+            pool_config.http_url, endpoint.http_url
+        );
+    }
+    let pool_config = ClientConfig {
+        http_url: endpoint.http_url.clone(),
+        ws_url: endpoint.ws_url.clone(),
+        ..pool_config
+    };
     // solana rpc client
     let rpc_client = RpcClient::new(pool_config.http_url.to_string());
+    // paces the reads of the bulk commands
+    let scanner = RpcScanner::new(pool_config.rpc_scan);
 
     // anchor client.
     let anchor_config = pool_config.clone();
@@ -561,7 +1291,16 @@ fn main() -> Result<()> {
     let anchor_client = Client::new(url, Rc::new(wallet));
     let program = anchor_client.program(pool_config.raydium_v3_program)?;
 
-    let opts = Opts::parse();
+    let simulate = opts.simulate;
+    if simulate && !opts.command.can_simulate() {
+        return Err(format_err!(
+            "the command can't be simulated, its transactions depend on each other"
+        ));
+    }
+    if pool_config.faucet.auto && !simulate && opts.command.spends_tokens() {
+        run_faucet(&rpc_client, &pool_config, &payer)?;
+    }
+
     match opts.command {
         CommandsName::NewMint {
             authority,
@@ -655,13 +1394,17 @@ fn main() -> Result<()> {
             let signers = vec![&payer, &mint];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_and_init_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_and_init_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::NewToken {
             mint,
@@ -690,13 +1433,17 @@ fn main() -> Result<()> {
             // send
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_ata_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_ata_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::MintTo {
             mint,
@@ -716,13 +1463,17 @@ fn main() -> Result<()> {
             let signers = vec![&payer];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &mint_to_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &mint_to_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::WrapSol { amount } => {
             let wrap_sol_instr = wrap_sol_instr(&pool_config, amount)?;
@@ -730,13 +1481,17 @@ fn main() -> Result<()> {
             let signers = vec![&payer];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &wrap_sol_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &wrap_sol_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::UnWrapSol { wrap_sol_account } => {
             let unwrap_sol_instr =
@@ -745,13 +1500,17 @@ fn main() -> Result<()> {
             let signers = vec![&payer];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &unwrap_sol_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &unwrap_sol_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::CreateConfig {
             config_index,
@@ -772,19 +1531,24 @@ fn main() -> Result<()> {
             let signers = vec![&payer, &admin];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::UpdateConfig {
             config_index,
             param,
             value,
             remaining,
+            yes,
         } => {
             let mut remaing_accounts = Vec::new();
             let mut update_value = 0;
@@ -793,6 +1557,8 @@ fn main() -> Result<()> {
                 Some(0) => update_value = value,
                 Some(1) => update_value = value,
                 Some(2) => update_value = value,
+                Some(5) => update_value = value,
+                Some(6) => update_value = value,
                 Some(3) => {
                     let remaining_key = remaining.unwrap();
                     remaing_accounts.push(AccountMeta::new_readonly(remaining_key, false));
@@ -810,6 +1576,16 @@ fn main() -> Result<()> {
                 ],
                 &pool_config.raydium_v3_program,
             );
+            // show what the raw param and value change before sending them
+            let amm_config = deserialize_anchor_account::<raydium_amm_v3::states::AmmConfig>(
+                &rpc_client.get_account(&amm_config_key)?,
+            )?;
+            let updated_config = apply_amm_config_update(&amm_config, param, value, remaining)?;
+            let changes = amm_config_diff(&amm_config, &updated_config);
+            if !confirm_config_diff(&changes, yes)? {
+                info!("update of {} aborted", amm_config_key);
+                return Ok(());
+            }
             let update_amm_config_instr = update_amm_config_instr(
                 &pool_config.clone(),
                 amm_config_key,
@@ -821,67 +1597,80 @@ fn main() -> Result<()> {
             let signers = vec![&payer, &admin];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &update_amm_config_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &update_amm_config_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
-        CommandsName::CreateOperation => {
-            let create_instr = create_operation_account_instr(&pool_config.clone())?;
+        CommandsName::UpdatePoolCreationFee {
+            config_index,
+            pool_creation_fee,
+        } => {
+            let (amm_config_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::AMM_CONFIG_SEED.as_bytes(),
+                    &config_index.to_be_bytes(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let update_instr = update_pool_creation_fee_instr(
+                &pool_config.clone(),
+                amm_config_key,
+                pool_creation_fee,
+            )?;
             // send
             let signers = vec![&payer, &admin];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &update_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
-        CommandsName::UpdateOperation { param, keys } => {
-            let create_instr = update_operation_account_instr(&pool_config.clone(), param, keys)?;
+        CommandsName::ProposeConfigOwner {
+            config_index,
+            new_owner,
+        } => {
+            let (amm_config_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::AMM_CONFIG_SEED.as_bytes(),
+                    &config_index.to_be_bytes(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let propose_instr =
+                propose_config_owner_instr(&pool_config.clone(), amm_config_key, new_owner)?;
             // send
             let signers = vec![&payer, &admin];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &propose_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
-        CommandsName::CreatePool {
-            config_index,
-            price,
-            mint0,
-            mint1,
-            open_time,
-        } => {
-            let mut price = price;
-            let mut mint0 = mint0;
-            let mut mint1 = mint1;
-            if mint0 > mint1 {
-                std::mem::swap(&mut mint0, &mut mint1);
-                price = 1.0 / price;
-            }
-            println!("mint0:{}, mint1:{}, price:{}", mint0, mint1, price);
-            let load_pubkeys = vec![mint0, mint1];
-            let rsps = rpc_client.get_multiple_accounts(&load_pubkeys)?;
-            let mint0_owner = rsps[0].clone().unwrap().owner;
-            let mint1_owner = rsps[1].clone().unwrap().owner;
-            let mint0_account =
-                spl_token::state::Mint::unpack(&rsps[0].as_ref().unwrap().data).unwrap();
-            let mint1_account =
-                spl_token::state::Mint::unpack(&rsps[1].as_ref().unwrap().data).unwrap();
-            let sqrt_price_x64 =
-                price_to_sqrt_price_x64(price, mint0_account.decimals, mint1_account.decimals);
+        CommandsName::AcceptConfigOwner { config_index } => {
             let (amm_config_key, __bump) = Pubkey::find_program_address(
                 &[
                     raydium_amm_v3::states::AMM_CONFIG_SEED.as_bytes(),
@@ -889,46 +1678,214 @@ fn main() -> Result<()> {
                 ],
                 &pool_config.raydium_v3_program,
             );
-            let tick = tick_math::get_tick_at_sqrt_price(sqrt_price_x64).unwrap();
-            println!(
-                "tick:{}, price:{}, sqrt_price_x64:{}, amm_config_key:{}",
-                tick, price, sqrt_price_x64, amm_config_key
-            );
-
-            let create_pool_instr = create_pool_instr(
-                &pool_config.clone(),
-                amm_config_key,
-                mint0,
-                mint1,
-                mint0_owner,
-                mint1_owner,
-                pool_config.tickarray_bitmap_extension.unwrap(),
-                sqrt_price_x64,
-                open_time,
-            )?;
-
+            let accept_instr = accept_config_owner_instr(&pool_config.clone(), amm_config_key)?;
             // send
             let signers = vec![&payer];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_pool_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &accept_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
-        CommandsName::InitReward {
-            open_time,
-            end_time,
-            emissions,
+        CommandsName::SetProtocolFeeSplit {
+            config_index,
+            primary_destination,
+            secondary_destination,
+            split_rate,
+        } => {
+            let (amm_config_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::AMM_CONFIG_SEED.as_bytes(),
+                    &config_index.to_be_bytes(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let split_instr = set_protocol_fee_split_instr(
+                &pool_config.clone(),
+                amm_config_key,
+                primary_destination,
+                secondary_destination,
+                split_rate,
+            )?;
+            // send
+            let signers = vec![&payer, &admin];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &split_instr,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::CreateOperation => {
+            let create_instr = create_operation_account_instr(&pool_config.clone())?;
+            // send
+            let signers = vec![&payer, &admin];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_instr,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::UpdateOperation { param, keys } => {
+            let create_instr = update_operation_account_instr(&pool_config.clone(), param, keys)?;
+            // send
+            let signers = vec![&payer, &admin];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_instr,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::SetPoolStatusBit {
+            pool_id,
+            bit,
+            disable,
+        } => {
+            let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&pool_id)?,
+            )?;
+            info!("pool:{}, status:{:#010b}", pool_id, pool_state.status);
+            let set_instr =
+                set_pool_status_bit_instr(&pool_config.clone(), pool_id, bit, !disable)?;
+            // send
+            let signers = vec![&payer, &admin];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &set_instr,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::CreatePool {
+            config_index,
+            price,
+            mint0,
+            mint1,
+            open_time,
+        } => {
+            let pair = CanonicalPair::new(mint0, mint1);
+            let (mint0, mint1, price) = (pair.mint0, pair.mint1, pair.pool_price(price));
+            info!("mint0:{}, mint1:{}, price:{}", mint0, mint1, price);
+            let load_pubkeys = vec![mint0, mint1];
+            let rsps = rpc_client.get_multiple_accounts(&load_pubkeys)?;
+            let mint0_owner = rsps[0].clone().unwrap().owner;
+            let mint1_owner = rsps[1].clone().unwrap().owner;
+            let mint0_account =
+                spl_token::state::Mint::unpack(&rsps[0].as_ref().unwrap().data).unwrap();
+            let mint1_account =
+                spl_token::state::Mint::unpack(&rsps[1].as_ref().unwrap().data).unwrap();
+            let sqrt_price_x64 =
+                price_to_sqrt_price_x64(price, mint0_account.decimals, mint1_account.decimals);
+            let (amm_config_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::AMM_CONFIG_SEED.as_bytes(),
+                    &config_index.to_be_bytes(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let tick = tick_math::get_tick_at_sqrt_price(sqrt_price_x64).unwrap();
+            info!(
+                "tick:{}, price:{}, sqrt_price_x64:{}, amm_config_key:{}",
+                tick, price, sqrt_price_x64, amm_config_key
+            );
+
+            // the creation fee is paid to the fund owner of the config
+            let amm_config = deserialize_anchor_account::<raydium_amm_v3::states::AmmConfig>(
+                &rpc_client.get_account(&amm_config_key)?,
+            )?;
+            let create_pool_instr = create_pool_instr(
+                &pool_config.clone(),
+                amm_config_key,
+                mint0,
+                mint1,
+                mint0_owner,
+                mint1_owner,
+                pool_config.tickarray_bitmap_extension.unwrap(),
+                (amm_config.pool_creation_fee > 0).then_some(amm_config.fund_owner),
+                sqrt_price_x64,
+                open_time,
+            )?;
+
+            // send
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_pool_instr,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::EstimateCreatePool {
+            config_index,
+            price,
+            mint0,
+            mint1,
+        } => {
+            let estimate = estimate_create_pool(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                config_index,
+                mint0,
+                mint1,
+                price,
+            )?;
+            info!("{}", estimate.report());
+        }
+        CommandsName::InitReward {
+            open_time,
+            end_time,
+            emissions,
             reward_mint,
         } => {
             let mint_account = rpc_client.get_account(&reward_mint)?;
             let emissions_per_second_x64 = (emissions * fixed_point_64::Q64 as f64) as u128;
             let program = anchor_client.program(pool_config.raydium_v3_program)?;
-            println!("{}", pool_config.pool_id_account.unwrap());
+            info!("{}", pool_config.pool_id_account.unwrap());
             let pool_account: raydium_amm_v3::states::PoolState =
                 program.account(pool_config.pool_id_account.unwrap())?;
             let operator_account_key = Pubkey::find_program_address(
@@ -964,13 +1921,17 @@ fn main() -> Result<()> {
             let signers = vec![&payer, &admin];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::SetRewardParams {
             index,
@@ -982,7 +1943,7 @@ fn main() -> Result<()> {
             let emissions_per_second_x64 = (emissions * fixed_point_64::Q64 as f64) as u128;
 
             let program = anchor_client.program(pool_config.raydium_v3_program)?;
-            println!("{}", pool_config.pool_id_account.unwrap());
+            info!("{}", pool_config.pool_id_account.unwrap());
             let pool_account: raydium_amm_v3::states::PoolState =
                 program.account(pool_config.pool_id_account.unwrap())?;
             let operator_account_key = Pubkey::find_program_address(
@@ -1017,13 +1978,17 @@ fn main() -> Result<()> {
             let signers = vec![&payer, &admin];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &create_instr,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &create_instr,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
             );
-            let signature = send_txn(&rpc_client, &txn, true)?;
-            println!("{}", signature);
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
         }
         CommandsName::TransferRewardOwner {
             pool_id,
@@ -1035,26 +2000,30 @@ fn main() -> Result<()> {
                 transfer_reward_owner(&pool_config.clone(), pool_id, new_owner, encode, authority)
                     .unwrap();
             if encode {
-                println!(
+                info!(
                     "instruction.data:{:?}",
                     transfer_reward_owner_instrs[0].data
                 );
                 let message = Message::new(&transfer_reward_owner_instrs, None);
                 let serialize_data = serialize(&message).unwrap();
                 let raw_data = bs58::encode(serialize_data).into_string();
-                println!("raw_data:{:?}", raw_data);
+                info!("raw_data:{:?}", raw_data);
             } else {
                 // send
                 let signers = vec![&payer, &admin];
                 let recent_hash = rpc_client.get_latest_blockhash()?;
                 let txn = Transaction::new_signed_with_payer(
-                    &transfer_reward_owner_instrs,
+                    &with_compute_budget(
+                        &rpc_client,
+                        &pool_config.compute_budget,
+                        &payer.pubkey(),
+                        &transfer_reward_owner_instrs,
+                    )?,
                     Some(&payer.pubkey()),
                     &signers,
                     recent_hash,
                 );
-                let signature = send_txn(&rpc_client, &txn, true)?;
-                println!("{}", signature);
+                send_or_simulate_txn(&rpc_client, &txn, simulate)?;
             }
         }
         CommandsName::OpenPosition {
@@ -1063,7 +2032,9 @@ fn main() -> Result<()> {
             is_base_0,
             input_amount,
             with_metadata,
+            owner,
         } => {
+            let position_owner = owner.unwrap_or(payer.pubkey());
             // load pool to get observation
             let pool: raydium_amm_v3::states::PoolState =
                 program.account(pool_config.pool_id_account.unwrap())?;
@@ -1086,43 +2057,38 @@ fn main() -> Result<()> {
                 tick_math::get_tick_at_sqrt_price(tick_upper_price_x64)?,
                 pool.tick_spacing.into(),
             );
-            println!(
+            info!(
                 "tick_lower_index:{}, tick_upper_index:{}",
                 tick_lower_index, tick_upper_index
             );
-            let tick_lower_price_x64 = tick_math::get_sqrt_price_at_tick(tick_lower_index)?;
-            let tick_upper_price_x64 = tick_math::get_sqrt_price_at_tick(tick_upper_index)?;
-            let liquidity = if is_base_0 {
-                liquidity_math::get_liquidity_from_single_amount_0(
-                    pool.sqrt_price_x64,
-                    tick_lower_price_x64,
-                    tick_upper_price_x64,
-                    input_amount,
-                )
-            } else {
-                liquidity_math::get_liquidity_from_single_amount_1(
-                    pool.sqrt_price_x64,
-                    tick_lower_price_x64,
-                    tick_upper_price_x64,
-                    input_amount,
-                )
-            };
-            let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
-                pool.tick_current,
-                pool.sqrt_price_x64,
+            let open_cost = estimate_open_cost(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                &pool_config.pool_id_account.unwrap(),
                 tick_lower_index,
                 tick_upper_index,
-                liquidity as i128,
             )?;
-            println!(
+            info!("{}", open_cost.report());
+            // same calculation as the program, so the amounts pass its slippage check
+            let (liquidity, amount_0, amount_1) =
+                liquidity_math::get_liquidity_and_amounts_from_single_amount(
+                    pool.tick_current,
+                    pool.sqrt_price_x64,
+                    tick_lower_index,
+                    tick_upper_index,
+                    input_amount,
+                    is_base_0,
+                )?;
+            info!(
                 "amount_0:{}, amount_1:{}, liquidity:{}",
                 amount_0, amount_1, liquidity
             );
             // calc with slippage
+            let tolerance_rate = slippage_to_tolerance_rate(pool_config.slippage);
             let amount_0_with_slippage =
-                amount_with_slippage(amount_0 as u64, pool_config.slippage, true);
+                liquidity_math::amount_with_tolerance(amount_0, tolerance_rate);
             let amount_1_with_slippage =
-                amount_with_slippage(amount_1 as u64, pool_config.slippage, true);
+                liquidity_math::amount_with_tolerance(amount_1, tolerance_rate);
             // calc with transfer_fee
             let transfer_fee = get_pool_mints_inverse_fee(
                 &rpc_client,
@@ -1131,7 +2097,7 @@ fn main() -> Result<()> {
                 amount_0_with_slippage,
                 amount_1_with_slippage,
             );
-            println!(
+            info!(
                 "transfer_fee_0:{}, transfer_fee_1:{}",
                 transfer_fee.0.transfer_fee, transfer_fee.1.transfer_fee
             );
@@ -1155,14 +2121,14 @@ fn main() -> Result<()> {
             // load position
             let position_nft_infos = get_all_nft_and_position_by_owner(
                 &rpc_client,
-                &payer.pubkey(),
+                &position_owner,
                 &pool_config.raydium_v3_program,
             );
             let positions: Vec<Pubkey> = position_nft_infos
                 .iter()
                 .map(|item| item.position)
                 .collect();
-            let rsps = rpc_client.get_multiple_accounts(&positions)?;
+            let rsps = scanner.get_multiple_accounts(&rpc_client, &positions)?;
             let mut user_positions = Vec::new();
             for rsp in rsps {
                 match rsp {
@@ -1195,9 +2161,6 @@ fn main() -> Result<()> {
                 ));
 
                 let mut instructions = Vec::new();
-                let request_inits_instr =
-                    ComputeBudgetInstruction::set_compute_unit_limit(1400_000u32);
-                instructions.push(request_inits_instr);
                 let open_position_instr = open_position_with_token22_nft_instr(
                     &pool_config.clone(),
                     pool_config.pool_id_account.unwrap(),
@@ -1206,7 +2169,7 @@ fn main() -> Result<()> {
                     pool.token_mint_0,
                     pool.token_mint_1,
                     nft_mint.pubkey(),
-                    payer.pubkey(),
+                    position_owner,
                     spl_associated_token_account::get_associated_token_address_with_program_id(
                         &payer.pubkey(),
                         &pool_config.mint0.unwrap(),
@@ -1232,108 +2195,111 @@ fn main() -> Result<()> {
                 let signers = vec![&payer, &nft_mint];
                 let recent_hash = rpc_client.get_latest_blockhash()?;
                 let txn = Transaction::new_signed_with_payer(
-                    &instructions,
+                    &with_compute_budget(
+                        &rpc_client,
+                        &pool_config.compute_budget,
+                        &payer.pubkey(),
+                        &instructions,
+                    )?,
                     Some(&payer.pubkey()),
                     &signers,
                     recent_hash,
                 );
-                let signature = send_txn(&rpc_client, &txn, true)?;
-                println!("{}", signature);
+                send_or_simulate_txn(&rpc_client, &txn, simulate)?;
             } else {
                 // personal position exist
-                println!("personal position exist:{:?}", find_position);
+                info!("personal position exist:{:?}", find_position);
             }
         }
-        CommandsName::IncreaseLiquidity {
+        CommandsName::LadderOpen {
             tick_lower_price,
             tick_upper_price,
+            num_buckets,
+            total_amount,
             is_base_0,
-            imput_amount,
+            with_metadata,
         } => {
-            // load pool to get observation
             let pool: raydium_amm_v3::states::PoolState =
                 program.account(pool_config.pool_id_account.unwrap())?;
-
-            // load position
-            let position_nft_infos = get_all_nft_and_position_by_owner(
-                &rpc_client,
-                &payer.pubkey(),
-                &pool_config.raydium_v3_program,
-            );
-            let positions: Vec<Pubkey> = position_nft_infos
-                .iter()
-                .map(|item| item.position)
-                .collect();
-            let rsps = rpc_client.get_multiple_accounts(&positions)?;
-            let mut user_positions = Vec::new();
-            for rsp in rsps {
-                match rsp {
-                    None => continue,
-                    Some(rsp) => {
-                        let position = deserialize_anchor_account::<
-                            raydium_amm_v3::states::PersonalPositionState,
-                        >(&rsp)?;
-                        user_positions.push(position);
-                    }
-                }
+            let tick_at_price = |price: f64| -> Result<i32> {
+                Ok(tick_with_spacing(
+                    tick_math::get_tick_at_sqrt_price(price_to_sqrt_price_x64(
+                        price,
+                        pool.mint_decimals_0,
+                        pool.mint_decimals_1,
+                    ))?,
+                    pool.tick_spacing.into(),
+                ))
+            };
+            let ticks = ladder_ticks(
+                tick_at_price(tick_lower_price)?,
+                tick_at_price(tick_upper_price)?,
+                pool.tick_spacing,
+                num_buckets,
+            )?;
+            let total_value = if is_base_0 {
+                total_amount as f64 * from_x64_price(pool.sqrt_price_x64).powi(2)
+            } else {
+                total_amount as f64
+            };
+            let buckets = plan_ladder(&pool, &ticks, total_value)?;
+            for bucket in buckets.iter() {
+                info!(
+                    "ticks:[{}, {}], liquidity:{}, amount_0:{}, amount_1:{}",
+                    bucket.tick_lower_index,
+                    bucket.tick_upper_index,
+                    bucket.liquidity,
+                    bucket.amount_0,
+                    bucket.amount_1
+                );
             }
-
-            let tick_lower_price_x64 = price_to_sqrt_price_x64(
-                tick_lower_price,
-                pool.mint_decimals_0,
-                pool.mint_decimals_1,
-            );
-            let tick_upper_price_x64 = price_to_sqrt_price_x64(
-                tick_upper_price,
+            let nft_mints = ladder_open(
+                &rpc_client,
+                &pool_config,
+                &payer,
+                &buckets,
+                with_metadata,
+                simulate,
+            )?;
+            info!("positions:{:?}", nft_mints);
+        }
+        CommandsName::PlaceRangeOrder {
+            price,
+            amount,
+            side,
+        } => {
+            let pool: raydium_amm_v3::states::PoolState =
+                program.account(pool_config.pool_id_account.unwrap())?;
+            let order_tick = tick_math::get_tick_at_sqrt_price(price_to_sqrt_price_x64(
+                price,
                 pool.mint_decimals_0,
                 pool.mint_decimals_1,
+            ))?;
+            let (tick_lower_index, tick_upper_index) =
+                range_order_ticks(pool.tick_current, order_tick, pool.tick_spacing, side)?;
+            info!(
+                "tick_lower_index:{}, tick_upper_index:{}, tick_current:{}",
+                tick_lower_index, tick_upper_index, pool.tick_current
             );
-            let tick_lower_index = tick_with_spacing(
-                tick_math::get_tick_at_sqrt_price(tick_lower_price_x64)?,
-                pool.tick_spacing.into(),
-            );
-            let tick_upper_index = tick_with_spacing(
-                tick_math::get_tick_at_sqrt_price(tick_upper_price_x64)?,
-                pool.tick_spacing.into(),
-            );
-            println!(
-                "tick_lower_index:{}, tick_upper_index:{}",
-                tick_lower_index, tick_upper_index
-            );
-            let tick_lower_price_x64 = tick_math::get_sqrt_price_at_tick(tick_lower_index)?;
-            let tick_upper_price_x64 = tick_math::get_sqrt_price_at_tick(tick_upper_index)?;
-            let liquidity = if is_base_0 {
-                liquidity_math::get_liquidity_from_single_amount_0(
-                    pool.sqrt_price_x64,
-                    tick_lower_price_x64,
-                    tick_upper_price_x64,
-                    imput_amount,
-                )
-            } else {
-                liquidity_math::get_liquidity_from_single_amount_1(
+            // the range is outside the market, only the token sold is deposited
+            let (liquidity, amount_0, amount_1) =
+                liquidity_math::get_liquidity_and_amounts_from_single_amount(
+                    pool.tick_current,
                     pool.sqrt_price_x64,
-                    tick_lower_price_x64,
-                    tick_upper_price_x64,
-                    imput_amount,
-                )
-            };
-            let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
-                pool.tick_current,
-                pool.sqrt_price_x64,
-                tick_lower_index,
-                tick_upper_index,
-                liquidity as i128,
-            )?;
-            println!(
+                    tick_lower_index,
+                    tick_upper_index,
+                    amount,
+                    side == RangeOrderSide::Sell,
+                )?;
+            info!(
                 "amount_0:{}, amount_1:{}, liquidity:{}",
                 amount_0, amount_1, liquidity
             );
-            // calc with slippage
+            let tolerance_rate = slippage_to_tolerance_rate(pool_config.slippage);
             let amount_0_with_slippage =
-                amount_with_slippage(amount_0 as u64, pool_config.slippage, true);
+                liquidity_math::amount_with_tolerance(amount_0, tolerance_rate);
             let amount_1_with_slippage =
-                amount_with_slippage(amount_1 as u64, pool_config.slippage, true);
-            // calc with transfer_fee
+                liquidity_math::amount_with_tolerance(amount_1, tolerance_rate);
             let transfer_fee = get_pool_mints_inverse_fee(
                 &rpc_client,
                 pool.token_mint_0,
@@ -1341,10 +2307,6 @@ fn main() -> Result<()> {
                 amount_0_with_slippage,
                 amount_1_with_slippage,
             );
-            println!(
-                "transfer_fee_0:{}, transfer_fee_1:{}",
-                transfer_fee.0.transfer_fee, transfer_fee.1.transfer_fee
-            );
             let amount_0_max = (amount_0_with_slippage as u64)
                 .checked_add(transfer_fee.0.transfer_fee)
                 .unwrap();
@@ -1352,21 +2314,238 @@ fn main() -> Result<()> {
                 .checked_add(transfer_fee.1.transfer_fee)
                 .unwrap();
 
-            let tick_array_lower_start_index =
-                raydium_amm_v3::states::TickArrayState::get_array_start_index(
-                    tick_lower_index,
-                    pool.tick_spacing.into(),
-                );
-            let tick_array_upper_start_index =
-                raydium_amm_v3::states::TickArrayState::get_array_start_index(
-                    tick_upper_index,
-                    pool.tick_spacing.into(),
-                );
-            let mut find_position = raydium_amm_v3::states::PersonalPositionState::default();
-            for position in user_positions {
-                if position.pool_id == pool_config.pool_id_account.unwrap()
-                    && position.tick_lower_index == tick_lower_index
-                    && position.tick_upper_index == tick_upper_index
+            let nft_mint = Keypair::generate(&mut OsRng);
+            let instructions = open_position_with_token22_nft_instr(
+                &pool_config.clone(),
+                pool_config.pool_id_account.unwrap(),
+                pool.token_vault_0,
+                pool.token_vault_1,
+                pool.token_mint_0,
+                pool.token_mint_1,
+                nft_mint.pubkey(),
+                payer.pubkey(),
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &pool.token_mint_0,
+                    &transfer_fee.0.owner,
+                ),
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &pool.token_mint_1,
+                    &transfer_fee.1.owner,
+                ),
+                vec![AccountMeta::new(
+                    pool_config.tickarray_bitmap_extension.unwrap(),
+                    false,
+                )],
+                liquidity,
+                amount_0_max,
+                amount_1_max,
+                tick_lower_index,
+                tick_upper_index,
+                TickArrayState::get_array_start_index(tick_lower_index, pool.tick_spacing),
+                TickArrayState::get_array_start_index(tick_upper_index, pool.tick_spacing),
+                false,
+            )?;
+            let signers = vec![&payer, &nft_mint];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            info!("nft_mint:{}", nft_mint.pubkey());
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::CheckRangeOrder { nft_mint, side } => {
+            let (personal_position_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::POSITION_SEED.as_bytes(),
+                    nft_mint.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let position: raydium_amm_v3::states::PersonalPositionState =
+                program.account(personal_position_key)?;
+            let pool: raydium_amm_v3::states::PoolState = program.account(position.pool_id)?;
+            let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+                pool.tick_current,
+                pool.sqrt_price_x64,
+                position.tick_lower_index,
+                position.tick_upper_index,
+                -i128::try_from(position.liquidity)?,
+            )?;
+            let fill = range_order_fill(
+                pool.sqrt_price_x64,
+                position.tick_lower_index,
+                position.tick_upper_index,
+                side,
+            )?;
+            info!(
+                "tick_lower_index:{}, tick_upper_index:{}, tick_current:{}, amount_0:{}, amount_1:{}, filled:{:.2}%",
+                { position.tick_lower_index },
+                { position.tick_upper_index },
+                pool.tick_current,
+                amount_0,
+                amount_1,
+                fill * 100.0
+            );
+            if fill >= 1.0 {
+                // the position converts back if the market returns into the range
+                info!("filled, decrease the liquidity to take the proceeds");
+            }
+        }
+        CommandsName::EstimateOpenCost {
+            tick_lower_price,
+            tick_upper_price,
+        } => {
+            let pool: raydium_amm_v3::states::PoolState =
+                program.account(pool_config.pool_id_account.unwrap())?;
+            let tick_lower_index = tick_with_spacing(
+                tick_math::get_tick_at_sqrt_price(price_to_sqrt_price_x64(
+                    tick_lower_price,
+                    pool.mint_decimals_0,
+                    pool.mint_decimals_1,
+                ))?,
+                pool.tick_spacing.into(),
+            );
+            let tick_upper_index = tick_with_spacing(
+                tick_math::get_tick_at_sqrt_price(price_to_sqrt_price_x64(
+                    tick_upper_price,
+                    pool.mint_decimals_0,
+                    pool.mint_decimals_1,
+                ))?,
+                pool.tick_spacing.into(),
+            );
+            info!(
+                "tick_lower_index:{}, tick_upper_index:{}",
+                tick_lower_index, tick_upper_index
+            );
+            let open_cost = estimate_open_cost(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                &pool_config.pool_id_account.unwrap(),
+                tick_lower_index,
+                tick_upper_index,
+            )?;
+            info!("{}", open_cost.report());
+        }
+        CommandsName::IncreaseLiquidity {
+            tick_lower_price,
+            tick_upper_price,
+            is_base_0,
+            imput_amount,
+        } => {
+            // load pool to get observation
+            let pool: raydium_amm_v3::states::PoolState =
+                program.account(pool_config.pool_id_account.unwrap())?;
+
+            // load position
+            let position_nft_infos = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            );
+            let positions: Vec<Pubkey> = position_nft_infos
+                .iter()
+                .map(|item| item.position)
+                .collect();
+            let rsps = scanner.get_multiple_accounts(&rpc_client, &positions)?;
+            let mut user_positions = Vec::new();
+            for rsp in rsps {
+                match rsp {
+                    None => continue,
+                    Some(rsp) => {
+                        let position = deserialize_anchor_account::<
+                            raydium_amm_v3::states::PersonalPositionState,
+                        >(&rsp)?;
+                        user_positions.push(position);
+                    }
+                }
+            }
+
+            let tick_lower_price_x64 = price_to_sqrt_price_x64(
+                tick_lower_price,
+                pool.mint_decimals_0,
+                pool.mint_decimals_1,
+            );
+            let tick_upper_price_x64 = price_to_sqrt_price_x64(
+                tick_upper_price,
+                pool.mint_decimals_0,
+                pool.mint_decimals_1,
+            );
+            let tick_lower_index = tick_with_spacing(
+                tick_math::get_tick_at_sqrt_price(tick_lower_price_x64)?,
+                pool.tick_spacing.into(),
+            );
+            let tick_upper_index = tick_with_spacing(
+                tick_math::get_tick_at_sqrt_price(tick_upper_price_x64)?,
+                pool.tick_spacing.into(),
+            );
+            info!(
+                "tick_lower_index:{}, tick_upper_index:{}",
+                tick_lower_index, tick_upper_index
+            );
+            // same calculation as the program, so the amounts pass its slippage check
+            let (liquidity, amount_0, amount_1) =
+                liquidity_math::get_liquidity_and_amounts_from_single_amount(
+                    pool.tick_current,
+                    pool.sqrt_price_x64,
+                    tick_lower_index,
+                    tick_upper_index,
+                    imput_amount,
+                    is_base_0,
+                )?;
+            info!(
+                "amount_0:{}, amount_1:{}, liquidity:{}",
+                amount_0, amount_1, liquidity
+            );
+            // calc with slippage
+            let tolerance_rate = slippage_to_tolerance_rate(pool_config.slippage);
+            let amount_0_with_slippage =
+                liquidity_math::amount_with_tolerance(amount_0, tolerance_rate);
+            let amount_1_with_slippage =
+                liquidity_math::amount_with_tolerance(amount_1, tolerance_rate);
+            // calc with transfer_fee
+            let transfer_fee = get_pool_mints_inverse_fee(
+                &rpc_client,
+                pool.token_mint_0,
+                pool.token_mint_1,
+                amount_0_with_slippage,
+                amount_1_with_slippage,
+            );
+            info!(
+                "transfer_fee_0:{}, transfer_fee_1:{}",
+                transfer_fee.0.transfer_fee, transfer_fee.1.transfer_fee
+            );
+            let amount_0_max = (amount_0_with_slippage as u64)
+                .checked_add(transfer_fee.0.transfer_fee)
+                .unwrap();
+            let amount_1_max = (amount_1_with_slippage as u64)
+                .checked_add(transfer_fee.1.transfer_fee)
+                .unwrap();
+
+            let tick_array_lower_start_index =
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    tick_lower_index,
+                    pool.tick_spacing.into(),
+                );
+            let tick_array_upper_start_index =
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    tick_upper_index,
+                    pool.tick_spacing.into(),
+                );
+            let mut find_position = raydium_amm_v3::states::PersonalPositionState::default();
+            for position in user_positions {
+                if position.pool_id == pool_config.pool_id_account.unwrap()
+                    && position.tick_lower_index == tick_lower_index
+                    && position.tick_upper_index == tick_upper_index
                 {
                     find_position = position.clone();
                 }
@@ -1417,23 +2596,27 @@ fn main() -> Result<()> {
                 let signers = vec![&payer];
                 let recent_hash = rpc_client.get_latest_blockhash()?;
                 let txn = Transaction::new_signed_with_payer(
-                    &increase_instr,
+                    &with_compute_budget(
+                        &rpc_client,
+                        &pool_config.compute_budget,
+                        &payer.pubkey(),
+                        &increase_instr,
+                    )?,
                     Some(&payer.pubkey()),
                     &signers,
                     recent_hash,
                 );
-                let signature = send_txn(&rpc_client, &txn, true)?;
-                println!("{}", signature);
+                send_or_simulate_txn(&rpc_client, &txn, simulate)?;
             } else {
                 // personal position not exist
-                println!("personal position exist:{:?}", find_position);
+                info!("personal position exist:{:?}", find_position);
             }
         }
         CommandsName::DecreaseLiquidity {
             tick_lower_index,
             tick_upper_index,
             liquidity,
-            simulate,
+            keep_position,
         } => {
             // load pool to get observation
             let pool: raydium_amm_v3::states::PoolState =
@@ -1459,7 +2642,7 @@ fn main() -> Result<()> {
                 .iter()
                 .map(|item| item.position)
                 .collect();
-            let rsps = rpc_client.get_multiple_accounts(&positions)?;
+            let rsps = scanner.get_multiple_accounts(&rpc_client, &positions)?;
             let mut user_positions = Vec::new();
             for rsp in rsps {
                 match rsp {
@@ -1479,7 +2662,7 @@ fn main() -> Result<()> {
                     && position.tick_upper_index == tick_upper_index
                 {
                     find_position = position.clone();
-                    println!("liquidity:{:?}", find_position);
+                    info!("liquidity:{:?}", find_position);
                 }
             }
             if find_position.nft_mint != Pubkey::default()
@@ -1570,12 +2753,14 @@ fn main() -> Result<()> {
                     tick_array_lower_start_index,
                     tick_array_upper_start_index,
                 )?;
-                if liquidity == find_position.liquidity {
-                    let close_position_instr = close_personal_position_instr(
+                // the program closes the position only if the decrease left nothing in it
+                if !keep_position {
+                    let close_position_instr = close_personal_position_v2_instr(
                         &pool_config.clone(),
                         find_position.nft_mint,
                         user_nft_token_info.key,
                         user_nft_token_info.program,
+                        true,
                     )?;
                     decrease_instr.extend(close_position_instr);
                 }
@@ -1583,36 +2768,36 @@ fn main() -> Result<()> {
                 let signers = vec![&payer];
                 let recent_hash = rpc_client.get_latest_blockhash()?;
                 let txn = Transaction::new_signed_with_payer(
-                    &decrease_instr,
+                    &with_compute_budget(
+                        &rpc_client,
+                        &pool_config.compute_budget,
+                        &payer.pubkey(),
+                        &decrease_instr,
+                    )?,
                     Some(&payer.pubkey()),
                     &signers,
                     recent_hash,
                 );
-                if simulate {
-                    let ret = simulate_transaction(
-                        &rpc_client,
-                        &txn,
-                        true,
-                        CommitmentConfig::confirmed(),
-                    )?;
-                    println!("{:#?}", ret);
-                } else {
-                    let signature = send_txn(&rpc_client, &txn, true)?;
-                    println!("{}", signature);
-                }
+                send_or_simulate_txn(&rpc_client, &txn, simulate)?;
             } else {
                 // personal position not exist
-                println!("personal position exist:{:?}", find_position);
+                info!("personal position exist:{:?}", find_position);
             }
         }
         CommandsName::Swap {
             input_token,
             output_token,
             base_in,
-            simulate,
+            quote_only,
             amount,
             limit_price,
+            limit_tick,
+            slippage_bps,
         } => {
+            let slippage = match slippage_bps {
+                Some(slippage_bps) => slippage_from_bps(slippage_bps)?,
+                None => pool_config.slippage,
+            };
             // load mult account
             let load_accounts = vec![
                 input_token,
@@ -1640,8 +2825,12 @@ fn main() -> Result<()> {
                 deserialize_anchor_account::<raydium_amm_v3::states::TickArrayBitmapExtension>(
                     tickarray_bitmap_extension_account.as_ref().unwrap(),
                 )?;
-            let zero_for_one = user_input_state.base.mint == pool_state.token_mint_0
-                && user_output_state.base.mint == pool_state.token_mint_1;
+            let zero_for_one = swap_direction(
+                pool_state.token_mint_0,
+                pool_state.token_mint_1,
+                user_input_state.base.mint,
+                user_output_state.base.mint,
+            )?;
             // load tick_arrays
             let mut tick_arrays = load_cur_and_next_five_tick_array(
                 &rpc_client,
@@ -1649,7 +2838,7 @@ fn main() -> Result<()> {
                 &pool_state,
                 &tickarray_bitmap_extension,
                 zero_for_one,
-            );
+            )?;
 
             let mut sqrt_price_limit_x64 = None;
             if limit_price.is_some() {
@@ -1660,7 +2849,26 @@ fn main() -> Result<()> {
                 );
                 sqrt_price_limit_x64 = Some(sqrt_price_x64);
             }
+            if let Some(limit_tick) = limit_tick {
+                sqrt_price_limit_x64 = Some(tick_math::get_sqrt_price_limit_at_tick(limit_tick)?);
+            }
 
+            if quote_only {
+                let simulation = simulate_swap(
+                    amount,
+                    sqrt_price_limit_x64,
+                    zero_for_one,
+                    base_in,
+                    &amm_config_state,
+                    &pool_state,
+                    &tickarray_bitmap_extension,
+                    &tick_arrays,
+                )?;
+                info!("{}", report_swap_simulation(&simulation, &pool_state));
+                return Ok(());
+            }
+            // the quote consumes the tick arrays, keep them to size a fillable swap
+            let quote_tick_arrays = tick_arrays.clone();
             let (mut other_amount_threshold, mut tick_array_indexs) =
                 utils::get_out_put_amount_and_remaining_accounts(
                     amount,
@@ -1671,20 +2879,65 @@ fn main() -> Result<()> {
                     &pool_state,
                     &tickarray_bitmap_extension,
                     &mut tick_arrays,
-                )
-                .unwrap();
-            println!(
+                )?;
+            info!(
                 "amount:{}, other_amount_threshold:{}",
                 amount, other_amount_threshold
             );
+            let (input_vault, output_vault) = if zero_for_one {
+                (pool_state.token_vault_0, pool_state.token_vault_1)
+            } else {
+                (pool_state.token_vault_1, pool_state.token_vault_0)
+            };
+            // the program reads the direction from the vaults, check it agrees with the mints
+            if check_swap_accounts(
+                &pool_state,
+                input_vault,
+                output_vault,
+                user_input_state.base.mint,
+                user_output_state.base.mint,
+            )? != zero_for_one
+            {
+                return Err(format_err!(
+                    "the swap vaults don't match the swap direction"
+                ));
+            }
+            // fail before sending when the output vault can't pay the quoted output
+            let output_vault_balance = rpc_client
+                .get_token_account_balance(&output_vault)?
+                .amount
+                .parse::<u64>()?;
+            let amount_out = if base_in {
+                other_amount_threshold
+            } else {
+                amount
+            };
+            if amount_out > output_vault_balance {
+                let shortfall = raydium_amm_v3::instructions::OutputVaultShortfall {
+                    amount_out,
+                    max_amount_out: output_vault_balance,
+                };
+                return Err(format_err!(
+                    "{}",
+                    report_output_vault_shortfall(
+                        &shortfall,
+                        base_in,
+                        zero_for_one,
+                        &amm_config_state,
+                        &pool_state,
+                        &tickarray_bitmap_extension,
+                        &quote_tick_arrays,
+                    )
+                ));
+            }
             if base_in {
                 // min out
                 other_amount_threshold =
-                    amount_with_slippage(other_amount_threshold, pool_config.slippage, false);
+                    amount_with_slippage(other_amount_threshold, slippage, false);
             } else {
                 // max in
                 other_amount_threshold =
-                    amount_with_slippage(other_amount_threshold, pool_config.slippage, true);
+                    amount_with_slippage(other_amount_threshold, slippage, true);
             }
 
             let current_or_next_tick_array_key = Pubkey::find_program_address(
@@ -1720,22 +2973,12 @@ fn main() -> Result<()> {
                 .collect();
             remaining_accounts.append(&mut accounts);
             let mut instructions = Vec::new();
-            let request_inits_instr = ComputeBudgetInstruction::set_compute_unit_limit(1400_000u32);
-            instructions.push(request_inits_instr);
             let swap_instr = swap_instr(
                 &pool_config.clone(),
                 pool_state.amm_config,
                 pool_config.pool_id_account.unwrap(),
-                if zero_for_one {
-                    pool_state.token_vault_0
-                } else {
-                    pool_state.token_vault_1
-                },
-                if zero_for_one {
-                    pool_state.token_vault_1
-                } else {
-                    pool_state.token_vault_0
-                },
+                input_vault,
+                output_vault,
                 pool_state.observation_key,
                 input_token,
                 output_token,
@@ -1752,7 +2995,12 @@ fn main() -> Result<()> {
             let signers = vec![&payer];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &instructions,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
@@ -1760,20 +3008,51 @@ fn main() -> Result<()> {
             if simulate {
                 let ret =
                     simulate_transaction(&rpc_client, &txn, true, CommitmentConfig::confirmed())?;
-                println!("{:#?}", ret);
+                info!("{:#?}", ret);
+                if let Some(shortfall) = decode_output_vault_shortfall(
+                    &ret.value.return_data,
+                    &pool_config.raydium_v3_program,
+                ) {
+                    info!(
+                        "{}",
+                        report_output_vault_shortfall(
+                            &shortfall,
+                            base_in,
+                            zero_for_one,
+                            &amm_config_state,
+                            &pool_state,
+                            &tickarray_bitmap_extension,
+                            &quote_tick_arrays,
+                        )
+                    );
+                } else if let Some(amount) =
+                    decode_swap_amount(&ret.value.return_data, &pool_config.raydium_v3_program)
+                {
+                    info!(
+                        "{}:{}",
+                        if base_in { "amount_out" } else { "amount_in" },
+                        amount
+                    );
+                }
             } else {
                 let signature = send_txn(&rpc_client, &txn, true)?;
-                println!("{}", signature);
+                info!("{}", signature);
             }
         }
         CommandsName::SwapV2 {
             input_token,
             output_token,
             base_in,
-            simulate,
+            quote_only,
             amount,
             limit_price,
+            limit_tick,
+            slippage_bps,
         } => {
+            let slippage = match slippage_bps {
+                Some(slippage_bps) => slippage_from_bps(slippage_bps)?,
+                None => pool_config.slippage,
+            };
             // load mult account
             let load_accounts = vec![
                 input_token,
@@ -1808,8 +3087,12 @@ fn main() -> Result<()> {
                 deserialize_anchor_account::<raydium_amm_v3::states::TickArrayBitmapExtension>(
                     tickarray_bitmap_extension_account.as_ref().unwrap(),
                 )?;
-            let zero_for_one = user_input_state.base.mint == pool_state.token_mint_0
-                && user_output_state.base.mint == pool_state.token_mint_1;
+            let zero_for_one = swap_direction(
+                pool_state.token_mint_0,
+                pool_state.token_mint_1,
+                user_input_state.base.mint,
+                user_output_state.base.mint,
+            )?;
 
             let transfer_fee = if base_in {
                 if zero_for_one {
@@ -1828,7 +3111,7 @@ fn main() -> Result<()> {
                 &pool_state,
                 &tickarray_bitmap_extension,
                 zero_for_one,
-            );
+            )?;
 
             let mut sqrt_price_limit_x64 = None;
             if limit_price.is_some() {
@@ -1839,7 +3122,26 @@ fn main() -> Result<()> {
                 );
                 sqrt_price_limit_x64 = Some(sqrt_price_x64);
             }
+            if let Some(limit_tick) = limit_tick {
+                sqrt_price_limit_x64 = Some(tick_math::get_sqrt_price_limit_at_tick(limit_tick)?);
+            }
 
+            if quote_only {
+                let simulation = simulate_swap(
+                    amount_specified,
+                    sqrt_price_limit_x64,
+                    zero_for_one,
+                    base_in,
+                    &amm_config_state,
+                    &pool_state,
+                    &tickarray_bitmap_extension,
+                    &tick_arrays,
+                )?;
+                info!("{}", report_swap_simulation(&simulation, &pool_state));
+                return Ok(());
+            }
+            // the quote consumes the tick arrays, keep them to size a fillable swap
+            let quote_tick_arrays = tick_arrays.clone();
             let (mut other_amount_threshold, tick_array_indexs) =
                 utils::get_out_put_amount_and_remaining_accounts(
                     amount_specified,
@@ -1850,20 +3152,65 @@ fn main() -> Result<()> {
                     &pool_state,
                     &tickarray_bitmap_extension,
                     &mut tick_arrays,
-                )
-                .unwrap();
-            println!(
+                )?;
+            info!(
                 "amount:{}, other_amount_threshold:{}",
                 amount, other_amount_threshold
             );
+            let (input_vault, output_vault) = if zero_for_one {
+                (pool_state.token_vault_0, pool_state.token_vault_1)
+            } else {
+                (pool_state.token_vault_1, pool_state.token_vault_0)
+            };
+            // the program reads the direction from the vaults, check it agrees with the mints
+            if check_swap_accounts(
+                &pool_state,
+                input_vault,
+                output_vault,
+                user_input_state.base.mint,
+                user_output_state.base.mint,
+            )? != zero_for_one
+            {
+                return Err(format_err!(
+                    "the swap vaults don't match the swap direction"
+                ));
+            }
+            // fail before sending when the output vault can't pay the quoted output
+            let output_vault_balance = rpc_client
+                .get_token_account_balance(&output_vault)?
+                .amount
+                .parse::<u64>()?;
+            let amount_out = if base_in {
+                other_amount_threshold
+            } else {
+                amount_specified
+            };
+            if amount_out > output_vault_balance {
+                let shortfall = raydium_amm_v3::instructions::OutputVaultShortfall {
+                    amount_out,
+                    max_amount_out: output_vault_balance,
+                };
+                return Err(format_err!(
+                    "{}",
+                    report_output_vault_shortfall(
+                        &shortfall,
+                        base_in,
+                        zero_for_one,
+                        &amm_config_state,
+                        &pool_state,
+                        &tickarray_bitmap_extension,
+                        &quote_tick_arrays,
+                    )
+                ));
+            }
             if base_in {
                 // calc mint out amount with slippage
                 other_amount_threshold =
-                    amount_with_slippage(other_amount_threshold, pool_config.slippage, false);
+                    amount_with_slippage(other_amount_threshold, slippage, false);
             } else {
                 // calc max in with slippage
                 other_amount_threshold =
-                    amount_with_slippage(other_amount_threshold, pool_config.slippage, true);
+                    amount_with_slippage(other_amount_threshold, slippage, true);
                 // calc max in with transfer_fee
                 let transfer_fee = if zero_for_one {
                     get_transfer_inverse_fee(&mint0_state, epoch, other_amount_threshold)
@@ -1897,22 +3244,12 @@ fn main() -> Result<()> {
                 .collect();
             remaining_accounts.append(&mut accounts);
             let mut instructions = Vec::new();
-            let request_inits_instr = ComputeBudgetInstruction::set_compute_unit_limit(1400_000u32);
-            instructions.push(request_inits_instr);
             let swap_instr = swap_v2_instr(
                 &pool_config.clone(),
                 pool_state.amm_config,
                 pool_config.pool_id_account.unwrap(),
-                if zero_for_one {
-                    pool_state.token_vault_0
-                } else {
-                    pool_state.token_vault_1
-                },
-                if zero_for_one {
-                    pool_state.token_vault_1
-                } else {
-                    pool_state.token_vault_0
-                },
+                input_vault,
+                output_vault,
                 pool_state.observation_key,
                 input_token,
                 output_token,
@@ -1938,7 +3275,12 @@ fn main() -> Result<()> {
             let signers = vec![&payer];
             let recent_hash = rpc_client.get_latest_blockhash()?;
             let txn = Transaction::new_signed_with_payer(
-                &instructions,
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
                 Some(&payer.pubkey()),
                 &signers,
                 recent_hash,
@@ -1946,43 +3288,267 @@ fn main() -> Result<()> {
             if simulate {
                 let ret =
                     simulate_transaction(&rpc_client, &txn, true, CommitmentConfig::confirmed())?;
-                println!("{:#?}", ret);
+                info!("{:#?}", ret);
+                if let Some(shortfall) = decode_output_vault_shortfall(
+                    &ret.value.return_data,
+                    &pool_config.raydium_v3_program,
+                ) {
+                    info!(
+                        "{}",
+                        report_output_vault_shortfall(
+                            &shortfall,
+                            base_in,
+                            zero_for_one,
+                            &amm_config_state,
+                            &pool_state,
+                            &tickarray_bitmap_extension,
+                            &quote_tick_arrays,
+                        )
+                    );
+                } else if let Some(amount) =
+                    decode_swap_amount(&ret.value.return_data, &pool_config.raydium_v3_program)
+                {
+                    info!(
+                        "{}:{}",
+                        if base_in { "amount_out" } else { "amount_in" },
+                        amount
+                    );
+                }
             } else {
                 let signature = send_txn(&rpc_client, &txn, true)?;
-                println!("{}", signature);
+                info!("{}", signature);
             }
         }
-        CommandsName::PPositionByOwner { user_wallet } => {
-            // load position
-            let position_nft_infos = get_all_nft_and_position_by_owner(
-                &rpc_client,
-                &user_wallet,
-                &pool_config.raydium_v3_program,
-            );
+        CommandsName::SwapRouter {
+            input_token,
+            amount_in,
+            pools,
+            lookup_table,
+        } => {
+            let input_token_state = StateWithExtensions::<Account>::unpack(
+                &rpc_client.get_account(&input_token)?.data,
+            )?;
+            let route_input_mint = input_token_state.base.mint;
+            // quote every hop with the output of the previous hop
+            let mut hops = Vec::new();
+            let mut input_mint = route_input_mint;
+            let mut amount = amount_in;
+            for pool_id in pools {
+                let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                    &rpc_client.get_account(&pool_id)?,
+                )?;
+                let zero_for_one = input_mint == pool_state.token_mint_0;
+                if !zero_for_one && input_mint != pool_state.token_mint_1 {
+                    panic!("pool {} doesn't trade mint {}", pool_id, input_mint);
+                }
+                let (output_mint, input_vault, output_vault) = if zero_for_one {
+                    (
+                        pool_state.token_mint_1,
+                        pool_state.token_vault_0,
+                        pool_state.token_vault_1,
+                    )
+                } else {
+                    (
+                        pool_state.token_mint_0,
+                        pool_state.token_vault_1,
+                        pool_state.token_vault_0,
+                    )
+                };
+                let tickarray_bitmap_extension_key = Pubkey::find_program_address(
+                    &[
+                        POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                        pool_id.to_bytes().as_ref(),
+                    ],
+                    &pool_config.raydium_v3_program,
+                )
+                .0;
+                let rsps = rpc_client.get_multiple_accounts(&[
+                    pool_state.amm_config,
+                    tickarray_bitmap_extension_key,
+                    output_mint,
+                ])?;
+                let amm_config_state = deserialize_anchor_account::<
+                    raydium_amm_v3::states::AmmConfig,
+                >(rsps[0].as_ref().unwrap())?;
+                let tickarray_bitmap_extension = deserialize_anchor_account::<
+                    raydium_amm_v3::states::TickArrayBitmapExtension,
+                >(rsps[1].as_ref().unwrap())?;
+                let output_token_program = rsps[2].as_ref().unwrap().owner;
+
+                let mut hop_config = pool_config.clone();
+                hop_config.pool_id_account = Some(pool_id);
+                let mut tick_arrays = load_cur_and_next_five_tick_array(
+                    &rpc_client,
+                    &hop_config,
+                    &pool_state,
+                    &tickarray_bitmap_extension,
+                    zero_for_one,
+                )
+                .map_err(|err| format_err!("pool {}: {}", pool_id, err))?;
+                let (amount_out, tick_array_indexs) =
+                    utils::get_out_put_amount_and_remaining_accounts(
+                        amount,
+                        None,
+                        zero_for_one,
+                        true,
+                        &amm_config_state,
+                        &pool_state,
+                        &tickarray_bitmap_extension,
+                        &mut tick_arrays,
+                    )
+                    .map_err(|err| format_err!("pool {}: {}", pool_id, err))?;
+                let output_token_account = get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &output_mint,
+                    &output_token_program,
+                );
+                let mut accounts = vec![
+                    AccountMeta::new_readonly(pool_state.amm_config, false),
+                    AccountMeta::new(pool_id, false),
+                    AccountMeta::new(output_token_account, false),
+                    AccountMeta::new(input_vault, false),
+                    AccountMeta::new(output_vault, false),
+                    AccountMeta::new_readonly(output_mint, false),
+                    AccountMeta::new(pool_state.observation_key, false),
+                    AccountMeta::new_readonly(tickarray_bitmap_extension_key, false),
+                ];
+                accounts.extend(tick_array_indexs.into_iter().map(|index| {
+                    AccountMeta::new(
+                        Pubkey::find_program_address(
+                            &[
+                                raydium_amm_v3::states::TICK_ARRAY_SEED.as_bytes(),
+                                pool_id.to_bytes().as_ref(),
+                                &index.to_be_bytes(),
+                            ],
+                            &pool_config.raydium_v3_program,
+                        )
+                        .0,
+                        false,
+                    )
+                }));
+                info!(
+                    "hop pool:{}, input_mint:{}, output_mint:{}, amount_in:{}, amount_out:{}",
+                    pool_id, input_mint, output_mint, amount, amount_out
+                );
+                hops.push(RouteHop {
+                    pool_id,
+                    input_mint,
+                    output_mint,
+                    output_token_account,
+                    accounts,
+                    amount_in: amount,
+                    amount_out,
+                });
+                input_mint = output_mint;
+                amount = amount_out;
+            }
+
+            // a resumed leg only swaps the balance the previous legs added to the intermediate account
+            let intermediate_token_accounts: Vec<Pubkey> =
+                hops.iter().map(|hop| hop.output_token_account).collect();
+            let mut input_balances_before = std::collections::HashMap::new();
+            for (key, account) in intermediate_token_accounts
+                .iter()
+                .zip(rpc_client.get_multiple_accounts(&intermediate_token_accounts)?)
+            {
+                let account = account.ok_or(format_err!("token account {} doesn't exist", key))?;
+                let token_state = StateWithExtensions::<Account>::unpack(&account.data)?;
+                input_balances_before.insert(*key, token_state.base.amount);
+            }
+            let lookup_tables = match lookup_table {
+                Some(key) => vec![decode_lookup_table(
+                    key,
+                    &rpc_client.get_account(&key)?.data,
+                )?],
+                None => Vec::new(),
+            };
+
+            // the legs are sized with their compute budget, too large to simulate as legacy
+            // transactions they request the maximum units
+            let build_leg_instructions = |leg: &RouteLeg| -> Result<Vec<Instruction>> {
+                let mut instructions = pool_config
+                    .compute_budget
+                    .instructions(MAX_COMPUTE_UNIT_LIMIT);
+                if leg.is_first() {
+                    instructions.extend(swap_router_base_in_instr(
+                        &pool_config,
+                        input_token,
+                        route_input_mint,
+                        leg.remaining_accounts(&hops),
+                        amount_in,
+                        leg.amount_out_minimum,
+                    )?);
+                } else {
+                    let input_token_account = hops[leg.start - 1].output_token_account;
+                    instructions.extend(swap_router_resume_base_in_instr(
+                        &pool_config,
+                        input_token_account,
+                        hops[leg.start].input_mint,
+                        leg.remaining_accounts(&hops),
+                        input_balances_before[&input_token_account],
+                        leg.amount_in_minimum,
+                        leg.amount_out_minimum,
+                    )?);
+                }
+                Ok(instructions)
+            };
+            let plan = plan_route_by_tx_size(
+                &payer.pubkey(),
+                &hops,
+                pool_config.slippage,
+                &lookup_tables,
+                &build_leg_instructions,
+            )?;
+            info!("{:?}", plan);
+
+            for (i, leg) in plan.legs().iter().enumerate() {
+                let instructions = build_leg_instructions(leg)?;
+                let recent_hash = rpc_client.get_latest_blockhash()?;
+                let message = v0::Message::try_compile(
+                    &payer.pubkey(),
+                    &instructions,
+                    &lookup_tables,
+                    recent_hash,
+                )?;
+                let txn = VersionedTransaction::try_new(VersionedMessage::V0(message), &[&payer])?;
+                if simulate {
+                    let ret = rpc_client.simulate_transaction(&txn)?;
+                    info!("leg:{} {:#?}", i, ret);
+                    // the later legs swap the output of this one
+                    break;
+                }
+                let signature = rpc_client.send_and_confirm_transaction(&txn)?;
+                info!(
+                    "leg:{}, hops:{}..{}, amount_out_minimum:{}, signature:{}",
+                    i, leg.start, leg.end, leg.amount_out_minimum, signature
+                );
+            }
+        }
+        CommandsName::PPositionByOwner { user_wallet } => {
+            // load position
+            let position_nft_infos = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &user_wallet,
+                &pool_config.raydium_v3_program,
+            );
             let positions: Vec<Pubkey> = position_nft_infos
                 .iter()
                 .map(|item| item.position)
                 .collect();
-            let rsps = rpc_client.get_multiple_accounts(&positions)?;
-            let mut user_positions = Vec::new();
-            for rsp in rsps {
-                match rsp {
-                    None => continue,
-                    Some(rsp) => {
-                        let position = deserialize_anchor_account::<
-                            raydium_amm_v3::states::PersonalPositionState,
-                        >(&rsp)?;
-                        let (personal_position_key, __bump) = Pubkey::find_program_address(
-                            &[
-                                raydium_amm_v3::states::POSITION_SEED.as_bytes(),
-                                position.nft_mint.to_bytes().as_ref(),
-                            ],
-                            &program.id(),
-                        );
-                        println!("id:{}, lower:{}, upper:{}, liquidity:{}, fees_owed_0:{}, fees_owed_1:{}, fee_growth_inside_0:{}, fee_growth_inside_1:{}", personal_position_key, position.tick_lower_index, position.tick_upper_index, position.liquidity, position.token_fees_owed_0, position.token_fees_owed_1, position.fee_growth_inside_0_last_x64, position.fee_growth_inside_1_last_x64);
-                        user_positions.push(position);
-                    }
-                }
+            for position in load_positions(&rpc_client, &scanner, &positions)? {
+                info!("id:{}, layout:{:?}, pool:{}, lower:{}, upper:{}, liquidity:{}, fees_owed_0:{}, fees_owed_1:{}, rewards_owed:{:?}", position.key, position.layout, position.pool_id, position.tick_lower_index, position.tick_upper_index, position.liquidity, position.token_fees_owed_0, position.token_fees_owed_1, position.reward_amounts_owed);
+            }
+        }
+        CommandsName::Portfolio { owner, json } => {
+            let portfolio = load_portfolio(&rpc_client, &pool_config, &scanner, &owner)?;
+            info!(
+                "positions:{}\n{}",
+                portfolio.positions.len(),
+                portfolio.table()
+            );
+            if let Some(out_file) = json {
+                std::fs::write(&out_file, serde_json::to_string_pretty(&portfolio)?)?;
+                info!("file:{}", out_file);
             }
         }
         CommandsName::PTickState { tick, pool_id } => {
@@ -1991,7 +3557,7 @@ fn main() -> Result<()> {
             } else {
                 pool_config.pool_id_account.unwrap()
             };
-            println!("pool_id:{}", pool_id);
+            info!("pool_id:{}", pool_id);
             let pool: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
 
             let tick_array_start_index =
@@ -2013,7 +3579,7 @@ fn main() -> Result<()> {
             let tick_state = tick_array_account
                 .get_tick_state_mut(tick, pool.tick_spacing.into())
                 .unwrap();
-            println!("{:?}", tick_state);
+            info!("{:?}", tick_state);
         }
         CommandsName::CompareKey { key0, key1 } => {
             let mut token_mint_0 = key0;
@@ -2021,41 +3587,82 @@ fn main() -> Result<()> {
             if token_mint_0 > token_mint_1 {
                 std::mem::swap(&mut token_mint_0, &mut token_mint_1);
             }
-            println!("mint0:{}, mint1:{}", token_mint_0, token_mint_1);
+            info!("mint0:{}, mint1:{}", token_mint_0, token_mint_1);
         }
         CommandsName::PMint { mint } => {
             let mint_data = &rpc_client.get_account_data(&mint)?;
             let mint_state = StateWithExtensions::<Mint>::unpack(mint_data)?;
-            println!("mint_state:{:?}", mint_state);
+            info!("mint_state:{:?}", mint_state);
             let extensions = get_account_extensions(&mint_state);
-            println!("mint_extensions:{:#?}", extensions);
+            info!("mint_extensions:{:#?}", extensions);
         }
         CommandsName::PToken { token } => {
             let token_data = &rpc_client.get_account_data(&token)?;
             let token_state = StateWithExtensions::<Account>::unpack(token_data)?;
-            println!("token_state:{:?}", token_state);
+            info!("token_state:{:?}", token_state);
             let extensions = get_account_extensions(&token_state);
-            println!("token_extensions:{:#?}", extensions);
+            info!("token_extensions:{:#?}", extensions);
         }
         CommandsName::POperation => {
             let (operation_account_key, __bump) = Pubkey::find_program_address(
                 &[raydium_amm_v3::states::OPERATION_SEED.as_bytes()],
                 &program.id(),
             );
-            println!("{}", operation_account_key);
+            info!("{}", operation_account_key);
             let operation_account: raydium_amm_v3::states::OperationState =
                 program.account(operation_account_key)?;
-            println!("{:#?}", operation_account);
+            info!("{:#?}", operation_account);
         }
         CommandsName::PObservation => {
             let pool: raydium_amm_v3::states::PoolState =
                 program.account(pool_config.pool_id_account.unwrap())?;
-            println!("{}", pool.observation_key);
+            info!("{}", pool.observation_key);
+            let observation_account: raydium_amm_v3::states::ObservationState =
+                program.account(pool.observation_key)?;
+            info!("{:#?}", observation_account);
+        }
+        CommandsName::Twap {
+            pool_id,
+            seconds,
+            until,
+        } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let pool: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
             let observation_account: raydium_amm_v3::states::ObservationState =
                 program.account(pool.observation_key)?;
-            println!("{:#?}", observation_account);
+            // the observations are stamped with the cluster time
+            let block_timestamp = rpc_client.get_block_time(rpc_client.get_slot()?)? as u32;
+            let average_tick = observation_account
+                .average_tick_between(
+                    block_timestamp,
+                    pool.tick_current,
+                    seconds.checked_add(until).unwrap(),
+                    until,
+                )
+                .map_err(|err| format_err!("no twap over {} seconds: {}", seconds, err))?;
+            info!(
+                "pool_id:{}, twap over {}s ending {}s ago: tick {}, price {}, spot: tick {}, price {}",
+                pool_id,
+                seconds,
+                until,
+                average_tick,
+                sqrt_price_x64_to_price(
+                    tick_math::get_sqrt_price_at_tick(average_tick).unwrap(),
+                    pool.mint_decimals_0,
+                    pool.mint_decimals_1
+                ),
+                { pool.tick_current },
+                sqrt_price_x64_to_price(
+                    pool.sqrt_price_x64,
+                    pool.mint_decimals_0,
+                    pool.mint_decimals_1
+                )
+            );
         }
-        CommandsName::PConfig { config_index } => {
+        CommandsName::PConfig {
+            config_index,
+            output,
+        } => {
             let (amm_config_key, __bump) = Pubkey::find_program_address(
                 &[
                     raydium_amm_v3::states::AMM_CONFIG_SEED.as_bytes(),
@@ -2063,19 +3670,49 @@ fn main() -> Result<()> {
                 ],
                 &program.id(),
             );
-            println!("{}", amm_config_key);
             let amm_config_account: raydium_amm_v3::states::AmmConfig =
                 program.account(amm_config_key)?;
-            println!("{:#?}", amm_config_account);
+            if output.format() == OutputFormat::Text {
+                info!("{}", amm_config_key);
+                info!("{:#?}", amm_config_account);
+            } else {
+                print_records(
+                    output.format(),
+                    &[AmmConfigRecord::new(&amm_config_key, &amm_config_account)],
+                )?;
+            }
+        }
+        CommandsName::ListConfigs { output } => {
+            let amm_configs =
+                list_amm_configs(&rpc_client, &scanner, &pool_config.raydium_v3_program)?;
+            if output.format() == OutputFormat::Text {
+                for (amm_config_key, amm_config) in amm_configs.iter() {
+                    info!(
+                        "index:{}, amm_config:{}, tick_spacing:{}, trade_fee_rate:{}",
+                        amm_config.index,
+                        amm_config_key,
+                        amm_config.tick_spacing,
+                        format_fee_rate(amm_config.trade_fee_rate)
+                    );
+                }
+            } else {
+                print_records(
+                    output.format(),
+                    &amm_configs
+                        .iter()
+                        .map(|(key, amm_config)| AmmConfigRecord::new(key, amm_config))
+                        .collect::<Vec<_>>(),
+                )?;
+            }
         }
         CommandsName::PriceToTick { price } => {
-            println!("price:{}, tick:{}", price, price_to_tick(price));
+            info!("price:{}, tick:{}", price, price_to_tick(price));
         }
         CommandsName::TickToPrice { tick } => {
-            println!("tick:{}, price:{}", tick, tick_to_price(tick));
+            info!("tick:{}, price:{}", tick, tick_to_price(tick));
         }
         CommandsName::TickWithSpacing { tick, tick_spacing } => {
-            println!(
+            info!(
                 "tick:{}, tick_spacing:{}, tick_with_spacing:{}",
                 tick,
                 tick_spacing,
@@ -2083,7 +3720,7 @@ fn main() -> Result<()> {
             );
         }
         CommandsName::TickArraryStartIndex { tick, tick_spacing } => {
-            println!(
+            info!(
                 "tick:{}, tick_spacing:{},tick_array_start_index:{}",
                 tick,
                 tick_spacing,
@@ -2104,62 +3741,48 @@ fn main() -> Result<()> {
                 tick_upper,
                 liquidity,
             )?;
-            println!("amount_0:{}, amount_1:{}", amounts.0, amounts.1);
+            info!("amount_0:{}, amount_1:{}", amounts.0, amounts.1);
         }
-        CommandsName::PPersonalPositionByPool { pool_id } => {
+        CommandsName::PPersonalPositionByPool { pool_id, output } => {
             let pool_id = if let Some(pool_id) = pool_id {
                 pool_id
             } else {
                 pool_config.pool_id_account.unwrap()
             };
-            println!("pool_id:{}", pool_id);
-            let position_accounts_by_pool = rpc_client.get_program_accounts_with_config(
+            let positions_by_pool = scan_positions_by_pool(
+                &rpc_client,
+                &scanner,
                 &pool_config.raydium_v3_program,
-                RpcProgramAccountsConfig {
-                    filters: Some(vec![
-                        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
-                            8 + 1 + size_of::<Pubkey>(),
-                            &pool_id.to_bytes(),
-                        )),
-                        RpcFilterType::DataSize(
-                            raydium_amm_v3::states::PersonalPositionState::LEN as u64,
-                        ),
-                    ]),
-                    account_config: RpcAccountInfoConfig {
-                        encoding: Some(UiAccountEncoding::Base64),
-                        ..RpcAccountInfoConfig::default()
-                    },
-                    with_context: Some(false),
-                },
+                &pool_id,
             )?;
+            if output.format() != OutputFormat::Text {
+                let records: Vec<PositionRecord> =
+                    positions_by_pool.iter().map(PositionRecord::from).collect();
+                print_records(output.format(), &records)?;
+                return Ok(());
+            }
+            info!("pool_id:{}", pool_id);
 
             let mut total_fees_owed_0 = 0;
             let mut total_fees_owed_1 = 0;
             let mut total_reward_owed = 0;
-            for position in position_accounts_by_pool {
-                let personal_position = deserialize_anchor_account::<
-                    raydium_amm_v3::states::PersonalPositionState,
-                >(&position.1)?;
-                if personal_position.pool_id == pool_id {
-                    println!(
-                        "personal_position:{}, lower:{}, upper:{}, liquidity:{}, token_fees_owed_0:{}, token_fees_owed_1:{}, reward_amount_owed:{}, fee_growth_inside:{}, fee_growth_inside_1:{}, reward_inside:{}",
-                        position.0,
-                        personal_position.tick_lower_index,
-                        personal_position.tick_upper_index,
-                        personal_position.liquidity,
-                        personal_position.token_fees_owed_0,
-                        personal_position.token_fees_owed_1,
-                        personal_position.reward_infos[0].reward_amount_owed,
-                        personal_position.fee_growth_inside_0_last_x64,
-                        personal_position.fee_growth_inside_1_last_x64,
-                        personal_position.reward_infos[0].growth_inside_last_x64,
-                    );
-                    total_fees_owed_0 += personal_position.token_fees_owed_0;
-                    total_fees_owed_1 += personal_position.token_fees_owed_1;
-                    total_reward_owed += personal_position.reward_infos[0].reward_amount_owed;
-                }
+            for position in positions_by_pool {
+                info!(
+                    "personal_position:{}, layout:{:?}, lower:{}, upper:{}, liquidity:{}, token_fees_owed_0:{}, token_fees_owed_1:{}, reward_amount_owed:{}",
+                    position.key,
+                    position.layout,
+                    position.tick_lower_index,
+                    position.tick_upper_index,
+                    position.liquidity,
+                    position.token_fees_owed_0,
+                    position.token_fees_owed_1,
+                    position.reward_amounts_owed[0],
+                );
+                total_fees_owed_0 += position.token_fees_owed_0;
+                total_fees_owed_1 += position.token_fees_owed_1;
+                total_reward_owed += position.reward_amounts_owed[0];
             }
-            println!(
+            info!(
                 "total_fees_owed_0:{}, total_fees_owed_1:{}, total_reward_owed:{}",
                 total_fees_owed_0, total_fees_owed_1, total_reward_owed
             );
@@ -2170,8 +3793,9 @@ fn main() -> Result<()> {
             } else {
                 pool_config.pool_id_account.unwrap()
             };
-            println!("pool_id:{}", pool_id);
-            let position_accounts_by_pool = rpc_client.get_program_accounts_with_config(
+            info!("pool_id:{}", pool_id);
+            let position_accounts_by_pool = scanner.get_program_accounts_with_config(
+                &rpc_client,
                 &pool_config.raydium_v3_program,
                 RpcProgramAccountsConfig {
                     filters: Some(vec![
@@ -2196,12 +3820,12 @@ fn main() -> Result<()> {
                     raydium_amm_v3::states::ProtocolPositionState,
                 >(&position.1)?;
                 if protocol_position.pool_id == pool_id {
-                    println!(
+                    info!(
                         "protocol_position:{} lower_index:{}, upper_index:{}, liquidity:{}",
                         position.0,
-                        protocol_position.tick_lower_index,
-                        protocol_position.tick_upper_index,
-                        protocol_position.liquidity,
+                        { protocol_position.tick_lower_index },
+                        { protocol_position.tick_upper_index },
+                        { protocol_position.liquidity },
                     );
                 }
             }
@@ -2212,8 +3836,9 @@ fn main() -> Result<()> {
             } else {
                 pool_config.pool_id_account.unwrap()
             };
-            println!("pool_id:{}", pool_id);
-            let tick_arrays_by_pool = rpc_client.get_program_accounts_with_config(
+            info!("pool_id:{}", pool_id);
+            let tick_arrays_by_pool = scanner.get_program_accounts_with_config(
+                &rpc_client,
                 &pool_config.raydium_v3_program,
                 RpcProgramAccountsConfig {
                     filters: Some(vec![
@@ -2233,7 +3858,7 @@ fn main() -> Result<()> {
                     raydium_amm_v3::states::TickArrayState,
                 >(&tick_array.1)?;
                 if tick_array_state.pool_id == pool_id {
-                    println!(
+                    info!(
                         "tick_array:{}, {}, {}",
                         tick_array.0,
                         identity(tick_array_state.start_tick_index),
@@ -2241,21 +3866,315 @@ fn main() -> Result<()> {
                     );
                     for tick_state in tick_array_state.ticks {
                         if tick_state.liquidity_gross != 0 {
-                            println!("{:#?}", tick_state);
+                            info!("{:#?}", tick_state);
                         }
                     }
                 }
             }
         }
-        CommandsName::PPool { pool_id } => {
+        CommandsName::PPool { pool_id, output } => {
             let pool_id = if let Some(pool_id) = pool_id {
                 pool_id
             } else {
                 pool_config.pool_id_account.unwrap()
             };
-            println!("pool_id:{}", pool_id);
             let pool_account: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
-            println!("{:#?}", pool_account);
+            if output.format() == OutputFormat::Text {
+                info!("pool_id:{}", pool_id);
+                info!("{:#?}", pool_account);
+            } else {
+                print_records(output.format(), &[PoolRecord::new(&pool_id, &pool_account)])?;
+            }
+        }
+        CommandsName::CreatePoolStats { pool_id } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let instructions = create_pool_stats_instr(&pool_config, pool_id)?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::RollStats { pool_id } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let (pool_stats_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::POOL_STATS_SEED.as_bytes(),
+                    pool_id.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let instructions = roll_stats_instr(&pool_config, pool_stats_key)?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::PPoolStats { pool_id } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let (pool_stats_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::POOL_STATS_SEED.as_bytes(),
+                    pool_id.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let pool_stats: raydium_amm_v3::states::PoolStatsState =
+                program.account(pool_stats_key)?;
+            let total = pool_stats.total;
+            let updated_at = pool_stats.updated_at;
+            info!("pool_stats:{}", pool_stats_key);
+            info!(
+                "since:{}, updated_at:{}, volume_0:{}, volume_1:{}, fees_0:{}, fees_1:{}, swap_count:{}",
+                { total.start_time },
+                updated_at,
+                { total.volume_0 },
+                { total.volume_1 },
+                { total.fees_0 },
+                { total.fees_1 },
+                { total.swap_count }
+            );
+        }
+        CommandsName::PPositionAmounts { nft_mint } => {
+            let (personal_position_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::POSITION_SEED.as_bytes(),
+                    nft_mint.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let personal_position = deserialize_anchor_account::<
+                raydium_amm_v3::states::PersonalPositionState,
+            >(&rpc_client.get_account(&personal_position_key)?)?;
+            let pool_state = deserialize_anchor_account::<PoolState>(
+                &rpc_client.get_account(&personal_position.pool_id)?,
+            )?;
+            let tick_array = |tick_index: i32| {
+                raydium_amm_v3::states::tick_array_key(
+                    &pool_config.raydium_v3_program,
+                    &personal_position.pool_id,
+                    TickArrayState::get_array_start_index(tick_index, pool_state.tick_spacing),
+                )
+            };
+            let instructions = get_position_amounts_instr(
+                &pool_config,
+                nft_mint,
+                personal_position.pool_id,
+                tick_array(personal_position.tick_lower_index),
+                tick_array(personal_position.tick_upper_index),
+            )?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            let ret = simulate_transaction(&rpc_client, &txn, true, CommitmentConfig::confirmed())?;
+            let position_amounts =
+                decode_position_amounts(&ret.value.return_data, &pool_config.raydium_v3_program)
+                    .ok_or(format_err!(
+                        "no position amounts returned, simulation error: {:?}",
+                        ret.value.err
+                    ))?;
+            info!("{:#?}", position_amounts);
+        }
+        CommandsName::PPoolFlow { pool_id } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let pool_account: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
+            info!("pool_id:{}", pool_id);
+            info!("{}", PoolOrderFlow::new(&pool_account).report());
+        }
+        CommandsName::PReward { pool_id } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let pool_account: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
+            info!("pool_id:{}", pool_id);
+            for (index, reward_info) in pool_account.reward_infos.iter().enumerate() {
+                if !reward_info.initialized() {
+                    continue;
+                }
+                let vault_balance =
+                    rpc_client.get_token_account_balance(&reward_info.token_vault)?;
+                let schedule = RewardSchedule::new(
+                    index,
+                    reward_info,
+                    vault_balance.amount.parse::<u64>()?,
+                    vault_balance.decimals,
+                );
+                info!("{}", schedule.report());
+            }
+        }
+        CommandsName::RewardApr {
+            pool_id,
+            tick_lower,
+            tick_upper,
+            reward_prices,
+            max_transactions,
+            output,
+        } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let pool: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
+            let amm_config: raydium_amm_v3::states::AmmConfig = program.account(pool.amm_config)?;
+            let observation_state: raydium_amm_v3::states::ObservationState =
+                program.account(pool.observation_key)?;
+            let tick_lower = tick_lower.unwrap_or(tick_math::MIN_TICK);
+            let tick_upper = tick_upper.unwrap_or(tick_math::MAX_TICK);
+            if tick_lower >= tick_upper {
+                return Err(format_err!(
+                    "tick_lower {} must be below tick_upper {}",
+                    tick_lower,
+                    tick_upper
+                ));
+            }
+            let (tick_current, sqrt_price_x64, liquidity) =
+                (pool.tick_current, pool.sqrt_price_x64, pool.liquidity);
+            // the observations are stamped with the cluster time
+            let block_timestamp = rpc_client.get_block_time(rpc_client.get_slot()?)? as u64;
+            // the observations only hold tick cumulatives, the fees are read from the swap events
+            let longest_window = APR_WINDOWS
+                .iter()
+                .map(|(_, seconds)| *seconds)
+                .max()
+                .unwrap();
+            let (fee_growths, covered_from) = load_swap_fee_growths(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                &pool_id,
+                &amm_config,
+                block_timestamp.saturating_sub(longest_window as u64),
+                max_transactions,
+            )?;
+
+            let mut resolver = PoolResolver::new(&rpc_client, pool_config.raydium_v3_program);
+            let reward_infos = pool.reward_infos;
+            let mut records = Vec::new();
+            for (window, seconds) in APR_WINDOWS {
+                let (window_sqrt_price_x64, price_source) = window_sqrt_price_x64(
+                    &observation_state,
+                    block_timestamp as u32,
+                    tick_current,
+                    sqrt_price_x64,
+                    seconds,
+                );
+                let unit_value =
+                    liquidity_unit_value(window_sqrt_price_x64, tick_lower, tick_upper)?;
+
+                let fee_from = block_timestamp
+                    .saturating_sub(seconds as u64)
+                    .max(covered_from);
+                let fee_per_liquidity: f64 = fee_growths
+                    .iter()
+                    .filter(|fee_growth| fee_growth.block_time >= fee_from)
+                    .map(|fee_growth| fee_growth.fee_per_liquidity)
+                    .sum();
+                let fee_seconds = block_timestamp - fee_from;
+                records.push(AprRecord {
+                    pool_id: pool_id.to_string(),
+                    window: window.to_string(),
+                    source: "fee".to_string(),
+                    token_mint: String::new(),
+                    price_source: price_source.to_string(),
+                    seconds: fee_seconds,
+                    apr: annualized_rate(fee_per_liquidity, fee_seconds, unit_value),
+                });
+
+                for (index, reward_info) in reward_infos.iter().enumerate() {
+                    if !reward_info.initialized() {
+                        continue;
+                    }
+                    let (token_mint, open_time, end_time, emissions_per_second_x64) = (
+                        reward_info.token_mint,
+                        reward_info.open_time,
+                        reward_info.end_time,
+                        reward_info.emissions_per_second_x64,
+                    );
+                    let price = if token_mint == pool.token_mint_1 {
+                        Some(1.0)
+                    } else if token_mint == pool.token_mint_0 {
+                        Some(from_x64_price(window_sqrt_price_x64).powi(2))
+                    } else {
+                        reward_prices
+                            .iter()
+                            .find(|(mint, _)| *mint == token_mint)
+                            .and_then(|(_, price)| {
+                                resolver.decimals(&token_mint).map(|decimals| {
+                                    raw_price(*price, decimals, pool.mint_decimals_1)
+                                })
+                            })
+                    };
+                    // the reward only emits between its open and end times
+                    let emitting = open_time <= block_timestamp && block_timestamp < end_time;
+                    let reward_per_second = if emitting {
+                        reward_per_liquidity_per_second(emissions_per_second_x64, liquidity)
+                    } else {
+                        0.0
+                    };
+                    records.push(AprRecord {
+                        pool_id: pool_id.to_string(),
+                        window: window.to_string(),
+                        source: format!("reward_{}", index),
+                        token_mint: token_mint.to_string(),
+                        price_source: price_source.to_string(),
+                        seconds: seconds as u64,
+                        apr: price.and_then(|price| {
+                            annualized_rate(
+                                reward_per_second * price * seconds as f64,
+                                seconds as u64,
+                                unit_value,
+                            )
+                        }),
+                    });
+                }
+            }
+
+            if output.format() == OutputFormat::Text {
+                info!(
+                    "pool_id:{}, ticks:[{}, {}], liquidity in range:{}",
+                    pool_id, tick_lower, tick_upper, liquidity
+                );
+                for record in records.iter() {
+                    info!(
+                        "{} {} {}: apr {} over {}s, valued at the {} price",
+                        record.window,
+                        record.source,
+                        record.token_mint,
+                        record
+                            .apr
+                            .map_or("unpriced, pass --reward-price".to_string(), |apr| {
+                                format!("{:.2}%", apr * 100.0)
+                            }),
+                        record.seconds,
+                        record.price_source
+                    );
+                }
+            } else {
+                print_records(output.format(), &records)?;
+            }
         }
         CommandsName::PBitmapExtension { bitmap_extension } => {
             let bitmap_extension = if let Some(bitmap_extension) = bitmap_extension {
@@ -2263,37 +4182,212 @@ fn main() -> Result<()> {
             } else {
                 pool_config.tickarray_bitmap_extension.unwrap()
             };
-            println!("bitmap_extension:{}", bitmap_extension);
+            info!("bitmap_extension:{}", bitmap_extension);
             let bitmap_extension_account: raydium_amm_v3::states::TickArrayBitmapExtension =
                 program.account(bitmap_extension)?;
-            println!("{:#?}", bitmap_extension_account);
+            info!("{:#?}", bitmap_extension_account);
         }
         CommandsName::PProtocol { protocol_id } => {
             let protocol_account: raydium_amm_v3::states::ProtocolPositionState =
                 program.account(protocol_id)?;
-            println!("{:#?}", protocol_account);
+            info!("{:#?}", protocol_account);
         }
         CommandsName::PPersonal { personal_id } => {
             let personal_account: raydium_amm_v3::states::PersonalPositionState =
                 program.account(personal_id)?;
-            println!("{:#?}", personal_account);
+            info!("{:#?}", personal_account);
         }
-        CommandsName::DecodeInstruction { instr_hex_data } => {
-            handle_program_instruction(&instr_hex_data, InstructionDecodeType::BaseHex)?;
-        }
-        CommandsName::DecodeEvent { log_event } => {
-            handle_program_log(
-                &pool_config.raydium_v3_program.to_string(),
-                &log_event,
-                false,
+        CommandsName::GenRewardMerkle {
+            reward_index,
+            out_file,
+        } => {
+            let pool_id = pool_config.pool_id_account.unwrap();
+            let pool: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
+            // read before the positions, a collect afterwards makes the root rejected instead of
+            // paying the position twice
+            let reward_info = pool.reward_infos[reward_index as usize];
+            if !reward_info.initialized() || reward_info.last_update_time != reward_info.end_time {
+                panic!("reward {} is not initialized or not ended", reward_index);
+            }
+            let position_accounts_by_pool = scanner.get_program_accounts_with_config(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                RpcProgramAccountsConfig {
+                    filters: Some(vec![
+                        RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+                            8 + 1 + size_of::<Pubkey>(),
+                            &pool_id.to_bytes(),
+                        )),
+                        RpcFilterType::DataSize(
+                            raydium_amm_v3::states::PersonalPositionState::LEN as u64,
+                        ),
+                    ]),
+                    account_config: RpcAccountInfoConfig {
+                        encoding: Some(UiAccountEncoding::Base64),
+                        ..RpcAccountInfoConfig::default()
+                    },
+                    with_context: Some(false),
+                },
             )?;
-        }
-        CommandsName::DecodeTxLog { tx_id } => {
-            let signature = Signature::from_str(&tx_id)?;
-            let tx = rpc_client.get_transaction_with_config(
-                &signature,
-                RpcTransactionConfig {
-                    encoding: Some(UiTransactionEncoding::Json),
+            // aggregate the owed rewards by the wallet holding the position nft
+            let mut owed_by_owner: std::collections::BTreeMap<Pubkey, u64> =
+                std::collections::BTreeMap::new();
+            for position in position_accounts_by_pool {
+                let personal_position = deserialize_anchor_account::<
+                    raydium_amm_v3::states::PersonalPositionState,
+                >(&position.1)?;
+                if personal_position.pool_id != pool_id {
+                    continue;
+                }
+                let owed = get_position_reward_owed(
+                    &rpc_client,
+                    &pool_config,
+                    &pool,
+                    &personal_position,
+                    reward_index as usize,
+                )?;
+                if owed == 0 {
+                    continue;
+                }
+                match get_nft_owner(&rpc_client, &personal_position.nft_mint)? {
+                    Some(owner) => {
+                        let total = owed_by_owner.entry(owner).or_insert(0);
+                        *total = total.checked_add(owed).unwrap();
+                    }
+                    None => info!(
+                        "position:{} nft is burned, owed:{} skipped",
+                        position.0, owed
+                    ),
+                }
+            }
+            let claims: Vec<(Pubkey, u64)> = owed_by_owner.into_iter().collect();
+            let (merkle_root, proofs) = build_reward_merkle_tree(&claims);
+            let total_amount = claims.iter().map(|(_, amount)| amount).sum::<u64>();
+            let snapshot = RewardMerkleSnapshot {
+                pool_id: pool_id.to_string(),
+                reward_index,
+                merkle_root: hex::encode(merkle_root),
+                total_amount,
+                reward_claimed: reward_info.reward_claimed,
+                claims: claims
+                    .iter()
+                    .zip(proofs.iter())
+                    .map(|((claimant, amount), proof)| RewardMerkleClaim {
+                        claimant: claimant.to_string(),
+                        amount: *amount,
+                        proof: proof.iter().map(hex::encode).collect(),
+                    })
+                    .collect(),
+            };
+            let out_file =
+                out_file.unwrap_or(format!("reward_merkle_{}_{}.json", pool_id, reward_index));
+            std::fs::write(&out_file, serde_json::to_string_pretty(&snapshot)?)?;
+            info!(
+                "merkle_root:{}, total_amount:{}, claims:{}, file:{}",
+                snapshot.merkle_root,
+                total_amount,
+                snapshot.claims.len(),
+                out_file
+            );
+        }
+        CommandsName::SetRewardMerkleRoot { snapshot_file } => {
+            let snapshot: RewardMerkleSnapshot =
+                serde_json::from_str(&std::fs::read_to_string(&snapshot_file)?)?;
+            let pool_id = Pubkey::from_str(&snapshot.pool_id)?;
+            let pool: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
+            let merkle_root: [u8; 32] = hex::decode(&snapshot.merkle_root)?
+                .try_into()
+                .map_err(|_| format_err!("invalid merkle root"))?;
+            let set_root_instr = set_reward_merkle_root_instr(
+                &pool_config.clone(),
+                pool_id,
+                pool.reward_infos[snapshot.reward_index as usize].token_vault,
+                snapshot.reward_index,
+                merkle_root,
+                snapshot.total_amount,
+                snapshot.reward_claimed,
+            )?;
+            // send
+            let signers = vec![&payer, &admin];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &set_root_instr,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::ClaimMerkleReward { snapshot_file } => {
+            let snapshot: RewardMerkleSnapshot =
+                serde_json::from_str(&std::fs::read_to_string(&snapshot_file)?)?;
+            let pool_id = Pubkey::from_str(&snapshot.pool_id)?;
+            let pool: raydium_amm_v3::states::PoolState = program.account(pool_id)?;
+            let claim = snapshot
+                .claims
+                .iter()
+                .find(|claim| claim.claimant == payer.pubkey().to_string())
+                .ok_or(format_err!("{} not in the snapshot", payer.pubkey()))?;
+            let mut proof = Vec::new();
+            for node in claim.proof.iter() {
+                let node: [u8; 32] = hex::decode(node)?
+                    .try_into()
+                    .map_err(|_| format_err!("invalid proof node"))?;
+                proof.push(node);
+            }
+            let reward_info = pool.reward_infos[snapshot.reward_index as usize];
+            let reward_mint_account = rpc_client.get_account(&reward_info.token_mint)?;
+            let claim_instr = claim_merkle_reward_instr(
+                &pool_config.clone(),
+                pool_id,
+                snapshot.reward_index,
+                reward_info.token_vault,
+                reward_info.token_mint,
+                spl_associated_token_account::get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &reward_info.token_mint,
+                    &reward_mint_account.owner,
+                ),
+                claim.amount,
+                proof,
+            )?;
+            // send
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &claim_instr,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::DecodeInstruction { instr_hex_data } => {
+            handle_program_instruction(&instr_hex_data, InstructionDecodeType::BaseHex)?;
+        }
+        CommandsName::DecodeEvent { log_event } => {
+            handle_program_log(
+                &pool_config.raydium_v3_program.to_string(),
+                &log_event,
+                false,
+            )?;
+        }
+        CommandsName::DecodeTxLog { tx_id } => {
+            let signature = Signature::from_str(&tx_id)?;
+            let tx = rpc_client.get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
                     commitment: Some(CommitmentConfig::confirmed()),
                     max_supported_transaction_version: Some(0),
                 },
@@ -2316,7 +4410,1342 @@ fn main() -> Result<()> {
             // decode logs
             parse_program_event(&pool_config.raydium_v3_program.to_string(), meta.clone())?;
         }
-    }
+        CommandsName::DecodeLogs { signature } => {
+            let tx = rpc_client.get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )?;
+            let logs = match tx.transaction.meta.map(|meta| meta.log_messages) {
+                Some(OptionSerializer::Some(logs)) => logs,
+                _ => return Err(format_err!("transaction {} has no logs", signature)),
+            };
+            let events = raydium_amm_v3::events::decode::decode_logs(
+                &logs,
+                &pool_config.raydium_v3_program,
+            )?;
+            info!("events:{}", events.len());
+            for event in events.iter() {
+                info!("{} {:#?}", event.name(), event);
+            }
+        }
+        CommandsName::WatchPool {
+            pool_id,
+            arrays_around,
+        } => {
+            let pool_id = pool_id.unwrap_or_else(|| pool_config.pool_id_account.unwrap());
+            let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&pool_id)?,
+            )?;
+            info!(
+                "pool:{}, ws:{}, {}",
+                pool_id,
+                pool_config.ws_url,
+                describe_pool_update(None, &pool_state)
+            );
+            let watch = watch_pool(&pool_config, &pool_id, &pool_state, arrays_around)?;
+            let mut last_pool_state = Some(pool_state);
+            for notification in watch.notifications.iter() {
+                match describe_notification(&notification, &mut last_pool_state) {
+                    Ok(line) => info!("{}", line),
+                    Err(err) => warn!("{:?}: {}", notification.account, err),
+                }
+            }
+        }
+        CommandsName::Diagnose {
+            samples,
+            swap_amount,
+        } => {
+            let pool_id = pool_config.pool_id_account.unwrap();
+            let tickarray_bitmap_extension_key = pool_config.tickarray_bitmap_extension.unwrap();
+            let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&pool_id)?,
+            )?;
+            // the accounts every quote and swap reads
+            let hot_accounts = vec![
+                pool_id,
+                pool_state.amm_config,
+                pool_state.observation_key,
+                tickarray_bitmap_extension_key,
+            ];
 
-    Ok(())
+            info!("rpc:{}, ws:{}", pool_config.http_url, pool_config.ws_url);
+            let blockhash_stats = LatencyStats::measure("getLatestBlockhash", samples, || {
+                Ok(rpc_client.get_latest_blockhash()?)
+            });
+            info!("{}", blockhash_stats.report());
+            let accounts_stats = LatencyStats::measure("getMultipleAccounts", samples, || {
+                Ok(rpc_client.get_multiple_accounts(&hot_accounts)?)
+            });
+            info!("{}", accounts_stats.report());
+            match measure_ws_slot_lag(&rpc_client, &pool_config.ws_url, Duration::from_secs(10)) {
+                Ok(ws_slot_lag) => info!("{}", ws_slot_lag.report()),
+                Err(err) => info!("{:<24} {:<8} {}", "slotSubscribe", "FAILING", err),
+            }
+
+            if let Some(amount) = swap_amount {
+                let rsps = rpc_client.get_multiple_accounts(&[
+                    pool_state.amm_config,
+                    tickarray_bitmap_extension_key,
+                ])?;
+                let amm_config_state = deserialize_anchor_account::<
+                    raydium_amm_v3::states::AmmConfig,
+                >(rsps[0].as_ref().unwrap())?;
+                let tickarray_bitmap_extension = deserialize_anchor_account::<
+                    raydium_amm_v3::states::TickArrayBitmapExtension,
+                >(rsps[1].as_ref().unwrap())?;
+                let quote = load_cur_and_next_five_tick_array(
+                    &rpc_client,
+                    &pool_config,
+                    &pool_state,
+                    &tickarray_bitmap_extension,
+                    true,
+                )
+                .and_then(|mut tick_arrays| {
+                    utils::get_out_put_amount_and_remaining_accounts(
+                        amount,
+                        None,
+                        true,
+                        true,
+                        &amm_config_state,
+                        &pool_state,
+                        &tickarray_bitmap_extension,
+                        &mut tick_arrays,
+                    )
+                });
+                let (amount_out, mut tick_array_indexs) = match quote {
+                    Ok(quote) => quote,
+                    Err(err) => {
+                        info!("{:<24} {:<8} {}", "simulateSwap", "FAILING", err);
+                        return Ok(());
+                    }
+                };
+                let tick_array_key = |index: i32| {
+                    Pubkey::find_program_address(
+                        &[
+                            raydium_amm_v3::states::TICK_ARRAY_SEED.as_bytes(),
+                            pool_id.to_bytes().as_ref(),
+                            &index.to_be_bytes(),
+                        ],
+                        &pool_config.raydium_v3_program,
+                    )
+                    .0
+                };
+                let current_or_next_tick_array_key =
+                    tick_array_key(tick_array_indexs.pop_front().unwrap());
+                let mut remaining_accounts = vec![AccountMeta::new_readonly(
+                    tickarray_bitmap_extension_key,
+                    false,
+                )];
+                remaining_accounts.extend(
+                    tick_array_indexs
+                        .into_iter()
+                        .map(|index| AccountMeta::new(tick_array_key(index), false)),
+                );
+                let mut instructions = Vec::new();
+                instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(
+                    1400_000u32,
+                ));
+                instructions.extend(swap_instr(
+                    &pool_config.clone(),
+                    pool_state.amm_config,
+                    pool_id,
+                    pool_state.token_vault_0,
+                    pool_state.token_vault_1,
+                    pool_state.observation_key,
+                    get_associated_token_address(&payer.pubkey(), &pool_state.token_mint_0),
+                    get_associated_token_address(&payer.pubkey(), &pool_state.token_mint_1),
+                    current_or_next_tick_array_key,
+                    remaining_accounts,
+                    amount,
+                    // a stale quote fails the simulation with the slippage error
+                    amount_with_slippage(amount_out, pool_config.slippage, false),
+                    None,
+                    true,
+                )?);
+                let signers = vec![&payer];
+                let recent_hash = rpc_client.get_latest_blockhash()?;
+                let txn = Transaction::new_signed_with_payer(
+                    &instructions,
+                    Some(&payer.pubkey()),
+                    &signers,
+                    recent_hash,
+                );
+                let start = Instant::now();
+                let ret =
+                    simulate_transaction(&rpc_client, &txn, true, CommitmentConfig::confirmed());
+                let elapsed = start.elapsed().as_millis();
+                match ret {
+                    Ok(ret) => match ret.value.err {
+                        None => info!(
+                            "{:<24} {:<8} {}ms, amount_in:{}, quoted_amount_out:{}, units_consumed:{:?}",
+                            "simulateSwap",
+                            "OK",
+                            elapsed,
+                            amount,
+                            amount_out,
+                            ret.value.units_consumed
+                        ),
+                        Some(err) => {
+                            info!(
+                                "{:<24} {:<8} {}ms, error:{}",
+                                "simulateSwap", "FAILING", elapsed, err
+                            );
+                            for log in ret.value.logs.unwrap_or_default() {
+                                info!("    {}", log);
+                            }
+                        }
+                    },
+                    Err(err) => info!(
+                        "{:<24} {:<8} {}ms, error:{}",
+                        "simulateSwap", "FAILING", elapsed, err
+                    ),
+                }
+            }
+        }
+        CommandsName::ExportSnapshot { pool_id, out_file } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            let snapshot = load_pool_snapshot(
+                &rpc_client,
+                &scanner,
+                &pool_config.raydium_v3_program,
+                &pool_id,
+            )?;
+            let out_file =
+                out_file.unwrap_or(format!("pool_snapshot_{}_{}.json", pool_id, snapshot.slot));
+            std::fs::write(&out_file, serde_json::to_string_pretty(&snapshot)?)?;
+            info!(
+                "slot:{}, merkle_root:{}, accounts:{}, file:{}",
+                snapshot.slot,
+                snapshot.merkle_root,
+                snapshot.accounts.len(),
+                out_file
+            );
+        }
+        CommandsName::SnapshotPool { pool_id, out_file } => {
+            let snapshot = load_pool_liquidity_snapshot(
+                &rpc_client,
+                &scanner,
+                &pool_config.raydium_v3_program,
+                &pool_id,
+            )?;
+            std::fs::write(&out_file, serde_json::to_string_pretty(&snapshot)?)?;
+            info!(
+                "slot:{}, ticks:{}, positions:{}, accounts:{}, file:{}",
+                snapshot.slot,
+                snapshot.ticks.len(),
+                snapshot.positions.len(),
+                snapshot.accounts.len(),
+                out_file
+            );
+        }
+        CommandsName::Replay {
+            snapshot_file,
+            swaps_file,
+            out_file,
+        } => {
+            let snapshot: PoolLiquiditySnapshot =
+                serde_json::from_str(&std::fs::read_to_string(&snapshot_file)?)?;
+            let swaps: Vec<ReplaySwap> =
+                serde_json::from_str(&std::fs::read_to_string(&swaps_file)?)?;
+            let mut replay_pool = ReplayPool::from_snapshot(&snapshot)?;
+            let report = replay_swaps(&mut replay_pool, &swaps)?;
+            for line in report_replay(&report) {
+                info!("{}", line);
+            }
+            if let Some(out_file) = out_file {
+                std::fs::write(&out_file, serde_json::to_string_pretty(&report)?)?;
+            }
+        }
+        CommandsName::VerifySnapshot { snapshot_file } => {
+            let snapshot: PoolSnapshot =
+                serde_json::from_str(&std::fs::read_to_string(&snapshot_file)?)?;
+            // the root must match the accounts listed in the file
+            let merkle_root = hex::encode(snapshot_merkle_root(&snapshot.accounts)?);
+            if merkle_root != snapshot.merkle_root {
+                panic!(
+                    "snapshot merkle_root:{} does not match the accounts, derived:{}",
+                    snapshot.merkle_root, merkle_root
+                );
+            }
+            let pool_id = Pubkey::from_str(&snapshot.pool_id)?;
+            let current = load_pool_snapshot(
+                &rpc_client,
+                &scanner,
+                &pool_config.raydium_v3_program,
+                &pool_id,
+            )?;
+            if current.merkle_root == snapshot.merkle_root {
+                info!(
+                    "snapshot matches, slot:{}, current slot:{}, merkle_root:{}",
+                    snapshot.slot, current.slot, current.merkle_root
+                );
+            } else {
+                info!(
+                    "snapshot differs, slot:{}, merkle_root:{}, current slot:{}, current merkle_root:{}",
+                    snapshot.slot, snapshot.merkle_root, current.slot, current.merkle_root
+                );
+                for diff in snapshot.diff(&current) {
+                    info!("    {}", diff);
+                }
+            }
+        }
+        CommandsName::BestPool {
+            input_mint,
+            output_mint,
+            amount_in,
+            pools,
+        } => {
+            let pools = if pools.is_empty() {
+                find_pair_pools(
+                    &rpc_client,
+                    &scanner,
+                    &pool_config.raydium_v3_program,
+                    input_mint,
+                    output_mint,
+                )?
+            } else {
+                pools
+            };
+            if pools.is_empty() {
+                info!("no pool of {} and {}", input_mint, output_mint);
+            }
+            let quotes = quote_pools(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                &pools,
+                input_mint,
+                amount_in,
+            );
+            for (rank, (pool_id, quote)) in quotes.iter().enumerate() {
+                match quote {
+                    Ok(quote) => info!(
+                        "{}. pool:{}, trade_fee_rate:{}, amount_in:{}, amount_out:{}, tick_arrays:{:?}",
+                        rank + 1,
+                        pool_id,
+                        quote.trade_fee_rate,
+                        quote.amount_in,
+                        quote.amount_out,
+                        quote.tick_array_start_indexes
+                    ),
+                    Err(err) => info!("-. pool:{}, error:{}", pool_id, err),
+                }
+            }
+        }
+        CommandsName::InitWizard {
+            config_index,
+            tick_spacing,
+            trade_fee_rate,
+            protocol_fee_rate,
+            fund_fee_rate,
+            price,
+            mint0,
+            mint1,
+            decimals,
+            mint_amount,
+            position_amount,
+            swap_amount,
+            summary_file,
+            yes,
+        } => {
+            let options = WizardOptions {
+                config_index,
+                tick_spacing,
+                trade_fee_rate,
+                protocol_fee_rate,
+                fund_fee_rate,
+                mint0,
+                mint1,
+                mint_decimals: decimals,
+                mint_amount,
+                price,
+                position_amount_0: position_amount,
+                swap_amount_0: swap_amount,
+                assume_yes: yes,
+            };
+            let mut summary = WizardSummary::default();
+            let result = run_init_wizard(
+                &rpc_client,
+                &pool_config,
+                &payer,
+                &admin,
+                &options,
+                &mut summary,
+            );
+            // the summary records the steps done before a failure as well
+            summary.write(&summary_file)?;
+            info!("summary written to {}", summary_file);
+            result?;
+        }
+        CommandsName::QuorumRead { keys } => {
+            let (responses, errors) = rpc_pool.get_multiple_accounts_from_all(&keys);
+            for response in &responses {
+                info!(
+                    "{} slot:{} hash:{}",
+                    response.http_url, response.slot, response.hash
+                );
+            }
+            for err in errors {
+                info!("{}", err);
+            }
+            let quorum = select_quorum(responses, rpc_pool.quorum)?;
+            info!(
+                "quorum of {}/{} endpoints at slot {}, hash:{}",
+                quorum.agreeing.len(),
+                rpc_pool.endpoints.len(),
+                quorum.slot,
+                quorum.hash
+            );
+        }
+        CommandsName::VerifyBuild {
+            commit,
+            repo,
+            so_path,
+        } => {
+            let (commit, local_binary) = match so_path {
+                Some(so_path) => (None, std::fs::read(&so_path)?),
+                None => {
+                    let binary = build_program_at_commit(std::path::Path::new(&repo), &commit)?;
+                    (Some(commit), binary)
+                }
+            };
+            let report = verify_build(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                commit,
+                &local_binary,
+            )?;
+            info!("{}", report.report());
+            if !report.matches() {
+                return Err(format_err!("the deployed program doesn't match the build"));
+            }
+        }
+        CommandsName::PoolManifest { pool_ids, out_dir } => {
+            let pool_ids = if pool_ids.is_empty() {
+                vec![pool_config.pool_id_account.unwrap()]
+            } else {
+                pool_ids
+            };
+            for pool_id in pool_ids {
+                let manifest = load_pool_manifest(
+                    &rpc_client,
+                    &scanner,
+                    &pool_config.raydium_v3_program,
+                    &pool_id,
+                )?;
+                let out_file =
+                    std::path::Path::new(&out_dir).join(format!("pool_manifest_{}.json", pool_id));
+                std::fs::write(&out_file, serde_json::to_string_pretty(&manifest)?)?;
+                info!(
+                    "pool:{}, slot:{}, tick_arrays:{}, file:{}",
+                    pool_id,
+                    manifest.slot,
+                    manifest.accounts.tick_arrays.len(),
+                    out_file.display()
+                );
+            }
+        }
+        CommandsName::ExportHistory {
+            owner,
+            from,
+            out_file,
+        } => {
+            let from_time = match from {
+                Some(from) => parse_utc_date(&from)?,
+                None => 0,
+            };
+            let rows = export_position_history(
+                &rpc_client,
+                &pool_config.raydium_v3_program,
+                &owner,
+                from_time,
+            )?;
+            let mut csv = String::from(POSITION_HISTORY_CSV_HEADER);
+            csv.push('\n');
+            for row in rows.iter() {
+                csv.push_str(&row.to_csv());
+                csv.push('\n');
+            }
+            std::fs::write(&out_file, csv)?;
+            info!("rows:{}, file:{}", rows.len(), out_file);
+        }
+        CommandsName::RunSchedule {
+            plan_file,
+            notify_cmd,
+            dry_run,
+            poll_interval,
+            attempts,
+        } => {
+            run_schedule(
+                &rpc_client,
+                &pool_config,
+                &payer,
+                &admin,
+                &plan_file,
+                &ScheduleNotifier {
+                    command: notify_cmd,
+                },
+                &ScheduleOptions {
+                    dry_run: dry_run || simulate,
+                    poll_interval: poll_interval.max(1),
+                    attempts: attempts.max(1),
+                },
+            )?;
+        }
+        CommandsName::CloseAllPositions {
+            pool_id,
+            slippage,
+            yes,
+        } => {
+            let summary = close_all_positions(
+                &rpc_client,
+                &pool_config,
+                &payer,
+                &pool_id,
+                slippage.unwrap_or(pool_config.slippage),
+                yes,
+                simulate,
+            )?;
+            info!(
+                "closed:{}, failed:{}",
+                summary.closed.len(),
+                summary.failed.len()
+            );
+            for (nft_mint, reason) in summary.failed.iter() {
+                info!("failed {}: {}", nft_mint, reason);
+            }
+            for (mint, amount) in summary.proceeds.iter() {
+                info!("proceeds mint:{}, amount:{}", mint, amount);
+            }
+        }
+        CommandsName::CollectFees { nft_mint } => {
+            let nft_token_info = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            )
+            .into_iter()
+            .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+            .ok_or(format_err!(
+                "position {} is not held by the payer",
+                nft_mint
+            ))?;
+            let position = deserialize_anchor_account::<
+                raydium_amm_v3::states::PersonalPositionState,
+            >(&rpc_client.get_account(&nft_token_info.position)?)?;
+            let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&position.pool_id)?,
+            )?;
+            let tick_array_lower_start_index =
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_lower_index,
+                    pool_state.tick_spacing,
+                );
+            let tick_array_upper_start_index =
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_upper_index,
+                    pool_state.tick_spacing,
+                );
+            let tick_array_key = |start_index: i32| {
+                Pubkey::find_program_address(
+                    &[
+                        raydium_amm_v3::states::TICK_ARRAY_SEED.as_bytes(),
+                        position.pool_id.to_bytes().as_ref(),
+                        &start_index.to_be_bytes(),
+                    ],
+                    &pool_config.raydium_v3_program,
+                )
+                .0
+            };
+            let mut tick_array_lower =
+                deserialize_anchor_account::<raydium_amm_v3::states::TickArrayState>(
+                    &rpc_client.get_account(&tick_array_key(tick_array_lower_start_index))?,
+                )?;
+            let mut tick_array_upper =
+                deserialize_anchor_account::<raydium_amm_v3::states::TickArrayState>(
+                    &rpc_client.get_account(&tick_array_key(tick_array_upper_start_index))?,
+                )?;
+            let tick_lower = *tick_array_lower
+                .get_tick_state_mut(position.tick_lower_index, pool_state.tick_spacing)?;
+            let tick_upper = *tick_array_upper
+                .get_tick_state_mut(position.tick_upper_index, pool_state.tick_spacing)?;
+            let (fees_0, fees_1) =
+                position_claimable_fees(&pool_state, &position, &tick_lower, &tick_upper);
+            info!(
+                "claimable fees, {}:{}, {}:{}",
+                pool_state.token_mint_0, fees_0, pool_state.token_mint_1, fees_1
+            );
+
+            // the token accounts receiving the fees and rewards are created if missing
+            let mut mints = vec![pool_state.token_mint_0, pool_state.token_mint_1];
+            for reward_info in pool_state.reward_infos.iter() {
+                if reward_info.initialized() && !mints.contains(&reward_info.token_mint) {
+                    mints.push(reward_info.token_mint);
+                }
+            }
+            let token_programs = mint_token_programs(&rpc_client, &mints)?;
+            let mut instructions: Vec<Instruction> = mints
+                .iter()
+                .map(|mint| {
+                    create_associated_token_account_idempotent(
+                        &payer.pubkey(),
+                        &payer.pubkey(),
+                        mint,
+                        &token_programs[mint],
+                    )
+                })
+                .collect();
+            let tickarray_bitmap_extension = Pubkey::find_program_address(
+                &[
+                    POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                    position.pool_id.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            )
+            .0;
+            let mut remaining_accounts = vec![AccountMeta::new(tickarray_bitmap_extension, false)];
+            remaining_accounts.extend(reward_remaining_accounts(
+                &pool_state,
+                &payer.pubkey(),
+                &token_programs,
+            )?);
+            instructions.extend(collect_fees_instr(
+                &pool_config,
+                position.pool_id,
+                pool_state.token_vault_0,
+                pool_state.token_vault_1,
+                pool_state.token_mint_0,
+                pool_state.token_mint_1,
+                nft_mint,
+                nft_token_info.key,
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &pool_state.token_mint_0,
+                    &token_programs[&pool_state.token_mint_0],
+                ),
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &pool_state.token_mint_1,
+                    &token_programs[&pool_state.token_mint_1],
+                ),
+                remaining_accounts,
+                position.tick_lower_index,
+                position.tick_upper_index,
+                tick_array_lower_start_index,
+                tick_array_upper_start_index,
+            )?);
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::CompoundFees { nft_mint } => {
+            let nft_token_info = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            )
+            .into_iter()
+            .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+            .ok_or(format_err!(
+                "position {} is not held by the payer",
+                nft_mint
+            ))?;
+            let position = deserialize_anchor_account::<
+                raydium_amm_v3::states::PersonalPositionState,
+            >(&rpc_client.get_account(&nft_token_info.position)?)?;
+            let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&position.pool_id)?,
+            )?;
+            let tick_array_lower_start_index =
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_lower_index,
+                    pool_state.tick_spacing,
+                );
+            let tick_array_upper_start_index =
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_upper_index,
+                    pool_state.tick_spacing,
+                );
+            let tick_array_key = |start_index: i32| {
+                Pubkey::find_program_address(
+                    &[
+                        raydium_amm_v3::states::TICK_ARRAY_SEED.as_bytes(),
+                        position.pool_id.to_bytes().as_ref(),
+                        &start_index.to_be_bytes(),
+                    ],
+                    &pool_config.raydium_v3_program,
+                )
+                .0
+            };
+            let mut tick_array_lower =
+                deserialize_anchor_account::<raydium_amm_v3::states::TickArrayState>(
+                    &rpc_client.get_account(&tick_array_key(tick_array_lower_start_index))?,
+                )?;
+            let mut tick_array_upper =
+                deserialize_anchor_account::<raydium_amm_v3::states::TickArrayState>(
+                    &rpc_client.get_account(&tick_array_key(tick_array_upper_start_index))?,
+                )?;
+            let tick_lower = *tick_array_lower
+                .get_tick_state_mut(position.tick_lower_index, pool_state.tick_spacing)?;
+            let tick_upper = *tick_array_upper
+                .get_tick_state_mut(position.tick_upper_index, pool_state.tick_spacing)?;
+            let (fees_0, fees_1) =
+                position_claimable_fees(&pool_state, &position, &tick_lower, &tick_upper);
+            // same calculation as the program, the slippage is taken on the liquidity
+            let liquidity = raydium_amm_v3::instructions::compound_liquidity(
+                pool_state.sqrt_price_x64,
+                position.tick_lower_index,
+                position.tick_upper_index,
+                fees_0,
+                fees_1,
+            )
+            .map_err(|_| {
+                format_err!(
+                    "the fees owed to {}, {} and {}, are worth no liquidity",
+                    nft_mint,
+                    fees_0,
+                    fees_1
+                )
+            })?;
+            let liquidity_min = (liquidity as f64 * (1.0 - pool_config.slippage)) as u128;
+            info!(
+                "claimable fees, {}:{}, {}:{}, liquidity:{}",
+                pool_state.token_mint_0, fees_0, pool_state.token_mint_1, fees_1, liquidity
+            );
+
+            let tickarray_bitmap_extension = Pubkey::find_program_address(
+                &[
+                    POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                    position.pool_id.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            )
+            .0;
+            let instructions = compound_fees_instr(
+                &pool_config,
+                position.pool_id,
+                nft_mint,
+                nft_token_info.key,
+                vec![AccountMeta::new(tickarray_bitmap_extension, false)],
+                position.tick_lower_index,
+                position.tick_upper_index,
+                tick_array_lower_start_index,
+                tick_array_upper_start_index,
+                liquidity_min,
+            )?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::CollectReward { nft_mint } => {
+            let nft_token_info = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            )
+            .into_iter()
+            .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+            .ok_or(format_err!(
+                "position {} is not held by the payer",
+                nft_mint
+            ))?;
+            let position = deserialize_anchor_account::<
+                raydium_amm_v3::states::PersonalPositionState,
+            >(&rpc_client.get_account(&nft_token_info.position)?)?;
+            let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&position.pool_id)?,
+            )?;
+            let mints: Vec<Pubkey> = pool_state
+                .reward_infos
+                .iter()
+                .filter(|reward_info| reward_info.initialized())
+                .map(|reward_info| reward_info.token_mint)
+                .collect();
+            if mints.is_empty() {
+                info!("pool {} has no reward", position.pool_id);
+                return Ok(());
+            }
+
+            // the token accounts receiving the rewards are created if missing
+            let token_programs = mint_token_programs(&rpc_client, &mints)?;
+            let mut instructions: Vec<Instruction> = mints
+                .iter()
+                .map(|mint| {
+                    create_associated_token_account_idempotent(
+                        &payer.pubkey(),
+                        &payer.pubkey(),
+                        mint,
+                        &token_programs[mint],
+                    )
+                })
+                .collect();
+            instructions.extend(collect_reward_instr(
+                &pool_config,
+                position.pool_id,
+                nft_mint,
+                nft_token_info.key,
+                reward_remaining_accounts(&pool_state, &payer.pubkey(), &token_programs)?,
+                position.tick_lower_index,
+                position.tick_upper_index,
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_lower_index,
+                    pool_state.tick_spacing,
+                ),
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_upper_index,
+                    pool_state.tick_spacing,
+                ),
+            )?);
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::MigrateFromV2 {
+            amm_id,
+            pool_id,
+            lp_amount,
+            range_percent,
+            slippage,
+            yes,
+        } => {
+            let pool_id = pool_id.unwrap_or(pool_config.pool_id_account.unwrap());
+            if let Some(nft_mint) = migrate_from_v2(
+                &rpc_client,
+                &pool_config,
+                &payer,
+                &amm_id,
+                &pool_id,
+                lp_amount,
+                range_percent,
+                slippage.unwrap_or(pool_config.slippage),
+                yes,
+            )? {
+                info!("migrated to position {}", nft_mint);
+            }
+        }
+        CommandsName::MigratePosition {
+            source_nft_mint,
+            target_nft_mint,
+            liquidity,
+        } => {
+            let nft_infos = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            );
+            let find_nft = |nft_mint: Pubkey| {
+                nft_infos
+                    .iter()
+                    .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+                    .ok_or(format_err!(
+                        "position {} is not held by the payer",
+                        nft_mint
+                    ))
+            };
+            let source_nft = find_nft(source_nft_mint)?;
+            let target_nft = find_nft(target_nft_mint)?;
+            let source_position = deserialize_anchor_account::<
+                raydium_amm_v3::states::PersonalPositionState,
+            >(&rpc_client.get_account(&source_nft.position)?)?;
+            let target_position = deserialize_anchor_account::<
+                raydium_amm_v3::states::PersonalPositionState,
+            >(&rpc_client.get_account(&target_nft.position)?)?;
+            let source_pool = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&source_position.pool_id)?,
+            )?;
+            let target_pool = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&target_position.pool_id)?,
+            )?;
+            let liquidity = liquidity.unwrap_or(source_position.liquidity);
+
+            // the withdrawal and the deposit both pay the transfer fees
+            let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+                source_pool.tick_current,
+                source_pool.sqrt_price_x64,
+                source_position.tick_lower_index,
+                source_position.tick_upper_index,
+                -(liquidity as i128),
+            )?;
+            let withdraw_fee = get_pool_mints_transfer_fee(
+                &rpc_client,
+                source_pool.token_mint_0,
+                source_pool.token_mint_1,
+                amount_0,
+                amount_1,
+            );
+            let received_0 = amount_0 - withdraw_fee.0.transfer_fee;
+            let received_1 = amount_1 - withdraw_fee.1.transfer_fee;
+            let deposit_fee = get_pool_mints_transfer_fee(
+                &rpc_client,
+                source_pool.token_mint_0,
+                source_pool.token_mint_1,
+                received_0,
+                received_1,
+            );
+            let target_liquidity =
+                raydium_amm_v3::libraries::liquidity_math::get_liquidity_from_amounts_at_ticks(
+                    target_pool.sqrt_price_x64,
+                    target_position.tick_lower_index,
+                    target_position.tick_upper_index,
+                    received_0 - deposit_fee.0.transfer_fee,
+                    received_1 - deposit_fee.1.transfer_fee,
+                )?;
+            info!(
+                "withdraw amount_0:{}, amount_1:{}, target liquidity:{}",
+                amount_0, amount_1, target_liquidity
+            );
+
+            // the token accounts receiving the fees and rewards are created if missing
+            let mut mints = vec![source_pool.token_mint_0, source_pool.token_mint_1];
+            for reward_info in source_pool.reward_infos.iter() {
+                if reward_info.initialized() && !mints.contains(&reward_info.token_mint) {
+                    mints.push(reward_info.token_mint);
+                }
+            }
+            let token_programs = mint_token_programs(&rpc_client, &mints)?;
+            let mut instructions: Vec<Instruction> = mints
+                .iter()
+                .map(|mint| {
+                    create_associated_token_account_idempotent(
+                        &payer.pubkey(),
+                        &payer.pubkey(),
+                        mint,
+                        &token_programs[mint],
+                    )
+                })
+                .collect();
+            let tickarray_bitmap_extension = |pool_id: &Pubkey| {
+                Pubkey::find_program_address(
+                    &[
+                        POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                        pool_id.to_bytes().as_ref(),
+                    ],
+                    &pool_config.raydium_v3_program,
+                )
+                .0
+            };
+            let mut remaining_accounts = Vec::new();
+            if target_pool.is_overflow_default_tickarray_bitmap(vec![
+                target_position.tick_lower_index,
+                target_position.tick_upper_index,
+            ]) {
+                remaining_accounts.push(AccountMeta::new(
+                    tickarray_bitmap_extension(&target_position.pool_id),
+                    false,
+                ));
+            }
+            remaining_accounts.push(AccountMeta::new(
+                tickarray_bitmap_extension(&source_position.pool_id),
+                false,
+            ));
+            remaining_accounts.extend(reward_remaining_accounts(
+                &source_pool,
+                &payer.pubkey(),
+                &token_programs,
+            )?);
+            instructions.extend(migrate_position_instr(
+                &pool_config,
+                &MigrationSide {
+                    pool_account_key: source_position.pool_id,
+                    pool_state: &source_pool,
+                    nft_mint_key: source_nft_mint,
+                    nft_token_key: source_nft.key,
+                    tick_lower_index: source_position.tick_lower_index,
+                    tick_upper_index: source_position.tick_upper_index,
+                },
+                &MigrationSide {
+                    pool_account_key: target_position.pool_id,
+                    pool_state: &target_pool,
+                    nft_mint_key: target_nft_mint,
+                    nft_token_key: target_nft.key,
+                    tick_lower_index: target_position.tick_lower_index,
+                    tick_upper_index: target_position.tick_upper_index,
+                },
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &source_pool.token_mint_0,
+                    &token_programs[&source_pool.token_mint_0],
+                ),
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &source_pool.token_mint_1,
+                    &token_programs[&source_pool.token_mint_1],
+                ),
+                remaining_accounts,
+                liquidity,
+                amount_with_slippage(received_0, pool_config.slippage, false),
+                amount_with_slippage(received_1, pool_config.slippage, false),
+                (target_liquidity as f64 * (1.0 - pool_config.slippage)) as u128,
+            )?);
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::SetFeeRecipient {
+            nft_mint,
+            fee_recipient,
+        } => {
+            let nft_token_info = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            )
+            .into_iter()
+            .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+            .ok_or(format_err!(
+                "position {} is not held by the payer",
+                nft_mint
+            ))?;
+            let instructions =
+                set_fee_recipient_instr(&pool_config, nft_mint, nft_token_info.key, fee_recipient)?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::TransferPosition {
+            nft_mint,
+            new_owner,
+        } => {
+            let nft_token_info = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            )
+            .into_iter()
+            .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+            .ok_or(format_err!(
+                "position {} is not held by the payer",
+                nft_mint
+            ))?;
+            let instructions = transfer_position_instr(
+                &pool_config,
+                nft_mint,
+                nft_token_info.key,
+                nft_token_info.program,
+                new_owner,
+            )?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::LockPosition {
+            nft_mint,
+            unlock_time,
+        } => {
+            let nft_token_info = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            )
+            .into_iter()
+            .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+            .ok_or(format_err!(
+                "position {} is not held by the payer",
+                nft_mint
+            ))?;
+            let instructions = lock_position_instr(
+                &pool_config,
+                nft_mint,
+                nft_token_info.key,
+                nft_token_info.program,
+                unlock_time,
+            )?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::UnlockPosition { nft_mint } => {
+            let nft_token_program = rpc_client.get_account(&nft_mint)?.owner;
+            let nft_token_key = get_associated_token_address_with_program_id(
+                &payer.pubkey(),
+                &nft_mint,
+                &nft_token_program,
+            );
+            // the nft is returned to the associated token account of the payer
+            let mut instructions = vec![create_associated_token_account_idempotent(
+                &payer.pubkey(),
+                &payer.pubkey(),
+                &nft_mint,
+                &nft_token_program,
+            )];
+            instructions.extend(unlock_position_instr(
+                &pool_config,
+                nft_mint,
+                nft_token_key,
+                nft_token_program,
+            )?);
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::CollectLockedPosition { nft_mint } => {
+            let (personal_position_key, __bump) = Pubkey::find_program_address(
+                &[
+                    raydium_amm_v3::states::POSITION_SEED.as_bytes(),
+                    nft_mint.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            );
+            let position = deserialize_anchor_account::<
+                raydium_amm_v3::states::PersonalPositionState,
+            >(&rpc_client.get_account(&personal_position_key)?)?;
+            let pool_state = deserialize_anchor_account::<raydium_amm_v3::states::PoolState>(
+                &rpc_client.get_account(&position.pool_id)?,
+            )?;
+
+            // the token accounts receiving the fees and rewards are created if missing
+            let mut mints = vec![pool_state.token_mint_0, pool_state.token_mint_1];
+            for reward_info in pool_state.reward_infos.iter() {
+                if reward_info.initialized() && !mints.contains(&reward_info.token_mint) {
+                    mints.push(reward_info.token_mint);
+                }
+            }
+            let token_programs = mint_token_programs(&rpc_client, &mints)?;
+            let mut instructions: Vec<Instruction> = mints
+                .iter()
+                .map(|mint| {
+                    create_associated_token_account_idempotent(
+                        &payer.pubkey(),
+                        &payer.pubkey(),
+                        mint,
+                        &token_programs[mint],
+                    )
+                })
+                .collect();
+            let tickarray_bitmap_extension = Pubkey::find_program_address(
+                &[
+                    POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                    position.pool_id.to_bytes().as_ref(),
+                ],
+                &pool_config.raydium_v3_program,
+            )
+            .0;
+            let mut remaining_accounts = vec![AccountMeta::new(tickarray_bitmap_extension, false)];
+            remaining_accounts.extend(reward_remaining_accounts(
+                &pool_state,
+                &payer.pubkey(),
+                &token_programs,
+            )?);
+            instructions.extend(collect_locked_position_instr(
+                &pool_config,
+                position.pool_id,
+                pool_state.token_vault_0,
+                pool_state.token_vault_1,
+                pool_state.token_mint_0,
+                pool_state.token_mint_1,
+                nft_mint,
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &pool_state.token_mint_0,
+                    &token_programs[&pool_state.token_mint_0],
+                ),
+                get_associated_token_address_with_program_id(
+                    &payer.pubkey(),
+                    &pool_state.token_mint_1,
+                    &token_programs[&pool_state.token_mint_1],
+                ),
+                remaining_accounts,
+                position.tick_lower_index,
+                position.tick_upper_index,
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_lower_index,
+                    pool_state.tick_spacing,
+                ),
+                raydium_amm_v3::states::TickArrayState::get_array_start_index(
+                    position.tick_upper_index,
+                    pool_state.tick_spacing,
+                ),
+            )?);
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::UpdatePositionMetadata { nft_mint } => {
+            let nft_token_info = get_all_nft_and_position_by_owner(
+                &rpc_client,
+                &payer.pubkey(),
+                &pool_config.raydium_v3_program,
+            )
+            .into_iter()
+            .find(|nft_info| nft_info.mint == nft_mint && nft_info.amount == 1)
+            .ok_or(format_err!(
+                "position {} is not held by the payer",
+                nft_mint
+            ))?;
+            let personal_position =
+                deserialize_anchor_account::<raydium_amm_v3::states::PersonalPositionState>(
+                    &rpc_client.get_account(&nft_token_info.position)?,
+                )?;
+            let instructions = update_position_metadata_instr(
+                &pool_config,
+                nft_mint,
+                nft_token_info.key,
+                personal_position.pool_id,
+            )?;
+            let signers = vec![&payer];
+            let recent_hash = rpc_client.get_latest_blockhash()?;
+            let txn = Transaction::new_signed_with_payer(
+                &with_compute_budget(
+                    &rpc_client,
+                    &pool_config.compute_budget,
+                    &payer.pubkey(),
+                    &instructions,
+                )?,
+                Some(&payer.pubkey()),
+                &signers,
+                recent_hash,
+            );
+            send_or_simulate_txn(&rpc_client, &txn, simulate)?;
+        }
+        CommandsName::Faucet => {
+            run_faucet(&rpc_client, &pool_config, &payer)?;
+            info!(
+                "payer {} holds {} sol",
+                payer.pubkey(),
+                lamports_to_sol(rpc_client.get_balance(&payer.pubkey())?)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod main_test {
+    use super::*;
+
+    fn profile_config() -> Ini {
+        let mut config = Ini::new();
+        config
+            .read(
+                "[Global]\nhttp_url = http://localhost:8899\n\
+                 [Pool]\nmint0 = mint_a\n\
+                 [profile.devnet]\nhttp_url = https://api.devnet.solana.com\nmint0 = mint_b\n\
+                 [profile.typo]\nmint_0 = mint_c\n"
+                    .to_string(),
+            )
+            .unwrap();
+        config
+    }
+
+    #[test]
+    fn apply_profile_test() {
+        let mut config = profile_config();
+        apply_profile(&mut config, "devnet").unwrap();
+        assert_eq!(
+            config.get("Global", "http_url").unwrap(),
+            "https://api.devnet.solana.com"
+        );
+        assert_eq!(config.get("Pool", "mint0").unwrap(), "mint_b");
+
+        let mut config = profile_config();
+        let err = apply_profile(&mut config, "typo").unwrap_err();
+        assert_eq!(err.to_string(), "unknown key mint_0 in profile typo");
+        assert_eq!(config.get("Global", "mint_0"), None);
+
+        let mut config = profile_config();
+        assert!(apply_profile(&mut config, "mainnet").is_err());
+    }
 }
@@ -0,0 +1,375 @@
+//! Example of a program integrating the concentrated liquidity AMM by CPI.
+//!
+//! The account structs list the accounts in the order expected by the AMM instructions, the
+//! remaining accounts of the consumer instructions are forwarded unchanged.
+//!
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{self, Mint, Token2022, TokenAccount};
+use raydium_amm_v3::cpi;
+use raydium_amm_v3::program::AmmV3;
+use raydium_amm_v3::states::{AmmConfig, ObservationState, PoolState};
+
+declare_id!("6WPHudCEh3r9XFAoofSURXiszmkTw9bxvfrnDGsJhEof");
+
+#[program]
+pub mod cpi_consumer {
+    use super::*;
+
+    /// Swap through the AMM `swap_v2` instruction
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, the remaining accounts are the tickarray_bitmap_extension
+    /// if needed and the tick arrays in the swap direction
+    /// * `amount` - Arranged in pairs with other_amount_threshold. (amount_in, amount_out_minimum) or (amount_out, amount_in_maximum)
+    /// * `other_amount_threshold` - For slippage check
+    /// * `sqrt_price_limit_x64` - The Q64.64 sqrt price √P limit
+    /// * `is_base_input` - swap base input or swap base output
+    ///
+    pub fn proxy_swap<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ProxySwap<'info>>,
+        amount: u64,
+        other_amount_threshold: u64,
+        sqrt_price_limit_x64: u128,
+        is_base_input: bool,
+    ) -> Result<()> {
+        swap_v2(
+            ctx.accounts,
+            ctx.remaining_accounts,
+            amount,
+            other_amount_threshold,
+            sqrt_price_limit_x64,
+            is_base_input,
+        )
+    }
+
+    /// Open a position through the AMM `open_position_with_token22_nft` instruction, the
+    /// position NFT is a Token-2022 mint without metadata
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, the remaining account is the tickarray_bitmap_extension
+    /// if a tick array is out of the default bitmap
+    /// * `tick_lower_index` - The low boundary of market
+    /// * `tick_upper_index` - The upper boundary of market
+    /// * `tick_array_lower_start_index` - The start index of tick array which include tick low
+    /// * `tick_array_upper_start_index` - The start index of tick array which include tick upper
+    /// * `liquidity` - The liquidity to be added, if zero, calculate liquidity base amount_0 or amount_1 according base_flag
+    /// * `amount_0_max` - The max amount of token_0 to spend, which serves as a slippage check
+    /// * `amount_1_max` - The max amount of token_1 to spend, which serves as a slippage check
+    /// * `base_flag` - if the liquidity specified as zero, true: calculate liquidity base amount_0_max otherwise base amount_1_max
+    ///
+    pub fn proxy_open_position<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ProxyOpenPosition<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        base_flag: Option<bool>,
+    ) -> Result<()> {
+        let cpi_accounts = cpi::accounts::OpenPositionWithToken22Nft {
+            payer: ctx.accounts.payer.to_account_info(),
+            position_nft_owner: ctx.accounts.position_nft_owner.to_account_info(),
+            position_nft_mint: ctx.accounts.position_nft_mint.to_account_info(),
+            position_nft_account: ctx.accounts.position_nft_account.to_account_info(),
+            pool_state: ctx.accounts.pool_state.to_account_info(),
+            protocol_position: ctx.accounts.protocol_position.to_account_info(),
+            tick_array_lower: ctx.accounts.tick_array_lower.to_account_info(),
+            tick_array_upper: ctx.accounts.tick_array_upper.to_account_info(),
+            personal_position: ctx.accounts.personal_position.to_account_info(),
+            token_account_0: ctx.accounts.token_account_0.to_account_info(),
+            token_account_1: ctx.accounts.token_account_1.to_account_info(),
+            token_vault_0: ctx.accounts.token_vault_0.to_account_info(),
+            token_vault_1: ctx.accounts.token_vault_1.to_account_info(),
+            rent: ctx.accounts.rent.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program.to_account_info(),
+            associated_token_program: ctx.accounts.associated_token_program.to_account_info(),
+            token_program_2022: ctx.accounts.token_program_2022.to_account_info(),
+            vault_0_mint: ctx.accounts.vault_0_mint.to_account_info(),
+            vault_1_mint: ctx.accounts.vault_1_mint.to_account_info(),
+        };
+        let cpi_context =
+            CpiContext::new(ctx.accounts.clmm_program.to_account_info(), cpi_accounts)
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec());
+        cpi::open_position_with_token22_nft(
+            cpi_context,
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            false,
+            base_flag,
+        )
+    }
+
+    /// Called back by the AMM `flash` instruction: swap the borrowed token_0 through `swap_v2`,
+    /// then repay the loan and its fees to the lending vaults out of the payer token accounts.
+    /// The lending pool must hold the tokens of the swap pool, a swap through the lending pool
+    /// itself is a nested call and is rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, the remaining accounts are the tick arrays of the swap
+    /// * `fee_0` - The fee owed on top of the borrowed token_0
+    /// * `fee_1` - The fee owed on top of the borrowed token_1
+    /// * `data` - The borsh encoded `FlashSwapData`
+    ///
+    pub fn flash_callback<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, FlashSwapCallback<'info>>,
+        fee_0: u64,
+        fee_1: u64,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let data = FlashSwapData::try_from_slice(&data)?;
+        let swap = &ctx.accounts.swap;
+        swap_v2(
+            swap,
+            ctx.remaining_accounts,
+            data.amount_in,
+            data.amount_out_minimum,
+            0,
+            true,
+        )?;
+
+        for (token_account, lending_vault, mint, amount) in [
+            (
+                &swap.input_token_account,
+                &ctx.accounts.lending_vault_0,
+                &swap.input_vault_mint,
+                data.amount_0.checked_add(fee_0).unwrap(),
+            ),
+            (
+                &swap.output_token_account,
+                &ctx.accounts.lending_vault_1,
+                &swap.output_vault_mint,
+                data.amount_1.checked_add(fee_1).unwrap(),
+            ),
+        ] {
+            token_interface::transfer_checked(
+                CpiContext::new(
+                    swap.token_program.to_account_info(),
+                    token_interface::TransferChecked {
+                        from: token_account.to_account_info(),
+                        mint: mint.to_account_info(),
+                        to: lending_vault.to_account_info(),
+                        authority: swap.payer.to_account_info(),
+                    },
+                ),
+                amount,
+                mint.decimals,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Call the AMM `swap_v2` instruction with the accounts of `ProxySwap`
+fn swap_v2<'info>(
+    accounts: &ProxySwap<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+    amount: u64,
+    other_amount_threshold: u64,
+    sqrt_price_limit_x64: u128,
+    is_base_input: bool,
+) -> Result<()> {
+    let cpi_accounts = cpi::accounts::SwapSingleV2 {
+        payer: accounts.payer.to_account_info(),
+        amm_config: accounts.amm_config.to_account_info(),
+        pool_state: accounts.pool_state.to_account_info(),
+        input_token_account: accounts.input_token_account.to_account_info(),
+        output_token_account: accounts.output_token_account.to_account_info(),
+        input_vault: accounts.input_vault.to_account_info(),
+        output_vault: accounts.output_vault.to_account_info(),
+        observation_state: accounts.observation_state.to_account_info(),
+        token_program: accounts.token_program.to_account_info(),
+        token_program_2022: accounts.token_program_2022.to_account_info(),
+        memo_program: accounts.memo_program.to_account_info(),
+        input_vault_mint: accounts.input_vault_mint.to_account_info(),
+        output_vault_mint: accounts.output_vault_mint.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(accounts.clmm_program.to_account_info(), cpi_accounts)
+        .with_remaining_accounts(remaining_accounts.to_vec());
+    cpi::swap_v2(
+        cpi_context,
+        amount,
+        other_amount_threshold,
+        sqrt_price_limit_x64,
+        is_base_input,
+    )
+}
+
+/// The data the borrower passes through the AMM `flash` instruction to `flash_callback`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlashSwapData {
+    /// The borrowed amounts, repaid with their fees
+    pub amount_0: u64,
+    pub amount_1: u64,
+    /// The amount of token_0 swapped for token_1
+    pub amount_in: u64,
+    /// For slippage check of the swap
+    pub amount_out_minimum: u64,
+}
+
+#[derive(Accounts)]
+pub struct ProxySwap<'info> {
+    pub clmm_program: Program<'info, AmmV3>,
+
+    /// The user performing the swap
+    pub payer: Signer<'info>,
+
+    /// The factory state to read protocol fees
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// The program account of the pool in which the swap will be performed
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The user token account for input token
+    #[account(mut)]
+    pub input_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The user token account for output token
+    #[account(mut)]
+    pub output_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault token account for input token
+    #[account(mut)]
+    pub input_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The vault token account for output token
+    #[account(mut)]
+    pub output_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The program account for the most recent oracle observation
+    #[account(mut, address = pool_state.load()?.observation_key)]
+    pub observation_state: AccountLoader<'info, ObservationState>,
+
+    /// SPL program for token transfers
+    pub token_program: Program<'info, Token>,
+
+    /// SPL program 2022 for token transfers
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// CHECK: checked by the clmm program
+    pub memo_program: UncheckedAccount<'info>,
+
+    /// The mint of the input vault
+    #[account(address = input_vault.mint)]
+    pub input_vault_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of the output vault
+    #[account(address = output_vault.mint)]
+    pub output_vault_mint: Box<InterfaceAccount<'info, Mint>>,
+    // remaining accounts
+    // tickarray_bitmap_extension: must add account if need regardless the sequence
+    // tick_array_account_1
+    // tick_array_account_2
+    // tick_array_account_...
+}
+
+#[derive(Accounts)]
+pub struct ProxyOpenPosition<'info> {
+    pub clmm_program: Program<'info, AmmV3>,
+
+    /// Pays to mint the position
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Receives the position NFT
+    pub position_nft_owner: UncheckedAccount<'info>,
+
+    /// Unique token mint address, initialized by the clmm program
+    #[account(mut)]
+    pub position_nft_mint: Signer<'info>,
+
+    /// CHECK: ATA address where position NFT will be minted, initialized by the clmm program
+    #[account(mut)]
+    pub position_nft_account: UncheckedAccount<'info>,
+
+    /// Add liquidity for this pool
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// CHECK: Store the information of market marking in range, initialized by the clmm program
+    #[account(mut)]
+    pub protocol_position: UncheckedAccount<'info>,
+
+    /// CHECK: Account to store data for the position's lower tick
+    #[account(mut)]
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Account to store data for the position's upper tick
+    #[account(mut)]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    /// CHECK: personal position state, initialized by the clmm program
+    #[account(mut)]
+    pub personal_position: UncheckedAccount<'info>,
+
+    /// The token_0 account deposit token to the pool
+    #[account(mut)]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token_1 account deposit token to the pool
+    #[account(mut)]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(mut)]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(mut)]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Sysvar for token mint and ATA creation
+    pub rent: Sysvar<'info, Rent>,
+
+    /// Program to create the position manager state account
+    pub system_program: Program<'info, System>,
+
+    /// Program to transfer for token account
+    pub token_program: Program<'info, Token>,
+
+    /// Program to create an ATA for receiving position NFT
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// Program to create NFT mint/token account and transfer for token22 account
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// The mint of token vault 0
+    #[account(address = token_vault_0.mint)]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token vault 1
+    #[account(address = token_vault_1.mint)]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+    // remaining account
+    // tickarray_bitmap_extension: must add account if a tick array is out of the default bitmap
+}
+
+#[derive(Accounts)]
+pub struct FlashSwapCallback<'info> {
+    /// The accounts of the swap, the payer signs the flash instruction and repays the loan
+    pub swap: ProxySwap<'info>,
+
+    /// The token_0 vault of the lending pool, repaid out of the swap input token account
+    #[account(mut, constraint = lending_vault_0.mint == swap.input_vault_mint.key())]
+    pub lending_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token_1 vault of the lending pool, repaid out of the swap output token account
+    #[account(mut, constraint = lending_vault_1.mint == swap.output_vault_mint.key())]
+    pub lending_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+    // remaining accounts
+    // the tick arrays of the swap, as for `ProxySwap`
+}
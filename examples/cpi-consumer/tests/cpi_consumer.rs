@@ -0,0 +1,879 @@
+use anchor_lang::__private::bytemuck;
+use anchor_lang::{
+    system_program, AccountDeserialize, AccountSerialize, AnchorSerialize, InstructionData,
+    ToAccountMetas,
+};
+use raydium_amm_v3::error::ErrorCode;
+use raydium_amm_v3::states::{
+    AmmConfig, PersonalPositionState, PoolState, PoolStatusBitFlag, PoolStatusBitIndex,
+    TickArrayState, AMM_CONFIG_SEED, LOCKED_POSITION_SEED, OBSERVATION_SEED, POOL_SEED,
+    POOL_TICK_ARRAY_BITMAP_SEED, POOL_VAULT_SEED, POSITION_SEED, TICK_ARRAY_SEED,
+};
+use raydium_amm_v3::test_fixtures::build_amm_config;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::{
+    account::Account,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction, InstructionError},
+    program_pack::Pack,
+    pubkey::Pubkey,
+    signature::{Keypair, Signer},
+    system_instruction, sysvar,
+    transaction::{Transaction, TransactionError},
+};
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+
+const TICK_SPACING: u16 = 10;
+const TICK_LOWER_INDEX: i32 = -100;
+const TICK_UPPER_INDEX: i32 = 100;
+const USER_BALANCE: u64 = 10_000_000;
+
+/// Anchor entrypoints require the accounts to live as long as their data
+fn clmm_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    raydium_amm_v3::entry(program_id, accounts, data)
+}
+
+fn cpi_consumer_entry(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    cpi_consumer::entry(program_id, accounts, data)
+}
+
+struct TestPool {
+    amm_config: Pubkey,
+    pool_state: Pubkey,
+    token_mint_0: Pubkey,
+    token_mint_1: Pubkey,
+    token_vault_0: Pubkey,
+    token_vault_1: Pubkey,
+    observation_state: Pubkey,
+    user_token_account_0: Pubkey,
+    user_token_account_1: Pubkey,
+}
+
+fn clmm_pda(seeds: &[&[u8]]) -> Pubkey {
+    Pubkey::find_program_address(seeds, &raydium_amm_v3::id()).0
+}
+
+fn tick_array_key(pool_state: &Pubkey, start_index: i32) -> Pubkey {
+    clmm_pda(&[
+        TICK_ARRAY_SEED.as_bytes(),
+        pool_state.as_ref(),
+        &start_index.to_be_bytes(),
+    ])
+}
+
+async fn process(
+    context: &mut ProgramTestContext,
+    instructions: &[Instruction],
+    signers: &[&Keypair],
+) -> Result<(), BanksClientError> {
+    let mut all_signers = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+    let transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        context.last_blockhash,
+    );
+    context.banks_client.process_transaction(transaction).await
+}
+
+async fn token_balance(context: &mut ProgramTestContext, token_account: &Pubkey) -> u64 {
+    let account = context
+        .banks_client
+        .get_account(*token_account)
+        .await
+        .unwrap()
+        .unwrap();
+    spl_token::state::Account::unpack(&account.data)
+        .unwrap()
+        .amount
+}
+
+async fn create_mint(context: &mut ProgramTestContext, mint: &Keypair) {
+    let rent = context.banks_client.get_rent().await.unwrap();
+    let payer = context.payer.pubkey();
+    let instructions = [
+        system_instruction::create_account(
+            &payer,
+            &mint.pubkey(),
+            rent.minimum_balance(spl_token::state::Mint::LEN),
+            spl_token::state::Mint::LEN as u64,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::initialize_mint(&spl_token::id(), &mint.pubkey(), &payer, None, 6)
+            .unwrap(),
+    ];
+    process(context, &instructions, &[mint]).await.unwrap();
+}
+
+async fn create_funded_token_account(context: &mut ProgramTestContext, mint: &Pubkey) -> Pubkey {
+    let payer = context.payer.pubkey();
+    let token_account =
+        get_associated_token_address_with_program_id(&payer, mint, &spl_token::id());
+    let instructions = [
+        spl_associated_token_account::instruction::create_associated_token_account(
+            &payer,
+            &payer,
+            mint,
+            &spl_token::id(),
+        ),
+        spl_token::instruction::mint_to(
+            &spl_token::id(),
+            mint,
+            &token_account,
+            &payer,
+            &[],
+            USER_BALANCE,
+        )
+        .unwrap(),
+    ];
+    process(context, &instructions, &[]).await.unwrap();
+    token_account
+}
+
+/// Start the programs with an amm config and create a pool at tick 0
+async fn setup() -> (ProgramTestContext, TestPool) {
+    let mut program_test = ProgramTest::new(
+        "cpi_consumer",
+        cpi_consumer::id(),
+        processor!(cpi_consumer_entry),
+    );
+    program_test.add_program(
+        "raydium_amm_v3",
+        raydium_amm_v3::id(),
+        processor!(clmm_entry),
+    );
+
+    // the amm config is created by the admin, it is injected instead
+    let amm_config = clmm_pda(&[AMM_CONFIG_SEED.as_bytes(), &0u16.to_be_bytes()]);
+    let mut amm_config_data = Vec::new();
+    build_amm_config(0, TICK_SPACING, 2500)
+        .try_serialize(&mut amm_config_data)
+        .unwrap();
+    amm_config_data.resize(AmmConfig::LEN, 0);
+    program_test.add_account(
+        amm_config,
+        Account {
+            lamports: 1_000_000_000,
+            data: amm_config_data,
+            owner: raydium_amm_v3::id(),
+            executable: false,
+            rent_epoch: 0,
+        },
+    );
+    let mut context = program_test.start_with_context().await;
+
+    let mut mints = [Keypair::new(), Keypair::new()];
+    mints.sort_by_key(|mint| mint.pubkey());
+    for mint in mints.iter() {
+        create_mint(&mut context, mint).await;
+    }
+    let token_mint_0 = mints[0].pubkey();
+    let token_mint_1 = mints[1].pubkey();
+    let user_token_account_0 = create_funded_token_account(&mut context, &token_mint_0).await;
+    let user_token_account_1 = create_funded_token_account(&mut context, &token_mint_1).await;
+
+    let pool_state = clmm_pda(&[
+        POOL_SEED.as_bytes(),
+        amm_config.as_ref(),
+        token_mint_0.as_ref(),
+        token_mint_1.as_ref(),
+    ]);
+    let pool = TestPool {
+        amm_config,
+        pool_state,
+        token_mint_0,
+        token_mint_1,
+        token_vault_0: clmm_pda(&[
+            POOL_VAULT_SEED.as_bytes(),
+            pool_state.as_ref(),
+            token_mint_0.as_ref(),
+        ]),
+        token_vault_1: clmm_pda(&[
+            POOL_VAULT_SEED.as_bytes(),
+            pool_state.as_ref(),
+            token_mint_1.as_ref(),
+        ]),
+        observation_state: clmm_pda(&[OBSERVATION_SEED.as_bytes(), pool_state.as_ref()]),
+        user_token_account_0,
+        user_token_account_1,
+    };
+
+    let create_pool = Instruction {
+        program_id: raydium_amm_v3::id(),
+        accounts: raydium_amm_v3::accounts::CreatePool {
+            pool_creator: context.payer.pubkey(),
+            amm_config,
+            pool_state,
+            token_mint_0,
+            token_mint_1,
+            token_vault_0: pool.token_vault_0,
+            token_vault_1: pool.token_vault_1,
+            observation_state: pool.observation_state,
+            tick_array_bitmap: clmm_pda(&[
+                POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+                pool_state.as_ref(),
+            ]),
+            token_program_0: spl_token::id(),
+            token_program_1: spl_token::id(),
+            system_program: system_program::ID,
+            rent: sysvar::rent::id(),
+        }
+        .to_account_metas(None),
+        data: raydium_amm_v3::instruction::CreatePool {
+            sqrt_price_x64: 1u128 << 64,
+            open_time: 0,
+        }
+        .data(),
+    };
+    process(&mut context, &[create_pool], &[]).await.unwrap();
+
+    (context, pool)
+}
+
+async fn proxy_open_position(
+    context: &mut ProgramTestContext,
+    pool: &TestPool,
+    liquidity: u128,
+) -> Result<Pubkey, BanksClientError> {
+    let payer = context.payer.pubkey();
+    let position_nft_mint = Keypair::new();
+    let tick_array_lower_start_index =
+        TickArrayState::get_array_start_index(TICK_LOWER_INDEX, TICK_SPACING);
+    let tick_array_upper_start_index =
+        TickArrayState::get_array_start_index(TICK_UPPER_INDEX, TICK_SPACING);
+    let personal_position = clmm_pda(&[
+        POSITION_SEED.as_bytes(),
+        position_nft_mint.pubkey().as_ref(),
+    ]);
+
+    let instruction = Instruction {
+        program_id: cpi_consumer::id(),
+        accounts: cpi_consumer::accounts::ProxyOpenPosition {
+            clmm_program: raydium_amm_v3::id(),
+            payer,
+            position_nft_owner: payer,
+            position_nft_mint: position_nft_mint.pubkey(),
+            position_nft_account: get_associated_token_address_with_program_id(
+                &payer,
+                &position_nft_mint.pubkey(),
+                &anchor_spl::token_2022::ID,
+            ),
+            pool_state: pool.pool_state,
+            protocol_position: clmm_pda(&[
+                POSITION_SEED.as_bytes(),
+                pool.pool_state.as_ref(),
+                &TICK_LOWER_INDEX.to_be_bytes(),
+                &TICK_UPPER_INDEX.to_be_bytes(),
+            ]),
+            tick_array_lower: tick_array_key(&pool.pool_state, tick_array_lower_start_index),
+            tick_array_upper: tick_array_key(&pool.pool_state, tick_array_upper_start_index),
+            personal_position,
+            token_account_0: pool.user_token_account_0,
+            token_account_1: pool.user_token_account_1,
+            token_vault_0: pool.token_vault_0,
+            token_vault_1: pool.token_vault_1,
+            rent: sysvar::rent::id(),
+            system_program: system_program::ID,
+            token_program: spl_token::id(),
+            associated_token_program: spl_associated_token_account::id(),
+            token_program_2022: anchor_spl::token_2022::ID,
+            vault_0_mint: pool.token_mint_0,
+            vault_1_mint: pool.token_mint_1,
+        }
+        .to_account_metas(None),
+        data: cpi_consumer::instruction::ProxyOpenPosition {
+            tick_lower_index: TICK_LOWER_INDEX,
+            tick_upper_index: TICK_UPPER_INDEX,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            liquidity,
+            amount_0_max: USER_BALANCE,
+            amount_1_max: USER_BALANCE,
+            base_flag: None,
+        }
+        .data(),
+    };
+    process(context, &[instruction], &[&position_nft_mint]).await?;
+    Ok(personal_position)
+}
+
+fn proxy_swap_0_for_1_accounts(
+    context: &ProgramTestContext,
+    pool: &TestPool,
+) -> cpi_consumer::accounts::ProxySwap {
+    cpi_consumer::accounts::ProxySwap {
+        clmm_program: raydium_amm_v3::id(),
+        payer: context.payer.pubkey(),
+        amm_config: pool.amm_config,
+        pool_state: pool.pool_state,
+        input_token_account: pool.user_token_account_0,
+        output_token_account: pool.user_token_account_1,
+        input_vault: pool.token_vault_0,
+        output_vault: pool.token_vault_1,
+        observation_state: pool.observation_state,
+        token_program: spl_token::id(),
+        token_program_2022: anchor_spl::token_2022::ID,
+        memo_program: spl_memo::id(),
+        input_vault_mint: pool.token_mint_0,
+        output_vault_mint: pool.token_mint_1,
+    }
+}
+
+/// The tick arrays in the swap direction, starting from the one of the current tick
+fn swap_0_for_1_tick_arrays(pool: &TestPool) -> Vec<AccountMeta> {
+    [
+        TickArrayState::get_array_start_index(0, TICK_SPACING),
+        TickArrayState::get_array_start_index(TICK_LOWER_INDEX, TICK_SPACING),
+    ]
+    .into_iter()
+    .map(|start_index| AccountMeta::new(tick_array_key(&pool.pool_state, start_index), false))
+    .collect()
+}
+
+fn proxy_swap_0_for_1_instruction(
+    context: &ProgramTestContext,
+    pool: &TestPool,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Instruction {
+    let mut accounts = proxy_swap_0_for_1_accounts(context, pool).to_account_metas(None);
+    accounts.extend(swap_0_for_1_tick_arrays(pool));
+    Instruction {
+        program_id: cpi_consumer::id(),
+        accounts,
+        data: cpi_consumer::instruction::ProxySwap {
+            amount: amount_in,
+            other_amount_threshold: amount_out_minimum,
+            sqrt_price_limit_x64: 0,
+            is_base_input: true,
+        }
+        .data(),
+    }
+}
+
+async fn proxy_swap_0_for_1(
+    context: &mut ProgramTestContext,
+    pool: &TestPool,
+    amount_in: u64,
+    amount_out_minimum: u64,
+) -> Result<(), BanksClientError> {
+    let instruction = proxy_swap_0_for_1_instruction(context, pool, amount_in, amount_out_minimum);
+    process(context, &[instruction], &[]).await
+}
+
+/// Borrow token_0 from the pool with `flash`, the `cpi_consumer` callback swaps `amount_in`
+/// through the swap accounts of the pool and repays the loan
+fn flash_swap_0_for_1_instruction(
+    context: &ProgramTestContext,
+    pool: &TestPool,
+    amount_0: u64,
+    amount_in: u64,
+) -> Instruction {
+    let mut accounts = raydium_amm_v3::accounts::Flash {
+        payer: context.payer.pubkey(),
+        amm_config: pool.amm_config,
+        pool_state: pool.pool_state,
+        recipient_token_account_0: pool.user_token_account_0,
+        recipient_token_account_1: pool.user_token_account_1,
+        token_vault_0: pool.token_vault_0,
+        token_vault_1: pool.token_vault_1,
+        vault_0_mint: pool.token_mint_0,
+        vault_1_mint: pool.token_mint_1,
+        token_program: spl_token::id(),
+        token_program_2022: anchor_spl::token_2022::ID,
+        callback_program: cpi_consumer::id(),
+    }
+    .to_account_metas(None);
+    // the accounts of the callback
+    accounts.extend(
+        cpi_consumer::accounts::FlashSwapCallback {
+            swap: proxy_swap_0_for_1_accounts(context, pool),
+            lending_vault_0: pool.token_vault_0,
+            lending_vault_1: pool.token_vault_1,
+        }
+        .to_account_metas(None),
+    );
+    accounts.extend(swap_0_for_1_tick_arrays(pool));
+    Instruction {
+        program_id: raydium_amm_v3::id(),
+        accounts,
+        data: raydium_amm_v3::instruction::Flash {
+            amount_0,
+            amount_1: 0,
+            data: cpi_consumer::FlashSwapData {
+                amount_0,
+                amount_1: 0,
+                amount_in,
+                amount_out_minimum: 1,
+            }
+            .try_to_vec()
+            .unwrap(),
+        }
+        .data(),
+    }
+}
+
+/// The custom error code the failed transaction ended with
+fn custom_error_code(error: BanksClientError) -> u32 {
+    match error.unwrap() {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => code,
+        error => panic!("unexpected error {error:?}"),
+    }
+}
+
+async fn load_pool_state(context: &mut ProgramTestContext, pool: &TestPool) -> PoolState {
+    let account = context
+        .banks_client
+        .get_account(pool.pool_state)
+        .await
+        .unwrap()
+        .unwrap();
+    PoolState::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+async fn load_personal_position(
+    context: &mut ProgramTestContext,
+    personal_position: &Pubkey,
+) -> PersonalPositionState {
+    let account = context
+        .banks_client
+        .get_account(*personal_position)
+        .await
+        .unwrap()
+        .unwrap();
+    PersonalPositionState::try_deserialize(&mut account.data.as_slice()).unwrap()
+}
+
+/// Overwrite the reentrancy lock of the pool, the state a nested call sees while an instruction
+/// of the pool is in progress
+async fn set_reentrancy_lock(context: &mut ProgramTestContext, pool: &TestPool, lock: u8) {
+    let mut account = context
+        .banks_client
+        .get_account(pool.pool_state)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_state: &mut PoolState =
+        bytemuck::from_bytes_mut(&mut account.data[8..8 + std::mem::size_of::<PoolState>()]);
+    pool_state.reentrancy_lock = lock;
+    context.set_account(&pool.pool_state, &account.into());
+}
+
+/// Set the flash status bit of the pool, the admin opting it into lending
+async fn enable_flash(context: &mut ProgramTestContext, pool: &TestPool) {
+    let mut account = context
+        .banks_client
+        .get_account(pool.pool_state)
+        .await
+        .unwrap()
+        .unwrap();
+    let pool_state: &mut PoolState =
+        bytemuck::from_bytes_mut(&mut account.data[8..8 + std::mem::size_of::<PoolState>()]);
+    pool_state.set_status_by_bit(PoolStatusBitIndex::Flash, PoolStatusBitFlag::Enable);
+    context.set_account(&pool.pool_state, &account.into());
+}
+
+/// Make the payer the owner and fund owner of the amm config, the config is injected with the
+/// admin as its owner
+async fn set_amm_config_owners(context: &mut ProgramTestContext, pool: &TestPool) {
+    let mut account = context
+        .banks_client
+        .get_account(pool.amm_config)
+        .await
+        .unwrap()
+        .unwrap();
+    let mut amm_config = AmmConfig::try_deserialize(&mut account.data.as_slice()).unwrap();
+    amm_config.owner = context.payer.pubkey();
+    amm_config.fund_owner = context.payer.pubkey();
+    let mut data = Vec::new();
+    amm_config.try_serialize(&mut data).unwrap();
+    data.resize(AmmConfig::LEN, 0);
+    account.data = data;
+    context.set_account(&pool.amm_config, &account.into());
+}
+
+/// The instructions moving vault funds or reward and fee accounting that the payer can sign,
+/// every one must be rejected while the pool is locked
+fn pool_accounting_instructions(context: &ProgramTestContext, pool: &TestPool) -> Vec<Instruction> {
+    let payer = context.payer.pubkey();
+    let mut collect_fund_fee_batch_accounts = raydium_amm_v3::accounts::CollectFundFeeBatch {
+        owner: payer,
+        token_program: spl_token::id(),
+        token_program_2022: anchor_spl::token_2022::ID,
+    }
+    .to_account_metas(None);
+    collect_fund_fee_batch_accounts.extend([
+        AccountMeta::new(pool.pool_state, false),
+        AccountMeta::new_readonly(pool.amm_config, false),
+        AccountMeta::new(pool.token_vault_0, false),
+        AccountMeta::new(pool.token_vault_1, false),
+        AccountMeta::new_readonly(pool.token_mint_0, false),
+        AccountMeta::new_readonly(pool.token_mint_1, false),
+        AccountMeta::new(pool.user_token_account_0, false),
+        AccountMeta::new(pool.user_token_account_1, false),
+    ]);
+    vec![
+        Instruction {
+            program_id: raydium_amm_v3::id(),
+            accounts: raydium_amm_v3::accounts::CollectProtocolFee {
+                owner: payer,
+                pool_state: pool.pool_state,
+                amm_config: pool.amm_config,
+                token_vault_0: pool.token_vault_0,
+                token_vault_1: pool.token_vault_1,
+                vault_0_mint: pool.token_mint_0,
+                vault_1_mint: pool.token_mint_1,
+                recipient_token_account_0: pool.user_token_account_0,
+                recipient_token_account_1: pool.user_token_account_1,
+                token_program: spl_token::id(),
+                token_program_2022: anchor_spl::token_2022::ID,
+            }
+            .to_account_metas(None),
+            data: raydium_amm_v3::instruction::CollectProtocolFee {
+                amount_0_requested: u64::MAX,
+                amount_1_requested: u64::MAX,
+            }
+            .data(),
+        },
+        Instruction {
+            program_id: raydium_amm_v3::id(),
+            accounts: raydium_amm_v3::accounts::CollectFundFee {
+                owner: payer,
+                pool_state: pool.pool_state,
+                amm_config: pool.amm_config,
+                token_vault_0: pool.token_vault_0,
+                token_vault_1: pool.token_vault_1,
+                vault_0_mint: pool.token_mint_0,
+                vault_1_mint: pool.token_mint_1,
+                recipient_token_account_0: pool.user_token_account_0,
+                recipient_token_account_1: pool.user_token_account_1,
+                token_program: spl_token::id(),
+                token_program_2022: anchor_spl::token_2022::ID,
+            }
+            .to_account_metas(None),
+            data: raydium_amm_v3::instruction::CollectFundFee {
+                amount_0_requested: u64::MAX,
+                amount_1_requested: u64::MAX,
+            }
+            .data(),
+        },
+        Instruction {
+            program_id: raydium_amm_v3::id(),
+            accounts: collect_fund_fee_batch_accounts,
+            data: raydium_amm_v3::instruction::CollectFundFeeBatch {}.data(),
+        },
+        Instruction {
+            program_id: raydium_amm_v3::id(),
+            accounts: raydium_amm_v3::accounts::TopUpReward {
+                reward_funder: payer,
+                funder_token_account: pool.user_token_account_0,
+                pool_state: pool.pool_state,
+                reward_token_vault: pool.token_vault_0,
+                reward_token_mint: pool.token_mint_0,
+                reward_token_program: spl_token::id(),
+            }
+            .to_account_metas(None),
+            data: raydium_amm_v3::instruction::TopUpReward {
+                reward_index: 0,
+                amount: 1,
+            }
+            .data(),
+        },
+        Instruction {
+            program_id: raydium_amm_v3::id(),
+            accounts: raydium_amm_v3::accounts::UpdateRewardInfos {
+                pool_state: pool.pool_state,
+            }
+            .to_account_metas(None),
+            data: raydium_amm_v3::instruction::UpdateRewardInfos {}.data(),
+        },
+        Instruction {
+            program_id: raydium_amm_v3::id(),
+            accounts: raydium_amm_v3::accounts::CompactTickArray {
+                pool_state: pool.pool_state,
+                tick_array: tick_array_key(
+                    &pool.pool_state,
+                    TickArrayState::get_array_start_index(TICK_LOWER_INDEX, TICK_SPACING),
+                ),
+            }
+            .to_account_metas(None),
+            data: raydium_amm_v3::instruction::CompactTickArray {}.data(),
+        },
+    ]
+}
+
+#[tokio::test]
+async fn proxy_open_position_test() {
+    let (mut context, pool) = setup().await;
+    let liquidity = 100_000_000;
+
+    let personal_position = proxy_open_position(&mut context, &pool, liquidity)
+        .await
+        .unwrap();
+
+    let personal_position_state = load_personal_position(&mut context, &personal_position).await;
+    assert_eq!(personal_position_state.pool_id, pool.pool_state);
+    assert_eq!(personal_position_state.tick_lower_index, TICK_LOWER_INDEX);
+    assert_eq!(personal_position_state.tick_upper_index, TICK_UPPER_INDEX);
+    assert_eq!(personal_position_state.liquidity, liquidity);
+
+    let pool_state = load_pool_state(&mut context, &pool).await;
+    assert_eq!({ pool_state.liquidity }, liquidity);
+    assert!(token_balance(&mut context, &pool.token_vault_0).await > 0);
+    assert!(token_balance(&mut context, &pool.token_vault_1).await > 0);
+}
+
+#[tokio::test]
+async fn proxy_swap_test() {
+    let (mut context, pool) = setup().await;
+    proxy_open_position(&mut context, &pool, 100_000_000)
+        .await
+        .unwrap();
+
+    let balance_0_before = token_balance(&mut context, &pool.user_token_account_0).await;
+    let balance_1_before = token_balance(&mut context, &pool.user_token_account_1).await;
+    let amount_in = 10_000;
+    proxy_swap_0_for_1(&mut context, &pool, amount_in, 1)
+        .await
+        .unwrap();
+
+    let balance_0_after = token_balance(&mut context, &pool.user_token_account_0).await;
+    let balance_1_after = token_balance(&mut context, &pool.user_token_account_1).await;
+    assert_eq!(balance_0_before - balance_0_after, amount_in);
+    let amount_out = balance_1_after - balance_1_before;
+    assert!(amount_out > 0 && amount_out < amount_in);
+
+    // the slippage check of the clmm program is reached through the CPI
+    assert!(
+        proxy_swap_0_for_1(&mut context, &pool, amount_in, amount_in)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn reentrancy_lock_test() {
+    let (mut context, pool) = setup().await;
+    proxy_open_position(&mut context, &pool, 100_000_000)
+        .await
+        .unwrap();
+    assert_eq!(
+        load_pool_state(&mut context, &pool).await.reentrancy_lock,
+        0
+    );
+
+    // calls of a consumer into a pool with an instruction in progress are rejected
+    set_reentrancy_lock(&mut context, &pool, 1).await;
+    let pool_reentrancy = u32::from(ErrorCode::PoolReentrancy);
+    assert_eq!(
+        custom_error_code(
+            proxy_swap_0_for_1(&mut context, &pool, 10_000, 1)
+                .await
+                .unwrap_err()
+        ),
+        pool_reentrancy
+    );
+    assert_eq!(
+        custom_error_code(
+            proxy_open_position(&mut context, &pool, 100_000_000)
+                .await
+                .unwrap_err()
+        ),
+        pool_reentrancy
+    );
+
+    // nor can a nested call collect the fees or move the rewards of the pool
+    set_amm_config_owners(&mut context, &pool).await;
+    for instruction in pool_accounting_instructions(&context, &pool) {
+        assert_eq!(
+            custom_error_code(
+                process(&mut context, &[instruction], &[])
+                    .await
+                    .unwrap_err()
+            ),
+            pool_reentrancy
+        );
+    }
+
+    // consecutive calls are not nested, every one releases the lock it takes
+    set_reentrancy_lock(&mut context, &pool, 0).await;
+    let instructions = [
+        proxy_swap_0_for_1_instruction(&context, &pool, 10_000, 1),
+        proxy_swap_0_for_1_instruction(&context, &pool, 20_000, 1),
+    ];
+    process(&mut context, &instructions, &[]).await.unwrap();
+    assert_eq!(
+        load_pool_state(&mut context, &pool).await.reentrancy_lock,
+        0
+    );
+}
+
+#[tokio::test]
+async fn flash_disabled_by_default_test() {
+    let (mut context, pool) = setup().await;
+    proxy_open_position(&mut context, &pool, 100_000_000)
+        .await
+        .unwrap();
+    assert_eq!(load_pool_state(&mut context, &pool).await.status, 0);
+    let vault_0_before = token_balance(&mut context, &pool.token_vault_0).await;
+
+    // a pool lends only once the admin opted it in
+    let instruction = flash_swap_0_for_1_instruction(&context, &pool, 10_000, 10_000);
+    assert_eq!(
+        custom_error_code(
+            process(&mut context, &[instruction], &[])
+                .await
+                .unwrap_err()
+        ),
+        u32::from(ErrorCode::NotApproved)
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.token_vault_0).await,
+        vault_0_before
+    );
+}
+
+#[tokio::test]
+async fn flash_callback_reentry_test() {
+    let (mut context, pool) = setup().await;
+    proxy_open_position(&mut context, &pool, 100_000_000)
+        .await
+        .unwrap();
+    enable_flash(&mut context, &pool).await;
+    let pool_before = load_pool_state(&mut context, &pool).await;
+    let vault_0_before = token_balance(&mut context, &pool.token_vault_0).await;
+    let vault_1_before = token_balance(&mut context, &pool.token_vault_1).await;
+
+    // the callback swaps through the lending pool while the flash loan holds its lock, the
+    // nested swap is rejected and the whole flash loan with it
+    let instruction = flash_swap_0_for_1_instruction(&context, &pool, 10_000, 10_000);
+    assert!(process(&mut context, &[instruction], &[]).await.is_err());
+
+    let pool_after = load_pool_state(&mut context, &pool).await;
+    assert_eq!(pool_after.reentrancy_lock, 0);
+    assert_eq!({ pool_after.sqrt_price_x64 }, {
+        pool_before.sqrt_price_x64
+    });
+    assert_eq!({ pool_after.fee_growth_global_0_x64 }, {
+        pool_before.fee_growth_global_0_x64
+    });
+    assert_eq!(
+        token_balance(&mut context, &pool.token_vault_0).await,
+        vault_0_before
+    );
+    assert_eq!(
+        token_balance(&mut context, &pool.token_vault_1).await,
+        vault_1_before
+    );
+}
+
+#[tokio::test]
+async fn collect_locked_position_test() {
+    let (mut context, pool) = setup().await;
+    let personal_position = proxy_open_position(&mut context, &pool, 100_000_000)
+        .await
+        .unwrap();
+    let position_nft_mint = load_personal_position(&mut context, &personal_position)
+        .await
+        .nft_mint;
+    let payer = context.payer.pubkey();
+    let locked_position = clmm_pda(&[LOCKED_POSITION_SEED.as_bytes(), position_nft_mint.as_ref()]);
+    let locked_nft_account = get_associated_token_address_with_program_id(
+        &locked_position,
+        &position_nft_mint,
+        &anchor_spl::token_2022::ID,
+    );
+    let lock_position = Instruction {
+        program_id: raydium_amm_v3::id(),
+        accounts: raydium_amm_v3::accounts::LockPosition {
+            nft_owner: payer,
+            position_nft_mint,
+            nft_account: get_associated_token_address_with_program_id(
+                &payer,
+                &position_nft_mint,
+                &anchor_spl::token_2022::ID,
+            ),
+            personal_position,
+            locked_position,
+            locked_nft_account,
+            token_program: anchor_spl::token_2022::ID,
+            associated_token_program: spl_associated_token_account::id(),
+            system_program: system_program::ID,
+        }
+        .to_account_metas(None),
+        data: raydium_amm_v3::instruction::LockPosition {
+            unlock_time: i64::MAX as u64,
+        }
+        .data(),
+    };
+    process(&mut context, &[lock_position], &[]).await.unwrap();
+
+    // the swap pays token_0 fees to the locked position
+    proxy_swap_0_for_1(&mut context, &pool, 100_000, 1)
+        .await
+        .unwrap();
+    let balance_0_before = token_balance(&mut context, &pool.user_token_account_0).await;
+    let balance_1_before = token_balance(&mut context, &pool.user_token_account_1).await;
+
+    let collect_locked_position = Instruction {
+        program_id: raydium_amm_v3::id(),
+        accounts: raydium_amm_v3::accounts::CollectLockedPosition {
+            nft_owner: payer,
+            locked_position,
+            personal_position,
+            pool_state: pool.pool_state,
+            protocol_position: clmm_pda(&[
+                POSITION_SEED.as_bytes(),
+                pool.pool_state.as_ref(),
+                &TICK_LOWER_INDEX.to_be_bytes(),
+                &TICK_UPPER_INDEX.to_be_bytes(),
+            ]),
+            token_vault_0: pool.token_vault_0,
+            token_vault_1: pool.token_vault_1,
+            tick_array_lower: tick_array_key(
+                &pool.pool_state,
+                TickArrayState::get_array_start_index(TICK_LOWER_INDEX, TICK_SPACING),
+            ),
+            tick_array_upper: tick_array_key(
+                &pool.pool_state,
+                TickArrayState::get_array_start_index(TICK_UPPER_INDEX, TICK_SPACING),
+            ),
+            recipient_token_account_0: pool.user_token_account_0,
+            recipient_token_account_1: pool.user_token_account_1,
+            token_program: spl_token::id(),
+            token_program_2022: anchor_spl::token_2022::ID,
+            vault_0_mint: pool.token_mint_0,
+            vault_1_mint: pool.token_mint_1,
+        }
+        .to_account_metas(None),
+        data: raydium_amm_v3::instruction::CollectLockedPosition {}.data(),
+    };
+    process(&mut context, &[collect_locked_position], &[])
+        .await
+        .unwrap();
+
+    // the fees are paid to the locker, the liquidity and the nft stay locked
+    assert!(token_balance(&mut context, &pool.user_token_account_0).await > balance_0_before);
+    assert_eq!(
+        token_balance(&mut context, &pool.user_token_account_1).await,
+        balance_1_before
+    );
+    let position = load_personal_position(&mut context, &personal_position).await;
+    assert_eq!({ position.token_fees_owed_0 }, 0);
+    assert_eq!({ position.liquidity }, 100_000_000);
+    let locked_nft_account = context
+        .banks_client
+        .get_account(locked_nft_account)
+        .await
+        .unwrap()
+        .unwrap();
+    let locked_nft_account = anchor_spl::token_interface::TokenAccount::try_deserialize(
+        &mut locked_nft_account.data.as_slice(),
+    )
+    .unwrap();
+    assert_eq!(locked_nft_account.amount, 1);
+}
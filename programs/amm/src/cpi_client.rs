@@ -0,0 +1,527 @@
+//! Instruction builders for the programs calling the pools by cross program invocation. The
+//! program derived accounts are derived here from the seeds the program checks them with, a
+//! caller only brings the pool, the position and its own token accounts, then invokes the built
+//! instruction with the matching account infos.
+
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{instruction::Instruction, system_program, sysvar};
+use anchor_lang::InstructionData;
+use anchor_spl::{associated_token, token, token_2022};
+
+pub fn pool_address(amm_config: &Pubkey, token_mint_0: &Pubkey, token_mint_1: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            POOL_SEED.as_bytes(),
+            amm_config.as_ref(),
+            token_mint_0.as_ref(),
+            token_mint_1.as_ref(),
+        ],
+        &crate::id(),
+    )
+    .0
+}
+
+pub fn pool_vault_address(pool_id: &Pubkey, token_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            POOL_VAULT_SEED.as_bytes(),
+            pool_id.as_ref(),
+            token_mint.as_ref(),
+        ],
+        &crate::id(),
+    )
+    .0
+}
+
+pub fn observation_address(pool_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[OBSERVATION_SEED.as_bytes(), pool_id.as_ref()],
+        &crate::id(),
+    )
+    .0
+}
+
+/// The tick array starting at `start_tick_index`, see `TickArrayState::get_array_start_index`
+pub fn tick_array_address(pool_id: &Pubkey, start_tick_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_id.as_ref(),
+            &start_tick_index.to_be_bytes(),
+        ],
+        &crate::id(),
+    )
+    .0
+}
+
+pub fn tick_array_bitmap_extension_address(pool_id: &Pubkey) -> Pubkey {
+    TickArrayBitmapExtension::key(*pool_id)
+}
+
+/// The liquidity all the positions of a pool over the same ticks share
+pub fn protocol_position_address(
+    pool_id: &Pubkey,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            POSITION_SEED.as_bytes(),
+            pool_id.as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes(),
+        ],
+        &crate::id(),
+    )
+    .0
+}
+
+pub fn personal_position_address(position_nft_mint: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), position_nft_mint.as_ref()],
+        &crate::id(),
+    )
+    .0
+}
+
+/// The account holding the position nft minted by `open_position_with_token22_nft`
+pub fn token22_nft_account_address(nft_owner: &Pubkey, position_nft_mint: &Pubkey) -> Pubkey {
+    associated_token::get_associated_token_address_with_program_id(
+        nft_owner,
+        position_nft_mint,
+        &token_2022::ID,
+    )
+}
+
+/// The accounts of a pool its instructions take
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolKeys {
+    pub pool_id: Pubkey,
+    pub amm_config: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub token_vault_0: Pubkey,
+    pub token_vault_1: Pubkey,
+    pub observation: Pubkey,
+    pub tick_spacing: u16,
+}
+
+impl PoolKeys {
+    /// Derive the keys of the pool of `amm_config` over the two mints, `token_mint_0` is the
+    /// smaller one and `tick_spacing` the one of the config
+    pub fn new(
+        amm_config: Pubkey,
+        token_mint_0: Pubkey,
+        token_mint_1: Pubkey,
+        tick_spacing: u16,
+    ) -> Self {
+        let pool_id = pool_address(&amm_config, &token_mint_0, &token_mint_1);
+        Self {
+            pool_id,
+            amm_config,
+            token_mint_0,
+            token_mint_1,
+            token_vault_0: pool_vault_address(&pool_id, &token_mint_0),
+            token_vault_1: pool_vault_address(&pool_id, &token_mint_1),
+            observation: observation_address(&pool_id),
+            tick_spacing,
+        }
+    }
+
+    /// The keys recorded in a loaded pool, without deriving anything
+    pub fn from_pool_state(pool_id: Pubkey, pool_state: &PoolState) -> Self {
+        Self {
+            pool_id,
+            amm_config: pool_state.amm_config,
+            token_mint_0: pool_state.token_mint_0,
+            token_mint_1: pool_state.token_mint_1,
+            token_vault_0: pool_state.token_vault_0,
+            token_vault_1: pool_state.token_vault_1,
+            observation: pool_state.observation_key,
+            tick_spacing: pool_state.tick_spacing,
+        }
+    }
+
+    /// The tick array holding `tick_index`
+    pub fn tick_array(&self, tick_index: i32) -> Pubkey {
+        tick_array_address(
+            &self.pool_id,
+            TickArrayState::get_array_start_index(tick_index, self.tick_spacing),
+        )
+    }
+
+    pub fn tick_array_bitmap_extension(&self) -> Pubkey {
+        tick_array_bitmap_extension_address(&self.pool_id)
+    }
+}
+
+/// The accounts of a position in a pool
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PositionKeys {
+    pub nft_mint: Pubkey,
+    /// The token account holding the position nft
+    pub nft_account: Pubkey,
+    pub personal_position: Pubkey,
+    pub protocol_position: Pubkey,
+    pub tick_array_lower: Pubkey,
+    pub tick_array_upper: Pubkey,
+}
+
+impl PositionKeys {
+    pub fn new(
+        pool: &PoolKeys,
+        nft_mint: Pubkey,
+        nft_account: Pubkey,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+    ) -> Self {
+        Self {
+            nft_mint,
+            nft_account,
+            personal_position: personal_position_address(&nft_mint),
+            protocol_position: protocol_position_address(
+                &pool.pool_id,
+                tick_lower_index,
+                tick_upper_index,
+            ),
+            tick_array_lower: pool.tick_array(tick_lower_index),
+            tick_array_upper: pool.tick_array(tick_upper_index),
+        }
+    }
+}
+
+/// `swap_v2` over `pool`, token_0 for token_1 if `zero_for_one`. The tick arrays are the ones
+/// starting at `tick_array_start_indexes`, in the order the swap crosses them
+pub fn swap_v2(
+    pool: &PoolKeys,
+    payer: Pubkey,
+    input_token_account: Pubkey,
+    output_token_account: Pubkey,
+    zero_for_one: bool,
+    tick_array_start_indexes: &[i32],
+    amount: u64,
+    other_amount_threshold: u64,
+    sqrt_price_limit_x64: u128,
+    is_base_input: bool,
+) -> Instruction {
+    let (input_vault, output_vault, input_vault_mint, output_vault_mint) = if zero_for_one {
+        (
+            pool.token_vault_0,
+            pool.token_vault_1,
+            pool.token_mint_0,
+            pool.token_mint_1,
+        )
+    } else {
+        (
+            pool.token_vault_1,
+            pool.token_vault_0,
+            pool.token_mint_1,
+            pool.token_mint_0,
+        )
+    };
+    let mut accounts = crate::accounts::SwapSingleV2 {
+        payer,
+        amm_config: pool.amm_config,
+        pool_state: pool.pool_id,
+        input_token_account,
+        output_token_account,
+        input_vault,
+        output_vault,
+        observation_state: pool.observation,
+        token_program: token::ID,
+        token_program_2022: token_2022::ID,
+        memo_program: spl_memo::id(),
+        input_vault_mint,
+        output_vault_mint,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new_readonly(
+        pool.tick_array_bitmap_extension(),
+        false,
+    ));
+    accounts.extend(tick_array_start_indexes.iter().map(|start_index| {
+        AccountMeta::new(tick_array_address(&pool.pool_id, *start_index), false)
+    }));
+    Instruction {
+        program_id: crate::id(),
+        accounts,
+        data: crate::instruction::SwapV2 {
+            amount,
+            other_amount_threshold,
+            sqrt_price_limit_x64,
+            is_base_input,
+        }
+        .data(),
+    }
+}
+
+/// `open_position_with_token22_nft` minting the nft of `position` to `position_nft_owner`, the
+/// position nft mint signs the instruction. Its nft account must be the one of
+/// `token22_nft_account_address`
+pub fn open_position_with_token22_nft(
+    pool: &PoolKeys,
+    position: &PositionKeys,
+    payer: Pubkey,
+    position_nft_owner: Pubkey,
+    token_account_0: Pubkey,
+    token_account_1: Pubkey,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    with_metadata: bool,
+    base_flag: Option<bool>,
+) -> Instruction {
+    let mut accounts = crate::accounts::OpenPositionWithToken22Nft {
+        payer,
+        position_nft_owner,
+        position_nft_mint: position.nft_mint,
+        position_nft_account: position.nft_account,
+        pool_state: pool.pool_id,
+        protocol_position: position.protocol_position,
+        tick_array_lower: position.tick_array_lower,
+        tick_array_upper: position.tick_array_upper,
+        personal_position: position.personal_position,
+        token_account_0,
+        token_account_1,
+        token_vault_0: pool.token_vault_0,
+        token_vault_1: pool.token_vault_1,
+        rent: sysvar::rent::ID,
+        system_program: system_program::ID,
+        token_program: token::ID,
+        associated_token_program: associated_token::ID,
+        token_program_2022: token_2022::ID,
+        vault_0_mint: pool.token_mint_0,
+        vault_1_mint: pool.token_mint_1,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new(pool.tick_array_bitmap_extension(), false));
+    Instruction {
+        program_id: crate::id(),
+        accounts,
+        data: crate::instruction::OpenPositionWithToken22Nft {
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index: TickArrayState::get_array_start_index(
+                tick_lower_index,
+                pool.tick_spacing,
+            ),
+            tick_array_upper_start_index: TickArrayState::get_array_start_index(
+                tick_upper_index,
+                pool.tick_spacing,
+            ),
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            with_metadata,
+            base_flag,
+        }
+        .data(),
+    }
+}
+
+/// `increase_liquidity_v2` of `position`, signed by the owner of its nft
+pub fn increase_liquidity_v2(
+    pool: &PoolKeys,
+    position: &PositionKeys,
+    nft_owner: Pubkey,
+    token_account_0: Pubkey,
+    token_account_1: Pubkey,
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    base_flag: Option<bool>,
+) -> Instruction {
+    let mut accounts = crate::accounts::IncreaseLiquidityV2 {
+        nft_owner,
+        nft_account: position.nft_account,
+        pool_state: pool.pool_id,
+        protocol_position: position.protocol_position,
+        personal_position: position.personal_position,
+        tick_array_lower: position.tick_array_lower,
+        tick_array_upper: position.tick_array_upper,
+        token_account_0,
+        token_account_1,
+        token_vault_0: pool.token_vault_0,
+        token_vault_1: pool.token_vault_1,
+        token_program: token::ID,
+        token_program_2022: token_2022::ID,
+        vault_0_mint: pool.token_mint_0,
+        vault_1_mint: pool.token_mint_1,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new(pool.tick_array_bitmap_extension(), false));
+    Instruction {
+        program_id: crate::id(),
+        accounts,
+        data: crate::instruction::IncreaseLiquidityV2 {
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            base_flag,
+        }
+        .data(),
+    }
+}
+
+/// `decrease_liquidity_v2` of `position`, signed by the owner of its nft. The reward accounts of
+/// the initialized rewards of the pool are appended by the caller, as `decrease_liquidity_v2`
+/// documents them
+pub fn decrease_liquidity_v2(
+    pool: &PoolKeys,
+    position: &PositionKeys,
+    nft_owner: Pubkey,
+    recipient_token_account_0: Pubkey,
+    recipient_token_account_1: Pubkey,
+    liquidity: u128,
+    amount_0_min: u64,
+    amount_1_min: u64,
+) -> Instruction {
+    let mut accounts = crate::accounts::DecreaseLiquidityV2 {
+        nft_owner,
+        nft_account: position.nft_account,
+        personal_position: position.personal_position,
+        pool_state: pool.pool_id,
+        protocol_position: position.protocol_position,
+        token_vault_0: pool.token_vault_0,
+        token_vault_1: pool.token_vault_1,
+        tick_array_lower: position.tick_array_lower,
+        tick_array_upper: position.tick_array_upper,
+        recipient_token_account_0,
+        recipient_token_account_1,
+        token_program: token::ID,
+        token_program_2022: token_2022::ID,
+        memo_program: spl_memo::id(),
+        vault_0_mint: pool.token_mint_0,
+        vault_1_mint: pool.token_mint_1,
+    }
+    .to_account_metas(None);
+    accounts.push(AccountMeta::new(pool.tick_array_bitmap_extension(), false));
+    Instruction {
+        program_id: crate::id(),
+        accounts,
+        data: crate::instruction::DecreaseLiquidityV2 {
+            liquidity,
+            amount_0_min,
+            amount_1_min,
+        }
+        .data(),
+    }
+}
+
+#[cfg(test)]
+mod cpi_client_test {
+    use super::*;
+    use anchor_lang::Discriminator;
+
+    fn pool_keys() -> PoolKeys {
+        PoolKeys::new(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            10,
+        )
+    }
+
+    #[test]
+    fn pool_keys_test() {
+        let pool = pool_keys();
+        assert_eq!(
+            pool.pool_id,
+            pool_address(&pool.amm_config, &pool.token_mint_0, &pool.token_mint_1)
+        );
+        assert_ne!(pool.token_vault_0, pool.token_vault_1);
+        // the start index of the array is derived from the tick
+        assert_eq!(pool.tick_array(-1), tick_array_address(&pool.pool_id, -600));
+        assert_eq!(pool.tick_array(599), tick_array_address(&pool.pool_id, 0));
+
+        let pool_state = PoolState {
+            amm_config: pool.amm_config,
+            token_mint_0: pool.token_mint_0,
+            token_mint_1: pool.token_mint_1,
+            token_vault_0: pool.token_vault_0,
+            token_vault_1: pool.token_vault_1,
+            observation_key: pool.observation,
+            tick_spacing: pool.tick_spacing,
+            ..Default::default()
+        };
+        assert_eq!(PoolKeys::from_pool_state(pool.pool_id, &pool_state), pool);
+    }
+
+    #[test]
+    fn swap_v2_test() {
+        let pool = pool_keys();
+        let payer = Pubkey::new_unique();
+        let ix = swap_v2(
+            &pool,
+            payer,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            false,
+            &[0, 600],
+            100,
+            90,
+            0,
+            true,
+        );
+        assert_eq!(ix.program_id, crate::id());
+        assert_eq!(ix.accounts.len(), 16);
+        assert!(ix.accounts[0].pubkey == payer && ix.accounts[0].is_signer);
+        // one for zero: token_1 is the input
+        assert_eq!(ix.accounts[5].pubkey, pool.token_vault_1);
+        assert_eq!(ix.accounts[6].pubkey, pool.token_vault_0);
+        assert_eq!(ix.accounts[11].pubkey, pool.token_mint_1);
+        assert_eq!(ix.accounts[13].pubkey, pool.tick_array_bitmap_extension());
+        assert_eq!(
+            ix.accounts[15].pubkey,
+            tick_array_address(&pool.pool_id, 600)
+        );
+        assert_eq!(ix.data[..8], crate::instruction::SwapV2::DISCRIMINATOR);
+    }
+
+    #[test]
+    fn position_keys_test() {
+        let pool = pool_keys();
+        let owner = Pubkey::new_unique();
+        let nft_mint = Pubkey::new_unique();
+        let position = PositionKeys::new(
+            &pool,
+            nft_mint,
+            token22_nft_account_address(&owner, &nft_mint),
+            -10,
+            1200,
+        );
+        assert_eq!(
+            position.protocol_position,
+            protocol_position_address(&pool.pool_id, -10, 1200)
+        );
+        assert_eq!(
+            position.tick_array_lower,
+            tick_array_address(&pool.pool_id, -600)
+        );
+        assert_eq!(
+            position.tick_array_upper,
+            tick_array_address(&pool.pool_id, 1200)
+        );
+
+        let ix = decrease_liquidity_v2(
+            &pool,
+            &position,
+            owner,
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            1_000,
+            0,
+            0,
+        );
+        assert!(ix.accounts[0].pubkey == owner && ix.accounts[0].is_signer);
+        assert_eq!(ix.accounts[1].pubkey, position.nft_account);
+        assert_eq!(ix.accounts[2].pubkey, position.personal_position);
+        assert_eq!(
+            ix.accounts.last().unwrap().pubkey,
+            pool.tick_array_bitmap_extension()
+        );
+    }
+}
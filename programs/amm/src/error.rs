@@ -105,4 +105,100 @@ pub enum ErrorCode {
     MaxTokenOverflow,
     #[msg("calculate overflow")]
     CalculateOverflow,
+    #[msg("Reward has not ended yet")]
+    RewardNotEnded,
+    #[msg("Reward has been consolidated into a merkle snapshot")]
+    RewardConsolidated,
+    #[msg("Invalid merkle proof")]
+    InvalidMerkleProof,
+    #[msg("Swap exceeded the maximum steps for the provided tick arrays")]
+    SwapStepLimitExceeded,
+    #[msg("Buyback token must be one of the pool tokens")]
+    InvalidBuybackMint,
+    #[msg("Pool price is out of the buyback price range")]
+    BuybackPriceOutOfRange,
+    #[msg("Reward rebate rate must be positive and not exceed the maximum")]
+    InvalidRewardRebateRate,
+    #[msg("The reward is paid out of protocol fees and has no funded vault")]
+    FeeRebateReward,
+    #[msg("Reconcile remaining accounts must be distinct positions with their tick arrays")]
+    InvalidReconcileAccounts,
+    #[msg("Missing or invalid ed25519 signature instruction")]
+    InvalidSignatureInstruction,
+    #[msg("The relayed action has expired")]
+    RelayMessageExpired,
+    #[msg("The relayed action nonce doesn't match the position")]
+    InvalidRelayNonce,
+    #[msg("Relayed actions must use the token accounts of the position owner")]
+    InvalidRelayTokenAccount,
+    #[msg("Invalid or expired initial price reference of the pool")]
+    InvalidPriceReference,
+    #[msg("Initial price is too far from the reference price")]
+    InitialPriceOutOfBand,
+    #[msg("The output vault can't pay the swap, the max output amount is in the return data")]
+    InsufficientOutputVault,
+    #[msg("The pool is locked by an instruction in progress, nested calls are not allowed")]
+    PoolReentrancy,
+    #[msg("Swap input amount is below the minimum of the pool")]
+    SwapAmountBelowMinimum,
+    #[msg("The token accounts must be owned by the fee recipient of the position")]
+    InvalidFeeRecipient,
+    #[msg("The user token account doesn't hold the mint of its vault")]
+    SwapTokenAccountMintMismatch,
+    #[msg("The swap route has no pool")]
+    EmptySwapRoute,
+    #[msg("The position nft has no metadata the program can update")]
+    PositionMetadataNotUpdatable,
+    #[msg("Batch remaining accounts must be groups of a pool, its amm config, vaults, mints and recipients")]
+    InvalidCollectFundFeeBatchAccounts,
+    #[msg("The observations don't cover the twap period")]
+    InvalidTwapPeriod,
+    #[msg("The pool status bit is not an operation of the pool")]
+    InvalidPoolStatusBit,
+    #[msg("Liquidity can't be added to a pool it can't be removed from")]
+    ConflictingPoolStatus,
+    #[msg("The reward vault doesn't cover the unclaimed and remaining emissions")]
+    InsufficientRewardVault,
+    #[msg("The amm config, observation or output mint of a route hop doesn't belong to its pool")]
+    InvalidRouteHop,
+    #[msg("The unlock time must be in the future")]
+    InvalidUnlockTime,
+    #[msg("The position is locked until its unlock time")]
+    PositionLocked,
+    #[msg("Liquidity can only be migrated to another pool of the same pair")]
+    InvalidMigration,
+    #[msg("The config owner can only be changed by a proposal the new owner accepts")]
+    OwnerTransferNotAccepted,
+    #[msg("The protocol fee split account or the token accounts of its destinations are invalid")]
+    InvalidProtocolFeeSplit,
+    #[msg("The flash callback program can't be this program")]
+    InvalidFlashCallback,
+    #[msg("The vaults didn't get back the flash loan and its fee")]
+    FlashNotRepaid,
+    #[msg("A flash loan needs liquidity in range to pay its fee to")]
+    FlashWithoutLiquidity,
+    #[msg("The position can only be transferred to another owner")]
+    InvalidPositionTransfer,
+    #[msg("The token account of the position nft is frozen")]
+    PositionNftAccountFrozen,
+    #[msg("Checked math overflowed or underflowed")]
+    MathOverflow,
+    #[msg("A fee or fee growth accumulator overflowed or underflowed")]
+    FeeAccumulatorOverflow,
+    #[msg("The position nft can't be sent to the default pubkey")]
+    InvalidPositionNftOwner,
+    #[msg("The fees owed to the position are worth no liquidity")]
+    NothingToCompound,
+    #[msg("The pool stats account is of another pool")]
+    PoolStatsMismatch,
+    #[msg("The fee rate must be below the fee rate denominator")]
+    InvalidFeeRate,
+    #[msg("The buyback crank can't run again before its minimum interval")]
+    BuybackCrankTooEarly,
+    #[msg("Rewards were collected since the merkle snapshot was taken")]
+    RewardClaimedSinceSnapshot,
+    #[msg("The claims against the merkle snapshot exceed its total amount")]
+    MerkleClaimExceedsTotal,
+    #[msg("The fund owner of the config must be passed as a writable wallet to receive the pool creation fee")]
+    InvalidPoolCreationFeeReceiver,
 }
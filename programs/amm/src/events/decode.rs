@@ -0,0 +1,185 @@
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+const PROGRAM_DATA: &str = "Program data: ";
+
+macro_rules! amm_events {
+    ($($event:ident),* $(,)?) => {
+        /// An event emitted by the program
+        #[cfg_attr(feature = "client", derive(Debug))]
+        pub enum AmmEvent {
+            $($event($event),)*
+        }
+
+        impl AmmEvent {
+            /// Decode the discriminator and borsh data of an event, None if the discriminator
+            /// isn't one of an event of the program
+            pub fn try_from_bytes(data: &[u8]) -> std::io::Result<Option<Self>> {
+                if data.len() < 8 {
+                    return Ok(None);
+                }
+                let (discriminator, body) = data.split_at(8);
+                $(
+                    if discriminator == &$event::DISCRIMINATOR[..] {
+                        return Ok(Some(AmmEvent::$event($event::try_from_slice(body)?)));
+                    }
+                )*
+                Ok(None)
+            }
+
+            /// The name of the event
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(AmmEvent::$event(_) => stringify!($event),)*
+                }
+            }
+        }
+    };
+}
+
+amm_events!(
+    ConfigChangeEvent,
+    ConfigOwnerProposedEvent,
+    SetProtocolFeeSplitEvent,
+    PoolCreatedEvent,
+    PoolStatusChangedEvent,
+    CollectProtocolFeeEvent,
+    SwapEvent,
+    FlashEvent,
+    LiquidityChangeEvent,
+    LiquidityCalculateEvent,
+    CreatePersonalPositionEvent,
+    IncreaseLiquidityEvent,
+    CompoundFeesEvent,
+    DecreaseLiquidityEvent,
+    CollectPersonalFeeEvent,
+    CollectRewardEvent,
+    SetFeeRecipientEvent,
+    TransferPositionEvent,
+    MigratePositionEvent,
+    LockPositionEvent,
+    UnlockPositionEvent,
+    RelayedPositionActionEvent,
+    UpdateRewardInfosEvent,
+    TopUpRewardEvent,
+    InitializeFeeRebateRewardEvent,
+    RewardMerkleRootEvent,
+    ClaimMerkleRewardEvent,
+    TickArrayCompactedEvent,
+    SetBuybackConfigEvent,
+    BuybackEvent,
+    VaultReconciliationEvent,
+);
+
+/// Decode the events emitted by `program_id` from the log messages of a transaction, including
+/// the ones emitted in a CPI. The data logged by other programs and the unknown discriminators
+/// are skipped.
+pub fn decode_logs(logs: &[String], program_id: &Pubkey) -> std::io::Result<Vec<AmmEvent>> {
+    let program_id = program_id.to_string();
+    // the programs invoked, the innermost last
+    let mut invoked: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+    for log in logs {
+        if let Some(data) = log.strip_prefix(PROGRAM_DATA) {
+            if invoked.last() != Some(&program_id.as_str()) {
+                continue;
+            }
+            let data = anchor_lang::__private::base64::decode(data)
+                .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+            if let Some(event) = AmmEvent::try_from_bytes(&data)? {
+                events.push(event);
+            }
+            continue;
+        }
+        let mut words = match log.strip_prefix("Program ") {
+            Some(rest) => rest.split_whitespace(),
+            None => continue,
+        };
+        match (words.next(), words.next()) {
+            (Some(program), Some("invoke")) => invoked.push(program),
+            (Some(_), Some("success")) | (Some(_), Some("failed:")) => {
+                invoked.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(events)
+}
+
+#[cfg(test)]
+mod decode_test {
+    use super::*;
+    use anchor_lang::Event;
+
+    fn data_log(event: &impl Event) -> String {
+        format!(
+            "{}{}",
+            PROGRAM_DATA,
+            anchor_lang::__private::base64::encode(event.data())
+        )
+    }
+
+    #[test]
+    fn decode_logs_test() {
+        let other_program = Pubkey::new_unique();
+        let position_nft_mint = Pubkey::new_unique();
+        let lock = LockPositionEvent {
+            personal_position: Pubkey::new_unique(),
+            owner: Pubkey::new_unique(),
+            position_nft_mint,
+            unlock_time: 42,
+        };
+        let config_change = ConfigChangeEvent {
+            index: 3,
+            owner: Pubkey::new_unique(),
+            protocol_fee_rate: 120_000,
+            trade_fee_rate: 2_500,
+            tick_spacing: 60,
+            fund_fee_rate: 40_000,
+            fund_owner: Pubkey::new_unique(),
+            pool_creation_fee: 1_000_000,
+        };
+        let logs = vec![
+            format!("Program {} invoke [1]", crate::id()),
+            "Program log: Instruction: LockPosition".to_string(),
+            data_log(&lock),
+            // an event shaped log of another program called by this one
+            format!("Program {} invoke [2]", other_program),
+            data_log(&lock),
+            format!("Program {} success", other_program),
+            data_log(&config_change),
+            format!(
+                "Program {} consumed 1000 of 200000 compute units",
+                crate::id()
+            ),
+            format!("Program {} success", crate::id()),
+            // after the program returned
+            data_log(&lock),
+        ];
+
+        let events = decode_logs(&logs, &crate::id()).unwrap();
+        assert_eq!(events.len(), 2);
+        match &events[0] {
+            AmmEvent::LockPositionEvent(event) => {
+                assert_eq!(event.position_nft_mint, position_nft_mint);
+                assert_eq!(event.unlock_time, 42);
+            }
+            _ => panic!("expected a lock event"),
+        }
+        match &events[1] {
+            AmmEvent::ConfigChangeEvent(event) => {
+                assert_eq!(event.index, 3);
+                assert_eq!(event.tick_spacing, 60);
+            }
+            _ => panic!("expected a config change event"),
+        }
+        assert_eq!(events[1].name(), "ConfigChangeEvent");
+
+        // a truncated event is an error, an unknown discriminator is skipped
+        let mut data = lock.data();
+        data.pop();
+        assert!(AmmEvent::try_from_bytes(&data).is_err());
+        assert!(AmmEvent::try_from_bytes(&[0; 16]).unwrap().is_none());
+    }
+}
@@ -0,0 +1,42 @@
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct AcceptConfigOwner<'info> {
+    /// The proposed owner
+    pub new_owner: Signer<'info>,
+
+    /// Amm config account to be transferred
+    #[account(mut)]
+    pub amm_config: Account<'info, AmmConfig>,
+
+    #[account(
+        mut,
+        seeds = [
+            CONFIG_OWNER_PROPOSAL_SEED.as_bytes(),
+            amm_config.key().as_ref(),
+        ],
+        bump = config_owner_proposal.bump,
+    )]
+    pub config_owner_proposal: Account<'info, ConfigOwnerProposal>,
+}
+
+pub fn accept_config_owner(ctx: Context<AcceptConfigOwner>) -> Result<()> {
+    let amm_config = &mut ctx.accounts.amm_config;
+    amm_config.owner = ctx
+        .accounts
+        .config_owner_proposal
+        .accept(ctx.accounts.new_owner.key())?;
+
+    emit!(ConfigChangeEvent {
+        index: amm_config.index,
+        owner: amm_config.owner,
+        trade_fee_rate: amm_config.trade_fee_rate,
+        protocol_fee_rate: amm_config.protocol_fee_rate,
+        tick_spacing: amm_config.tick_spacing,
+        fund_fee_rate: amm_config.fund_fee_rate,
+        fund_owner: amm_config.fund_owner,
+        pool_creation_fee: amm_config.pool_creation_fee,
+    });
+    Ok(())
+}
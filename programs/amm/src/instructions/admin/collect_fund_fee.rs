@@ -69,6 +69,7 @@ pub fn collect_fund_fee(
     let amount_1: u64;
     {
         let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+        pool_state.check_unlocked()?;
         amount_0 = amount_0_requested.min(pool_state.fund_fees_token_0);
         amount_1 = amount_1_requested.min(pool_state.fund_fees_token_1);
 
@@ -0,0 +1,130 @@
+use crate::decrease_liquidity::check_unclaimed_fees_and_vault;
+use crate::error::ErrorCode;
+use crate::states::*;
+use crate::util::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+
+/// The remaining accounts of every pool in the batch
+pub const COLLECT_FUND_FEE_BATCH_ACCOUNTS_PER_POOL: usize = 8;
+
+#[derive(Accounts)]
+pub struct CollectFundFeeBatch<'info> {
+    /// Only admin or the fund_owner of every pool's amm config can collect fee
+    pub owner: Signer<'info>,
+
+    /// The SPL program to perform token transfers
+    pub token_program: Program<'info, Token>,
+
+    /// The SPL program 2022 to perform token transfers
+    pub token_program_2022: Program<'info, Token2022>,
+    // remaining accounts, for every pool
+    // pool_state
+    // amm_config
+    // token_vault_0
+    // token_vault_1
+    // vault_0_mint
+    // vault_1_mint
+    // recipient_token_account_0
+    // recipient_token_account_1
+}
+
+/// Collect all the fund fees of every pool of the remaining accounts
+pub fn collect_fund_fee_batch<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CollectFundFeeBatch<'info>>,
+) -> Result<()> {
+    require!(
+        !ctx.remaining_accounts.is_empty()
+            && ctx.remaining_accounts.len() % COLLECT_FUND_FEE_BATCH_ACCOUNTS_PER_POOL == 0,
+        ErrorCode::InvalidCollectFundFeeBatchAccounts
+    );
+    for accounts in ctx
+        .remaining_accounts
+        .chunks(COLLECT_FUND_FEE_BATCH_ACCOUNTS_PER_POOL)
+    {
+        collect_pool_fund_fee(
+            &ctx.accounts.owner,
+            accounts,
+            &ctx.accounts.token_program,
+            &ctx.accounts.token_program_2022,
+        )?;
+    }
+    Ok(())
+}
+
+fn collect_pool_fund_fee<'c: 'info, 'info>(
+    owner: &Signer<'info>,
+    accounts: &'c [AccountInfo<'info>],
+    token_program: &Program<'info, Token>,
+    token_program_2022: &Program<'info, Token2022>,
+) -> Result<()> {
+    let pool_state_loader = AccountLoader::<PoolState>::try_from(&accounts[0])?;
+    let amm_config = Account::<AmmConfig>::try_from(&accounts[1])?;
+    let token_vault_0 = &accounts[2];
+    let token_vault_1 = &accounts[3];
+    let vault_0_mint = Box::new(InterfaceAccount::<Mint>::try_from(&accounts[4])?);
+    let vault_1_mint = Box::new(InterfaceAccount::<Mint>::try_from(&accounts[5])?);
+    let recipient_token_account_0 = &accounts[6];
+    let recipient_token_account_1 = &accounts[7];
+    require!(
+        owner.key() == amm_config.fund_owner || owner.key() == crate::admin::id(),
+        ErrorCode::NotApproved
+    );
+
+    let amount_0: u64;
+    let amount_1: u64;
+    {
+        let mut pool_state = pool_state_loader.load_mut()?;
+        pool_state.check_unlocked()?;
+        require_keys_eq!(pool_state.amm_config, amm_config.key());
+        require_keys_eq!(pool_state.token_vault_0, token_vault_0.key());
+        require_keys_eq!(pool_state.token_vault_1, token_vault_1.key());
+        require_keys_eq!(pool_state.token_mint_0, vault_0_mint.key());
+        require_keys_eq!(pool_state.token_mint_1, vault_1_mint.key());
+        // the recipients must be token accounts of the vault mints
+        for (recipient, mint) in [
+            (recipient_token_account_0, &vault_0_mint),
+            (recipient_token_account_1, &vault_1_mint),
+        ] {
+            let recipient = InterfaceAccount::<TokenAccount>::try_from(recipient)?;
+            require_keys_eq!(recipient.mint, mint.key());
+        }
+
+        amount_0 = pool_state.fund_fees_token_0;
+        amount_1 = pool_state.fund_fees_token_1;
+        pool_state.fund_fees_token_0 = 0;
+        pool_state.fund_fees_token_1 = 0;
+    }
+    transfer_from_pool_vault_to_user(
+        &pool_state_loader,
+        token_vault_0,
+        recipient_token_account_0,
+        Some(vault_0_mint),
+        token_program,
+        Some(token_program_2022.to_account_info()),
+        amount_0,
+    )?;
+
+    transfer_from_pool_vault_to_user(
+        &pool_state_loader,
+        token_vault_1,
+        recipient_token_account_1,
+        Some(vault_1_mint),
+        token_program,
+        Some(token_program_2022.to_account_info()),
+        amount_1,
+    )?;
+
+    check_unclaimed_fees_and_vault(&pool_state_loader, token_vault_0, token_vault_1)?;
+
+    emit!(CollectProtocolFeeEvent {
+        pool_state: pool_state_loader.key(),
+        recipient_token_account_0: recipient_token_account_0.key(),
+        recipient_token_account_1: recipient_token_account_1.key(),
+        amount_0,
+        amount_1,
+    });
+
+    Ok(())
+}
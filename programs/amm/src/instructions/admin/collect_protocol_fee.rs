@@ -61,10 +61,51 @@ pub struct CollectProtocolFee<'info> {
 
     /// The SPL program 2022 to perform token transfers
     pub token_program_2022: Program<'info, Token2022>,
+    // remaining accounts, only if the amm config splits the protocol fees
+    // protocol_fee_split
+    // secondary_recipient_token_account_0
+    // secondary_recipient_token_account_1
 }
 
-pub fn collect_protocol_fee(
-    ctx: Context<CollectProtocolFee>,
+/// The token accounts of the secondary destination, after checking the recipients of both
+/// destinations against the protocol fee split of the amm config
+fn protocol_fee_split_recipients<'c: 'info, 'info>(
+    accounts: &CollectProtocolFee<'info>,
+    remaining_accounts: &'c [AccountInfo<'info>],
+) -> Result<(&'c AccountInfo<'info>, &'c AccountInfo<'info>)> {
+    require!(
+        remaining_accounts.len() >= 3,
+        ErrorCode::InvalidProtocolFeeSplit
+    );
+    let protocol_fee_split = Account::<ProtocolFeeSplit>::try_from(&remaining_accounts[0])?;
+    require!(
+        protocol_fee_split.amm_config == accounts.amm_config.key(),
+        ErrorCode::InvalidProtocolFeeSplit
+    );
+    for recipient in [
+        &accounts.recipient_token_account_0,
+        &accounts.recipient_token_account_1,
+    ] {
+        require!(
+            recipient.owner == protocol_fee_split.primary_destination,
+            ErrorCode::InvalidProtocolFeeSplit
+        );
+    }
+    for (recipient, mint) in [
+        (&remaining_accounts[1], accounts.vault_0_mint.key()),
+        (&remaining_accounts[2], accounts.vault_1_mint.key()),
+    ] {
+        let recipient = InterfaceAccount::<TokenAccount>::try_from(recipient)?;
+        require!(
+            recipient.owner == protocol_fee_split.secondary_destination && recipient.mint == mint,
+            ErrorCode::InvalidProtocolFeeSplit
+        );
+    }
+    Ok((&remaining_accounts[1], &remaining_accounts[2]))
+}
+
+pub fn collect_protocol_fee<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CollectProtocolFee<'info>>,
     amount_0_requested: u64,
     amount_1_requested: u64,
 ) -> Result<()> {
@@ -72,6 +113,9 @@ pub fn collect_protocol_fee(
     let amount_1: u64;
     {
         let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+        pool_state.check_unlocked()?;
+        // fee rebate rewards take their share of the protocol fees first
+        pool_state.update_reward_infos(Clock::get()?.unix_timestamp as u64)?;
 
         amount_0 = amount_0_requested.min(pool_state.protocol_fees_token_0);
         amount_1 = amount_1_requested.min(pool_state.protocol_fees_token_1);
@@ -85,6 +129,17 @@ pub fn collect_protocol_fee(
             .checked_sub(amount_1)
             .unwrap();
     }
+    let split_recipients = if ctx.accounts.amm_config.protocol_fee_split_rate > 0 {
+        Some(protocol_fee_split_recipients(
+            &ctx.accounts,
+            ctx.remaining_accounts,
+        )?)
+    } else {
+        None
+    };
+    let (amount_0, secondary_amount_0) = ctx.accounts.amm_config.split_protocol_fee(amount_0);
+    let (amount_1, secondary_amount_1) = ctx.accounts.amm_config.split_protocol_fee(amount_1);
+
     transfer_from_pool_vault_to_user(
         &ctx.accounts.pool_state,
         &ctx.accounts.token_vault_0.to_account_info(),
@@ -105,6 +160,36 @@ pub fn collect_protocol_fee(
         amount_1,
     )?;
 
+    if let Some((secondary_recipient_0, secondary_recipient_1)) = split_recipients {
+        transfer_from_pool_vault_to_user(
+            &ctx.accounts.pool_state,
+            &ctx.accounts.token_vault_0.to_account_info(),
+            secondary_recipient_0,
+            Some(ctx.accounts.vault_0_mint.clone()),
+            &ctx.accounts.token_program,
+            Some(ctx.accounts.token_program_2022.to_account_info()),
+            secondary_amount_0,
+        )?;
+
+        transfer_from_pool_vault_to_user(
+            &ctx.accounts.pool_state,
+            &ctx.accounts.token_vault_1.to_account_info(),
+            secondary_recipient_1,
+            Some(ctx.accounts.vault_1_mint.clone()),
+            &ctx.accounts.token_program,
+            Some(ctx.accounts.token_program_2022.to_account_info()),
+            secondary_amount_1,
+        )?;
+
+        emit!(CollectProtocolFeeEvent {
+            pool_state: ctx.accounts.pool_state.key(),
+            recipient_token_account_0: secondary_recipient_0.key(),
+            recipient_token_account_1: secondary_recipient_1.key(),
+            amount_0: secondary_amount_0,
+            amount_1: secondary_amount_1,
+        });
+    }
+
     check_unclaimed_fees_and_vault(
         &ctx.accounts.pool_state,
         &ctx.accounts.token_vault_0.to_account_info(),
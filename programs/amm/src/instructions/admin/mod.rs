@@ -4,12 +4,24 @@ pub use create_amm_config::*;
 pub mod update_amm_config;
 pub use update_amm_config::*;
 
+pub mod propose_config_owner;
+pub use propose_config_owner::*;
+
+pub mod accept_config_owner;
+pub use accept_config_owner::*;
+
 pub mod collect_protocol_fee;
 pub use collect_protocol_fee::*;
 
+pub mod set_protocol_fee_split;
+pub use set_protocol_fee_split::*;
+
 pub mod collect_fund_fee;
 pub use collect_fund_fee::*;
 
+pub mod collect_fund_fee_batch;
+pub use collect_fund_fee_batch::*;
+
 pub mod create_operation_account;
 pub use create_operation_account::*;
 
@@ -21,3 +33,18 @@ pub use transfer_reward_owner::*;
 
 pub mod update_pool_status;
 pub use update_pool_status::*;
+
+pub mod set_pool_status_bit;
+pub use set_pool_status_bit::*;
+
+pub mod update_pool_min_swap_amount;
+pub use update_pool_min_swap_amount::*;
+
+pub mod set_reward_merkle_root;
+pub use set_reward_merkle_root::*;
+
+pub mod set_buyback_config;
+pub use set_buyback_config::*;
+
+pub mod reconcile_vaults;
+pub use reconcile_vaults::*;
@@ -0,0 +1,52 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct ProposeConfigOwner<'info> {
+    /// Only admin or config owner can propose a new owner
+    #[account(
+        mut,
+        constraint = (owner.key() == amm_config.owner || owner.key() == crate::admin::id()) @ ErrorCode::NotApproved
+    )]
+    pub owner: Signer<'info>,
+
+    /// Amm config account to be transferred
+    #[account(mut)]
+    pub amm_config: Account<'info, AmmConfig>,
+
+    /// Stores the proposed owner until it accepts
+    #[account(
+        init_if_needed,
+        seeds = [
+            CONFIG_OWNER_PROPOSAL_SEED.as_bytes(),
+            amm_config.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        space = ConfigOwnerProposal::LEN
+    )]
+    pub config_owner_proposal: Account<'info, ConfigOwnerProposal>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn propose_config_owner(ctx: Context<ProposeConfigOwner>, new_owner: Pubkey) -> Result<()> {
+    require_keys_neq!(new_owner, Pubkey::default());
+    let amm_config = &mut ctx.accounts.amm_config;
+    // from now on the owner can't be set directly by update_amm_config
+    amm_config.two_step_owner_transfer = true;
+
+    let proposal = &mut ctx.accounts.config_owner_proposal;
+    proposal.bump = ctx.bumps.config_owner_proposal;
+    proposal.amm_config = amm_config.key();
+    proposal.pending_owner = new_owner;
+    proposal.proposed_at = Clock::get()?.unix_timestamp as u64;
+
+    emit!(ConfigOwnerProposedEvent {
+        amm_config: proposal.amm_config,
+        owner: amm_config.owner,
+        pending_owner: new_owner,
+    });
+    Ok(())
+}
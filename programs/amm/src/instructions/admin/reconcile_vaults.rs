@@ -0,0 +1,229 @@
+use crate::error::ErrorCode;
+use crate::increase_liquidity::calculate_latest_token_fees;
+use crate::libraries::{big_num::U256, fixed_point_64, full_math::MulDiv, liquidity_math};
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+use std::collections::BTreeSet;
+
+#[derive(Accounts)]
+pub struct ReconcileVaults<'info> {
+    /// Only admin can publish the reconciliation report
+    #[account(
+        mut,
+        address = crate::admin::id() @ ErrorCode::NotApproved
+    )]
+    pub owner: Signer<'info>,
+
+    /// The pool to reconcile, its reward infos are updated to the current time
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Stores the report of the last reconciliation of the pool
+    #[account(
+        init_if_needed,
+        seeds = [
+            VAULT_RECONCILIATION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        space = VaultReconciliationState::LEN
+    )]
+    pub reconciliation_state: Box<Account<'info, VaultReconciliationState>>,
+
+    pub system_program: Program<'info, System>,
+    // remaining accounts, for every sampled position
+    // personal_position
+    // tick_array_lower: the tick array of the position's lower tick
+    // tick_array_upper: the tick array of the position's upper tick
+}
+
+pub fn reconcile_vaults<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, ReconcileVaults<'info>>,
+) -> Result<()> {
+    require!(
+        ctx.remaining_accounts.len() % 3 == 0,
+        ErrorCode::InvalidReconcileAccounts
+    );
+    let clock = Clock::get()?;
+    let pool_id = ctx.accounts.pool_state.key();
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    pool_state.check_unlocked()?;
+    // rewards are owed up to now
+    pool_state.update_reward_infos(clock.unix_timestamp as u64)?;
+
+    let reconciliation_state = &mut ctx.accounts.reconciliation_state;
+    reconciliation_state.reset(
+        ctx.bumps.reconciliation_state,
+        pool_id,
+        clock.slot,
+        clock.unix_timestamp as u64,
+    );
+    reconciliation_state.protocol_fees_0 = pool_state.protocol_fees_token_0;
+    reconciliation_state.protocol_fees_1 = pool_state.protocol_fees_token_1;
+    reconciliation_state.fund_fees_0 = pool_state.fund_fees_token_0;
+    reconciliation_state.fund_fees_1 = pool_state.fund_fees_token_1;
+
+    let mut sampled_positions = BTreeSet::new();
+    for accounts in ctx.remaining_accounts.chunks(3) {
+        let personal_position_loader =
+            AccountLoader::<PersonalPositionState>::try_from(&accounts[0])?;
+        let personal_position = personal_position_loader.load()?;
+        require_keys_eq!(personal_position.pool_id, pool_id);
+        // a position counted twice would hide a shortfall
+        require!(
+            sampled_positions.insert(personal_position_loader.key()),
+            ErrorCode::InvalidReconcileAccounts
+        );
+        let tick_array_lower_loader = AccountLoader::<TickArrayState>::try_from(&accounts[1])?;
+        let tick_array_upper_loader = AccountLoader::<TickArrayState>::try_from(&accounts[2])?;
+        let tick_array_lower = tick_array_lower_loader.load()?;
+        let tick_array_upper = tick_array_upper_loader.load()?;
+        require_keys_eq!(tick_array_lower.pool_id, pool_id);
+        require_keys_eq!(tick_array_upper.pool_id, pool_id);
+        let tick_lower = tick_array_lower.ticks[tick_array_lower.get_tick_offset_in_array(
+            personal_position.tick_lower_index,
+            pool_state.tick_spacing,
+        )?];
+        let tick_upper = tick_array_upper.ticks[tick_array_upper.get_tick_offset_in_array(
+            personal_position.tick_upper_index,
+            pool_state.tick_spacing,
+        )?];
+
+        add_position_owed_amounts(
+            reconciliation_state,
+            pool_state,
+            &personal_position,
+            &tick_lower,
+            &tick_upper,
+        )?;
+    }
+
+    reconciliation_state.settle(
+        ctx.accounts.token_vault_0.amount,
+        ctx.accounts.token_vault_1.amount,
+    );
+
+    emit!(VaultReconciliationEvent {
+        pool_state: pool_id,
+        position_count: reconciliation_state.position_count,
+        vault_amount_0: reconciliation_state.vault_amount_0,
+        vault_amount_1: reconciliation_state.vault_amount_1,
+        surplus_0: reconciliation_state.surplus_0,
+        surplus_1: reconciliation_state.surplus_1,
+    });
+
+    Ok(())
+}
+
+/// Add the amounts the pool owes to the position: the withdrawable liquidity, and the fees and
+/// rewards owed including the ones accrued since the position was last updated
+pub fn add_position_owed_amounts(
+    reconciliation_state: &mut VaultReconciliationState,
+    pool_state: &PoolState,
+    personal_position: &PersonalPositionState,
+    tick_lower: &TickState,
+    tick_upper: &TickState,
+) -> Result<()> {
+    let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+        pool_state.tick_current,
+        pool_state.sqrt_price_x64,
+        personal_position.tick_lower_index,
+        personal_position.tick_upper_index,
+        -i128::try_from(personal_position.liquidity).unwrap(),
+    )?;
+
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = get_fee_growth_inside(
+        tick_lower,
+        tick_upper,
+        pool_state.tick_current,
+        pool_state.fee_growth_global_0_x64,
+        pool_state.fee_growth_global_1_x64,
+    )?;
+    let fees_owed_0 = calculate_latest_token_fees(
+        personal_position.token_fees_owed_0,
+        personal_position.fee_growth_inside_0_last_x64,
+        fee_growth_inside_0_x64,
+        personal_position.liquidity,
+    );
+    let fees_owed_1 = calculate_latest_token_fees(
+        personal_position.token_fees_owed_1,
+        personal_position.fee_growth_inside_1_last_x64,
+        fee_growth_inside_1_x64,
+        personal_position.liquidity,
+    );
+
+    let reward_infos = pool_state.reward_infos;
+    let reward_growths_inside = get_reward_growths_inside(
+        tick_lower,
+        tick_upper,
+        pool_state.tick_current,
+        &reward_infos,
+    )?;
+    for i in 0..REWARD_NUM {
+        let reward_info = reward_infos[i];
+        if !reward_info.initialized() {
+            continue;
+        }
+        let position_reward_info = personal_position.reward_infos[i];
+        let reward_growth_delta =
+            reward_growths_inside[i].wrapping_sub(position_reward_info.growth_inside_last_x64);
+        let reward_owed = U256::from(reward_growth_delta)
+            .mul_div_floor(
+                U256::from(personal_position.liquidity),
+                U256::from(fixed_point_64::Q64),
+            )
+            .unwrap()
+            .to_underflow_u64()
+            .checked_add(position_reward_info.reward_amount_owed)
+            .unwrap();
+        reconciliation_state.rewards_owed[i] = reconciliation_state.rewards_owed[i]
+            .checked_add(reward_owed)
+            .unwrap();
+        // fee rebate rewards are paid out of the pool vaults
+        if reward_info.token_vault == pool_state.token_vault_0 {
+            reconciliation_state.vault_rewards_owed_0 = reconciliation_state
+                .vault_rewards_owed_0
+                .checked_add(reward_owed)
+                .unwrap();
+        } else if reward_info.token_vault == pool_state.token_vault_1 {
+            reconciliation_state.vault_rewards_owed_1 = reconciliation_state
+                .vault_rewards_owed_1
+                .checked_add(reward_owed)
+                .unwrap();
+        }
+    }
+
+    reconciliation_state.liquidity_amount_0 = reconciliation_state
+        .liquidity_amount_0
+        .checked_add(amount_0)
+        .unwrap();
+    reconciliation_state.liquidity_amount_1 = reconciliation_state
+        .liquidity_amount_1
+        .checked_add(amount_1)
+        .unwrap();
+    reconciliation_state.fees_owed_0 = reconciliation_state
+        .fees_owed_0
+        .checked_add(fees_owed_0)
+        .unwrap();
+    reconciliation_state.fees_owed_1 = reconciliation_state
+        .fees_owed_1
+        .checked_add(fees_owed_1)
+        .unwrap();
+    reconciliation_state.position_count =
+        reconciliation_state.position_count.checked_add(1).unwrap();
+    Ok(())
+}
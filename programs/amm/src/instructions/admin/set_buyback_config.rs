@@ -0,0 +1,99 @@
+use crate::error::ErrorCode;
+use crate::libraries::tick_math;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+#[derive(Accounts)]
+pub struct SetBuybackConfig<'info> {
+    /// Only admin or config owner can set the buyback config
+    #[account(
+        mut,
+        constraint = (owner.key() == amm_config.owner || owner.key() == crate::admin::id()) @ ErrorCode::NotApproved
+    )]
+    pub owner: Signer<'info>,
+
+    /// Amm config account stores owner
+    #[account(
+        address = pool_state.load()?.amm_config
+    )]
+    pub amm_config: Account<'info, AmmConfig>,
+
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Stores the buyback config and accounting of the pool
+    #[account(
+        init_if_needed,
+        seeds = [
+            BUYBACK_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        space = BuybackState::LEN
+    )]
+    pub buyback_state: Account<'info, BuybackState>,
+
+    /// The token account receiving the buyback token, its mint is the buyback token
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_buyback_config(
+    ctx: Context<SetBuybackConfig>,
+    min_sqrt_price_x64: u128,
+    max_sqrt_price_x64: u128,
+    max_amount_in_per_crank: u64,
+    twap_period: u32,
+    max_twap_tick_deviation: u32,
+    min_crank_interval: u64,
+) -> Result<()> {
+    let buyback_mint = ctx.accounts.recipient_token_account.mint;
+    {
+        let pool_state = ctx.accounts.pool_state.load()?;
+        require!(
+            buyback_mint == pool_state.token_mint_0 || buyback_mint == pool_state.token_mint_1,
+            ErrorCode::InvalidBuybackMint
+        );
+    }
+    require!(
+        min_sqrt_price_x64 > tick_math::MIN_SQRT_PRICE_X64
+            && max_sqrt_price_x64 < tick_math::MAX_SQRT_PRICE_X64
+            && min_sqrt_price_x64 < max_sqrt_price_x64,
+        ErrorCode::SqrtPriceLimitOverflow
+    );
+    require_gt!(twap_period, 0, ErrorCode::InvalidTwapPeriod);
+    // a deviation over the whole tick range disables the twap bound
+    require_gte!(
+        (tick_math::MAX_TICK - tick_math::MIN_TICK) as u32,
+        max_twap_tick_deviation,
+        ErrorCode::InvaildTickIndex
+    );
+
+    let buyback_state = &mut ctx.accounts.buyback_state;
+    buyback_state.bump = ctx.bumps.buyback_state;
+    buyback_state.pool_id = ctx.accounts.pool_state.key();
+    buyback_state.buyback_mint = buyback_mint;
+    buyback_state.recipient_token_account = ctx.accounts.recipient_token_account.key();
+    buyback_state.min_sqrt_price_x64 = min_sqrt_price_x64;
+    buyback_state.max_sqrt_price_x64 = max_sqrt_price_x64;
+    buyback_state.max_amount_in_per_crank = max_amount_in_per_crank;
+    buyback_state.twap_period = twap_period;
+    buyback_state.max_twap_tick_deviation = max_twap_tick_deviation;
+    buyback_state.min_crank_interval = min_crank_interval;
+
+    emit!(SetBuybackConfigEvent {
+        pool_state: buyback_state.pool_id,
+        buyback_mint,
+        recipient_token_account: buyback_state.recipient_token_account,
+        min_sqrt_price_x64,
+        max_sqrt_price_x64,
+        max_amount_in_per_crank,
+        twap_period,
+        max_twap_tick_deviation,
+        min_crank_interval,
+    });
+
+    Ok(())
+}
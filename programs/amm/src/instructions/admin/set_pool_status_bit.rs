@@ -0,0 +1,79 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetPoolStatusBit<'info> {
+    #[account(
+        address = crate::admin::id()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+}
+
+/// Enable or disable one operation of the pool, leaving the others as they are
+pub fn set_pool_status_bit(ctx: Context<SetPoolStatusBit>, bit: u8, enable: bool) -> Result<()> {
+    let bit = match bit {
+        0 => PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity,
+        1 => PoolStatusBitIndex::DecreaseLiquidity,
+        2 => PoolStatusBitIndex::CollectFee,
+        3 => PoolStatusBitIndex::CollectReward,
+        4 => PoolStatusBitIndex::Swap,
+        5 => PoolStatusBitIndex::Flash,
+        _ => return err!(ErrorCode::InvalidPoolStatusBit),
+    };
+    let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    let old_status = pool_state.status;
+    pool_state.set_status_by_bit(
+        bit,
+        if enable {
+            PoolStatusBitFlag::Enable
+        } else {
+            PoolStatusBitFlag::Disable
+        },
+    );
+    check_pool_status(&pool_state)?;
+
+    emit!(PoolStatusChangedEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        old_status,
+        new_status: pool_state.status,
+    });
+    Ok(())
+}
+
+/// Liquidity can't be added to a pool it can't be removed from
+pub fn check_pool_status(pool_state: &PoolState) -> Result<()> {
+    require!(
+        !pool_state.get_status_by_bit(PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity)
+            || pool_state.get_status_by_bit(PoolStatusBitIndex::DecreaseLiquidity),
+        ErrorCode::ConflictingPoolStatus
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod set_pool_status_bit_test {
+    use super::*;
+
+    #[test]
+    fn check_pool_status_test() {
+        let mut pool_state = PoolState::default();
+        assert!(check_pool_status(&pool_state).is_ok());
+
+        pool_state.set_status_by_bit(
+            PoolStatusBitIndex::DecreaseLiquidity,
+            PoolStatusBitFlag::Disable,
+        );
+        assert!(check_pool_status(&pool_state).is_err());
+
+        // withdrawals can be paused once deposits are
+        pool_state.set_status_by_bit(
+            PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity,
+            PoolStatusBitFlag::Disable,
+        );
+        assert!(check_pool_status(&pool_state).is_ok());
+    }
+}
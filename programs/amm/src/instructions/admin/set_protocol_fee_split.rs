@@ -0,0 +1,61 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetProtocolFeeSplit<'info> {
+    /// Only admin or config owner can set the protocol fee split
+    #[account(
+        mut,
+        constraint = (owner.key() == amm_config.owner || owner.key() == crate::admin::id()) @ ErrorCode::NotApproved
+    )]
+    pub owner: Signer<'info>,
+
+    /// Amm config account stores the split rate
+    #[account(mut)]
+    pub amm_config: Account<'info, AmmConfig>,
+
+    /// Stores the destinations of the protocol fees
+    #[account(
+        init_if_needed,
+        seeds = [
+            PROTOCOL_FEE_SPLIT_SEED.as_bytes(),
+            amm_config.key().as_ref(),
+        ],
+        bump,
+        payer = owner,
+        space = ProtocolFeeSplit::LEN
+    )]
+    pub protocol_fee_split: Account<'info, ProtocolFeeSplit>,
+
+    /// CHECK: The owner of the token accounts receiving the rest of the protocol fees
+    pub primary_destination: UncheckedAccount<'info>,
+
+    /// CHECK: The owner of the token accounts receiving the split share of the protocol fees
+    pub secondary_destination: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_protocol_fee_split(
+    ctx: Context<SetProtocolFeeSplit>,
+    protocol_fee_split_rate: u32,
+) -> Result<()> {
+    require_gte!(FEE_RATE_DENOMINATOR_VALUE, protocol_fee_split_rate);
+    let amm_config = &mut ctx.accounts.amm_config;
+    amm_config.protocol_fee_split_rate = protocol_fee_split_rate;
+
+    let protocol_fee_split = &mut ctx.accounts.protocol_fee_split;
+    protocol_fee_split.bump = ctx.bumps.protocol_fee_split;
+    protocol_fee_split.amm_config = amm_config.key();
+    protocol_fee_split.primary_destination = ctx.accounts.primary_destination.key();
+    protocol_fee_split.secondary_destination = ctx.accounts.secondary_destination.key();
+
+    emit!(SetProtocolFeeSplitEvent {
+        amm_config: protocol_fee_split.amm_config,
+        primary_destination: protocol_fee_split.primary_destination,
+        secondary_destination: protocol_fee_split.secondary_destination,
+        protocol_fee_split_rate,
+    });
+    Ok(())
+}
@@ -0,0 +1,186 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+#[derive(Accounts)]
+#[instruction(reward_index: u8)]
+pub struct SetRewardMerkleRoot<'info> {
+    /// Admin or operation owner, pays to create the snapshot account
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    /// load info from the account to judge permission
+    #[account(
+        seeds = [
+            OPERATION_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub operation_state: AccountLoader<'info, OperationState>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The snapshot of owed rewards
+    #[account(
+        init,
+        seeds = [
+            REWARD_MERKLE_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &[reward_index],
+        ],
+        bump,
+        payer = authority,
+        space = RewardMerkleState::LEN
+    )]
+    pub reward_merkle_state: Account<'info, RewardMerkleState>,
+
+    /// Reward vault of the pool, must hold the snapshot total
+    pub reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_reward_merkle_root(
+    ctx: Context<SetRewardMerkleRoot>,
+    reward_index: u8,
+    merkle_root: [u8; 32],
+    total_amount: u64,
+    snapshot_reward_claimed: u64,
+) -> Result<()> {
+    require!(
+        (reward_index as usize) < REWARD_NUM,
+        ErrorCode::InvalidRewardIndex
+    );
+    let authority = ctx.accounts.authority.key();
+    require!(
+        authority == crate::admin::id()
+            || ctx
+                .accounts
+                .operation_state
+                .load()?
+                .validate_operation_owner(authority),
+        ErrorCode::NotApproved
+    );
+
+    let current_timestamp = u64::try_from(Clock::get()?.unix_timestamp).unwrap();
+    let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.check_unlocked()?;
+    pool_state.update_reward_infos(current_timestamp)?;
+
+    let reward_info = pool_state.reward_infos[reward_index as usize];
+    if !reward_info.initialized() {
+        return err!(ErrorCode::UnInitializedRewardInfo);
+    }
+    if reward_info.consolidated() {
+        return err!(ErrorCode::RewardConsolidated);
+    }
+    require_eq!(
+        reward_info.last_update_time,
+        reward_info.end_time,
+        ErrorCode::RewardNotEnded
+    );
+    require_keys_eq!(
+        ctx.accounts.reward_token_vault.key(),
+        reward_info.token_vault
+    );
+
+    let reward_info = &mut pool_state.reward_infos[reward_index as usize];
+    consolidate_reward(
+        reward_info,
+        snapshot_reward_claimed,
+        total_amount,
+        ctx.accounts.reward_token_vault.amount,
+    )?;
+
+    let reward_merkle_state = &mut ctx.accounts.reward_merkle_state;
+    reward_merkle_state.bump = ctx.bumps.reward_merkle_state;
+    reward_merkle_state.pool_id = ctx.accounts.pool_state.key();
+    reward_merkle_state.reward_index = reward_index;
+    reward_merkle_state.reward_mint = reward_info.token_mint;
+    reward_merkle_state.reward_vault = reward_info.token_vault;
+    reward_merkle_state.merkle_root = merkle_root;
+    reward_merkle_state.total_amount = total_amount;
+
+    emit!(RewardMerkleRootEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        reward_index,
+        merkle_root,
+        total_amount,
+    });
+
+    Ok(())
+}
+
+/// Replace the unclaimed amount of an ended reward by the snapshot total. The snapshot is bound to
+/// the `reward_claimed` of the pool when it was taken, a position collecting in between is paid
+/// out of an amount the snapshot still owes it, so the snapshot must be taken again.
+fn consolidate_reward(
+    reward_info: &mut RewardInfo,
+    snapshot_reward_claimed: u64,
+    total_amount: u64,
+    vault_amount: u64,
+) -> Result<()> {
+    require_eq!(
+        reward_info.reward_claimed,
+        snapshot_reward_claimed,
+        ErrorCode::RewardClaimedSinceSnapshot
+    );
+    // The snapshot can't promise more than what is owed by the pool and held by the vault
+    let unclaimed = reward_info
+        .reward_total_emissioned
+        .checked_sub(reward_info.reward_claimed)
+        .ok_or(ErrorCode::MathOverflow)?;
+    require_gte!(unclaimed, total_amount);
+    require_gte!(vault_amount, total_amount);
+
+    // From now on the unclaimed amount of the pool is the snapshot total, rounding dust left in
+    // the vault can be collected by the funder with `collect_remaining_rewards`.
+    reward_info.reward_claimed = reward_info
+        .reward_total_emissioned
+        .checked_sub(total_amount)
+        .ok_or(ErrorCode::MathOverflow)?;
+    reward_info.reward_state = RewardState::Consolidated as u8;
+    Ok(())
+}
+
+#[cfg(test)]
+mod set_reward_merkle_root_test {
+    use super::*;
+
+    #[test]
+    fn collect_after_snapshot_test() {
+        let mut pool_state = PoolState::default();
+        pool_state.reward_infos[0].reward_state = RewardState::Ended as u8;
+        pool_state.reward_infos[0].reward_total_emissioned = 1_000;
+        pool_state.reward_infos[0].reward_claimed = 100;
+        // the snapshot owes 900, then a position collects 50 of its share before the root is set
+        let snapshot_reward_claimed = pool_state.reward_infos[0].reward_claimed;
+        pool_state.add_reward_clamed(0, 50).unwrap();
+        assert_eq!(
+            consolidate_reward(
+                &mut pool_state.reward_infos[0],
+                snapshot_reward_claimed,
+                900,
+                900
+            )
+            .unwrap_err(),
+            ErrorCode::RewardClaimedSinceSnapshot.into()
+        );
+        assert!(!pool_state.reward_infos[0].consolidated());
+
+        // a snapshot taken after the collect owes 850
+        let snapshot_reward_claimed = pool_state.reward_infos[0].reward_claimed;
+        consolidate_reward(
+            &mut pool_state.reward_infos[0],
+            snapshot_reward_claimed,
+            850,
+            850,
+        )
+        .unwrap();
+        let reward_claimed = pool_state.reward_infos[0].reward_claimed;
+        assert_eq!(reward_claimed, 150);
+        assert!(pool_state.reward_infos[0].consolidated());
+    }
+}
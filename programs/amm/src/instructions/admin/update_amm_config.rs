@@ -21,6 +21,10 @@ pub fn update_amm_config(ctx: Context<UpdateAmmConfig>, param: u8, value: u32) -
         Some(1) => update_protocol_fee_rate(amm_config, value),
         Some(2) => update_fund_fee_rate(amm_config, value),
         Some(3) => {
+            require!(
+                !amm_config.two_step_owner_transfer,
+                ErrorCode::OwnerTransferNotAccepted
+            );
             let new_owner = *ctx.remaining_accounts.iter().next().unwrap().key;
             set_new_owner(amm_config, new_owner);
         }
@@ -28,6 +32,8 @@ pub fn update_amm_config(ctx: Context<UpdateAmmConfig>, param: u8, value: u32) -
             let new_fund_owner = *ctx.remaining_accounts.iter().next().unwrap().key;
             set_new_fund_owner(amm_config, new_fund_owner);
         }
+        Some(5) => amm_config.max_initial_tick_deviation = value,
+        Some(6) => amm_config.swap_delay_after_create = value,
         _ => return err!(ErrorCode::InvalidUpdateConfigFlag),
     }
 
@@ -39,6 +45,30 @@ pub fn update_amm_config(ctx: Context<UpdateAmmConfig>, param: u8, value: u32) -
         tick_spacing: amm_config.tick_spacing,
         fund_fee_rate: amm_config.fund_fee_rate,
         fund_owner: amm_config.fund_owner,
+        pool_creation_fee: amm_config.pool_creation_fee,
+    });
+
+    Ok(())
+}
+
+/// Set the lamports the creator of a pool pays to the fund owner, a u64 doesn't fit the value of
+/// `update_amm_config`
+pub fn update_pool_creation_fee(
+    ctx: Context<UpdateAmmConfig>,
+    pool_creation_fee: u64,
+) -> Result<()> {
+    let amm_config = &mut ctx.accounts.amm_config;
+    amm_config.pool_creation_fee = pool_creation_fee;
+
+    emit!(ConfigChangeEvent {
+        index: amm_config.index,
+        owner: amm_config.owner,
+        trade_fee_rate: amm_config.trade_fee_rate,
+        protocol_fee_rate: amm_config.protocol_fee_rate,
+        tick_spacing: amm_config.tick_spacing,
+        fund_fee_rate: amm_config.fund_fee_rate,
+        fund_owner: amm_config.fund_owner,
+        pool_creation_fee: amm_config.pool_creation_fee,
     });
 
     Ok(())
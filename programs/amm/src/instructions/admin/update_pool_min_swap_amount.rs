@@ -0,0 +1,22 @@
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct UpdatePoolMinSwapAmount<'info> {
+    #[account(
+        address = crate::admin::id()
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+}
+
+pub fn update_pool_min_swap_amount(
+    ctx: Context<UpdatePoolMinSwapAmount>,
+    min_swap_amount_in: u64,
+) -> Result<()> {
+    let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.min_swap_amount_in = min_swap_amount_in;
+    Ok(())
+}
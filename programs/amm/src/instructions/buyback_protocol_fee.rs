@@ -0,0 +1,492 @@
+use crate::decrease_liquidity::check_unclaimed_fees_and_vault;
+use crate::error::ErrorCode;
+use crate::states::*;
+use crate::swap::swap_internal;
+use crate::util::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+#[derive(Accounts)]
+pub struct BuybackProtocolFee<'info> {
+    /// Anyone can crank the buyback
+    pub payer: Signer<'info>,
+
+    /// The factory state to read protocol fees
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// The pool whose protocol fees are converted
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Stores the buyback config and accounting of the pool
+    #[account(
+        mut,
+        seeds = [
+            BUYBACK_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        bump = buyback_state.bump,
+    )]
+    pub buyback_state: Box<Account<'info, BuybackState>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of buyback token
+    #[account(
+        address = buyback_state.buyback_mint
+    )]
+    pub buyback_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account receiving the buyback token
+    #[account(
+        mut,
+        address = buyback_state.recipient_token_account
+    )]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The program account for the most recent oracle observation
+    #[account(mut, address = pool_state.load()?.observation_key)]
+    pub observation_state: AccountLoader<'info, ObservationState>,
+
+    /// SPL program for token transfers
+    pub token_program: Program<'info, Token>,
+
+    /// SPL program 2022 for token transfers
+    pub token_program_2022: Program<'info, Token2022>,
+    // remaining accounts
+    // tickarray_bitmap_extension: must add account if need regardless the sequence
+    // tick_array_account_1
+    // tick_array_account_2
+    // tick_array_account_...
+}
+
+pub fn buyback_protocol_fee<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, BuybackProtocolFee<'info>>,
+) -> Result<()> {
+    let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+
+    let zero_for_one;
+    let amount_in;
+    let amount_out;
+    {
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        pool_state.lock_reentrancy()?;
+        require_gt!(block_timestamp, pool_state.open_time);
+        // fee rebate rewards take their share of the protocol fees first
+        pool_state.update_reward_infos(block_timestamp)?;
+
+        let observation_state = &mut ctx.accounts.observation_state.load_mut()?;
+        let (crank_zero_for_one, amount_specified, sqrt_price_limit_x64) = begin_crank(
+            pool_state,
+            &ctx.accounts.buyback_state,
+            observation_state,
+            block_timestamp,
+        )?;
+        zero_for_one = crank_zero_for_one;
+
+        let mut tickarray_bitmap_extension = None;
+        let tick_array_states = &mut VecDeque::new();
+
+        let tick_array_bitmap_extension_key = TickArrayBitmapExtension::key(pool_state.key());
+        for account_info in ctx.remaining_accounts.into_iter() {
+            if account_info.key().eq(&tick_array_bitmap_extension_key) {
+                tickarray_bitmap_extension = Some(
+                    *(AccountLoader::<TickArrayBitmapExtension>::try_from(account_info)?
+                        .load()?
+                        .deref()),
+                );
+                continue;
+            }
+            tick_array_states.push_back(AccountLoad::load_data_mut(account_info)?);
+        }
+
+        let (amount_0, amount_1) = swap_internal(
+            &ctx.accounts.amm_config,
+            pool_state,
+            tick_array_states,
+            observation_state,
+            &tickarray_bitmap_extension,
+            amount_specified,
+            sqrt_price_limit_x64,
+            zero_for_one,
+            true,
+            oracle::block_timestamp(),
+        )?;
+        (amount_in, amount_out) = end_crank(
+            pool_state,
+            zero_for_one,
+            amount_specified,
+            amount_0,
+            amount_1,
+        )?;
+    }
+
+    let output_vault = if zero_for_one {
+        &ctx.accounts.token_vault_1
+    } else {
+        &ctx.accounts.token_vault_0
+    };
+    if output_vault.amount <= amount_out {
+        // freeze pool, disable all instructions
+        ctx.accounts.pool_state.load_mut()?.set_status(255);
+    }
+    transfer_from_pool_vault_to_user(
+        &ctx.accounts.pool_state,
+        &output_vault.to_account_info(),
+        &ctx.accounts.recipient_token_account.to_account_info(),
+        Some(ctx.accounts.buyback_mint.clone()),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.to_account_info()),
+        amount_out,
+    )?;
+
+    check_unclaimed_fees_and_vault(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.token_vault_0.to_account_info(),
+        &ctx.accounts.token_vault_1.to_account_info(),
+    )?;
+    ctx.accounts.pool_state.load_mut()?.unlock_reentrancy();
+
+    let buyback_state = &mut ctx.accounts.buyback_state;
+    buyback_state.total_amount_in = buyback_state
+        .total_amount_in
+        .checked_add(amount_in)
+        .ok_or(ErrorCode::MathOverflow)?;
+    buyback_state.total_amount_out = buyback_state
+        .total_amount_out
+        .checked_add(amount_out)
+        .ok_or(ErrorCode::MathOverflow)?;
+    buyback_state.crank_count = buyback_state
+        .crank_count
+        .checked_add(1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    buyback_state.last_crank_time = block_timestamp;
+
+    emit!(BuybackEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        cranker: ctx.accounts.payer.key(),
+        buyback_mint: buyback_state.buyback_mint,
+        recipient_token_account: buyback_state.recipient_token_account,
+        amount_in,
+        amount_out,
+        sqrt_price_x64: ctx.accounts.pool_state.load()?.sqrt_price_x64,
+    });
+
+    Ok(())
+}
+
+/// Check the crank can run and take the protocol fees it sells out of the pool, returns the swap
+/// direction, the amount sold and the price limit. The limit is bounded by the admin range and
+/// by the twap, a price moved right before the crank can't be sold into.
+fn begin_crank(
+    pool_state: &mut PoolState,
+    buyback_state: &BuybackState,
+    observation_state: &ObservationState,
+    block_timestamp: u64,
+) -> Result<(bool, u64, u128)> {
+    // a paused or frozen pool can't be swapped by the crank either
+    require!(
+        pool_state.get_status_by_bit(PoolStatusBitIndex::Swap),
+        ErrorCode::NotApproved
+    );
+    buyback_state.check_crank_interval(block_timestamp)?;
+    let zero_for_one = buyback_state.zero_for_one(pool_state.token_mint_1);
+    let twap_tick = observation_state.average_tick(
+        block_timestamp as u32,
+        pool_state.tick_current,
+        buyback_state.twap_period,
+    )?;
+    let sqrt_price_limit_x64 = buyback_state
+        .crank_sqrt_price_limit_x64(zero_for_one, pool_state.sqrt_price_x64, twap_tick)?
+        .ok_or(ErrorCode::BuybackPriceOutOfRange)?;
+
+    // only the protocol fees of the token sold are swapped, they already sit in the vault
+    let amount_specified = if zero_for_one {
+        pool_state.protocol_fees_token_0
+    } else {
+        pool_state.protocol_fees_token_1
+    }
+    .min(buyback_state.max_amount_in_per_crank);
+    require_gt!(amount_specified, 0, ErrorCode::TooSmallInputOrOutputAmount);
+    if zero_for_one {
+        pool_state.protocol_fees_token_0 = pool_state
+            .protocol_fees_token_0
+            .checked_sub(amount_specified)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+    } else {
+        pool_state.protocol_fees_token_1 = pool_state
+            .protocol_fees_token_1
+            .checked_sub(amount_specified)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+    }
+    Ok((zero_for_one, amount_specified, sqrt_price_limit_x64))
+}
+
+/// Give the protocol fees the swap didn't use back to the pool, returns the amounts in and out
+fn end_crank(
+    pool_state: &mut PoolState,
+    zero_for_one: bool,
+    amount_specified: u64,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<(u64, u64)> {
+    let (amount_in, amount_out) = if zero_for_one {
+        (amount_0, amount_1)
+    } else {
+        (amount_1, amount_0)
+    };
+    require!(
+        amount_in != 0 && amount_out != 0,
+        ErrorCode::TooSmallInputOrOutputAmount
+    );
+
+    // the swap stops at the price limit, the part not swapped stays protocol fees
+    let amount_remaining = amount_specified
+        .checked_sub(amount_in)
+        .ok_or(ErrorCode::MathOverflow)?;
+    if zero_for_one {
+        pool_state.protocol_fees_token_0 = pool_state
+            .protocol_fees_token_0
+            .checked_add(amount_remaining)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+    } else {
+        pool_state.protocol_fees_token_1 = pool_state
+            .protocol_fees_token_1
+            .checked_add(amount_remaining)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+    }
+    Ok((amount_in, amount_out))
+}
+
+#[cfg(test)]
+mod buyback_protocol_fee_test {
+    use super::*;
+    use crate::libraries::tick_math;
+    use crate::states::pool_test::build_pool;
+    use crate::states::tick_array_test::{build_tick, build_tick_array_with_tick_states};
+    use std::cell::RefCell;
+
+    const NOW: u64 = 1_000_000;
+    const LIQUIDITY: u128 = 1_000_000_000_000;
+    const PROTOCOL_FEES: u64 = 1_000_000_000_000;
+
+    /// A pool at `tick_current` with liquidity over [0, 590) and protocol fees of token_0, it was
+    /// at tick 300 for the hour before the crank. The buyback sells token_0 for token_1.
+    fn build_crank(
+        tick_current: i32,
+    ) -> (
+        AmmConfig,
+        RefCell<PoolState>,
+        RefCell<TickArrayState>,
+        RefCell<ObservationState>,
+        BuybackState,
+    ) {
+        let tick_spacing = 10;
+        let amm_config = AmmConfig {
+            trade_fee_rate: 1000,
+            tick_spacing,
+            ..Default::default()
+        };
+        let pool_state = build_pool(
+            tick_current,
+            tick_spacing,
+            tick_math::get_sqrt_price_at_tick(tick_current).unwrap(),
+            LIQUIDITY,
+        );
+        pool_state.borrow_mut().protocol_fees_token_0 = PROTOCOL_FEES;
+        let tick_array = build_tick_array_with_tick_states(
+            pool_state.borrow().key(),
+            0,
+            tick_spacing,
+            vec![
+                build_tick(0, LIQUIDITY, LIQUIDITY as i128).take(),
+                build_tick(590, LIQUIDITY, -(LIQUIDITY as i128)).take(),
+            ],
+        );
+        pool_state
+            .borrow_mut()
+            .flip_tick_array_bit(None, 0)
+            .unwrap();
+        let observation_state = RefCell::new(ObservationState::default());
+        {
+            let mut observation_state = observation_state.borrow_mut();
+            observation_state.pool_id = pool_state.borrow().key();
+            observation_state.update((NOW - 3600) as u32, 300);
+            observation_state.update((NOW - 10) as u32, 300);
+        }
+        let buyback_state = BuybackState {
+            buyback_mint: pool_state.borrow().token_mint_1,
+            min_sqrt_price_x64: tick_math::get_sqrt_price_at_tick(-1000).unwrap(),
+            max_sqrt_price_x64: tick_math::get_sqrt_price_at_tick(1000).unwrap(),
+            max_amount_in_per_crank: PROTOCOL_FEES,
+            twap_period: 1800,
+            max_twap_tick_deviation: 100,
+            min_crank_interval: 600,
+            ..Default::default()
+        };
+        (
+            amm_config,
+            pool_state,
+            tick_array,
+            observation_state,
+            buyback_state,
+        )
+    }
+
+    /// Run the crank as the instruction does, without the token transfers
+    fn crank(
+        amm_config: &AmmConfig,
+        pool_state: &RefCell<PoolState>,
+        tick_array: &RefCell<TickArrayState>,
+        observation_state: &RefCell<ObservationState>,
+        buyback_state: &BuybackState,
+    ) -> Result<(u64, u64)> {
+        let pool_state = &mut pool_state.borrow_mut();
+        let observation_state = &mut observation_state.borrow_mut();
+        let (zero_for_one, amount_specified, sqrt_price_limit_x64) =
+            begin_crank(pool_state, buyback_state, observation_state, NOW)?;
+        let (amount_0, amount_1) = swap_internal(
+            amm_config,
+            pool_state,
+            &mut VecDeque::from([tick_array.borrow_mut()]),
+            observation_state,
+            &None,
+            amount_specified,
+            sqrt_price_limit_x64,
+            zero_for_one,
+            true,
+            NOW as u32,
+        )?;
+        end_crank(
+            pool_state,
+            zero_for_one,
+            amount_specified,
+            amount_0,
+            amount_1,
+        )
+    }
+
+    #[test]
+    fn crank_stops_at_twap_band_test() {
+        let (amm_config, pool_state, tick_array, observation_state, buyback_state) =
+            build_crank(300);
+        let (amount_in, amount_out) = crank(
+            &amm_config,
+            &pool_state,
+            &tick_array,
+            &observation_state,
+            &buyback_state,
+        )
+        .unwrap();
+        assert!(amount_in > 0 && amount_out > 0);
+        let pool_state = pool_state.borrow();
+        // the fees are more than the band can absorb, the swap stops 100 ticks below the twap
+        // and the fees not sold stay protocol fees
+        let sqrt_price_x64 = pool_state.sqrt_price_x64;
+        assert_eq!(
+            sqrt_price_x64,
+            tick_math::get_sqrt_price_at_tick(200).unwrap()
+        );
+        let protocol_fees_token_0 = pool_state.protocol_fees_token_0;
+        assert_eq!(protocol_fees_token_0, PROTOCOL_FEES - amount_in);
+    }
+
+    #[test]
+    fn crank_after_price_pushed_down_test() {
+        // a sandwich pushed the price below the twap band right before the crank
+        let (amm_config, pool_state, tick_array, observation_state, buyback_state) =
+            build_crank(150);
+        assert_eq!(
+            crank(
+                &amm_config,
+                &pool_state,
+                &tick_array,
+                &observation_state,
+                &buyback_state,
+            )
+            .unwrap_err(),
+            ErrorCode::BuybackPriceOutOfRange.into()
+        );
+        let protocol_fees_token_0 = pool_state.borrow().protocol_fees_token_0;
+        assert_eq!(protocol_fees_token_0, PROTOCOL_FEES);
+    }
+
+    #[test]
+    fn crank_with_swap_disabled_test() {
+        let (amm_config, pool_state, tick_array, observation_state, buyback_state) =
+            build_crank(300);
+        pool_state
+            .borrow_mut()
+            .set_status_by_bit(PoolStatusBitIndex::Swap, PoolStatusBitFlag::Disable);
+        assert_eq!(
+            crank(
+                &amm_config,
+                &pool_state,
+                &tick_array,
+                &observation_state,
+                &buyback_state,
+            )
+            .unwrap_err(),
+            ErrorCode::NotApproved.into()
+        );
+        let protocol_fees_token_0 = pool_state.borrow().protocol_fees_token_0;
+        assert_eq!(protocol_fees_token_0, PROTOCOL_FEES);
+
+        // a frozen pool has every bit disabled
+        pool_state.borrow_mut().set_status(255);
+        assert_eq!(
+            crank(
+                &amm_config,
+                &pool_state,
+                &tick_array,
+                &observation_state,
+                &buyback_state,
+            )
+            .unwrap_err(),
+            ErrorCode::NotApproved.into()
+        );
+    }
+
+    #[test]
+    fn crank_before_interval_test() {
+        let (amm_config, pool_state, tick_array, observation_state, mut buyback_state) =
+            build_crank(300);
+        buyback_state.last_crank_time = NOW - 599;
+        assert_eq!(
+            crank(
+                &amm_config,
+                &pool_state,
+                &tick_array,
+                &observation_state,
+                &buyback_state,
+            )
+            .unwrap_err(),
+            ErrorCode::BuybackCrankTooEarly.into()
+        );
+        buyback_state.last_crank_time = NOW - 600;
+        assert!(crank(
+            &amm_config,
+            &pool_state,
+            &tick_array,
+            &observation_state,
+            &buyback_state,
+        )
+        .is_ok());
+    }
+}
@@ -0,0 +1,114 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use crate::util::transfer_from_pool_vault_to_user;
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+
+#[derive(Accounts)]
+pub struct ClaimMerkleReward<'info> {
+    /// The claimant in the snapshot, pays to create the claim record
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The snapshot of owed rewards
+    #[account(
+        mut,
+        constraint = reward_merkle_state.pool_id == pool_state.key()
+    )]
+    pub reward_merkle_state: Account<'info, RewardMerkleState>,
+
+    /// Record of the claim, can only be created once for a claimant
+    #[account(
+        init,
+        seeds = [
+            REWARD_MERKLE_CLAIM_SEED.as_bytes(),
+            reward_merkle_state.key().as_ref(),
+            claimant.key().as_ref(),
+        ],
+        bump,
+        payer = claimant,
+        space = RewardMerkleClaimState::LEN
+    )]
+    pub claim_state: Account<'info, RewardMerkleClaimState>,
+
+    /// Reward vault of the pool
+    #[account(
+        mut,
+        address = reward_merkle_state.reward_vault
+    )]
+    pub reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The destination token account for receive reward
+    #[account(
+        mut,
+        token::mint = reward_token_vault.mint
+    )]
+    pub recipient_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of reward token vault
+    #[account(
+        address = reward_token_vault.mint
+    )]
+    pub reward_vault_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// SPL program to transfer out tokens
+    pub token_program: Program<'info, Token>,
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn claim_merkle_reward(
+    ctx: Context<ClaimMerkleReward>,
+    amount: u64,
+    proof: Vec<[u8; 32]>,
+) -> Result<()> {
+    let reward_merkle_state = &mut ctx.accounts.reward_merkle_state;
+    let claimant = ctx.accounts.claimant.key();
+    require!(
+        RewardMerkleState::verify(
+            &proof,
+            &reward_merkle_state.merkle_root,
+            RewardMerkleState::leaf(&claimant, amount),
+        ),
+        ErrorCode::InvalidMerkleProof
+    );
+    reward_merkle_state.record_claim(amount)?;
+
+    let claim_state = &mut ctx.accounts.claim_state;
+    claim_state.bump = ctx.bumps.claim_state;
+    claim_state.reward_merkle = reward_merkle_state.key();
+    claim_state.claimant = claimant;
+    claim_state.amount = amount;
+
+    let reward_index = reward_merkle_state.reward_index;
+    {
+        let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+        pool_state.check_unlocked()?;
+        pool_state.add_reward_clamed(reward_index as usize, amount)?;
+    }
+
+    transfer_from_pool_vault_to_user(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.reward_token_vault.to_account_info(),
+        &ctx.accounts.recipient_token_account.to_account_info(),
+        Some(ctx.accounts.reward_vault_mint.clone()),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.to_account_info()),
+        amount,
+    )?;
+
+    emit!(ClaimMerkleRewardEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        reward_index,
+        claimant,
+        amount,
+    });
+
+    Ok(())
+}
@@ -14,7 +14,7 @@ pub struct ClosePosition<'info> {
     /// Mint address bound to the personal position.
     #[account(
       mut,
-      address = personal_position.nft_mint,
+      address = personal_position.load()?.nft_mint,
       mint::token_program = token_program,
     )]
     pub position_nft_mint: Box<InterfaceAccount<'info, Mint>>,
@@ -30,12 +30,12 @@ pub struct ClosePosition<'info> {
     pub position_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
-        mut, 
+        mut,
         seeds = [POSITION_SEED.as_bytes(), position_nft_mint.key().as_ref()],
         bump,
         close = nft_owner
     )]
-    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
 
     /// System program to close the position state account
     pub system_program: Program<'info, System>,
@@ -47,60 +47,147 @@ pub struct ClosePosition<'info> {
 pub fn close_position<'a, 'b, 'c, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, ClosePosition<'info>>,
 ) -> Result<()> {
-    if ctx.accounts.personal_position.liquidity != 0
-        || ctx.accounts.personal_position.token_fees_owed_0 != 0
-        || ctx.accounts.personal_position.token_fees_owed_1 != 0
     {
-        msg!(
-            "remaing liquidity:{},token_fees_owed_0:{},token_fees_owed_1:{}",
-            ctx.accounts.personal_position.liquidity,
-            ctx.accounts.personal_position.token_fees_owed_0,
-            ctx.accounts.personal_position.token_fees_owed_1
-        );
-        return err!(ErrorCode::ClosePositionErr);
-    }
-
-    for i in 0..ctx.accounts.personal_position.reward_infos.len() {
-        if ctx.accounts.personal_position.reward_infos[i].reward_amount_owed != 0 {
+        let personal_position = ctx.accounts.personal_position.load()?;
+        if personal_position.liquidity != 0
+            || personal_position.token_fees_owed_0 != 0
+            || personal_position.token_fees_owed_1 != 0
+        {
             msg!(
-                "remaing reward index:{},amount:{}",
-                i,
-                ctx.accounts.personal_position.reward_infos[i].reward_amount_owed,
+                "remaing liquidity:{},token_fees_owed_0:{},token_fees_owed_1:{}",
+                { personal_position.liquidity },
+                { personal_position.token_fees_owed_0 },
+                { personal_position.token_fees_owed_1 }
             );
             return err!(ErrorCode::ClosePositionErr);
         }
+
+        for i in 0..personal_position.reward_infos.len() {
+            let reward_amount_owed = personal_position.reward_infos[i].reward_amount_owed;
+            if reward_amount_owed != 0 {
+                msg!("remaing reward index:{},amount:{}", i, reward_amount_owed,);
+                return err!(ErrorCode::ClosePositionErr);
+            }
+        }
     }
 
-    let token_program = ctx.accounts.token_program.to_account_info();
-    let position_nft_mint = ctx.accounts.position_nft_mint.to_account_info();
-    let personal_nft_account = ctx.accounts.position_nft_account.to_account_info();
-    burn(
+    burn_position_nft(
         &ctx.accounts.nft_owner,
-        &position_nft_mint,
-        &personal_nft_account,
-        &token_program,
+        &ctx.accounts.position_nft_mint.to_account_info(),
+        &ctx.accounts.position_nft_account.to_account_info(),
+        &ctx.accounts.personal_position,
+        &ctx.accounts.token_program.to_account_info(),
+    )
+}
+
+/// Burn the position nft and close its token account, and its mint if it belongs to token2022
+fn burn_position_nft<'info>(
+    nft_owner: &Signer<'info>,
+    position_nft_mint: &AccountInfo<'info>,
+    personal_nft_account: &AccountInfo<'info>,
+    personal_position: &AccountLoader<'info, PersonalPositionState>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    burn(
+        nft_owner,
+        position_nft_mint,
+        personal_nft_account,
+        token_program,
         &[],
         1,
     )?;
 
     // close use nft token account
     close_spl_account(
-        &ctx.accounts.nft_owner,
-        &ctx.accounts.nft_owner,
-        &personal_nft_account,
-        &token_program,
+        nft_owner,
+        nft_owner,
+        personal_nft_account,
+        token_program,
         &[],
     )?;
 
     if *position_nft_mint.owner == spl_token_2022::id() {
         // close nft mint account
         close_spl_account(
-            &ctx.accounts.personal_position.to_account_info(),
-            &ctx.accounts.nft_owner,
-            &position_nft_mint,
-            &token_program,
-            &[&ctx.accounts.personal_position.seeds()],
+            &personal_position.to_account_info(),
+            nft_owner,
+            position_nft_mint,
+            token_program,
+            &[&personal_position.load()?.seeds()],
         )?;
     }
     Ok(())
 }
+
+#[derive(Accounts)]
+pub struct ClosePositionV2<'info> {
+    /// The position nft owner
+    #[account(mut)]
+    pub nft_owner: Signer<'info>,
+
+    /// Mint address bound to the personal position.
+    #[account(
+      mut,
+      address = personal_position.load()?.nft_mint,
+      mint::token_program = token_program,
+    )]
+    pub position_nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// User token account where position NFT be minted to
+    #[account(
+        mut,
+        token::mint = position_nft_mint,
+        token::authority = nft_owner,
+        constraint = position_nft_account.amount == 1,
+        token::token_program = token_program,
+    )]
+    pub position_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Closed only if it is empty, see close_position_v2
+    #[account(
+        mut,
+        seeds = [POSITION_SEED.as_bytes(), position_nft_mint.key().as_ref()],
+        bump,
+    )]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// System program to close the position state account
+    pub system_program: Program<'info, System>,
+
+    /// Token/Token2022 program to close token/mint account
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+/// Close the position like close_position. With `close_if_empty` a position that still has
+/// liquidity, fees or rewards is left intact instead of failing, so the close can follow a
+/// decrease in the same transaction whatever amount it removes.
+pub fn close_position_v2<'a, 'b, 'c, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, ClosePositionV2<'info>>,
+    close_if_empty: bool,
+) -> Result<()> {
+    let personal_position = &ctx.accounts.personal_position;
+    {
+        let personal_position = personal_position.load()?;
+        if !personal_position.is_empty() {
+            msg!(
+                "remaing liquidity:{},token_fees_owed_0:{},token_fees_owed_1:{}",
+                { personal_position.liquidity },
+                { personal_position.token_fees_owed_0 },
+                { personal_position.token_fees_owed_1 }
+            );
+            if close_if_empty {
+                return Ok(());
+            }
+            return err!(ErrorCode::ClosePositionErr);
+        }
+    }
+
+    burn_position_nft(
+        &ctx.accounts.nft_owner,
+        &ctx.accounts.position_nft_mint.to_account_info(),
+        &ctx.accounts.position_nft_account.to_account_info(),
+        personal_position,
+        &ctx.accounts.token_program.to_account_info(),
+    )?;
+    personal_position.close(ctx.accounts.nft_owner.to_account_info())
+}
@@ -76,12 +76,17 @@ fn get_remaining_reward_amount(
 ) -> Result<u64> {
     let current_timestamp = u64::try_from(Clock::get()?.unix_timestamp).unwrap();
     let mut pool_state = pool_state_loader.load_mut()?;
+    pool_state.check_unlocked()?;
     pool_state.update_reward_infos(current_timestamp)?;
 
     let reward_info = pool_state.reward_infos[reward_index as usize];
     if !reward_info.initialized() {
         return err!(ErrorCode::UnInitializedRewardInfo);
     }
+    // the reward vault of a fee rebate reward is the pool vault
+    if pool_state.is_fee_rebate_reward(reward_index as usize) {
+        return err!(ErrorCode::FeeRebateReward);
+    }
     require_eq!(
         reward_info.last_update_time,
         reward_info.end_time,
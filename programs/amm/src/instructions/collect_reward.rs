@@ -0,0 +1,208 @@
+use super::decrease_liquidity::collect_rewards;
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Token2022, TokenAccount};
+
+#[derive(Accounts)]
+pub struct CollectReward<'info> {
+    /// The position owner or delegated authority
+    pub nft_owner: Signer<'info>,
+
+    /// The token account for the tokenized position
+    #[account(
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Collect the rewards of this position
+    #[account(mut, constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
+    )]
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// The tick array of the position's lower tick, only read
+    #[account(constraint = tick_array_lower.load()?.pool_id == pool_state.key())]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// The tick array of the position's upper tick, only read
+    #[account(constraint = tick_array_upper.load()?.pool_id == pool_state.key())]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    /// SPL program to transfer out tokens
+    pub token_program: Program<'info, Token>,
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+    // remaining account
+    // for every initialized reward: reward_token_vault, recipient_token_account, reward_vault_mint
+}
+
+/// Accrue the pool rewards up to `timestamp` and add the position's share since its last update
+/// to its owed amounts. The liquidity and the ticks are not changed.
+pub fn accrue_position_rewards(
+    pool_state: &mut PoolState,
+    protocol_position: &mut ProtocolPositionState,
+    personal_position: &mut PersonalPositionState,
+    tick_lower: &TickState,
+    tick_upper: &TickState,
+    timestamp: u64,
+) -> Result<()> {
+    let updated_reward_infos = pool_state.update_reward_infos(timestamp)?;
+    let reward_growths_inside = get_reward_growths_inside(
+        tick_lower,
+        tick_upper,
+        pool_state.tick_current,
+        &updated_reward_infos,
+    )?;
+    protocol_position.update_reward_growths_inside(reward_growths_inside);
+    personal_position.update_rewards(reward_growths_inside, true)?;
+    personal_position.record_action(timestamp);
+    Ok(())
+}
+
+pub fn collect_reward<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CollectReward<'info>>,
+) -> Result<()> {
+    let personal_position = &mut ctx.accounts.personal_position.load_mut()?;
+    {
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        require!(
+            pool_state.get_status_by_bit(PoolStatusBitIndex::CollectReward),
+            ErrorCode::NotApproved
+        );
+        // held until the rewards are transferred
+        pool_state.lock_reentrancy()?;
+
+        let tick_array_lower = ctx.accounts.tick_array_lower.load()?;
+        let tick_array_upper = ctx.accounts.tick_array_upper.load()?;
+        let tick_lower = tick_array_lower.ticks[tick_array_lower.get_tick_offset_in_array(
+            personal_position.tick_lower_index,
+            pool_state.tick_spacing,
+        )?];
+        let tick_upper = tick_array_upper.ticks[tick_array_upper.get_tick_offset_in_array(
+            personal_position.tick_upper_index,
+            pool_state.tick_spacing,
+        )?];
+        accrue_position_rewards(
+            pool_state,
+            &mut ctx.accounts.protocol_position.load_mut()?,
+            personal_position,
+            &tick_lower,
+            &tick_upper,
+            Clock::get()?.unix_timestamp as u64,
+        )?;
+    }
+
+    let remaining_accounts: Vec<&'info AccountInfo<'info>> =
+        ctx.remaining_accounts.iter().collect();
+    let reward_amounts = collect_rewards(
+        &ctx.accounts.pool_state,
+        &remaining_accounts,
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.to_account_info()),
+        personal_position,
+        &ctx.accounts.nft_account.owner,
+        true,
+    )?;
+    emit!(CollectRewardEvent {
+        position_nft_mint: personal_position.nft_mint,
+        reward_amounts,
+    });
+
+    ctx.accounts.pool_state.load_mut()?.unlock_reentrancy();
+    Ok(())
+}
+
+#[cfg(test)]
+mod collect_reward_test {
+    use super::*;
+    use crate::libraries::fixed_point_64;
+    use crate::states::pool_test::build_pool;
+
+    #[test]
+    fn accrue_position_rewards_test() {
+        let pool_state_ref = build_pool(100, 10, fixed_point_64::Q64, 1_000);
+        let mut pool_state = pool_state_ref.borrow_mut();
+        pool_state.reward_infos[0].token_mint = Pubkey::new_unique();
+        pool_state.reward_infos[0].token_vault = Pubkey::new_unique();
+        pool_state.reward_infos[0].reward_state = RewardState::Opening as u8;
+        pool_state.reward_infos[0].open_time = 1_000;
+        pool_state.reward_infos[0].end_time = 2_000;
+        pool_state.reward_infos[0].last_update_time = 1_000;
+        // 100 tokens per second shared by the 1000 liquidity in range
+        pool_state.reward_infos[0].emissions_per_second_x64 = 100 * fixed_point_64::Q64;
+
+        let tick_lower = TickState {
+            tick: -100,
+            ..Default::default()
+        };
+        let tick_upper = TickState {
+            tick: 300,
+            ..Default::default()
+        };
+        let mut protocol_position = ProtocolPositionState::default();
+        // the position holds 400 of the 1000 liquidity in range
+        let mut personal_position = PersonalPositionState {
+            tick_lower_index: -100,
+            tick_upper_index: 300,
+            liquidity: 400,
+            ..Default::default()
+        };
+        personal_position.reward_infos[1].reward_amount_owed = 5;
+
+        accrue_position_rewards(
+            &mut pool_state,
+            &mut protocol_position,
+            &mut personal_position,
+            &tick_lower,
+            &tick_upper,
+            1_010,
+        )
+        .unwrap();
+        // 10 seconds emit 1000 tokens, 1 per unit of liquidity
+        let reward_infos = personal_position.reward_infos;
+        assert_eq!(reward_infos[0].reward_amount_owed, 400);
+        assert_eq!(reward_infos[0].growth_inside_last_x64, fixed_point_64::Q64);
+        let reward_growth_inside = protocol_position.reward_growth_inside;
+        assert_eq!(reward_growth_inside[0], fixed_point_64::Q64);
+        // the other slots are untouched
+        assert_eq!(reward_infos[1].reward_amount_owed, 5);
+        let last_updated_at = personal_position.last_updated_at;
+        assert_eq!(last_updated_at, 1_010);
+
+        // accruing again at the same time adds nothing
+        accrue_position_rewards(
+            &mut pool_state,
+            &mut protocol_position,
+            &mut personal_position,
+            &tick_lower,
+            &tick_upper,
+            1_010,
+        )
+        .unwrap();
+        let reward_amount_owed = personal_position.reward_infos[0].reward_amount_owed;
+        assert_eq!(reward_amount_owed, 400);
+        // the liquidity is not changed
+        let position_liquidity = personal_position.liquidity;
+        assert_eq!(position_liquidity, 400);
+        let liquidity = pool_state.liquidity;
+        assert_eq!(liquidity, 1_000);
+    }
+}
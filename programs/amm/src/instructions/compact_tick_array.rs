@@ -0,0 +1,30 @@
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct CompactTickArray<'info> {
+    /// The pool of the tick array, can't be compacted while an instruction of the pool runs
+    #[account(address = tick_array.load()?.pool_id)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The tick array whose uninitialized ticks are reset
+    #[account(mut)]
+    pub tick_array: AccountLoader<'info, TickArrayState>,
+}
+
+pub fn compact_tick_array<'a, 'b, 'c, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CompactTickArray<'info>>,
+) -> Result<()> {
+    ctx.accounts.pool_state.load()?.check_unlocked()?;
+    let mut tick_array = ctx.accounts.tick_array.load_mut()?;
+    let cleared_tick_count = tick_array.compact()?;
+
+    emit!(TickArrayCompactedEvent {
+        pool_state: tick_array.pool_id,
+        tick_array: ctx.accounts.tick_array.key(),
+        start_tick_index: tick_array.start_tick_index,
+        cleared_tick_count,
+    });
+
+    Ok(())
+}
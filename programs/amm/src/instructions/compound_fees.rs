@@ -0,0 +1,230 @@
+use super::add_liquidity_to_ticks;
+use super::increase_liquidity::calculate_latest_token_fees;
+use crate::error::ErrorCode;
+use crate::libraries::liquidity_math;
+use crate::states::*;
+use crate::util::AccountLoad;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+#[derive(Accounts)]
+pub struct CompoundFees<'info> {
+    /// The position owner
+    pub nft_owner: Signer<'info>,
+
+    /// The token account for the tokenized position
+    #[account(
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
+    )]
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// Compound the fees of this position
+    #[account(mut, constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// Stores init state for the lower tick
+    #[account(mut, constraint = tick_array_lower.load()?.pool_id == pool_state.key())]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// Stores init state for the upper tick
+    #[account(mut, constraint = tick_array_upper.load()?.pool_id == pool_state.key())]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+    // remaining account
+    // #[account(
+    //     seeds = [
+    //         POOL_TICK_ARRAY_BITMAP_SEED.as_bytes(),
+    //         pool_state.key().as_ref(),
+    //     ],
+    //     bump
+    // )]
+    // pub tick_array_bitmap: AccountLoader<'info, TickArrayBitmapExtension>,
+}
+
+/// The liquidity the fees owed to a position over [tick_lower_index, tick_upper_index] are worth
+/// at the current price, the part of the fees it doesn't use stays owed
+pub fn compound_liquidity(
+    sqrt_price_x64: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    fees_owed_0: u64,
+    fees_owed_1: u64,
+) -> Result<u128> {
+    let liquidity = liquidity_math::get_liquidity_from_amounts_at_ticks(
+        sqrt_price_x64,
+        tick_lower_index,
+        tick_upper_index,
+        fees_owed_0,
+        fees_owed_1,
+    )?;
+    if liquidity == 0 {
+        return err!(ErrorCode::NothingToCompound);
+    }
+    Ok(liquidity)
+}
+
+/// Add the fees owed to the position to its liquidity. The fee tokens already sit in the pool
+/// vaults, so nothing is transferred, the amounts are only moved from the fees owed to the
+/// position's liquidity.
+pub fn compound_fees<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CompoundFees<'info>>,
+    liquidity_min: u128,
+) -> Result<()> {
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    if !pool_state.get_status_by_bit(PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity) {
+        return err!(ErrorCode::NotApproved);
+    }
+    pool_state.lock_reentrancy()?;
+    let protocol_position = &mut ctx.accounts.protocol_position.load_mut()?;
+    let personal_position = &mut ctx.accounts.personal_position.load_mut()?;
+    let tick_lower = personal_position.tick_lower_index;
+    let tick_upper = personal_position.tick_upper_index;
+
+    // the fees owed up to now, computed the same way the liquidity change below updates the
+    // protocol position
+    let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = {
+        let tick_array_lower = ctx.accounts.tick_array_lower.load()?;
+        let tick_array_upper = ctx.accounts.tick_array_upper.load()?;
+        let tick_lower_state = tick_array_lower.ticks
+            [tick_array_lower.get_tick_offset_in_array(tick_lower, pool_state.tick_spacing)?];
+        let tick_upper_state = tick_array_upper.ticks
+            [tick_array_upper.get_tick_offset_in_array(tick_upper, pool_state.tick_spacing)?];
+        get_fee_growth_inside(
+            &tick_lower_state,
+            &tick_upper_state,
+            pool_state.tick_current,
+            pool_state.fee_growth_global_0_x64,
+            pool_state.fee_growth_global_1_x64,
+        )?
+    };
+    let fees_owed_0 = calculate_latest_token_fees(
+        personal_position.token_fees_owed_0,
+        personal_position.fee_growth_inside_0_last_x64,
+        fee_growth_inside_0_x64,
+        personal_position.liquidity,
+    );
+    let fees_owed_1 = calculate_latest_token_fees(
+        personal_position.token_fees_owed_1,
+        personal_position.fee_growth_inside_1_last_x64,
+        fee_growth_inside_1_x64,
+        personal_position.liquidity,
+    );
+
+    let liquidity = compound_liquidity(
+        pool_state.sqrt_price_x64,
+        tick_lower,
+        tick_upper,
+        fees_owed_0,
+        fees_owed_1,
+    )?;
+    require_gte!(liquidity, liquidity_min, ErrorCode::PriceSlippageCheck);
+
+    let use_tickarray_bitmap_extension =
+        pool_state.is_overflow_default_tickarray_bitmap(vec![tick_lower, tick_upper]);
+    let liquidity_before = pool_state.liquidity;
+    let (amount_0, amount_1) = add_liquidity_to_ticks(
+        &AccountLoad::<TickArrayState>::try_from(&ctx.accounts.tick_array_lower.to_account_info())?,
+        &AccountLoad::<TickArrayState>::try_from(&ctx.accounts.tick_array_upper.to_account_info())?,
+        protocol_position,
+        if use_tickarray_bitmap_extension {
+            require_keys_eq!(
+                ctx.remaining_accounts[0].key(),
+                TickArrayBitmapExtension::key(ctx.accounts.pool_state.key())
+            );
+            Some(&ctx.remaining_accounts[0])
+        } else {
+            None
+        },
+        pool_state,
+        liquidity,
+        tick_lower,
+        tick_upper,
+    )?;
+
+    personal_position.token_fees_owed_0 = fees_owed_0
+        .checked_sub(amount_0)
+        .ok_or(ErrorCode::MathOverflow)?;
+    personal_position.token_fees_owed_1 = fees_owed_1
+        .checked_sub(amount_1)
+        .ok_or(ErrorCode::MathOverflow)?;
+    personal_position.fee_growth_inside_0_last_x64 = protocol_position.fee_growth_inside_0_last_x64;
+    personal_position.fee_growth_inside_1_last_x64 = protocol_position.fee_growth_inside_1_last_x64;
+
+    // update rewards, must update before increase liquidity
+    personal_position.update_rewards(protocol_position.reward_growth_inside, true)?;
+    personal_position.liquidity = personal_position
+        .liquidity
+        .checked_add(liquidity)
+        .ok_or(ErrorCode::MathOverflow)?;
+    personal_position.record_action(Clock::get()?.unix_timestamp as u64);
+
+    emit!(LiquidityChangeEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        tick: pool_state.tick_current,
+        tick_lower,
+        tick_upper,
+        liquidity_before,
+        liquidity_after: pool_state.liquidity,
+    });
+    emit!(CompoundFeesEvent {
+        position_nft_mint: personal_position.nft_mint,
+        liquidity,
+        amount_0,
+        amount_1,
+    });
+
+    pool_state.unlock_reentrancy();
+    Ok(())
+}
+
+#[cfg(test)]
+mod compound_fees_test {
+    use super::*;
+    use crate::libraries::tick_math;
+
+    #[test]
+    fn compound_liquidity_test() {
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(100).unwrap();
+        let liquidity = compound_liquidity(sqrt_price_x64, -100, 300, 1_000, 5_000).unwrap();
+        // the liquidity costs no more than the fees owed, the token in excess stays owed
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            100,
+            sqrt_price_x64,
+            -100,
+            300,
+            i128::try_from(liquidity).unwrap(),
+        )
+        .unwrap();
+        assert!(amount_0 <= 1_000 && amount_1 <= 5_000);
+        assert!(amount_0 > 0 && amount_1 > 0);
+
+        // above the range only the token_1 fees can be compounded
+        let above = tick_math::get_sqrt_price_at_tick(400).unwrap();
+        assert!(compound_liquidity(above, -100, 300, 0, 5_000).unwrap() > 0);
+        assert_eq!(
+            compound_liquidity(above, -100, 300, 1_000, 0).unwrap_err(),
+            ErrorCode::NothingToCompound.into()
+        );
+        assert_eq!(
+            compound_liquidity(sqrt_price_x64, -100, 300, 0, 0).unwrap_err(),
+            ErrorCode::NothingToCompound.into()
+        );
+    }
+}
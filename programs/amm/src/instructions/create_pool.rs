@@ -2,6 +2,8 @@ use crate::error::ErrorCode;
 use crate::states::*;
 use crate::{libraries::tick_math, util};
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 // use solana_program::{program::invoke_signed, system_instruction};
 #[derive(Accounts)]
@@ -107,6 +109,9 @@ pub struct CreatePool<'info> {
     pub system_program: Program<'info, System>,
     /// Sysvar for program account
     pub rent: Sysvar<'info, Rent>,
+    // remaining account
+    // the instructions sysvar, if the config requires a price reference signed by its owner
+    // the fund owner of the config, writable, if the config charges a pool creation fee
 }
 
 pub fn create_pool(ctx: Context<CreatePool>, sqrt_price_x64: u128, open_time: u64) -> Result<()> {
@@ -121,6 +126,48 @@ pub fn create_pool(ctx: Context<CreatePool>, sqrt_price_x64: u128, open_time: u6
     let mut pool_state = ctx.accounts.pool_state.load_init()?;
 
     let tick = tick_math::get_tick_at_sqrt_price(sqrt_price_x64)?;
+    let amm_config = ctx.accounts.amm_config.as_ref();
+    if amm_config.max_initial_tick_deviation > 0 {
+        // the canonical pool PDA can't be taken by a creator picking an absurd price
+        let instructions_sysvar = ctx
+            .remaining_accounts
+            .get(0)
+            .ok_or(ErrorCode::InvalidPriceReference)?;
+        require_keys_eq!(
+            instructions_sysvar.key(),
+            sysvar::instructions::id(),
+            ErrorCode::InvalidPriceReference
+        );
+        let message = util::load_ed25519_message(instructions_sysvar, &amm_config.owner)?;
+        PoolPriceReference::from_bytes(&message)?.check_initial_price(
+            ctx.accounts.amm_config.key(),
+            ctx.accounts.token_mint_0.key(),
+            ctx.accounts.token_mint_1.key(),
+            sqrt_price_x64,
+            amm_config.max_initial_tick_deviation,
+            block_timestamp,
+        )?;
+    }
+    if amm_config.pool_creation_fee > 0 {
+        let pool_creation_fee_receiver =
+            find_pool_creation_fee_receiver(ctx.remaining_accounts, &amm_config.fund_owner)?;
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.pool_creator.to_account_info(),
+                    to: pool_creation_fee_receiver.clone(),
+                },
+            ),
+            amm_config.pool_creation_fee,
+        )?;
+    }
+    // swaps are held off for a while so the creator can add liquidity before snipers trade
+    let open_time = if amm_config.swap_delay_after_create > 0 {
+        block_timestamp + u64::from(amm_config.swap_delay_after_create)
+    } else {
+        0
+    };
     #[cfg(feature = "enable-log")]
     msg!(
         "create pool, init_price: {}, init_tick:{}",
@@ -137,7 +184,7 @@ pub fn create_pool(ctx: Context<CreatePool>, sqrt_price_x64: u128, open_time: u6
     pool_state.initialize(
         bump,
         sqrt_price_x64,
-        0,
+        open_time,
         tick,
         ctx.accounts.pool_creator.key(),
         ctx.accounts.token_vault_0.key(),
@@ -165,3 +212,85 @@ pub fn create_pool(ctx: Context<CreatePool>, sqrt_price_x64: u128, open_time: u6
     });
     Ok(())
 }
+
+/// The fund owner of the config among the remaining accounts, a writable wallet receiving the
+/// pool creation fee
+fn find_pool_creation_fee_receiver<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+    fund_owner: &Pubkey,
+) -> Result<&'a AccountInfo<'info>> {
+    let receiver = remaining_accounts
+        .iter()
+        .find(|account_info| account_info.key() == *fund_owner)
+        .ok_or(ErrorCode::InvalidPoolCreationFeeReceiver)?;
+    require!(
+        receiver.is_writable && *receiver.owner == anchor_lang::system_program::ID,
+        ErrorCode::InvalidPoolCreationFeeReceiver
+    );
+    Ok(receiver)
+}
+
+#[cfg(test)]
+mod create_pool_test {
+    use super::*;
+
+    #[test]
+    fn find_pool_creation_fee_receiver_test() {
+        let fund_owner = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        let system_program = anchor_lang::system_program::ID;
+        let token_program = anchor_spl::token::ID;
+        let (mut lamports_0, mut lamports_1) = (0, 0);
+        let (mut data_0, mut data_1) = (vec![], vec![]);
+        let other_account = AccountInfo::new(
+            &other,
+            false,
+            true,
+            &mut lamports_0,
+            &mut data_0,
+            &system_program,
+            false,
+            0,
+        );
+        let mut fund_owner_account = AccountInfo::new(
+            &fund_owner,
+            false,
+            true,
+            &mut lamports_1,
+            &mut data_1,
+            &system_program,
+            false,
+            0,
+        );
+
+        let remaining_accounts = [other_account.clone(), fund_owner_account.clone()];
+        assert_eq!(
+            find_pool_creation_fee_receiver(&remaining_accounts, &fund_owner)
+                .unwrap()
+                .key(),
+            fund_owner
+        );
+
+        // missing
+        assert_eq!(
+            find_pool_creation_fee_receiver(&[other_account.clone()], &fund_owner).unwrap_err(),
+            error!(ErrorCode::InvalidPoolCreationFeeReceiver)
+        );
+
+        // not writable
+        fund_owner_account.is_writable = false;
+        assert_eq!(
+            find_pool_creation_fee_receiver(&[fund_owner_account.clone()], &fund_owner)
+                .unwrap_err(),
+            error!(ErrorCode::InvalidPoolCreationFeeReceiver)
+        );
+
+        // not a wallet
+        fund_owner_account.is_writable = true;
+        fund_owner_account.owner = &token_program;
+        assert_eq!(
+            find_pool_creation_fee_receiver(&[fund_owner_account], &fund_owner).unwrap_err(),
+            error!(ErrorCode::InvalidPoolCreationFeeReceiver)
+        );
+    }
+}
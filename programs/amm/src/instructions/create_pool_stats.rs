@@ -0,0 +1,33 @@
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct CreatePoolStats<'info> {
+    /// Pays to create the pool stats account, anyone can
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// The pool the stats are of
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The stats of the pool, recorded by the swaps that pass it in their remaining accounts
+    #[account(
+        init,
+        seeds = [
+            POOL_STATS_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+        ],
+        bump,
+        payer = payer,
+        space = PoolStatsState::LEN
+    )]
+    pub pool_stats: AccountLoader<'info, PoolStatsState>,
+
+    pub system_program: Program<'info, System>,
+}
+
+pub fn create_pool_stats(ctx: Context<CreatePoolStats>) -> Result<()> {
+    let mut pool_stats = ctx.accounts.pool_stats.load_init()?;
+    pool_stats.initialize(ctx.bumps.pool_stats, ctx.accounts.pool_state.key());
+    Ok(())
+}
@@ -19,15 +19,15 @@ pub struct DecreaseLiquidity<'info> {
 
     /// The token account for the tokenized position
     #[account(
-        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
         constraint = nft_account.amount == 1,
         token::authority = nft_owner
     )]
     pub nft_account: Box<Account<'info, TokenAccount>>,
 
     /// Decrease liquidity for this position
-    #[account(mut, constraint = personal_position.pool_id == pool_state.key())]
-    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+    #[account(mut, constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
 
     #[account(mut)]
     pub pool_state: AccountLoader<'info, PoolState>,
@@ -37,13 +37,13 @@ pub struct DecreaseLiquidity<'info> {
         seeds = [
             POSITION_SEED.as_bytes(),
             pool_state.key().as_ref(),
-            &personal_position.tick_lower_index.to_be_bytes(),
-            &personal_position.tick_upper_index.to_be_bytes(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
         ],
         bump,
-        constraint = protocol_position.pool_id == pool_state.key(),
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
     )]
-    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
 
     /// Token_0 vault
     #[account(
@@ -102,8 +102,9 @@ pub fn decrease_liquidity_v1<'a, 'b, 'c: 'info, 'info>(
 ) -> Result<()> {
     decrease_liquidity(
         &ctx.accounts.pool_state,
-        &mut ctx.accounts.protocol_position,
-        &mut ctx.accounts.personal_position,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
+        &ctx.accounts.nft_account.owner,
         &ctx.accounts.token_vault_0.to_account_info(),
         &ctx.accounts.token_vault_1.to_account_info(),
         &ctx.accounts.tick_array_lower,
@@ -124,8 +125,9 @@ pub fn decrease_liquidity_v1<'a, 'b, 'c: 'info, 'info>(
 
 pub fn decrease_liquidity<'a, 'b, 'c: 'info, 'info>(
     pool_state_loader: &'b AccountLoader<'info, PoolState>,
-    protocol_position: &'b mut Box<Account<'info, ProtocolPositionState>>,
-    personal_position: &'b mut Box<Account<'info, PersonalPositionState>>,
+    protocol_position_loader: &'b AccountLoader<'info, ProtocolPositionState>,
+    personal_position_loader: &'b AccountLoader<'info, PersonalPositionState>,
+    nft_owner: &Pubkey,
     token_vault_0: &'b AccountInfo<'info>,
     token_vault_1: &'b AccountInfo<'info>,
     tick_array_lower_loader: &'b AccountLoader<'info, TickArrayState>,
@@ -146,7 +148,18 @@ pub fn decrease_liquidity<'a, 'b, 'c: 'info, 'info>(
     //     let memp_program = accounts.memo_program.as_ref().unwrap().to_account_info();
     //     invoke_memo_instruction(DECREASE_MEMO_MSG, memp_program)?;
     // }
+    crate::log_compute_units!("decrease liquidity start");
+    let personal_position = &mut personal_position_loader.load_mut()?;
     assert!(liquidity <= personal_position.liquidity);
+    // the withdrawn liquidity goes to the token accounts chosen by the signer, the fees only if
+    // they belong to the fee recipient, otherwise they stay owed to the position
+    let can_pay_fees_to = |recipient_token_account: &AccountInfo<'info>| -> Result<bool> {
+        let recipient_token_account =
+            InterfaceAccount::<token_interface::TokenAccount>::try_from(recipient_token_account)?;
+        Ok(personal_position.can_pay_fees_to(nft_owner, &recipient_token_account.owner))
+    };
+    let pay_fees_0 = can_pay_fees_to(recipient_token_account_0)?;
+    let pay_fees_1 = can_pay_fees_to(recipient_token_account_1)?;
     let liquidity_before;
     let pool_sqrt_price_x64;
     let pool_tick_current;
@@ -154,13 +167,15 @@ pub fn decrease_liquidity<'a, 'b, 'c: 'info, 'info>(
 
     let remaining_collect_accounts = &mut Vec::new();
     {
-        let pool_state = pool_state_loader.load()?;
+        let pool_state = &mut pool_state_loader.load_mut()?;
         if !pool_state.get_status_by_bit(PoolStatusBitIndex::DecreaseLiquidity)
             && !pool_state.get_status_by_bit(PoolStatusBitIndex::CollectFee)
             && !pool_state.get_status_by_bit(PoolStatusBitIndex::CollectReward)
         {
             return err!(ErrorCode::NotApproved);
         }
+        // held until the tokens and rewards are transferred
+        pool_state.lock_reentrancy()?;
         liquidity_before = pool_state.liquidity;
         pool_sqrt_price_x64 = pool_state.sqrt_price_x64;
         pool_tick_current = pool_state.tick_current;
@@ -191,13 +206,16 @@ pub fn decrease_liquidity<'a, 'b, 'c: 'info, 'info>(
     let (decrease_amount_0, latest_fees_owed_0, decrease_amount_1, latest_fees_owed_1) =
         decrease_liquidity_and_update_position(
             pool_state_loader,
-            protocol_position,
+            &mut protocol_position_loader.load_mut()?,
             personal_position,
             tick_array_lower_loader,
             tick_array_upper_loader,
             tickarray_bitmap_extension,
             liquidity,
+            pay_fees_0,
+            pay_fees_1,
         )?;
+    crate::log_compute_units!("decrease liquidity position updated");
 
     let mut transfer_fee_0 = 0;
     let mut transfer_fee_1 = 0;
@@ -270,6 +288,7 @@ pub fn decrease_liquidity<'a, 'b, 'c: 'info, 'info>(
         transfer_amount_1,
     )?;
 
+    crate::log_compute_units!("decrease liquidity tokens transferred");
     check_unclaimed_fees_and_vault(pool_state_loader, token_vault_0, token_vault_1)?;
 
     let reward_amounts = collect_rewards(
@@ -278,12 +297,14 @@ pub fn decrease_liquidity<'a, 'b, 'c: 'info, 'info>(
         token_program,
         token_2022_program_opt.clone(),
         personal_position,
+        nft_owner,
         if token_2022_program_opt.is_none() {
             false
         } else {
             true
         },
     )?;
+    crate::log_compute_units!("decrease liquidity rewards collected");
     emit!(DecreaseLiquidityEvent {
         position_nft_mint: personal_position.nft_mint,
         liquidity,
@@ -296,17 +317,20 @@ pub fn decrease_liquidity<'a, 'b, 'c: 'info, 'info>(
         transfer_fee_1: transfer_fee_1,
     });
 
+    pool_state_loader.load_mut()?.unlock_reentrancy();
     Ok(())
 }
 
 pub fn decrease_liquidity_and_update_position<'a, 'b, 'c: 'info, 'info>(
     pool_state_loader: &AccountLoader<'info, PoolState>,
-    protocol_position: &mut Box<Account<'info, ProtocolPositionState>>,
-    personal_position: &mut Box<Account<'info, PersonalPositionState>>,
+    protocol_position: &mut ProtocolPositionState,
+    personal_position: &mut PersonalPositionState,
     tick_array_lower: &AccountLoader<'info, TickArrayState>,
     tick_array_upper: &AccountLoader<'info, TickArrayState>,
     tick_array_bitmap_extension: Option<&'c AccountInfo<'info>>,
     liquidity: u128,
+    pay_fees_0: bool,
+    pay_fees_1: bool,
 ) -> Result<(u64, u64, u64, u64)> {
     let mut pool_state = pool_state_loader.load_mut()?;
     let mut decrease_amount_0 = 0;
@@ -348,8 +372,12 @@ pub fn decrease_liquidity_and_update_position<'a, 'b, 'c: 'info, 'info>(
     let mut latest_fees_owed_0 = 0;
     let mut latest_fees_owed_1 = 0;
     if pool_state.get_status_by_bit(PoolStatusBitIndex::CollectFee) {
-        latest_fees_owed_0 = personal_position.token_fees_owed_0;
-        latest_fees_owed_1 = personal_position.token_fees_owed_1;
+        if pay_fees_0 {
+            latest_fees_owed_0 = personal_position.token_fees_owed_0;
+        }
+        if pay_fees_1 {
+            latest_fees_owed_1 = personal_position.token_fees_owed_1;
+        }
 
         require_gte!(
             pool_state.total_fees_token_0 - pool_state.total_fees_claimed_token_0,
@@ -360,8 +388,14 @@ pub fn decrease_liquidity_and_update_position<'a, 'b, 'c: 'info, 'info>(
             latest_fees_owed_1
         );
 
-        personal_position.token_fees_owed_0 = 0;
-        personal_position.token_fees_owed_1 = 0;
+        personal_position.token_fees_owed_0 = personal_position
+            .token_fees_owed_0
+            .checked_sub(latest_fees_owed_0)
+            .ok_or(ErrorCode::MathOverflow)?;
+        personal_position.token_fees_owed_1 = personal_position
+            .token_fees_owed_1
+            .checked_sub(latest_fees_owed_1)
+            .ok_or(ErrorCode::MathOverflow)?;
 
         pool_state.total_fees_claimed_token_0 = pool_state
             .total_fees_claimed_token_0
@@ -372,6 +406,7 @@ pub fn decrease_liquidity_and_update_position<'a, 'b, 'c: 'info, 'info>(
             .checked_add(latest_fees_owed_1)
             .unwrap();
     }
+    personal_position.record_action(Clock::get()?.unix_timestamp as u64);
 
     Ok((
         decrease_amount_0,
@@ -460,6 +495,7 @@ pub fn collect_rewards<'a, 'b, 'c, 'info>(
     token_program: &'b Program<'info, Token>,
     token_program_2022: Option<AccountInfo<'info>>,
     personal_position_state: &mut PersonalPositionState,
+    nft_owner: &Pubkey,
     need_reward_mint: bool,
 ) -> Result<[u64; REWARD_NUM]> {
     let mut reward_amounts: [u64; REWARD_NUM] = [0, 0, 0];
@@ -500,11 +536,20 @@ pub fn collect_rewards<'a, 'b, 'c, 'info>(
             reward_token_vault.key(),
             pool_state_loader.load_mut()?.reward_infos[i].token_vault
         );
+        // a reward is only paid to the fee recipient if set, otherwise it stays owed
+        if !personal_position_state.can_pay_fees_to(nft_owner, &recipient_token_account.owner) {
+            continue;
+        }
 
         let reward_amount_owed = personal_position_state.reward_infos[i].reward_amount_owed;
         if reward_amount_owed == 0 {
             continue;
         }
+        if pool_state_loader.load()?.reward_infos[i].consolidated() {
+            // the owed amount is included in the merkle snapshot, claim with `claim_merkle_reward`
+            personal_position_state.reward_infos[i].reward_amount_owed = 0;
+            continue;
+        }
         pool_state_loader
             .load()?
             .check_unclaimed_reward(i, reward_amount_owed)?;
@@ -12,15 +12,15 @@ pub struct DecreaseLiquidityV2<'info> {
 
     /// The token account for the tokenized position
     #[account(
-        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
         constraint = nft_account.amount == 1,
         token::authority = nft_owner,
     )]
     pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
     /// Decrease liquidity for this position
-    #[account(mut, constraint = personal_position.pool_id == pool_state.key())]
-    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+    #[account(mut, constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
 
     #[account(mut)]
     pub pool_state: AccountLoader<'info, PoolState>,
@@ -30,13 +30,13 @@ pub struct DecreaseLiquidityV2<'info> {
         seeds = [
             POSITION_SEED.as_bytes(),
             pool_state.key().as_ref(),
-            &personal_position.tick_lower_index.to_be_bytes(),
-            &personal_position.tick_upper_index.to_be_bytes(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
         ],
         bump,
-        constraint = protocol_position.pool_id == pool_state.key(),
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
     )]
-    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
 
     /// Token_0 vault
     #[account(
@@ -116,8 +116,9 @@ pub fn decrease_liquidity_v2<'a, 'b, 'c: 'info, 'info>(
 ) -> Result<()> {
     decrease_liquidity(
         &ctx.accounts.pool_state,
-        &mut ctx.accounts.protocol_position,
-        &mut ctx.accounts.personal_position,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
+        &ctx.accounts.nft_account.owner,
         &ctx.accounts.token_vault_0.to_account_info(),
         &ctx.accounts.token_vault_1.to_account_info(),
         &ctx.accounts.tick_array_lower,
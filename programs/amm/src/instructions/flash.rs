@@ -0,0 +1,370 @@
+use crate::error::ErrorCode;
+use crate::libraries::{big_num::U128, fixed_point_64, full_math::MulDiv};
+use crate::states::*;
+use crate::util::*;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{hash::hash, instruction::Instruction, program::invoke};
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+
+#[derive(Accounts)]
+pub struct Flash<'info> {
+    /// The borrower, its signature is passed on to the callback
+    pub payer: Signer<'info>,
+
+    /// The factory state to read the trade and protocol fee rates
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// The pool lending its vaults
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Receives the borrowed token_0
+    #[account(mut, token::mint = vault_0_mint)]
+    pub recipient_token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Receives the borrowed token_1
+    #[account(mut, token::mint = vault_1_mint)]
+    pub recipient_token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of token vault 0
+    #[account(address = token_vault_0.mint)]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token vault 1
+    #[account(address = token_vault_1.mint)]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// SPL program for token transfers
+    pub token_program: Program<'info, Token>,
+
+    /// SPL program 2022 for token transfers
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// CHECK: the program called back once the tokens are lent, it must repay the vaults
+    #[account(
+        executable,
+        constraint = callback_program.key() != crate::id() @ ErrorCode::InvalidFlashCallback
+    )]
+    pub callback_program: UncheckedAccount<'info>,
+    // remaining accounts
+    // the accounts of the callback instruction, in its order
+}
+
+/// The instruction the callback program is invoked with, the anchor instruction
+/// `flash_callback(fee_0: u64, fee_1: u64, data: Vec<u8>)`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct FlashCallback {
+    /// The fees owed on top of the borrowed amounts
+    pub fee_0: u64,
+    pub fee_1: u64,
+    /// Passed through from the flash instruction
+    pub data: Vec<u8>,
+}
+
+impl FlashCallback {
+    pub fn discriminator() -> [u8; 8] {
+        hash(b"global:flash_callback").to_bytes()[..8]
+            .try_into()
+            .unwrap()
+    }
+
+    pub fn instruction_data(&self) -> Result<Vec<u8>> {
+        let mut data = Self::discriminator().to_vec();
+        data.extend(self.try_to_vec()?);
+        Ok(data)
+    }
+}
+
+/// The fee of borrowing `amount`, charged at the trade fee rate and rounded up
+pub fn flash_fee(amm_config: &AmmConfig, amount: u64) -> Result<u64> {
+    Ok(U128::from(amount)
+        .mul_div_ceil(
+            U128::from(amm_config.trade_fee_rate),
+            U128::from(FEE_RATE_DENOMINATOR_VALUE),
+        )
+        .ok_or(ErrorCode::MathOverflow)?
+        .as_u64())
+}
+
+/// Share the amount paid on top of a loan between the protocol, the fund and the liquidity in
+/// range, as the fee of a swap is shared
+pub fn accrue_flash_fee(
+    pool_state: &mut PoolState,
+    amm_config: &AmmConfig,
+    paid: u64,
+    zero: bool,
+) -> Result<()> {
+    let share = |rate: u32| -> Result<u64> {
+        Ok(U128::from(paid)
+            .checked_mul(rate.into())
+            .ok_or(ErrorCode::MathOverflow)?
+            .checked_div(FEE_RATE_DENOMINATOR_VALUE.into())
+            .ok_or(ErrorCode::MathOverflow)?
+            .as_u64())
+    };
+    let protocol_fee = share(amm_config.protocol_fee_rate)?;
+    let fund_fee = share(amm_config.fund_fee_rate)?;
+    let lp_fee = paid
+        .checked_sub(protocol_fee)
+        .ok_or(ErrorCode::MathOverflow)?
+        .checked_sub(fund_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let fee_growth_global_x64_delta = U128::from(lp_fee)
+        .mul_div_floor(
+            U128::from(fixed_point_64::Q64),
+            U128::from(pool_state.liquidity),
+        )
+        .ok_or(ErrorCode::MathOverflow)?
+        .as_u128();
+
+    if zero {
+        pool_state.fee_growth_global_0_x64 = pool_state
+            .fee_growth_global_0_x64
+            .checked_add(fee_growth_global_x64_delta)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        pool_state.total_fees_token_0 = pool_state
+            .total_fees_token_0
+            .checked_add(lp_fee)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        pool_state.protocol_fees_token_0 = pool_state
+            .protocol_fees_token_0
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        pool_state.fund_fees_token_0 = pool_state
+            .fund_fees_token_0
+            .checked_add(fund_fee)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+    } else {
+        pool_state.fee_growth_global_1_x64 = pool_state
+            .fee_growth_global_1_x64
+            .checked_add(fee_growth_global_x64_delta)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        pool_state.total_fees_token_1 = pool_state
+            .total_fees_token_1
+            .checked_add(lp_fee)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        pool_state.protocol_fees_token_1 = pool_state
+            .protocol_fees_token_1
+            .checked_add(protocol_fee)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        pool_state.fund_fees_token_1 = pool_state
+            .fund_fees_token_1
+            .checked_add(fund_fee)
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+    }
+    Ok(())
+}
+
+/// Lend the amounts out of the vaults, call back the callback program, and check the vaults
+/// got back at least the amounts plus the fees. Everything paid on top of the amounts is
+/// shared as fees. Only pools the admin enabled flash for lend.
+pub fn flash<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, Flash<'info>>,
+    amount_0: u64,
+    amount_1: u64,
+    data: Vec<u8>,
+) -> Result<()> {
+    {
+        let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+        require!(
+            pool_state.get_status_by_bit(PoolStatusBitIndex::Flash),
+            ErrorCode::NotApproved
+        );
+        let liquidity = pool_state.liquidity;
+        require_gt!(liquidity, 0, ErrorCode::FlashWithoutLiquidity);
+        // held until the loan is repaid
+        pool_state.lock_reentrancy()?;
+    }
+    let fee_0 = flash_fee(&ctx.accounts.amm_config, amount_0)?;
+    let fee_1 = flash_fee(&ctx.accounts.amm_config, amount_1)?;
+    let balance_0_before = ctx.accounts.token_vault_0.amount;
+    let balance_1_before = ctx.accounts.token_vault_1.amount;
+
+    transfer_from_pool_vault_to_user(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.token_vault_0.to_account_info(),
+        &ctx.accounts.recipient_token_account_0.to_account_info(),
+        Some(ctx.accounts.vault_0_mint.clone()),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.to_account_info()),
+        amount_0,
+    )?;
+    transfer_from_pool_vault_to_user(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.token_vault_1.to_account_info(),
+        &ctx.accounts.recipient_token_account_1.to_account_info(),
+        Some(ctx.accounts.vault_1_mint.clone()),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.to_account_info()),
+        amount_1,
+    )?;
+
+    let callback = FlashCallback { fee_0, fee_1, data };
+    invoke(
+        &Instruction {
+            program_id: ctx.accounts.callback_program.key(),
+            accounts: ctx
+                .remaining_accounts
+                .iter()
+                .map(|account_info| {
+                    if account_info.is_writable {
+                        AccountMeta::new(*account_info.key, account_info.is_signer)
+                    } else {
+                        AccountMeta::new_readonly(*account_info.key, account_info.is_signer)
+                    }
+                })
+                .collect(),
+            data: callback.instruction_data()?,
+        },
+        &[
+            ctx.remaining_accounts,
+            &[ctx.accounts.callback_program.to_account_info()],
+        ]
+        .concat(),
+    )?;
+
+    ctx.accounts.token_vault_0.reload()?;
+    ctx.accounts.token_vault_1.reload()?;
+    let balance_0_after = ctx.accounts.token_vault_0.amount;
+    let balance_1_after = ctx.accounts.token_vault_1.amount;
+    require_gte!(
+        balance_0_after,
+        balance_0_before
+            .checked_add(fee_0)
+            .ok_or(ErrorCode::MathOverflow)?,
+        ErrorCode::FlashNotRepaid
+    );
+    require_gte!(
+        balance_1_after,
+        balance_1_before
+            .checked_add(fee_1)
+            .ok_or(ErrorCode::MathOverflow)?,
+        ErrorCode::FlashNotRepaid
+    );
+    let paid_0 = balance_0_after - balance_0_before;
+    let paid_1 = balance_1_after - balance_1_before;
+
+    let pool_state = &mut ctx.accounts.pool_state.load_mut()?;
+    accrue_flash_fee(pool_state, &ctx.accounts.amm_config, paid_0, true)?;
+    accrue_flash_fee(pool_state, &ctx.accounts.amm_config, paid_1, false)?;
+    pool_state.unlock_reentrancy();
+
+    emit!(FlashEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        sender: ctx.accounts.payer.key(),
+        recipient_token_account_0: ctx.accounts.recipient_token_account_0.key(),
+        recipient_token_account_1: ctx.accounts.recipient_token_account_1.key(),
+        amount_0,
+        amount_1,
+        paid_0,
+        paid_1,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod flash_test {
+    use super::*;
+    use crate::states::pool_test::build_pool;
+
+    #[test]
+    fn flash_fee_test() {
+        let amm_config = AmmConfig {
+            trade_fee_rate: 2500,
+            ..Default::default()
+        };
+        assert_eq!(flash_fee(&amm_config, 0).unwrap(), 0);
+        assert_eq!(flash_fee(&amm_config, 1_000_000).unwrap(), 2_500);
+        // rounded up in favor of the pool
+        assert_eq!(flash_fee(&amm_config, 1).unwrap(), 1);
+        assert_eq!(flash_fee(&amm_config, 1_000_001).unwrap(), 2_501);
+    }
+
+    #[test]
+    fn accrue_flash_fee_test() {
+        let amm_config = AmmConfig {
+            trade_fee_rate: 2500,
+            protocol_fee_rate: 120_000,
+            fund_fee_rate: 40_000,
+            ..Default::default()
+        };
+        let pool_state_ref = build_pool(1, 10, fixed_point_64::Q64, 1_000);
+        let mut pool_state = pool_state_ref.borrow_mut();
+        pool_state.fee_growth_global_0_x64 = 0;
+        pool_state.fee_growth_global_1_x64 = 0;
+
+        accrue_flash_fee(&mut pool_state, &amm_config, 10_000, true).unwrap();
+        let protocol_fees_token_0 = pool_state.protocol_fees_token_0;
+        let fund_fees_token_0 = pool_state.fund_fees_token_0;
+        let total_fees_token_0 = pool_state.total_fees_token_0;
+        let fee_growth_global_0_x64 = pool_state.fee_growth_global_0_x64;
+        assert_eq!(protocol_fees_token_0, 1_200);
+        assert_eq!(fund_fees_token_0, 400);
+        assert_eq!(total_fees_token_0, 8_400);
+        // 8400 shared by the 1000 liquidity in range
+        assert_eq!(
+            fee_growth_global_0_x64,
+            8 * fixed_point_64::Q64 + fixed_point_64::Q64 * 2 / 5
+        );
+
+        // token_1 is untouched
+        let fee_growth_global_1_x64 = pool_state.fee_growth_global_1_x64;
+        let total_fees_token_1 = pool_state.total_fees_token_1;
+        assert_eq!((fee_growth_global_1_x64, total_fees_token_1), (0, 0));
+    }
+
+    #[test]
+    fn accrue_flash_fee_overflow_test() {
+        let amm_config = AmmConfig {
+            trade_fee_rate: 2500,
+            protocol_fee_rate: 120_000,
+            ..Default::default()
+        };
+        let pool_state_ref = build_pool(1, 10, fixed_point_64::Q64, 1_000);
+        let mut pool_state = pool_state_ref.borrow_mut();
+        pool_state.protocol_fees_token_1 = u64::MAX;
+        assert_eq!(
+            accrue_flash_fee(&mut pool_state, &amm_config, 10_000, false).unwrap_err(),
+            ErrorCode::FeeAccumulatorOverflow.into()
+        );
+
+        // the shares can't exceed the amount paid
+        let amm_config = AmmConfig {
+            protocol_fee_rate: FEE_RATE_DENOMINATOR_VALUE,
+            fund_fee_rate: 1,
+            ..amm_config
+        };
+        assert_eq!(
+            accrue_flash_fee(&mut pool_state, &amm_config, 10_000_000, true).unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn flash_callback_data_test() {
+        let callback = FlashCallback {
+            fee_0: 1,
+            fee_1: 2,
+            data: vec![3],
+        };
+        let data = callback.instruction_data().unwrap();
+        assert_eq!(data[..8], FlashCallback::discriminator());
+        assert_eq!(FlashCallback::try_from_slice(&data[8..]).unwrap(), callback);
+    }
+}
@@ -0,0 +1,147 @@
+use crate::libraries::tick_math;
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct GetEffectiveParams<'info> {
+    /// The amm config of the pool
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Account<'info, AmmConfig>,
+
+    /// The pool to read
+    pub pool_state: AccountLoader<'info, PoolState>,
+}
+
+/// The parameters that are applied to the pool, combined from the amm config and the pool
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct EffectiveParams {
+    pub amm_config: Pubkey,
+    /// The trade fee, denominated in hundredths of a bip (10^-6)
+    pub trade_fee_rate: u32,
+    /// The rate of protocol fee within trade fee
+    pub protocol_fee_rate: u32,
+    /// The rate of fund fee within trade fee
+    pub fund_fee_rate: u32,
+    /// The share of the collected protocol fees sent to the secondary destination
+    pub protocol_fee_split_rate: u32,
+    pub tick_spacing: u16,
+    /// Raw status bits of the pool
+    pub status: u8,
+    /// Whether each operation is allowed by the status bits
+    pub open_position_or_increase_liquidity_enabled: bool,
+    pub decrease_liquidity_enabled: bool,
+    pub collect_fee_enabled: bool,
+    pub collect_reward_enabled: bool,
+    pub swap_enabled: bool,
+    pub flash_enabled: bool,
+    /// The timestamp allowed for swap in the pool
+    pub open_time: u64,
+    /// The lowest and highest tick a position can use
+    pub min_tick: i32,
+    pub max_tick: i32,
+    /// Tick array start index range covered by the pool bitmap, outside of it the
+    /// tick array bitmap extension account is required
+    pub min_tick_array_start_index_without_extension: i32,
+    pub max_tick_array_start_index_without_extension: i32,
+    /// The minimum input amount of a swap, 0 if not limited
+    pub min_swap_amount_in: u64,
+    /// Lamports the creator of a pool of the config pays to its fund owner, 0 if free
+    pub pool_creation_fee: u64,
+    /// The max ticks the initial price of a new pool can be away from the reference price, 0 if
+    /// not limited
+    pub max_initial_tick_deviation: u32,
+    /// Seconds after the creation of a pool before it can be swapped
+    pub swap_delay_after_create: u32,
+}
+
+impl EffectiveParams {
+    pub fn new(amm_config: &AmmConfig, amm_config_key: Pubkey, pool_state: &PoolState) -> Self {
+        let tick_spacing = i32::from(pool_state.tick_spacing);
+        let (min_start_index, max_start_index) = pool_state.tick_array_start_index_range();
+        Self {
+            amm_config: amm_config_key,
+            trade_fee_rate: amm_config.trade_fee_rate,
+            protocol_fee_rate: amm_config.protocol_fee_rate,
+            fund_fee_rate: amm_config.fund_fee_rate,
+            protocol_fee_split_rate: amm_config.protocol_fee_split_rate,
+            tick_spacing: pool_state.tick_spacing,
+            status: pool_state.status,
+            open_position_or_increase_liquidity_enabled: pool_state
+                .get_status_by_bit(PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity),
+            decrease_liquidity_enabled: pool_state
+                .get_status_by_bit(PoolStatusBitIndex::DecreaseLiquidity),
+            collect_fee_enabled: pool_state.get_status_by_bit(PoolStatusBitIndex::CollectFee),
+            collect_reward_enabled: pool_state.get_status_by_bit(PoolStatusBitIndex::CollectReward),
+            swap_enabled: pool_state.get_status_by_bit(PoolStatusBitIndex::Swap),
+            flash_enabled: pool_state.get_status_by_bit(PoolStatusBitIndex::Flash),
+            open_time: pool_state.open_time,
+            min_tick: tick_math::MIN_TICK / tick_spacing * tick_spacing,
+            max_tick: tick_math::MAX_TICK / tick_spacing * tick_spacing,
+            min_tick_array_start_index_without_extension: min_start_index,
+            // the range returned by pool is exclusive at the upper end
+            max_tick_array_start_index_without_extension: max_start_index
+                - TickArrayState::tick_count(pool_state.tick_spacing),
+            min_swap_amount_in: pool_state.min_swap_amount_in,
+            pool_creation_fee: amm_config.pool_creation_fee,
+            max_initial_tick_deviation: amm_config.max_initial_tick_deviation,
+            swap_delay_after_create: amm_config.swap_delay_after_create,
+        }
+    }
+}
+
+pub fn get_effective_params(ctx: Context<GetEffectiveParams>) -> Result<EffectiveParams> {
+    let pool_state = ctx.accounts.pool_state.load()?;
+    Ok(EffectiveParams::new(
+        &ctx.accounts.amm_config,
+        ctx.accounts.amm_config.key(),
+        &pool_state,
+    ))
+}
+
+#[cfg(test)]
+mod get_effective_params_test {
+    use super::*;
+    use crate::states::pool_test::build_pool;
+
+    #[test]
+    fn effective_params_test() {
+        let amm_config = AmmConfig {
+            trade_fee_rate: 2500,
+            protocol_fee_rate: 120000,
+            fund_fee_rate: 40000,
+            tick_spacing: 10,
+            protocol_fee_split_rate: 250000,
+            pool_creation_fee: 5_000_000_000,
+            max_initial_tick_deviation: 500,
+            swap_delay_after_create: 60,
+            ..Default::default()
+        };
+        let pool_state_ref = build_pool(1, 10, 1000, 10000);
+        let mut pool_state = pool_state_ref.borrow_mut();
+        pool_state.set_status_by_bit(PoolStatusBitIndex::Swap, PoolStatusBitFlag::Disable);
+        pool_state.min_swap_amount_in = 100;
+
+        // flash is disabled until the admin enables it
+        let params = EffectiveParams::new(&amm_config, Pubkey::default(), &pool_state);
+        assert!(!params.flash_enabled);
+        pool_state.set_status_by_bit(PoolStatusBitIndex::Flash, PoolStatusBitFlag::Enable);
+
+        let params = EffectiveParams::new(&amm_config, Pubkey::default(), &pool_state);
+        assert_eq!(params.trade_fee_rate, 2500);
+        assert_eq!(params.protocol_fee_rate, 120000);
+        assert_eq!(params.fund_fee_rate, 40000);
+        assert!(!params.swap_enabled);
+        assert!(params.flash_enabled);
+        assert!(params.open_position_or_increase_liquidity_enabled);
+        assert!(params.decrease_liquidity_enabled);
+        assert_eq!(params.min_tick, -443630);
+        assert_eq!(params.max_tick, 443630);
+        assert_eq!(params.min_tick_array_start_index_without_extension, -307200);
+        assert_eq!(params.max_tick_array_start_index_without_extension, 306600);
+        assert_eq!(params.min_swap_amount_in, 100);
+        assert_eq!(params.pool_creation_fee, 5_000_000_000);
+        assert_eq!(params.protocol_fee_split_rate, 250000);
+        assert_eq!(params.max_initial_tick_deviation, 500);
+        assert_eq!(params.swap_delay_after_create, 60);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::increase_liquidity::calculate_latest_token_fees;
+use crate::libraries::{big_num::U256, fixed_point_64, full_math::MulDiv, liquidity_math};
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct GetPositionAmounts<'info> {
+    /// The pool of the position
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The position to value
+    #[account(constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// The tick array of the position's lower tick
+    #[account(constraint = tick_array_lower.load()?.pool_id == pool_state.key())]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// The tick array of the position's upper tick
+    #[account(constraint = tick_array_upper.load()?.pool_id == pool_state.key())]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+}
+
+/// The value of a position at the current price of the pool
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct PositionAmounts {
+    pub personal_position: Pubkey,
+    pub liquidity: u128,
+    /// The price and tick the amounts are computed at
+    pub sqrt_price_x64: u128,
+    pub tick_current: i32,
+    /// The token amounts received by removing all the liquidity of the position
+    pub amount_0: u64,
+    pub amount_1: u64,
+    /// The fees owed to the position, including the ones accrued since it was last updated
+    pub fees_owed_0: u64,
+    pub fees_owed_1: u64,
+    /// The rewards owed to the position in the order of the pool reward infos, 0 for the
+    /// uninitialized ones
+    pub rewards_owed: [u64; REWARD_NUM],
+}
+
+impl PositionAmounts {
+    pub fn new(
+        pool_state: &PoolState,
+        personal_position: &PersonalPositionState,
+        personal_position_key: Pubkey,
+        tick_lower: &TickState,
+        tick_upper: &TickState,
+    ) -> Result<Self> {
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            personal_position.tick_lower_index,
+            personal_position.tick_upper_index,
+            -i128::try_from(personal_position.liquidity).unwrap(),
+        )?;
+
+        let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = get_fee_growth_inside(
+            tick_lower,
+            tick_upper,
+            pool_state.tick_current,
+            pool_state.fee_growth_global_0_x64,
+            pool_state.fee_growth_global_1_x64,
+        )?;
+
+        let reward_infos = pool_state.reward_infos;
+        let reward_growths_inside = get_reward_growths_inside(
+            tick_lower,
+            tick_upper,
+            pool_state.tick_current,
+            &reward_infos,
+        )?;
+        let mut rewards_owed = [0; REWARD_NUM];
+        for i in 0..REWARD_NUM {
+            if !reward_infos[i].initialized() {
+                continue;
+            }
+            let position_reward_info = personal_position.reward_infos[i];
+            let reward_growth_delta =
+                reward_growths_inside[i].wrapping_sub(position_reward_info.growth_inside_last_x64);
+            rewards_owed[i] = U256::from(reward_growth_delta)
+                .mul_div_floor(
+                    U256::from(personal_position.liquidity),
+                    U256::from(fixed_point_64::Q64),
+                )
+                .unwrap()
+                .to_underflow_u64()
+                .checked_add(position_reward_info.reward_amount_owed)
+                .unwrap();
+        }
+
+        Ok(Self {
+            personal_position: personal_position_key,
+            liquidity: personal_position.liquidity,
+            sqrt_price_x64: pool_state.sqrt_price_x64,
+            tick_current: pool_state.tick_current,
+            amount_0,
+            amount_1,
+            fees_owed_0: calculate_latest_token_fees(
+                personal_position.token_fees_owed_0,
+                personal_position.fee_growth_inside_0_last_x64,
+                fee_growth_inside_0_x64,
+                personal_position.liquidity,
+            ),
+            fees_owed_1: calculate_latest_token_fees(
+                personal_position.token_fees_owed_1,
+                personal_position.fee_growth_inside_1_last_x64,
+                fee_growth_inside_1_x64,
+                personal_position.liquidity,
+            ),
+            rewards_owed,
+        })
+    }
+}
+
+pub fn get_position_amounts(ctx: Context<GetPositionAmounts>) -> Result<PositionAmounts> {
+    // the rewards are accrued up to now on a copy, the pool itself is not written
+    let mut pool_state = *ctx.accounts.pool_state.load()?;
+    pool_state.update_reward_infos(Clock::get()?.unix_timestamp as u64)?;
+
+    let personal_position = &ctx.accounts.personal_position.load()?;
+    let tick_array_lower = ctx.accounts.tick_array_lower.load()?;
+    let tick_array_upper = ctx.accounts.tick_array_upper.load()?;
+    let tick_lower = tick_array_lower.ticks[tick_array_lower
+        .get_tick_offset_in_array(personal_position.tick_lower_index, pool_state.tick_spacing)?];
+    let tick_upper = tick_array_upper.ticks[tick_array_upper
+        .get_tick_offset_in_array(personal_position.tick_upper_index, pool_state.tick_spacing)?];
+
+    PositionAmounts::new(
+        &pool_state,
+        personal_position,
+        ctx.accounts.personal_position.key(),
+        &tick_lower,
+        &tick_upper,
+    )
+}
+
+#[cfg(test)]
+mod get_position_amounts_test {
+    use super::*;
+    use crate::libraries::tick_math;
+    use crate::states::pool_test::build_pool;
+
+    #[test]
+    fn position_amounts_test() {
+        let tick_current = 100;
+        let pool_state_ref = build_pool(
+            tick_current,
+            10,
+            tick_math::get_sqrt_price_at_tick(tick_current).unwrap(),
+            1_000_000,
+        );
+        let mut pool_state = pool_state_ref.borrow_mut();
+        pool_state.fee_growth_global_0_x64 = 3 * fixed_point_64::Q64;
+        pool_state.fee_growth_global_1_x64 = 5 * fixed_point_64::Q64;
+        pool_state.reward_infos[0].reward_state = RewardState::Opening as u8;
+        pool_state.reward_infos[0].reward_growth_global_x64 = 2 * fixed_point_64::Q64;
+
+        let tick_lower = TickState {
+            tick: -100,
+            ..Default::default()
+        };
+        let tick_upper = TickState {
+            tick: 300,
+            ..Default::default()
+        };
+        let mut personal_position = PersonalPositionState {
+            tick_lower_index: -100,
+            tick_upper_index: 300,
+            liquidity: 1_000_000,
+            fee_growth_inside_0_last_x64: fixed_point_64::Q64,
+            fee_growth_inside_1_last_x64: 5 * fixed_point_64::Q64,
+            token_fees_owed_1: 7,
+            ..Default::default()
+        };
+        personal_position.reward_infos[0].growth_inside_last_x64 = fixed_point_64::Q64;
+        personal_position.reward_infos[0].reward_amount_owed = 11;
+
+        let amounts = PositionAmounts::new(
+            &pool_state,
+            &personal_position,
+            Pubkey::default(),
+            &tick_lower,
+            &tick_upper,
+        )
+        .unwrap();
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            tick_current,
+            pool_state.sqrt_price_x64,
+            -100,
+            300,
+            -1_000_000,
+        )
+        .unwrap();
+        // the price is inside the range, the position holds both tokens
+        assert!(amount_0 > 0 && amount_1 > 0);
+        assert_eq!((amounts.amount_0, amounts.amount_1), (amount_0, amount_1));
+        assert_eq!(amounts.fees_owed_0, 2_000_000);
+        // no fee growth since the last update, only the fees already owed
+        assert_eq!(amounts.fees_owed_1, 7);
+        assert_eq!(amounts.rewards_owed, [1_000_011, 0, 0]);
+        assert_eq!(amounts.liquidity, 1_000_000);
+        assert_eq!(amounts.tick_current, tick_current);
+    }
+
+    #[test]
+    fn position_amounts_out_of_range_test() {
+        let tick_current = 500;
+        let pool_state_ref = build_pool(
+            tick_current,
+            10,
+            tick_math::get_sqrt_price_at_tick(tick_current).unwrap(),
+            0,
+        );
+        let mut pool_state = pool_state_ref.borrow_mut();
+        pool_state.fee_growth_global_0_x64 = 0;
+        pool_state.fee_growth_global_1_x64 = 0;
+        let personal_position = PersonalPositionState {
+            tick_lower_index: -100,
+            tick_upper_index: 300,
+            liquidity: 1_000_000,
+            ..Default::default()
+        };
+        let tick_lower = TickState {
+            tick: -100,
+            ..Default::default()
+        };
+        let tick_upper = TickState {
+            tick: 300,
+            ..Default::default()
+        };
+
+        let amounts = PositionAmounts::new(
+            &pool_state,
+            &personal_position,
+            Pubkey::default(),
+            &tick_lower,
+            &tick_upper,
+        )
+        .unwrap();
+        // above the range the position is all token_1
+        assert_eq!(amounts.amount_0, 0);
+        assert!(amounts.amount_1 > 0);
+        assert_eq!((amounts.fees_owed_0, amounts.fees_owed_1), (0, 0));
+    }
+}
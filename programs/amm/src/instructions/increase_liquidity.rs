@@ -14,7 +14,7 @@ pub struct IncreaseLiquidity<'info> {
 
     /// The token account for nft
     #[account(
-        constraint = nft_account.mint == personal_position.nft_mint,
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
         constraint = nft_account.amount == 1,
         token::authority = nft_owner
     )]
@@ -28,17 +28,17 @@ pub struct IncreaseLiquidity<'info> {
         seeds = [
             POSITION_SEED.as_bytes(),
             pool_state.key().as_ref(),
-            &personal_position.tick_lower_index.to_be_bytes(),
-            &personal_position.tick_upper_index.to_be_bytes(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
         ],
         bump,
-        constraint = protocol_position.pool_id == pool_state.key(),
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
     )]
-    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
 
     /// Increase liquidity for this position
-    #[account(mut, constraint = personal_position.pool_id == pool_state.key())]
-    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+    #[account(mut, constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
 
     /// Stores init state for the lower tick
     #[account(mut, constraint = tick_array_lower.load()?.pool_id == pool_state.key())]
@@ -99,8 +99,8 @@ pub fn increase_liquidity_v1<'a, 'b, 'c: 'info, 'info>(
     increase_liquidity(
         &ctx.accounts.nft_owner,
         &ctx.accounts.pool_state,
-        &mut ctx.accounts.protocol_position,
-        &mut ctx.accounts.personal_position,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
         &ctx.accounts.tick_array_lower,
         &ctx.accounts.tick_array_upper,
         &ctx.accounts.token_account_0.to_account_info(),
@@ -122,8 +122,8 @@ pub fn increase_liquidity_v1<'a, 'b, 'c: 'info, 'info>(
 pub fn increase_liquidity<'a, 'b, 'c: 'info, 'info>(
     nft_owner: &'b Signer<'info>,
     pool_state_loader: &'b AccountLoader<'info, PoolState>,
-    protocol_position: &'b mut Box<Account<'info, ProtocolPositionState>>,
-    personal_position: &'b mut Box<Account<'info, PersonalPositionState>>,
+    protocol_position_loader: &'b AccountLoader<'info, ProtocolPositionState>,
+    personal_position_loader: &'b AccountLoader<'info, PersonalPositionState>,
     tick_array_lower_loader: &'b AccountLoader<'info, TickArrayState>,
     tick_array_upper_loader: &'b AccountLoader<'info, TickArrayState>,
     token_account_0: &'b AccountInfo<'info>,
@@ -141,11 +141,15 @@ pub fn increase_liquidity<'a, 'b, 'c: 'info, 'info>(
     amount_1_max: u64,
     base_flag: Option<bool>,
 ) -> Result<()> {
+    crate::log_compute_units!("increase liquidity start");
     let mut liquidity = liquidity;
     let pool_state = &mut pool_state_loader.load_mut()?;
     if !pool_state.get_status_by_bit(PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity) {
         return err!(ErrorCode::NotApproved);
     }
+    pool_state.lock_reentrancy()?;
+    let protocol_position = &mut protocol_position_loader.load_mut()?;
+    let personal_position = &mut personal_position_loader.load_mut()?;
     let tick_lower = personal_position.tick_lower_index;
     let tick_upper = personal_position.tick_upper_index;
 
@@ -182,6 +186,7 @@ pub fn increase_liquidity<'a, 'b, 'c: 'info, 'info>(
         tick_upper,
         base_flag,
     )?;
+    crate::log_compute_units!("increase liquidity added");
 
     personal_position.token_fees_owed_0 = calculate_latest_token_fees(
         personal_position.token_fees_owed_0,
@@ -202,6 +207,8 @@ pub fn increase_liquidity<'a, 'b, 'c: 'info, 'info>(
     // update rewards, must update before increase liquidity
     personal_position.update_rewards(protocol_position.reward_growth_inside, true)?;
     personal_position.liquidity = personal_position.liquidity.checked_add(liquidity).unwrap();
+    personal_position.record_action(Clock::get()?.unix_timestamp as u64);
+    crate::log_compute_units!("increase liquidity position updated");
 
     emit!(IncreaseLiquidityEvent {
         position_nft_mint: personal_position.nft_mint,
@@ -212,6 +219,7 @@ pub fn increase_liquidity<'a, 'b, 'c: 'info, 'info>(
         amount_1_transfer_fee
     });
 
+    pool_state.unlock_reentrancy();
     Ok(())
 }
 
@@ -0,0 +1,92 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct InitializeFeeRebateReward<'info> {
+    /// The rebate is paid out of protocol fees, only admin or operation owner can initialize it
+    pub authority: Signer<'info>,
+
+    /// Set reward for this pool
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// load info from the account to judge reward permission
+    #[account(
+        seeds = [
+            OPERATION_SEED.as_bytes(),
+        ],
+        bump,
+    )]
+    pub operation_state: AccountLoader<'info, OperationState>,
+}
+
+#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Debug, PartialEq)]
+pub struct InitializeFeeRebateRewardParam {
+    /// Reward open time
+    pub open_time: u64,
+    /// Reward end time
+    pub end_time: u64,
+    /// Token reward per second are earned per unit of liquidity
+    pub emissions_per_second_x64: u128,
+    /// The reward token, token_mint_0 or token_mint_1 of the pool
+    pub token_mint: Pubkey,
+    /// The share of the protocol fees the rebate can take at every reward update
+    pub rebate_rate: u32,
+}
+
+impl InitializeFeeRebateRewardParam {
+    pub fn reward_param(&self) -> InitializeRewardParam {
+        InitializeRewardParam {
+            open_time: self.open_time,
+            end_time: self.end_time,
+            emissions_per_second_x64: self.emissions_per_second_x64,
+        }
+    }
+}
+
+pub fn initialize_fee_rebate_reward(
+    ctx: Context<InitializeFeeRebateReward>,
+    param: InitializeFeeRebateRewardParam,
+) -> Result<()> {
+    let operation_state = ctx.accounts.operation_state.load()?;
+    require!(
+        ctx.accounts.authority.key() == crate::admin::id()
+            || operation_state.validate_operation_owner(ctx.accounts.authority.key()),
+        ErrorCode::NotApproved
+    );
+
+    let clock = Clock::get()?;
+    param.reward_param().check(clock.unix_timestamp as u64)?;
+
+    let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.check_unlocked()?;
+    // accrue the running rewards before the reward list changes
+    pool_state.update_reward_infos(clock.unix_timestamp as u64)?;
+    let reward_index = pool_state.initialize_fee_rebate_reward(
+        param.open_time,
+        param.end_time,
+        param.emissions_per_second_x64,
+        &param.token_mint,
+        param.rebate_rate,
+        &ctx.accounts.authority.key(),
+    )?;
+    #[cfg(feature = "enable-log")]
+    msg!(
+        "fee rebate reward_index:{}, reward_info:{:?}",
+        reward_index,
+        pool_state.reward_infos[reward_index],
+    );
+
+    emit!(InitializeFeeRebateRewardEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        reward_index: reward_index as u8,
+        token_mint: param.token_mint,
+        open_time: param.open_time,
+        end_time: param.end_time,
+        emissions_per_second_x64: param.emissions_per_second_x64,
+        rebate_rate: param.rebate_rate,
+    });
+
+    Ok(())
+}
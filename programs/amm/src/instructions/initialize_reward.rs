@@ -128,6 +128,7 @@ pub fn initialize_reward(
     );
 
     let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.check_unlocked()?;
     pool_state.initialize_reward(
         param.open_time,
         param.end_time,
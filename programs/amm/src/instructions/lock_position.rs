@@ -0,0 +1,300 @@
+use super::decrease_liquidity::decrease_liquidity;
+use crate::error::ErrorCode;
+use crate::states::*;
+use crate::util::close_spl_account;
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{
+    self, Mint, Token2022, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct LockPosition<'info> {
+    /// The position owner, the only one who can unlock the position
+    #[account(mut)]
+    pub nft_owner: Signer<'info>,
+
+    /// Mint address of the tokenized position
+    pub position_nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account holding the position nft
+    #[account(
+        mut,
+        token::mint = position_nft_mint,
+        token::authority = nft_owner,
+        constraint = nft_account.amount == 1
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position to lock
+    #[account(constraint = personal_position.load()?.nft_mint == position_nft_mint.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// Stores the owner and unlock time of the position
+    #[account(
+        init,
+        seeds = [
+            LOCKED_POSITION_SEED.as_bytes(),
+            position_nft_mint.key().as_ref(),
+        ],
+        bump,
+        payer = nft_owner,
+        space = LockedPositionState::LEN
+    )]
+    pub locked_position: Box<Account<'info, LockedPositionState>>,
+
+    /// The token account of the locked position holding the nft until it is unlocked
+    #[account(
+        init,
+        payer = nft_owner,
+        associated_token::mint = position_nft_mint,
+        associated_token::authority = locked_position,
+        associated_token::token_program = token_program,
+    )]
+    pub locked_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token program of the position nft, spl token or token 2022
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UnlockPosition<'info> {
+    /// The owner who locked the position
+    #[account(mut, address = locked_position.owner @ ErrorCode::NotApproved)]
+    pub nft_owner: Signer<'info>,
+
+    /// Mint address of the tokenized position
+    #[account(address = locked_position.position_nft_mint)]
+    pub position_nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account of the owner receiving the position nft
+    #[account(
+        mut,
+        token::mint = position_nft_mint,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Closed once the nft is returned, the rent goes back to the owner
+    #[account(
+        mut,
+        seeds = [
+            LOCKED_POSITION_SEED.as_bytes(),
+            position_nft_mint.key().as_ref(),
+        ],
+        bump,
+        close = nft_owner
+    )]
+    pub locked_position: Box<Account<'info, LockedPositionState>>,
+
+    /// The token account of the locked position holding the nft, closed once it is returned
+    #[account(
+        mut,
+        associated_token::mint = position_nft_mint,
+        associated_token::authority = locked_position,
+        associated_token::token_program = token_program,
+    )]
+    pub locked_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token program of the position nft, spl token or token 2022
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct CollectLockedPosition<'info> {
+    /// The owner who locked the position, the fees and rewards are paid to them
+    #[account(address = locked_position.owner @ ErrorCode::NotApproved)]
+    pub nft_owner: Signer<'info>,
+
+    /// Holds the position nft, it stays locked
+    pub locked_position: Box<Account<'info, LockedPositionState>>,
+
+    /// Collect fees and rewards for this position
+    #[account(mut, address = locked_position.personal_position)]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    #[account(mut, address = locked_position.pool_id)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
+    )]
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// Token_0 vault
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token_1 vault
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Stores init state for the lower tick
+    #[account(mut, constraint = tick_array_lower.load()?.pool_id == pool_state.key())]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// Stores init state for the upper tick
+    #[account(mut, constraint = tick_array_upper.load()?.pool_id == pool_state.key())]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    /// The destination token account for receive the token_0 fees
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub recipient_token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The destination token account for receive the token_1 fees
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub recipient_token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SPL program to transfer out tokens
+    pub token_program: Program<'info, Token>,
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// The mint of token vault 0
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token vault 1
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+    // remaining account
+    // tick_array_bitmap: the tick array bitmap extension of the pool if needed
+    // for every reward: reward_token_vault, recipient_token_account, reward_vault_mint
+}
+
+/// Move the position nft into the custody of the program until `unlock_time`
+pub fn lock_position(ctx: Context<LockPosition>, unlock_time: u64) -> Result<()> {
+    let current_timestamp = u64::try_from(Clock::get()?.unix_timestamp).unwrap();
+    require_gt!(unlock_time, current_timestamp, ErrorCode::InvalidUnlockTime);
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.nft_account.to_account_info(),
+                mint: ctx.accounts.position_nft_mint.to_account_info(),
+                to: ctx.accounts.locked_nft_account.to_account_info(),
+                authority: ctx.accounts.nft_owner.to_account_info(),
+            },
+        ),
+        1,
+        0,
+    )?;
+
+    let locked_position = &mut ctx.accounts.locked_position;
+    locked_position.bump = [ctx.bumps.locked_position];
+    locked_position.owner = ctx.accounts.nft_owner.key();
+    locked_position.position_nft_mint = ctx.accounts.position_nft_mint.key();
+    locked_position.personal_position = ctx.accounts.personal_position.key();
+    locked_position.pool_id = ctx.accounts.personal_position.load()?.pool_id;
+    locked_position.locked_at = current_timestamp;
+    locked_position.unlock_time = unlock_time;
+
+    emit!(LockPositionEvent {
+        personal_position: locked_position.personal_position,
+        owner: locked_position.owner,
+        position_nft_mint: locked_position.position_nft_mint,
+        unlock_time,
+    });
+    Ok(())
+}
+
+/// Return the position nft to its owner once the unlock time is reached
+pub fn unlock_position(ctx: Context<UnlockPosition>) -> Result<()> {
+    let current_timestamp = u64::try_from(Clock::get()?.unix_timestamp).unwrap();
+    let locked_position = &ctx.accounts.locked_position;
+    require!(
+        locked_position.is_unlockable(current_timestamp),
+        ErrorCode::PositionLocked
+    );
+
+    let locked_position_info = locked_position.to_account_info();
+    token_interface::transfer_checked(
+        CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.locked_nft_account.to_account_info(),
+                mint: ctx.accounts.position_nft_mint.to_account_info(),
+                to: ctx.accounts.nft_account.to_account_info(),
+                authority: locked_position_info.clone(),
+            },
+            &[&locked_position.seeds()],
+        ),
+        1,
+        0,
+    )?;
+    close_spl_account(
+        &locked_position_info,
+        &ctx.accounts.nft_owner.to_account_info(),
+        &ctx.accounts.locked_nft_account.to_account_info(),
+        &ctx.accounts.token_program.to_account_info(),
+        &[&locked_position.seeds()],
+    )?;
+
+    emit!(UnlockPositionEvent {
+        personal_position: locked_position.personal_position,
+        owner: locked_position.owner,
+        position_nft_mint: locked_position.position_nft_mint,
+    });
+    Ok(())
+}
+
+/// Collect the fees and rewards of a locked position for the owner who locked it, the nft and
+/// the liquidity stay locked
+pub fn collect_locked_position<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, CollectLockedPosition<'info>>,
+) -> Result<()> {
+    // the locker is the holder of the position, the fee recipient it set still applies
+    let owner = ctx.accounts.locked_position.owner;
+    // decreasing zero liquidity only collects the fees and rewards owed
+    decrease_liquidity(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
+        &owner,
+        &ctx.accounts.token_vault_0.to_account_info(),
+        &ctx.accounts.token_vault_1.to_account_info(),
+        &ctx.accounts.tick_array_lower,
+        &ctx.accounts.tick_array_upper,
+        &ctx.accounts.recipient_token_account_0.to_account_info(),
+        &ctx.accounts.recipient_token_account_1.to_account_info(),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.clone()),
+        None,
+        Some(ctx.accounts.vault_0_mint.clone()),
+        Some(ctx.accounts.vault_1_mint.clone()),
+        &ctx.remaining_accounts,
+        0,
+        0,
+        0,
+    )
+}
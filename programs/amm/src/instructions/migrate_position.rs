@@ -0,0 +1,295 @@
+use super::decrease_liquidity::decrease_liquidity;
+use super::increase_liquidity::increase_liquidity;
+use crate::error::ErrorCode;
+use crate::libraries::liquidity_math;
+use crate::states::*;
+use crate::util::get_transfer_fee;
+use anchor_lang::prelude::*;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+
+#[derive(Accounts)]
+pub struct MigratePosition<'info> {
+    /// The owner of both positions
+    pub nft_owner: Signer<'info>,
+
+    /// The token account for the nft of the source position
+    #[account(
+        constraint = source_nft_account.mint == source_personal_position.load()?.nft_mint,
+        constraint = source_nft_account.amount == 1,
+        token::authority = nft_owner,
+    )]
+    pub source_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position the liquidity is withdrawn from
+    #[account(mut, constraint = source_personal_position.load()?.pool_id == source_pool_state.key())]
+    pub source_personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    #[account(mut)]
+    pub source_pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            source_pool_state.key().as_ref(),
+            &source_personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &source_personal_position.load()?.tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        constraint = source_protocol_position.load()?.pool_id == source_pool_state.key(),
+    )]
+    pub source_protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// Token_0 vault of the source pool
+    #[account(
+        mut,
+        constraint = source_token_vault_0.key() == source_pool_state.load()?.token_vault_0
+    )]
+    pub source_token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token_1 vault of the source pool
+    #[account(
+        mut,
+        constraint = source_token_vault_1.key() == source_pool_state.load()?.token_vault_1
+    )]
+    pub source_token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Stores init state for the lower tick of the source position
+    #[account(mut, constraint = source_tick_array_lower.load()?.pool_id == source_pool_state.key())]
+    pub source_tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// Stores init state for the upper tick of the source position
+    #[account(mut, constraint = source_tick_array_upper.load()?.pool_id == source_pool_state.key())]
+    pub source_tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    /// The token account for the nft of the target position
+    #[account(
+        constraint = target_nft_account.mint == target_personal_position.load()?.nft_mint,
+        constraint = target_nft_account.amount == 1,
+        token::authority = nft_owner,
+    )]
+    pub target_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position the liquidity is added to, in a pool of the same pair and another fee tier
+    #[account(mut, constraint = target_personal_position.load()?.pool_id == target_pool_state.key())]
+    pub target_personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    #[account(mut)]
+    pub target_pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            target_pool_state.key().as_ref(),
+            &target_personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &target_personal_position.load()?.tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        constraint = target_protocol_position.load()?.pool_id == target_pool_state.key(),
+    )]
+    pub target_protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// Token_0 vault of the target pool
+    #[account(
+        mut,
+        constraint = target_token_vault_0.key() == target_pool_state.load()?.token_vault_0
+    )]
+    pub target_token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token_1 vault of the target pool
+    #[account(
+        mut,
+        constraint = target_token_vault_1.key() == target_pool_state.load()?.token_vault_1
+    )]
+    pub target_token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Stores init state for the lower tick of the target position
+    #[account(mut, constraint = target_tick_array_lower.load()?.pool_id == target_pool_state.key())]
+    pub target_tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// Stores init state for the upper tick of the target position
+    #[account(mut, constraint = target_tick_array_upper.load()?.pool_id == target_pool_state.key())]
+    pub target_tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    /// The owner's token_0 account receiving the withdrawal and the fees of the source position,
+    /// then paying the deposit of the target position
+    #[account(
+        mut,
+        token::mint = vault_0_mint,
+        token::authority = nft_owner,
+    )]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The owner's token_1 account receiving the withdrawal and the fees of the source position,
+    /// then paying the deposit of the target position
+    #[account(
+        mut,
+        token::mint = vault_1_mint,
+        token::authority = nft_owner,
+    )]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SPL program to transfer out tokens
+    pub token_program: Program<'info, Token>,
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// The mint of token vault 0 of both pools
+    #[account(
+        address = source_token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token vault 1 of both pools
+    #[account(
+        address = source_token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+    // remaining accounts
+    // the tick array bitmap extension of the target pool if the target position needs it
+    // then the remaining accounts of decrease_liquidity_v2 for the source position: the tick
+    // array bitmap extension of the source pool if needed, and the reward accounts
+}
+
+/// Move `liquidity` of the source position into the target position, a position of the same pair
+/// in a pool of another fee tier. The withdrawn amounts pass through the owner's token accounts
+/// within the instruction, and the target liquidity is the most those amounts provide at the
+/// price of the target pool. What the target position doesn't take stays in the token accounts.
+pub fn migrate_position<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, MigratePosition<'info>>,
+    liquidity: u128,
+    amount_0_min: u64,
+    amount_1_min: u64,
+    target_liquidity_min: u128,
+) -> Result<()> {
+    require_gt!(liquidity, 0, ErrorCode::InvalidMigration);
+    let (withdrawn_amount_0, withdrawn_amount_1, target_needs_bitmap_extension) = {
+        let source_pool_state = ctx.accounts.source_pool_state.load()?;
+        let target_pool_state = ctx.accounts.target_pool_state.load()?;
+        require!(
+            ctx.accounts.source_pool_state.key() != ctx.accounts.target_pool_state.key()
+                && source_pool_state.amm_config != target_pool_state.amm_config
+                && source_pool_state.token_mint_0 == target_pool_state.token_mint_0
+                && source_pool_state.token_mint_1 == target_pool_state.token_mint_1,
+            ErrorCode::InvalidMigration
+        );
+        let source_position = ctx.accounts.source_personal_position.load()?;
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            source_pool_state.tick_current,
+            source_pool_state.sqrt_price_x64,
+            source_position.tick_lower_index,
+            source_position.tick_upper_index,
+            -i128::try_from(liquidity).map_err(|_| ErrorCode::MathOverflow)?,
+        )?;
+        let target_position = ctx.accounts.target_personal_position.load()?;
+        (
+            amount_0,
+            amount_1,
+            target_pool_state.is_overflow_default_tickarray_bitmap(vec![
+                target_position.tick_lower_index,
+                target_position.tick_upper_index,
+            ]),
+        )
+    };
+    let (target_remaining_accounts, source_remaining_accounts) = ctx
+        .remaining_accounts
+        .split_at(if target_needs_bitmap_extension { 1 } else { 0 });
+
+    decrease_liquidity(
+        &ctx.accounts.source_pool_state,
+        &ctx.accounts.source_protocol_position,
+        &ctx.accounts.source_personal_position,
+        &ctx.accounts.source_nft_account.owner,
+        &ctx.accounts.source_token_vault_0.to_account_info(),
+        &ctx.accounts.source_token_vault_1.to_account_info(),
+        &ctx.accounts.source_tick_array_lower,
+        &ctx.accounts.source_tick_array_upper,
+        &ctx.accounts.token_account_0.to_account_info(),
+        &ctx.accounts.token_account_1.to_account_info(),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.clone()),
+        None,
+        Some(ctx.accounts.vault_0_mint.clone()),
+        Some(ctx.accounts.vault_1_mint.clone()),
+        source_remaining_accounts,
+        liquidity,
+        amount_0_min,
+        amount_1_min,
+    )?;
+
+    // the owner receives the withdrawal less the transfer fees, and the target vaults receive
+    // the deposit less the transfer fees again
+    let amount_0_max = withdrawn_amount_0
+        .checked_sub(get_transfer_fee(
+            ctx.accounts.vault_0_mint.clone(),
+            withdrawn_amount_0,
+        )?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let amount_1_max = withdrawn_amount_1
+        .checked_sub(get_transfer_fee(
+            ctx.accounts.vault_1_mint.clone(),
+            withdrawn_amount_1,
+        )?)
+        .ok_or(ErrorCode::MathOverflow)?;
+    let target_liquidity = {
+        let target_pool_state = ctx.accounts.target_pool_state.load()?;
+        let target_position = ctx.accounts.target_personal_position.load()?;
+        liquidity_math::get_liquidity_from_amounts_at_ticks(
+            target_pool_state.sqrt_price_x64,
+            target_position.tick_lower_index,
+            target_position.tick_upper_index,
+            amount_0_max
+                .checked_sub(get_transfer_fee(
+                    ctx.accounts.vault_0_mint.clone(),
+                    amount_0_max,
+                )?)
+                .ok_or(ErrorCode::MathOverflow)?,
+            amount_1_max
+                .checked_sub(get_transfer_fee(
+                    ctx.accounts.vault_1_mint.clone(),
+                    amount_1_max,
+                )?)
+                .ok_or(ErrorCode::MathOverflow)?,
+        )?
+    };
+    require_gt!(target_liquidity, 0, ErrorCode::InvalidMigration);
+    require_gte!(
+        target_liquidity,
+        target_liquidity_min,
+        ErrorCode::PriceSlippageCheck
+    );
+
+    increase_liquidity(
+        &ctx.accounts.nft_owner,
+        &ctx.accounts.target_pool_state,
+        &ctx.accounts.target_protocol_position,
+        &ctx.accounts.target_personal_position,
+        &ctx.accounts.target_tick_array_lower,
+        &ctx.accounts.target_tick_array_upper,
+        &ctx.accounts.token_account_0.to_account_info(),
+        &ctx.accounts.token_account_1.to_account_info(),
+        &ctx.accounts.target_token_vault_0.to_account_info(),
+        &ctx.accounts.target_token_vault_1.to_account_info(),
+        &ctx.accounts.token_program,
+        Some(&ctx.accounts.token_program_2022),
+        Some(ctx.accounts.vault_0_mint.clone()),
+        Some(ctx.accounts.vault_1_mint.clone()),
+        target_remaining_accounts,
+        target_liquidity,
+        amount_0_max,
+        amount_1_max,
+        None,
+    )?;
+
+    emit!(MigratePositionEvent {
+        source_position_nft_mint: ctx.accounts.source_personal_position.load()?.nft_mint,
+        target_position_nft_mint: ctx.accounts.target_personal_position.load()?.nft_mint,
+        source_liquidity: liquidity,
+        target_liquidity,
+        amount_0: amount_0_max,
+        amount_1: amount_1_max,
+    });
+    Ok(())
+}
@@ -10,6 +10,12 @@ pub use open_position_v2::*;
 pub mod open_position_with_token22_nft;
 pub use open_position_with_token22_nft::*;
 
+pub mod open_position_with_token_amounts;
+pub use open_position_with_token_amounts::*;
+
+pub mod open_position_with_swap;
+pub use open_position_with_swap::*;
+
 pub mod close_position;
 pub use close_position::*;
 
@@ -19,12 +25,33 @@ pub use increase_liquidity::*;
 pub mod increase_liquidity_v2;
 pub use increase_liquidity_v2::*;
 
+pub mod compound_fees;
+pub use compound_fees::*;
+
 pub mod decrease_liquidity;
 pub use decrease_liquidity::*;
 
 pub mod decrease_liquidity_v2;
 pub use decrease_liquidity_v2::*;
 
+pub mod relayed_position;
+pub use relayed_position::*;
+
+pub mod set_fee_recipient;
+pub use set_fee_recipient::*;
+
+pub mod update_position_metadata;
+pub use update_position_metadata::*;
+
+pub mod lock_position;
+pub use lock_position::*;
+
+pub mod transfer_position;
+pub use transfer_position::*;
+
+pub mod migrate_position;
+pub use migrate_position::*;
+
 pub mod swap;
 pub use swap::*;
 
@@ -34,17 +61,56 @@ pub use swap_v2::*;
 pub mod swap_router_base_in;
 pub use swap_router_base_in::*;
 
+pub mod swap_router_base_out;
+pub use swap_router_base_out::*;
+
+pub mod flash;
+pub use flash::*;
+
+pub mod create_pool_stats;
+pub use create_pool_stats::*;
+
+pub mod roll_stats;
+pub use roll_stats::*;
+
+pub mod buyback_protocol_fee;
+pub use buyback_protocol_fee::*;
+
+pub mod get_effective_params;
+pub use get_effective_params::*;
+
+pub mod get_position_amounts;
+pub use get_position_amounts::*;
+
+pub mod collect_reward;
+pub use collect_reward::*;
+
 pub mod update_reward_info;
 pub use update_reward_info::*;
 
+pub mod compact_tick_array;
+pub use compact_tick_array::*;
+
 pub mod initialize_reward;
 pub use initialize_reward::*;
 
+pub mod initialize_fee_rebate_reward;
+pub use initialize_fee_rebate_reward::*;
+
 pub mod set_reward_params;
 pub use set_reward_params::*;
 
+pub mod top_up_reward;
+pub use top_up_reward::*;
+
 pub mod collect_remaining_rewards;
 pub use collect_remaining_rewards::*;
 
+pub mod claim_merkle_reward;
+pub use claim_merkle_reward::*;
+
 pub mod admin;
 pub use admin::*;
+
+#[cfg(test)]
+mod pool_activity_fuzz_test;
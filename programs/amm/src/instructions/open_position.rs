@@ -1,6 +1,5 @@
 use crate::error::ErrorCode;
 use crate::libraries::liquidity_math;
-use crate::libraries::tick_math;
 use crate::states::*;
 use crate::util::*;
 use anchor_lang::prelude::*;
@@ -23,7 +22,6 @@ use std::cell::RefMut;
 #[cfg(feature = "enable-log")]
 use std::convert::identity;
 use std::ops::Deref;
-use std::ops::DerefMut;
 
 #[derive(Accounts)]
 #[instruction(tick_lower_index: i32, tick_upper_index: i32,tick_array_lower_start_index:i32,tick_array_upper_start_index:i32)]
@@ -32,7 +30,10 @@ pub struct OpenPosition<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    /// CHECK: Receives the position NFT
+    /// CHECK: Receives the position NFT, it may be another account than the payer
+    #[account(
+        constraint = valid_position_nft_owner(&position_nft_owner.key()) @ ErrorCode::InvalidPositionNftOwner
+    )]
     pub position_nft_owner: UncheckedAccount<'info>,
 
     /// Unique token mint address
@@ -76,7 +77,7 @@ pub struct OpenPosition<'info> {
         payer = payer,
         space = ProtocolPositionState::LEN
     )]
-    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
 
     /// CHECK: Account to store data for the position's lower tick
     #[account(
@@ -110,7 +111,7 @@ pub struct OpenPosition<'info> {
         payer = payer,
         space = PersonalPositionState::LEN
     )]
-    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
 
     /// The token_0 account deposit token to the pool
     #[account(
@@ -165,6 +166,13 @@ pub struct OpenPosition<'info> {
     // pub tick_array_bitmap: AccountLoader<'info, TickArrayBitmapExtension>,
 }
 
+/// The position nft may be sent to another account than the payer, so that a program can open
+/// positions for its users by cpi while paying the deposits and the rent itself. Sending it to the
+/// default pubkey would lose the position.
+pub fn valid_position_nft_owner(position_nft_owner: &Pubkey) -> bool {
+    *position_nft_owner != Pubkey::default()
+}
+
 pub fn open_position_v1<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, OpenPosition<'info>>,
     liquidity: u128,
@@ -186,8 +194,8 @@ pub fn open_position_v1<'a, 'b, 'c: 'info, 'info>(
         &ctx.accounts.pool_state,
         &ctx.accounts.tick_array_lower,
         &ctx.accounts.tick_array_upper,
-        &mut ctx.accounts.protocol_position,
-        &mut ctx.accounts.personal_position,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
         &ctx.accounts.token_account_0.to_account_info(),
         &ctx.accounts.token_account_1.to_account_info(),
         &ctx.accounts.token_vault_0.to_account_info(),
@@ -225,8 +233,8 @@ pub fn open_position<'a, 'b, 'c: 'info, 'info>(
     pool_state_loader: &'b AccountLoader<'info, PoolState>,
     tick_array_lower_loader: &'b UncheckedAccount<'info>,
     tick_array_upper_loader: &'b UncheckedAccount<'info>,
-    protocol_position: &'b mut Box<Account<'info, ProtocolPositionState>>,
-    personal_position: &'b mut Box<Account<'info, PersonalPositionState>>,
+    protocol_position_loader: &'b AccountLoader<'info, ProtocolPositionState>,
+    personal_position_loader: &'b AccountLoader<'info, PersonalPositionState>,
     token_account_0: &'b AccountInfo<'info>,
     token_account_1: &'b AccountInfo<'info>,
     token_vault_0: &'b AccountInfo<'info>,
@@ -260,6 +268,8 @@ pub fn open_position<'a, 'b, 'c: 'info, 'info>(
         if !pool_state.get_status_by_bit(PoolStatusBitIndex::OpenPositionOrIncreaseLiquidity) {
             return err!(ErrorCode::NotApproved);
         }
+        // held until the position NFT is minted
+        pool_state.lock_reentrancy()?;
         check_ticks_order(tick_lower_index, tick_upper_index)?;
         check_tick_array_start_index(
             tick_array_lower_start_index,
@@ -299,7 +309,7 @@ pub fn open_position<'a, 'b, 'c: 'info, 'info>(
             };
 
         // check if protocol position is initilized
-        let protocol_position = protocol_position.deref_mut();
+        let protocol_position = &mut ProtocolPositionState::load_or_init(protocol_position_loader)?;
         if protocol_position.pool_id == Pubkey::default() {
             protocol_position.bump = protocol_position_bump;
             protocol_position.pool_id = pool_state_loader.key();
@@ -351,7 +361,7 @@ pub fn open_position<'a, 'b, 'c: 'info, 'info>(
             base_flag,
         )?;
 
-        // let personal_position = &mut personal_position;
+        let personal_position = &mut personal_position_loader.load_init()?;
         personal_position.bump = [personal_position_bump];
         personal_position.nft_mint = position_nft_mint.key();
         personal_position.pool_id = pool_state_loader.key();
@@ -366,6 +376,7 @@ pub fn open_position<'a, 'b, 'c: 'info, 'info>(
         // update rewards, must update before update liquidity
         personal_position.update_rewards(protocol_position.reward_growth_inside, false)?;
         personal_position.liquidity = liquidity;
+        personal_position.record_action(Clock::get()?.unix_timestamp as u64);
 
         emit!(CreatePersonalPositionEvent {
             pool_state: pool_state_loader.key(),
@@ -384,7 +395,7 @@ pub fn open_position<'a, 'b, 'c: 'info, 'info>(
     mint_nft_and_remove_mint_authority(
         payer,
         pool_state_loader,
-        personal_position,
+        personal_position_loader,
         position_nft_mint,
         position_nft_account,
         metadata_account,
@@ -395,7 +406,9 @@ pub fn open_position<'a, 'b, 'c: 'info, 'info>(
         rent,
         with_metadata,
         use_metadata_extension,
-    )
+    )?;
+    pool_state_loader.load_mut()?.unlock_reentrancy();
+    Ok(())
 }
 
 /// Add liquidity to an initialized pool
@@ -431,12 +444,13 @@ pub fn add_liquidity<'b, 'c: 'info, 'info>(
             // because only v2 instruction support token_2022, vault_0_mint must be exist
             let amount_0_transfer_fee =
                 get_transfer_fee(vault_0_mint.clone().unwrap(), amount_0_max).unwrap();
-            *liquidity = liquidity_math::get_liquidity_from_single_amount_0(
+            *liquidity = liquidity_math::get_liquidity_from_single_amount(
                 pool_state.sqrt_price_x64,
-                tick_math::get_sqrt_price_at_tick(tick_lower_index)?,
-                tick_math::get_sqrt_price_at_tick(tick_upper_index)?,
+                tick_lower_index,
+                tick_upper_index,
                 amount_0_max.checked_sub(amount_0_transfer_fee).unwrap(),
-            );
+                true,
+            )?;
             #[cfg(feature = "enable-log")]
             msg!(
                 "liquidity: {}, amount_0_max:{}, amount_0_transfer_fee:{}",
@@ -449,12 +463,13 @@ pub fn add_liquidity<'b, 'c: 'info, 'info>(
             // because only v2 instruction support token_2022, vault_1_mint must be exist
             let amount_1_transfer_fee =
                 get_transfer_fee(vault_1_mint.clone().unwrap(), amount_1_max).unwrap();
-            *liquidity = liquidity_math::get_liquidity_from_single_amount_1(
+            *liquidity = liquidity_math::get_liquidity_from_single_amount(
                 pool_state.sqrt_price_x64,
-                tick_math::get_sqrt_price_at_tick(tick_lower_index)?,
-                tick_math::get_sqrt_price_at_tick(tick_upper_index)?,
+                tick_lower_index,
+                tick_upper_index,
                 amount_1_max.checked_sub(amount_1_transfer_fee).unwrap(),
-            );
+                false,
+            )?;
             #[cfg(feature = "enable-log")]
             msg!(
                 "liquidity: {}, amount_1_max:{}, amount_1_transfer_fee:{}",
@@ -466,73 +481,17 @@ pub fn add_liquidity<'b, 'c: 'info, 'info>(
     }
     assert!(*liquidity > 0);
     let liquidity_before = pool_state.liquidity;
-    require_keys_eq!(tick_array_lower_loader.load()?.pool_id, pool_state.key());
-    require_keys_eq!(tick_array_upper_loader.load()?.pool_id, pool_state.key());
-
-    // get tick_state
-    let mut tick_lower_state = *tick_array_lower_loader
-        .load_mut()?
-        .get_tick_state_mut(tick_lower_index, pool_state.tick_spacing)?;
-    let mut tick_upper_state = *tick_array_upper_loader
-        .load_mut()?
-        .get_tick_state_mut(tick_upper_index, pool_state.tick_spacing)?;
-    if tick_lower_state.tick == 0 {
-        tick_lower_state.tick = tick_lower_index;
-    }
-    if tick_upper_state.tick == 0 {
-        tick_upper_state.tick = tick_upper_index;
-    }
-    let clock = Clock::get()?;
-    let (amount_0, amount_1, flip_tick_lower, flip_tick_upper) = modify_position(
-        i128::try_from(*liquidity).unwrap(),
-        pool_state,
+    let (amount_0, amount_1) = add_liquidity_to_ticks(
+        tick_array_lower_loader,
+        tick_array_upper_loader,
         protocol_position,
-        &mut tick_lower_state,
-        &mut tick_upper_state,
-        clock.unix_timestamp as u64,
-    )?;
-
-    // update tick_state
-    tick_array_lower_loader.load_mut()?.update_tick_state(
+        tick_array_bitmap_extension,
+        pool_state,
+        *liquidity,
         tick_lower_index,
-        pool_state.tick_spacing,
-        tick_lower_state,
-    )?;
-    tick_array_upper_loader.load_mut()?.update_tick_state(
         tick_upper_index,
-        pool_state.tick_spacing,
-        tick_upper_state,
     )?;
 
-    if flip_tick_lower {
-        let mut tick_array_lower = tick_array_lower_loader.load_mut()?;
-        let before_init_tick_count = tick_array_lower.initialized_tick_count;
-        tick_array_lower.update_initialized_tick_count(true)?;
-
-        if before_init_tick_count == 0 {
-            pool_state.flip_tick_array_bit(
-                tick_array_bitmap_extension,
-                tick_array_lower.start_tick_index,
-            )?;
-        }
-    }
-    if flip_tick_upper {
-        let mut tick_array_upper = tick_array_upper_loader.load_mut()?;
-        let before_init_tick_count = tick_array_upper.initialized_tick_count;
-        tick_array_upper.update_initialized_tick_count(true)?;
-
-        if before_init_tick_count == 0 {
-            pool_state.flip_tick_array_bit(
-                tick_array_bitmap_extension,
-                tick_array_upper.start_tick_index,
-            )?;
-        }
-    }
-    require!(
-        amount_0 > 0 || amount_1 > 0,
-        ErrorCode::ForbidBothZeroForSupplyLiquidity
-    );
-
     let mut amount_0_transfer_fee = 0;
     let mut amount_1_transfer_fee = 0;
     if vault_0_mint.is_some() {
@@ -611,6 +570,88 @@ pub fn add_liquidity<'b, 'c: 'info, 'info>(
     ))
 }
 
+/// Add `liquidity` over [tick_lower_index, tick_upper_index] to the ticks, the protocol position
+/// and the pool, marking the tick arrays of newly initialized ticks in the bitmap. Returns the
+/// token amounts the liquidity is worth, rounded up, nothing is transferred.
+pub fn add_liquidity_to_ticks<'b, 'c: 'info, 'info>(
+    tick_array_lower_loader: &'b AccountLoad<'info, TickArrayState>,
+    tick_array_upper_loader: &'b AccountLoad<'info, TickArrayState>,
+    protocol_position: &mut ProtocolPositionState,
+    tick_array_bitmap_extension: Option<&'c AccountInfo<'info>>,
+    pool_state: &mut RefMut<PoolState>,
+    liquidity: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+) -> Result<(u64, u64)> {
+    require_keys_eq!(tick_array_lower_loader.load()?.pool_id, pool_state.key());
+    require_keys_eq!(tick_array_upper_loader.load()?.pool_id, pool_state.key());
+
+    // get tick_state
+    let mut tick_lower_state = *tick_array_lower_loader
+        .load_mut()?
+        .get_tick_state_mut(tick_lower_index, pool_state.tick_spacing)?;
+    let mut tick_upper_state = *tick_array_upper_loader
+        .load_mut()?
+        .get_tick_state_mut(tick_upper_index, pool_state.tick_spacing)?;
+    if tick_lower_state.tick == 0 {
+        tick_lower_state.tick = tick_lower_index;
+    }
+    if tick_upper_state.tick == 0 {
+        tick_upper_state.tick = tick_upper_index;
+    }
+    let clock = Clock::get()?;
+    let (amount_0, amount_1, flip_tick_lower, flip_tick_upper) = modify_position(
+        i128::try_from(liquidity).unwrap(),
+        pool_state,
+        protocol_position,
+        &mut tick_lower_state,
+        &mut tick_upper_state,
+        clock.unix_timestamp as u64,
+    )?;
+
+    // update tick_state
+    tick_array_lower_loader.load_mut()?.update_tick_state(
+        tick_lower_index,
+        pool_state.tick_spacing,
+        tick_lower_state,
+    )?;
+    tick_array_upper_loader.load_mut()?.update_tick_state(
+        tick_upper_index,
+        pool_state.tick_spacing,
+        tick_upper_state,
+    )?;
+
+    if flip_tick_lower {
+        let mut tick_array_lower = tick_array_lower_loader.load_mut()?;
+        let before_init_tick_count = tick_array_lower.initialized_tick_count;
+        tick_array_lower.update_initialized_tick_count(true)?;
+
+        if before_init_tick_count == 0 {
+            pool_state.flip_tick_array_bit(
+                tick_array_bitmap_extension,
+                tick_array_lower.start_tick_index,
+            )?;
+        }
+    }
+    if flip_tick_upper {
+        let mut tick_array_upper = tick_array_upper_loader.load_mut()?;
+        let before_init_tick_count = tick_array_upper.initialized_tick_count;
+        tick_array_upper.update_initialized_tick_count(true)?;
+
+        if before_init_tick_count == 0 {
+            pool_state.flip_tick_array_bit(
+                tick_array_bitmap_extension,
+                tick_array_upper.start_tick_index,
+            )?;
+        }
+    }
+    require!(
+        amount_0 > 0 || amount_1 > 0,
+        ErrorCode::ForbidBothZeroForSupplyLiquidity
+    );
+    Ok((amount_0, amount_1))
+}
+
 pub fn modify_position(
     liquidity_delta: i128,
     pool_state: &mut RefMut<PoolState>,
@@ -697,7 +738,7 @@ pub fn update_position(
         pool_state.tick_current,
         pool_state.fee_growth_global_0_x64,
         pool_state.fee_growth_global_1_x64,
-    );
+    )?;
 
     // Update reward outside if needed
     let reward_growths_inside = tick_array::get_reward_growths_inside(
@@ -705,7 +746,7 @@ pub fn update_position(
         tick_upper_state.deref(),
         pool_state.tick_current,
         &updated_reward_infos,
-    );
+    )?;
 
     protocol_position_state.update(
         tick_lower_state.tick,
@@ -729,7 +770,7 @@ pub fn update_position(
 fn mint_nft_and_remove_mint_authority<'info>(
     payer: &Signer<'info>,
     pool_state_loader: &AccountLoader<'info, PoolState>,
-    personal_position: &Account<'info, PersonalPositionState>,
+    personal_position: &AccountLoader<'info, PersonalPositionState>,
     position_nft_mint: &AccountInfo<'info>,
     position_nft_account: &AccountInfo<'info>,
     metadata_account: Option<&UncheckedAccount<'info>>,
@@ -811,7 +852,7 @@ fn mint_nft_and_remove_mint_authority<'info>(
     )
 }
 
-fn get_metadata_data(personal_position_id: Pubkey) -> (String, String, String) {
+pub fn get_metadata_data(personal_position_id: Pubkey) -> (String, String, String) {
     return (
         String::from("Raydium Concentrated Liquidity"),
         String::from("RCL"),
@@ -933,11 +974,23 @@ pub fn initialize_token_metadata_extension<'info>(
     Ok(())
 }
 
+#[cfg(test)]
+mod open_position_test {
+    use super::*;
+
+    #[test]
+    fn valid_position_nft_owner_test() {
+        // the payer or any other account can receive the position nft
+        assert!(valid_position_nft_owner(&Pubkey::new_unique()));
+        assert!(!valid_position_nft_owner(&Pubkey::default()));
+    }
+}
+
 #[cfg(test)]
 mod modify_position_test {
     use super::modify_position;
     use crate::error::ErrorCode;
-    use crate::libraries::tick_math;
+    use crate::libraries::{liquidity_math, tick_math};
     use crate::states::oracle::block_timestamp_mock;
     use crate::states::pool_test::build_pool;
     use crate::states::protocol_position::*;
@@ -1160,4 +1213,43 @@ mod modify_position_test {
 
         // check protocol position state
     }
+
+    #[test]
+    fn quote_matches_modify_position_test() {
+        let tick_current = 25;
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick_current).unwrap() + 12345;
+        for (tick_lower_index, tick_upper_index, base_0) in [
+            (-100, 100, true),
+            (-100, 100, false),
+            (50, 100, true),
+            (-100, 0, false),
+        ] {
+            let (liquidity, quote_amount_0, quote_amount_1) =
+                liquidity_math::get_liquidity_and_amounts_from_single_amount(
+                    tick_current,
+                    sqrt_price_x64,
+                    tick_lower_index,
+                    tick_upper_index,
+                    123_456_789,
+                    base_0,
+                )
+                .unwrap();
+
+            let pool_state_ref = build_pool(tick_current, 10, sqrt_price_x64, 10000);
+            let pool_state = &mut pool_state_ref.borrow_mut();
+            let (amount_0, amount_1, _, _) = modify_position(
+                liquidity as i128,
+                pool_state,
+                &mut ProtocolPositionState::default(),
+                &mut build_tick(tick_lower_index, 0, 0).take(),
+                &mut build_tick(tick_upper_index, 0, 0).take(),
+                block_timestamp_mock(),
+            )
+            .unwrap();
+            assert_eq!((quote_amount_0, quote_amount_1), (amount_0, amount_1));
+            // the quoted maximum passes the slippage check of the program
+            assert!(liquidity_math::amount_with_tolerance(quote_amount_0, 0) >= amount_0);
+            assert!(liquidity_math::amount_with_tolerance(quote_amount_1, 0) >= amount_1);
+        }
+    }
 }
@@ -1,4 +1,5 @@
-use super::open_position::open_position;
+use super::open_position::{open_position, valid_position_nft_owner};
+use crate::error::ErrorCode;
 use crate::states::*;
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
@@ -12,7 +13,10 @@ pub struct OpenPositionV2<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    /// CHECK: Receives the position NFT
+    /// CHECK: Receives the position NFT, it may be another account than the payer
+    #[account(
+        constraint = valid_position_nft_owner(&position_nft_owner.key()) @ ErrorCode::InvalidPositionNftOwner
+    )]
     pub position_nft_owner: UncheckedAccount<'info>,
 
     /// Unique token mint address
@@ -55,7 +59,7 @@ pub struct OpenPositionV2<'info> {
         payer = payer,
         space = ProtocolPositionState::LEN
     )]
-    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
 
     /// CHECK: Account to store data for the position's lower tick
     #[account(
@@ -89,7 +93,7 @@ pub struct OpenPositionV2<'info> {
         payer = payer,
         space = PersonalPositionState::LEN
     )]
-    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
 
     /// The token_0 account deposit token to the pool
     #[account(
@@ -177,8 +181,8 @@ pub fn open_position_v2<'a, 'b, 'c: 'info, 'info>(
         &ctx.accounts.pool_state,
         &ctx.accounts.tick_array_lower,
         &ctx.accounts.tick_array_upper,
-        &mut ctx.accounts.protocol_position,
-        &mut ctx.accounts.personal_position,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
         &ctx.accounts.token_account_0.to_account_info(),
         &ctx.accounts.token_account_1.to_account_info(),
         &ctx.accounts.token_vault_0.to_account_info(),
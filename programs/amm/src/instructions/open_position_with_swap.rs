@@ -0,0 +1,357 @@
+use super::open_position::{open_position, valid_position_nft_owner};
+use crate::error::ErrorCode;
+use crate::libraries::{liquidity_math, tick_math};
+use crate::states::*;
+use crate::swap_v2::{exact_internal_v2, SwapSingleV2};
+use crate::util::{self, create_position_nft_mint_with_extensions};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{create, AssociatedToken, Create};
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+
+#[derive(Accounts)]
+#[instruction(tick_lower_index: i32, tick_upper_index: i32,tick_array_lower_start_index:i32,tick_array_upper_start_index:i32)]
+pub struct OpenPositionWithSwap<'info> {
+    /// Pays to mint the position and for the swap
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: Receives the position NFT, it may be another account than the payer
+    #[account(
+        constraint = valid_position_nft_owner(&position_nft_owner.key()) @ ErrorCode::InvalidPositionNftOwner
+    )]
+    pub position_nft_owner: UncheckedAccount<'info>,
+
+    /// Unique token mint address, initialize in constract
+    #[account(mut)]
+    pub position_nft_mint: Signer<'info>,
+
+    /// CHECK: ATA address where position NFT will be minted, initialize in constract
+    #[account(mut)]
+    pub position_nft_account: UncheckedAccount<'info>,
+
+    /// The factory state to read the swap fee
+    #[account(address = pool_state.load()?.amm_config)]
+    pub amm_config: Box<Account<'info, AmmConfig>>,
+
+    /// Swap in and add liquidity for this pool
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// Store the information of market marking in range
+    #[account(
+        init_if_needed,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &tick_lower_index.to_be_bytes(),
+            &tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        payer = payer,
+        space = ProtocolPositionState::LEN
+    )]
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// CHECK:  Account to store data for the position's lower tick
+    #[account(
+        mut,
+        seeds = [
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &tick_array_lower_start_index.to_be_bytes(),
+        ],
+        bump,
+    )]
+    pub tick_array_lower: UncheckedAccount<'info>,
+
+    /// CHECK: Account to store data for the position's upper tick
+    #[account(
+        mut,
+        seeds = [
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &tick_array_upper_start_index.to_be_bytes(),
+        ],
+        bump,
+    )]
+    pub tick_array_upper: UncheckedAccount<'info>,
+
+    /// personal position state
+    #[account(
+        init,
+        seeds = [POSITION_SEED.as_bytes(), position_nft_mint.key().as_ref()],
+        bump,
+        payer = payer,
+        space = PersonalPositionState::LEN
+    )]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// The token_0 account of the payer, swapped from or to and deposited to the pool
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint
+    )]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token_1 account of the payer, swapped from or to and deposited to the pool
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint
+    )]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The program account for the most recent oracle observation
+    #[account(mut, address = pool_state.load()?.observation_key)]
+    pub observation_state: AccountLoader<'info, ObservationState>,
+
+    /// Sysvar for token mint and ATA creation
+    pub rent: Sysvar<'info, Rent>,
+
+    /// Program to create the position manager state account
+    pub system_program: Program<'info, System>,
+
+    /// Program to transfer for token account
+    pub token_program: Program<'info, Token>,
+
+    /// Program to create an ATA for receiving position NFT
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    /// Program to create NFT mint/token account and transfer for token22 account
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// CHECK:
+    #[account(
+        address = spl_memo::id()
+    )]
+    pub memo_program: UncheckedAccount<'info>,
+
+    /// The mint of token vault 0
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token vault 1
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+    // remaining accounts
+    // tickarray_bitmap_extension: must be the first account if the position or the swap needs it
+    // tick_array_account_1 of the swap
+    // tick_array_account_2 of the swap
+    // tick_array_account_...
+}
+
+/// Swap part of one token for the other inside the pool, then open a position with everything
+/// left of `amount_0_max` and `amount_1_max` at the price after the swap. The swap and the deposit
+/// happen in one instruction, so the price can't move between them.
+pub fn open_position_with_swap<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, OpenPositionWithSwap<'info>>,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    swap_amount_in: u64,
+    zero_for_one: bool,
+    sqrt_price_limit_x64: u128,
+    liquidity_min: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+    with_metadata: bool,
+) -> Result<()> {
+    // the swap is paid from the budget of its input token
+    require_gte!(
+        if zero_for_one {
+            amount_0_max
+        } else {
+            amount_1_max
+        },
+        swap_amount_in,
+        ErrorCode::PriceSlippageCheck
+    );
+
+    let (amount_0_max, amount_1_max) = if swap_amount_in == 0 {
+        (amount_0_max, amount_1_max)
+    } else {
+        let balance_0_before = ctx.accounts.token_account_0.amount;
+        let balance_1_before = ctx.accounts.token_account_1.amount;
+        let accounts = &ctx.accounts;
+        let (input_token_account, output_token_account, input_vault, output_vault) = if zero_for_one
+        {
+            (
+                &accounts.token_account_0,
+                &accounts.token_account_1,
+                &accounts.token_vault_0,
+                &accounts.token_vault_1,
+            )
+        } else {
+            (
+                &accounts.token_account_1,
+                &accounts.token_account_0,
+                &accounts.token_vault_1,
+                &accounts.token_vault_0,
+            )
+        };
+        let (input_vault_mint, output_vault_mint) = if zero_for_one {
+            (&accounts.vault_0_mint, &accounts.vault_1_mint)
+        } else {
+            (&accounts.vault_1_mint, &accounts.vault_0_mint)
+        };
+        exact_internal_v2(
+            &mut SwapSingleV2 {
+                payer: accounts.payer.clone(),
+                amm_config: accounts.amm_config.clone(),
+                pool_state: accounts.pool_state.clone(),
+                input_token_account: input_token_account.clone(),
+                output_token_account: output_token_account.clone(),
+                input_vault: input_vault.clone(),
+                output_vault: output_vault.clone(),
+                observation_state: accounts.observation_state.clone(),
+                token_program: accounts.token_program.clone(),
+                token_program_2022: accounts.token_program_2022.clone(),
+                memo_program: accounts.memo_program.clone(),
+                input_vault_mint: input_vault_mint.clone(),
+                output_vault_mint: output_vault_mint.clone(),
+            },
+            ctx.remaining_accounts,
+            swap_amount_in,
+            if sqrt_price_limit_x64 == 0 {
+                if zero_for_one {
+                    tick_math::MIN_SQRT_PRICE_X64 + 1
+                } else {
+                    tick_math::MAX_SQRT_PRICE_X64 - 1
+                }
+            } else {
+                sqrt_price_limit_x64
+            },
+            true,
+        )?;
+
+        // the swap may stop at the price limit, the budgets move by what it really paid and received
+        ctx.accounts.token_account_0.reload()?;
+        ctx.accounts.token_account_1.reload()?;
+        let balance_0_after = ctx.accounts.token_account_0.amount;
+        let balance_1_after = ctx.accounts.token_account_1.amount;
+        if zero_for_one {
+            (
+                amount_0_max
+                    .checked_sub(balance_0_before.checked_sub(balance_0_after).unwrap())
+                    .unwrap(),
+                amount_1_max
+                    .checked_add(balance_1_after.checked_sub(balance_1_before).unwrap())
+                    .unwrap(),
+            )
+        } else {
+            (
+                amount_0_max
+                    .checked_add(balance_0_after.checked_sub(balance_0_before).unwrap())
+                    .unwrap(),
+                amount_1_max
+                    .checked_sub(balance_1_before.checked_sub(balance_1_after).unwrap())
+                    .unwrap(),
+            )
+        }
+    };
+
+    // the most liquidity the budgets add at the price after the swap, the deposit pays the
+    // transfer fees on top of the amounts
+    let liquidity = {
+        let pool_state = ctx.accounts.pool_state.load()?;
+        liquidity_math::get_liquidity_from_amounts(
+            pool_state.sqrt_price_x64,
+            tick_math::get_sqrt_price_at_tick(tick_lower_index)?,
+            tick_math::get_sqrt_price_at_tick(tick_upper_index)?,
+            amount_0_max
+                .checked_sub(util::get_transfer_fee(
+                    ctx.accounts.vault_0_mint.clone(),
+                    amount_0_max,
+                )?)
+                .unwrap(),
+            amount_1_max
+                .checked_sub(util::get_transfer_fee(
+                    ctx.accounts.vault_1_mint.clone(),
+                    amount_1_max,
+                )?)
+                .unwrap(),
+        )?
+    };
+    require_gte!(liquidity, liquidity_min, ErrorCode::PriceSlippageCheck);
+    require_gt!(liquidity, 0, ErrorCode::ForbidBothZeroForSupplyLiquidity);
+
+    create_position_nft_mint_with_extensions(
+        &ctx.accounts.payer,
+        &ctx.accounts.position_nft_mint,
+        &ctx.accounts.pool_state.to_account_info(),
+        &ctx.accounts.personal_position.to_account_info(),
+        &ctx.accounts.system_program,
+        &ctx.accounts.token_program_2022,
+        with_metadata,
+    )?;
+
+    // create user position nft account
+    create(CpiContext::new(
+        ctx.accounts.associated_token_program.to_account_info(),
+        Create {
+            payer: ctx.accounts.payer.to_account_info(),
+            associated_token: ctx.accounts.position_nft_account.to_account_info(),
+            authority: ctx.accounts.position_nft_owner.to_account_info(),
+            mint: ctx.accounts.position_nft_mint.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info(),
+            token_program: ctx.accounts.token_program_2022.to_account_info(),
+        },
+    ))?;
+
+    open_position(
+        &ctx.accounts.payer,
+        &ctx.accounts.position_nft_owner,
+        &ctx.accounts.position_nft_mint,
+        &ctx.accounts.position_nft_account,
+        None,
+        &ctx.accounts.pool_state,
+        &ctx.accounts.tick_array_lower,
+        &ctx.accounts.tick_array_upper,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
+        &ctx.accounts.token_account_0.to_account_info(),
+        &ctx.accounts.token_account_1.to_account_info(),
+        &ctx.accounts.token_vault_0.to_account_info(),
+        &ctx.accounts.token_vault_1.to_account_info(),
+        &ctx.accounts.rent,
+        &ctx.accounts.system_program,
+        &ctx.accounts.token_program,
+        &ctx.accounts.associated_token_program,
+        None,
+        Some(&ctx.accounts.token_program_2022),
+        Some(ctx.accounts.vault_0_mint.clone()),
+        Some(ctx.accounts.vault_1_mint.clone()),
+        &ctx.remaining_accounts,
+        ctx.bumps.protocol_position,
+        ctx.bumps.personal_position,
+        liquidity,
+        amount_0_max,
+        amount_1_max,
+        tick_lower_index,
+        tick_upper_index,
+        tick_array_lower_start_index,
+        tick_array_upper_start_index,
+        with_metadata,
+        None,
+        true,
+    )
+}
@@ -1,4 +1,5 @@
-use super::open_position::open_position;
+use super::open_position::{open_position, valid_position_nft_owner};
+use crate::error::ErrorCode;
 use crate::states::*;
 use crate::util::create_position_nft_mint_with_extensions;
 use anchor_lang::prelude::*;
@@ -13,7 +14,10 @@ pub struct OpenPositionWithToken22Nft<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    /// CHECK: Receives the position NFT
+    /// CHECK: Receives the position NFT, it may be another account than the payer
+    #[account(
+        constraint = valid_position_nft_owner(&position_nft_owner.key()) @ ErrorCode::InvalidPositionNftOwner
+    )]
     pub position_nft_owner: UncheckedAccount<'info>,
 
     /// Unique token mint address, initialize in constract
@@ -41,7 +45,7 @@ pub struct OpenPositionWithToken22Nft<'info> {
         payer = payer,
         space = ProtocolPositionState::LEN
     )]
-    pub protocol_position: Box<Account<'info, ProtocolPositionState>>,
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
 
     /// CHECK:  Account to store data for the position's lower tick
     #[account(
@@ -75,7 +79,7 @@ pub struct OpenPositionWithToken22Nft<'info> {
         payer = payer,
         space = PersonalPositionState::LEN
     )]
-    pub personal_position: Box<Account<'info, PersonalPositionState>>,
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
 
     /// The token_0 account deposit token to the pool
     #[account(
@@ -186,8 +190,8 @@ pub fn open_position_with_token22_nft<'a, 'b, 'c: 'info, 'info>(
         &ctx.accounts.pool_state,
         &ctx.accounts.tick_array_lower,
         &ctx.accounts.tick_array_upper,
-        &mut ctx.accounts.protocol_position,
-        &mut ctx.accounts.personal_position,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
         &ctx.accounts.token_account_0.to_account_info(),
         &ctx.accounts.token_account_1.to_account_info(),
         &ctx.accounts.token_vault_0.to_account_info(),
@@ -0,0 +1,74 @@
+use super::open_position_with_token22_nft::{
+    open_position_with_token22_nft, OpenPositionWithToken22Nft,
+};
+use crate::error::ErrorCode;
+use crate::libraries::{liquidity_math, tick_math};
+use crate::util::get_transfer_fee;
+use anchor_lang::prelude::*;
+
+/// Open a position with the most liquidity the desired amounts provide at the pool price of the
+/// execution. The liquidity is computed on-chain, so the client doesn't round it against a price
+/// that may have moved, and the deposit is checked against the min amounts instead.
+pub fn open_position_with_token_amounts<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, OpenPositionWithToken22Nft<'info>>,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    tick_array_lower_start_index: i32,
+    tick_array_upper_start_index: i32,
+    amount_0_desired: u64,
+    amount_1_desired: u64,
+    amount_0_min: u64,
+    amount_1_min: u64,
+    with_metadata: bool,
+) -> Result<()> {
+    let liquidity = {
+        let pool_state = ctx.accounts.pool_state.load()?;
+        // the vaults receive the desired amounts less the transfer fees
+        let amount_0 = amount_0_desired
+            .checked_sub(get_transfer_fee(
+                ctx.accounts.vault_0_mint.clone(),
+                amount_0_desired,
+            )?)
+            .unwrap();
+        let amount_1 = amount_1_desired
+            .checked_sub(get_transfer_fee(
+                ctx.accounts.vault_1_mint.clone(),
+                amount_1_desired,
+            )?)
+            .unwrap();
+        let liquidity = liquidity_math::get_liquidity_from_amounts(
+            pool_state.sqrt_price_x64,
+            tick_math::get_sqrt_price_at_tick(tick_lower_index)?,
+            tick_math::get_sqrt_price_at_tick(tick_upper_index)?,
+            amount_0,
+            amount_1,
+        )?;
+        require_gt!(liquidity, 0, ErrorCode::ForbidBothZeroForSupplyLiquidity);
+        // the amounts the position deposits, rounded up as the deposit is
+        let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+            pool_state.tick_current,
+            pool_state.sqrt_price_x64,
+            tick_lower_index,
+            tick_upper_index,
+            i128::try_from(liquidity).unwrap(),
+        )?;
+        require!(
+            amount_0 >= amount_0_min && amount_1 >= amount_1_min,
+            ErrorCode::PriceSlippageCheck
+        );
+        liquidity
+    };
+
+    open_position_with_token22_nft(
+        ctx,
+        liquidity,
+        amount_0_desired,
+        amount_1_desired,
+        tick_lower_index,
+        tick_upper_index,
+        tick_array_lower_start_index,
+        tick_array_upper_start_index,
+        with_metadata,
+        None,
+    )
+}
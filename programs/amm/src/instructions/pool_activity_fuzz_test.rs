@@ -0,0 +1,663 @@
+//! Stateful fuzzing of a pool. Random sequences of open, increase, decrease, swap and reward
+//! operations run against in-memory states built with the test fixtures, through the same
+//! functions the instructions use, and the global invariants of the pool are checked after
+//! every operation: the vaults cover everything owed, the liquidity of the pool and of its ticks
+//! matches the positions, and nothing accrues a negative amount. Catches bugs in the interaction
+//! of instructions that the unit tests of each instruction miss.
+
+use super::increase_liquidity::calculate_latest_token_fees;
+use super::open_position::modify_position;
+use super::swap::swap_internal;
+use crate::libraries::{big_num::U256, fixed_point_64, full_math::MulDiv};
+use crate::libraries::{liquidity_math, tick_math};
+use crate::states::*;
+use crate::test_fixtures::{
+    build_amm_config, build_personal_position_state, PoolBuilder, PoolFixture,
+};
+use anchor_lang::prelude::*;
+use proptest::prelude::*;
+use proptest::sample::Index;
+use std::collections::BTreeMap;
+
+const TICK_SPACING: u16 = 10;
+/// The tick arrays of the pool, positions and swaps stay within them
+const TICK_ARRAY_START_INDEXES: [i32; 4] = [-1200, -600, 0, 600];
+/// Positions opened before the operations and never decreased by them, they keep an initialized
+/// tick in every tick array so swaps find liquidity on both sides of the price
+const BACKSTOP_RANGES: [(i32, i32); 2] = [(-1190, 1190), (-590, 590)];
+const BACKSTOP_LIQUIDITY: u128 = 1_000_000_000_000;
+/// Swaps stop at the price of this tick, inside the ranges of the backstop positions
+const SWAP_TICK_LIMIT: i32 = 1100;
+const REWARD_EMISSIONS_PER_SECOND: u128 = 10;
+const START_TIME: u64 = 1_700_000_000;
+
+#[derive(Debug, Clone)]
+enum Operation {
+    Open {
+        tick_lower: i32,
+        tick_upper: i32,
+        liquidity: u128,
+    },
+    Increase {
+        position: Index,
+        liquidity: u128,
+    },
+    /// Remove a percentage of the liquidity of a position and collect its fees
+    Decrease {
+        position: Index,
+        percent: u8,
+    },
+    Swap {
+        zero_for_one: bool,
+        is_base_input: bool,
+        amount: u64,
+    },
+    /// Let time pass, emitting rewards
+    Advance {
+        seconds: u64,
+    },
+    CollectRewards {
+        position: Index,
+    },
+}
+
+fn operation_strategy() -> impl Strategy<Value = Operation> {
+    let tick_max = BACKSTOP_RANGES[0].1 / TICK_SPACING as i32;
+    prop_oneof![
+        (-tick_max..tick_max, 1..60i32, 1..BACKSTOP_LIQUIDITY).prop_map(
+            move |(lower, width, liquidity)| Operation::Open {
+                tick_lower: lower * TICK_SPACING as i32,
+                tick_upper: (lower + width).min(tick_max) * TICK_SPACING as i32,
+                liquidity,
+            }
+        ),
+        (any::<Index>(), 1..BACKSTOP_LIQUIDITY).prop_map(|(position, liquidity)| {
+            Operation::Increase {
+                position,
+                liquidity,
+            }
+        }),
+        (any::<Index>(), 0..=100u8)
+            .prop_map(|(position, percent)| Operation::Decrease { position, percent }),
+        (any::<bool>(), any::<bool>(), 1..10_000_000_000u64).prop_map(
+            |(zero_for_one, is_base_input, amount)| Operation::Swap {
+                zero_for_one,
+                is_base_input,
+                amount,
+            }
+        ),
+        (1..10_000u64).prop_map(|seconds| Operation::Advance { seconds }),
+        any::<Index>().prop_map(|position| Operation::CollectRewards { position }),
+    ]
+}
+
+/// A pool with its positions and the balances of its vaults
+struct PoolSimulation {
+    fixture: PoolFixture,
+    protocol_positions: BTreeMap<(i32, i32), ProtocolPositionState>,
+    positions: Vec<PersonalPositionState>,
+    vault_0: u64,
+    vault_1: u64,
+    timestamp: u64,
+    /// The global growths at the last check, they never decrease
+    fee_growth_global_0_x64: u128,
+    fee_growth_global_1_x64: u128,
+    reward_growth_global_x64: u128,
+}
+
+impl PoolSimulation {
+    fn new() -> Self {
+        let mut amm_config = build_amm_config(0, TICK_SPACING, 2500);
+        amm_config.protocol_fee_rate = 120_000;
+        amm_config.fund_fee_rate = 40_000;
+        let mut builder = PoolBuilder::new(TICK_SPACING).amm_config(amm_config);
+        for start_index in TICK_ARRAY_START_INDEXES {
+            builder = builder.tick_array(start_index, vec![]);
+        }
+        let fixture = builder.build();
+        {
+            let mut pool_state = fixture.pool_state.borrow_mut();
+            // the tick arrays start without initialized ticks, the positions set their bits
+            for start_index in TICK_ARRAY_START_INDEXES {
+                pool_state.flip_tick_array_bit(None, start_index).unwrap();
+            }
+            pool_state.reward_infos[0] = RewardInfo {
+                reward_state: RewardState::Opening as u8,
+                open_time: START_TIME,
+                end_time: START_TIME + 100 * 24 * 3600,
+                last_update_time: START_TIME,
+                emissions_per_second_x64: REWARD_EMISSIONS_PER_SECOND << fixed_point_64::RESOLUTION,
+                token_mint: Pubkey::new_unique(),
+                ..Default::default()
+            };
+        }
+        let mut simulation = Self {
+            fixture,
+            protocol_positions: BTreeMap::new(),
+            positions: Vec::new(),
+            vault_0: 0,
+            vault_1: 0,
+            timestamp: START_TIME,
+            fee_growth_global_0_x64: 0,
+            fee_growth_global_1_x64: 0,
+            reward_growth_global_x64: 0,
+        };
+        for (tick_lower, tick_upper) in BACKSTOP_RANGES {
+            simulation.open(tick_lower, tick_upper, BACKSTOP_LIQUIDITY);
+        }
+        simulation
+    }
+
+    fn tick_array_index(tick: i32) -> usize {
+        let start_index = TickArrayState::get_array_start_index(tick, TICK_SPACING);
+        TICK_ARRAY_START_INDEXES
+            .iter()
+            .position(|index| *index == start_index)
+            .unwrap()
+    }
+
+    fn tick_state(&self, tick: i32) -> TickState {
+        let tick_array = self.fixture.tick_array_states[Self::tick_array_index(tick)].borrow();
+        let offset = tick_array
+            .get_tick_offset_in_array(tick, TICK_SPACING)
+            .unwrap();
+        let mut tick_state = tick_array.ticks[offset];
+        tick_state.tick = tick;
+        tick_state
+    }
+
+    fn set_tick_state(&self, tick_state: TickState) {
+        self.fixture.tick_array_states[Self::tick_array_index(tick_state.tick)]
+            .borrow_mut()
+            .update_tick_state(tick_state.tick, TICK_SPACING, tick_state)
+            .unwrap();
+    }
+
+    /// Count the tick in its tick array and flip the bit of the array, as the liquidity
+    /// instructions do when a tick is initialized or cleared
+    fn flip_tick(&self, tick: i32, initialized: bool) {
+        let mut tick_array =
+            self.fixture.tick_array_states[Self::tick_array_index(tick)].borrow_mut();
+        let mut pool_state = self.fixture.pool_state.borrow_mut();
+        if initialized {
+            if tick_array.initialized_tick_count == 0 {
+                pool_state
+                    .flip_tick_array_bit(None, tick_array.start_tick_index)
+                    .unwrap();
+            }
+            tick_array.update_initialized_tick_count(true).unwrap();
+        } else {
+            tick_array.update_initialized_tick_count(false).unwrap();
+            if tick_array.initialized_tick_count == 0 {
+                pool_state
+                    .flip_tick_array_bit(None, tick_array.start_tick_index)
+                    .unwrap();
+            }
+        }
+    }
+
+    /// Change the liquidity of a position the way increase_liquidity and decrease_liquidity do,
+    /// returns the token amounts of the change
+    fn modify(&mut self, index: usize, liquidity_delta: i128) -> (u64, u64) {
+        let tick_lower = self.positions[index].tick_lower_index;
+        let tick_upper = self.positions[index].tick_upper_index;
+        let mut tick_lower_state = self.tick_state(tick_lower);
+        let mut tick_upper_state = self.tick_state(tick_upper);
+        let (amount_0, amount_1, flip_tick_lower, flip_tick_upper) = modify_position(
+            liquidity_delta,
+            &mut self.fixture.pool_state.borrow_mut(),
+            self.protocol_positions
+                .entry((tick_lower, tick_upper))
+                .or_default(),
+            &mut tick_lower_state,
+            &mut tick_upper_state,
+            self.timestamp,
+        )
+        .unwrap();
+        self.set_tick_state(tick_lower_state);
+        self.set_tick_state(tick_upper_state);
+        for (tick, flipped) in [(tick_lower, flip_tick_lower), (tick_upper, flip_tick_upper)] {
+            if flipped {
+                self.flip_tick(tick, liquidity_delta > 0);
+            }
+        }
+
+        let protocol_position = &self.protocol_positions[&(tick_lower, tick_upper)];
+        let position = &mut self.positions[index];
+        position.token_fees_owed_0 = calculate_latest_token_fees(
+            position.token_fees_owed_0,
+            position.fee_growth_inside_0_last_x64,
+            protocol_position.fee_growth_inside_0_last_x64,
+            position.liquidity,
+        );
+        position.token_fees_owed_1 = calculate_latest_token_fees(
+            position.token_fees_owed_1,
+            position.fee_growth_inside_1_last_x64,
+            protocol_position.fee_growth_inside_1_last_x64,
+            position.liquidity,
+        );
+        position.fee_growth_inside_0_last_x64 = protocol_position.fee_growth_inside_0_last_x64;
+        position.fee_growth_inside_1_last_x64 = protocol_position.fee_growth_inside_1_last_x64;
+        position
+            .update_rewards(protocol_position.reward_growth_inside, true)
+            .unwrap();
+        position.liquidity =
+            liquidity_math::add_delta(position.liquidity, liquidity_delta).unwrap();
+        (amount_0, amount_1)
+    }
+
+    fn deposit(&mut self, amount_0: u64, amount_1: u64) {
+        self.vault_0 = self.vault_0.checked_add(amount_0).unwrap();
+        self.vault_1 = self.vault_1.checked_add(amount_1).unwrap();
+    }
+
+    fn withdraw(&mut self, amount_0: u64, amount_1: u64) {
+        self.vault_0 = self
+            .vault_0
+            .checked_sub(amount_0)
+            .expect("vault 0 can't pay the withdrawal");
+        self.vault_1 = self
+            .vault_1
+            .checked_sub(amount_1)
+            .expect("vault 1 can't pay the withdrawal");
+    }
+
+    /// A position the operations may decrease, the backstop positions are skipped
+    fn user_position(&self, position: &Index) -> Option<usize> {
+        let user_positions = self.positions.len() - BACKSTOP_RANGES.len();
+        if user_positions == 0 {
+            None
+        } else {
+            Some(BACKSTOP_RANGES.len() + position.index(user_positions))
+        }
+    }
+
+    fn open(&mut self, tick_lower: i32, tick_upper: i32, liquidity: u128) {
+        let position = build_personal_position_state(
+            &self.fixture.pool_state.borrow(),
+            tick_lower,
+            tick_upper,
+            0,
+        );
+        self.positions.push(position);
+        let (amount_0, amount_1) = self.modify(self.positions.len() - 1, liquidity as i128);
+        self.deposit(amount_0, amount_1);
+    }
+
+    fn decrease(&mut self, index: usize, liquidity: u128) {
+        let (amount_0, amount_1) = self.modify(index, -(liquidity as i128));
+        let position = &mut self.positions[index];
+        let (fees_0, fees_1) = (position.token_fees_owed_0, position.token_fees_owed_1);
+        position.token_fees_owed_0 = 0;
+        position.token_fees_owed_1 = 0;
+        {
+            let mut pool_state = self.fixture.pool_state.borrow_mut();
+            pool_state.total_fees_claimed_token_0 += fees_0;
+            pool_state.total_fees_claimed_token_1 += fees_1;
+        }
+        self.withdraw(amount_0 + fees_0, amount_1 + fees_1);
+    }
+
+    fn collect_rewards(&mut self, index: usize) {
+        self.modify(index, 0);
+        let reward_info = &mut self.positions[index].reward_infos[0];
+        let reward_amount = reward_info.reward_amount_owed;
+        reward_info.reward_amount_owed = 0;
+        self.fixture.pool_state.borrow_mut().reward_infos[0].reward_claimed += reward_amount;
+    }
+
+    fn swap(&mut self, zero_for_one: bool, is_base_input: bool, amount: u64) {
+        let sqrt_price_limit_x64 = tick_math::get_sqrt_price_at_tick(if zero_for_one {
+            -SWAP_TICK_LIMIT
+        } else {
+            SWAP_TICK_LIMIT
+        })
+        .unwrap();
+        let pool_state_before = *self.fixture.pool_state.borrow();
+        if (zero_for_one && sqrt_price_limit_x64 >= pool_state_before.sqrt_price_x64)
+            || (!zero_for_one && sqrt_price_limit_x64 <= pool_state_before.sqrt_price_x64)
+        {
+            return;
+        }
+        let tick_arrays_before: Vec<TickArrayState> = self
+            .fixture
+            .tick_array_states
+            .iter()
+            .map(|tick_array| *tick_array.borrow())
+            .collect();
+        let observation_before = *self.fixture.observation_state.borrow();
+
+        let result = {
+            let mut tick_array_states = self.fixture.tick_array_states_mut();
+            if zero_for_one {
+                tick_array_states = tick_array_states.into_iter().rev().collect();
+            }
+            swap_internal(
+                &self.fixture.amm_config,
+                &mut self.fixture.pool_state.borrow_mut(),
+                &mut tick_array_states,
+                &mut self.fixture.observation_state.borrow_mut(),
+                &None,
+                amount,
+                sqrt_price_limit_x64,
+                zero_for_one,
+                is_base_input,
+                self.timestamp as u32,
+            )
+        };
+        match result {
+            Ok((amount_0, amount_1)) if amount_0 != 0 && amount_1 != 0 => {
+                if zero_for_one {
+                    self.deposit(amount_0, 0);
+                    self.withdraw(0, amount_1);
+                } else {
+                    self.deposit(0, amount_1);
+                    self.withdraw(amount_0, 0);
+                }
+            }
+            // the transaction of a failed swap reverts, and so do the states
+            _ => {
+                *self.fixture.pool_state.borrow_mut() = pool_state_before;
+                for (tick_array, before) in self
+                    .fixture
+                    .tick_array_states
+                    .iter()
+                    .zip(tick_arrays_before)
+                {
+                    *tick_array.borrow_mut() = before;
+                }
+                *self.fixture.observation_state.borrow_mut() = observation_before;
+            }
+        }
+    }
+
+    fn apply(&mut self, operation: &Operation) {
+        match operation {
+            Operation::Open {
+                tick_lower,
+                tick_upper,
+                liquidity,
+            } => self.open(*tick_lower, *tick_upper, *liquidity),
+            Operation::Increase {
+                position,
+                liquidity,
+            } => {
+                let (amount_0, amount_1) =
+                    self.modify(position.index(self.positions.len()), *liquidity as i128);
+                self.deposit(amount_0, amount_1);
+            }
+            Operation::Decrease { position, percent } => {
+                if let Some(index) = self.user_position(position) {
+                    let liquidity = self.positions[index].liquidity * u128::from(*percent) / 100;
+                    self.decrease(index, liquidity);
+                }
+            }
+            Operation::Swap {
+                zero_for_one,
+                is_base_input,
+                amount,
+            } => self.swap(*zero_for_one, *is_base_input, *amount),
+            Operation::Advance { seconds } => {
+                self.timestamp += seconds;
+                self.fixture
+                    .pool_state
+                    .borrow_mut()
+                    .update_reward_infos(self.timestamp)
+                    .unwrap();
+            }
+            Operation::CollectRewards { position } => {
+                self.collect_rewards(position.index(self.positions.len()))
+            }
+        }
+    }
+
+    /// Remove all the liquidity and collect everything owed, the vaults keep at least the
+    /// protocol and fund fees
+    fn wind_down(&mut self) {
+        for index in 0..self.positions.len() {
+            self.decrease(index, self.positions[index].liquidity);
+            self.collect_rewards(index);
+        }
+        self.check_invariants();
+        let pool_state = self.fixture.pool_state.borrow();
+        assert_eq!({ pool_state.liquidity }, 0);
+        assert!(self.vault_0 >= pool_state.protocol_fees_token_0 + pool_state.fund_fees_token_0);
+        assert!(self.vault_1 >= pool_state.protocol_fees_token_1 + pool_state.fund_fees_token_1);
+        for tick_array in self.fixture.tick_array_states.iter() {
+            assert_eq!(tick_array.borrow().initialized_tick_count, 0);
+        }
+    }
+
+    fn check_invariants(&mut self) {
+        let pool_state = *self.fixture.pool_state.borrow();
+        let reward_info = pool_state.reward_infos[0];
+
+        let mut active_liquidity = 0;
+        let mut tick_liquidity: BTreeMap<i32, (u128, i128)> = BTreeMap::new();
+        let mut range_liquidity: BTreeMap<(i32, i32), u128> = BTreeMap::new();
+        // what the positions can withdraw, and the fees and rewards owed to them
+        let mut claims_0 =
+            u128::from(pool_state.protocol_fees_token_0 + pool_state.fund_fees_token_0);
+        let mut claims_1 =
+            u128::from(pool_state.protocol_fees_token_1 + pool_state.fund_fees_token_1);
+        let mut fees_owed_0 = 0u128;
+        let mut fees_owed_1 = 0u128;
+        let mut rewards_owed = u128::from(reward_info.reward_claimed);
+        for position in self.positions.iter() {
+            let (tick_lower, tick_upper) = (position.tick_lower_index, position.tick_upper_index);
+            let liquidity = position.liquidity;
+            if pool_state.tick_current >= tick_lower && pool_state.tick_current < tick_upper {
+                active_liquidity += liquidity;
+            }
+            let lower = tick_liquidity.entry(tick_lower).or_default();
+            lower.0 += liquidity;
+            lower.1 += liquidity as i128;
+            let upper = tick_liquidity.entry(tick_upper).or_default();
+            upper.0 += liquidity;
+            upper.1 -= liquidity as i128;
+            *range_liquidity.entry((tick_lower, tick_upper)).or_default() += liquidity;
+
+            let tick_lower_state = self.tick_state(tick_lower);
+            let tick_upper_state = self.tick_state(tick_upper);
+            let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) =
+                tick_array::get_fee_growth_inside(
+                    &tick_lower_state,
+                    &tick_upper_state,
+                    pool_state.tick_current,
+                    pool_state.fee_growth_global_0_x64,
+                    pool_state.fee_growth_global_1_x64,
+                )
+                .unwrap();
+            let reward_growths_inside = tick_array::get_reward_growths_inside(
+                &tick_lower_state,
+                &tick_upper_state,
+                pool_state.tick_current,
+                &pool_state.reward_infos,
+            )
+            .unwrap();
+            // the growths inside the range since the last update of the position are positive
+            for (growth_inside_x64, growth_inside_last_x64) in [
+                (
+                    fee_growth_inside_0_x64,
+                    position.fee_growth_inside_0_last_x64,
+                ),
+                (
+                    fee_growth_inside_1_x64,
+                    position.fee_growth_inside_1_last_x64,
+                ),
+                (
+                    reward_growths_inside[0],
+                    position.reward_infos[0].growth_inside_last_x64,
+                ),
+            ] {
+                if liquidity > 0 {
+                    assert!(
+                        (growth_inside_x64.wrapping_sub(growth_inside_last_x64) as i128) >= 0,
+                        "negative growth inside [{}, {}]",
+                        tick_lower,
+                        tick_upper
+                    );
+                }
+            }
+
+            fees_owed_0 += u128::from(calculate_latest_token_fees(
+                position.token_fees_owed_0,
+                position.fee_growth_inside_0_last_x64,
+                fee_growth_inside_0_x64,
+                liquidity,
+            ));
+            fees_owed_1 += u128::from(calculate_latest_token_fees(
+                position.token_fees_owed_1,
+                position.fee_growth_inside_1_last_x64,
+                fee_growth_inside_1_x64,
+                liquidity,
+            ));
+            rewards_owed += u128::from(position.reward_infos[0].reward_amount_owed);
+            rewards_owed += U256::from(
+                reward_growths_inside[0]
+                    .wrapping_sub(position.reward_infos[0].growth_inside_last_x64),
+            )
+            .mul_div_floor(U256::from(liquidity), U256::from(fixed_point_64::Q64))
+            .unwrap()
+            .as_u128();
+            if liquidity > 0 {
+                let (amount_0, amount_1) = liquidity_math::get_delta_amounts_signed(
+                    pool_state.tick_current,
+                    pool_state.sqrt_price_x64,
+                    tick_lower,
+                    tick_upper,
+                    -(liquidity as i128),
+                )
+                .unwrap();
+                claims_0 += u128::from(amount_0);
+                claims_1 += u128::from(amount_1);
+            }
+        }
+
+        // liquidity consistency
+        assert_eq!({ pool_state.liquidity }, active_liquidity);
+        for (tick, (liquidity_gross, liquidity_net)) in tick_liquidity.iter() {
+            let tick_state = self.tick_state(*tick);
+            assert_eq!(
+                { tick_state.liquidity_gross },
+                *liquidity_gross,
+                "tick {}",
+                tick
+            );
+            assert_eq!(
+                { tick_state.liquidity_net },
+                *liquidity_net,
+                "tick {}",
+                tick
+            );
+        }
+        for (range, liquidity) in range_liquidity.iter() {
+            let range_position_liquidity = self.protocol_positions[range].liquidity;
+            assert_eq!(range_position_liquidity, *liquidity);
+        }
+        for tick_array in self.fixture.tick_array_states.iter() {
+            let tick_array = tick_array.borrow();
+            let initialized_ticks = tick_array
+                .ticks
+                .iter()
+                .filter(|tick_state| tick_state.is_initialized())
+                .count();
+            assert_eq!(
+                usize::from(tick_array.initialized_tick_count),
+                initialized_ticks
+            );
+            for tick_state in tick_array.ticks.iter() {
+                if tick_state.is_initialized() {
+                    assert!(tick_liquidity.contains_key(&{ tick_state.tick }));
+                }
+            }
+        }
+
+        // vault conservation, the vaults pay every position out in full
+        assert!(
+            u128::from(self.vault_0) >= claims_0 + fees_owed_0,
+            "vault_0:{}, claims:{}, fees owed:{}",
+            self.vault_0,
+            claims_0,
+            fees_owed_0
+        );
+        assert!(
+            u128::from(self.vault_1) >= claims_1 + fees_owed_1,
+            "vault_1:{}, claims:{}, fees owed:{}",
+            self.vault_1,
+            claims_1,
+            fees_owed_1
+        );
+        // the fees owed were earned, as decrease_liquidity requires before paying them
+        assert!(
+            u128::from(pool_state.total_fees_token_0 - pool_state.total_fees_claimed_token_0)
+                >= fees_owed_0
+        );
+        assert!(
+            u128::from(pool_state.total_fees_token_1 - pool_state.total_fees_claimed_token_1)
+                >= fees_owed_1
+        );
+        // no more rewards are owed than emitted
+        assert!(
+            rewards_owed <= u128::from(reward_info.reward_total_emissioned),
+            "rewards owed:{}, emitted:{}",
+            rewards_owed,
+            { reward_info.reward_total_emissioned }
+        );
+
+        // the global growths only increase
+        assert!(pool_state.fee_growth_global_0_x64 >= self.fee_growth_global_0_x64);
+        assert!(pool_state.fee_growth_global_1_x64 >= self.fee_growth_global_1_x64);
+        assert!(reward_info.reward_growth_global_x64 >= self.reward_growth_global_x64);
+        self.fee_growth_global_0_x64 = pool_state.fee_growth_global_0_x64;
+        self.fee_growth_global_1_x64 = pool_state.fee_growth_global_1_x64;
+        self.reward_growth_global_x64 = reward_info.reward_growth_global_x64;
+    }
+}
+
+#[test]
+fn pool_simulation_test() {
+    let mut simulation = PoolSimulation::new();
+    simulation.check_invariants();
+    for operation in [
+        Operation::Open {
+            tick_lower: -100,
+            tick_upper: 100,
+            liquidity: 1_000_000,
+        },
+        Operation::Swap {
+            zero_for_one: true,
+            is_base_input: true,
+            amount: 10_000_000_000,
+        },
+        Operation::Advance { seconds: 60 },
+        Operation::Swap {
+            zero_for_one: false,
+            is_base_input: false,
+            amount: 1_000_000,
+        },
+    ] {
+        simulation.apply(&operation);
+        simulation.check_invariants();
+    }
+    // the first swap moved the price
+    assert!(simulation.fixture.pool_state.borrow().total_fees_token_0 > 0);
+    simulation.wind_down();
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn pool_activity_invariants_test(
+        operations in prop::collection::vec(operation_strategy(), 1..64),
+    ) {
+        let mut simulation = PoolSimulation::new();
+        simulation.check_invariants();
+        for operation in operations.iter() {
+            simulation.apply(operation);
+            simulation.check_invariants();
+        }
+        simulation.wind_down();
+    }
+}
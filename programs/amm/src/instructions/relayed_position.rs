@@ -0,0 +1,366 @@
+use super::decrease_liquidity::decrease_liquidity;
+use super::increase_liquidity::increase_liquidity;
+use crate::error::ErrorCode;
+use crate::states::*;
+use crate::util::verify_ed25519_signature;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{Mint, Token2022, TokenAccount};
+
+#[derive(Accounts)]
+pub struct RelayedCollect<'info> {
+    /// Submits the action signed by the position owner and pays for the transaction
+    pub relayer: Signer<'info>,
+
+    /// The token account for the tokenized position, its owner signs the relayed action
+    #[account(
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
+        constraint = nft_account.amount == 1,
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Collect fees and rewards for this position
+    #[account(mut, constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
+    )]
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// Token_0 vault
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Token_1 vault
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Stores init state for the lower tick
+    #[account(mut, constraint = tick_array_lower.load()?.pool_id == pool_state.key())]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// Stores init state for the upper tick
+    #[account(mut, constraint = tick_array_upper.load()?.pool_id == pool_state.key())]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    /// The token account of the position owner, or of its fee recipient if set, receiving the
+    /// token_0 fees
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint,
+        constraint = recipient_token_account_0.owner == personal_position.load()?.payout_owner(&nft_account.owner) @ ErrorCode::InvalidRelayTokenAccount
+    )]
+    pub recipient_token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The token account of the position owner, or of its fee recipient if set, receiving the
+    /// token_1 fees
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint,
+        constraint = recipient_token_account_1.owner == personal_position.load()?.payout_owner(&nft_account.owner) @ ErrorCode::InvalidRelayTokenAccount
+    )]
+    pub recipient_token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// SPL program to transfer out tokens
+    pub token_program: Program<'info, Token>,
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// The mint of token vault 0
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token vault 1
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: the instructions sysvar, to read the owner signature verified by the ed25519 program
+    #[account(address = sysvar::instructions::id())]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    // remaining account
+    // tick_array_bitmap: the tick array bitmap extension of the pool if needed
+    // for every reward: reward_token_vault, recipient_token_account of the position owner, reward_vault_mint
+}
+
+#[derive(Accounts)]
+pub struct RelayedIncreaseLiquidity<'info> {
+    /// Submits the action signed by the position owner and pays for the transaction, also the
+    /// transfer authority of the owner's token accounts, so it must be their approved delegate
+    pub relayer: Signer<'info>,
+
+    /// The token account for the tokenized position, its owner signs the relayed action
+    #[account(
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
+        constraint = nft_account.amount == 1,
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    #[account(
+        mut,
+        seeds = [
+            POSITION_SEED.as_bytes(),
+            pool_state.key().as_ref(),
+            &personal_position.load()?.tick_lower_index.to_be_bytes(),
+            &personal_position.load()?.tick_upper_index.to_be_bytes(),
+        ],
+        bump,
+        constraint = protocol_position.load()?.pool_id == pool_state.key(),
+    )]
+    pub protocol_position: AccountLoader<'info, ProtocolPositionState>,
+
+    /// Increase liquidity for this position
+    #[account(mut, constraint = personal_position.load()?.pool_id == pool_state.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// Stores init state for the lower tick
+    #[account(mut, constraint = tick_array_lower.load()?.pool_id == pool_state.key())]
+    pub tick_array_lower: AccountLoader<'info, TickArrayState>,
+
+    /// Stores init state for the upper tick
+    #[account(mut, constraint = tick_array_upper.load()?.pool_id == pool_state.key())]
+    pub tick_array_upper: AccountLoader<'info, TickArrayState>,
+
+    /// The position owner's token account spending token_0
+    #[account(
+        mut,
+        token::mint = token_vault_0.mint,
+        constraint = token_account_0.owner == nft_account.owner @ ErrorCode::InvalidRelayTokenAccount
+    )]
+    pub token_account_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position owner's token account spending token_1
+    #[account(
+        mut,
+        token::mint = token_vault_1.mint,
+        constraint = token_account_1.owner == nft_account.owner @ ErrorCode::InvalidRelayTokenAccount
+    )]
+    pub token_account_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_0
+    #[account(
+        mut,
+        constraint = token_vault_0.key() == pool_state.load()?.token_vault_0
+    )]
+    pub token_vault_0: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The address that holds pool tokens for token_1
+    #[account(
+        mut,
+        constraint = token_vault_1.key() == pool_state.load()?.token_vault_1
+    )]
+    pub token_vault_1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// Program to create mint account and mint tokens
+    pub token_program: Program<'info, Token>,
+
+    /// Token program 2022
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// The mint of token vault 0
+    #[account(
+        address = token_vault_0.mint
+    )]
+    pub vault_0_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The mint of token vault 1
+    #[account(
+        address = token_vault_1.mint
+    )]
+    pub vault_1_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: the instructions sysvar, to read the owner signature verified by the ed25519 program
+    #[account(address = sysvar::instructions::id())]
+    pub instructions_sysvar: UncheckedAccount<'info>,
+    // remaining account
+    // tick_array_bitmap: the tick array bitmap extension of the pool if needed
+}
+
+pub fn relayed_collect<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, RelayedCollect<'info>>,
+    nonce: u64,
+    expires_at: u64,
+) -> Result<()> {
+    let owner = ctx.accounts.nft_account.owner;
+    let message = RelayedPositionMessage {
+        action: RelayAction::Collect,
+        personal_position: ctx.accounts.personal_position.key(),
+        relayer: ctx.accounts.relayer.key(),
+        nonce,
+        expires_at,
+        liquidity: 0,
+        amount_0_max: 0,
+        amount_1_max: 0,
+        base_flag: None,
+    };
+    authorize_relayed_action(
+        &ctx.accounts.instructions_sysvar,
+        &mut ctx.accounts.personal_position.load_mut()?,
+        &owner,
+        &message,
+    )?;
+    check_reward_recipients(
+        ctx.accounts.pool_state.key(),
+        &ctx.remaining_accounts,
+        &ctx.accounts.personal_position.load()?.payout_owner(&owner),
+    )?;
+
+    // decreasing zero liquidity only collects the fees and rewards owed
+    decrease_liquidity(
+        &ctx.accounts.pool_state,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
+        &owner,
+        &ctx.accounts.token_vault_0.to_account_info(),
+        &ctx.accounts.token_vault_1.to_account_info(),
+        &ctx.accounts.tick_array_lower,
+        &ctx.accounts.tick_array_upper,
+        &ctx.accounts.recipient_token_account_0.to_account_info(),
+        &ctx.accounts.recipient_token_account_1.to_account_info(),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.clone()),
+        None,
+        Some(ctx.accounts.vault_0_mint.clone()),
+        Some(ctx.accounts.vault_1_mint.clone()),
+        &ctx.remaining_accounts,
+        0,
+        0,
+        0,
+    )?;
+
+    emit!(RelayedPositionActionEvent {
+        personal_position: message.personal_position,
+        owner,
+        relayer: message.relayer,
+        action: message.action,
+        nonce,
+    });
+    Ok(())
+}
+
+pub fn relayed_increase_liquidity<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, RelayedIncreaseLiquidity<'info>>,
+    liquidity: u128,
+    amount_0_max: u64,
+    amount_1_max: u64,
+    base_flag: Option<bool>,
+    nonce: u64,
+    expires_at: u64,
+) -> Result<()> {
+    let owner = ctx.accounts.nft_account.owner;
+    let message = RelayedPositionMessage {
+        action: RelayAction::IncreaseLiquidity,
+        personal_position: ctx.accounts.personal_position.key(),
+        relayer: ctx.accounts.relayer.key(),
+        nonce,
+        expires_at,
+        liquidity,
+        amount_0_max,
+        amount_1_max,
+        base_flag,
+    };
+    authorize_relayed_action(
+        &ctx.accounts.instructions_sysvar,
+        &mut ctx.accounts.personal_position.load_mut()?,
+        &owner,
+        &message,
+    )?;
+
+    increase_liquidity(
+        &ctx.accounts.relayer,
+        &ctx.accounts.pool_state,
+        &ctx.accounts.protocol_position,
+        &ctx.accounts.personal_position,
+        &ctx.accounts.tick_array_lower,
+        &ctx.accounts.tick_array_upper,
+        &ctx.accounts.token_account_0.to_account_info(),
+        &ctx.accounts.token_account_1.to_account_info(),
+        &ctx.accounts.token_vault_0.to_account_info(),
+        &ctx.accounts.token_vault_1.to_account_info(),
+        &ctx.accounts.token_program,
+        Some(&ctx.accounts.token_program_2022),
+        Some(ctx.accounts.vault_0_mint.clone()),
+        Some(ctx.accounts.vault_1_mint.clone()),
+        &ctx.remaining_accounts,
+        liquidity,
+        amount_0_max,
+        amount_1_max,
+        base_flag,
+    )?;
+
+    emit!(RelayedPositionActionEvent {
+        personal_position: message.personal_position,
+        owner,
+        relayer: message.relayer,
+        action: message.action,
+        nonce,
+    });
+    Ok(())
+}
+
+/// Check the owner signed the action for this relayer, it hasn't expired, and consume its nonce
+fn authorize_relayed_action(
+    instructions_sysvar: &AccountInfo,
+    personal_position: &mut PersonalPositionState,
+    owner: &Pubkey,
+    message: &RelayedPositionMessage,
+) -> Result<()> {
+    verify_ed25519_signature(instructions_sysvar, owner, &message.to_bytes())?;
+    require_gte!(
+        message.expires_at,
+        Clock::get()?.unix_timestamp as u64,
+        ErrorCode::RelayMessageExpired
+    );
+    personal_position.use_relay_nonce(message.nonce)
+}
+
+/// The rewards must be collected to the owner's token accounts too, the reward accounts come in
+/// groups of the reward vault, the recipient and the reward mint
+fn check_reward_recipients<'c: 'info, 'info>(
+    pool_id: Pubkey,
+    remaining_accounts: &'c [AccountInfo<'info>],
+    owner: &Pubkey,
+) -> Result<()> {
+    let tickarray_bitmap_extension_key = TickArrayBitmapExtension::key(pool_id);
+    let reward_accounts: Vec<&'c AccountInfo<'info>> = remaining_accounts
+        .iter()
+        .filter(|account_info| account_info.key() != tickarray_bitmap_extension_key)
+        .collect();
+    for reward_group in reward_accounts.chunks(3) {
+        if let Some(recipient_token_account) = reward_group.get(1) {
+            let recipient_token_account =
+                InterfaceAccount::<TokenAccount>::try_from(*recipient_token_account)?;
+            require_keys_eq!(
+                recipient_token_account.owner,
+                *owner,
+                ErrorCode::InvalidRelayTokenAccount
+            );
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use crate::states::*;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RollStats<'info> {
+    /// The stats to roll, of any pool
+    #[account(mut)]
+    pub pool_stats: AccountLoader<'info, PoolStatsState>,
+}
+
+/// Drop the buckets of the pool stats that fell out of the window, so that the totals read by
+/// the consumers stay current when the pool doesn't swap
+pub fn roll_stats(ctx: Context<RollStats>) -> Result<()> {
+    let block_timestamp = Clock::get()?.unix_timestamp as u64;
+    ctx.accounts.pool_stats.load_mut()?.roll(block_timestamp)
+}
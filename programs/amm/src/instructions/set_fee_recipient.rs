@@ -0,0 +1,48 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+#[derive(Accounts)]
+pub struct SetFeeRecipient<'info> {
+    /// The position nft owner
+    pub nft_owner: Signer<'info>,
+
+    /// The token account for the tokenized position
+    #[account(
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+}
+
+pub fn set_fee_recipient(ctx: Context<SetFeeRecipient>, fee_recipient: Pubkey) -> Result<()> {
+    require_keys_neq!(
+        fee_recipient,
+        Pubkey::default(),
+        ErrorCode::InvalidFeeRecipient
+    );
+    let personal_position = &mut ctx.accounts.personal_position.load_mut()?;
+    // bound to the nft owner, a later holder of the nft isn't paying out to it
+    personal_position.set_fee_recipient(fee_recipient, &ctx.accounts.nft_owner.key());
+    emit_fee_recipient(personal_position);
+    Ok(())
+}
+
+pub fn clear_fee_recipient(ctx: Context<SetFeeRecipient>) -> Result<()> {
+    let personal_position = &mut ctx.accounts.personal_position.load_mut()?;
+    personal_position.clear_fee_recipient();
+    emit_fee_recipient(personal_position);
+    Ok(())
+}
+
+fn emit_fee_recipient(personal_position: &PersonalPositionState) {
+    emit!(SetFeeRecipientEvent {
+        position_nft_mint: personal_position.nft_mint,
+        fee_recipient: personal_position.fee_recipient,
+    });
+}
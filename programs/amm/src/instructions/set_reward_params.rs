@@ -57,6 +57,7 @@ pub fn set_reward_params<'a, 'b, 'c: 'info, 'info>(
     require_gt!(open_time, current_timestamp);
 
     let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.check_unlocked()?;
 
     if !admin_operator {
         require_keys_eq!(ctx.accounts.authority.key(), pool_state.owner);
@@ -68,6 +69,14 @@ pub fn set_reward_params<'a, 'b, 'c: 'info, 'info>(
     if !reward_info.initialized() {
         return err!(ErrorCode::UnInitializedRewardInfo);
     }
+    if reward_info.consolidated() {
+        return err!(ErrorCode::RewardConsolidated);
+    }
+    // a fee rebate reward spends protocol fees instead of a funded vault
+    let fee_rebate = pool_state.is_fee_rebate_reward(reward_index as usize);
+    if fee_rebate && !admin_operator {
+        return err!(ErrorCode::NotApproved);
+    }
 
     let reward_amount = if admin_operator {
         admin_update(
@@ -94,7 +103,7 @@ pub fn set_reward_params<'a, 'b, 'c: 'info, 'info>(
 
     pool_state.reward_infos[reward_index as usize] = reward_info;
 
-    if reward_amount > 0 {
+    if reward_amount > 0 && !fee_rebate {
         let mut remaining_accounts = ctx.remaining_accounts.iter();
 
         let reward_token_vault =
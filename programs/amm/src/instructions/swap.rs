@@ -1,16 +1,12 @@
 use crate::error::ErrorCode;
-use crate::libraries::{
-    big_num::U128, fixed_point_64, full_math::MulDiv, liquidity_math, swap_math, tick_math,
-};
+use crate::libraries::{swap_quote, tick_math};
 use crate::states::*;
 use crate::util::*;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
 use std::cell::RefMut;
 use std::collections::VecDeque;
-#[cfg(feature = "enable-log")]
-use std::convert::identity;
-use std::ops::{Deref, Neg};
+use std::ops::Deref;
 
 #[derive(Accounts)]
 pub struct SwapSingle<'info> {
@@ -29,7 +25,8 @@ pub struct SwapSingle<'info> {
     #[account(mut)]
     pub input_token_account: Box<Account<'info, TokenAccount>>,
 
-    /// The user token account for output token
+    /// The token account receiving the output token, it can be owned by anyone so the output is
+    /// delivered to a recipient other than the payer
     #[account(mut)]
     pub output_token_account: Box<Account<'info, TokenAccount>>,
 
@@ -84,47 +81,6 @@ pub struct SwapAccounts<'b, 'info> {
     pub observation_state: &'b mut AccountLoader<'info, ObservationState>,
 }
 
-// the top level state of the swap, the results of which are recorded in storage at the end
-#[derive(Debug)]
-pub struct SwapState {
-    // the amount remaining to be swapped in/out of the input/output asset
-    pub amount_specified_remaining: u64,
-    // the amount already swapped out/in of the output/input asset
-    pub amount_calculated: u64,
-    // current sqrt(price)
-    pub sqrt_price_x64: u128,
-    // the tick associated with the current price
-    pub tick: i32,
-    // the global fee growth of the input token
-    pub fee_growth_global_x64: u128,
-    // the global fee of the input token
-    pub fee_amount: u64,
-    // amount of input token paid as protocol fee
-    pub protocol_fee: u64,
-    // amount of input token paid as fund fee
-    pub fund_fee: u64,
-    // the current liquidity in range
-    pub liquidity: u128,
-}
-
-#[derive(Default)]
-struct StepComputations {
-    // the price at the beginning of the step
-    sqrt_price_start_x64: u128,
-    // the next tick to swap to from the current tick in the swap direction
-    tick_next: i32,
-    // whether tick_next is initialized or not
-    initialized: bool,
-    // sqrt(price) for the next tick (1/0)
-    sqrt_price_next_x64: u128,
-    // how much is being swapped in in this step
-    amount_in: u64,
-    // how much is being swapped out
-    amount_out: u64,
-    // how much fee is being paid in
-    fee_amount: u64,
-}
-
 pub fn swap_internal<'b, 'info>(
     amm_config: &AmmConfig,
     pool_state: &mut RefMut<PoolState>,
@@ -137,329 +93,29 @@ pub fn swap_internal<'b, 'info>(
     is_base_input: bool,
     block_timestamp: u32,
 ) -> Result<(u64, u64)> {
-    require!(amount_specified != 0, ErrorCode::ZeroAmountSpecified);
     if !pool_state.get_status_by_bit(PoolStatusBitIndex::Swap) {
         return err!(ErrorCode::NotApproved);
     }
-    require!(
-        if zero_for_one {
-            sqrt_price_limit_x64 < pool_state.sqrt_price_x64
-                && sqrt_price_limit_x64 > tick_math::MIN_SQRT_PRICE_X64
-        } else {
-            sqrt_price_limit_x64 > pool_state.sqrt_price_x64
-                && sqrt_price_limit_x64 < tick_math::MAX_SQRT_PRICE_X64
-        },
-        ErrorCode::SqrtPriceLimitOverflow
-    );
-
-    let liquidity_start = pool_state.liquidity;
-
-    let updated_reward_infos = pool_state.update_reward_infos(block_timestamp as u64)?;
-
-    let mut state = SwapState {
-        amount_specified_remaining: amount_specified,
-        amount_calculated: 0,
-        sqrt_price_x64: pool_state.sqrt_price_x64,
-        tick: pool_state.tick_current,
-        fee_growth_global_x64: if zero_for_one {
-            pool_state.fee_growth_global_0_x64
-        } else {
-            pool_state.fee_growth_global_1_x64
-        },
-        fee_amount: 0,
-        protocol_fee: 0,
-        fund_fee: 0,
-        liquidity: liquidity_start,
-    };
-
     // check observation account is owned by the pool
     require_keys_eq!(observation_state.pool_id, pool_state.key());
 
-    let (mut is_match_pool_current_tick_array, first_vaild_tick_array_start_index) =
-        pool_state.get_first_initialized_tick_array(&tickarray_bitmap_extension, zero_for_one)?;
-    let mut current_vaild_tick_array_start_index = first_vaild_tick_array_start_index;
-
-    let mut tick_array_current = tick_array_states.pop_front().unwrap();
-    // find the first active tick array account
-    for _ in 0..tick_array_states.len() {
-        if tick_array_current.start_tick_index == current_vaild_tick_array_start_index {
-            break;
-        }
-        tick_array_current = tick_array_states
-            .pop_front()
-            .ok_or(ErrorCode::NotEnoughTickArrayAccount)?;
-    }
-    // check the first tick_array account is owned by the pool
-    require_keys_eq!(tick_array_current.pool_id, pool_state.key());
-    // check first tick array account is correct
-    require_eq!(
-        tick_array_current.start_tick_index,
-        current_vaild_tick_array_start_index,
-        ErrorCode::InvalidFirstTickArrayAccount
-    );
-
-    // continue swapping as long as we haven't used the entire input/output and haven't
-    // reached the price limit
-    while state.amount_specified_remaining != 0 && state.sqrt_price_x64 != sqrt_price_limit_x64 {
-        #[cfg(feature = "enable-log")]
-        msg!(
-            "while begin, is_base_input:{},fee_growth_global_x32:{}, state_sqrt_price_x64:{}, state_tick:{},state_liquidity:{},state.protocol_fee:{}, protocol_fee_rate:{}",
-            is_base_input,
-            state.fee_growth_global_x64,
-            state.sqrt_price_x64,
-            state.tick,
-            state.liquidity,
-            state.protocol_fee,
-            amm_config.protocol_fee_rate
-        );
-        // Save these three pieces of information for PriceChangeEvent
-        // let tick_before = state.tick;
-        // let sqrt_price_x64_before = state.sqrt_price_x64;
-        // let liquidity_before = state.liquidity;
-
-        let mut step = StepComputations::default();
-        step.sqrt_price_start_x64 = state.sqrt_price_x64;
-
-        let mut next_initialized_tick = if let Some(tick_state) = tick_array_current
-            .next_initialized_tick(state.tick, pool_state.tick_spacing, zero_for_one)?
-        {
-            Box::new(*tick_state)
-        } else {
-            if !is_match_pool_current_tick_array {
-                is_match_pool_current_tick_array = true;
-                Box::new(*tick_array_current.first_initialized_tick(zero_for_one)?)
-            } else {
-                Box::new(TickState::default())
-            }
-        };
-        #[cfg(feature = "enable-log")]
-        msg!(
-            "next_initialized_tick, status:{}, tick_index:{}, tick_array_current:{}",
-            next_initialized_tick.is_initialized(),
-            identity(next_initialized_tick.tick),
-            tick_array_current.key().to_string(),
-        );
-        if !next_initialized_tick.is_initialized() {
-            let next_initialized_tickarray_index = pool_state
-                .next_initialized_tick_array_start_index(
-                    &tickarray_bitmap_extension,
-                    current_vaild_tick_array_start_index,
-                    zero_for_one,
-                )?;
-            if next_initialized_tickarray_index.is_none() {
-                return err!(ErrorCode::LiquidityInsufficient);
-            }
-
-            while tick_array_current.start_tick_index != next_initialized_tickarray_index.unwrap() {
-                tick_array_current = tick_array_states
-                    .pop_front()
-                    .ok_or(ErrorCode::NotEnoughTickArrayAccount)?;
-                // check the tick_array account is owned by the pool
-                require_keys_eq!(tick_array_current.pool_id, pool_state.key());
-            }
-            current_vaild_tick_array_start_index = next_initialized_tickarray_index.unwrap();
-
-            let first_initialized_tick = tick_array_current.first_initialized_tick(zero_for_one)?;
-            next_initialized_tick = Box::new(*first_initialized_tick);
-        }
-        step.tick_next = next_initialized_tick.tick;
-        step.initialized = next_initialized_tick.is_initialized();
-
-        if step.tick_next < tick_math::MIN_TICK {
-            step.tick_next = tick_math::MIN_TICK;
-        } else if step.tick_next > tick_math::MAX_TICK {
-            step.tick_next = tick_math::MAX_TICK;
-        }
-        step.sqrt_price_next_x64 = tick_math::get_sqrt_price_at_tick(step.tick_next)?;
-
-        let target_price = if (zero_for_one && step.sqrt_price_next_x64 < sqrt_price_limit_x64)
-            || (!zero_for_one && step.sqrt_price_next_x64 > sqrt_price_limit_x64)
-        {
-            sqrt_price_limit_x64
-        } else {
-            step.sqrt_price_next_x64
-        };
-
-        if zero_for_one {
-            require_gte!(state.tick, step.tick_next);
-            require_gte!(step.sqrt_price_start_x64, step.sqrt_price_next_x64);
-            require_gte!(step.sqrt_price_start_x64, target_price);
-        } else {
-            require_gt!(step.tick_next, state.tick);
-            require_gte!(step.sqrt_price_next_x64, step.sqrt_price_start_x64);
-            require_gte!(target_price, step.sqrt_price_start_x64);
-        }
-        #[cfg(feature = "enable-log")]
-        msg!(
-            "sqrt_price_current_x64:{}, sqrt_price_target:{}, liquidity:{}, amount_remaining:{}",
-            step.sqrt_price_start_x64,
-            target_price,
-            state.liquidity,
-            state.amount_specified_remaining
-        );
-        let swap_step = swap_math::compute_swap_step(
-            step.sqrt_price_start_x64,
-            target_price,
-            state.liquidity,
-            state.amount_specified_remaining,
-            amm_config.trade_fee_rate,
-            is_base_input,
-            zero_for_one,
-            block_timestamp,
-        )?;
-        #[cfg(feature = "enable-log")]
-        msg!("{:#?}", swap_step);
-        if zero_for_one {
-            require_gte!(swap_step.sqrt_price_next_x64, target_price);
-        } else {
-            require_gte!(target_price, swap_step.sqrt_price_next_x64);
-        }
-        state.sqrt_price_x64 = swap_step.sqrt_price_next_x64;
-        step.amount_in = swap_step.amount_in;
-        step.amount_out = swap_step.amount_out;
-        step.fee_amount = swap_step.fee_amount;
-
-        if is_base_input {
-            state.amount_specified_remaining = state
-                .amount_specified_remaining
-                .checked_sub(step.amount_in + step.fee_amount)
-                .unwrap();
-            state.amount_calculated = state
-                .amount_calculated
-                .checked_add(step.amount_out)
-                .unwrap();
-        } else {
-            state.amount_specified_remaining = state
-                .amount_specified_remaining
-                .checked_sub(step.amount_out)
-                .unwrap();
-
-            let step_amount_calculate = step
-                .amount_in
-                .checked_add(step.fee_amount)
-                .ok_or(ErrorCode::CalculateOverflow)?;
-            state.amount_calculated = state
-                .amount_calculated
-                .checked_add(step_amount_calculate)
-                .ok_or(ErrorCode::CalculateOverflow)?;
-        }
-
-        let step_fee_amount = step.fee_amount;
-        // if the protocol fee is on, calculate how much is owed, decrement fee_amount, and increment protocol_fee
-        if amm_config.protocol_fee_rate > 0 {
-            let delta = U128::from(step_fee_amount)
-                .checked_mul(amm_config.protocol_fee_rate.into())
-                .unwrap()
-                .checked_div(FEE_RATE_DENOMINATOR_VALUE.into())
-                .unwrap()
-                .as_u64();
-            step.fee_amount = step.fee_amount.checked_sub(delta).unwrap();
-            state.protocol_fee = state.protocol_fee.checked_add(delta).unwrap();
-        }
-        // if the fund fee is on, calculate how much is owed, decrement fee_amount, and increment fund_fee
-        if amm_config.fund_fee_rate > 0 {
-            let delta = U128::from(step_fee_amount)
-                .checked_mul(amm_config.fund_fee_rate.into())
-                .unwrap()
-                .checked_div(FEE_RATE_DENOMINATOR_VALUE.into())
-                .unwrap()
-                .as_u64();
-            step.fee_amount = step.fee_amount.checked_sub(delta).unwrap();
-            state.fund_fee = state.fund_fee.checked_add(delta).unwrap();
-        }
-
-        // update global fee tracker
-        if state.liquidity > 0 {
-            let fee_growth_global_x64_delta = U128::from(step.fee_amount)
-                .mul_div_floor(U128::from(fixed_point_64::Q64), U128::from(state.liquidity))
-                .unwrap()
-                .as_u128();
-
-            state.fee_growth_global_x64 = state
-                .fee_growth_global_x64
-                .checked_add(fee_growth_global_x64_delta)
-                .unwrap();
-            state.fee_amount = state.fee_amount.checked_add(step.fee_amount).unwrap();
-            #[cfg(feature = "enable-log")]
-            msg!(
-                "fee_growth_global_x64_delta:{}, state.fee_growth_global_x64:{}, state.liquidity:{}, step.fee_amount:{}, state.fee_amount:{}",
-                fee_growth_global_x64_delta,
-                state.fee_growth_global_x64, state.liquidity, step.fee_amount, state.fee_amount
-            );
-        }
-        // shift tick if we reached the next price
-        if state.sqrt_price_x64 == step.sqrt_price_next_x64 {
-            // if the tick is initialized, run the tick transition
-            if step.initialized {
-                #[cfg(feature = "enable-log")]
-                msg!("loading next tick {}", step.tick_next);
-
-                let mut liquidity_net = next_initialized_tick.cross(
-                    if zero_for_one {
-                        state.fee_growth_global_x64
-                    } else {
-                        pool_state.fee_growth_global_0_x64
-                    },
-                    if zero_for_one {
-                        pool_state.fee_growth_global_1_x64
-                    } else {
-                        state.fee_growth_global_x64
-                    },
-                    &updated_reward_infos,
-                );
-                // update tick_state to tick_array account
-                tick_array_current.update_tick_state(
-                    next_initialized_tick.tick,
-                    pool_state.tick_spacing.into(),
-                    *next_initialized_tick,
-                )?;
+    let liquidity_start = pool_state.liquidity;
 
-                if zero_for_one {
-                    liquidity_net = liquidity_net.neg();
-                }
-                state.liquidity = liquidity_math::add_delta(state.liquidity, liquidity_net)?;
-            }
+    let updated_reward_infos = pool_state.update_reward_infos(block_timestamp as u64)?;
 
-            state.tick = if zero_for_one {
-                step.tick_next - 1
-            } else {
-                step.tick_next
-            };
-        } else if state.sqrt_price_x64 != step.sqrt_price_start_x64 {
-            // recompute unless we're on a lower tick boundary (i.e. already transitioned ticks), and haven't moved
-            // if only a small amount of quantity is traded, the input may be consumed by fees, resulting in no price change. If state.sqrt_price_x64, i.e., the latest price in the pool, is used to recalculate the tick, some errors may occur.
-            // for example, if zero_for_one, and the price falls exactly on an initialized tick t after the first trade, then at this point, pool.sqrtPriceX64 = get_sqrt_price_at_tick(t), while pool.tick = t-1. if the input quantity of the
-            // second trade is very small and the pool price does not change after the transaction, if the tick is recalculated, pool.tick will be equal to t, which is incorrect.
-            state.tick = tick_math::get_tick_at_sqrt_price(state.sqrt_price_x64)?;
-        }
+    let state = swap_quote::compute_swap(
+        amm_config,
+        pool_state,
+        tick_array_states,
+        tickarray_bitmap_extension,
+        &updated_reward_infos,
+        amount_specified,
+        sqrt_price_limit_x64,
+        zero_for_one,
+        is_base_input,
+        block_timestamp,
+    )?;
 
-        #[cfg(feature = "enable-log")]
-        msg!(
-            "end, is_base_input:{},step_amount_in:{}, step_amount_out:{}, step_fee_amount:{},fee_growth_global_x32:{}, state_sqrt_price_x64:{}, state_tick:{}, state_liquidity:{},state.protocol_fee:{}, protocol_fee_rate:{}, state.fund_fee:{}, fund_fee_rate:{}",
-            is_base_input,
-            step.amount_in,
-            step.amount_out,
-            step.fee_amount,
-            state.fee_growth_global_x64,
-            state.sqrt_price_x64,
-            state.tick,
-            state.liquidity,
-            state.protocol_fee,
-            amm_config.protocol_fee_rate,
-            state.fund_fee,
-            amm_config.fund_fee_rate,
-        );
-        // emit!(PriceChangeEvent {
-        //     pool_state: pool_state.key(),
-        //     tick_before,
-        //     tick_after: state.tick,
-        //     sqrt_price_x64_before,
-        //     sqrt_price_x64_after: state.sqrt_price_x64,
-        //     liquidity_before,
-        //     liquidity_after: state.liquidity,
-        //     zero_for_one,
-        // });
-    }
     // update tick
     if state.tick != pool_state.tick_current {
         // update the previous tick to the observation
@@ -476,7 +132,7 @@ pub fn swap_internal<'b, 'info>(
         (
             amount_specified
                 .checked_sub(state.amount_specified_remaining)
-                .unwrap(),
+                .ok_or(ErrorCode::MathOverflow)?,
             state.amount_calculated,
         )
     } else {
@@ -484,69 +140,127 @@ pub fn swap_internal<'b, 'info>(
             state.amount_calculated,
             amount_specified
                 .checked_sub(state.amount_specified_remaining)
-                .unwrap(),
+                .ok_or(ErrorCode::MathOverflow)?,
         )
     };
+    require_gte!(
+        if zero_for_one { amount_0 } else { amount_1 },
+        pool_state.min_swap_amount_in,
+        ErrorCode::SwapAmountBelowMinimum
+    );
 
     if zero_for_one {
         pool_state.fee_growth_global_0_x64 = state.fee_growth_global_x64;
         pool_state.total_fees_token_0 = pool_state
             .total_fees_token_0
             .checked_add(state.fee_amount)
-            .unwrap();
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
 
         if state.protocol_fee > 0 {
             pool_state.protocol_fees_token_0 = pool_state
                 .protocol_fees_token_0
                 .checked_add(state.protocol_fee)
-                .unwrap();
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
         }
         if state.fund_fee > 0 {
             pool_state.fund_fees_token_0 = pool_state
                 .fund_fees_token_0
                 .checked_add(state.fund_fee)
-                .unwrap();
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
         }
         pool_state.swap_in_amount_token_0 = pool_state
             .swap_in_amount_token_0
             .checked_add(u128::from(amount_0))
-            .unwrap();
+            .ok_or(ErrorCode::MathOverflow)?;
         pool_state.swap_out_amount_token_1 = pool_state
             .swap_out_amount_token_1
             .checked_add(u128::from(amount_1))
-            .unwrap();
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool_state.swap_count_0_for_1 = pool_state
+            .swap_count_0_for_1
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
     } else {
         pool_state.fee_growth_global_1_x64 = state.fee_growth_global_x64;
         pool_state.total_fees_token_1 = pool_state
             .total_fees_token_1
             .checked_add(state.fee_amount)
-            .unwrap();
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
 
         if state.protocol_fee > 0 {
             pool_state.protocol_fees_token_1 = pool_state
                 .protocol_fees_token_1
                 .checked_add(state.protocol_fee)
-                .unwrap();
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
         }
         if state.fund_fee > 0 {
             pool_state.fund_fees_token_1 = pool_state
                 .fund_fees_token_1
                 .checked_add(state.fund_fee)
-                .unwrap();
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
         }
         pool_state.swap_in_amount_token_1 = pool_state
             .swap_in_amount_token_1
             .checked_add(u128::from(amount_1))
-            .unwrap();
+            .ok_or(ErrorCode::MathOverflow)?;
         pool_state.swap_out_amount_token_0 = pool_state
             .swap_out_amount_token_0
             .checked_add(u128::from(amount_0))
-            .unwrap();
+            .ok_or(ErrorCode::MathOverflow)?;
+        pool_state.swap_count_1_for_0 = pool_state
+            .swap_count_1_for_0
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
     }
 
     Ok((amount_0, amount_1))
 }
 
+/// Set as return data of a swap failing with `InsufficientOutputVault`, so the client can retry
+/// with a trade the vault can fill
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputVaultShortfall {
+    /// The output amount the swap computed
+    pub amount_out: u64,
+    /// The balance of the output vault, the most a swap can pay out
+    pub max_amount_out: u64,
+}
+
+/// Fail before any transfer when the output vault holds less than `amount_out`
+pub fn check_output_vault_balance(vault_balance: u64, amount_out: u64) -> Result<()> {
+    if amount_out > vault_balance {
+        let shortfall = OutputVaultShortfall {
+            amount_out,
+            max_amount_out: vault_balance,
+        };
+        solana_program::program::set_return_data(&shortfall.try_to_vec()?);
+        return err!(ErrorCode::InsufficientOutputVault);
+    }
+    Ok(())
+}
+
+/// The user token accounts must hold the mints of the vaults they pay into and are paid from, a
+/// wrapped sol or duplicate mint account passed on the wrong side fails here instead of swapping
+/// in the other direction
+pub fn check_swap_token_account_mints(
+    input_token_account_mint: Pubkey,
+    input_vault_mint: Pubkey,
+    output_token_account_mint: Pubkey,
+    output_vault_mint: Pubkey,
+) -> Result<()> {
+    require_keys_eq!(
+        input_token_account_mint,
+        input_vault_mint,
+        ErrorCode::SwapTokenAccountMintMismatch
+    );
+    require_keys_eq!(
+        output_token_account_mint,
+        output_vault_mint,
+        ErrorCode::SwapTokenAccountMintMismatch
+    );
+    Ok(())
+}
+
 /// Performs a single exact input/output swap
 /// if is_base_input = true, return vaule is the max_amount_out, otherwise is min_amount_in
 pub fn exact_internal<'b, 'c: 'info, 'info>(
@@ -557,6 +271,7 @@ pub fn exact_internal<'b, 'c: 'info, 'info>(
     is_base_input: bool,
 ) -> Result<u64> {
     let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+    crate::log_compute_units!("swap start");
 
     let amount_0;
     let amount_1;
@@ -569,27 +284,34 @@ pub fn exact_internal<'b, 'c: 'info, 'info>(
     {
         swap_price_before = ctx.pool_state.load()?.sqrt_price_x64;
         let pool_state = &mut ctx.pool_state.load_mut()?;
-        zero_for_one = ctx.input_vault.mint == pool_state.token_mint_0;
+        // held until the tokens are transferred
+        pool_state.lock_reentrancy()?;
+        zero_for_one =
+            pool_state.swap_direction(&ctx.input_vault.key(), &ctx.output_vault.key())?;
+        check_swap_token_account_mints(
+            ctx.input_token_account.mint,
+            ctx.input_vault.mint,
+            ctx.output_token_account.mint,
+            ctx.output_vault.mint,
+        )?;
 
         require_gt!(block_timestamp, pool_state.open_time);
 
-        require!(
-            if zero_for_one {
-                ctx.input_vault.key() == pool_state.token_vault_0
-                    && ctx.output_vault.key() == pool_state.token_vault_1
-            } else {
-                ctx.input_vault.key() == pool_state.token_vault_1
-                    && ctx.output_vault.key() == pool_state.token_vault_0
-            },
-            ErrorCode::InvalidInputPoolVault
-        );
-
         let mut tickarray_bitmap_extension = None;
         let tick_array_states = &mut VecDeque::new();
         tick_array_states.push_back(ctx.tick_array_state.load_mut()?);
 
         let tick_array_bitmap_extension_key = TickArrayBitmapExtension::key(pool_state.key());
+        let mut pool_stats = None;
         for account_info in remaining_accounts.into_iter() {
+            if is_pool_stats_account(account_info) {
+                let stats = AccountLoad::<PoolStatsState>::load_data_mut(account_info)?;
+                // the stats of the other pools of a route are recorded by their own swaps
+                if stats.pool_id == ctx.pool_state.key() {
+                    pool_stats = Some(stats);
+                }
+                continue;
+            }
             if account_info.key().eq(&tick_array_bitmap_extension_key) {
                 tickarray_bitmap_extension = Some(
                     *(AccountLoader::<TickArrayBitmapExtension>::try_from(account_info)?
@@ -601,6 +323,8 @@ pub fn exact_internal<'b, 'c: 'info, 'info>(
             tick_array_states.push_back(AccountLoad::load_data_mut(account_info)?);
         }
 
+        crate::log_compute_units!("swap tick arrays loaded");
+        let (fees_0_before, fees_1_before) = pool_state.trade_fee_accumulators();
         (amount_0, amount_1) = swap_internal(
             &ctx.amm_config,
             pool_state,
@@ -621,6 +345,7 @@ pub fn exact_internal<'b, 'c: 'info, 'info>(
             is_base_input,
             oracle::block_timestamp(),
         )?;
+        crate::log_compute_units!("swap computed");
 
         #[cfg(feature = "enable-log")]
         msg!(
@@ -633,7 +358,23 @@ pub fn exact_internal<'b, 'c: 'info, 'info>(
             amount_0 != 0 && amount_1 != 0,
             ErrorCode::TooSmallInputOrOutputAmount
         );
+
+        if let Some(pool_stats) = pool_stats.as_mut() {
+            let (fees_0, fees_1) = pool_state.trade_fee_accumulators();
+            pool_stats.record_swap(
+                ctx.pool_state.key(),
+                block_timestamp,
+                amount_0,
+                amount_1,
+                fees_0.wrapping_sub(fees_0_before),
+                fees_1.wrapping_sub(fees_1_before),
+            )?;
+        }
     }
+    check_output_vault_balance(
+        ctx.output_vault.amount,
+        if zero_for_one { amount_1 } else { amount_0 },
+    )?;
     let (token_account_0, token_account_1, vault_0, vault_1) = if zero_for_one {
         (
             ctx.input_token_account.clone(),
@@ -701,6 +442,8 @@ pub fn exact_internal<'b, 'c: 'info, 'info>(
     }
     ctx.output_vault.reload()?;
     ctx.input_vault.reload()?;
+    ctx.pool_state.load_mut()?.unlock_reentrancy();
+    crate::log_compute_units!("swap tokens transferred");
 
     let pool_state = ctx.pool_state.load()?;
     emit!(SwapEvent {
@@ -758,7 +501,7 @@ pub fn swap<'a, 'b, 'c: 'info, 'info>(
     other_amount_threshold: u64,
     sqrt_price_limit_x64: u128,
     is_base_input: bool,
-) -> Result<()> {
+) -> Result<u64> {
     let amount = exact_internal(
         &mut SwapAccounts {
             signer: ctx.accounts.payer.clone(),
@@ -789,11 +532,12 @@ pub fn swap<'a, 'b, 'c: 'info, 'info>(
         );
     }
 
-    Ok(())
+    Ok(amount)
 }
 
 #[cfg(test)]
 mod swap_test {
+    use crate::libraries::{fixed_point_64, liquidity_math};
     use liquidity_math::get_delta_amounts_signed;
     use tick_array_bitmap_extension_test::{
         build_tick_array_bitmap_extension_info, BuildExtensionAccountInfo,
@@ -921,7 +665,8 @@ mod swap_test {
                     tick_math::get_sqrt_price_at_tick(position_param.tick_upper).unwrap(),
                     position_param.amount_0,
                     position_param.amount_1,
-                );
+                )
+                .unwrap();
 
                 let (amount_0, amount_1) = get_delta_amounts_signed(
                     start_tick,
@@ -1196,6 +941,14 @@ mod swap_test {
             assert!(pool_state.borrow().sqrt_price_x64 < sqrt_price_x64);
             assert!(pool_state.borrow().liquidity == (liquidity - 790917615645));
             assert!(amount_0 == 60941200010);
+
+            // the three swaps are counted as sells of token_0
+            assert!(pool_state.borrow().swap_count_0_for_1 == 3);
+            assert!(pool_state.borrow().swap_count_1_for_0 == 0);
+            assert!(
+                pool_state.borrow().swap_in_amount_token_0
+                    == u128::from(12188240002u64 + 121882400020 + 60941200010)
+            );
         }
 
         #[test]
@@ -1642,6 +1395,115 @@ mod swap_test {
         }
     }
 
+    #[test]
+    fn check_output_vault_balance_test() {
+        assert!(check_output_vault_balance(1000, 999).is_ok());
+        assert!(check_output_vault_balance(1000, 1000).is_ok());
+        assert_eq!(
+            check_output_vault_balance(1000, 1001).unwrap_err(),
+            ErrorCode::InsufficientOutputVault.into()
+        );
+        let shortfall = OutputVaultShortfall {
+            amount_out: 1001,
+            max_amount_out: 1000,
+        };
+        assert_eq!(
+            OutputVaultShortfall::try_from_slice(&shortfall.try_to_vec().unwrap()).unwrap(),
+            shortfall
+        );
+    }
+
+    #[test]
+    fn check_swap_token_account_mints_test() {
+        let mint_0 = Pubkey::new_unique();
+        let mint_1 = Pubkey::new_unique();
+        assert!(check_swap_token_account_mints(mint_0, mint_0, mint_1, mint_1).is_ok());
+        // the user token accounts passed on the wrong sides
+        assert_eq!(
+            check_swap_token_account_mints(mint_1, mint_0, mint_0, mint_1).unwrap_err(),
+            ErrorCode::SwapTokenAccountMintMismatch.into()
+        );
+        assert_eq!(
+            check_swap_token_account_mints(mint_0, mint_0, mint_0, mint_1).unwrap_err(),
+            ErrorCode::SwapTokenAccountMintMismatch.into()
+        );
+    }
+
+    #[cfg(test)]
+    mod min_swap_amount_test {
+        use super::*;
+        use crate::error::ErrorCode;
+
+        fn swap_with_min_amount(
+            min_swap_amount_in: u64,
+            amount_specified: u64,
+            zero_for_one: bool,
+            is_base_input: bool,
+        ) -> Result<(u64, u64)> {
+            let tick_current = -28859;
+            let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick_current).unwrap();
+            let (amm_config, pool_state, tick_array_states, observation_state) = build_swap_param(
+                tick_current,
+                60,
+                sqrt_price_x64,
+                121219,
+                vec![TickArrayInfo {
+                    start_tick_index: -32400,
+                    ticks: vec![
+                        build_tick(-32400, 277065331032, -277065331032).take(),
+                        build_tick(-29220, 1330680689, -1330680689).take(),
+                        build_tick(-28860, 6408486554, -6408486554).take(),
+                    ],
+                }],
+            );
+            pool_state.borrow_mut().min_swap_amount_in = min_swap_amount_in;
+            swap_internal(
+                &amm_config,
+                &mut pool_state.borrow_mut(),
+                &mut get_tick_array_states_mut(&tick_array_states).borrow_mut(),
+                &mut observation_state.borrow_mut(),
+                &None,
+                amount_specified,
+                if zero_for_one {
+                    tick_math::get_sqrt_price_at_tick(-32400).unwrap()
+                } else {
+                    tick_math::get_sqrt_price_at_tick(-28800).unwrap()
+                },
+                zero_for_one,
+                is_base_input,
+                oracle::block_timestamp_mock() as u32,
+            )
+        }
+
+        #[test]
+        fn base_input_below_minimum_test() {
+            let (amount_0, _) = swap_with_min_amount(0, 25, true, true).unwrap();
+            assert_eq!(amount_0, 25);
+            let (amount_0, _) = swap_with_min_amount(25, 25, true, true).unwrap();
+            assert_eq!(amount_0, 25);
+            assert_eq!(
+                swap_with_min_amount(26, 25, true, true).unwrap_err(),
+                ErrorCode::SwapAmountBelowMinimum.into()
+            );
+        }
+
+        #[test]
+        fn base_output_below_minimum_test() {
+            // the minimum applies to the computed input, not the specified output
+            let (amount_0, amount_1) = swap_with_min_amount(0, 100, false, false).unwrap();
+            assert_eq!(amount_0, 100);
+            assert!(amount_1 > 0);
+            assert_eq!(
+                swap_with_min_amount(amount_1, 100, false, false).unwrap(),
+                (amount_0, amount_1)
+            );
+            assert_eq!(
+                swap_with_min_amount(amount_1 + 1, 100, false, false).unwrap_err(),
+                ErrorCode::SwapAmountBelowMinimum.into()
+            );
+        }
+    }
+
     #[cfg(test)]
     mod liquidity_insufficient_test {
         use super::*;
@@ -1682,6 +1544,95 @@ mod swap_test {
         }
     }
 
+    #[cfg(test)]
+    mod swap_step_limit_test {
+        use super::*;
+        use crate::error::ErrorCode;
+        use crate::libraries::swap_quote::max_swap_steps;
+
+        #[test]
+        fn max_swap_steps_test() {
+            assert_eq!(max_swap_steps(1).unwrap(), TICK_ARRAY_SIZE_USIZE + 2);
+            assert_eq!(
+                max_swap_steps(3).unwrap(),
+                3 * (TICK_ARRAY_SIZE_USIZE + 1) + 1
+            );
+        }
+
+        #[test]
+        fn cross_every_tick_of_tick_array_test() {
+            // every tick of the tick array [60, 120) is initialized, the swap takes one step for each
+            let ticks = (TICK_ARRAY_SIZE..2 * TICK_ARRAY_SIZE)
+                .map(|tick| build_tick(tick, 1, 0).take())
+                .collect();
+            let (amm_config, pool_state, tick_array_states, observation_state) = build_swap_param(
+                119,
+                1,
+                tick_math::get_sqrt_price_at_tick(119).unwrap() + 1,
+                0,
+                vec![TickArrayInfo {
+                    start_tick_index: 60,
+                    ticks,
+                }],
+            );
+
+            swap_internal(
+                &amm_config,
+                &mut pool_state.borrow_mut(),
+                &mut get_tick_array_states_mut(&tick_array_states).borrow_mut(),
+                &mut observation_state.borrow_mut(),
+                &None,
+                1000,
+                tick_math::get_sqrt_price_at_tick(60).unwrap(),
+                true,
+                true,
+                oracle::block_timestamp_mock() as u32,
+            )
+            .unwrap();
+            assert_eq!(pool_state.borrow().tick_current, 59);
+            assert_eq!(
+                pool_state.borrow().sqrt_price_x64,
+                tick_math::get_sqrt_price_at_tick(60).unwrap()
+            );
+        }
+
+        #[test]
+        fn bitmap_flipped_for_empty_tick_arrays_test() {
+            // the bitmap marks tick arrays which hold no initialized tick
+            let tick_current = -10;
+            let (amm_config, pool_state, tick_array_states, observation_state) = build_swap_param(
+                tick_current,
+                10,
+                tick_math::get_sqrt_price_at_tick(tick_current).unwrap(),
+                1000000,
+                vec![
+                    TickArrayInfo {
+                        start_tick_index: -600,
+                        ticks: vec![],
+                    },
+                    TickArrayInfo {
+                        start_tick_index: -1200,
+                        ticks: vec![],
+                    },
+                ],
+            );
+
+            let result = swap_internal(
+                &amm_config,
+                &mut pool_state.borrow_mut(),
+                &mut get_tick_array_states_mut(&tick_array_states).borrow_mut(),
+                &mut observation_state.borrow_mut(),
+                &None,
+                u64::MAX / 2,
+                tick_math::get_sqrt_price_at_tick(-1800).unwrap(),
+                true,
+                true,
+                oracle::block_timestamp_mock() as u32,
+            );
+            assert_eq!(result.unwrap_err(), ErrorCode::InvalidTickArray.into());
+        }
+    }
+
     #[test]
     fn explain_why_zero_for_one_less_or_equal_current_tick() {
         let tick_current = -28859;
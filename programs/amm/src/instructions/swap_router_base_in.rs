@@ -36,7 +36,53 @@ pub fn swap_router_base_in<'a, 'b, 'c: 'info, 'info>(
     ctx: Context<'a, 'b, 'c, 'info, SwapRouterBaseIn<'info>>,
     amount_in: u64,
     amount_out_minimum: u64,
-) -> Result<()> {
+) -> Result<u64> {
+    let amount_out = swap_router_internal(&ctx, amount_in)?;
+    require_gte!(
+        amount_out,
+        amount_out_minimum,
+        ErrorCode::TooLittleOutputReceived
+    );
+
+    Ok(amount_out)
+}
+
+/// Resume a route split across transactions. The input amount is the balance the previous legs
+/// added to the input token account on top of `input_balance_before`.
+pub fn swap_router_resume_base_in<'a, 'b, 'c: 'info, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, SwapRouterBaseIn<'info>>,
+    input_balance_before: u64,
+    amount_in_minimum: u64,
+    amount_out_minimum: u64,
+) -> Result<u64> {
+    let amount_in = ctx
+        .accounts
+        .input_token_account
+        .amount
+        .saturating_sub(input_balance_before);
+    // slippage check of the previous legs
+    require_gte!(
+        amount_in,
+        amount_in_minimum,
+        ErrorCode::TooLittleOutputReceived
+    );
+    require_gt!(amount_in, 0, ErrorCode::TooSmallInputOrOutputAmount);
+
+    let amount_out = swap_router_internal(&ctx, amount_in)?;
+    require_gte!(
+        amount_out,
+        amount_out_minimum,
+        ErrorCode::TooLittleOutputReceived
+    );
+
+    Ok(amount_out)
+}
+
+/// Swap `amount_in` across the pools in the remaining accounts and return the output amount
+fn swap_router_internal<'a, 'b, 'c: 'info, 'info>(
+    ctx: &Context<'a, 'b, 'c, 'info, SwapRouterBaseIn<'info>>,
+    amount_in: u64,
+) -> Result<u64> {
     let mut amount_in_internal = amount_in;
     let mut input_token_account = Box::new(ctx.accounts.input_token_account.clone());
     let mut input_token_mint = Box::new(ctx.accounts.input_token_mint.clone());
@@ -68,13 +114,13 @@ pub fn swap_router_base_in<'a, 'b, 'c: 'info, 'info>(
         let observation_state =
             AccountLoader::<ObservationState>::try_from(remaining_accounts.next().unwrap())?;
 
-        {
-            let pool_state = pool_state_loader.load()?;
-            // check observation account is owned by the pool
-            require_keys_eq!(pool_state.observation_key, observation_state.key());
-            // check ammConfig account is associate with the pool
-            require_keys_eq!(pool_state.amm_config, amm_config.key());
-        }
+        check_route_hop(
+            &pool_state_loader.load()?,
+            amm_config.key(),
+            observation_state.key(),
+            output_token_mint.key(),
+            output_vault.mint,
+        )?;
 
         // solana_program::log::sol_log_compute_units();
         accounts = remaining_accounts.as_slice();
@@ -103,11 +149,161 @@ pub fn swap_router_base_in<'a, 'b, 'c: 'info, 'info>(
         input_token_account = output_token_account;
         input_token_mint = output_token_mint;
     }
-    require_gte!(
-        amount_in_internal,
-        amount_out_minimum,
-        ErrorCode::TooLittleOutputReceived
-    );
 
+    Ok(amount_in_internal)
+}
+
+/// Check the accounts of one pool of a route belong to it. Every pool is checked against its own
+/// amm config, so the pools of a route can be of different fee tiers.
+pub fn check_route_hop(
+    pool_state: &PoolState,
+    amm_config: Pubkey,
+    observation_state: Pubkey,
+    output_mint: Pubkey,
+    output_vault_mint: Pubkey,
+) -> Result<()> {
+    require_keys_eq!(
+        pool_state.amm_config,
+        amm_config,
+        ErrorCode::InvalidRouteHop
+    );
+    require_keys_eq!(
+        pool_state.observation_key,
+        observation_state,
+        ErrorCode::InvalidRouteHop
+    );
+    require_keys_eq!(output_mint, output_vault_mint, ErrorCode::InvalidRouteHop);
     Ok(())
 }
+
+#[cfg(test)]
+mod swap_router_base_in_test {
+    use super::*;
+    use crate::libraries::tick_math;
+    use crate::states::pool_test::build_pool;
+    use crate::states::tick_array_test::{build_tick, build_tick_array_with_tick_states};
+    use crate::swap::swap_internal;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+
+    /// A pool at tick 0 with one position over [0, 590) and the amm config of the fee tier
+    fn build_route_hop(
+        trade_fee_rate: u32,
+    ) -> (
+        AmmConfig,
+        RefCell<PoolState>,
+        RefCell<TickArrayState>,
+        RefCell<ObservationState>,
+    ) {
+        let tick_spacing = 10;
+        let liquidity = 1_000_000_000_000;
+        let amm_config = AmmConfig {
+            trade_fee_rate,
+            tick_spacing,
+            ..Default::default()
+        };
+        let pool_state = build_pool(
+            0,
+            tick_spacing,
+            tick_math::get_sqrt_price_at_tick(0).unwrap(),
+            liquidity,
+        );
+        let tick_array = build_tick_array_with_tick_states(
+            pool_state.borrow().key(),
+            0,
+            tick_spacing,
+            vec![
+                build_tick(0, liquidity, liquidity as i128).take(),
+                build_tick(590, liquidity, -(liquidity as i128)).take(),
+            ],
+        );
+        pool_state
+            .borrow_mut()
+            .flip_tick_array_bit(None, 0)
+            .unwrap();
+        let observation_state = RefCell::new(ObservationState::default());
+        observation_state.borrow_mut().pool_id = pool_state.borrow().key();
+        (amm_config, pool_state, tick_array, observation_state)
+    }
+
+    /// Swap `amount_in` of token_1 through every pool in order, each output is the next input
+    fn swap_route(trade_fee_rates: &[u32], amount_in: u64) -> u64 {
+        let mut amount = amount_in;
+        for trade_fee_rate in trade_fee_rates {
+            let (amm_config, pool_state, tick_array, observation_state) =
+                build_route_hop(*trade_fee_rate);
+            let mut tick_array_states = VecDeque::from([tick_array.borrow_mut()]);
+            let (amount_0, amount_1) = swap_internal(
+                &amm_config,
+                &mut pool_state.borrow_mut(),
+                &mut tick_array_states,
+                &mut observation_state.borrow_mut(),
+                &None,
+                amount,
+                tick_math::MAX_SQRT_PRICE_X64 - 1,
+                false,
+                true,
+                oracle::block_timestamp_mock() as u32,
+            )
+            .unwrap();
+            assert_eq!(amount_1, amount);
+            amount = amount_0;
+        }
+        amount
+    }
+
+    #[test]
+    fn check_route_hop_test() {
+        let (_, pool_state_0, _, _) = build_route_hop(500);
+        let (_, pool_state_1, _, _) = build_route_hop(3000);
+        let output_mint = Pubkey::new_unique();
+        for pool_state in [pool_state_0.borrow(), pool_state_1.borrow()] {
+            assert!(check_route_hop(
+                &pool_state,
+                pool_state.amm_config,
+                pool_state.observation_key,
+                output_mint,
+                output_mint
+            )
+            .is_ok());
+        }
+
+        // the amm config of another fee tier
+        let pool_state_0 = pool_state_0.borrow();
+        let pool_state_1 = pool_state_1.borrow();
+        assert!(check_route_hop(
+            &pool_state_0,
+            pool_state_1.amm_config,
+            pool_state_0.observation_key,
+            output_mint,
+            output_mint
+        )
+        .is_err());
+        assert!(check_route_hop(
+            &pool_state_0,
+            pool_state_0.amm_config,
+            Pubkey::new_unique(),
+            output_mint,
+            output_mint
+        )
+        .is_err());
+        assert!(check_route_hop(
+            &pool_state_0,
+            pool_state_0.amm_config,
+            pool_state_0.observation_key,
+            output_mint,
+            Pubkey::new_unique()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn mixed_fee_route_test() {
+        let amount_in = 1_000_000;
+        let low_fee_route = swap_route(&[500, 500], amount_in);
+        let mixed_fee_route = swap_route(&[500, 3000], amount_in);
+        let high_fee_route = swap_route(&[3000, 3000], amount_in);
+        assert!(low_fee_route > mixed_fee_route);
+        assert!(mixed_fee_route > high_fee_route);
+    }
+}
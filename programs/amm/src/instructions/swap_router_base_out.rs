@@ -0,0 +1,417 @@
+use crate::error::ErrorCode;
+use crate::libraries::tick_math;
+use crate::states::*;
+use crate::swap::{check_output_vault_balance, check_swap_token_account_mints, swap_internal};
+use crate::swap_router_base_in::check_route_hop;
+use crate::util::*;
+use anchor_lang::prelude::*;
+use anchor_spl::{
+    token::Token,
+    token_interface::{Mint, Token2022, TokenAccount},
+};
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+#[derive(Accounts)]
+pub struct SwapRouterBaseOut<'info> {
+    /// The user performing the swap
+    pub payer: Signer<'info>,
+
+    /// The token account that pays input tokens to the first pool
+    #[account(mut)]
+    pub input_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// The mint of input token
+    #[account(mut)]
+    pub input_token_mint: InterfaceAccount<'info, Mint>,
+
+    /// The token account that receives the output tokens of the last pool
+    #[account(mut)]
+    pub output_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    /// SPL program for token transfers
+    pub token_program: Program<'info, Token>,
+    /// SPL program 2022 for token transfers
+    pub token_program_2022: Program<'info, Token2022>,
+
+    /// CHECK:
+    // #[account(
+    //     address = spl_memo::id()
+    // )]
+    pub memo_program: UncheckedAccount<'info>,
+    // remaining accounts, for each pool of the route in order
+    // amm_config
+    // pool_state
+    // input_vault
+    // output_vault
+    // output_vault_mint
+    // observation_state
+    // tickarray_bitmap_extension if needed, then the tick arrays
+}
+
+/// The accounts of one pool of the route
+struct RouteHop<'c, 'info> {
+    amm_config: Box<Account<'info, AmmConfig>>,
+    pool_state: AccountLoader<'info, PoolState>,
+    input_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    output_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+    output_vault_mint: Box<InterfaceAccount<'info, Mint>>,
+    observation_state: AccountLoader<'info, ObservationState>,
+    tick_array_accounts: &'c [AccountInfo<'info>],
+}
+
+/// Swap for exactly `amount_out` of the output token across the pools of the route. The route is
+/// walked from the last pool to the first: each pool pays its output to the next pool's input
+/// vault, or to the user for the last one, and is owed the input computed for that output. Only
+/// the input of the first pool is paid by the user, so no intermediate token account is needed.
+pub fn swap_router_base_out<'a, 'b, 'c: 'info, 'info>(
+    mut ctx: Context<'a, 'b, 'c, 'info, SwapRouterBaseOut<'info>>,
+    amount_out: u64,
+    amount_in_maximum: u64,
+) -> Result<()> {
+    require_gt!(amount_out, 0, ErrorCode::TooSmallInputOrOutputAmount);
+    let hops = load_route_hops(ctx.remaining_accounts)?;
+    require!(!hops.is_empty(), ErrorCode::EmptySwapRoute);
+
+    let output_balance_before = ctx.accounts.output_token_account.amount;
+    let input_token_mint = Box::new(ctx.accounts.input_token_mint.clone());
+    require_keys_eq!(
+        ctx.accounts.input_token_account.mint,
+        input_token_mint.key(),
+        ErrorCode::SwapTokenAccountMintMismatch
+    );
+    let amounts = route_base_out_amounts(
+        hops.len(),
+        amount_out,
+        |i, amount| get_transfer_inverse_fee(hops[i].output_vault_mint.clone(), amount),
+        |i, hop_amounts| {
+            // the input comes from the previous pool, or from the user for the first one
+            let (input_mint, source) = if i == 0 {
+                (
+                    input_token_mint.clone(),
+                    ctx.accounts.input_token_account.key(),
+                )
+            } else {
+                (
+                    hops[i - 1].output_vault_mint.clone(),
+                    hops[i - 1].output_vault.key(),
+                )
+            };
+            let (destination, destination_mint) = if i + 1 == hops.len() {
+                (
+                    ctx.accounts.output_token_account.to_account_info(),
+                    ctx.accounts.output_token_account.mint,
+                )
+            } else {
+                (
+                    hops[i + 1].input_vault.to_account_info(),
+                    hops[i + 1].input_vault.mint,
+                )
+            };
+            swap_hop_base_out(
+                &ctx.accounts.payer,
+                &hops[i],
+                input_mint,
+                source,
+                &destination,
+                destination_mint,
+                hop_amounts,
+                &ctx.accounts.token_program,
+                &ctx.accounts.token_program_2022,
+            )
+        },
+    )?;
+    let input_transfer_fee =
+        get_transfer_inverse_fee(input_token_mint.clone(), amounts[0].amount_in)?;
+    let amount_in_with_fee = amounts[0]
+        .amount_in
+        .checked_add(input_transfer_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    require_gte!(
+        amount_in_maximum,
+        amount_in_with_fee,
+        ErrorCode::TooMuchInputPaid
+    );
+    transfer_from_user_to_pool_vault(
+        &ctx.accounts.payer,
+        &ctx.accounts.input_token_account.to_account_info(),
+        &hops[0].input_vault.to_account_info(),
+        Some(input_token_mint),
+        &ctx.accounts.token_program,
+        Some(ctx.accounts.token_program_2022.to_account_info()),
+        amount_in_with_fee,
+    )?;
+
+    ctx.accounts.output_token_account.reload()?;
+    require_gte!(
+        ctx.accounts
+            .output_token_account
+            .amount
+            .checked_sub(output_balance_before)
+            // a circular route paying from the output account can leave it below its balance
+            .ok_or(ErrorCode::TooLittleOutputReceived)?,
+        amount_out,
+        ErrorCode::TooLittleOutputReceived
+    );
+
+    Ok(())
+}
+
+/// Split the remaining accounts into the pools of the route, the tick arrays of a pool run up to
+/// the amm config of the next one
+fn load_route_hops<'c: 'info, 'info>(
+    remaining_accounts: &'c [AccountInfo<'info>],
+) -> Result<Vec<RouteHop<'c, 'info>>> {
+    let mut hops = Vec::new();
+    let mut accounts: &'c [AccountInfo<'info>] = remaining_accounts;
+    while !accounts.is_empty() {
+        require_gte!(accounts.len(), 6, ErrorCode::AccountLack);
+        let tick_array_count = accounts[6..]
+            .iter()
+            .position(|account_info| account_info.data_len() == AmmConfig::LEN)
+            .unwrap_or(accounts.len() - 6);
+        hops.push(RouteHop {
+            amm_config: Box::new(Account::<AmmConfig>::try_from(&accounts[0])?),
+            pool_state: AccountLoader::<PoolState>::try_from(&accounts[1])?,
+            input_vault: Box::new(InterfaceAccount::<TokenAccount>::try_from(&accounts[2])?),
+            output_vault: Box::new(InterfaceAccount::<TokenAccount>::try_from(&accounts[3])?),
+            output_vault_mint: Box::new(InterfaceAccount::<Mint>::try_from(&accounts[4])?),
+            observation_state: AccountLoader::<ObservationState>::try_from(&accounts[5])?,
+            tick_array_accounts: &accounts[6..6 + tick_array_count],
+        });
+        accounts = &accounts[6 + tick_array_count..];
+    }
+    Ok(hops)
+}
+
+/// The amounts of one pool of a base out route
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RouteHopAmounts {
+    /// The amount received by the next pool's input vault, or by the user for the last pool
+    pub amount_out: u64,
+    /// The transfer fee the pool pays on top of `amount_out`
+    pub output_transfer_fee: u64,
+    /// The input the pool is owed for its output, before the transfer fee of paying it
+    pub amount_in: u64,
+}
+
+/// Chain the amounts of the route from the last pool to the first. `output_transfer_fee` gives the
+/// inverse transfer fee of a pool's output and `swap_hop` swaps a pool for its output, returning
+/// the input it is owed, which is the output the previous pool has to deliver.
+pub fn route_base_out_amounts<F, S>(
+    hop_count: usize,
+    amount_out: u64,
+    mut output_transfer_fee: F,
+    mut swap_hop: S,
+) -> Result<Vec<RouteHopAmounts>>
+where
+    F: FnMut(usize, u64) -> Result<u64>,
+    S: FnMut(usize, &RouteHopAmounts) -> Result<u64>,
+{
+    let mut amounts = vec![RouteHopAmounts::default(); hop_count];
+    let mut amount_out = amount_out;
+    for i in (0..hop_count).rev() {
+        amounts[i].amount_out = amount_out;
+        amounts[i].output_transfer_fee = output_transfer_fee(i, amount_out)?;
+        amounts[i].amount_in = swap_hop(i, &amounts[i])?;
+        amount_out = amounts[i].amount_in;
+    }
+    Ok(amounts)
+}
+
+/// Swap exactly `amount_out` of the hop's output token into `destination`, the pool paying its
+/// transfer fee. The input is left for the caller to pay into the input vault, the amount the
+/// pool is owed is returned.
+fn swap_hop_base_out<'c: 'info, 'info>(
+    payer: &Signer<'info>,
+    hop: &RouteHop<'c, 'info>,
+    input_mint: Box<InterfaceAccount<'info, Mint>>,
+    source: Pubkey,
+    destination: &AccountInfo<'info>,
+    destination_mint: Pubkey,
+    amounts: &RouteHopAmounts,
+    token_program: &Program<'info, Token>,
+    token_program_2022: &Program<'info, Token2022>,
+) -> Result<u64> {
+    let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+    let amount_out = amounts.amount_out;
+    let output_transfer_fee = amounts.output_transfer_fee;
+    let amount_out_with_fee = amount_out
+        .checked_add(output_transfer_fee)
+        .ok_or(ErrorCode::MathOverflow)?;
+
+    let zero_for_one;
+    let amount_0;
+    let amount_1;
+    {
+        let pool_state = &mut hop.pool_state.load_mut()?;
+        // held until the output is transferred
+        pool_state.lock_reentrancy()?;
+        check_route_hop(
+            pool_state,
+            hop.amm_config.key(),
+            hop.observation_state.key(),
+            hop.output_vault_mint.key(),
+            hop.output_vault.mint,
+        )?;
+        zero_for_one =
+            pool_state.swap_direction(&hop.input_vault.key(), &hop.output_vault.key())?;
+        check_swap_token_account_mints(
+            input_mint.key(),
+            hop.input_vault.mint,
+            destination_mint,
+            hop.output_vault.mint,
+        )?;
+        require_gt!(block_timestamp, pool_state.open_time);
+
+        let mut tickarray_bitmap_extension = None;
+        let tick_array_states = &mut VecDeque::new();
+        let tick_array_bitmap_extension_key = TickArrayBitmapExtension::key(pool_state.key());
+        for account_info in hop.tick_array_accounts.iter() {
+            if account_info.key().eq(&tick_array_bitmap_extension_key) {
+                tickarray_bitmap_extension = Some(
+                    *(AccountLoader::<TickArrayBitmapExtension>::try_from(account_info)?
+                        .load()?
+                        .deref()),
+                );
+                continue;
+            }
+            tick_array_states.push_back(AccountLoad::load_data_mut(account_info)?);
+        }
+
+        (amount_0, amount_1) = swap_internal(
+            &hop.amm_config,
+            pool_state,
+            tick_array_states,
+            &mut hop.observation_state.load_mut()?,
+            &tickarray_bitmap_extension,
+            amount_out_with_fee,
+            if zero_for_one {
+                tick_math::MIN_SQRT_PRICE_X64 + 1
+            } else {
+                tick_math::MAX_SQRT_PRICE_X64 - 1
+            },
+            zero_for_one,
+            false,
+            oracle::block_timestamp(),
+        )?;
+        require!(
+            amount_0 != 0 && amount_1 != 0,
+            ErrorCode::TooSmallInputOrOutputAmount
+        );
+    }
+    let (amount_in, amount_out_swapped) = if zero_for_one {
+        (amount_0, amount_1)
+    } else {
+        (amount_1, amount_0)
+    };
+    // there is no price limit, a route is never partially filled
+    require_eq!(amount_out_swapped, amount_out_with_fee);
+    check_output_vault_balance(hop.output_vault.amount, amount_out_with_fee)?;
+    let input_transfer_fee = get_transfer_inverse_fee(input_mint, amount_in)?;
+
+    if hop.output_vault.amount <= amount_out_with_fee {
+        // freeze pool, disable all instructions
+        hop.pool_state.load_mut()?.set_status(255);
+    }
+    transfer_from_pool_vault_to_user(
+        &hop.pool_state,
+        &hop.output_vault.to_account_info(),
+        destination,
+        Some(hop.output_vault_mint.clone()),
+        token_program,
+        Some(token_program_2022.to_account_info()),
+        amount_out_with_fee,
+    )?;
+    hop.pool_state.load_mut()?.unlock_reentrancy();
+
+    let pool_state = hop.pool_state.load()?;
+    let (token_account_0, token_account_1, transfer_fee_0, transfer_fee_1) = if zero_for_one {
+        (
+            source,
+            destination.key(),
+            input_transfer_fee,
+            output_transfer_fee,
+        )
+    } else {
+        (
+            destination.key(),
+            source,
+            output_transfer_fee,
+            input_transfer_fee,
+        )
+    };
+    emit!(SwapEvent {
+        pool_state: hop.pool_state.key(),
+        sender: payer.key(),
+        token_account_0,
+        token_account_1,
+        amount_0: if zero_for_one { amount_in } else { amount_out },
+        transfer_fee_0,
+        amount_1: if zero_for_one { amount_out } else { amount_in },
+        transfer_fee_1,
+        zero_for_one,
+        sqrt_price_x64: pool_state.sqrt_price_x64,
+        liquidity: pool_state.liquidity,
+        tick: pool_state.tick_current
+    });
+
+    Ok(amount_in)
+}
+
+#[cfg(test)]
+mod swap_router_base_out_test {
+    use super::*;
+
+    #[test]
+    fn route_base_out_amounts_two_hops_test() {
+        // the intermediate mint charges a 1% transfer fee, the output mint none, and each pool
+        // takes 2 input per output
+        let mut swapped = Vec::new();
+        let amounts = route_base_out_amounts(
+            2,
+            1_000,
+            |i, amount| Ok(if i == 0 { amount / 100 } else { 0 }),
+            |i, hop_amounts| {
+                swapped.push(i);
+                Ok((hop_amounts.amount_out + hop_amounts.output_transfer_fee) * 2)
+            },
+        )
+        .unwrap();
+        // the last pool is swapped first, its input is the output of the first pool
+        assert_eq!(swapped, vec![1, 0]);
+        assert_eq!(
+            amounts,
+            vec![
+                RouteHopAmounts {
+                    amount_out: 2_000,
+                    output_transfer_fee: 20,
+                    amount_in: 4_040,
+                },
+                RouteHopAmounts {
+                    amount_out: 1_000,
+                    output_transfer_fee: 0,
+                    amount_in: 2_000,
+                },
+            ]
+        );
+
+        // a failing pool stops the route before the pools ahead of it are swapped
+        let mut swapped = Vec::new();
+        let result = route_base_out_amounts(
+            2,
+            1_000,
+            |_, _| Ok(0),
+            |i, _| {
+                swapped.push(i);
+                err!(ErrorCode::InsufficientOutputVault)
+            },
+        );
+        assert_eq!(
+            result.unwrap_err(),
+            ErrorCode::InsufficientOutputVault.into()
+        );
+        assert_eq!(swapped, vec![1]);
+    }
+}
@@ -3,7 +3,7 @@ use std::ops::Deref;
 
 use crate::error::ErrorCode;
 use crate::libraries::tick_math;
-use crate::swap::swap_internal;
+use crate::swap::{check_output_vault_balance, check_swap_token_account_mints, swap_internal};
 use crate::util::*;
 use crate::{states::*, util};
 use anchor_lang::prelude::*;
@@ -29,7 +29,8 @@ pub struct SwapSingleV2<'info> {
     #[account(mut)]
     pub input_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    /// The user token account for output token
+    /// The token account receiving the output token, it can be owned by anyone so the output is
+    /// delivered to a recipient other than the payer
     #[account(mut)]
     pub output_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
@@ -87,6 +88,7 @@ pub fn exact_internal_v2<'c: 'info, 'info>(
     // invoke_memo_instruction(SWAP_MEMO_MSG, ctx.memo_program.to_account_info())?;
 
     let block_timestamp = solana_program::clock::Clock::get()?.unix_timestamp as u64;
+    crate::log_compute_units!("swap start");
 
     let amount_0;
     let amount_1;
@@ -111,26 +113,33 @@ pub fn exact_internal_v2<'c: 'info, 'info>(
     {
         swap_price_before = ctx.pool_state.load()?.sqrt_price_x64;
         let pool_state = &mut ctx.pool_state.load_mut()?;
-        zero_for_one = ctx.input_vault.mint == pool_state.token_mint_0;
+        // held until the tokens are transferred
+        pool_state.lock_reentrancy()?;
+        zero_for_one =
+            pool_state.swap_direction(&ctx.input_vault.key(), &ctx.output_vault.key())?;
+        check_swap_token_account_mints(
+            ctx.input_token_account.mint,
+            ctx.input_vault.mint,
+            ctx.output_token_account.mint,
+            ctx.output_vault.mint,
+        )?;
 
         require_gt!(block_timestamp, pool_state.open_time);
 
-        require!(
-            if zero_for_one {
-                ctx.input_vault.key() == pool_state.token_vault_0
-                    && ctx.output_vault.key() == pool_state.token_vault_1
-            } else {
-                ctx.input_vault.key() == pool_state.token_vault_1
-                    && ctx.output_vault.key() == pool_state.token_vault_0
-            },
-            ErrorCode::InvalidInputPoolVault
-        );
-
         let mut tickarray_bitmap_extension = None;
         let tick_array_states = &mut VecDeque::new();
 
         let tick_array_bitmap_extension_key = TickArrayBitmapExtension::key(pool_state.key());
+        let mut pool_stats = None;
         for account_info in remaining_accounts.into_iter() {
+            if is_pool_stats_account(account_info) {
+                let stats = AccountLoad::<PoolStatsState>::load_data_mut(account_info)?;
+                // the stats of the other pools of a route are recorded by their own swaps
+                if stats.pool_id == ctx.pool_state.key() {
+                    pool_stats = Some(stats);
+                }
+                continue;
+            }
             if account_info.key().eq(&tick_array_bitmap_extension_key) {
                 tickarray_bitmap_extension = Some(
                     *(AccountLoader::<TickArrayBitmapExtension>::try_from(account_info)?
@@ -142,6 +151,8 @@ pub fn exact_internal_v2<'c: 'info, 'info>(
             tick_array_states.push_back(AccountLoad::load_data_mut(account_info)?);
         }
 
+        crate::log_compute_units!("swap tick arrays loaded");
+        let (fees_0_before, fees_1_before) = pool_state.trade_fee_accumulators();
         (amount_0, amount_1) = swap_internal(
             &ctx.amm_config,
             pool_state,
@@ -162,6 +173,7 @@ pub fn exact_internal_v2<'c: 'info, 'info>(
             is_base_input,
             oracle::block_timestamp(),
         )?;
+        crate::log_compute_units!("swap computed");
 
         #[cfg(feature = "enable-log")]
         msg!(
@@ -174,7 +186,24 @@ pub fn exact_internal_v2<'c: 'info, 'info>(
             amount_0 != 0 && amount_1 != 0,
             ErrorCode::TooSmallInputOrOutputAmount
         );
+
+        if let Some(pool_stats) = pool_stats.as_mut() {
+            let (fees_0, fees_1) = pool_state.trade_fee_accumulators();
+            pool_stats.record_swap(
+                ctx.pool_state.key(),
+                block_timestamp,
+                amount_0,
+                amount_1,
+                fees_0.wrapping_sub(fees_0_before),
+                fees_1.wrapping_sub(fees_1_before),
+            )?;
+        }
     }
+    // the vault pays the output amount including the transfer fee
+    check_output_vault_balance(
+        ctx.output_vault.amount,
+        if zero_for_one { amount_1 } else { amount_0 },
+    )?;
     let (token_account_0, token_account_1, vault_0, vault_1, vault_0_mint, vault_1_mint) =
         if zero_for_one {
             (
@@ -284,6 +313,8 @@ pub fn exact_internal_v2<'c: 'info, 'info>(
     }
     ctx.output_token_account.reload()?;
     ctx.input_token_account.reload()?;
+    ctx.pool_state.load_mut()?.unlock_reentrancy();
+    crate::log_compute_units!("swap tokens transferred");
 
     let pool_state = ctx.pool_state.load()?;
     emit!(SwapEvent {
@@ -341,7 +372,7 @@ pub fn swap_v2<'a, 'b, 'c: 'info, 'info>(
     other_amount_threshold: u64,
     sqrt_price_limit_x64: u128,
     is_base_input: bool,
-) -> Result<()> {
+) -> Result<u64> {
     let amount_result = exact_internal_v2(
         ctx.accounts,
         ctx.remaining_accounts,
@@ -363,5 +394,5 @@ pub fn swap_v2<'a, 'b, 'c: 'info, 'info>(
         );
     }
 
-    Ok(())
+    Ok(amount_result)
 }
@@ -0,0 +1,186 @@
+use crate::error::ErrorCode;
+use crate::libraries::{fixed_point_64, full_math::MulDiv, U256};
+use crate::states::*;
+use crate::util::{self, transfer_from_user_to_pool_vault};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+#[derive(Accounts)]
+pub struct TopUpReward<'info> {
+    /// The funder of the reward, the pool owner or the admin
+    pub reward_funder: Signer<'info>,
+
+    /// The funder's reward token account
+    #[account(
+        mut,
+        token::mint = reward_token_mint
+    )]
+    pub funder_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The pool of the reward
+    #[account(mut)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// The reward vault the top up is deposited to
+    #[account(mut)]
+    pub reward_token_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The mint of the reward vault
+    #[account(address = reward_token_vault.mint)]
+    pub reward_token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub reward_token_program: Interface<'info, TokenInterface>,
+}
+
+/// Deposit `amount` more of a running reward and extend its end time by the seconds the amount
+/// lasts at the current emission rate. The rate and the emitted amounts are left as they are.
+pub fn top_up_reward(ctx: Context<TopUpReward>, reward_index: u8, amount: u64) -> Result<()> {
+    require_gt!(REWARD_NUM, reward_index as usize);
+    let current_timestamp = u64::try_from(Clock::get()?.unix_timestamp).unwrap();
+    let end_time;
+    let unclaimed_amount;
+    {
+        let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+        pool_state.check_unlocked()?;
+        pool_state.update_reward_infos(current_timestamp)?;
+
+        let reward_info = pool_state.reward_infos[reward_index as usize];
+        if !reward_info.initialized() {
+            return err!(ErrorCode::UnInitializedRewardInfo);
+        }
+        if reward_info.consolidated() {
+            return err!(ErrorCode::RewardConsolidated);
+        }
+        // the reward vault of a fee rebate reward is the pool vault
+        if pool_state.is_fee_rebate_reward(reward_index as usize) {
+            return err!(ErrorCode::FeeRebateReward);
+        }
+        require!(
+            ctx.accounts.reward_funder.key() == reward_info.authority
+                || ctx.accounts.reward_funder.key() == pool_state.owner
+                || ctx.accounts.reward_funder.key() == crate::admin::id(),
+            ErrorCode::NotApproved
+        );
+        require_keys_eq!(
+            ctx.accounts.reward_token_vault.key(),
+            reward_info.token_vault
+        );
+        // an ended reward starts a new cycle with set_reward_params instead
+        require_gt!(
+            reward_info.end_time,
+            current_timestamp,
+            ErrorCode::NotApproveUpdateRewardEmissiones
+        );
+
+        let extend_period = reward_extend_period(reward_info.emissions_per_second_x64, amount)?;
+        require_gt!(extend_period, 0, ErrorCode::InvalidRewardPeriod);
+        end_time = reward_info
+            .end_time
+            .checked_add(extend_period)
+            .ok_or(ErrorCode::InvalidRewardPeriod)?;
+        require_gte!(
+            reward_period_limit::MAX_REWARD_PERIOD,
+            end_time - current_timestamp.max(reward_info.open_time),
+            ErrorCode::InvalidRewardPeriod
+        );
+        pool_state.reward_infos[reward_index as usize].end_time = end_time;
+        // the vault must cover what is emitted and not claimed yet, then the rest of the cycle
+        unclaimed_amount = reward_info
+            .reward_total_emissioned
+            .checked_sub(reward_info.reward_claimed)
+            .unwrap()
+            .checked_add(
+                U256::from(end_time - current_timestamp.max(reward_info.open_time))
+                    .mul_div_floor(
+                        U256::from(reward_info.emissions_per_second_x64),
+                        U256::from(fixed_point_64::Q64),
+                    )
+                    .unwrap()
+                    .as_u64(),
+            )
+            .unwrap();
+    }
+
+    let amount_with_transfer_fee = amount
+        .checked_add(util::get_transfer_inverse_fee(
+            ctx.accounts.reward_token_mint.clone(),
+            amount,
+        )?)
+        .unwrap();
+    transfer_from_user_to_pool_vault(
+        &ctx.accounts.reward_funder,
+        &ctx.accounts.funder_token_account.to_account_info(),
+        &ctx.accounts.reward_token_vault.to_account_info(),
+        Some(ctx.accounts.reward_token_mint.clone()),
+        &ctx.accounts.reward_token_program.to_account_info(),
+        Some(ctx.accounts.reward_token_program.to_account_info()),
+        amount_with_transfer_fee,
+    )?;
+    ctx.accounts.reward_token_vault.reload()?;
+    require_gte!(
+        ctx.accounts.reward_token_vault.amount,
+        unclaimed_amount,
+        ErrorCode::InsufficientRewardVault
+    );
+
+    emit!(TopUpRewardEvent {
+        pool_state: ctx.accounts.pool_state.key(),
+        reward_index,
+        amount,
+        end_time,
+    });
+    Ok(())
+}
+
+/// The seconds `amount` lasts at the emission rate, rounded down so the extension is funded
+pub fn reward_extend_period(emissions_per_second_x64: u128, amount: u64) -> Result<u64> {
+    let period = U256::from(amount)
+        .mul_div_floor(
+            U256::from(fixed_point_64::Q64),
+            U256::from(emissions_per_second_x64),
+        )
+        .ok_or(ErrorCode::InvalidRewardPeriod)?;
+    // a low emission rate makes a large top up last longer than any timestamp
+    if period > U256::from(u64::MAX) {
+        return err!(ErrorCode::InvalidRewardPeriod);
+    }
+    Ok(period.as_u64())
+}
+
+#[cfg(test)]
+mod top_up_reward_test {
+    use super::*;
+
+    #[test]
+    fn reward_extend_period_test() {
+        // 10 tokens per second
+        let emissions_per_second_x64 = 10 * fixed_point_64::Q64;
+        assert_eq!(
+            reward_extend_period(emissions_per_second_x64, 1_000).unwrap(),
+            100
+        );
+        // the seconds are rounded down, the extension never emits more than the top up
+        assert_eq!(
+            reward_extend_period(emissions_per_second_x64, 1_009).unwrap(),
+            100
+        );
+        assert_eq!(
+            reward_extend_period(emissions_per_second_x64, 9).unwrap(),
+            0
+        );
+        // a fraction of a token per second
+        assert_eq!(
+            reward_extend_period(fixed_point_64::Q64 / 4, 1_000).unwrap(),
+            4_000
+        );
+        // the period of a large top up at a low rate doesn't fit in the timestamps
+        assert_eq!(
+            reward_extend_period(1, u64::MAX).unwrap_err(),
+            ErrorCode::InvalidRewardPeriod.into()
+        );
+        assert_eq!(
+            reward_extend_period(fixed_point_64::Q64 / 1_000, u64::MAX / 100).unwrap_err(),
+            ErrorCode::InvalidRewardPeriod.into()
+        );
+    }
+}
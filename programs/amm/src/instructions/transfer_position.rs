@@ -0,0 +1,123 @@
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token_interface::{self, Mint, TokenAccount, TokenInterface, TransferChecked};
+
+#[derive(Accounts)]
+pub struct TransferPosition<'info> {
+    /// The position owner
+    #[account(mut)]
+    pub nft_owner: Signer<'info>,
+
+    /// CHECK: The new owner of the position, receiving the nft in its associated token account
+    #[account(
+        constraint = new_owner.key() != nft_owner.key() @ ErrorCode::InvalidPositionTransfer,
+        constraint = new_owner.key() != Pubkey::default() @ ErrorCode::InvalidPositionTransfer
+    )]
+    pub new_owner: UncheckedAccount<'info>,
+
+    /// Mint address of the tokenized position
+    pub position_nft_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    /// The token account holding the position nft
+    #[account(
+        mut,
+        token::mint = position_nft_mint,
+        token::authority = nft_owner,
+        constraint = nft_account.amount == 1
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The associated token account of the new owner receiving the position nft
+    #[account(
+        init_if_needed,
+        payer = nft_owner,
+        associated_token::mint = position_nft_mint,
+        associated_token::authority = new_owner,
+        associated_token::token_program = token_program,
+    )]
+    pub recipient_nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// The position moved with the nft
+    #[account(mut, constraint = personal_position.load()?.nft_mint == position_nft_mint.key())]
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// The token program of the position nft, spl token or token 2022
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Drop what the previous owner set up for the position, the fee recipient it chose no longer
+/// receives the payouts and the actions it signed for relaying can't be submitted anymore
+pub fn reset_position_delegation(personal_position: &mut PersonalPositionState) {
+    personal_position.clear_fee_recipient();
+    personal_position.relay_nonce = personal_position.relay_nonce.checked_add(1).unwrap();
+}
+
+/// Move the position nft to the associated token account of `new_owner` and reset the
+/// delegation of the position, in one instruction
+pub fn transfer_position(ctx: Context<TransferPosition>) -> Result<()> {
+    // a frozen account can't send or receive the nft, fail with a clear error before the cpi
+    require!(
+        !ctx.accounts.nft_account.is_frozen(),
+        ErrorCode::PositionNftAccountFrozen
+    );
+    require!(
+        !ctx.accounts.recipient_nft_account.is_frozen(),
+        ErrorCode::PositionNftAccountFrozen
+    );
+
+    token_interface::transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.nft_account.to_account_info(),
+                mint: ctx.accounts.position_nft_mint.to_account_info(),
+                to: ctx.accounts.recipient_nft_account.to_account_info(),
+                authority: ctx.accounts.nft_owner.to_account_info(),
+            },
+        ),
+        1,
+        0,
+    )?;
+
+    reset_position_delegation(&mut ctx.accounts.personal_position.load_mut()?);
+
+    emit!(TransferPositionEvent {
+        personal_position: ctx.accounts.personal_position.key(),
+        position_nft_mint: ctx.accounts.position_nft_mint.key(),
+        from: ctx.accounts.nft_owner.key(),
+        to: ctx.accounts.new_owner.key(),
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod transfer_position_test {
+    use super::*;
+
+    #[test]
+    fn reset_position_delegation_test() {
+        let mut personal_position = PersonalPositionState {
+            liquidity: 1_000,
+            relay_nonce: 3,
+            fee_recipient: Pubkey::new_unique(),
+            ..Default::default()
+        };
+        reset_position_delegation(&mut personal_position);
+        let (fee_recipient, relay_nonce) = (
+            personal_position.fee_recipient,
+            personal_position.relay_nonce,
+        );
+        assert_eq!(fee_recipient, Pubkey::default());
+        // the actions signed with the nonce 3 are no longer valid
+        assert_eq!(relay_nonce, 4);
+        // the position itself is untouched
+        let liquidity = personal_position.liquidity;
+        assert_eq!(liquidity, 1_000);
+    }
+}
@@ -0,0 +1,186 @@
+use super::get_metadata_data;
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_spl::metadata::Metadata;
+use anchor_spl::token_2022::spl_token_2022::{
+    self,
+    extension::{BaseStateWithExtensions, StateWithExtensions},
+};
+use anchor_spl::token_2022::Token2022;
+use anchor_spl::token_interface::TokenAccount;
+use mpl_token_metadata::{
+    instruction::update_metadata_accounts_v2,
+    state::{DataV2, Metadata as MetaplexMetadata, TokenMetadataAccount, PREFIX},
+};
+use spl_token_metadata_interface::state::{Field, TokenMetadata};
+
+#[derive(Accounts)]
+pub struct UpdatePositionMetadata<'info> {
+    /// The position nft owner, pays the rent of a longer token-2022 metadata
+    #[account(mut)]
+    pub nft_owner: Signer<'info>,
+
+    /// The token account for the tokenized position
+    #[account(
+        constraint = nft_account.mint == personal_position.load()?.nft_mint,
+        constraint = nft_account.amount == 1,
+        token::authority = nft_owner
+    )]
+    pub nft_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: The mint of the position nft, holds the metadata of token-2022 positions
+    #[account(
+        mut,
+        address = personal_position.load()?.nft_mint
+    )]
+    pub position_nft_mint: UncheckedAccount<'info>,
+
+    /// The position, update authority of the token-2022 metadata
+    pub personal_position: AccountLoader<'info, PersonalPositionState>,
+
+    /// The pool of the position, update authority of the metaplex metadata
+    #[account(address = personal_position.load()?.pool_id)]
+    pub pool_state: AccountLoader<'info, PoolState>,
+
+    /// CHECK: The metaplex metadata of the position nft, unused by token-2022 positions
+    #[account(
+        mut,
+        seeds = [
+            PREFIX.as_bytes(),
+            metadata_program.key().as_ref(),
+            position_nft_mint.key().as_ref(),
+        ],
+        seeds::program = metadata_program.key(),
+        bump
+    )]
+    pub metadata_account: UncheckedAccount<'info>,
+
+    /// Program to update the metaplex metadata
+    pub metadata_program: Program<'info, Metadata>,
+
+    /// Program to update the token-2022 metadata
+    pub token_program_2022: Program<'info, Token2022>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Rewrite the name, symbol and uri of the position nft metadata to the current ones
+pub fn update_position_metadata(ctx: Context<UpdatePositionMetadata>) -> Result<()> {
+    let (name, symbol, uri) = get_metadata_data(ctx.accounts.personal_position.key());
+    if *ctx.accounts.position_nft_mint.owner == Token2022::id() {
+        update_token_metadata_extension(ctx.accounts, name, symbol, uri)
+    } else {
+        update_metaplex_metadata(ctx.accounts, name, symbol, uri)
+    }
+}
+
+fn update_metaplex_metadata(
+    accounts: &UpdatePositionMetadata,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    let metadata_account = accounts.metadata_account.to_account_info();
+    // positions opened without metadata have none, and the mint authority is gone so it can't be
+    // created anymore
+    require!(
+        !metadata_account.data_is_empty(),
+        ErrorCode::PositionMetadataNotUpdatable
+    );
+    let metadata = MetaplexMetadata::from_account_info(&metadata_account)?;
+    require!(
+        metadata.is_mutable && metadata.update_authority == accounts.pool_state.key(),
+        ErrorCode::PositionMetadataNotUpdatable
+    );
+
+    let pool_state = accounts.pool_state.load()?;
+    solana_program::program::invoke_signed(
+        &update_metadata_accounts_v2(
+            accounts.metadata_program.key(),
+            metadata_account.key(),
+            accounts.pool_state.key(),
+            None,
+            Some(DataV2 {
+                name,
+                symbol,
+                uri,
+                seller_fee_basis_points: metadata.data.seller_fee_basis_points,
+                creators: metadata.data.creators,
+                collection: metadata.collection,
+                uses: metadata.uses,
+            }),
+            None,
+            None,
+        ),
+        &[metadata_account, accounts.pool_state.to_account_info()],
+        &[&pool_state.seeds()],
+    )?;
+    Ok(())
+}
+
+fn update_token_metadata_extension<'info>(
+    accounts: &UpdatePositionMetadata<'info>,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    let position_nft_mint = accounts.position_nft_mint.to_account_info();
+    let mint_data = position_nft_mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<spl_token_2022::state::Mint>::unpack(&mint_data)?;
+    let mut metadata = mint_state
+        .get_variable_len_extension::<TokenMetadata>()
+        .map_err(|_| ErrorCode::PositionMetadataNotUpdatable)?;
+    require!(
+        Option::<Pubkey>::from(metadata.update_authority) == Some(accounts.personal_position.key()),
+        ErrorCode::PositionMetadataNotUpdatable
+    );
+    let fields = [
+        (Field::Name, name),
+        (Field::Symbol, symbol),
+        (Field::Uri, uri),
+    ];
+    for (field, value) in fields.iter() {
+        metadata.update(field.clone(), value.clone());
+    }
+    // a longer metadata is reallocated by the token program, the owner tops up the rent first
+    let new_account_len = mint_state.try_get_new_account_len::<TokenMetadata>(&metadata)?;
+    let new_rent_exempt_lamports = Rent::get()?.minimum_balance(new_account_len);
+    let additional_lamports = new_rent_exempt_lamports.saturating_sub(position_nft_mint.lamports());
+    // CPI call will borrow the account data
+    drop(mint_data);
+    if additional_lamports > 0 {
+        transfer(
+            CpiContext::new(
+                accounts.system_program.to_account_info(),
+                Transfer {
+                    from: accounts.nft_owner.to_account_info(),
+                    to: position_nft_mint.clone(),
+                },
+            ),
+            additional_lamports,
+        )?;
+    }
+
+    let personal_position = accounts.personal_position.to_account_info();
+    for (field, value) in fields {
+        solana_program::program::invoke_signed(
+            &spl_token_metadata_interface::instruction::update_field(
+                accounts.token_program_2022.key,
+                position_nft_mint.key,
+                personal_position.key,
+                field,
+                value,
+            ),
+            &[
+                position_nft_mint.clone(),
+                personal_position.clone(),
+                accounts.token_program_2022.to_account_info(),
+            ],
+            &[&accounts.personal_position.load()?.seeds()],
+        )?;
+    }
+    Ok(())
+}
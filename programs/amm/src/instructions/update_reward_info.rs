@@ -13,6 +13,7 @@ pub fn update_reward_infos<'a, 'b, 'c, 'info>(
 ) -> Result<()> {
     let clock = Clock::get()?;
     let mut pool_state = ctx.accounts.pool_state.load_mut()?;
+    pool_state.check_unlocked()?;
     let updated_reward_infos =
         pool_state.update_reward_infos(u64::try_from(clock.unix_timestamp).unwrap())?;
 
@@ -22,3 +23,335 @@ pub fn update_reward_infos<'a, 'b, 'c, 'info>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod reward_fairness_test {
+    use crate::instructions::modify_position;
+    use crate::libraries::{fixed_point_64, liquidity_math, tick_math};
+    use crate::states::pool_test::build_pool;
+    use crate::states::*;
+    use anchor_lang::prelude::Pubkey;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+    use std::collections::{BTreeMap, HashMap};
+    use std::convert::identity;
+
+    const TICK_SPACING: u16 = 10;
+    const OPEN_TIME: u64 = 1_700_000_000;
+    const DURATION: u64 = 10_000;
+    const EMISSIONS_PER_SECOND: u64 = 1_000;
+
+    struct Lp {
+        position: PersonalPositionState,
+        /// The reward the LP is due by its share of the in range liquidity over time
+        expected_reward: f64,
+        open: bool,
+    }
+
+    /// A pool with one reward campaign, LPs open and close positions and the price moves while
+    /// the campaign runs. The reward each LP is due is tracked independently of the program math
+    /// from its liquidity seconds in range.
+    struct Campaign {
+        pool_state: std::cell::RefCell<PoolState>,
+        ticks: BTreeMap<i32, TickState>,
+        protocol_positions: HashMap<(i32, i32), ProtocolPositionState>,
+        lps: Vec<Lp>,
+        timestamp: u64,
+        /// The number of times the reward infos and the position rewards were updated, each
+        /// one can round by one token
+        rounding_events: u64,
+    }
+
+    impl Campaign {
+        fn new(tick_current: i32) -> Self {
+            let pool_state = build_pool(
+                tick_current,
+                TICK_SPACING,
+                tick_math::get_sqrt_price_at_tick(tick_current).unwrap(),
+                0,
+            );
+            {
+                let mut pool_state = pool_state.borrow_mut();
+                pool_state.fee_growth_global_0_x64 = 0;
+                pool_state.fee_growth_global_1_x64 = 0;
+                pool_state
+                    .initialize_reward(
+                        OPEN_TIME,
+                        OPEN_TIME + DURATION,
+                        u128::from(EMISSIONS_PER_SECOND) * fixed_point_64::Q64,
+                        &Pubkey::new_unique(),
+                        &Pubkey::new_unique(),
+                        &Pubkey::new_unique(),
+                        &OperationState {
+                            bump: 0,
+                            operation_owners: [Pubkey::default(); OPERATION_SIZE_USIZE],
+                            whitelist_mints: [Pubkey::default(); WHITE_MINT_SIZE_USIZE],
+                        },
+                    )
+                    .unwrap();
+            }
+            Campaign {
+                pool_state,
+                ticks: BTreeMap::new(),
+                protocol_positions: HashMap::new(),
+                lps: Vec::new(),
+                timestamp: OPEN_TIME,
+                rounding_events: 0,
+            }
+        }
+
+        fn in_range(&self, lp: &Lp) -> bool {
+            let tick_current = self.pool_state.borrow().tick_current;
+            lp.open
+                && lp.position.tick_lower_index <= tick_current
+                && tick_current < lp.position.tick_upper_index
+        }
+
+        /// Move the time forward and accrue the expected rewards of the LPs in range
+        fn advance_to(&mut self, timestamp: u64) {
+            assert!(timestamp >= self.timestamp);
+            let start = self.timestamp.clamp(OPEN_TIME, OPEN_TIME + DURATION);
+            let end = timestamp.clamp(OPEN_TIME, OPEN_TIME + DURATION);
+            let in_range: Vec<bool> = self.lps.iter().map(|lp| self.in_range(lp)).collect();
+            let active_liquidity: u128 = self
+                .lps
+                .iter()
+                .zip(in_range.iter())
+                .filter(|(_, in_range)| **in_range)
+                .map(|(lp, _)| lp.position.liquidity)
+                .sum();
+            // the model and the pool agree on the liquidity in range
+            assert_eq!(
+                active_liquidity,
+                identity(self.pool_state.borrow().liquidity)
+            );
+            if active_liquidity > 0 {
+                let emitted = ((end - start) * EMISSIONS_PER_SECOND) as f64;
+                for (lp, in_range) in self.lps.iter_mut().zip(in_range) {
+                    if in_range {
+                        lp.expected_reward +=
+                            emitted * lp.position.liquidity as f64 / active_liquidity as f64;
+                    }
+                }
+            }
+            self.timestamp = timestamp;
+        }
+
+        fn modify(&mut self, tick_lower: i32, tick_upper: i32, liquidity_delta: i128) {
+            let mut tick_lower_state =
+                self.ticks
+                    .get(&tick_lower)
+                    .copied()
+                    .unwrap_or_else(|| TickState {
+                        tick: tick_lower,
+                        ..Default::default()
+                    });
+            let mut tick_upper_state =
+                self.ticks
+                    .get(&tick_upper)
+                    .copied()
+                    .unwrap_or_else(|| TickState {
+                        tick: tick_upper,
+                        ..Default::default()
+                    });
+            let protocol_position = self
+                .protocol_positions
+                .entry((tick_lower, tick_upper))
+                .or_default();
+            modify_position(
+                liquidity_delta,
+                &mut self.pool_state.borrow_mut(),
+                protocol_position,
+                &mut tick_lower_state,
+                &mut tick_upper_state,
+                self.timestamp,
+            )
+            .unwrap();
+            for tick_state in [tick_lower_state, tick_upper_state] {
+                if tick_state.is_initialized() {
+                    self.ticks.insert(tick_state.tick, tick_state);
+                } else {
+                    self.ticks.remove(&identity(tick_state.tick));
+                }
+            }
+            self.rounding_events += 1;
+        }
+
+        fn open_position(&mut self, tick_lower: i32, tick_upper: i32, liquidity: u128) -> usize {
+            self.modify(tick_lower, tick_upper, i128::try_from(liquidity).unwrap());
+            let reward_growth_inside =
+                self.protocol_positions[&(tick_lower, tick_upper)].reward_growth_inside;
+            let mut position = PersonalPositionState {
+                tick_lower_index: tick_lower,
+                tick_upper_index: tick_upper,
+                ..Default::default()
+            };
+            position
+                .update_rewards(reward_growth_inside, false)
+                .unwrap();
+            position.liquidity = liquidity;
+            self.lps.push(Lp {
+                position,
+                expected_reward: 0.0,
+                open: true,
+            });
+            self.lps.len() - 1
+        }
+
+        fn close_position(&mut self, lp_index: usize) {
+            let position = &self.lps[lp_index].position;
+            let (tick_lower, tick_upper) = (position.tick_lower_index, position.tick_upper_index);
+            let liquidity = position.liquidity;
+            self.modify(tick_lower, tick_upper, -i128::try_from(liquidity).unwrap());
+            let reward_growth_inside =
+                self.protocol_positions[&(tick_lower, tick_upper)].reward_growth_inside;
+            let lp = &mut self.lps[lp_index];
+            lp.position
+                .update_rewards(reward_growth_inside, true)
+                .unwrap();
+            lp.position.liquidity = 0;
+            lp.open = false;
+        }
+
+        /// Move the price to the tick, crossing the initialized ticks on the way as a swap does
+        fn move_price(&mut self, tick_target: i32) {
+            let mut pool_state = self.pool_state.borrow_mut();
+            let reward_infos = pool_state.update_reward_infos(self.timestamp).unwrap();
+            self.rounding_events += 1;
+            let tick_current = pool_state.tick_current;
+            if tick_target > tick_current {
+                for (_, tick_state) in self.ticks.range_mut(tick_current + 1..=tick_target) {
+                    let liquidity_net = tick_state.cross(0, 0, &reward_infos).unwrap();
+                    pool_state.liquidity =
+                        liquidity_math::add_delta(pool_state.liquidity, liquidity_net).unwrap();
+                }
+            } else if tick_target < tick_current {
+                for (_, tick_state) in self.ticks.range_mut(tick_target + 1..=tick_current).rev() {
+                    let liquidity_net = tick_state.cross(0, 0, &reward_infos).unwrap();
+                    pool_state.liquidity =
+                        liquidity_math::add_delta(pool_state.liquidity, -liquidity_net).unwrap();
+                }
+            }
+            pool_state.tick_current = tick_target;
+            pool_state.sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick_target).unwrap();
+        }
+
+        /// Close the open positions after the campaign ends and check the rewards paid out
+        fn settle(mut self) {
+            self.advance_to(OPEN_TIME + DURATION + 1);
+            for lp_index in 0..self.lps.len() {
+                if self.lps[lp_index].open {
+                    self.close_position(lp_index);
+                }
+            }
+            let reward_info = self.pool_state.borrow().reward_infos[0];
+            let total_emissioned = reward_info.reward_total_emissioned;
+            let mut total_distributed = 0;
+            let mut total_expected = 0.0;
+            for lp in self.lps.iter() {
+                let reward = lp.position.reward_infos[0].reward_amount_owed;
+                total_distributed += reward;
+                total_expected += lp.expected_reward;
+                // the share of each LP follows its liquidity seconds in range
+                assert!(
+                    (reward as f64 - lp.expected_reward).abs() <= self.rounding_events as f64,
+                    "reward:{}, expected:{}, tolerance:{}",
+                    reward,
+                    lp.expected_reward,
+                    self.rounding_events
+                );
+            }
+            // nothing is paid out beyond the emissions, and no more than rounding is left behind
+            assert!(total_distributed <= total_emissioned);
+            assert!(total_emissioned - total_distributed <= self.rounding_events);
+            assert!(
+                (total_emissioned as f64 - total_expected).abs() <= self.rounding_events as f64
+            );
+            assert!(total_emissioned <= DURATION * EMISSIONS_PER_SECOND + self.rounding_events);
+        }
+    }
+
+    #[test]
+    fn cohorts_of_one_range_share_by_liquidity_test() {
+        let mut campaign = Campaign::new(0);
+        // early cohort
+        campaign.open_position(-100, 100, 1_000_000_000);
+        campaign.open_position(-100, 100, 3_000_000_000);
+        campaign.advance_to(OPEN_TIME + 2_500);
+        // late cohort
+        let late = campaign.open_position(-100, 100, 4_000_000_000);
+        campaign.advance_to(OPEN_TIME + 5_000);
+        // the late cohort leaves half way
+        campaign.close_position(late);
+        campaign.advance_to(OPEN_TIME + 7_500);
+        campaign.open_position(-100, 100, 2_000_000_000);
+        campaign.settle();
+    }
+
+    #[test]
+    fn price_moving_across_ranges_test() {
+        let mut campaign = Campaign::new(0);
+        // a wide range in range all the time and narrow ranges on both sides of the price
+        campaign.open_position(-1000, 1000, 5_000_000_000);
+        campaign.open_position(-300, -100, 2_000_000_000);
+        campaign.open_position(100, 300, 2_000_000_000);
+        campaign.open_position(-50, 50, 8_000_000_000);
+        for (step, tick) in [150, 250, -150, -20, 400, -900, 0].into_iter().enumerate() {
+            campaign.advance_to(OPEN_TIME + 1_000 * (step as u64 + 1));
+            campaign.move_price(tick);
+        }
+        campaign.settle();
+    }
+
+    #[test]
+    fn no_liquidity_in_range_test() {
+        let mut campaign = Campaign::new(0);
+        let lp = campaign.open_position(-100, 100, 1_000_000_000);
+        campaign.advance_to(OPEN_TIME + 2_000);
+        // the price leaves the only range, nothing is emitted until it returns
+        campaign.move_price(500);
+        campaign.advance_to(OPEN_TIME + 4_000);
+        campaign.move_price(0);
+        campaign.advance_to(OPEN_TIME + 6_000);
+        campaign.close_position(lp);
+        campaign.advance_to(OPEN_TIME + 8_000);
+        campaign.open_position(-200, 200, 1_000_000_000);
+        let reward_info = campaign.pool_state.borrow().reward_infos[0];
+        assert!(identity(reward_info.reward_total_emissioned) < 5_000 * EMISSIONS_PER_SECOND);
+        campaign.settle();
+    }
+
+    #[test]
+    fn random_cohorts_test() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut campaign = Campaign::new(0);
+            campaign.open_position(-200, 200, 1_000_000_000);
+            let step = 200;
+            for timestamp in (OPEN_TIME + step..OPEN_TIME + DURATION).step_by(step as usize) {
+                campaign.advance_to(timestamp);
+                match rng.gen_range(0..3) {
+                    0 => {
+                        let tick_lower = rng.gen_range(-30..30) * i32::from(TICK_SPACING);
+                        let width = rng.gen_range(1..30) * i32::from(TICK_SPACING);
+                        let liquidity = rng.gen_range(1_000_000u128..10_000_000_000);
+                        campaign.open_position(tick_lower, tick_lower + width, liquidity);
+                    }
+                    1 => {
+                        let open: Vec<usize> = (0..campaign.lps.len())
+                            .filter(|i| campaign.lps[*i].open)
+                            .collect();
+                        if !open.is_empty() {
+                            campaign.close_position(open[rng.gen_range(0..open.len())]);
+                        }
+                    }
+                    _ => {
+                        let tick = rng.gen_range(-400..400);
+                        campaign.move_price(tick);
+                    }
+                }
+            }
+            campaign.settle();
+        }
+    }
+}
@@ -1,7 +1,13 @@
+#[cfg(any(test, feature = "cpi-client"))]
+pub mod cpi_client;
 pub mod error;
+#[cfg(any(test, feature = "client"))]
+pub mod events;
 pub mod instructions;
 pub mod libraries;
 pub mod states;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod test_fixtures;
 pub mod util;
 
 use anchor_lang::prelude::*;
@@ -42,7 +48,8 @@ pub mod amm_v3 {
     /// # Arguments
     ///
     /// * `ctx`- The accounts needed by instruction.
-    /// * `index` - The index of amm config, there may be multiple config.
+    /// * `index` - The index of amm config, there may be multiple config. Configs are only unique
+    /// by index, several can share a tick spacing or a trade fee rate.
     /// * `tick_spacing` - The tickspacing binding with config, cannot be changed.
     /// * `trade_fee_rate` - Trade fee rate, can be changed.
     /// * `protocol_fee_rate` - The rate of protocol fee within tarde fee.
@@ -79,14 +86,54 @@ pub mod amm_v3 {
     /// * `trade_fee_rate`- The new trade fee rate of amm config, be set when `param` is 0
     /// * `protocol_fee_rate`- The new protocol fee rate of amm config, be set when `param` is 1
     /// * `fund_fee_rate`- The new fund fee rate of amm config, be set when `param` is 2
-    /// * `new_owner`- The config's new owner, be set when `param` is 3
+    /// * `new_owner`- The config's new owner, be set when `param` is 3 until an owner is proposed
     /// * `new_fund_owner`- The config's new fund owner, be set when `param` is 4
-    /// * `param`- The vaule can be 0 | 1 | 2 | 3 | 4, otherwise will report a error
+    /// * `max_initial_tick_deviation`- The max ticks the initial price of a new pool can be away from the owner's reference price, zero to disable, be set when `param` is 5
+    /// * `swap_delay_after_create`- The seconds a new pool can't be swapped, zero to disable, be set when `param` is 6
+    /// * `param`- The vaule can be 0 | 1 | 2 | 3 | 4 | 5 | 6, otherwise will report a error
     ///
     pub fn update_amm_config(ctx: Context<UpdateAmmConfig>, param: u8, value: u32) -> Result<()> {
         instructions::update_amm_config(ctx, param, value)
     }
 
+    /// Updates the pool creation fee of the amm config
+    /// Must be called by the admin
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `pool_creation_fee`- The lamports paid to the fund owner for creating a pool, zero to disable
+    ///
+    pub fn update_pool_creation_fee(
+        ctx: Context<UpdateAmmConfig>,
+        pool_creation_fee: u64,
+    ) -> Result<()> {
+        instructions::update_pool_creation_fee(ctx, pool_creation_fee)
+    }
+
+    /// Proposes a new owner of the amm config, who becomes the owner by accepting it.
+    /// Must be called by the current owner or admin, the owner can't be set by
+    /// `update_amm_config` anymore afterwards
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `new_owner`- The proposed owner, replaces a pending proposal
+    ///
+    pub fn propose_config_owner(ctx: Context<ProposeConfigOwner>, new_owner: Pubkey) -> Result<()> {
+        instructions::propose_config_owner(ctx, new_owner)
+    }
+
+    /// Accepts the owner proposal of the amm config, signed by the proposed owner
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn accept_config_owner(ctx: Context<AcceptConfigOwner>) -> Result<()> {
+        instructions::accept_config_owner(ctx)
+    }
+
     /// Creates a pool for the given token pair and the initial price
     ///
     /// # Arguments
@@ -94,6 +141,9 @@ pub mod amm_v3 {
     /// * `ctx`- The context of accounts
     /// * `sqrt_price_x64` - the initial sqrt price (amount_token_1 / amount_token_0) of the pool as a Q64.64
     /// Note: The open_time must be smaller than the current block_timestamp on chain.
+    /// If the config sets `max_initial_tick_deviation`, the instruction before must be the ed25519
+    /// program verifying a `PoolPriceReference` signed by the config owner, and the instructions
+    /// sysvar is passed as a remaining account.
     pub fn create_pool(
         ctx: Context<CreatePool>,
         sqrt_price_x64: u128,
@@ -113,6 +163,47 @@ pub mod amm_v3 {
         instructions::update_pool_status(ctx, status)
     }
 
+    /// Enable or disable one operation of a pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `bit` - The `PoolStatusBitIndex` of the operation
+    /// * `enable` - Whether the operation is allowed
+    ///
+    pub fn set_pool_status_bit(
+        ctx: Context<SetPoolStatusBit>,
+        bit: u8,
+        enable: bool,
+    ) -> Result<()> {
+        instructions::set_pool_status_bit(ctx, bit, enable)
+    }
+
+    /// Update the minimum input amount of the swaps of a pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `min_swap_amount_in` - The minimum input amount including the fee, 0 to disable
+    ///
+    pub fn update_pool_min_swap_amount(
+        ctx: Context<UpdatePoolMinSwapAmount>,
+        min_swap_amount_in: u64,
+    ) -> Result<()> {
+        instructions::update_pool_min_swap_amount(ctx, min_swap_amount_in)
+    }
+
+    /// Returns the effective fees, status, tick limits and creation bands of the pool as return
+    /// data, combined from the amm config and the pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn get_effective_params(ctx: Context<GetEffectiveParams>) -> Result<EffectiveParams> {
+        instructions::get_effective_params(ctx)
+    }
+
     /// Creates an operation account for the program
     ///
     /// # Arguments
@@ -173,6 +264,21 @@ pub mod amm_v3 {
         instructions::initialize_reward(ctx, param)
     }
 
+    /// Initialize a reward for a given pool paid in token_0 or token_1 out of the protocol fees
+    /// of the pool, no reward vault is funded
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `param` - reward open and end timestamp, emissions, token mint and the share of the protocol fees the rebate can take
+    ///
+    pub fn initialize_fee_rebate_reward(
+        ctx: Context<InitializeFeeRebateReward>,
+        param: InitializeFeeRebateRewardParam,
+    ) -> Result<()> {
+        instructions::initialize_fee_rebate_reward(ctx, param)
+    }
+
     /// Collect remaining reward token for reward founder
     ///
     /// # Arguments
@@ -187,6 +293,50 @@ pub mod amm_v3 {
         instructions::collect_remaining_rewards(ctx, reward_index)
     }
 
+    /// Snapshot the owed rewards of an ended reward into a merkle root, afterwards the rewards
+    /// are claimed with `claim_merkle_reward` instead of being collected by positions
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `reward_index` - the index to reward info
+    /// * `merkle_root` - root of the snapshot tree, leaf is `keccak(claimant, amount)`
+    /// * `total_amount` - the total owed amount in the snapshot
+    /// * `snapshot_reward_claimed` - the reward claimed of the pool when the snapshot was taken, the
+    /// root is rejected if positions collected since
+    ///
+    pub fn set_reward_merkle_root(
+        ctx: Context<SetRewardMerkleRoot>,
+        reward_index: u8,
+        merkle_root: [u8; 32],
+        total_amount: u64,
+        snapshot_reward_claimed: u64,
+    ) -> Result<()> {
+        instructions::set_reward_merkle_root(
+            ctx,
+            reward_index,
+            merkle_root,
+            total_amount,
+            snapshot_reward_claimed,
+        )
+    }
+
+    /// Claim the owed reward of the signer against a merkle snapshot
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    /// * `amount` - the owed amount in the snapshot
+    /// * `proof` - the merkle proof of the claimant leaf
+    ///
+    pub fn claim_merkle_reward(
+        ctx: Context<ClaimMerkleReward>,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        instructions::claim_merkle_reward(ctx, amount, proof)
+    }
+
     /// Update rewards info of the given pool, can be called for everyone
     ///
     /// # Arguments
@@ -199,6 +349,19 @@ pub mod amm_v3 {
         instructions::update_reward_infos(ctx)
     }
 
+    /// Reset the uninitialized ticks of a tick array that still hold values of their last use,
+    /// can be called for everyone as the ticks referenced by positions are not touched
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx`- The context of accounts
+    ///
+    pub fn compact_tick_array<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CompactTickArray<'info>>,
+    ) -> Result<()> {
+        instructions::compact_tick_array(ctx)
+    }
+
     /// Restset reward param, start a new reward cycle or extend the current cycle.
     ///
     /// # Arguments
@@ -226,6 +389,18 @@ pub mod amm_v3 {
         )
     }
 
+    /// Deposit more of a running reward, extending its end time at the current emission rate
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `reward_index` - The index of reward token in the pool.
+    /// * `amount` - The amount of reward token deposited, the end time is extended by the seconds it lasts
+    ///
+    pub fn top_up_reward(ctx: Context<TopUpReward>, reward_index: u8, amount: u64) -> Result<()> {
+        instructions::top_up_reward(ctx, reward_index, amount)
+    }
+
     /// Collect the protocol fee accrued to the pool
     ///
     /// # Arguments
@@ -234,14 +409,32 @@ pub mod amm_v3 {
     /// * `amount_0_requested` - The maximum amount of token_0 to send, can be 0 to collect fees in only token_1
     /// * `amount_1_requested` - The maximum amount of token_1 to send, can be 0 to collect fees in only token_0
     ///
-    pub fn collect_protocol_fee(
-        ctx: Context<CollectProtocolFee>,
+    /// If the amm config splits the protocol fees, the remaining accounts are its protocol fee
+    /// split and the token_0 and token_1 accounts of the secondary destination
+    ///
+    pub fn collect_protocol_fee<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CollectProtocolFee<'info>>,
         amount_0_requested: u64,
         amount_1_requested: u64,
     ) -> Result<()> {
         instructions::collect_protocol_fee(ctx, amount_0_requested, amount_1_requested)
     }
 
+    /// Split the protocol fees of an amm config between two destinations, the split share goes
+    /// to the token accounts of the secondary destination and the rest to the primary one
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `protocol_fee_split_rate` - The share of the secondary destination, denominated in hundredths of a bip (10^-6), 0 to stop splitting
+    ///
+    pub fn set_protocol_fee_split(
+        ctx: Context<SetProtocolFeeSplit>,
+        protocol_fee_split_rate: u32,
+    ) -> Result<()> {
+        instructions::set_protocol_fee_split(ctx, protocol_fee_split_rate)
+    }
+
     /// Collect the fund fee accrued to the pool
     ///
     /// # Arguments
@@ -258,6 +451,78 @@ pub mod amm_v3 {
         instructions::collect_fund_fee(ctx, amount_0_requested, amount_1_requested)
     }
 
+    /// Collect all the fund fees accrued to several pools in one transaction
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, the remaining accounts are for every pool its pool state,
+    /// amm config, token vaults, vault mints and recipient token accounts
+    ///
+    pub fn collect_fund_fee_batch<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CollectFundFeeBatch<'info>>,
+    ) -> Result<()> {
+        instructions::collect_fund_fee_batch(ctx)
+    }
+
+    /// Set the buyback token, recipient and slippage bounds used to convert the protocol fees of the pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `min_sqrt_price_x64` - The crank can't move the pool price below this price
+    /// * `max_sqrt_price_x64` - The crank can't move the pool price above this price
+    /// * `max_amount_in_per_crank` - The maximum amount of protocol fees swapped by one crank, 0 to disable
+    /// * `twap_period` - The seconds of the pool twap the crank price is bounded by
+    /// * `max_twap_tick_deviation` - The crank can't move the pool price more than this many ticks away from the twap
+    /// * `min_crank_interval` - The minimum seconds between two cranks
+    ///
+    pub fn set_buyback_config(
+        ctx: Context<SetBuybackConfig>,
+        min_sqrt_price_x64: u128,
+        max_sqrt_price_x64: u128,
+        max_amount_in_per_crank: u64,
+        twap_period: u32,
+        max_twap_tick_deviation: u32,
+        min_crank_interval: u64,
+    ) -> Result<()> {
+        instructions::set_buyback_config(
+            ctx,
+            min_sqrt_price_x64,
+            max_sqrt_price_x64,
+            max_amount_in_per_crank,
+            twap_period,
+            max_twap_tick_deviation,
+            min_crank_interval,
+        )
+    }
+
+    /// Permissionless crank swapping the protocol fees through the pool into the buyback token
+    /// and sending the output to the buyback recipient
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn buyback_protocol_fee<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, BuybackProtocolFee<'info>>,
+    ) -> Result<()> {
+        instructions::buyback_protocol_fee(ctx)
+    }
+
+    /// Compare the vault balances with the amounts owed out of them for the sampled positions and
+    /// write the report to the reconciliation account of the pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, the remaining accounts are the personal positions each
+    /// followed by its lower and upper tick arrays
+    ///
+    pub fn reconcile_vaults<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ReconcileVaults<'info>>,
+    ) -> Result<()> {
+        instructions::reconcile_vaults(ctx)
+    }
+
     /// #[deprecated(note = "Use `open_position_with_token22_nft` instead.")]
     /// Creates a new position wrapped in a NFT
     ///
@@ -379,6 +644,96 @@ pub mod amm_v3 {
         )
     }
 
+    /// Creates a new position wrapped in a Token2022 NFT with the most liquidity the desired
+    /// amounts provide, the liquidity is computed from the pool price at execution
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `tick_lower_index` - The low boundary of market
+    /// * `tick_upper_index` - The upper boundary of market
+    /// * `tick_array_lower_start_index` - The start index of tick array which include tick low
+    /// * `tick_array_upper_start_index` - The start index of tick array which include tick upper
+    /// * `amount_0_desired` - The max amount of token_0 to spend, transfer fee included
+    /// * `amount_1_desired` - The max amount of token_1 to spend, transfer fee included
+    /// * `amount_0_min` - The min amount of token_0 to deposit, which serves as a slippage check
+    /// * `amount_1_min` - The min amount of token_1 to deposit, which serves as a slippage check
+    /// * `with_metadata` - The flag indicating whether to create NFT mint metadata
+    ///
+    pub fn open_position_with_token_amounts<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, OpenPositionWithToken22Nft<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        amount_0_desired: u64,
+        amount_1_desired: u64,
+        amount_0_min: u64,
+        amount_1_min: u64,
+        with_metadata: bool,
+    ) -> Result<()> {
+        instructions::open_position_with_token_amounts(
+            ctx,
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            amount_0_desired,
+            amount_1_desired,
+            amount_0_min,
+            amount_1_min,
+            with_metadata,
+        )
+    }
+
+    /// Creates a new position wrapped in a Token2022 NFT, first swapping part of one token for the
+    /// other in the pool so the deposit matches the ratio of the range at the price after the swap
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `tick_lower_index` - The low boundary of market
+    /// * `tick_upper_index` - The upper boundary of market
+    /// * `tick_array_lower_start_index` - The start index of tick array which include tick low
+    /// * `tick_array_upper_start_index` - The start index of tick array which include tick upper
+    /// * `amount_0_max` - The max amount of token_0 to spend on the swap and the deposit
+    /// * `amount_1_max` - The max amount of token_1 to spend on the swap and the deposit
+    /// * `swap_amount_in` - The input amount of the swap, zero to open without swapping
+    /// * `zero_for_one` - The direction of the swap, true to swap token_0 for token_1
+    /// * `sqrt_price_limit_x64` - The swap stops at this price, zero for no limit
+    /// * `liquidity_min` - The min liquidity to add, which serves as a slippage check
+    /// * `with_metadata` - The flag indicating whether to create NFT mint metadata
+    ///
+    pub fn open_position_with_swap<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, OpenPositionWithSwap<'info>>,
+        tick_lower_index: i32,
+        tick_upper_index: i32,
+        tick_array_lower_start_index: i32,
+        tick_array_upper_start_index: i32,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        swap_amount_in: u64,
+        zero_for_one: bool,
+        sqrt_price_limit_x64: u128,
+        liquidity_min: u128,
+        with_metadata: bool,
+    ) -> Result<()> {
+        instructions::open_position_with_swap(
+            ctx,
+            amount_0_max,
+            amount_1_max,
+            swap_amount_in,
+            zero_for_one,
+            sqrt_price_limit_x64,
+            liquidity_min,
+            tick_lower_index,
+            tick_upper_index,
+            tick_array_lower_start_index,
+            tick_array_upper_start_index,
+            with_metadata,
+        )
+    }
+
     /// Close the user's position and NFT account. If the NFT mint belongs to token2022, it will also be closed and the funds returned to the NFT owner.
     ///
     /// # Arguments
@@ -391,6 +746,21 @@ pub mod amm_v3 {
         instructions::close_position(ctx)
     }
 
+    /// Close the user's position and NFT account like `close_position`, or leave the position
+    /// intact if it still has liquidity, fees or rewards and `close_if_empty` is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `close_if_empty` - Skip a position that is not empty instead of failing
+    ///
+    pub fn close_position_v2<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, ClosePositionV2<'info>>,
+        close_if_empty: bool,
+    ) -> Result<()> {
+        instructions::close_position_v2(ctx, close_if_empty)
+    }
+
     /// #[deprecated(note = "Use `increase_liquidity_v2` instead.")]
     /// Increases liquidity with a exist position, with amount paid by `payer`
     ///
@@ -434,6 +804,23 @@ pub mod amm_v3 {
         instructions::increase_liquidity_v2(ctx, liquidity, amount_0_max, amount_1_max, base_flag)
     }
 
+    /// Add the fees owed to a position to its liquidity at the current price, in one
+    /// instruction without withdrawing and depositing them. The fees the liquidity doesn't use
+    /// stay owed
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `liquidity_min` - The minimum liquidity the fees must be worth, which serves as a
+    /// slippage check
+    ///
+    pub fn compound_fees<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CompoundFees<'info>>,
+        liquidity_min: u128,
+    ) -> Result<()> {
+        instructions::compound_fees(ctx, liquidity_min)
+    }
+
     /// #[deprecated(note = "Use `decrease_liquidity_v2` instead.")]
     /// Decreases liquidity with a exist position
     ///
@@ -471,6 +858,197 @@ pub mod amm_v3 {
         instructions::decrease_liquidity_v2(ctx, liquidity, amount_0_min, amount_1_min)
     }
 
+    /// Collect the rewards owed to a position for every initialized reward, without changing
+    /// its liquidity or the ticks. The fees are not collected
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, with the reward vault, recipient token account and
+    /// reward mint of every initialized reward as remaining accounts
+    ///
+    pub fn collect_reward<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CollectReward<'info>>,
+    ) -> Result<()> {
+        instructions::collect_reward(ctx)
+    }
+
+    /// Make every collect and decrease of the position pay its fees and rewards out to token
+    /// accounts owned by `fee_recipient`, e.g. a treasury, whoever signs them. The withdrawn
+    /// liquidity still goes to the token accounts of the signer, and the recipient is ignored
+    /// once the nft is held by another owner.
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `fee_recipient` - The owner of the token accounts receiving the fees and rewards of
+    /// the position
+    ///
+    pub fn set_fee_recipient(ctx: Context<SetFeeRecipient>, fee_recipient: Pubkey) -> Result<()> {
+        instructions::set_fee_recipient(ctx, fee_recipient)
+    }
+
+    /// Clear the fee recipient of the position, the signer chooses the token accounts again
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn clear_fee_recipient(ctx: Context<SetFeeRecipient>) -> Result<()> {
+        instructions::clear_fee_recipient(ctx)
+    }
+
+    /// Rewrite the metadata of the position nft with the current name, symbol and uri, so
+    /// positions minted before a change of the metadata display properly in wallets
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn update_position_metadata(ctx: Context<UpdatePositionMetadata>) -> Result<()> {
+        instructions::update_position_metadata(ctx)
+    }
+
+    /// Returns the token amounts the position is worth at the current price of the pool, and
+    /// the fees and rewards owed to it. Nothing is written, it is meant to be simulated
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn get_position_amounts(ctx: Context<GetPositionAmounts>) -> Result<PositionAmounts> {
+        instructions::get_position_amounts(ctx)
+    }
+
+    /// Lock the position nft in the custody of the program until `unlock_time`. The position
+    /// can't be transferred or have its liquidity decreased until the owner unlocks it
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `unlock_time` - The position can't be unlocked before this timestamp
+    ///
+    pub fn lock_position(ctx: Context<LockPosition>, unlock_time: u64) -> Result<()> {
+        instructions::lock_position(ctx, unlock_time)
+    }
+
+    /// Return the position nft to the owner who locked it, once the unlock time is reached
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn unlock_position(ctx: Context<UnlockPosition>) -> Result<()> {
+        instructions::unlock_position(ctx)
+    }
+
+    /// Collect the fees and rewards of a locked position for the owner who locked it. The nft
+    /// and the liquidity stay locked
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn collect_locked_position<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CollectLockedPosition<'info>>,
+    ) -> Result<()> {
+        instructions::collect_locked_position(ctx)
+    }
+
+    /// Transfer the position nft to the associated token account of a new owner, clearing the
+    /// fee recipient and invalidating the relayed actions signed by the previous owner
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn transfer_position(ctx: Context<TransferPosition>) -> Result<()> {
+        instructions::transfer_position(ctx)
+    }
+
+    /// Move liquidity of a position into a position of the same pair in a pool of another fee
+    /// tier, in one instruction. The target liquidity is the most the withdrawn amounts provide
+    /// at the price of the target pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `liquidity` - The liquidity withdrawn from the source position
+    /// * `amount_0_min` - The minimum amount of token_0 withdrawn
+    /// * `amount_1_min` - The minimum amount of token_1 withdrawn
+    /// * `target_liquidity_min` - The minimum liquidity added to the target position
+    ///
+    pub fn migrate_position<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, MigratePosition<'info>>,
+        liquidity: u128,
+        amount_0_min: u64,
+        amount_1_min: u64,
+        target_liquidity_min: u128,
+    ) -> Result<()> {
+        instructions::migrate_position(
+            ctx,
+            liquidity,
+            amount_0_min,
+            amount_1_min,
+            target_liquidity_min,
+        )
+    }
+
+    /// Collect the fees and rewards of a position to the owner's token accounts, submitted by a
+    /// relayer paying for the transaction. The owner signs the action off chain, the instruction
+    /// before it must be the ed25519 program verifying that signature
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `nonce` - The relay nonce of the position, see `RelayedPositionMessage`
+    /// * `expires_at` - The timestamp after which the signed action can't be submitted
+    ///
+    pub fn relayed_collect<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, RelayedCollect<'info>>,
+        nonce: u64,
+        expires_at: u64,
+    ) -> Result<()> {
+        instructions::relayed_collect(ctx, nonce, expires_at)
+    }
+
+    /// Increases liquidity of a position out of the owner's token accounts, submitted by a
+    /// relayer paying for the transaction and approved as the delegate of the token accounts.
+    /// The owner signs the action off chain, the instruction before it must be the ed25519
+    /// program verifying that signature
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `liquidity` - The desired liquidity to be added, if zero, calculate liquidity base amount_0 or amount_1 according base_flag
+    /// * `amount_0_max` - The max amount of token_0 to spend, which serves as a slippage check
+    /// * `amount_1_max` - The max amount of token_1 to spend, which serves as a slippage check
+    /// * `base_flag` - must be specified if liquidity is zero, true: calculate liquidity base amount_0_max otherwise base amount_1_max
+    /// * `nonce` - The relay nonce of the position, see `RelayedPositionMessage`
+    /// * `expires_at` - The timestamp after which the signed action can't be submitted
+    ///
+    pub fn relayed_increase_liquidity<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, RelayedIncreaseLiquidity<'info>>,
+        liquidity: u128,
+        amount_0_max: u64,
+        amount_1_max: u64,
+        base_flag: Option<bool>,
+        nonce: u64,
+        expires_at: u64,
+    ) -> Result<()> {
+        if liquidity == 0 {
+            assert!(base_flag.is_some());
+        }
+        instructions::relayed_increase_liquidity(
+            ctx,
+            liquidity,
+            amount_0_max,
+            amount_1_max,
+            base_flag,
+            nonce,
+            expires_at,
+        )
+    }
+
     /// #[deprecated(note = "Use `swap_v2` instead.")]
     /// Swaps one token for as much as possible of another token across a single pool
     ///
@@ -482,13 +1060,16 @@ pub mod amm_v3 {
     /// * `sqrt_price_limit` - The Q64.64 sqrt price √P limit. If zero for one, the price cannot
     /// * `is_base_input` - swap base input or swap base output
     ///
+    /// Returns the output amount for base input or the input amount paid for base output, as the
+    /// return data of the instruction
+    ///
     pub fn swap<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, SwapSingle<'info>>,
         amount: u64,
         other_amount_threshold: u64,
         sqrt_price_limit_x64: u128,
         is_base_input: bool,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         instructions::swap(
             ctx,
             amount,
@@ -502,19 +1083,22 @@ pub mod amm_v3 {
     ///
     /// # Arguments
     ///
-    /// * `ctx` - The context of accounts
+    /// * `ctx` - The context of accounts, the pool stats account may be passed among the remaining accounts
     /// * `amount` - Arranged in pairs with other_amount_threshold. (amount_in, amount_out_minimum) or (amount_out, amount_in_maximum)
     /// * `other_amount_threshold` - For slippage check
     /// * `sqrt_price_limit` - The Q64.64 sqrt price √P limit. If zero for one, the price cannot
     /// * `is_base_input` - swap base input or swap base output
     ///
+    /// Returns the output amount for base input or the input amount paid for base output, as the
+    /// return data of the instruction
+    ///
     pub fn swap_v2<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, SwapSingleV2<'info>>,
         amount: u64,
         other_amount_threshold: u64,
         sqrt_price_limit_x64: u128,
         is_base_input: bool,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         instructions::swap_v2(
             ctx,
             amount,
@@ -532,11 +1116,97 @@ pub mod amm_v3 {
     /// * `amount_in` - Token amount to be swapped in
     /// * `amount_out_minimum` - Panic if output amount is below minimum amount. For slippage.
     ///
+    /// Returns the output amount of the last pool as the return data of the instruction
+    ///
     pub fn swap_router_base_in<'a, 'b, 'c: 'info, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, SwapRouterBaseIn<'info>>,
         amount_in: u64,
         amount_out_minimum: u64,
-    ) -> Result<()> {
+    ) -> Result<u64> {
         instructions::swap_router_base_in(ctx, amount_in, amount_out_minimum)
     }
+
+    /// Resume a route split across transactions from the intermediate token balance, base input
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `input_balance_before` - Balance of the input token account before the route started, only the amount above it is swapped
+    /// * `amount_in_minimum` - Panic if the intermediate amount is below minimum amount. For slippage of the previous legs.
+    /// * `amount_out_minimum` - Panic if output amount is below minimum amount. For slippage.
+    ///
+    /// Returns the output amount of the last pool as the return data of the instruction
+    ///
+    pub fn swap_router_resume_base_in<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, SwapRouterBaseIn<'info>>,
+        input_balance_before: u64,
+        amount_in_minimum: u64,
+        amount_out_minimum: u64,
+    ) -> Result<u64> {
+        instructions::swap_router_resume_base_in(
+            ctx,
+            input_balance_before,
+            amount_in_minimum,
+            amount_out_minimum,
+        )
+    }
+
+    /// Swap token for an exact amount of another token across the path provided, base output
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    /// * `amount_out` - Token amount to be received from the last pool
+    /// * `amount_in_maximum` - Panic if input amount is above maximum amount. For slippage.
+    ///
+    pub fn swap_router_base_out<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, SwapRouterBaseOut<'info>>,
+        amount_out: u64,
+        amount_in_maximum: u64,
+    ) -> Result<()> {
+        instructions::swap_router_base_out(ctx, amount_out, amount_in_maximum)
+    }
+
+    /// Lend tokens out of the pool vaults within the instruction, the callback program must repay
+    /// them plus the fee at the trade fee rate, which is shared like a swap fee. Disabled until
+    /// the admin sets the `Flash` status bit of the pool
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts, the remaining accounts are passed to the callback
+    /// * `amount_0` - The amount of token_0 to borrow
+    /// * `amount_1` - The amount of token_1 to borrow
+    /// * `data` - Passed through to the callback
+    ///
+    pub fn flash<'a, 'b, 'c: 'info, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, Flash<'info>>,
+        amount_0: u64,
+        amount_1: u64,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        instructions::flash(ctx, amount_0, amount_1, data)
+    }
+
+    /// Create the account tracking the swap volume and fees of a pool over the last 24 hours,
+    /// can be called for everyone. The swaps record into it when it is passed among their
+    /// remaining accounts
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn create_pool_stats(ctx: Context<CreatePoolStats>) -> Result<()> {
+        instructions::create_pool_stats(ctx)
+    }
+
+    /// Drop the expired buckets of a pool stats account and recompute its 24 hour totals, can be
+    /// called for everyone
+    ///
+    /// # Arguments
+    ///
+    /// * `ctx` - The context of accounts
+    ///
+    pub fn roll_stats(ctx: Context<RollStats>) -> Result<()> {
+        instructions::roll_stats(ctx)
+    }
 }
@@ -5,6 +5,7 @@ use super::full_math::MulDiv;
 use super::tick_math;
 use super::unsafe_math::UnsafeMathTrait;
 use crate::error::ErrorCode;
+use crate::states::FEE_RATE_DENOMINATOR_VALUE;
 use anchor_lang::prelude::*;
 
 /// Add a signed liquidity delta to liquidity and revert if it overflows or underflows
@@ -15,14 +16,13 @@ use anchor_lang::prelude::*;
 /// * `y` - The delta (ΔL) by which liquidity should be changed
 ///
 pub fn add_delta(x: u128, y: i128) -> Result<u128> {
-    let z: u128;
-    if y < 0 {
-        z = x - u128::try_from(-y).unwrap();
-        require_gt!(x, z, ErrorCode::LiquiditySubValueErr);
+    let z = if y < 0 {
+        x.checked_sub(y.unsigned_abs())
+            .ok_or(ErrorCode::LiquiditySubValueErr)?
     } else {
-        z = x + u128::try_from(y).unwrap();
-        require_gte!(z, x, ErrorCode::LiquidityAddValueErr);
-    }
+        x.checked_add(y.unsigned_abs())
+            .ok_or(ErrorCode::LiquidityAddValueErr)?
+    };
 
     Ok(z)
 }
@@ -33,7 +33,7 @@ pub fn get_liquidity_from_amount_0(
     mut sqrt_ratio_a_x64: u128,
     mut sqrt_ratio_b_x64: u128,
     amount_0: u64,
-) -> u128 {
+) -> Result<u128> {
     // sqrt_ratio_a_x64 should hold the smaller value
     if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
         std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
@@ -43,15 +43,15 @@ pub fn get_liquidity_from_amount_0(
             U128::from(sqrt_ratio_b_x64),
             U128::from(fixed_point_64::Q64),
         )
-        .unwrap();
+        .ok_or(ErrorCode::MathOverflow)?;
 
-    U128::from(amount_0)
+    Ok(U128::from(amount_0)
         .mul_div_floor(
             intermediate,
             U128::from(sqrt_ratio_b_x64 - sqrt_ratio_a_x64),
         )
-        .unwrap()
-        .as_u128()
+        .ok_or(ErrorCode::MathOverflow)?
+        .as_u128())
 }
 
 /// Computes the amount of liquidity received for a given amount of token_1 and price range
@@ -60,19 +60,19 @@ pub fn get_liquidity_from_amount_1(
     mut sqrt_ratio_a_x64: u128,
     mut sqrt_ratio_b_x64: u128,
     amount_1: u64,
-) -> u128 {
+) -> Result<u128> {
     // sqrt_ratio_a_x64 should hold the smaller value
     if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
         std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
     };
 
-    U128::from(amount_1)
+    Ok(U128::from(amount_1)
         .mul_div_floor(
             U128::from(fixed_point_64::Q64),
             U128::from(sqrt_ratio_b_x64 - sqrt_ratio_a_x64),
         )
-        .unwrap()
-        .as_u128()
+        .ok_or(ErrorCode::MathOverflow)?
+        .as_u128())
 }
 
 /// Computes the maximum amount of liquidity received for a given amount of token_0, token_1, the current
@@ -83,7 +83,7 @@ pub fn get_liquidity_from_amounts(
     mut sqrt_ratio_b_x64: u128,
     amount_0: u64,
     amount_1: u64,
-) -> u128 {
+) -> Result<u128> {
     // sqrt_ratio_a_x64 should hold the smaller value
     if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
         std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
@@ -95,16 +95,34 @@ pub fn get_liquidity_from_amounts(
     } else if sqrt_ratio_x64 < sqrt_ratio_b_x64 {
         // If P_lower < P < P_upper, active liquidity is the minimum of the liquidity provided
         // by token_0 and token_1
-        u128::min(
-            get_liquidity_from_amount_0(sqrt_ratio_x64, sqrt_ratio_b_x64, amount_0),
-            get_liquidity_from_amount_1(sqrt_ratio_a_x64, sqrt_ratio_x64, amount_1),
-        )
+        Ok(u128::min(
+            get_liquidity_from_amount_0(sqrt_ratio_x64, sqrt_ratio_b_x64, amount_0)?,
+            get_liquidity_from_amount_1(sqrt_ratio_a_x64, sqrt_ratio_x64, amount_1)?,
+        ))
     } else {
         // If P ≥ P_upper, only token_1 liquidity is active
         get_liquidity_from_amount_1(sqrt_ratio_a_x64, sqrt_ratio_b_x64, amount_1)
     }
 }
 
+/// The maximum liquidity the amounts provide over [tick_lower_index, tick_upper_index] at the
+/// pool price
+pub fn get_liquidity_from_amounts_at_ticks(
+    sqrt_price_x64: u128,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    amount_0: u64,
+    amount_1: u64,
+) -> Result<u128> {
+    get_liquidity_from_amounts(
+        sqrt_price_x64,
+        tick_math::get_sqrt_price_at_tick(tick_lower_index)?,
+        tick_math::get_sqrt_price_at_tick(tick_upper_index)?,
+        amount_0,
+        amount_1,
+    )
+}
+
 /// Computes the maximum amount of liquidity received for a given amount of token_0, token_1, the current
 /// pool prices and the prices at the tick boundaries
 pub fn get_liquidity_from_single_amount_0(
@@ -112,7 +130,7 @@ pub fn get_liquidity_from_single_amount_0(
     mut sqrt_ratio_a_x64: u128,
     mut sqrt_ratio_b_x64: u128,
     amount_0: u64,
-) -> u128 {
+) -> Result<u128> {
     // sqrt_ratio_a_x64 should hold the smaller value
     if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
         std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
@@ -127,7 +145,7 @@ pub fn get_liquidity_from_single_amount_0(
         get_liquidity_from_amount_0(sqrt_ratio_x64, sqrt_ratio_b_x64, amount_0)
     } else {
         // If P ≥ P_upper, only token_1 liquidity is active
-        0
+        Ok(0)
     }
 }
 
@@ -138,7 +156,7 @@ pub fn get_liquidity_from_single_amount_1(
     mut sqrt_ratio_a_x64: u128,
     mut sqrt_ratio_b_x64: u128,
     amount_1: u64,
-) -> u128 {
+) -> Result<u128> {
     // sqrt_ratio_a_x64 should hold the smaller value
     if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
         std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
@@ -146,7 +164,7 @@ pub fn get_liquidity_from_single_amount_1(
 
     if sqrt_ratio_x64 <= sqrt_ratio_a_x64 {
         // If P ≤ P_lower, only token_0 liquidity is active
-        0
+        Ok(0)
     } else if sqrt_ratio_x64 < sqrt_ratio_b_x64 {
         // If P_lower < P < P_upper, active liquidity is the minimum of the liquidity provided
         // by token_0 and token_1
@@ -183,13 +201,13 @@ pub fn get_delta_amount_0_unsigned(
         U256::div_rounding_up(
             numerator_1
                 .mul_div_ceil(numerator_2, U256::from(sqrt_ratio_b_x64))
-                .unwrap(),
+                .ok_or(ErrorCode::MathOverflow)?,
             U256::from(sqrt_ratio_a_x64),
         )
     } else {
         numerator_1
             .mul_div_floor(numerator_2, U256::from(sqrt_ratio_b_x64))
-            .unwrap()
+            .ok_or(ErrorCode::MathOverflow)?
             / U256::from(sqrt_ratio_a_x64)
     };
     if result > U256::from(u64::MAX) {
@@ -222,7 +240,7 @@ pub fn get_delta_amount_1_unsigned(
             U256::from(fixed_point_64::Q64),
         )
     }
-    .unwrap();
+    .ok_or(ErrorCode::MathOverflow)?;
     if result > U256::from(u64::MAX) {
         return Err(ErrorCode::MaxTokenOverflow.into());
     }
@@ -239,14 +257,14 @@ pub fn get_delta_amount_0_signed(
         get_delta_amount_0_unsigned(
             sqrt_ratio_a_x64,
             sqrt_ratio_b_x64,
-            u128::try_from(-liquidity).unwrap(),
+            liquidity.unsigned_abs(),
             false,
         )
     } else {
         get_delta_amount_0_unsigned(
             sqrt_ratio_a_x64,
             sqrt_ratio_b_x64,
-            u128::try_from(liquidity).unwrap(),
+            liquidity.unsigned_abs(),
             true,
         )
     }
@@ -262,14 +280,14 @@ pub fn get_delta_amount_1_signed(
         get_delta_amount_1_unsigned(
             sqrt_ratio_a_x64,
             sqrt_ratio_b_x64,
-            u128::try_from(-liquidity).unwrap(),
+            liquidity.unsigned_abs(),
             false,
         )
     } else {
         get_delta_amount_1_unsigned(
             sqrt_ratio_a_x64,
             sqrt_ratio_b_x64,
-            u128::try_from(liquidity).unwrap(),
+            liquidity.unsigned_abs(),
             true,
         )
     }
@@ -289,28 +307,258 @@ pub fn get_delta_amounts_signed(
             tick_math::get_sqrt_price_at_tick(tick_lower)?,
             tick_math::get_sqrt_price_at_tick(tick_upper)?,
             liquidity_delta,
-        )
-        .unwrap();
+        )?;
     } else if tick_current < tick_upper {
         amount_0 = get_delta_amount_0_signed(
             sqrt_price_x64_current,
             tick_math::get_sqrt_price_at_tick(tick_upper)?,
             liquidity_delta,
-        )
-        .unwrap();
+        )?;
         amount_1 = get_delta_amount_1_signed(
             tick_math::get_sqrt_price_at_tick(tick_lower)?,
             sqrt_price_x64_current,
             liquidity_delta,
-        )
-        .unwrap();
+        )?;
     } else {
         amount_1 = get_delta_amount_1_signed(
             tick_math::get_sqrt_price_at_tick(tick_lower)?,
             tick_math::get_sqrt_price_at_tick(tick_upper)?,
             liquidity_delta,
+        )?;
+    }
+    Ok((amount_0, amount_1))
+}
+
+/// Computes the liquidity received for a single token amount and the prices at the tick boundaries,
+/// as calculated by the program when liquidity is added from `amount_0_max` or `amount_1_max`
+///
+/// # Arguments
+///
+/// * `sqrt_price_x64` - The current sqrt price of the pool
+/// * `tick_lower` - The lower tick of the position
+/// * `tick_upper` - The upper tick of the position
+/// * `amount` - The amount of base token, excluding transfer fee
+/// * `base_0` - Whether the base token is token_0
+///
+pub fn get_liquidity_from_single_amount(
+    sqrt_price_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    amount: u64,
+    base_0: bool,
+) -> Result<u128> {
+    let sqrt_price_lower_x64 = tick_math::get_sqrt_price_at_tick(tick_lower)?;
+    let sqrt_price_upper_x64 = tick_math::get_sqrt_price_at_tick(tick_upper)?;
+    if base_0 {
+        get_liquidity_from_single_amount_0(
+            sqrt_price_x64,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            amount,
+        )
+    } else {
+        get_liquidity_from_single_amount_1(
+            sqrt_price_x64,
+            sqrt_price_lower_x64,
+            sqrt_price_upper_x64,
+            amount,
+        )
+    }
+}
+
+/// Computes the liquidity and the token amounts charged by the program when adding liquidity to a
+/// position from a single token amount, clients should quote with it to match the on-chain result
+///
+/// # Arguments
+///
+/// * `tick_current` - The current tick of the pool
+/// * `sqrt_price_x64` - The current sqrt price of the pool
+/// * `tick_lower` - The lower tick of the position
+/// * `tick_upper` - The upper tick of the position
+/// * `amount` - The amount of base token, excluding transfer fee
+/// * `base_0` - Whether the base token is token_0
+///
+pub fn get_liquidity_and_amounts_from_single_amount(
+    tick_current: i32,
+    sqrt_price_x64: u128,
+    tick_lower: i32,
+    tick_upper: i32,
+    amount: u64,
+    base_0: bool,
+) -> Result<(u128, u64, u64)> {
+    let liquidity =
+        get_liquidity_from_single_amount(sqrt_price_x64, tick_lower, tick_upper, amount, base_0)?;
+    let (amount_0, amount_1) = get_delta_amounts_signed(
+        tick_current,
+        sqrt_price_x64,
+        tick_lower,
+        tick_upper,
+        i128::try_from(liquidity).map_err(|_| ErrorCode::MathOverflow)?,
+    )?;
+    Ok((liquidity, amount_0, amount_1))
+}
+
+/// Maximum amount accepted by the slippage check of the program for a quoted amount, rounded up
+///
+/// # Arguments
+///
+/// * `amount` - The quoted amount
+/// * `tolerance_rate` - The tolerated price move, denominated in hundredths of a bip (10^-6)
+///
+pub fn amount_with_tolerance(amount: u64, tolerance_rate: u32) -> u64 {
+    let amount_max = U128::from(amount)
+        .mul_div_ceil(
+            U128::from(FEE_RATE_DENOMINATOR_VALUE + tolerance_rate),
+            U128::from(FEE_RATE_DENOMINATOR_VALUE),
         )
         .unwrap();
+    if amount_max > U128::from(u64::MAX) {
+        u64::MAX
+    } else {
+        amount_max.as_u64()
+    }
+}
+
+#[cfg(test)]
+mod liquidity_math_test {
+    use super::*;
+
+    #[test]
+    fn add_delta_overflow_test() {
+        assert_eq!(add_delta(10, -4).unwrap(), 6);
+        assert_eq!(add_delta(10, 4).unwrap(), 14);
+        assert_eq!(
+            add_delta(10, -11).unwrap_err(),
+            ErrorCode::LiquiditySubValueErr.into()
+        );
+        assert_eq!(
+            add_delta(u128::MAX, 1).unwrap_err(),
+            ErrorCode::LiquidityAddValueErr.into()
+        );
+        // the negation of i128::MIN doesn't fit an i128
+        assert_eq!(
+            add_delta(u128::MAX, i128::MIN).unwrap(),
+            u128::MAX - (1 << 127)
+        );
+    }
+
+    #[test]
+    fn get_liquidity_overflow_test() {
+        // a range next to the max price is worth more liquidity than a u128 holds
+        let sqrt_price_upper_x64 = tick_math::MAX_SQRT_PRICE_X64;
+        let sqrt_price_lower_x64 = sqrt_price_upper_x64 - 1;
+        assert_eq!(
+            get_liquidity_from_amount_0(sqrt_price_lower_x64, sqrt_price_upper_x64, u64::MAX)
+                .unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        assert_eq!(
+            get_liquidity_from_amounts(
+                tick_math::MIN_SQRT_PRICE_X64,
+                sqrt_price_lower_x64,
+                sqrt_price_upper_x64,
+                u64::MAX,
+                0
+            )
+            .unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+        // no token_0 is needed below the price
+        assert_eq!(
+            get_liquidity_from_single_amount_0(
+                sqrt_price_upper_x64,
+                sqrt_price_lower_x64,
+                sqrt_price_upper_x64,
+                u64::MAX
+            )
+            .unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn get_delta_amounts_overflow_test() {
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(0).unwrap();
+        // the liquidity is worth more than a u64 of either token, its negation doesn't fit an
+        // i128
+        for liquidity_delta in [i128::MIN, i128::MAX] {
+            assert_eq!(
+                get_delta_amounts_signed(0, sqrt_price_x64, -600, 600, liquidity_delta)
+                    .unwrap_err(),
+                ErrorCode::MaxTokenOverflow.into()
+            );
+        }
+    }
+
+    #[test]
+    fn liquidity_and_amounts_from_single_amount_test() {
+        let tick_current = 100;
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick_current).unwrap();
+        // in range, the base amount is fully used up to rounding
+        let (liquidity, amount_0, amount_1) = get_liquidity_and_amounts_from_single_amount(
+            tick_current,
+            sqrt_price_x64,
+            -600,
+            600,
+            1_000_000,
+            true,
+        )
+        .unwrap();
+        assert!(liquidity > 0);
+        assert!(amount_0 <= 1_000_000 && amount_0 >= 999_999);
+        assert!(amount_1 > 0);
+
+        // price above the range, token_0 alone adds no liquidity
+        let (liquidity, amount_0, amount_1) = get_liquidity_and_amounts_from_single_amount(
+            tick_current,
+            sqrt_price_x64,
+            -600,
+            0,
+            1_000_000,
+            true,
+        )
+        .unwrap();
+        assert_eq!((liquidity, amount_0, amount_1), (0, 0, 0));
+    }
+
+    #[test]
+    fn amount_with_tolerance_test() {
+        assert_eq!(amount_with_tolerance(1_000_000, 0), 1_000_000);
+        assert_eq!(amount_with_tolerance(1_000_000, 10_000), 1_010_000);
+        // rounded up
+        assert_eq!(amount_with_tolerance(1, 10_000), 2);
+        // large amounts keep full precision
+        assert_eq!(amount_with_tolerance(u64::MAX / 2, 0), u64::MAX / 2);
+        assert_eq!(amount_with_tolerance(u64::MAX, 10_000), u64::MAX);
+    }
+
+    #[test]
+    fn get_liquidity_from_amounts_at_ticks_test() {
+        let liquidity = 1_000_000_000_000u128;
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(100).unwrap();
+        let (amount_0, amount_1) =
+            get_delta_amounts_signed(100, sqrt_price_x64, -600, 600, -(liquidity as i128)).unwrap();
+
+        // the same range at the same price takes back at most the liquidity withdrawn
+        let same_range =
+            get_liquidity_from_amounts_at_ticks(sqrt_price_x64, -600, 600, amount_0, amount_1)
+                .unwrap();
+        assert!(same_range <= liquidity);
+        assert!(same_range >= liquidity - liquidity / 1_000_000);
+
+        // a wider range of another fee tier provides less liquidity from the same amounts
+        let wider_range =
+            get_liquidity_from_amounts_at_ticks(sqrt_price_x64, -1200, 1200, amount_0, amount_1)
+                .unwrap();
+        assert!(wider_range < same_range);
+
+        // a range above the price only takes token_0
+        let above_price =
+            get_liquidity_from_amounts_at_ticks(sqrt_price_x64, 200, 800, amount_0, 0).unwrap();
+        assert_eq!(
+            above_price,
+            get_liquidity_from_amounts_at_ticks(sqrt_price_x64, 200, 800, amount_0, amount_1)
+                .unwrap()
+        );
     }
-    Ok((amount_0, amount_1))
 }
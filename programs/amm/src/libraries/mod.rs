@@ -4,17 +4,22 @@ pub mod full_math;
 pub mod liquidity_math;
 pub mod sqrt_price_math;
 pub mod swap_math;
+pub mod swap_quote;
 
 pub mod tick_array_bit_map;
 pub mod tick_math;
 pub mod unsafe_math;
 
+#[cfg(test)]
+mod reference_math_test;
+
 pub use big_num::*;
 pub use fixed_point_64::*;
 pub use full_math::*;
 pub use liquidity_math::*;
 pub use sqrt_price_math::*;
 pub use swap_math::*;
+pub use swap_quote::*;
 
 pub use tick_array_bit_map::*;
 pub use tick_math::*;
@@ -0,0 +1,563 @@
+//! Differential tests of the math libraries against a port of the Uniswap V3 reference math
+//! (TickMath, SqrtPriceMath and SwapMath). The reference is computed in `U512` so none of its
+//! intermediate values overflow, with prices in Q64.64 instead of Q64.96.
+
+use super::big_num::U512;
+use super::{liquidity_math, sqrt_price_math, swap_math, tick_math};
+use proptest::prelude::*;
+
+mod reference {
+    use super::U512;
+    use crate::libraries::tick_math::{MAX_TICK, MIN_TICK};
+
+    const RESOLUTION: usize = 64;
+    const FEE_DENOMINATOR: u64 = 1_000_000;
+
+    /// `2^128 / 1.0001^(2^(i - 1))` for bit `i` of the absolute tick, the constants of TickMath
+    const TICK_FACTORS_X128: [u128; 19] = [
+        0xfffcb933bd6fad37aa2d162d1a594001,
+        0xfff97272373d413259a46990580e213a,
+        0xfff2e50f5f656932ef12357cf3c7fdcc,
+        0xffe5caca7e10e4e61c3624eaa0941cd0,
+        0xffcb9843d60f6159c9db58835c926644,
+        0xff973b41fa98c081472e6896dfb254c0,
+        0xff2ea16466c96a3843ec78b326b52861,
+        0xfe5dee046a99a2a811c461f1969c3053,
+        0xfcbe86c7900a88aedcffc83b479aa3a4,
+        0xf987a7253ac413176f2b074cf7815e54,
+        0xf3392b0822b70005940c7a398e4b70f3,
+        0xe7159475a2c29b7443b29c7fa6e889d9,
+        0xd097f3bdfd2022b8845ad8f792aa5825,
+        0xa9f746462d870fdf8a65dc1f90e061e5,
+        0x70d869a156d2a1b890bb3df62baf32f7,
+        0x31be135f97d08fd981231505542fcfa6,
+        0x9aa508b5b7a84e1c677de54f3e99bc9,
+        0x5d6af8dedb81196699c329225ee604,
+        0x2216e584f5fa1ea926041bedfe98,
+    ];
+
+    fn q64() -> U512 {
+        U512::one() << RESOLUTION
+    }
+
+    fn div_rounding_up(x: U512, y: U512) -> U512 {
+        let quotient = x / y;
+        if x % y == U512::zero() {
+            quotient
+        } else {
+            quotient + U512::one()
+        }
+    }
+
+    fn mul_div(a: U512, b: U512, denominator: U512) -> U512 {
+        a * b / denominator
+    }
+
+    fn mul_div_rounding_up(a: U512, b: U512, denominator: U512) -> U512 {
+        div_rounding_up(a * b, denominator)
+    }
+
+    /// TickMath.getSqrtRatioAtTick, the Q128.128 ratio is rounded up to Q64.64
+    pub fn get_sqrt_ratio_at_tick(tick: i32) -> u128 {
+        let abs_tick = tick.unsigned_abs();
+        let mut ratio = if abs_tick & 0x1 != 0 {
+            U512::from(TICK_FACTORS_X128[0])
+        } else {
+            U512::one() << 128
+        };
+        for (i, factor) in TICK_FACTORS_X128.iter().enumerate().skip(1) {
+            if abs_tick & (1 << i) != 0 {
+                ratio = (ratio * U512::from(*factor)) >> 128;
+            }
+        }
+        if tick > 0 {
+            ratio = ((U512::one() << 256) - U512::one()) / ratio;
+        }
+        div_rounding_up(ratio, q64()).as_u128()
+    }
+
+    /// The greatest tick whose sqrt ratio is at most `sqrt_ratio_x64`, found by bisection instead
+    /// of TickMath's log2 approximation
+    pub fn get_tick_at_sqrt_ratio(sqrt_ratio_x64: u128) -> i32 {
+        let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            if get_sqrt_ratio_at_tick(mid) <= sqrt_ratio_x64 {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        low
+    }
+
+    /// SqrtPriceMath.getAmount0Delta
+    pub fn get_amount_0_delta(
+        mut sqrt_ratio_a_x64: u128,
+        mut sqrt_ratio_b_x64: u128,
+        liquidity: u128,
+        round_up: bool,
+    ) -> U512 {
+        if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+            std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+        }
+        let numerator_1 = U512::from(liquidity) << RESOLUTION;
+        let numerator_2 = U512::from(sqrt_ratio_b_x64 - sqrt_ratio_a_x64);
+        if round_up {
+            div_rounding_up(
+                mul_div_rounding_up(numerator_1, numerator_2, U512::from(sqrt_ratio_b_x64)),
+                U512::from(sqrt_ratio_a_x64),
+            )
+        } else {
+            mul_div(numerator_1, numerator_2, U512::from(sqrt_ratio_b_x64))
+                / U512::from(sqrt_ratio_a_x64)
+        }
+    }
+
+    /// SqrtPriceMath.getAmount1Delta
+    pub fn get_amount_1_delta(
+        mut sqrt_ratio_a_x64: u128,
+        mut sqrt_ratio_b_x64: u128,
+        liquidity: u128,
+        round_up: bool,
+    ) -> U512 {
+        if sqrt_ratio_a_x64 > sqrt_ratio_b_x64 {
+            std::mem::swap(&mut sqrt_ratio_a_x64, &mut sqrt_ratio_b_x64);
+        }
+        let delta = U512::from(sqrt_ratio_b_x64 - sqrt_ratio_a_x64);
+        if round_up {
+            mul_div_rounding_up(U512::from(liquidity), delta, q64())
+        } else {
+            mul_div(U512::from(liquidity), delta, q64())
+        }
+    }
+
+    /// SqrtPriceMath.getNextSqrtPriceFromAmount0RoundingUp. The product can't overflow in
+    /// `U512`, so the `L / (L / √P + Δx)` fallback of the reference is never taken. `None` if
+    /// removing the amount takes more token_0 than the liquidity holds.
+    pub fn get_next_sqrt_price_from_amount_0_rounding_up(
+        sqrt_price_x64: u128,
+        liquidity: u128,
+        amount: u64,
+        add: bool,
+    ) -> Option<U512> {
+        if amount == 0 {
+            return Some(U512::from(sqrt_price_x64));
+        }
+        let numerator_1 = U512::from(liquidity) << RESOLUTION;
+        let product = U512::from(amount) * U512::from(sqrt_price_x64);
+        let denominator = if add {
+            numerator_1 + product
+        } else {
+            if numerator_1 <= product {
+                return None;
+            }
+            numerator_1 - product
+        };
+        Some(mul_div_rounding_up(
+            numerator_1,
+            U512::from(sqrt_price_x64),
+            denominator,
+        ))
+    }
+
+    /// SqrtPriceMath.getNextSqrtPriceFromAmount1RoundingDown, `None` if removing the amount moves
+    /// the price below zero
+    pub fn get_next_sqrt_price_from_amount_1_rounding_down(
+        sqrt_price_x64: u128,
+        liquidity: u128,
+        amount: u64,
+        add: bool,
+    ) -> Option<U512> {
+        let amount_x64 = U512::from(amount) << RESOLUTION;
+        if add {
+            Some(U512::from(sqrt_price_x64) + amount_x64 / U512::from(liquidity))
+        } else {
+            let quotient = div_rounding_up(amount_x64, U512::from(liquidity));
+            if U512::from(sqrt_price_x64) <= quotient {
+                return None;
+            }
+            Some(U512::from(sqrt_price_x64) - quotient)
+        }
+    }
+
+    /// SqrtPriceMath.getNextSqrtPriceFromInput
+    pub fn get_next_sqrt_price_from_input(
+        sqrt_price_x64: u128,
+        liquidity: u128,
+        amount_in: u64,
+        zero_for_one: bool,
+    ) -> Option<U512> {
+        if zero_for_one {
+            get_next_sqrt_price_from_amount_0_rounding_up(
+                sqrt_price_x64,
+                liquidity,
+                amount_in,
+                true,
+            )
+        } else {
+            get_next_sqrt_price_from_amount_1_rounding_down(
+                sqrt_price_x64,
+                liquidity,
+                amount_in,
+                true,
+            )
+        }
+    }
+
+    /// SqrtPriceMath.getNextSqrtPriceFromOutput
+    pub fn get_next_sqrt_price_from_output(
+        sqrt_price_x64: u128,
+        liquidity: u128,
+        amount_out: u64,
+        zero_for_one: bool,
+    ) -> Option<U512> {
+        if zero_for_one {
+            get_next_sqrt_price_from_amount_1_rounding_down(
+                sqrt_price_x64,
+                liquidity,
+                amount_out,
+                false,
+            )
+        } else {
+            get_next_sqrt_price_from_amount_0_rounding_up(
+                sqrt_price_x64,
+                liquidity,
+                amount_out,
+                false,
+            )
+        }
+    }
+
+    /// The outcome of SwapMath.computeSwapStep, amounts are left unbounded
+    #[derive(Debug)]
+    pub struct SwapStep {
+        pub sqrt_price_next_x64: U512,
+        pub amount_in: U512,
+        pub amount_out: U512,
+        pub fee_amount: U512,
+    }
+
+    /// SwapMath.computeSwapStep. The sign of the reference's `amountRemaining` is passed as
+    /// `is_base_input`. `None` if the next price can't be computed.
+    pub fn compute_swap_step(
+        sqrt_price_current_x64: u128,
+        sqrt_price_target_x64: u128,
+        liquidity: u128,
+        amount_remaining: u64,
+        fee_pips: u32,
+        is_base_input: bool,
+    ) -> Option<SwapStep> {
+        let zero_for_one = sqrt_price_current_x64 >= sqrt_price_target_x64;
+        let fee_pips = U512::from(fee_pips);
+        let mut amount_in = U512::zero();
+        let mut amount_out = U512::zero();
+        let sqrt_price_next_x64;
+        if is_base_input {
+            let amount_remaining_less_fee = mul_div(
+                U512::from(amount_remaining),
+                U512::from(FEE_DENOMINATOR) - fee_pips,
+                U512::from(FEE_DENOMINATOR),
+            );
+            amount_in = if zero_for_one {
+                get_amount_0_delta(
+                    sqrt_price_target_x64,
+                    sqrt_price_current_x64,
+                    liquidity,
+                    true,
+                )
+            } else {
+                get_amount_1_delta(
+                    sqrt_price_current_x64,
+                    sqrt_price_target_x64,
+                    liquidity,
+                    true,
+                )
+            };
+            sqrt_price_next_x64 = if amount_remaining_less_fee >= amount_in {
+                U512::from(sqrt_price_target_x64)
+            } else {
+                get_next_sqrt_price_from_input(
+                    sqrt_price_current_x64,
+                    liquidity,
+                    amount_remaining_less_fee.as_u64(),
+                    zero_for_one,
+                )?
+            };
+        } else {
+            amount_out = if zero_for_one {
+                get_amount_1_delta(
+                    sqrt_price_target_x64,
+                    sqrt_price_current_x64,
+                    liquidity,
+                    false,
+                )
+            } else {
+                get_amount_0_delta(
+                    sqrt_price_current_x64,
+                    sqrt_price_target_x64,
+                    liquidity,
+                    false,
+                )
+            };
+            sqrt_price_next_x64 = if U512::from(amount_remaining) >= amount_out {
+                U512::from(sqrt_price_target_x64)
+            } else {
+                get_next_sqrt_price_from_output(
+                    sqrt_price_current_x64,
+                    liquidity,
+                    amount_remaining,
+                    zero_for_one,
+                )?
+            };
+        }
+        if sqrt_price_next_x64 > U512::from(u128::MAX) {
+            return None;
+        }
+        let next = sqrt_price_next_x64.as_u128();
+
+        let max = U512::from(sqrt_price_target_x64) == sqrt_price_next_x64;
+        if zero_for_one {
+            if !(max && is_base_input) {
+                amount_in = get_amount_0_delta(next, sqrt_price_current_x64, liquidity, true);
+            }
+            if !(max && !is_base_input) {
+                amount_out = get_amount_1_delta(next, sqrt_price_current_x64, liquidity, false);
+            }
+        } else {
+            if !(max && is_base_input) {
+                amount_in = get_amount_1_delta(sqrt_price_current_x64, next, liquidity, true);
+            }
+            if !(max && !is_base_input) {
+                amount_out = get_amount_0_delta(sqrt_price_current_x64, next, liquidity, false);
+            }
+        }
+        if !is_base_input && amount_out > U512::from(amount_remaining) {
+            amount_out = U512::from(amount_remaining);
+        }
+        let fee_amount = if is_base_input && next != sqrt_price_target_x64 {
+            U512::from(amount_remaining) - amount_in
+        } else {
+            mul_div_rounding_up(amount_in, fee_pips, U512::from(FEE_DENOMINATOR) - fee_pips)
+        };
+        Some(SwapStep {
+            sqrt_price_next_x64,
+            amount_in,
+            amount_out,
+            fee_amount,
+        })
+    }
+}
+
+/// The reference value as a u64, `None` if it doesn't fit
+fn to_u64(value: U512) -> Option<u64> {
+    if value > U512::from(u64::MAX) {
+        None
+    } else {
+        Some(value.as_u64())
+    }
+}
+
+/// The reference price as a u128 if it's within the prices the pool can reach
+fn to_sqrt_price(value: U512) -> Option<u128> {
+    if value < U512::from(tick_math::MIN_SQRT_PRICE_X64)
+        || value > U512::from(tick_math::MAX_SQRT_PRICE_X64)
+    {
+        None
+    } else {
+        Some(value.as_u128())
+    }
+}
+
+#[test]
+fn get_sqrt_price_at_tick_bounds_test() {
+    // the program's Q64.64 factors are rounded, the price at the bounds is still within one unit
+    // of the reference
+    assert_eq!(
+        reference::get_sqrt_ratio_at_tick(tick_math::MIN_TICK),
+        tick_math::MIN_SQRT_PRICE_X64 + 1
+    );
+    assert_eq!(reference::get_sqrt_ratio_at_tick(0), 1u128 << 64);
+    assert_eq!(tick_math::get_sqrt_price_at_tick(0).unwrap(), 1u128 << 64);
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(4096))]
+
+    #[test]
+    fn get_sqrt_price_at_tick_differential_test(
+        tick in tick_math::MIN_TICK..=tick_math::MAX_TICK,
+    ) {
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick).unwrap();
+        let expected = reference::get_sqrt_ratio_at_tick(tick);
+        // the Q64.64 factors carry 53 significant bits and every step truncates, the error stays
+        // below one part in 2^31 of the price
+        let diff = sqrt_price_x64.abs_diff(expected);
+        prop_assert!(
+            diff <= (expected >> 31).max(1),
+            "tick:{}, sqrt_price_x64:{}, expected:{}",
+            tick,
+            sqrt_price_x64,
+            expected
+        );
+    }
+
+    #[test]
+    fn get_tick_at_sqrt_price_differential_test(
+        sqrt_price_x64 in tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64,
+    ) {
+        let tick = tick_math::get_tick_at_sqrt_price(sqrt_price_x64).unwrap();
+        // consistent with the program's own prices
+        prop_assert!(tick_math::get_sqrt_price_at_tick(tick).unwrap() <= sqrt_price_x64);
+        if tick < tick_math::MAX_TICK {
+            prop_assert!(tick_math::get_sqrt_price_at_tick(tick + 1).unwrap() > sqrt_price_x64);
+        }
+        // and off the reference by at most a tick where the prices of a tick differ
+        let expected = reference::get_tick_at_sqrt_ratio(sqrt_price_x64);
+        prop_assert!(
+            (tick - expected).abs() <= 1,
+            "sqrt_price_x64:{}, tick:{}, expected:{}",
+            sqrt_price_x64,
+            tick,
+            expected
+        );
+    }
+
+    #[test]
+    fn get_delta_amounts_differential_test(
+        sqrt_price_a_x64 in tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64,
+        sqrt_price_b_x64 in tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64,
+        liquidity in 1u128..(1u128 << 100),
+        round_up: bool,
+    ) {
+        let amount_0 = liquidity_math::get_delta_amount_0_unsigned(
+            sqrt_price_a_x64,
+            sqrt_price_b_x64,
+            liquidity,
+            round_up,
+        );
+        match to_u64(reference::get_amount_0_delta(
+            sqrt_price_a_x64,
+            sqrt_price_b_x64,
+            liquidity,
+            round_up,
+        )) {
+            Some(expected) => prop_assert_eq!(amount_0.unwrap(), expected),
+            None => prop_assert!(amount_0.is_err()),
+        }
+
+        let amount_1 = liquidity_math::get_delta_amount_1_unsigned(
+            sqrt_price_a_x64,
+            sqrt_price_b_x64,
+            liquidity,
+            round_up,
+        );
+        match to_u64(reference::get_amount_1_delta(
+            sqrt_price_a_x64,
+            sqrt_price_b_x64,
+            liquidity,
+            round_up,
+        )) {
+            Some(expected) => prop_assert_eq!(amount_1.unwrap(), expected),
+            None => prop_assert!(amount_1.is_err()),
+        }
+    }
+
+    #[test]
+    fn get_next_sqrt_price_differential_test(
+        sqrt_price_x64 in tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64,
+        liquidity in 1u128..(1u128 << 100),
+        amount in 0..u64::MAX,
+        zero_for_one: bool,
+    ) {
+        if let Some(expected) = reference::get_next_sqrt_price_from_input(
+            sqrt_price_x64,
+            liquidity,
+            amount,
+            zero_for_one,
+        )
+        .and_then(to_sqrt_price)
+        {
+            prop_assert_eq!(
+                sqrt_price_math::get_next_sqrt_price_from_input(
+                    sqrt_price_x64,
+                    liquidity,
+                    amount,
+                    zero_for_one,
+                )
+                .unwrap(),
+                expected
+            );
+        }
+        if let Some(expected) = reference::get_next_sqrt_price_from_output(
+            sqrt_price_x64,
+            liquidity,
+            amount,
+            zero_for_one,
+        )
+        .and_then(to_sqrt_price)
+        {
+            prop_assert_eq!(
+                sqrt_price_math::get_next_sqrt_price_from_output(
+                    sqrt_price_x64,
+                    liquidity,
+                    amount,
+                    zero_for_one,
+                )
+                .unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn compute_swap_step_differential_test(
+        tick_current in tick_math::MIN_TICK..tick_math::MAX_TICK,
+        tick_target in tick_math::MIN_TICK..tick_math::MAX_TICK,
+        liquidity in 1u128..(1u128 << 100),
+        amount_remaining in 1..u64::MAX,
+        fee_rate in 0u32..100_000,
+        is_base_input: bool,
+    ) {
+        let sqrt_price_current_x64 = tick_math::get_sqrt_price_at_tick(tick_current).unwrap();
+        let sqrt_price_target_x64 = tick_math::get_sqrt_price_at_tick(tick_target).unwrap();
+        let zero_for_one = sqrt_price_current_x64 >= sqrt_price_target_x64;
+        let expected = reference::compute_swap_step(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            amount_remaining,
+            fee_rate,
+            is_base_input,
+        );
+        // only the steps whose price and amounts the program can represent are compared
+        prop_assume!(expected.is_some());
+        let expected = expected.unwrap();
+        let expected_sqrt_price_next_x64 = to_sqrt_price(expected.sqrt_price_next_x64);
+        let expected_amount_in = to_u64(expected.amount_in);
+        let expected_amount_out = to_u64(expected.amount_out);
+        let expected_fee_amount = to_u64(expected.fee_amount);
+        prop_assume!(
+            expected_sqrt_price_next_x64.is_some()
+                && expected_amount_in.is_some()
+                && expected_amount_out.is_some()
+                && expected_fee_amount.is_some()
+        );
+
+        let step = swap_math::compute_swap_step(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            liquidity,
+            amount_remaining,
+            &swap_math::FlatFee(fee_rate),
+            is_base_input,
+            zero_for_one,
+            1,
+        )
+        .unwrap();
+        prop_assert_eq!(step.sqrt_price_next_x64, expected_sqrt_price_next_x64.unwrap());
+        prop_assert_eq!(step.amount_in, expected_amount_in.unwrap());
+        prop_assert_eq!(step.amount_out, expected_amount_out.unwrap());
+        prop_assert_eq!(step.fee_amount, expected_fee_amount.unwrap());
+    }
+}
@@ -1,6 +1,8 @@
 use super::full_math::MulDiv;
 use super::unsafe_math::UnsafeMathTrait;
 use super::{fixed_point_64, U256};
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
 
 /// Gets the next sqrt price √P' given a delta of token_0
 ///
@@ -32,9 +34,9 @@ pub fn get_next_sqrt_price_from_amount_0_rounding_up(
     liquidity: u128,
     amount: u64,
     add: bool,
-) -> u128 {
+) -> Result<u128> {
     if amount == 0 {
-        return sqrt_price_x64;
+        return Ok(sqrt_price_x64);
     };
     let numerator_1 = (U256::from(liquidity)) << fixed_point_64::RESOLUTION;
 
@@ -42,31 +44,32 @@ pub fn get_next_sqrt_price_from_amount_0_rounding_up(
         if let Some(product) = U256::from(amount).checked_mul(U256::from(sqrt_price_x64)) {
             let denominator = numerator_1 + U256::from(product);
             if denominator >= numerator_1 {
-                return numerator_1
-                    .mul_div_ceil(U256::from(sqrt_price_x64), denominator)
-                    .unwrap()
-                    .as_u128();
+                return to_u128(
+                    numerator_1
+                        .mul_div_ceil(U256::from(sqrt_price_x64), denominator)
+                        .ok_or(ErrorCode::MathOverflow)?,
+                );
             };
         }
 
-        U256::div_rounding_up(
+        to_u128(U256::div_rounding_up(
             numerator_1,
             (numerator_1 / U256::from(sqrt_price_x64))
                 .checked_add(U256::from(amount))
-                .unwrap(),
-        )
-        .as_u128()
+                .ok_or(ErrorCode::MathOverflow)?,
+        ))
     } else {
-        let product = U256::from(
-            U256::from(amount)
-                .checked_mul(U256::from(sqrt_price_x64))
-                .unwrap(),
-        );
-        let denominator = numerator_1.checked_sub(product).unwrap();
-        numerator_1
-            .mul_div_ceil(U256::from(sqrt_price_x64), denominator)
-            .unwrap()
-            .as_u128()
+        let product = U256::from(amount)
+            .checked_mul(U256::from(sqrt_price_x64))
+            .ok_or(ErrorCode::MathOverflow)?;
+        let denominator = numerator_1
+            .checked_sub(product)
+            .ok_or(ErrorCode::MathOverflow)?;
+        to_u128(
+            numerator_1
+                .mul_div_ceil(U256::from(sqrt_price_x64), denominator)
+                .ok_or(ErrorCode::MathOverflow)?,
+        )
     }
 }
 
@@ -89,16 +92,20 @@ pub fn get_next_sqrt_price_from_amount_1_rounding_down(
     liquidity: u128,
     amount: u64,
     add: bool,
-) -> u128 {
+) -> Result<u128> {
     if add {
         let quotient = U256::from(u128::from(amount) << fixed_point_64::RESOLUTION) / liquidity;
-        sqrt_price_x64.checked_add(quotient.as_u128()).unwrap()
+        Ok(sqrt_price_x64
+            .checked_add(to_u128(quotient)?)
+            .ok_or(ErrorCode::MathOverflow)?)
     } else {
         let quotient = U256::div_rounding_up(
             U256::from(u128::from(amount) << fixed_point_64::RESOLUTION),
             U256::from(liquidity),
         );
-        sqrt_price_x64.checked_sub(quotient.as_u128()).unwrap()
+        Ok(sqrt_price_x64
+            .checked_sub(to_u128(quotient)?)
+            .ok_or(ErrorCode::MathOverflow)?)
     }
 }
 
@@ -109,7 +116,7 @@ pub fn get_next_sqrt_price_from_input(
     liquidity: u128,
     amount_in: u64,
     zero_for_one: bool,
-) -> u128 {
+) -> Result<u128> {
     assert!(sqrt_price_x64 > 0);
     assert!(liquidity > 0);
 
@@ -130,7 +137,7 @@ pub fn get_next_sqrt_price_from_output(
     liquidity: u128,
     amount_out: u64,
     zero_for_one: bool,
-) -> u128 {
+) -> Result<u128> {
     assert!(sqrt_price_x64 > 0);
     assert!(liquidity > 0);
 
@@ -145,3 +152,51 @@ pub fn get_next_sqrt_price_from_output(
         get_next_sqrt_price_from_amount_0_rounding_up(sqrt_price_x64, liquidity, amount_out, false)
     }
 }
+
+/// A sqrt price out of the u128 range overflows
+fn to_u128(value: U256) -> Result<u128> {
+    require!(value <= U256::from(u128::MAX), ErrorCode::MathOverflow);
+    Ok(value.as_u128())
+}
+
+#[cfg(test)]
+mod sqrt_price_math_test {
+    use super::*;
+    use crate::libraries::tick_math;
+
+    #[test]
+    fn get_next_sqrt_price_from_input_test() {
+        let sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(0).unwrap();
+        let liquidity = 1_000_000_000u128;
+        let next_0 =
+            get_next_sqrt_price_from_input(sqrt_price_x64, liquidity, 1_000, true).unwrap();
+        assert!(next_0 < sqrt_price_x64);
+        let next_1 =
+            get_next_sqrt_price_from_input(sqrt_price_x64, liquidity, 1_000, false).unwrap();
+        assert!(next_1 > sqrt_price_x64);
+        // no amount doesn't move the price
+        assert_eq!(
+            get_next_sqrt_price_from_input(sqrt_price_x64, liquidity, 0, true).unwrap(),
+            sqrt_price_x64
+        );
+    }
+
+    #[test]
+    fn get_next_sqrt_price_overflow_test() {
+        // adding token_1 pushes the price past the u128 range
+        assert_eq!(
+            get_next_sqrt_price_from_input(u128::MAX - 1, 1, 1, false).unwrap_err(),
+            error!(ErrorCode::MathOverflow)
+        );
+        // taking more token_1 out than the price holds
+        assert_eq!(
+            get_next_sqrt_price_from_output(1 << 64, 1, u64::MAX, true).unwrap_err(),
+            error!(ErrorCode::MathOverflow)
+        );
+        // taking more token_0 out than the liquidity holds
+        assert_eq!(
+            get_next_sqrt_price_from_output(1 << 64, 1, u64::MAX, false).unwrap_err(),
+            error!(ErrorCode::MathOverflow)
+        );
+    }
+}
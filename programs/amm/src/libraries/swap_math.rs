@@ -5,7 +5,7 @@ use crate::error::ErrorCode;
 use crate::states::config::FEE_RATE_DENOMINATOR_VALUE;
 use anchor_lang::prelude::*;
 /// Result of a swap step
-#[derive(Default, Debug)]
+#[derive(Default, Debug, PartialEq)]
 pub struct SwapStep {
     /// The price after swapping the amount in/out, not to exceed the price target
     pub sqrt_price_next_x64: u128,
@@ -14,17 +14,61 @@ pub struct SwapStep {
     pub fee_amount: u64,
 }
 
-/// Computes the result of swapping some amount in, or amount out, given the parameters of the swap
-pub fn compute_swap_step(
+/// Decides the fee rate charged on a swap step, so fee models other than a flat rate can be
+/// simulated or added without touching the step math
+pub trait FeeModel {
+    /// The fee rate of the step starting at `sqrt_price_current_x64`, denominated in hundredths
+    /// of a bip (10^-6), must be less than `FEE_RATE_DENOMINATOR_VALUE`
+    fn fee_rate(
+        &self,
+        sqrt_price_current_x64: u128,
+        liquidity: u128,
+        zero_for_one: bool,
+        block_timestamp: u32,
+    ) -> u32;
+}
+
+/// The same fee rate on every step, the fee model of the program
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlatFee(pub u32);
+
+impl FeeModel for FlatFee {
+    #[inline(always)]
+    fn fee_rate(
+        &self,
+        _sqrt_price_current_x64: u128,
+        _liquidity: u128,
+        _zero_for_one: bool,
+        _block_timestamp: u32,
+    ) -> u32 {
+        self.0
+    }
+}
+
+/// Computes the result of swapping some amount in, or amount out, given the parameters of the swap.
+/// Generic over the fee model so the program's `FlatFee` path compiles to the plain rate, a
+/// `&dyn FeeModel` can be passed to simulate other models.
+pub fn compute_swap_step<F: FeeModel + ?Sized>(
     sqrt_price_current_x64: u128,
     sqrt_price_target_x64: u128,
     liquidity: u128,
     amount_remaining: u64,
-    fee_rate: u32,
+    fee_model: &F,
     is_base_input: bool,
     zero_for_one: bool,
     block_timestamp: u32,
 ) -> Result<SwapStep> {
+    let fee_rate = fee_model.fee_rate(
+        sqrt_price_current_x64,
+        liquidity,
+        zero_for_one,
+        block_timestamp,
+    );
+    require_gt!(
+        FEE_RATE_DENOMINATOR_VALUE,
+        fee_rate,
+        ErrorCode::InvalidFeeRate
+    );
     // let exact_in = amount_remaining >= 0;
     let mut swap_step = SwapStep::default();
     if is_base_input {
@@ -35,7 +79,7 @@ pub fn compute_swap_step(
                 (FEE_RATE_DENOMINATOR_VALUE - fee_rate).into(),
                 u64::from(FEE_RATE_DENOMINATOR_VALUE),
             )
-            .unwrap();
+            .ok_or(ErrorCode::MathOverflow)?;
 
         let amount_in = calculate_amount_in_range(
             sqrt_price_current_x64,
@@ -45,8 +89,8 @@ pub fn compute_swap_step(
             is_base_input,
             block_timestamp,
         )?;
-        if amount_in.is_some() {
-            swap_step.amount_in = amount_in.unwrap();
+        if let Some(amount_in) = amount_in {
+            swap_step.amount_in = amount_in;
         }
 
         swap_step.sqrt_price_next_x64 =
@@ -58,7 +102,7 @@ pub fn compute_swap_step(
                     liquidity,
                     amount_remaining_less_fee,
                     zero_for_one,
-                )
+                )?
             };
     } else {
         let amount_out = calculate_amount_in_range(
@@ -69,8 +113,8 @@ pub fn compute_swap_step(
             is_base_input,
             block_timestamp,
         )?;
-        if amount_out.is_some() {
-            swap_step.amount_out = amount_out.unwrap();
+        if let Some(amount_out) = amount_out {
+            swap_step.amount_out = amount_out;
         }
         // In exact output case, amount_remaining is negative
         swap_step.sqrt_price_next_x64 =
@@ -82,7 +126,7 @@ pub fn compute_swap_step(
                     liquidity,
                     amount_remaining,
                     zero_for_one,
-                )
+                )?
             }
     }
 
@@ -138,7 +182,7 @@ pub fn compute_swap_step(
             // swap dust is granted as fee
             u64::from(amount_remaining)
                 .checked_sub(swap_step.amount_in)
-                .unwrap()
+                .ok_or(ErrorCode::MathOverflow)?
         } else {
             // take pip percentage as fee
             swap_step
@@ -147,7 +191,7 @@ pub fn compute_swap_step(
                     fee_rate.into(),
                     (FEE_RATE_DENOMINATOR_VALUE - fee_rate).into(),
                 )
-                .unwrap()
+                .ok_or(ErrorCode::MathOverflow)?
         };
 
     Ok(swap_step)
@@ -295,7 +339,213 @@ mod swap_math_test {
     use super::*;
     use proptest::prelude::*;
 
+    /// The step at a flat `fee_rate` from its closed form: the price moves to the target if the
+    /// amount to get there is covered, otherwise as far as the amount goes, and the amounts are
+    /// the deltas between the current and the next price
+    fn reference_swap_step(
+        sqrt_price_current_x64: u128,
+        sqrt_price_target_x64: u128,
+        liquidity: u128,
+        amount_remaining: u64,
+        fee_rate: u32,
+        is_base_input: bool,
+        zero_for_one: bool,
+    ) -> Result<SwapStep> {
+        let delta = |price_a: u128, price_b: u128, round_up: bool, amount_0: bool| {
+            let (lower, upper) = (price_a.min(price_b), price_a.max(price_b));
+            if amount_0 {
+                liquidity_math::get_delta_amount_0_unsigned(lower, upper, liquidity, round_up)
+            } else {
+                liquidity_math::get_delta_amount_1_unsigned(lower, upper, liquidity, round_up)
+            }
+        };
+        let amount_available = if is_base_input {
+            amount_remaining
+                .mul_div_floor(
+                    (FEE_RATE_DENOMINATOR_VALUE - fee_rate).into(),
+                    FEE_RATE_DENOMINATOR_VALUE.into(),
+                )
+                .unwrap()
+        } else {
+            amount_remaining
+        };
+        // the amount is in token_0 for an input of zero for one or an output of one for zero
+        let specified_in_0 = zero_for_one == is_base_input;
+        let sqrt_price_next_x64 = match delta(
+            sqrt_price_current_x64,
+            sqrt_price_target_x64,
+            is_base_input,
+            specified_in_0,
+        ) {
+            Ok(amount) if amount_available >= amount => sqrt_price_target_x64,
+            _ if is_base_input => sqrt_price_math::get_next_sqrt_price_from_input(
+                sqrt_price_current_x64,
+                liquidity,
+                amount_available,
+                zero_for_one,
+            )?,
+            _ => sqrt_price_math::get_next_sqrt_price_from_output(
+                sqrt_price_current_x64,
+                liquidity,
+                amount_available,
+                zero_for_one,
+            )?,
+        };
+        let amount_in = delta(
+            sqrt_price_current_x64,
+            sqrt_price_next_x64,
+            true,
+            zero_for_one,
+        )?;
+        let amount_out = delta(
+            sqrt_price_current_x64,
+            sqrt_price_next_x64,
+            false,
+            !zero_for_one,
+        )?;
+        let fee_amount = if is_base_input && sqrt_price_next_x64 != sqrt_price_target_x64 {
+            amount_remaining
+                .checked_sub(amount_in)
+                .ok_or(ErrorCode::MathOverflow)?
+        } else {
+            amount_in
+                .mul_div_ceil(
+                    fee_rate.into(),
+                    (FEE_RATE_DENOMINATOR_VALUE - fee_rate).into(),
+                )
+                .unwrap()
+        };
+        Ok(SwapStep {
+            sqrt_price_next_x64,
+            amount_in,
+            amount_out: if is_base_input {
+                amount_out
+            } else {
+                amount_out.min(amount_remaining)
+            },
+            fee_amount,
+        })
+    }
+
+    /// A fee rate that grows with the distance of the price from a reference, a model the step
+    /// math is expected to take without changes
+    struct PriceDistanceFee {
+        base_rate: u32,
+        reference_sqrt_price_x64: u128,
+    }
+
+    impl FeeModel for PriceDistanceFee {
+        fn fee_rate(
+            &self,
+            sqrt_price_current_x64: u128,
+            _liquidity: u128,
+            _zero_for_one: bool,
+            _block_timestamp: u32,
+        ) -> u32 {
+            let distance = sqrt_price_current_x64.abs_diff(self.reference_sqrt_price_x64);
+            let surcharge = (distance / (self.reference_sqrt_price_x64 / 100 + 1)).min(1000) as u32;
+            self.base_rate + surcharge * 10
+        }
+    }
+
+    #[test]
+    fn dyn_fee_model_test() {
+        let reference_sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(0).unwrap();
+        let fee_model: &dyn FeeModel = &PriceDistanceFee {
+            base_rate: 2500,
+            reference_sqrt_price_x64,
+        };
+        let sqrt_price_current_x64 = tick_math::get_sqrt_price_at_tick(2000).unwrap();
+        let sqrt_price_target_x64 = tick_math::get_sqrt_price_at_tick(1000).unwrap();
+        let fee_rate = fee_model.fee_rate(sqrt_price_current_x64, 0, true, 1);
+        assert!(fee_rate > 2500);
+        // the model only picks the rate, the step is the flat rate step at that rate
+        assert_eq!(
+            compute_swap_step(
+                sqrt_price_current_x64,
+                sqrt_price_target_x64,
+                1_000_000_000,
+                1_000_000,
+                fee_model,
+                true,
+                true,
+                1,
+            )
+            .unwrap(),
+            compute_swap_step(
+                sqrt_price_current_x64,
+                sqrt_price_target_x64,
+                1_000_000_000,
+                1_000_000,
+                &FlatFee(fee_rate),
+                true,
+                true,
+                1,
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn fee_rate_above_denominator_test() {
+        let sqrt_price_current_x64 = tick_math::get_sqrt_price_at_tick(2000).unwrap();
+        let sqrt_price_target_x64 = tick_math::get_sqrt_price_at_tick(1000).unwrap();
+        for fee_rate in [FEE_RATE_DENOMINATOR_VALUE, u32::MAX] {
+            assert_eq!(
+                compute_swap_step(
+                    sqrt_price_current_x64,
+                    sqrt_price_target_x64,
+                    1_000_000_000,
+                    1_000_000,
+                    &FlatFee(fee_rate),
+                    true,
+                    true,
+                    1,
+                )
+                .unwrap_err(),
+                ErrorCode::InvalidFeeRate.into()
+            );
+        }
+    }
+
     proptest! {
+        #[test]
+        fn flat_fee_matches_reference_test(
+            sqrt_price_current_x64 in tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64,
+            sqrt_price_target_x64 in tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64,
+            liquidity in 1..u32::MAX as u128,
+            amount_remaining in 1..u64::MAX,
+            fee_rate in 0..FEE_RATE_DENOMINATOR_VALUE/2,
+            is_base_input in proptest::bool::ANY,
+        ) {
+            prop_assume!(sqrt_price_current_x64 != sqrt_price_target_x64);
+
+            let zero_for_one = sqrt_price_current_x64 > sqrt_price_target_x64;
+            let swap_step = compute_swap_step(
+                sqrt_price_current_x64,
+                sqrt_price_target_x64,
+                liquidity,
+                amount_remaining,
+                &FlatFee(fee_rate),
+                is_base_input,
+                zero_for_one,
+                1,
+            );
+            let reference_step = reference_swap_step(
+                sqrt_price_current_x64,
+                sqrt_price_target_x64,
+                liquidity,
+                amount_remaining,
+                fee_rate,
+                is_base_input,
+                zero_for_one,
+            );
+            match (swap_step, reference_step) {
+                (Ok(swap_step), Ok(reference_step)) => assert_eq!(swap_step, reference_step),
+                (swap_step, reference_step) => assert_eq!(swap_step.is_err(), reference_step.is_err()),
+            }
+        }
+
         #[test]
         fn compute_swap_step_test(
             sqrt_price_current_x64 in tick_math::MIN_SQRT_PRICE_X64..tick_math::MAX_SQRT_PRICE_X64,
@@ -313,7 +563,7 @@ mod swap_math_test {
                 sqrt_price_target_x64,
                 liquidity,
                 amount_remaining,
-                fee_rate,
+                &FlatFee(fee_rate),
                 is_base_input,
                 zero_for_one,
                 1,
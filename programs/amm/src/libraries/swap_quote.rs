@@ -0,0 +1,622 @@
+//! The swap loop of the program, free of accounts so a swap can be quoted off-chain with exactly
+//! the on-chain math. The program runs it over the loaded tick arrays and records the result in
+//! the pool, a client runs it over decoded copies of the accounts.
+use super::big_num::U128;
+use super::fixed_point_64;
+use super::full_math::MulDiv;
+use super::liquidity_math;
+use super::swap_math;
+use super::tick_math;
+use crate::error::ErrorCode;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use std::collections::VecDeque;
+#[cfg(feature = "enable-log")]
+use std::convert::identity;
+use std::ops::{DerefMut, Neg};
+
+// the top level state of the swap, the results of which are recorded in storage at the end
+#[derive(Debug)]
+pub struct SwapState {
+    // the amount remaining to be swapped in/out of the input/output asset
+    pub amount_specified_remaining: u64,
+    // the amount already swapped out/in of the output/input asset
+    pub amount_calculated: u64,
+    // current sqrt(price)
+    pub sqrt_price_x64: u128,
+    // the tick associated with the current price
+    pub tick: i32,
+    // the global fee growth of the input token
+    pub fee_growth_global_x64: u128,
+    // the global fee of the input token
+    pub fee_amount: u64,
+    // amount of input token paid as protocol fee
+    pub protocol_fee: u64,
+    // amount of input token paid as fund fee
+    pub fund_fee: u64,
+    // the current liquidity in range
+    pub liquidity: u128,
+    // the start index of every tick array the swap went through, in order
+    pub tick_array_start_indexes: Vec<i32>,
+}
+
+#[derive(Default)]
+struct StepComputations {
+    // the price at the beginning of the step
+    sqrt_price_start_x64: u128,
+    // the next tick to swap to from the current tick in the swap direction
+    tick_next: i32,
+    // whether tick_next is initialized or not
+    initialized: bool,
+    // sqrt(price) for the next tick (1/0)
+    sqrt_price_next_x64: u128,
+    // how much is being swapped in in this step
+    amount_in: u64,
+    // how much is being swapped out
+    amount_out: u64,
+    // how much fee is being paid in
+    fee_amount: u64,
+}
+
+/// The maximum number of steps of a swap over `tick_array_count` tick arrays, every step either
+/// crosses an initialized tick, moves to the next tick array or ends the swap
+pub fn max_swap_steps(tick_array_count: usize) -> Result<usize> {
+    Ok(tick_array_count
+        .checked_mul(TICK_ARRAY_SIZE_USIZE + 1)
+        .and_then(|steps| steps.checked_add(1))
+        .ok_or(ErrorCode::MathOverflow)?)
+}
+
+/// Swap `amount_specified` against the liquidity of the pool without writing to the pool. The
+/// crossed ticks are updated in `tick_array_states`, which can be the tick array accounts or
+/// copies of them: any `DerefMut` to a `TickArrayState` is accepted. `updated_reward_infos` are
+/// the reward infos of the pool at `block_timestamp`, recorded in the crossed ticks.
+pub fn compute_swap<T: DerefMut<Target = TickArrayState>>(
+    amm_config: &AmmConfig,
+    pool_state: &PoolState,
+    tick_array_states: &mut VecDeque<T>,
+    tickarray_bitmap_extension: &Option<TickArrayBitmapExtension>,
+    updated_reward_infos: &[RewardInfo; REWARD_NUM],
+    amount_specified: u64,
+    sqrt_price_limit_x64: u128,
+    zero_for_one: bool,
+    is_base_input: bool,
+    block_timestamp: u32,
+) -> Result<SwapState> {
+    let max_steps = max_swap_steps(tick_array_states.len())?;
+    compute_swap_with_max_steps(
+        amm_config,
+        pool_state,
+        tick_array_states,
+        tickarray_bitmap_extension,
+        updated_reward_infos,
+        amount_specified,
+        sqrt_price_limit_x64,
+        zero_for_one,
+        is_base_input,
+        block_timestamp,
+        max_steps,
+    )
+}
+
+/// The swap loop of `compute_swap`, failing once it takes more than `max_steps` steps
+fn compute_swap_with_max_steps<T: DerefMut<Target = TickArrayState>>(
+    amm_config: &AmmConfig,
+    pool_state: &PoolState,
+    tick_array_states: &mut VecDeque<T>,
+    tickarray_bitmap_extension: &Option<TickArrayBitmapExtension>,
+    updated_reward_infos: &[RewardInfo; REWARD_NUM],
+    amount_specified: u64,
+    sqrt_price_limit_x64: u128,
+    zero_for_one: bool,
+    is_base_input: bool,
+    block_timestamp: u32,
+    max_steps: usize,
+) -> Result<SwapState> {
+    require!(amount_specified != 0, ErrorCode::ZeroAmountSpecified);
+    require!(
+        if zero_for_one {
+            sqrt_price_limit_x64 < pool_state.sqrt_price_x64
+                && sqrt_price_limit_x64 > tick_math::MIN_SQRT_PRICE_X64
+        } else {
+            sqrt_price_limit_x64 > pool_state.sqrt_price_x64
+                && sqrt_price_limit_x64 < tick_math::MAX_SQRT_PRICE_X64
+        },
+        ErrorCode::SqrtPriceLimitOverflow
+    );
+    let pool_id = pool_state.key();
+
+    let mut state = SwapState {
+        amount_specified_remaining: amount_specified,
+        amount_calculated: 0,
+        sqrt_price_x64: pool_state.sqrt_price_x64,
+        tick: pool_state.tick_current,
+        fee_growth_global_x64: if zero_for_one {
+            pool_state.fee_growth_global_0_x64
+        } else {
+            pool_state.fee_growth_global_1_x64
+        },
+        fee_amount: 0,
+        protocol_fee: 0,
+        fund_fee: 0,
+        liquidity: pool_state.liquidity,
+        tick_array_start_indexes: Vec::new(),
+    };
+
+    let (mut is_match_pool_current_tick_array, first_vaild_tick_array_start_index) =
+        pool_state.get_first_initialized_tick_array(&tickarray_bitmap_extension, zero_for_one)?;
+    let mut current_vaild_tick_array_start_index = first_vaild_tick_array_start_index;
+
+    let mut steps: usize = 0;
+
+    let mut tick_array_current = tick_array_states
+        .pop_front()
+        .ok_or(ErrorCode::NotEnoughTickArrayAccount)?;
+    // find the first active tick array account
+    for _ in 0..tick_array_states.len() {
+        if tick_array_current.start_tick_index == current_vaild_tick_array_start_index {
+            break;
+        }
+        tick_array_current = tick_array_states
+            .pop_front()
+            .ok_or(ErrorCode::NotEnoughTickArrayAccount)?;
+    }
+    // check the first tick_array account is owned by the pool
+    require_keys_eq!(tick_array_current.pool_id, pool_id);
+    // check first tick array account is correct
+    require_eq!(
+        tick_array_current.start_tick_index,
+        current_vaild_tick_array_start_index,
+        ErrorCode::InvalidFirstTickArrayAccount
+    );
+    state
+        .tick_array_start_indexes
+        .push(current_vaild_tick_array_start_index);
+
+    // continue swapping as long as we haven't used the entire input/output and haven't
+    // reached the price limit
+    while state.amount_specified_remaining != 0 && state.sqrt_price_x64 != sqrt_price_limit_x64 {
+        steps += 1;
+        require_gte!(max_steps, steps, ErrorCode::SwapStepLimitExceeded);
+        #[cfg(feature = "enable-log")]
+        msg!(
+            "while begin, is_base_input:{},fee_growth_global_x32:{}, state_sqrt_price_x64:{}, state_tick:{},state_liquidity:{},state.protocol_fee:{}, protocol_fee_rate:{}",
+            is_base_input,
+            state.fee_growth_global_x64,
+            state.sqrt_price_x64,
+            state.tick,
+            state.liquidity,
+            state.protocol_fee,
+            amm_config.protocol_fee_rate
+        );
+        // Save these three pieces of information for PriceChangeEvent
+        // let tick_before = state.tick;
+        // let sqrt_price_x64_before = state.sqrt_price_x64;
+        // let liquidity_before = state.liquidity;
+
+        let mut step = StepComputations::default();
+        step.sqrt_price_start_x64 = state.sqrt_price_x64;
+
+        let mut next_initialized_tick = if let Some(tick_state) = tick_array_current
+            .next_initialized_tick(state.tick, pool_state.tick_spacing, zero_for_one)?
+        {
+            Box::new(*tick_state)
+        } else {
+            if !is_match_pool_current_tick_array {
+                is_match_pool_current_tick_array = true;
+                Box::new(*tick_array_current.first_initialized_tick(zero_for_one)?)
+            } else {
+                Box::new(TickState::default())
+            }
+        };
+        #[cfg(feature = "enable-log")]
+        msg!(
+            "next_initialized_tick, status:{}, tick_index:{}, tick_array_current:{}",
+            next_initialized_tick.is_initialized(),
+            identity(next_initialized_tick.tick),
+            tick_array_current.key().to_string(),
+        );
+        if !next_initialized_tick.is_initialized() {
+            let next_initialized_tickarray_index = pool_state
+                .next_initialized_tick_array_start_index(
+                    &tickarray_bitmap_extension,
+                    current_vaild_tick_array_start_index,
+                    zero_for_one,
+                )?;
+            let next_initialized_tickarray_index =
+                next_initialized_tickarray_index.ok_or(ErrorCode::LiquidityInsufficient)?;
+
+            while tick_array_current.start_tick_index != next_initialized_tickarray_index {
+                tick_array_current = tick_array_states
+                    .pop_front()
+                    .ok_or(ErrorCode::NotEnoughTickArrayAccount)?;
+                // check the tick_array account is owned by the pool
+                require_keys_eq!(tick_array_current.pool_id, pool_id);
+            }
+            current_vaild_tick_array_start_index = next_initialized_tickarray_index;
+            state
+                .tick_array_start_indexes
+                .push(current_vaild_tick_array_start_index);
+
+            let first_initialized_tick = tick_array_current.first_initialized_tick(zero_for_one)?;
+            next_initialized_tick = Box::new(*first_initialized_tick);
+        }
+        step.tick_next = next_initialized_tick.tick;
+        step.initialized = next_initialized_tick.is_initialized();
+
+        if step.tick_next < tick_math::MIN_TICK {
+            step.tick_next = tick_math::MIN_TICK;
+        } else if step.tick_next > tick_math::MAX_TICK {
+            step.tick_next = tick_math::MAX_TICK;
+        }
+        step.sqrt_price_next_x64 = tick_math::get_sqrt_price_at_tick(step.tick_next)?;
+
+        let target_price = if (zero_for_one && step.sqrt_price_next_x64 < sqrt_price_limit_x64)
+            || (!zero_for_one && step.sqrt_price_next_x64 > sqrt_price_limit_x64)
+        {
+            sqrt_price_limit_x64
+        } else {
+            step.sqrt_price_next_x64
+        };
+
+        if zero_for_one {
+            require_gte!(state.tick, step.tick_next);
+            require_gte!(step.sqrt_price_start_x64, step.sqrt_price_next_x64);
+            require_gte!(step.sqrt_price_start_x64, target_price);
+        } else {
+            require_gt!(step.tick_next, state.tick);
+            require_gte!(step.sqrt_price_next_x64, step.sqrt_price_start_x64);
+            require_gte!(target_price, step.sqrt_price_start_x64);
+        }
+        #[cfg(feature = "enable-log")]
+        msg!(
+            "sqrt_price_current_x64:{}, sqrt_price_target:{}, liquidity:{}, amount_remaining:{}",
+            step.sqrt_price_start_x64,
+            target_price,
+            state.liquidity,
+            state.amount_specified_remaining
+        );
+        let swap_step = swap_math::compute_swap_step(
+            step.sqrt_price_start_x64,
+            target_price,
+            state.liquidity,
+            state.amount_specified_remaining,
+            &swap_math::FlatFee(amm_config.trade_fee_rate),
+            is_base_input,
+            zero_for_one,
+            block_timestamp,
+        )?;
+        #[cfg(feature = "enable-log")]
+        msg!("{:#?}", swap_step);
+        if zero_for_one {
+            require_gte!(swap_step.sqrt_price_next_x64, target_price);
+        } else {
+            require_gte!(target_price, swap_step.sqrt_price_next_x64);
+        }
+        state.sqrt_price_x64 = swap_step.sqrt_price_next_x64;
+        step.amount_in = swap_step.amount_in;
+        step.amount_out = swap_step.amount_out;
+        step.fee_amount = swap_step.fee_amount;
+
+        if is_base_input {
+            state.amount_specified_remaining = state
+                .amount_specified_remaining
+                .checked_sub(
+                    step.amount_in
+                        .checked_add(step.fee_amount)
+                        .ok_or(ErrorCode::MathOverflow)?,
+                )
+                .ok_or(ErrorCode::MathOverflow)?;
+            state.amount_calculated = state
+                .amount_calculated
+                .checked_add(step.amount_out)
+                .ok_or(ErrorCode::MathOverflow)?;
+        } else {
+            state.amount_specified_remaining = state
+                .amount_specified_remaining
+                .checked_sub(step.amount_out)
+                .ok_or(ErrorCode::MathOverflow)?;
+
+            let step_amount_calculate = step
+                .amount_in
+                .checked_add(step.fee_amount)
+                .ok_or(ErrorCode::CalculateOverflow)?;
+            state.amount_calculated = state
+                .amount_calculated
+                .checked_add(step_amount_calculate)
+                .ok_or(ErrorCode::CalculateOverflow)?;
+        }
+
+        let step_fee_amount = step.fee_amount;
+        // if the protocol fee is on, calculate how much is owed, decrement fee_amount, and increment protocol_fee
+        if amm_config.protocol_fee_rate > 0 {
+            let delta = U128::from(step_fee_amount)
+                .checked_mul(amm_config.protocol_fee_rate.into())
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(FEE_RATE_DENOMINATOR_VALUE.into())
+                .ok_or(ErrorCode::MathOverflow)?
+                .as_u64();
+            step.fee_amount = step
+                .fee_amount
+                .checked_sub(delta)
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+            state.protocol_fee = state
+                .protocol_fee
+                .checked_add(delta)
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        }
+        // if the fund fee is on, calculate how much is owed, decrement fee_amount, and increment fund_fee
+        if amm_config.fund_fee_rate > 0 {
+            let delta = U128::from(step_fee_amount)
+                .checked_mul(amm_config.fund_fee_rate.into())
+                .ok_or(ErrorCode::MathOverflow)?
+                .checked_div(FEE_RATE_DENOMINATOR_VALUE.into())
+                .ok_or(ErrorCode::MathOverflow)?
+                .as_u64();
+            step.fee_amount = step
+                .fee_amount
+                .checked_sub(delta)
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+            state.fund_fee = state
+                .fund_fee
+                .checked_add(delta)
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+        }
+
+        // update global fee tracker
+        if state.liquidity > 0 {
+            let fee_growth_global_x64_delta = U128::from(step.fee_amount)
+                .mul_div_floor(U128::from(fixed_point_64::Q64), U128::from(state.liquidity))
+                .ok_or(ErrorCode::MathOverflow)?
+                .as_u128();
+
+            state.fee_growth_global_x64 = state
+                .fee_growth_global_x64
+                .checked_add(fee_growth_global_x64_delta)
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+            state.fee_amount = state
+                .fee_amount
+                .checked_add(step.fee_amount)
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
+            #[cfg(feature = "enable-log")]
+            msg!(
+                "fee_growth_global_x64_delta:{}, state.fee_growth_global_x64:{}, state.liquidity:{}, step.fee_amount:{}, state.fee_amount:{}",
+                fee_growth_global_x64_delta,
+                state.fee_growth_global_x64, state.liquidity, step.fee_amount, state.fee_amount
+            );
+        }
+        // shift tick if we reached the next price
+        if state.sqrt_price_x64 == step.sqrt_price_next_x64 {
+            // if the tick is initialized, run the tick transition
+            if step.initialized {
+                #[cfg(feature = "enable-log")]
+                msg!("loading next tick {}", step.tick_next);
+
+                let mut liquidity_net = next_initialized_tick.cross(
+                    if zero_for_one {
+                        state.fee_growth_global_x64
+                    } else {
+                        pool_state.fee_growth_global_0_x64
+                    },
+                    if zero_for_one {
+                        pool_state.fee_growth_global_1_x64
+                    } else {
+                        state.fee_growth_global_x64
+                    },
+                    updated_reward_infos,
+                )?;
+                // update tick_state to tick_array account
+                tick_array_current.update_tick_state(
+                    next_initialized_tick.tick,
+                    pool_state.tick_spacing.into(),
+                    *next_initialized_tick,
+                )?;
+
+                if zero_for_one {
+                    liquidity_net = liquidity_net.neg();
+                }
+                state.liquidity = liquidity_math::add_delta(state.liquidity, liquidity_net)?;
+            }
+
+            state.tick = if zero_for_one {
+                step.tick_next - 1
+            } else {
+                step.tick_next
+            };
+        } else if state.sqrt_price_x64 != step.sqrt_price_start_x64 {
+            // recompute unless we're on a lower tick boundary (i.e. already transitioned ticks), and haven't moved
+            // if only a small amount of quantity is traded, the input may be consumed by fees, resulting in no price change. If state.sqrt_price_x64, i.e., the latest price in the pool, is used to recalculate the tick, some errors may occur.
+            // for example, if zero_for_one, and the price falls exactly on an initialized tick t after the first trade, then at this point, pool.sqrtPriceX64 = get_sqrt_price_at_tick(t), while pool.tick = t-1. if the input quantity of the
+            // second trade is very small and the pool price does not change after the transaction, if the tick is recalculated, pool.tick will be equal to t, which is incorrect.
+            state.tick = tick_math::get_tick_at_sqrt_price(state.sqrt_price_x64)?;
+        }
+
+        #[cfg(feature = "enable-log")]
+        msg!(
+            "end, is_base_input:{},step_amount_in:{}, step_amount_out:{}, step_fee_amount:{},fee_growth_global_x32:{}, state_sqrt_price_x64:{}, state_tick:{}, state_liquidity:{},state.protocol_fee:{}, protocol_fee_rate:{}, state.fund_fee:{}, fund_fee_rate:{}",
+            is_base_input,
+            step.amount_in,
+            step.amount_out,
+            step.fee_amount,
+            state.fee_growth_global_x64,
+            state.sqrt_price_x64,
+            state.tick,
+            state.liquidity,
+            state.protocol_fee,
+            amm_config.protocol_fee_rate,
+            state.fund_fee,
+            amm_config.fund_fee_rate,
+        );
+        // emit!(PriceChangeEvent {
+        //     pool_state: pool_state.key(),
+        //     tick_before,
+        //     tick_after: state.tick,
+        //     sqrt_price_x64_before,
+        //     sqrt_price_x64_after: state.sqrt_price_x64,
+        //     liquidity_before,
+        //     liquidity_after: state.liquidity,
+        //     zero_for_one,
+        // });
+    }
+    Ok(state)
+}
+
+#[cfg(test)]
+mod swap_quote_test {
+    use super::*;
+    use crate::states::pool_test::build_pool;
+    use crate::states::tick_array_test::{build_tick, build_tick_array_with_tick_states};
+    use std::cell::RefCell;
+
+    #[test]
+    fn compute_swap_leaves_pool_untouched_test() {
+        let tick_spacing = 10;
+        let pool_state = build_pool(
+            0,
+            tick_spacing,
+            tick_math::get_sqrt_price_at_tick(0).unwrap(),
+            1_000_000_000,
+        );
+        let tick_array = build_tick_array_with_tick_states(
+            pool_state.borrow().key(),
+            -600,
+            tick_spacing,
+            vec![build_tick(-300, 1_000_000_000, 1_000_000_000).take()],
+        );
+        pool_state
+            .borrow_mut()
+            .flip_tick_array_bit(None, -600)
+            .unwrap();
+        let pool_before = *pool_state.borrow();
+        let amm_config = AmmConfig {
+            trade_fee_rate: 1000,
+            protocol_fee_rate: 120000,
+            fund_fee_rate: 40000,
+            tick_spacing,
+            ..Default::default()
+        };
+        // copies of the tick arrays are enough for a quote
+        let mut tick_array_copy = *tick_array.borrow();
+        let mut tick_arrays = VecDeque::from([&mut tick_array_copy]);
+        let state = compute_swap(
+            &amm_config,
+            &pool_state.borrow(),
+            &mut tick_arrays,
+            &None,
+            &[RewardInfo::default(); REWARD_NUM],
+            100_000,
+            tick_math::MIN_SQRT_PRICE_X64 + 1,
+            true,
+            true,
+            0,
+        )
+        .unwrap();
+
+        assert_eq!(state.amount_specified_remaining, 0);
+        assert!(state.amount_calculated > 0 && state.amount_calculated < 100_000);
+        assert!(state.sqrt_price_x64 < pool_before.sqrt_price_x64);
+        assert_eq!(state.tick_array_start_indexes, vec![-600]);
+        // 0.1% of the input, 12% and 4% of it go to the protocol and the fund
+        assert_eq!(state.protocol_fee + state.fund_fee + state.fee_amount, 100);
+        assert_eq!(state.protocol_fee, 12);
+        assert_eq!(state.fund_fee, 4);
+        let pool_after = *pool_state.borrow();
+        let (sqrt_price_before, sqrt_price_after) =
+            (pool_before.sqrt_price_x64, pool_after.sqrt_price_x64);
+        let (tick_before, tick_after) = (pool_before.tick_current, pool_after.tick_current);
+        assert_eq!(sqrt_price_after, sqrt_price_before);
+        assert_eq!(tick_after, tick_before);
+    }
+
+    /// Three tick arrays with every tick initialized, the densest set the bitmap can point a
+    /// swap to: the swap crosses all 180 ticks, one step each
+    fn build_dense_tick_arrays() -> (AmmConfig, RefCell<PoolState>, Vec<TickArrayState>) {
+        let start_indexes = [180, 120, 60];
+        let pool_state = build_pool(
+            239,
+            1,
+            tick_math::get_sqrt_price_at_tick(239).unwrap() + 1,
+            0,
+        );
+        let tick_arrays = start_indexes
+            .iter()
+            .map(|&start_index| {
+                pool_state
+                    .borrow_mut()
+                    .flip_tick_array_bit(None, start_index)
+                    .unwrap();
+                let ticks = (start_index..start_index + TICK_ARRAY_SIZE)
+                    .map(|tick| build_tick(tick, 1, 0).take())
+                    .collect();
+                build_tick_array_with_tick_states(pool_state.borrow().key(), start_index, 1, ticks)
+                    .take()
+            })
+            .collect();
+        let amm_config = AmmConfig {
+            tick_spacing: 1,
+            ..Default::default()
+        };
+        (amm_config, pool_state, tick_arrays)
+    }
+
+    fn compute_dense_swap(max_steps: Option<usize>) -> Result<SwapState> {
+        let (amm_config, pool_state, mut tick_arrays) = build_dense_tick_arrays();
+        let mut tick_arrays = tick_arrays.iter_mut().collect::<VecDeque<_>>();
+        let pool_state = pool_state.borrow();
+        let sqrt_price_limit_x64 = tick_math::get_sqrt_price_at_tick(60).unwrap();
+        let reward_infos = [RewardInfo::default(); REWARD_NUM];
+        match max_steps {
+            Some(max_steps) => compute_swap_with_max_steps(
+                &amm_config,
+                &pool_state,
+                &mut tick_arrays,
+                &None,
+                &reward_infos,
+                1000,
+                sqrt_price_limit_x64,
+                true,
+                true,
+                0,
+                max_steps,
+            ),
+            None => compute_swap(
+                &amm_config,
+                &pool_state,
+                &mut tick_arrays,
+                &None,
+                &reward_infos,
+                1000,
+                sqrt_price_limit_x64,
+                true,
+                true,
+                0,
+            ),
+        }
+    }
+
+    #[test]
+    fn dense_tick_arrays_within_step_bound_test() {
+        let state = compute_dense_swap(None).unwrap();
+        assert_eq!(state.tick, 59);
+        assert_eq!(
+            state.sqrt_price_x64,
+            tick_math::get_sqrt_price_at_tick(60).unwrap()
+        );
+        assert_eq!(state.tick_array_start_indexes, vec![180, 120, 60]);
+        // the swap takes exactly one step for each crossed tick
+        assert!(compute_dense_swap(Some(3 * TICK_ARRAY_SIZE_USIZE)).is_ok());
+    }
+
+    #[test]
+    fn dense_tick_arrays_exceed_step_bound_test() {
+        assert_eq!(
+            compute_dense_swap(Some(3 * TICK_ARRAY_SIZE_USIZE - 1)).unwrap_err(),
+            ErrorCode::SwapStepLimitExceeded.into()
+        );
+        // the bound of one tick array fewer can't fit the swap
+        assert_eq!(
+            compute_dense_swap(Some(max_swap_steps(2).unwrap())).unwrap_err(),
+            ErrorCode::SwapStepLimitExceeded.into()
+        );
+    }
+}
@@ -182,6 +182,14 @@ pub fn get_tick_at_sqrt_price(sqrt_price_x64: u128) -> Result<i32, anchor_lang::
     })
 }
 
+/// The sqrt price limit of a swap stopping at `tick_limit`. The price at the min or max tick
+/// is moved one unit inside the range, as swaps can't use the bounds as limits.
+pub fn get_sqrt_price_limit_at_tick(tick_limit: i32) -> Result<u128, anchor_lang::error::Error> {
+    Ok(get_sqrt_price_at_tick(tick_limit)?
+        .max(MIN_SQRT_PRICE_X64 + 1)
+        .min(MAX_SQRT_PRICE_X64 - 1))
+}
+
 #[cfg(test)]
 mod tick_math_test {
     use super::*;
@@ -224,6 +232,23 @@ mod tick_math_test {
         }
     }
 
+    #[test]
+    fn get_sqrt_price_limit_at_tick_test() {
+        assert_eq!(
+            get_sqrt_price_limit_at_tick(-28861).unwrap(),
+            get_sqrt_price_at_tick(-28861).unwrap()
+        );
+        assert_eq!(
+            get_sqrt_price_limit_at_tick(MIN_TICK).unwrap(),
+            MIN_SQRT_PRICE_X64 + 1
+        );
+        assert_eq!(
+            get_sqrt_price_limit_at_tick(MAX_TICK).unwrap(),
+            MAX_SQRT_PRICE_X64 - 1
+        );
+        assert!(get_sqrt_price_limit_at_tick(MAX_TICK + 1).is_err());
+    }
+
     #[test]
     fn tick_round_down() {
         // tick is negative
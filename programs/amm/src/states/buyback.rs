@@ -0,0 +1,252 @@
+use crate::error::ErrorCode;
+use crate::libraries::tick_math;
+use anchor_lang::prelude::*;
+
+pub const BUYBACK_SEED: &str = "buyback";
+
+/// Converts the protocol fees of a pool into the buyback token by swapping through the pool itself,
+/// the output is sent to the recipient token account, which can be owned by a treasury or by an
+/// address without private key to burn the tokens.
+///
+/// PDA of `[BUYBACK_SEED, pool_id]`
+///
+#[account]
+#[derive(Default, Debug)]
+pub struct BuybackState {
+    /// Bump to identify PDA
+    pub bump: u8,
+    /// The pool whose protocol fees are converted
+    pub pool_id: Pubkey,
+    /// The token to buy back, one of the pool tokens
+    pub buyback_mint: Pubkey,
+    /// The token account of buyback token receiving the output
+    pub recipient_token_account: Pubkey,
+    /// The crank can't move the pool price below this price
+    pub min_sqrt_price_x64: u128,
+    /// The crank can't move the pool price above this price
+    pub max_sqrt_price_x64: u128,
+    /// The maximum amount of protocol fees swapped by one crank, zero disables the crank
+    pub max_amount_in_per_crank: u64,
+    /// The total amount of protocol fees swapped
+    pub total_amount_in: u64,
+    /// The total amount of buyback token sent to the recipient
+    pub total_amount_out: u64,
+    /// Number of cranks executed
+    pub crank_count: u64,
+    /// The timestamp of the last crank
+    pub last_crank_time: u64,
+    /// The seconds of the pool twap the crank price is bounded by
+    pub twap_period: u32,
+    /// The crank can't move the pool price more than this many ticks away from the twap
+    pub max_twap_tick_deviation: u32,
+    /// The minimum seconds between two cranks
+    pub min_crank_interval: u64,
+    // Unused bytes for future upgrades.
+    pub padding: [u64; 6],
+}
+
+impl BuybackState {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 32 + 16 + 16 + 8 + 8 + 8 + 8 + 8 + 4 + 4 + 8 + 8 * 6;
+
+    /// Swap direction of the crank, the protocol fees of the other token are sold
+    pub fn zero_for_one(&self, token_mint_1: Pubkey) -> bool {
+        self.buyback_mint == token_mint_1
+    }
+
+    /// The price limit of the crank swap, returns None if the pool price is already out of the
+    /// allowed range in the swap direction
+    pub fn sqrt_price_limit_x64(&self, zero_for_one: bool, sqrt_price_x64: u128) -> Option<u128> {
+        price_limit_in_range(
+            zero_for_one,
+            sqrt_price_x64,
+            self.min_sqrt_price_x64,
+            self.max_sqrt_price_x64,
+        )
+    }
+
+    /// The price limit of the crank swap, the allowed range is narrowed to
+    /// `max_twap_tick_deviation` ticks around `twap_tick`. A price pushed out of it before the
+    /// crank makes the crank fail instead of selling into the manipulated price.
+    pub fn crank_sqrt_price_limit_x64(
+        &self,
+        zero_for_one: bool,
+        sqrt_price_x64: u128,
+        twap_tick: i32,
+    ) -> Result<Option<u128>> {
+        let deviation =
+            i32::try_from(self.max_twap_tick_deviation).map_err(|_| ErrorCode::MathOverflow)?;
+        let lower_tick = twap_tick.saturating_sub(deviation).max(tick_math::MIN_TICK);
+        let upper_tick = twap_tick.saturating_add(deviation).min(tick_math::MAX_TICK);
+        Ok(price_limit_in_range(
+            zero_for_one,
+            sqrt_price_x64,
+            self.min_sqrt_price_x64
+                .max(tick_math::get_sqrt_price_at_tick(lower_tick)?),
+            self.max_sqrt_price_x64
+                .min(tick_math::get_sqrt_price_at_tick(upper_tick)?),
+        ))
+    }
+
+    /// Check at least `min_crank_interval` seconds passed since the last crank
+    pub fn check_crank_interval(&self, block_timestamp: u64) -> Result<()> {
+        require_gte!(
+            block_timestamp,
+            self.last_crank_time.saturating_add(self.min_crank_interval),
+            ErrorCode::BuybackCrankTooEarly
+        );
+        Ok(())
+    }
+}
+
+/// The bound of `[min_sqrt_price_x64, max_sqrt_price_x64]` in the swap direction, None if the
+/// price is already at or past it
+fn price_limit_in_range(
+    zero_for_one: bool,
+    sqrt_price_x64: u128,
+    min_sqrt_price_x64: u128,
+    max_sqrt_price_x64: u128,
+) -> Option<u128> {
+    if zero_for_one {
+        if sqrt_price_x64 > min_sqrt_price_x64 {
+            return Some(min_sqrt_price_x64);
+        }
+    } else if sqrt_price_x64 < max_sqrt_price_x64 {
+        return Some(max_sqrt_price_x64);
+    }
+    None
+}
+
+/// Emitted when the buyback config of a pool is set
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct SetBuybackConfigEvent {
+    #[index]
+    pub pool_state: Pubkey,
+    pub buyback_mint: Pubkey,
+    pub recipient_token_account: Pubkey,
+    pub min_sqrt_price_x64: u128,
+    pub max_sqrt_price_x64: u128,
+    pub max_amount_in_per_crank: u64,
+    pub twap_period: u32,
+    pub max_twap_tick_deviation: u32,
+    pub min_crank_interval: u64,
+}
+
+/// Emitted when protocol fees are converted into the buyback token
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct BuybackEvent {
+    #[index]
+    pub pool_state: Pubkey,
+    /// The account executing the crank
+    pub cranker: Pubkey,
+    pub buyback_mint: Pubkey,
+    pub recipient_token_account: Pubkey,
+    /// The amount of protocol fees swapped
+    pub amount_in: u64,
+    /// The amount of buyback token sent to the recipient
+    pub amount_out: u64,
+    /// The pool price after the swap
+    pub sqrt_price_x64: u128,
+}
+
+#[cfg(test)]
+mod buyback_test {
+    use super::*;
+
+    #[test]
+    fn sqrt_price_limit_test() {
+        let buyback_state = BuybackState {
+            min_sqrt_price_x64: 100,
+            max_sqrt_price_x64: 200,
+            ..Default::default()
+        };
+        // sell token_0 for token_1, the price moves down to the lower bound
+        assert_eq!(buyback_state.sqrt_price_limit_x64(true, 150), Some(100));
+        assert_eq!(buyback_state.sqrt_price_limit_x64(true, 100), None);
+        assert_eq!(buyback_state.sqrt_price_limit_x64(true, 50), None);
+        // sell token_1 for token_0, the price moves up to the upper bound
+        assert_eq!(buyback_state.sqrt_price_limit_x64(false, 150), Some(200));
+        assert_eq!(buyback_state.sqrt_price_limit_x64(false, 200), None);
+        assert_eq!(buyback_state.sqrt_price_limit_x64(false, 250), None);
+    }
+
+    #[test]
+    fn crank_sqrt_price_limit_test() {
+        let buyback_state = BuybackState {
+            min_sqrt_price_x64: tick_math::get_sqrt_price_at_tick(-1000).unwrap(),
+            max_sqrt_price_x64: tick_math::get_sqrt_price_at_tick(1000).unwrap(),
+            max_twap_tick_deviation: 100,
+            ..Default::default()
+        };
+        let price_at = |tick| tick_math::get_sqrt_price_at_tick(tick).unwrap();
+        // the twap band is inside the admin range
+        assert_eq!(
+            buyback_state
+                .crank_sqrt_price_limit_x64(true, price_at(0), 0)
+                .unwrap(),
+            Some(price_at(-100))
+        );
+        assert_eq!(
+            buyback_state
+                .crank_sqrt_price_limit_x64(false, price_at(0), 0)
+                .unwrap(),
+            Some(price_at(100))
+        );
+        // a price pushed below the band before a crank selling token_0 fails it
+        assert_eq!(
+            buyback_state
+                .crank_sqrt_price_limit_x64(true, price_at(-150), 0)
+                .unwrap(),
+            None
+        );
+        // the admin range still applies when the twap is near its bound
+        assert_eq!(
+            buyback_state
+                .crank_sqrt_price_limit_x64(false, price_at(950), 950)
+                .unwrap(),
+            Some(price_at(1000))
+        );
+        // the band is clamped to the valid ticks
+        let buyback_state = BuybackState {
+            min_sqrt_price_x64: tick_math::MIN_SQRT_PRICE_X64 + 1,
+            max_sqrt_price_x64: tick_math::MAX_SQRT_PRICE_X64 - 1,
+            max_twap_tick_deviation: u32::MAX >> 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            buyback_state
+                .crank_sqrt_price_limit_x64(true, price_at(0), 0)
+                .unwrap(),
+            Some(tick_math::MIN_SQRT_PRICE_X64 + 1)
+        );
+        let buyback_state = BuybackState {
+            max_twap_tick_deviation: u32::MAX,
+            ..Default::default()
+        };
+        assert_eq!(
+            buyback_state
+                .crank_sqrt_price_limit_x64(true, price_at(0), 0)
+                .unwrap_err(),
+            ErrorCode::MathOverflow.into()
+        );
+    }
+
+    #[test]
+    fn check_crank_interval_test() {
+        let mut buyback_state = BuybackState {
+            min_crank_interval: 600,
+            ..Default::default()
+        };
+        // the first crank
+        assert!(buyback_state.check_crank_interval(1_000).is_ok());
+        buyback_state.last_crank_time = 1_000;
+        assert_eq!(
+            buyback_state.check_crank_interval(1_599).unwrap_err(),
+            ErrorCode::BuybackCrankTooEarly.into()
+        );
+        assert!(buyback_state.check_crank_interval(1_600).is_ok());
+        buyback_state.min_crank_interval = u64::MAX;
+        assert!(buyback_state.check_crank_interval(u64::MAX - 1).is_err());
+    }
+}
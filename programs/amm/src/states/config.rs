@@ -1,7 +1,13 @@
 use crate::error::ErrorCode;
+use crate::libraries::tick_math;
 use anchor_lang::prelude::*;
 
 pub const AMM_CONFIG_SEED: &str = "amm_config";
+pub const CONFIG_OWNER_PROPOSAL_SEED: &str = "config_owner_proposal";
+pub const PROTOCOL_FEE_SPLIT_SEED: &str = "protocol_fee_split";
+
+/// Prefix of the price references the config owner signs for pool creation
+pub const PRICE_REFERENCE_PREFIX: &[u8] = b"raydium_clmm_price_reference";
 
 pub const FEE_RATE_DENOMINATOR_VALUE: u32 = 1_000_000;
 
@@ -22,10 +28,22 @@ pub struct AmmConfig {
     pub tick_spacing: u16,
     /// The fund fee, denominated in hundredths of a bip (10^-6)
     pub fund_fee_rate: u32,
-    // padding space for upgrade
-    pub padding_u32: u32,
+    /// The share of the collected protocol fees sent to the secondary destination of the
+    /// `ProtocolFeeSplit`, denominated in hundredths of a bip (10^-6), zero to send it all to the
+    /// recipient
+    pub protocol_fee_split_rate: u32,
     pub fund_owner: Pubkey,
-    pub padding: [u64; 3],
+    /// The max ticks the initial price of a new pool can be away from a reference price signed by
+    /// the owner, zero if pools can be created at any price
+    pub max_initial_tick_deviation: u32,
+    /// Seconds after the creation of a pool before it can be swapped, zero to swap at once
+    pub swap_delay_after_create: u32,
+    /// Lamports the creator of a pool pays to the fund owner, zero if pools are created for free
+    pub pool_creation_fee: u64,
+    /// The owner can only be changed by a proposal the new owner accepts, set by the first
+    /// `propose_config_owner`
+    pub two_step_owner_transfer: bool,
+    pub padding: [u8; 7],
 }
 
 impl AmmConfig {
@@ -42,6 +60,127 @@ impl AmmConfig {
         );
         Ok(())
     }
+
+    /// The collected protocol fee amount split into the primary and secondary destination shares,
+    /// the rounding goes to the primary one
+    pub fn split_protocol_fee(&self, amount: u64) -> (u64, u64) {
+        let secondary = (u128::from(amount) * u128::from(self.protocol_fee_split_rate)
+            / u128::from(FEE_RATE_DENOMINATOR_VALUE)) as u64;
+        (amount - secondary, secondary)
+    }
+}
+
+/// The two owners the protocol fees of an amm config are split between, by its
+/// `protocol_fee_split_rate`
+///
+/// PDA of `[PROTOCOL_FEE_SPLIT_SEED, amm_config]`
+///
+#[account]
+#[derive(Default, Debug)]
+pub struct ProtocolFeeSplit {
+    /// Bump to identify PDA
+    pub bump: u8,
+    pub amm_config: Pubkey,
+    /// The owner of the recipient token accounts, e.g. the treasury
+    pub primary_destination: Pubkey,
+    /// The owner of the token accounts receiving the split share, e.g. an insurance fund
+    pub secondary_destination: Pubkey,
+    // Unused bytes for future upgrades.
+    pub padding: [u64; 4],
+}
+
+impl ProtocolFeeSplit {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 32 + 8 * 4;
+}
+
+/// An owner proposed for an amm config, who becomes the owner by accepting it
+///
+/// PDA of `[CONFIG_OWNER_PROPOSAL_SEED, amm_config]`
+///
+#[account]
+#[derive(Default, Debug)]
+pub struct ConfigOwnerProposal {
+    /// Bump to identify PDA
+    pub bump: u8,
+    pub amm_config: Pubkey,
+    /// The proposed owner, default once accepted
+    pub pending_owner: Pubkey,
+    /// The timestamp of the proposal
+    pub proposed_at: u64,
+    // Unused bytes for future upgrades.
+    pub padding: [u64; 4],
+}
+
+impl ConfigOwnerProposal {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 8 + 8 * 4;
+
+    /// Check the signer is the proposed owner and clear the proposal
+    pub fn accept(&mut self, signer: Pubkey) -> Result<Pubkey> {
+        require!(
+            self.pending_owner != Pubkey::default() && self.pending_owner == signer,
+            ErrorCode::NotApproved
+        );
+        self.pending_owner = Pubkey::default();
+        Ok(signer)
+    }
+}
+
+/// The price the config owner attests a pool should be created at, signed with an ed25519
+/// program instruction before `create_pool`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct PoolPriceReference {
+    pub amm_config: Pubkey,
+    pub token_mint_0: Pubkey,
+    pub token_mint_1: Pubkey,
+    pub sqrt_price_x64: u128,
+    /// The reference can't be used after this timestamp
+    pub expires_at: u64,
+}
+
+impl PoolPriceReference {
+    /// The bytes the config owner signs
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = PRICE_REFERENCE_PREFIX.to_vec();
+        message.extend_from_slice(&self.try_to_vec().unwrap());
+        message
+    }
+
+    pub fn from_bytes(message: &[u8]) -> Result<Self> {
+        require!(
+            message.starts_with(PRICE_REFERENCE_PREFIX),
+            ErrorCode::InvalidPriceReference
+        );
+        Self::try_from_slice(&message[PRICE_REFERENCE_PREFIX.len()..])
+            .map_err(|_| error!(ErrorCode::InvalidPriceReference))
+    }
+
+    /// Check the reference is for this pool and not expired, and the initial price is within
+    /// `max_tick_deviation` ticks of it
+    pub fn check_initial_price(
+        &self,
+        amm_config: Pubkey,
+        token_mint_0: Pubkey,
+        token_mint_1: Pubkey,
+        sqrt_price_x64: u128,
+        max_tick_deviation: u32,
+        timestamp: u64,
+    ) -> Result<()> {
+        require!(
+            self.amm_config == amm_config
+                && self.token_mint_0 == token_mint_0
+                && self.token_mint_1 == token_mint_1
+                && self.expires_at >= timestamp,
+            ErrorCode::InvalidPriceReference
+        );
+        let reference_tick = tick_math::get_tick_at_sqrt_price(self.sqrt_price_x64)?;
+        let tick = tick_math::get_tick_at_sqrt_price(sqrt_price_x64)?;
+        require_gte!(
+            max_tick_deviation,
+            tick.abs_diff(reference_tick),
+            ErrorCode::InitialPriceOutOfBand
+        );
+        Ok(())
+    }
 }
 
 /// Emitted when create or update a config
@@ -56,4 +195,128 @@ pub struct ConfigChangeEvent {
     pub tick_spacing: u16,
     pub fund_fee_rate: u32,
     pub fund_owner: Pubkey,
+    /// The lamports the creator of a pool pays to the fund owner
+    pub pool_creation_fee: u64,
+}
+
+/// Emitted when a new owner is proposed for a config
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct ConfigOwnerProposedEvent {
+    #[index]
+    pub amm_config: Pubkey,
+    pub owner: Pubkey,
+    pub pending_owner: Pubkey,
+}
+
+/// Emitted when the protocol fee split of a config is set
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct SetProtocolFeeSplitEvent {
+    #[index]
+    pub amm_config: Pubkey,
+    pub primary_destination: Pubkey,
+    pub secondary_destination: Pubkey,
+    pub protocol_fee_split_rate: u32,
+}
+
+#[cfg(test)]
+mod config_test {
+    use super::*;
+
+    #[test]
+    fn layout_len_unchanged_test() {
+        // the creation guards are carved from the padding
+        assert_eq!(
+            AmmConfig::default().try_to_vec().unwrap().len() + 8,
+            AmmConfig::LEN
+        );
+    }
+
+    #[test]
+    fn config_owner_proposal_accept_test() {
+        let pending_owner = Pubkey::new_unique();
+        let mut proposal = ConfigOwnerProposal {
+            pending_owner,
+            ..Default::default()
+        };
+        assert_eq!(
+            proposal.try_to_vec().unwrap().len() + 8,
+            ConfigOwnerProposal::LEN
+        );
+        // only the proposed owner can accept
+        assert!(proposal.accept(Pubkey::new_unique()).is_err());
+        assert_eq!(proposal.accept(pending_owner).unwrap(), pending_owner);
+        // and only once
+        assert!(proposal.accept(pending_owner).is_err());
+        let mut empty = ConfigOwnerProposal::default();
+        assert!(empty.accept(Pubkey::default()).is_err());
+    }
+
+    #[test]
+    fn split_protocol_fee_test() {
+        assert_eq!(
+            ProtocolFeeSplit::default().try_to_vec().unwrap().len() + 8,
+            ProtocolFeeSplit::LEN
+        );
+        let mut amm_config = AmmConfig::default();
+        // no split by default
+        assert_eq!(amm_config.split_protocol_fee(1_000), (1_000, 0));
+        amm_config.protocol_fee_split_rate = 250_000;
+        assert_eq!(amm_config.split_protocol_fee(1_000), (750, 250));
+        // the rounding goes to the primary destination
+        assert_eq!(amm_config.split_protocol_fee(3), (3, 0));
+        assert_eq!(amm_config.split_protocol_fee(0), (0, 0));
+        amm_config.protocol_fee_split_rate = FEE_RATE_DENOMINATOR_VALUE;
+        assert_eq!(amm_config.split_protocol_fee(u64::MAX), (0, u64::MAX));
+    }
+
+    #[test]
+    fn check_initial_price_test() {
+        let amm_config = Pubkey::new_unique();
+        let token_mint_0 = Pubkey::new_unique();
+        let token_mint_1 = Pubkey::new_unique();
+        let reference = PoolPriceReference {
+            amm_config,
+            token_mint_0,
+            token_mint_1,
+            sqrt_price_x64: tick_math::get_sqrt_price_at_tick(1000).unwrap(),
+            expires_at: 100,
+        };
+        assert_eq!(
+            PoolPriceReference::from_bytes(&reference.to_bytes()).unwrap(),
+            reference
+        );
+        assert!(PoolPriceReference::from_bytes(&reference.try_to_vec().unwrap()).is_err());
+
+        let check = |tick: i32, timestamp: u64| {
+            reference.check_initial_price(
+                amm_config,
+                token_mint_0,
+                token_mint_1,
+                tick_math::get_sqrt_price_at_tick(tick).unwrap(),
+                100,
+                timestamp,
+            )
+        };
+        assert!(check(1000, 100).is_ok());
+        assert!(check(900, 100).is_ok());
+        assert!(check(1100, 100).is_ok());
+        // a sniper's absurd price
+        assert!(check(899, 100).is_err());
+        assert!(check(-400_000, 100).is_err());
+        // expired
+        assert!(check(1000, 101).is_err());
+        // another pool
+        assert!(reference
+            .check_initial_price(
+                amm_config,
+                token_mint_1,
+                token_mint_0,
+                reference.sqrt_price_x64,
+                100,
+                0
+            )
+            .is_err());
+    }
 }
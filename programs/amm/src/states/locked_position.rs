@@ -0,0 +1,104 @@
+use anchor_lang::prelude::*;
+
+pub const LOCKED_POSITION_SEED: &str = "locked_position";
+
+/// Holds the nft of a position until the unlock time. While the nft is held the position can't
+/// be transferred and its liquidity can't be decreased, as both need the nft holder to sign.
+/// The owner still collects the fees and rewards with `collect_locked_position`.
+///
+/// PDA of `[LOCKED_POSITION_SEED, position_nft_mint]`, the nft is held by its associated token
+/// account.
+///
+#[account]
+#[derive(Default, Debug)]
+pub struct LockedPositionState {
+    /// Bump to identify PDA
+    pub bump: [u8; 1],
+    /// The owner who locked the position, the nft is returned to them once unlocked
+    pub owner: Pubkey,
+    /// Mint address of the tokenized position
+    pub position_nft_mint: Pubkey,
+    /// The personal position locked
+    pub personal_position: Pubkey,
+    /// The pool of the position
+    pub pool_id: Pubkey,
+    /// The timestamp the position was locked at
+    pub locked_at: u64,
+    /// The position can't be unlocked before this timestamp
+    pub unlock_time: u64,
+    // Unused bytes for future upgrades.
+    pub padding: [u64; 8],
+}
+
+impl LockedPositionState {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 32 + 32 + 8 + 8 + 8 * 8;
+
+    pub fn seeds(&self) -> [&[u8]; 3] {
+        [
+            LOCKED_POSITION_SEED.as_bytes(),
+            self.position_nft_mint.as_ref(),
+            self.bump.as_ref(),
+        ]
+    }
+
+    pub fn is_unlockable(&self, current_timestamp: u64) -> bool {
+        current_timestamp >= self.unlock_time
+    }
+}
+
+/// Emitted when a position nft is locked
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct LockPositionEvent {
+    #[index]
+    pub personal_position: Pubkey,
+    pub owner: Pubkey,
+    pub position_nft_mint: Pubkey,
+    pub unlock_time: u64,
+}
+
+/// Emitted when a position nft is returned to its owner
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct UnlockPositionEvent {
+    #[index]
+    pub personal_position: Pubkey,
+    pub owner: Pubkey,
+    pub position_nft_mint: Pubkey,
+}
+
+#[cfg(test)]
+mod locked_position_test {
+    use super::*;
+
+    #[test]
+    fn is_unlockable_test() {
+        let locked_position = LockedPositionState {
+            locked_at: 100,
+            unlock_time: 200,
+            ..Default::default()
+        };
+        assert!(!locked_position.is_unlockable(100));
+        assert!(!locked_position.is_unlockable(199));
+        assert!(locked_position.is_unlockable(200));
+        assert!(locked_position.is_unlockable(300));
+    }
+
+    #[test]
+    fn seeds_test() {
+        let position_nft_mint = Pubkey::new_unique();
+        let (key, bump) = Pubkey::find_program_address(
+            &[LOCKED_POSITION_SEED.as_bytes(), position_nft_mint.as_ref()],
+            &crate::id(),
+        );
+        let locked_position = LockedPositionState {
+            bump: [bump],
+            position_nft_mint,
+            ..Default::default()
+        };
+        assert_eq!(
+            Pubkey::create_program_address(&locked_position.seeds(), &crate::id()).unwrap(),
+            key
+        );
+    }
+}
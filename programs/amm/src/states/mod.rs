@@ -1,17 +1,29 @@
+pub mod buyback;
 pub mod config;
+pub mod locked_position;
 pub mod operation_account;
 pub mod oracle;
 pub mod personal_position;
 pub mod pool;
+pub mod pool_stats;
 pub mod protocol_position;
+pub mod relay;
+pub mod reward_merkle;
 pub mod tick_array;
 pub mod tickarray_bitmap_extension;
+pub mod vault_reconciliation;
 
+pub use buyback::*;
 pub use config::*;
+pub use locked_position::*;
 pub use operation_account::*;
 pub use oracle::*;
 pub use personal_position::*;
 pub use pool::*;
+pub use pool_stats::*;
 pub use protocol_position::*;
+pub use relay::*;
+pub use reward_merkle::*;
 pub use tick_array::*;
 pub use tickarray_bitmap_extension::*;
+pub use vault_reconciliation::*;
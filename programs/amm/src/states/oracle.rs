@@ -2,6 +2,7 @@
 ///
 use anchor_lang::prelude::*;
 
+use crate::error::ErrorCode;
 use crate::util::get_recent_epoch;
 
 /// Seed to derive account address and signature
@@ -105,6 +106,144 @@ impl ObservationState {
             self.observation_index = next_observation_index;
         }
     }
+
+    /// The index of the oldest observation and the number of recorded observations, the ring
+    /// buffer is full once the slot after the newest one is written
+    pub fn recorded_range(&self) -> (usize, usize) {
+        let next_index = (self.observation_index as usize + 1) % OBSERVATION_NUM;
+        if self.observations[next_index].block_timestamp == 0 {
+            (0, self.observation_index as usize + 1)
+        } else {
+            (next_index, OBSERVATION_NUM)
+        }
+    }
+
+    /// Returns the observations at or right before and right after `target`, found with a binary
+    /// search of the ring buffer. `target` must be between the oldest and the newest observation.
+    pub fn get_surrounding_observations(&self, target: u32) -> Result<(Observation, Observation)> {
+        require!(self.initialized, ErrorCode::InvalidTwapPeriod);
+        let (oldest_index, count) = self.recorded_range();
+        let observation_at = |i: usize| self.observations[(oldest_index + i) % OBSERVATION_NUM];
+        let newest = observation_at(count - 1);
+        require!(
+            observation_at(0).block_timestamp <= target && target <= newest.block_timestamp,
+            ErrorCode::InvalidTwapPeriod
+        );
+        if count == 1 || target == newest.block_timestamp {
+            return Ok((newest, newest));
+        }
+        // the last observation at or before the target, the one after it is newer than the target
+        let (mut low, mut high) = (0, count - 1);
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if observation_at(mid).block_timestamp <= target {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Ok((observation_at(low), observation_at(low + 1)))
+    }
+
+    /// Returns the tick cumulative `seconds_ago` seconds before `block_timestamp`. A time between
+    /// two observations is interpolated, and the pool has been at `tick_current` since the
+    /// latest one.
+    pub fn observe_single(
+        &self,
+        block_timestamp: u32,
+        tick_current: i32,
+        seconds_ago: u32,
+    ) -> Result<i64> {
+        require!(self.initialized, ErrorCode::InvalidTwapPeriod);
+        let target = block_timestamp
+            .checked_sub(seconds_ago)
+            .ok_or(ErrorCode::InvalidTwapPeriod)?;
+        let last_observation = self.observations[self.observation_index as usize];
+        if target >= last_observation.block_timestamp {
+            let delta_time = target - last_observation.block_timestamp;
+            return Ok(last_observation
+                .tick_cumulative
+                .wrapping_add(i64::from(tick_current) * i64::from(delta_time)));
+        }
+        let (before, after) = self.get_surrounding_observations(target)?;
+        Ok(interpolate_tick_cumulative(&before, &after, target))
+    }
+
+    /// Returns the tick cumulatives `seconds_agos` seconds before `block_timestamp`, the average
+    /// tick between two of them is their difference divided by the seconds elapsed
+    ///
+    /// # Arguments
+    ///
+    /// * `block_timestamp` - The current timestamp
+    /// * `tick_current` - The current tick of the pool
+    /// * `seconds_agos` - How long ago each tick cumulative is read
+    ///
+    pub fn observe(
+        &self,
+        block_timestamp: u32,
+        tick_current: i32,
+        seconds_agos: &[u32],
+    ) -> Result<Vec<i64>> {
+        seconds_agos
+            .iter()
+            .map(|seconds_ago| self.observe_single(block_timestamp, tick_current, *seconds_ago))
+            .collect()
+    }
+
+    /// Returns the time weighted average tick of the last `seconds_ago` seconds, rounded down
+    pub fn average_tick(
+        &self,
+        block_timestamp: u32,
+        tick_current: i32,
+        seconds_ago: u32,
+    ) -> Result<i32> {
+        self.average_tick_between(block_timestamp, tick_current, seconds_ago, 0)
+    }
+
+    /// Returns the time weighted average tick from `start_seconds_ago` to `end_seconds_ago`
+    /// seconds before `block_timestamp`, rounded down. The window doesn't need to start or end
+    /// on an observation.
+    pub fn average_tick_between(
+        &self,
+        block_timestamp: u32,
+        tick_current: i32,
+        start_seconds_ago: u32,
+        end_seconds_ago: u32,
+    ) -> Result<i32> {
+        require_gt!(
+            start_seconds_ago,
+            end_seconds_ago,
+            ErrorCode::InvalidTwapPeriod
+        );
+        let tick_cumulatives = self.observe(
+            block_timestamp,
+            tick_current,
+            &[start_seconds_ago, end_seconds_ago],
+        )?;
+        let delta_tick_cumulative = tick_cumulatives[1].wrapping_sub(tick_cumulatives[0]);
+        let seconds = i64::from(start_seconds_ago - end_seconds_ago);
+        let mut average_tick = delta_tick_cumulative / seconds;
+        if delta_tick_cumulative < 0 && delta_tick_cumulative % seconds != 0 {
+            average_tick -= 1;
+        }
+        Ok(average_tick as i32)
+    }
+}
+
+/// Returns the tick cumulative at `target`, between the timestamps of the `before` and `after`
+/// observations, assuming the average tick between them held all along. The product is taken
+/// before the division so windows not aligned on the observations keep their precision, the
+/// result is rounded toward zero.
+pub fn interpolate_tick_cumulative(before: &Observation, after: &Observation, target: u32) -> i64 {
+    let (before_timestamp, after_timestamp) = (before.block_timestamp, after.block_timestamp);
+    if target <= before_timestamp || after_timestamp <= before_timestamp {
+        return before.tick_cumulative;
+    }
+    let delta_tick_cumulative = after.tick_cumulative.wrapping_sub(before.tick_cumulative);
+    let elapsed = target.min(after_timestamp) - before_timestamp;
+    let interpolated = i128::from(delta_tick_cumulative) * i128::from(elapsed)
+        / i128::from(after_timestamp - before_timestamp);
+    before.tick_cumulative.wrapping_add(interpolated as i64)
 }
 
 /// Returns the block timestamp truncated to 32 bits, i.e. mod 2**32
@@ -235,3 +374,123 @@ pub mod oracle_layout_test {
         }
     }
 }
+
+#[cfg(test)]
+mod observe_test {
+    use super::*;
+
+    fn build_observation_state() -> ObservationState {
+        let mut observation_state = ObservationState::default();
+        observation_state.update(1000, 0);
+        // the pool was at tick 10 from 1000 to 1100, then at tick -20 until 1200
+        observation_state.update(1100, 10);
+        observation_state.update(1200, -20);
+        observation_state
+    }
+
+    #[test]
+    fn observe_test() {
+        let observation_state = build_observation_state();
+        // the pool has been at tick 5 since 1200
+        assert_eq!(
+            observation_state
+                .observe(1300, 5, &[0, 100, 150, 250, 300])
+                .unwrap(),
+            vec![-500, -1000, 0, 500, 0]
+        );
+        // older than the first observation
+        assert_eq!(
+            observation_state.observe(1300, 5, &[301]).unwrap_err(),
+            ErrorCode::InvalidTwapPeriod.into()
+        );
+        assert!(ObservationState::default().observe(1300, 5, &[0]).is_err());
+    }
+
+    #[test]
+    fn get_surrounding_observations_test() {
+        let observation_state = build_observation_state();
+        let (before, after) = observation_state
+            .get_surrounding_observations(1150)
+            .unwrap();
+        let (before_timestamp, after_timestamp) = (before.block_timestamp, after.block_timestamp);
+        assert_eq!((before_timestamp, after_timestamp), (1100, 1200));
+        // an exact match is the observation before
+        let (before, after) = observation_state
+            .get_surrounding_observations(1100)
+            .unwrap();
+        let (before_timestamp, after_timestamp) = (before.block_timestamp, after.block_timestamp);
+        assert_eq!((before_timestamp, after_timestamp), (1100, 1200));
+        assert!(observation_state.get_surrounding_observations(999).is_err());
+        assert!(observation_state
+            .get_surrounding_observations(1201)
+            .is_err());
+
+        // once the ring buffer wraps the oldest observation is the one after the newest
+        let mut observation_state = ObservationState::default();
+        for i in 0..OBSERVATION_NUM as u32 + 10 {
+            observation_state.update(1000 + i * 20, 1);
+        }
+        let (oldest_index, count) = observation_state.recorded_range();
+        assert_eq!((oldest_index, count), (10, OBSERVATION_NUM));
+        let oldest_timestamp = observation_state.observations[oldest_index].block_timestamp;
+        assert_eq!(oldest_timestamp, 1200);
+        assert!(observation_state
+            .get_surrounding_observations(1199)
+            .is_err());
+        let (before, after) = observation_state
+            .get_surrounding_observations(1210)
+            .unwrap();
+        let (before_timestamp, after_timestamp) = (before.block_timestamp, after.block_timestamp);
+        assert_eq!((before_timestamp, after_timestamp), (1200, 1220));
+        let (before, after) = observation_state
+            .get_surrounding_observations(2970)
+            .unwrap();
+        let (before_timestamp, after_timestamp) = (before.block_timestamp, after.block_timestamp);
+        assert_eq!((before_timestamp, after_timestamp), (2960, 2980));
+    }
+
+    #[test]
+    fn interpolate_tick_cumulative_test() {
+        let before = Observation {
+            block_timestamp: 1000,
+            tick_cumulative: 0,
+            ..Default::default()
+        };
+        // an average tick of 1.5 over 20 seconds
+        let after = Observation {
+            block_timestamp: 1020,
+            tick_cumulative: 30,
+            ..Default::default()
+        };
+        // dividing first would give 1 * 7
+        assert_eq!(interpolate_tick_cumulative(&before, &after, 1007), 10);
+        assert_eq!(interpolate_tick_cumulative(&before, &after, 1000), 0);
+        assert_eq!(interpolate_tick_cumulative(&before, &after, 1020), 30);
+        let after = Observation {
+            block_timestamp: 1020,
+            tick_cumulative: -30,
+            ..Default::default()
+        };
+        assert_eq!(interpolate_tick_cumulative(&before, &after, 1007), -10);
+    }
+
+    #[test]
+    fn average_tick_test() {
+        let observation_state = build_observation_state();
+        assert_eq!(observation_state.average_tick(1300, 5, 100).unwrap(), 5);
+        assert_eq!(observation_state.average_tick(1300, 5, 200).unwrap(), -8);
+        // -500 / 300 is rounded down
+        assert_eq!(observation_state.average_tick(1300, 5, 300).unwrap(), -2);
+        assert!(observation_state.average_tick(1300, 5, 0).is_err());
+        // from 1050 to 1150, 50 seconds at 10 then 50 at -20
+        assert_eq!(
+            observation_state
+                .average_tick_between(1300, 5, 250, 150)
+                .unwrap(),
+            -5
+        );
+        assert!(observation_state
+            .average_tick_between(1300, 5, 100, 100)
+            .is_err());
+    }
+}
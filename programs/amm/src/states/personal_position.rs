@@ -1,3 +1,4 @@
+use crate::error::ErrorCode;
 use crate::libraries::{big_num::U256, fixed_point_64, full_math::MulDiv};
 use crate::pool::REWARD_NUM;
 use crate::util::get_recent_epoch;
@@ -5,7 +6,10 @@ use anchor_lang::prelude::*;
 
 use super::POSITION_SEED;
 
-#[account]
+/// Zero copy with the field order of the borsh layout it replaced, the accounts created before
+/// are loaded as they are
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
 #[derive(Default, Debug)]
 pub struct PersonalPositionState {
     /// Bump to identify PDA
@@ -42,8 +46,20 @@ pub struct PersonalPositionState {
     pub reward_infos: [PositionRewardInfo; REWARD_NUM],
     // account update recent epoch
     pub recent_epoch: u64,
-    // Unused bytes for future upgrades.
-    pub padding: [u64; 7],
+    /// The timestamp of the first recorded action on the position, zero if none is recorded yet.
+    /// Positions opened before it was recorded get the timestamp of their next action, so the age
+    /// derived from it is a lower bound
+    pub created_at: u32,
+    /// The timestamp of the last open, increase, decrease or collect on the position
+    pub last_updated_at: u32,
+    /// The nonce the next relayed action signed by the owner must carry
+    pub relay_nonce: u64,
+    /// The owner of the token accounts the fees and rewards of the position are paid out to,
+    /// set by the nft owner. Default lets the signer choose the token accounts.
+    pub fee_recipient: Pubkey,
+    /// The first bytes of the nft owner that set the fee recipient, the recipient is ignored
+    /// once the nft is held by another owner. The account has no room left for the whole key.
+    pub fee_recipient_setter: [u8; 8],
 }
 
 impl PersonalPositionState {
@@ -86,16 +102,95 @@ impl PersonalPositionState {
                     .unwrap();
 
                 #[cfg(feature = "enable-log")]
-                msg!("update personal reward, index:{}, owed_before:{:?}, amount_owed_delta:{}, owed_after:{}, reward_growth_delta:{}, self.liquidity:{}", i, curr_reward_info.reward_amount_owed,amount_owed_delta, self.reward_infos[i].reward_amount_owed,reward_growth_delta,self.liquidity );
+                msg!("update personal reward, index:{}, owed_before:{:?}, amount_owed_delta:{}, owed_after:{}, reward_growth_delta:{}, self.liquidity:{}", i, { curr_reward_info.reward_amount_owed },amount_owed_delta, { self.reward_infos[i].reward_amount_owed },reward_growth_delta,{ self.liquidity });
             }
             self.reward_infos[i].growth_inside_last_x64 = reward_growth_inside;
         }
         self.recent_epoch = get_recent_epoch()?;
         Ok(())
     }
+
+    /// Record an action on the position at `timestamp`
+    pub fn record_action(&mut self, timestamp: u64) {
+        let timestamp = timestamp as u32;
+        if self.created_at == 0 {
+            self.created_at = timestamp;
+        }
+        self.last_updated_at = timestamp;
+    }
+
+    /// Consume the nonce of a relayed action, a signed action can't be submitted twice
+    pub fn use_relay_nonce(&mut self, nonce: u64) -> Result<()> {
+        require_eq!(self.relay_nonce, nonce, ErrorCode::InvalidRelayNonce);
+        self.relay_nonce = self.relay_nonce.checked_add(1).unwrap();
+        Ok(())
+    }
+
+    /// Pay the fees and rewards of the position to `fee_recipient` while `nft_owner` holds the nft
+    pub fn set_fee_recipient(&mut self, fee_recipient: Pubkey, nft_owner: &Pubkey) {
+        self.fee_recipient = fee_recipient;
+        self.fee_recipient_setter = fee_recipient_setter(nft_owner);
+    }
+
+    pub fn clear_fee_recipient(&mut self) {
+        self.fee_recipient = Pubkey::default();
+        self.fee_recipient_setter = [0; 8];
+    }
+
+    /// The fee recipient of the position held by `nft_owner`, None if unset or set by another
+    /// holder of the nft, e.g. before a plain token transfer of it
+    pub fn active_fee_recipient(&self, nft_owner: &Pubkey) -> Option<Pubkey> {
+        if self.fee_recipient == Pubkey::default()
+            || self.fee_recipient_setter != fee_recipient_setter(nft_owner)
+        {
+            None
+        } else {
+            Some(self.fee_recipient)
+        }
+    }
+
+    /// Whether the fees and rewards of the position held by `nft_owner` can be paid out to a
+    /// token account owned by `token_account_owner`. The withdrawn liquidity isn't restricted.
+    pub fn can_pay_fees_to(&self, nft_owner: &Pubkey, token_account_owner: &Pubkey) -> bool {
+        self.active_fee_recipient(nft_owner)
+            .map_or(true, |fee_recipient| fee_recipient == *token_account_owner)
+    }
+
+    /// The owner of the token accounts receiving the fees and rewards of the position held by
+    /// `nft_owner`
+    pub fn payout_owner(&self, nft_owner: &Pubkey) -> Pubkey {
+        self.active_fee_recipient(nft_owner).unwrap_or(*nft_owner)
+    }
+
+    /// No liquidity, fees or rewards are left, the position can be closed
+    pub fn is_empty(&self) -> bool {
+        self.liquidity == 0
+            && self.token_fees_owed_0 == 0
+            && self.token_fees_owed_1 == 0
+            && self
+                .reward_infos
+                .iter()
+                .all(|reward_info| reward_info.reward_amount_owed == 0)
+    }
+
+    /// Seconds since the first recorded action, None if no action is recorded yet
+    pub fn age(&self, timestamp: u64) -> Option<u64> {
+        if self.created_at == 0 {
+            return None;
+        }
+        Some(timestamp.saturating_sub(u64::from(self.created_at)))
+    }
 }
 
-#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize, Default, Debug, PartialEq)]
+/// The bytes of the nft owner recorded with the fee recipient, 64 bits keep a new holder from
+/// matching the holder that set it
+fn fee_recipient_setter(nft_owner: &Pubkey) -> [u8; 8] {
+    nft_owner.to_bytes()[..8].try_into().unwrap()
+}
+
+#[zero_copy(unsafe)]
+#[repr(C, packed)]
+#[derive(Default, Debug, PartialEq)]
 pub struct PositionRewardInfo {
     // Q64.64
     pub growth_inside_last_x64: u128,
@@ -144,6 +239,31 @@ pub struct CreatePersonalPositionEvent {
     pub deposit_amount_1_transfer_fee: u64,
 }
 
+/// Emitted when the fee recipient of a position is set or cleared
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct SetFeeRecipientEvent {
+    /// The position nft mint
+    #[index]
+    pub position_nft_mint: Pubkey,
+
+    /// The new fee recipient, default when cleared
+    pub fee_recipient: Pubkey,
+}
+
+/// Emitted when a position nft is transferred with transfer_position
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct TransferPositionEvent {
+    #[index]
+    pub personal_position: Pubkey,
+    pub position_nft_mint: Pubkey,
+    /// The previous owner
+    pub from: Pubkey,
+    /// The new owner
+    pub to: Pubkey,
+}
+
 /// Emitted when liquidity is increased.
 #[event]
 #[cfg_attr(feature = "client", derive(Debug))]
@@ -168,6 +288,24 @@ pub struct IncreaseLiquidityEvent {
     pub amount_1_transfer_fee: u64,
 }
 
+/// Emitted when the fees owed to a position are added to its liquidity
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct CompoundFeesEvent {
+    /// The position the fees were compounded for
+    #[index]
+    pub position_nft_mint: Pubkey,
+
+    /// The liquidity added to the position
+    pub liquidity: u128,
+
+    /// The fees of token_0 moved into the liquidity, the rest stays owed
+    pub amount_0: u64,
+
+    /// The fees of token_1 moved into the liquidity, the rest stays owed
+    pub amount_1: u64,
+}
+
 /// Emitted when liquidity is decreased.
 #[event]
 #[cfg_attr(feature = "client", derive(Debug))]
@@ -192,6 +330,30 @@ pub struct DecreaseLiquidityEvent {
     pub transfer_fee_1: u64,
 }
 
+/// Emitted when liquidity is migrated from a position to a position of another fee tier
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct MigratePositionEvent {
+    /// The position the liquidity was withdrawn from
+    #[index]
+    pub source_position_nft_mint: Pubkey,
+
+    /// The position the liquidity was added to
+    pub target_position_nft_mint: Pubkey,
+
+    /// The liquidity withdrawn from the source position
+    pub source_liquidity: u128,
+
+    /// The liquidity added to the target position
+    pub target_liquidity: u128,
+
+    /// The max amount of token_0 the target position could take from the withdrawal
+    pub amount_0: u64,
+
+    /// The max amount of token_1 the target position could take from the withdrawal
+    pub amount_1: u64,
+}
+
 /// Emitted when liquidity decreased or increase.
 #[event]
 #[cfg_attr(feature = "client", derive(Debug))]
@@ -237,6 +399,18 @@ pub struct CollectPersonalFeeEvent {
     pub amount_1: u64,
 }
 
+/// Emitted when the rewards of a position are collected without changing its liquidity
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct CollectRewardEvent {
+    /// The ID of the token for which rewards were collected
+    #[index]
+    pub position_nft_mint: Pubkey,
+
+    /// The amount of each reward transferred
+    pub reward_amounts: [u64; REWARD_NUM],
+}
+
 /// Emitted when Reward are updated for a pool
 #[event]
 #[cfg_attr(feature = "client", derive(Debug))]
@@ -244,3 +418,186 @@ pub struct UpdateRewardInfosEvent {
     /// Reward info
     pub reward_growth_global_x64: [u128; REWARD_NUM],
 }
+
+#[cfg(test)]
+mod personal_position_test {
+    use super::*;
+
+    #[test]
+    fn layout_len_unchanged_test() {
+        // the timestamps, nonce and fee recipient are carved from the padding, existing accounts
+        // keep their size
+        assert_eq!(
+            std::mem::size_of::<PersonalPositionState>() + 8,
+            PersonalPositionState::LEN
+        );
+    }
+
+    #[test]
+    fn borsh_layout_load_test() {
+        // an account written with the borsh layout before the zero copy one
+        let nft_mint = Pubkey::new_unique();
+        let pool_id = Pubkey::new_unique();
+        let fee_recipient = Pubkey::new_unique();
+        let fee_recipient_setter = [9u8; 8];
+        let mut data = PersonalPositionState::discriminator().to_vec();
+        data.extend_from_slice(
+            &(
+                [7u8],
+                nft_mint,
+                pool_id,
+                -120i32,
+                240i32,
+                5_000u128,
+                1u128 << 64,
+                2u128 << 64,
+                11u64,
+                13u64,
+            )
+                .try_to_vec()
+                .unwrap(),
+        );
+        for i in 0..REWARD_NUM {
+            data.extend_from_slice(&((i as u128) << 64, i as u64 + 1).try_to_vec().unwrap());
+        }
+        data.extend_from_slice(
+            &(
+                17u64,
+                1_000u32,
+                1_500u32,
+                3u64,
+                fee_recipient,
+                fee_recipient_setter,
+            )
+                .try_to_vec()
+                .unwrap(),
+        );
+        assert_eq!(data.len(), PersonalPositionState::LEN);
+
+        let position = PersonalPositionState::try_deserialize(&mut data.as_slice()).unwrap();
+        let (tick_lower_index, tick_upper_index, liquidity) = (
+            position.tick_lower_index,
+            position.tick_upper_index,
+            position.liquidity,
+        );
+        assert_eq!(position.bump, [7]);
+        assert_eq!(position.nft_mint, nft_mint);
+        assert_eq!(position.pool_id, pool_id);
+        assert_eq!((tick_lower_index, tick_upper_index), (-120, 240));
+        assert_eq!(liquidity, 5_000);
+        let (fee_growth_inside_0_last_x64, fee_growth_inside_1_last_x64) = (
+            position.fee_growth_inside_0_last_x64,
+            position.fee_growth_inside_1_last_x64,
+        );
+        assert_eq!(fee_growth_inside_0_last_x64, 1 << 64);
+        assert_eq!(fee_growth_inside_1_last_x64, 2 << 64);
+        let (token_fees_owed_0, token_fees_owed_1) =
+            (position.token_fees_owed_0, position.token_fees_owed_1);
+        assert_eq!((token_fees_owed_0, token_fees_owed_1), (11, 13));
+        for i in 0..REWARD_NUM {
+            let reward_info = position.reward_infos[i];
+            let growth_inside_last_x64 = reward_info.growth_inside_last_x64;
+            let reward_amount_owed = reward_info.reward_amount_owed;
+            assert_eq!(growth_inside_last_x64, (i as u128) << 64);
+            assert_eq!(reward_amount_owed, i as u64 + 1);
+        }
+        let (recent_epoch, created_at, last_updated_at, relay_nonce) = (
+            position.recent_epoch,
+            position.created_at,
+            position.last_updated_at,
+            position.relay_nonce,
+        );
+        assert_eq!(
+            (recent_epoch, created_at, last_updated_at, relay_nonce),
+            (17, 1_000, 1_500, 3)
+        );
+        assert_eq!(position.fee_recipient, fee_recipient);
+        assert_eq!(position.fee_recipient_setter, fee_recipient_setter);
+    }
+
+    #[test]
+    fn is_empty_test() {
+        let mut position = PersonalPositionState::default();
+        assert!(position.is_empty());
+        position.liquidity = 1;
+        assert!(!position.is_empty());
+        position.liquidity = 0;
+        position.token_fees_owed_1 = 1;
+        assert!(!position.is_empty());
+        position.token_fees_owed_1 = 0;
+        // a reward left to collect keeps the position open
+        position.reward_infos[2].reward_amount_owed = 1;
+        assert!(!position.is_empty());
+        position.reward_infos[2].reward_amount_owed = 0;
+        position.reward_infos[2].growth_inside_last_x64 = 1 << 64;
+        assert!(position.is_empty());
+    }
+
+    #[test]
+    fn record_action_test() {
+        // existing accounts read the timestamps as zero
+        let mut position = PersonalPositionState::default();
+        assert_eq!(position.age(1000), None);
+
+        position.record_action(1000);
+        let (created_at, last_updated_at) = (position.created_at, position.last_updated_at);
+        assert_eq!((created_at, last_updated_at), (1000, 1000));
+
+        position.record_action(1500);
+        let (created_at, last_updated_at) = (position.created_at, position.last_updated_at);
+        assert_eq!((created_at, last_updated_at), (1000, 1500));
+        assert_eq!(position.age(2000), Some(1000));
+    }
+
+    #[test]
+    fn fee_recipient_test() {
+        let nft_owner = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let mut position = PersonalPositionState::default();
+        // any account is accepted until a recipient is set
+        assert!(position.can_pay_fees_to(&nft_owner, &nft_owner));
+        assert_eq!(position.payout_owner(&nft_owner), nft_owner);
+
+        position.set_fee_recipient(treasury, &nft_owner);
+        assert_eq!(position.active_fee_recipient(&nft_owner), Some(treasury));
+        assert!(position.can_pay_fees_to(&nft_owner, &treasury));
+        assert!(!position.can_pay_fees_to(&nft_owner, &nft_owner));
+        assert_eq!(position.payout_owner(&nft_owner), treasury);
+
+        position.clear_fee_recipient();
+        assert_eq!(position.active_fee_recipient(&nft_owner), None);
+        assert!(position.can_pay_fees_to(&nft_owner, &nft_owner));
+    }
+
+    #[test]
+    fn fee_recipient_after_token_transfer_test() {
+        let seller = Pubkey::new_unique();
+        let buyer = Pubkey::new_unique();
+        let treasury = Pubkey::new_unique();
+        let mut position = PersonalPositionState::default();
+        position.set_fee_recipient(treasury, &seller);
+
+        // the nft was sent with a plain token transfer, the recipient of the seller is ignored
+        // for the buyer
+        assert_eq!(position.active_fee_recipient(&buyer), None);
+        assert!(position.can_pay_fees_to(&buyer, &buyer));
+        assert_eq!(position.payout_owner(&buyer), buyer);
+
+        // until the buyer sets its own
+        position.set_fee_recipient(treasury, &buyer);
+        assert!(!position.can_pay_fees_to(&buyer, &buyer));
+        assert_eq!(position.active_fee_recipient(&seller), None);
+    }
+
+    #[test]
+    fn use_relay_nonce_test() {
+        let mut position = PersonalPositionState::default();
+        assert!(position.use_relay_nonce(1).is_err());
+        position.use_relay_nonce(0).unwrap();
+        let relay_nonce = position.relay_nonce;
+        assert_eq!(relay_nonce, 1);
+        // the consumed nonce can't be replayed
+        assert!(position.use_relay_nonce(0).is_err());
+        position.use_relay_nonce(1).unwrap();
+    }
+}
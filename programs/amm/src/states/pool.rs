@@ -20,6 +20,8 @@ pub const POOL_REWARD_VAULT_SEED: &str = "pool_reward_vault";
 pub const POOL_TICK_ARRAY_BITMAP_SEED: &str = "pool_tick_array_bitmap_extension";
 // Number of rewards Token
 pub const REWARD_NUM: usize = 3;
+/// The maximum share of the protocol fees a fee rebate reward can take at every reward update
+pub const MAX_REWARD_REBATE_RATE: u32 = FEE_RATE_DENOMINATOR_VALUE / 2;
 
 #[cfg(feature = "paramset")]
 pub mod reward_period_limit {
@@ -34,12 +36,15 @@ pub mod reward_period_limit {
     pub const INCREASE_EMISSIONES_PERIOD: u64 = 72 * 60 * 60;
 }
 
+#[derive(PartialEq, Eq)]
 pub enum PoolStatusBitIndex {
     OpenPositionOrIncreaseLiquidity,
     DecreaseLiquidity,
     CollectFee,
     CollectReward,
     Swap,
+    /// Disabled until the admin sets its bit, unlike the other operations
+    Flash,
 }
 
 #[derive(PartialEq, Eq)]
@@ -112,8 +117,11 @@ pub struct PoolState {
     /// bit3, 1: disable collect reward, 0: normal
     /// bit4, 1: disable swap, 0: normal
     pub status: u8,
+    /// 1 while an instruction mutating the pool runs, rejects nested calls into the pool from the
+    /// CPIs of that instruction
+    pub reentrancy_lock: u8,
     /// Leave blank for future use
-    pub padding: [u8; 7],
+    pub padding: [u8; 6],
 
     pub reward_infos: [RewardInfo; REWARD_NUM],
 
@@ -136,8 +144,28 @@ pub struct PoolState {
     // account recent update epoch
     pub recent_epoch: u64,
 
+    /// The share of the protocol fees a fee rebate reward can take at every reward update, as a
+    /// fraction of FEE_RATE_DENOMINATOR_VALUE. Zero for rewards funded through their own vault.
+    pub reward_rebate_rates: [u32; REWARD_NUM],
+    pub padding5: u32,
+
+    /// The emissions of every reward, as token amounts in Q64.64, the truncation of
+    /// reward_growth_global_x64 left undistributed. Added to the next update.
+    pub reward_growth_remainders_x64: [u128; REWARD_NUM],
+    /// The fractional part, in Q64.64, of the emissions of every reward not yet counted in
+    /// reward_total_emissioned
+    pub reward_emission_remainders_x64: [u64; REWARD_NUM],
+
+    /// The number of swaps of token_0 for token_1, and of token_1 for token_0. With the swap in
+    /// and out amounts they split the taker flow of the pool by direction.
+    pub swap_count_0_for_1: u64,
+    pub swap_count_1_for_0: u64,
+
+    /// The minimum input amount of a swap, including the fee. Filters dust swaps, 0 to disable.
+    pub min_swap_amount_in: u64,
+
     // Unused bytes for future upgrades.
-    pub padding1: [u64; 24],
+    pub padding1: [u64; 10],
     pub padding2: [u64; 32],
 }
 
@@ -219,7 +247,8 @@ impl PoolState {
         self.swap_in_amount_token_1 = 0;
         self.swap_out_amount_token_0 = 0;
         self.status = 0;
-        self.padding = [0; 7];
+        self.reentrancy_lock = 0;
+        self.padding = [0; 6];
         self.tick_array_bitmap = [0; 16];
         self.total_fees_token_0 = 0;
         self.total_fees_claimed_token_0 = 0;
@@ -229,7 +258,14 @@ impl PoolState {
         self.fund_fees_token_1 = 0;
         self.open_time = open_time;
         self.recent_epoch = get_recent_epoch()?;
-        self.padding1 = [0; 24];
+        self.reward_rebate_rates = [0; REWARD_NUM];
+        self.padding5 = 0;
+        self.reward_growth_remainders_x64 = [0; REWARD_NUM];
+        self.reward_emission_remainders_x64 = [0; REWARD_NUM];
+        self.swap_count_0_for_1 = 0;
+        self.swap_count_1_for_0 = 0;
+        self.min_swap_amount_in = 0;
+        self.padding1 = [0; 10];
         self.padding2 = [0; 32];
         self.observation_key = observation_state_key;
 
@@ -308,6 +344,93 @@ impl PoolState {
         Ok(())
     }
 
+    /// Initialize a reward paid in token_0 or token_1 out of the protocol fees of the pool instead
+    /// of a funded reward vault, the pool vault of the mint is the reward vault.
+    pub fn initialize_fee_rebate_reward(
+        &mut self,
+        open_time: u64,
+        end_time: u64,
+        reward_per_second_x64: u128,
+        token_mint: &Pubkey,
+        rebate_rate: u32,
+        authority: &Pubkey,
+    ) -> Result<usize> {
+        require!(
+            rebate_rate > 0 && rebate_rate <= MAX_REWARD_REBATE_RATE,
+            ErrorCode::InvalidRewardRebateRate
+        );
+        let token_vault = if *token_mint == self.token_mint_0 {
+            self.token_vault_0
+        } else if *token_mint == self.token_mint_1 {
+            self.token_vault_1
+        } else {
+            return err!(ErrorCode::ExceptPoolVaultMint);
+        };
+
+        let reward_infos = self.reward_infos;
+        let lowest_index = match reward_infos.iter().position(|r| !r.initialized()) {
+            Some(lowest_index) => lowest_index,
+            None => return Err(ErrorCode::FullRewardInfo.into()),
+        };
+        require!(
+            !reward_infos
+                .iter()
+                .any(|reward_info| reward_info.token_mint == *token_mint),
+            ErrorCode::RewardTokenAlreadyInUse
+        );
+
+        self.reward_infos[lowest_index].last_update_time = open_time;
+        self.reward_infos[lowest_index].open_time = open_time;
+        self.reward_infos[lowest_index].end_time = end_time;
+        self.reward_infos[lowest_index].emissions_per_second_x64 = reward_per_second_x64;
+        self.reward_infos[lowest_index].token_mint = *token_mint;
+        self.reward_infos[lowest_index].token_vault = token_vault;
+        self.reward_infos[lowest_index].authority = *authority;
+        self.reward_rebate_rates[lowest_index] = rebate_rate;
+        self.recent_epoch = get_recent_epoch()?;
+        Ok(lowest_index)
+    }
+
+    /// Returns true if the reward is paid out of the protocol fees of the pool
+    pub fn is_fee_rebate_reward(&self, index: usize) -> bool {
+        self.reward_rebate_rates[index] != 0
+    }
+
+    /// Move the emission of a fee rebate reward over `time_delta` out of the protocol fees of the
+    /// reward token, bounded by the rebate rate of the protocol fees. Returns the amount moved.
+    fn take_fee_rebate(
+        &mut self,
+        index: usize,
+        token_mint: &Pubkey,
+        time_delta: u64,
+        emissions_per_second_x64: u128,
+    ) -> u64 {
+        let emission = U128::from(time_delta)
+            .mul_div_floor(
+                U128::from(emissions_per_second_x64),
+                U128::from(fixed_point_64::Q64),
+            )
+            .unwrap();
+        let protocol_fees = if *token_mint == self.token_mint_0 {
+            self.protocol_fees_token_0
+        } else {
+            self.protocol_fees_token_1
+        };
+        let rebate_bound = U128::from(protocol_fees)
+            .mul_div_floor(
+                U128::from(self.reward_rebate_rates[index]),
+                U128::from(FEE_RATE_DENOMINATOR_VALUE),
+            )
+            .unwrap();
+        let rebate_amount = emission.min(rebate_bound).as_u64();
+        if *token_mint == self.token_mint_0 {
+            self.protocol_fees_token_0 = protocol_fees.checked_sub(rebate_amount).unwrap();
+        } else {
+            self.protocol_fees_token_1 = protocol_fees.checked_sub(rebate_amount).unwrap();
+        }
+        rebate_amount
+    }
+
     // Calculates the next global reward growth variables based on the given timestamp.
     // The provided timestamp must be greater than or equal to the last updated timestamp.
     pub fn update_reward_infos(&mut self, curr_timestamp: u64) -> Result<[RewardInfo; REWARD_NUM]> {
@@ -321,6 +444,9 @@ impl PoolState {
             if !reward_info.initialized() {
                 continue;
             }
+            if reward_info.consolidated() {
+                continue;
+            }
             if curr_timestamp <= reward_info.open_time {
                 continue;
             }
@@ -332,12 +458,30 @@ impl PoolState {
                     .checked_sub(reward_info.last_update_time)
                     .unwrap();
 
-                let reward_growth_delta = U256::from(time_delta)
-                    .mul_div_floor(
-                        U256::from(reward_info.emissions_per_second_x64),
-                        U256::from(self.liquidity),
-                    )
-                    .unwrap();
+                // the emissions as token amounts in Q64.64
+                let emissions_x64 = if self.is_fee_rebate_reward(i) {
+                    // the rebate is only emitted as far as the protocol fees cover it
+                    let rebate_amount = self.take_fee_rebate(
+                        i,
+                        &reward_info.token_mint,
+                        time_delta,
+                        reward_info.emissions_per_second_x64,
+                    );
+                    U256::from(rebate_amount) << fixed_point_64::RESOLUTION
+                } else {
+                    U256::from(time_delta) * U256::from(reward_info.emissions_per_second_x64)
+                };
+                // carry what the truncation of the growth per unit of liquidity and of the
+                // emitted amount left behind, so the totals follow the schedule exactly
+                let distributable_x64 =
+                    emissions_x64 + U256::from(self.reward_growth_remainders_x64[i]);
+                let reward_growth_delta = distributable_x64 / U256::from(self.liquidity);
+                self.reward_growth_remainders_x64[i] =
+                    (distributable_x64 % U256::from(self.liquidity)).as_u128();
+                let emissioned_x64 =
+                    emissions_x64 + U256::from(self.reward_emission_remainders_x64[i]);
+                let reward_emissioned = (emissioned_x64 >> fixed_point_64::RESOLUTION).as_u64();
+                self.reward_emission_remainders_x64[i] = emissioned_x64.low_u64();
 
                 reward_info.reward_growth_global_x64 = reward_info
                     .reward_growth_global_x64
@@ -346,15 +490,7 @@ impl PoolState {
 
                 reward_info.reward_total_emissioned = reward_info
                     .reward_total_emissioned
-                    .checked_add(
-                        U128::from(time_delta)
-                            .mul_div_ceil(
-                                U128::from(reward_info.emissions_per_second_x64),
-                                U128::from(fixed_point_64::Q64),
-                            )
-                            .unwrap()
-                            .as_u64(),
-                    )
+                    .checked_add(reward_emissioned)
                     .unwrap();
                 #[cfg(feature = "enable-log")]
                 msg!(
@@ -454,7 +590,7 @@ impl PoolState {
         let (is_initialized, start_index) =
             if self.is_overflow_default_tickarray_bitmap(vec![self.tick_current]) {
                 tickarray_bitmap_extension
-                    .unwrap()
+                    .ok_or(ErrorCode::MissingTickArrayBitmapExtensionAccount)?
                     .check_tick_array_is_initialized(
                         TickArrayState::get_array_start_index(self.tick_current, self.tick_spacing),
                         self.tick_spacing,
@@ -531,9 +667,56 @@ impl PoolState {
         self.status = status
     }
 
+    /// Lock the pool for the running instruction, fails if another instruction on the call stack
+    /// holds the lock
+    pub fn lock_reentrancy(&mut self) -> Result<()> {
+        self.check_unlocked()?;
+        self.reentrancy_lock = 1;
+        Ok(())
+    }
+
+    /// Fail while another instruction on the call stack holds the lock, for the instructions that
+    /// move vault funds or reward and fee accounting without calling out of the program
+    pub fn check_unlocked(&self) -> Result<()> {
+        require_eq!(self.reentrancy_lock, 0, ErrorCode::PoolReentrancy);
+        Ok(())
+    }
+
+    pub fn unlock_reentrancy(&mut self) {
+        self.reentrancy_lock = 0;
+    }
+
+    /// The direction of a swap paying into `input_vault` and out of `output_vault`, read from the
+    /// vaults of the pool rather than from the mints of the user token accounts
+    pub fn swap_direction(&self, input_vault: &Pubkey, output_vault: &Pubkey) -> Result<bool> {
+        if *input_vault == self.token_vault_0 && *output_vault == self.token_vault_1 {
+            Ok(true)
+        } else if *input_vault == self.token_vault_1 && *output_vault == self.token_vault_0 {
+            Ok(false)
+        } else {
+            err!(ErrorCode::InvalidInputPoolVault)
+        }
+    }
+
+    /// The trade fees of token_0 and token_1 accumulated by the pool, the liquidity provider,
+    /// protocol and fund parts together. Only their difference over an instruction is meaningful,
+    /// the protocol and fund fees are reset when collected
+    pub fn trade_fee_accumulators(&self) -> (u64, u64) {
+        (
+            self.total_fees_token_0
+                .wrapping_add(self.protocol_fees_token_0)
+                .wrapping_add(self.fund_fees_token_0),
+            self.total_fees_token_1
+                .wrapping_add(self.protocol_fees_token_1)
+                .wrapping_add(self.fund_fees_token_1),
+        )
+    }
+
     pub fn set_status_by_bit(&mut self, bit: PoolStatusBitIndex, flag: PoolStatusBitFlag) {
+        // the flash bit is set to enable it, the others to disable their operation
+        let set = (flag == PoolStatusBitFlag::Disable) != (bit == PoolStatusBitIndex::Flash);
         let s = u8::from(1) << (bit as u8);
-        if flag == PoolStatusBitFlag::Disable {
+        if set {
             self.status = self.status.bitor(s);
         } else {
             let m = u8::from(255).bitxor(s);
@@ -543,8 +726,9 @@ impl PoolState {
 
     /// Get status by bit, if it is `noraml` status, return true
     pub fn get_status_by_bit(&self, bit: PoolStatusBitIndex) -> bool {
+        let opt_in = bit == PoolStatusBitIndex::Flash;
         let status = u8::from(1) << (bit as u8);
-        self.status.bitand(status) == 0
+        (self.status.bitand(status) == 0) != opt_in
     }
 
     pub fn is_overflow_default_tickarray_bitmap(&self, tick_indexs: Vec<i32>) -> bool {
@@ -594,6 +778,8 @@ pub enum RewardState {
     Opening,
     /// Reward end, reward time expire or
     Ended,
+    /// Reward ended and the owed rewards are claimed against a merkle snapshot
+    Consolidated,
 }
 
 #[zero_copy(unsafe)]
@@ -642,6 +828,11 @@ impl RewardInfo {
         self.token_mint.ne(&Pubkey::default())
     }
 
+    /// Returns true if the owed rewards have been moved to a merkle snapshot
+    pub fn consolidated(&self) -> bool {
+        self.reward_state == RewardState::Consolidated as u8
+    }
+
     pub fn get_reward_growths(reward_infos: &[RewardInfo; REWARD_NUM]) -> [u128; REWARD_NUM] {
         let mut reward_growths = [0u128; REWARD_NUM];
         for i in 0..REWARD_NUM {
@@ -682,6 +873,36 @@ pub struct PoolCreatedEvent {
     pub token_vault_1: Pubkey,
 }
 
+/// Emitted when the admin changes the status bits of a pool
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct PoolStatusChangedEvent {
+    #[index]
+    pub pool_state: Pubkey,
+
+    /// The status bits before the change, a set bit disables its operation
+    pub old_status: u8,
+
+    /// The status bits after the change
+    pub new_status: u8,
+}
+
+/// Emitted when a running reward is topped up and its end time extended
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct TopUpRewardEvent {
+    #[index]
+    pub pool_state: Pubkey,
+
+    pub reward_index: u8,
+
+    /// The amount deposited to the reward vault, without the transfer fee
+    pub amount: u64,
+
+    /// The end time of the reward after the top up
+    pub end_time: u64,
+}
+
 /// Emitted when the collected protocol fees are withdrawn by the factory owner
 #[event]
 #[cfg_attr(feature = "client", derive(Debug))]
@@ -737,7 +958,8 @@ pub struct SwapEvent {
     /// The transfer fee charged by the withheld_amount of the token_1
     pub transfer_fee_1: u64,
 
-    /// if true, amount_0 is negtive and amount_1 is positive
+    /// if true, amount_0 is negtive and amount_1 is positive, derived from the vaults the swap
+    /// paid into and out of
     pub zero_for_one: bool,
 
     /// The sqrt(price) of the pool after the swap, as a Q64.64
@@ -750,6 +972,31 @@ pub struct SwapEvent {
     pub tick: i32,
 }
 
+/// Emitted when tokens are lent out of the vaults and repaid in a flash
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct FlashEvent {
+    /// The pool lending the tokens
+    #[index]
+    pub pool_state: Pubkey,
+
+    /// The borrower
+    #[index]
+    pub sender: Pubkey,
+
+    /// The token accounts that received the borrowed tokens
+    pub recipient_token_account_0: Pubkey,
+    pub recipient_token_account_1: Pubkey,
+
+    /// The borrowed amounts
+    pub amount_0: u64,
+    pub amount_1: u64,
+
+    /// The amounts paid back on top of the borrowed ones, shared as fees
+    pub paid_0: u64,
+    pub paid_1: u64,
+}
+
 /// Emitted pool liquidity change when increase and decrease liquidity
 #[event]
 #[cfg_attr(feature = "client", derive(Debug))]
@@ -774,6 +1021,33 @@ pub struct LiquidityChangeEvent {
     pub liquidity_after: u128,
 }
 
+/// Emitted when a reward paid out of the protocol fees of the pool is initialized
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct InitializeFeeRebateRewardEvent {
+    /// The pool of the reward
+    #[index]
+    pub pool_state: Pubkey,
+
+    /// The index of the reward in the pool
+    pub reward_index: u8,
+
+    /// The reward token, token_mint_0 or token_mint_1 of the pool
+    pub token_mint: Pubkey,
+
+    /// Reward open time
+    pub open_time: u64,
+
+    /// Reward end time
+    pub end_time: u64,
+
+    /// Token reward per second are earned per unit of liquidity
+    pub emissions_per_second_x64: u128,
+
+    /// The share of the protocol fees the rebate can take at every reward update
+    pub rebate_rate: u32,
+}
+
 // /// Emitted when price move in a swap step
 // #[event]
 // #[cfg_attr(feature = "client", derive(Debug))]
@@ -1000,6 +1274,80 @@ pub mod pool_test {
                 false
             );
         }
+
+        #[test]
+        fn flash_status_opt_in_test() {
+            // a pool with every operation of the other bits enabled doesn't lend
+            let mut pool_state = PoolState::default();
+            assert_eq!(
+                pool_state.get_status_by_bit(PoolStatusBitIndex::Flash),
+                false
+            );
+
+            pool_state.set_status_by_bit(PoolStatusBitIndex::Flash, PoolStatusBitFlag::Enable);
+            assert_eq!(pool_state.status, 0b100000);
+            assert_eq!(
+                pool_state.get_status_by_bit(PoolStatusBitIndex::Flash),
+                true
+            );
+            assert_eq!(pool_state.get_status_by_bit(PoolStatusBitIndex::Swap), true);
+
+            pool_state.set_status_by_bit(PoolStatusBitIndex::Flash, PoolStatusBitFlag::Disable);
+            assert_eq!(pool_state.status, 0);
+            assert_eq!(
+                pool_state.get_status_by_bit(PoolStatusBitIndex::Flash),
+                false
+            );
+        }
+
+        #[test]
+        fn reentrancy_lock_test() {
+            let mut pool_state = PoolState::default();
+            pool_state.lock_reentrancy().unwrap();
+            // a nested call finds the pool locked
+            assert!(pool_state.lock_reentrancy().is_err());
+            // the status bits are independent of the lock
+            assert_eq!(pool_state.get_status_by_bit(PoolStatusBitIndex::Swap), true);
+
+            pool_state.unlock_reentrancy();
+            pool_state.lock_reentrancy().unwrap();
+        }
+
+        #[test]
+        fn check_unlocked_test() {
+            let mut pool_state = PoolState::default();
+            pool_state.check_unlocked().unwrap();
+            pool_state.lock_reentrancy().unwrap();
+            assert_eq!(
+                pool_state.check_unlocked().unwrap_err(),
+                ErrorCode::PoolReentrancy.into()
+            );
+            // the check doesn't take the lock
+            pool_state.unlock_reentrancy();
+            pool_state.check_unlocked().unwrap();
+            assert_eq!(pool_state.reentrancy_lock, 0);
+        }
+
+        #[test]
+        fn swap_direction_test() {
+            let mut pool_state = PoolState::default();
+            pool_state.token_vault_0 = Pubkey::new_unique();
+            pool_state.token_vault_1 = Pubkey::new_unique();
+            let (vault_0, vault_1) = (pool_state.token_vault_0, pool_state.token_vault_1);
+
+            assert_eq!(pool_state.swap_direction(&vault_0, &vault_1).unwrap(), true);
+            assert_eq!(
+                pool_state.swap_direction(&vault_1, &vault_0).unwrap(),
+                false
+            );
+            // the same vault on both sides
+            assert!(pool_state.swap_direction(&vault_0, &vault_0).is_err());
+            assert!(pool_state.swap_direction(&vault_1, &vault_1).is_err());
+            // a vault of another pool
+            assert!(pool_state
+                .swap_direction(&Pubkey::new_unique(), &vault_1)
+                .is_err());
+        }
     }
 
     mod update_reward_infos_test {
@@ -1057,6 +1405,160 @@ pub mod pool_test {
                 1666069200
             );
         }
+
+        #[test]
+        fn fee_rebate_reward_test() {
+            let pool_state = &mut PoolState::default();
+            pool_state.token_mint_0 = Pubkey::new_unique();
+            pool_state.token_mint_1 = Pubkey::new_unique();
+            pool_state.token_vault_0 = Pubkey::new_unique();
+            pool_state.token_vault_1 = Pubkey::new_unique();
+            let token_mint_0 = pool_state.token_mint_0;
+            let emissions_per_second_x64 = 10u128 << 64;
+
+            // the rebate is paid in a pool token
+            assert_eq!(
+                pool_state
+                    .initialize_fee_rebate_reward(
+                        1665982800,
+                        1666069200,
+                        emissions_per_second_x64,
+                        &Pubkey::new_unique(),
+                        MAX_REWARD_REBATE_RATE,
+                        &Pubkey::default(),
+                    )
+                    .unwrap_err(),
+                ErrorCode::ExceptPoolVaultMint.into()
+            );
+            assert_eq!(
+                pool_state
+                    .initialize_fee_rebate_reward(
+                        1665982800,
+                        1666069200,
+                        emissions_per_second_x64,
+                        &token_mint_0,
+                        MAX_REWARD_REBATE_RATE + 1,
+                        &Pubkey::default(),
+                    )
+                    .unwrap_err(),
+                ErrorCode::InvalidRewardRebateRate.into()
+            );
+
+            let reward_index = pool_state
+                .initialize_fee_rebate_reward(
+                    1665982800,
+                    1666069200,
+                    emissions_per_second_x64,
+                    &token_mint_0,
+                    MAX_REWARD_REBATE_RATE,
+                    &Pubkey::default(),
+                )
+                .unwrap();
+            assert_eq!(reward_index, 0);
+            assert!(pool_state.is_fee_rebate_reward(reward_index));
+            assert_eq!(
+                pool_state.reward_infos[reward_index].token_vault,
+                pool_state.token_vault_0
+            );
+
+            pool_state.liquidity = 100;
+            pool_state.protocol_fees_token_0 = 1000;
+
+            // 100 tokens emitted, covered by half of the protocol fees
+            let updated_reward_infos = pool_state.update_reward_infos(1665982810).unwrap();
+            assert_eq!(
+                identity(updated_reward_infos[0].reward_total_emissioned),
+                100
+            );
+            assert_eq!(identity(pool_state.protocol_fees_token_0), 900);
+
+            // 1000 tokens due, bounded by half of the protocol fees
+            let updated_reward_infos = pool_state.update_reward_infos(1665982910).unwrap();
+            assert_eq!(
+                identity(updated_reward_infos[0].reward_total_emissioned),
+                550
+            );
+            assert_eq!(identity(pool_state.protocol_fees_token_0), 450);
+            assert_eq!(
+                identity(updated_reward_infos[0].reward_growth_global_x64),
+                11 << 63
+            );
+            assert_eq!(identity(pool_state.protocol_fees_token_1), 0);
+        }
+
+        #[test]
+        fn reward_remainder_accumulation_test() {
+            let pool_state = &mut PoolState::default();
+            let operation_state = OperationState {
+                bump: 0,
+                operation_owners: [Pubkey::default(); OPERATION_SIZE_USIZE],
+                whitelist_mints: [Pubkey::default(); WHITE_MINT_SIZE_USIZE],
+            };
+            let open_time = 1665982800;
+            let duration = 30 * 24 * 60 * 60;
+            // a third of a token per second, neither the emitted amount nor the growth per unit
+            // of liquidity is a whole number at any update
+            let emissions_per_second_x64 = (1u128 << 64) / 3 + 7;
+            pool_state
+                .initialize_reward(
+                    open_time,
+                    open_time + duration,
+                    emissions_per_second_x64,
+                    &Pubkey::new_unique(),
+                    &Pubkey::default(),
+                    &Pubkey::default(),
+                    &operation_state,
+                )
+                .unwrap();
+            pool_state.liquidity = 7_000_003;
+
+            // updated every few seconds for the whole campaign
+            let mut timestamp = open_time;
+            while timestamp < open_time + duration {
+                timestamp += 1 + timestamp % 5;
+                pool_state.update_reward_infos(timestamp).unwrap();
+            }
+            let reward_info = pool_state.reward_infos[0];
+            let emissions_x64 = U256::from(duration) * U256::from(emissions_per_second_x64);
+            assert_eq!(
+                identity(reward_info.reward_total_emissioned),
+                (emissions_x64 >> fixed_point_64::RESOLUTION).as_u64()
+            );
+            assert_eq!(
+                identity(reward_info.reward_growth_global_x64),
+                (emissions_x64 / U256::from(pool_state.liquidity)).as_u128()
+            );
+
+            // the remainder follows the emissions across a change of the liquidity
+            let mut pool_state = PoolState::default();
+            pool_state
+                .initialize_reward(
+                    open_time,
+                    open_time + duration,
+                    emissions_per_second_x64,
+                    &Pubkey::new_unique(),
+                    &Pubkey::default(),
+                    &Pubkey::default(),
+                    &operation_state,
+                )
+                .unwrap();
+            let mut distributed_x64 = U256::default();
+            let mut timestamp = open_time;
+            for (step, liquidity) in [3u128, 1_000_000_007, 11, 1 << 70].into_iter().enumerate() {
+                pool_state.liquidity = liquidity;
+                let growth_before = pool_state.reward_infos[0].reward_growth_global_x64;
+                timestamp += 1_000 + step as u64;
+                pool_state.update_reward_infos(timestamp).unwrap();
+                let growth_after = pool_state.reward_infos[0].reward_growth_global_x64;
+                distributed_x64 = distributed_x64
+                    + U256::from(growth_after - growth_before) * U256::from(liquidity);
+            }
+            let remainder_x64 = pool_state.reward_growth_remainders_x64[0];
+            assert_eq!(
+                distributed_x64 + U256::from(remainder_x64),
+                U256::from(timestamp - open_time) * U256::from(emissions_per_second_x64)
+            );
+        }
     }
 
     mod use_tickarray_bitmap_extension_test {
@@ -1154,6 +1656,43 @@ pub mod pool_test {
             assert!(start_index == tick_spacing * TICK_ARRAY_SIZE * 512);
         }
 
+        #[test]
+        fn get_first_initialized_tick_array_without_liquidity_test() {
+            let tick_spacing = 1;
+            let tick_current = tick_spacing * TICK_ARRAY_SIZE * 511 - 1;
+
+            let pool_state_refcel = build_pool(
+                tick_current,
+                tick_spacing.try_into().unwrap(),
+                tick_math::get_sqrt_price_at_tick(tick_current).unwrap(),
+                0,
+            );
+            let mut pool_state = pool_state_refcel.borrow_mut();
+
+            // no tick array is initialized in either direction
+            let tick_array_bitmap_extension = Some(TickArrayBitmapExtension::default());
+            for zero_for_one in [true, false] {
+                assert_eq!(
+                    pool_state
+                        .get_first_initialized_tick_array(
+                            &tick_array_bitmap_extension,
+                            zero_for_one
+                        )
+                        .unwrap_err(),
+                    ErrorCode::InsufficientLiquidityForDirection.into()
+                );
+            }
+
+            // the current tick array is tracked by the extension which is not provided
+            pool_state.tick_current = tick_spacing * TICK_ARRAY_SIZE * 512;
+            assert_eq!(
+                pool_state
+                    .get_first_initialized_tick_array(&None, true)
+                    .unwrap_err(),
+                ErrorCode::MissingTickArrayBitmapExtensionAccount.into()
+            );
+        }
+
         mod next_initialized_tick_array_start_index_test {
 
             use super::*;
@@ -1593,7 +2132,8 @@ pub mod pool_test {
             let swap_in_amount_token_1: u128 = 0x11223344556677008899aabbccddeeff;
             let swap_out_amount_token_0: u128 = 0x11223344556677880099aabbccddeeff;
             let status: u8 = 0x1b;
-            let padding: [u8; 7] = [0x12, 0x13, 0x14, 0x15, 0x16, 0x17, 0x18];
+            let reentrancy_lock: u8 = 0x11;
+            let padding: [u8; 6] = [0x12, 0x13, 0x14, 0x15, 0x16, 0x17];
             // RewardInfo
             let reward_state: u8 = 0x1c;
             let open_time: u64 = 0x123456789abc0def;
@@ -1664,10 +2204,22 @@ pub mod pool_test {
             let fund_fees_token_1: u64 = 0x1230456789abcdef;
             let pool_open_time: u64 = 0x1203456789abcdef;
             let recent_epoch: u64 = 0x1023456789abcdef;
-            let mut padding1: [u64; 24] = [0u64; 24];
-            let mut padding1_data = [0u8; 8 * 24];
+            let reward_rebate_rates: [u32; REWARD_NUM] = [0x12345678, 0x23456789, 0x3456789a];
+            let padding5: u32 = 0x456789ab;
+            let reward_growth_remainders_x64: [u128; REWARD_NUM] = [
+                0x123456789abcdef0123456789abcdef,
+                0x23456789abcdef0123456789abcdef0,
+                0x3456789abcdef0123456789abcdef01,
+            ];
+            let reward_emission_remainders_x64: [u64; REWARD_NUM] =
+                [0x123456789abcdef0, 0x23456789abcdef01, 0x3456789abcdef012];
+            let swap_count_0_for_1: u64 = 0x2345678901abcdef;
+            let swap_count_1_for_0: u64 = 0x3456789012abcdef;
+            let min_swap_amount_in: u64 = 0x456789012abcdef3;
+            let mut padding1: [u64; 10] = [0u64; 10];
+            let mut padding1_data = [0u8; 8 * 10];
             let mut offset = 0;
-            for i in 0..24 {
+            for i in 0..10 {
                 padding1[i] = u64::MAX - i as u64;
                 padding1_data[offset..offset + 8].copy_from_slice(&padding1[i].to_le_bytes());
                 offset += 8;
@@ -1675,9 +2227,9 @@ pub mod pool_test {
             let mut padding2: [u64; 32] = [0u64; 32];
             let mut padding2_data = [0u8; 8 * 32];
             let mut offset = 0;
-            for i in 24..(24 + 32) {
-                padding2[i - 24] = u64::MAX - i as u64;
-                padding2_data[offset..offset + 8].copy_from_slice(&padding2[i - 24].to_le_bytes());
+            for i in 11..(11 + 32) {
+                padding2[i - 11] = u64::MAX - i as u64;
+                padding2_data[offset..offset + 8].copy_from_slice(&padding2[i - 11].to_le_bytes());
                 offset += 8;
             }
             // serialize original data
@@ -1735,8 +2287,10 @@ pub mod pool_test {
             offset += 16;
             pool_data[offset..offset + 1].copy_from_slice(&status.to_le_bytes());
             offset += 1;
-            pool_data[offset..offset + 7].copy_from_slice(&padding);
-            offset += 7;
+            pool_data[offset..offset + 1].copy_from_slice(&reentrancy_lock.to_le_bytes());
+            offset += 1;
+            pool_data[offset..offset + 6].copy_from_slice(&padding);
+            offset += 6;
             pool_data[offset..offset + RewardInfo::LEN * REWARD_NUM]
                 .copy_from_slice(&reward_info_datas);
             offset += RewardInfo::LEN * REWARD_NUM;
@@ -1760,8 +2314,28 @@ pub mod pool_test {
             offset += 8;
             pool_data[offset..offset + 8].copy_from_slice(&recent_epoch.to_le_bytes());
             offset += 8;
-            pool_data[offset..offset + 8 * 24].copy_from_slice(&padding1_data);
-            offset += 8 * 24;
+            for reward_rebate_rate in reward_rebate_rates {
+                pool_data[offset..offset + 4].copy_from_slice(&reward_rebate_rate.to_le_bytes());
+                offset += 4;
+            }
+            pool_data[offset..offset + 4].copy_from_slice(&padding5.to_le_bytes());
+            offset += 4;
+            for remainder in reward_growth_remainders_x64 {
+                pool_data[offset..offset + 16].copy_from_slice(&remainder.to_le_bytes());
+                offset += 16;
+            }
+            for remainder in reward_emission_remainders_x64 {
+                pool_data[offset..offset + 8].copy_from_slice(&remainder.to_le_bytes());
+                offset += 8;
+            }
+            pool_data[offset..offset + 8].copy_from_slice(&swap_count_0_for_1.to_le_bytes());
+            offset += 8;
+            pool_data[offset..offset + 8].copy_from_slice(&swap_count_1_for_0.to_le_bytes());
+            offset += 8;
+            pool_data[offset..offset + 8].copy_from_slice(&min_swap_amount_in.to_le_bytes());
+            offset += 8;
+            pool_data[offset..offset + 8 * 10].copy_from_slice(&padding1_data);
+            offset += 8 * 10;
             pool_data[offset..offset + 8 * 32].copy_from_slice(&padding2_data);
             offset += 8 * 32;
 
@@ -1824,6 +2398,8 @@ pub mod pool_test {
             assert_eq!(unpack_swap_out_amount_token_0, swap_out_amount_token_0);
             let unpack_status = unpack_data.status;
             assert_eq!(unpack_status, status);
+            let unpack_reentrancy_lock = unpack_data.reentrancy_lock;
+            assert_eq!(unpack_reentrancy_lock, reentrancy_lock);
             let unpack_padding = unpack_data.padding;
             assert_eq!(unpack_padding, padding);
 
@@ -1874,6 +2450,26 @@ pub mod pool_test {
             assert_eq!(unpack_open_time, pool_open_time);
             let unpack_recent_epoch = unpack_data.recent_epoch;
             assert_eq!(unpack_recent_epoch, recent_epoch);
+            let unpack_reward_rebate_rates = unpack_data.reward_rebate_rates;
+            assert_eq!(unpack_reward_rebate_rates, reward_rebate_rates);
+            let unpack_padding5 = unpack_data.padding5;
+            assert_eq!(unpack_padding5, padding5);
+            let unpack_reward_growth_remainders_x64 = unpack_data.reward_growth_remainders_x64;
+            assert_eq!(
+                unpack_reward_growth_remainders_x64,
+                reward_growth_remainders_x64
+            );
+            let unpack_reward_emission_remainders_x64 = unpack_data.reward_emission_remainders_x64;
+            assert_eq!(
+                unpack_reward_emission_remainders_x64,
+                reward_emission_remainders_x64
+            );
+            let unpack_swap_count_0_for_1 = unpack_data.swap_count_0_for_1;
+            assert_eq!(unpack_swap_count_0_for_1, swap_count_0_for_1);
+            let unpack_swap_count_1_for_0 = unpack_data.swap_count_1_for_0;
+            assert_eq!(unpack_swap_count_1_for_0, swap_count_1_for_0);
+            let unpack_min_swap_amount_in = unpack_data.min_swap_amount_in;
+            assert_eq!(unpack_min_swap_amount_in, min_swap_amount_in);
             let unpack_padding1 = unpack_data.padding1;
             assert_eq!(unpack_padding1, padding1);
             let unpack_padding2 = unpack_data.padding2;
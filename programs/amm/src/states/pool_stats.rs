@@ -0,0 +1,245 @@
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+
+pub const POOL_STATS_SEED: &str = "pool_stats";
+/// The seconds one bucket accumulates
+pub const STATS_BUCKET_SECONDS: u64 = 3_600;
+/// The number of buckets, together they cover the rolling window
+pub const STATS_BUCKET_NUM: usize = 24;
+pub const STATS_WINDOW_SECONDS: u64 = STATS_BUCKET_SECONDS * STATS_BUCKET_NUM as u64;
+
+/// The swaps of a pool during one bucket of time
+#[zero_copy(unsafe)]
+#[repr(C, packed)]
+#[derive(Default, Debug, PartialEq, Eq)]
+pub struct StatsBucket {
+    /// The start of the time the bucket accumulates, 0 if it was never written
+    pub start_time: u64,
+    /// The amounts of token_0 and token_1 swapped in or out of the pool
+    pub volume_0: u128,
+    pub volume_1: u128,
+    /// The trade fees paid in token_0 and token_1, the protocol and fund fees included
+    pub fees_0: u64,
+    pub fees_1: u64,
+    pub swap_count: u64,
+}
+
+impl StatsBucket {
+    pub const LEN: usize = 8 + 16 + 16 + 8 + 8 + 8;
+
+    fn add(&mut self, other: &StatsBucket) -> Result<()> {
+        self.volume_0 = self
+            .volume_0
+            .checked_add(other.volume_0)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.volume_1 = self
+            .volume_1
+            .checked_add(other.volume_1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.fees_0 = self
+            .fees_0
+            .checked_add(other.fees_0)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.fees_1 = self
+            .fees_1
+            .checked_add(other.fees_1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        self.swap_count = self
+            .swap_count
+            .checked_add(other.swap_count)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// The swap volume and fees of a pool over the last 24 hours, for on chain consumers that can't
+/// run an indexer. It is optional, the swaps that pass it among their remaining accounts record
+/// into hourly buckets, and `roll_stats` drops the expired buckets when no swap does.
+///
+/// PDA of `[POOL_STATS_SEED, pool_id]`
+///
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
+#[derive(Default, Debug)]
+pub struct PoolStatsState {
+    /// Bump to identify PDA
+    pub bump: u8,
+    /// The pool the stats are of
+    pub pool_id: Pubkey,
+    /// The time `total` was last computed at
+    pub updated_at: u64,
+    /// The sum of the buckets of the window ending at `updated_at`, its start_time is the start
+    /// of the oldest of them
+    pub total: StatsBucket,
+    /// Ring of hourly buckets, indexed by the hour modulo the number of buckets
+    pub buckets: [StatsBucket; STATS_BUCKET_NUM],
+    // Unused bytes for future upgrades.
+    pub padding: [u64; 8],
+}
+
+impl PoolStatsState {
+    pub const LEN: usize =
+        8 + 1 + 32 + 8 + StatsBucket::LEN + StatsBucket::LEN * STATS_BUCKET_NUM + 8 * 8;
+
+    pub fn key(pool_id: Pubkey) -> Pubkey {
+        Pubkey::find_program_address(
+            &[POOL_STATS_SEED.as_bytes(), pool_id.as_ref()],
+            &crate::id(),
+        )
+        .0
+    }
+
+    pub fn initialize(&mut self, bump: u8, pool_id: Pubkey) {
+        self.bump = bump;
+        self.pool_id = pool_id;
+    }
+
+    /// Record a swap of the pool at `timestamp` in the bucket of its hour, which is reset first
+    /// if it holds an older hour
+    pub fn record_swap(
+        &mut self,
+        pool_id: Pubkey,
+        timestamp: u64,
+        amount_0: u64,
+        amount_1: u64,
+        fee_0: u64,
+        fee_1: u64,
+    ) -> Result<()> {
+        if self.pool_id != pool_id {
+            return err!(ErrorCode::PoolStatsMismatch);
+        }
+        let start_time = timestamp - timestamp % STATS_BUCKET_SECONDS;
+        let index = (timestamp / STATS_BUCKET_SECONDS) as usize % STATS_BUCKET_NUM;
+        let bucket = &mut self.buckets[index];
+        if bucket.start_time != start_time {
+            *bucket = StatsBucket {
+                start_time,
+                ..Default::default()
+            };
+        }
+        bucket.add(&StatsBucket {
+            start_time,
+            volume_0: u128::from(amount_0),
+            volume_1: u128::from(amount_1),
+            fees_0: fee_0,
+            fees_1: fee_1,
+            swap_count: 1,
+        })?;
+        self.roll(timestamp)
+    }
+
+    /// Clear the buckets that are out of the window ending at `timestamp` and recompute the total
+    pub fn roll(&mut self, timestamp: u64) -> Result<()> {
+        let window_start = timestamp.saturating_sub(STATS_WINDOW_SECONDS);
+        let mut total = StatsBucket::default();
+        for bucket in self.buckets.iter_mut() {
+            if bucket.start_time == 0 {
+                continue;
+            }
+            if bucket.start_time <= window_start || bucket.start_time > timestamp {
+                *bucket = StatsBucket::default();
+                continue;
+            }
+            let start_time = bucket.start_time;
+            if total.start_time == 0 || start_time < total.start_time {
+                total.start_time = start_time;
+            }
+            total.add(bucket)?;
+        }
+        self.total = total;
+        self.updated_at = timestamp;
+        Ok(())
+    }
+}
+
+/// Whether a remaining account of a swap is a pool stats account, told apart from the tick arrays
+/// and the bitmap extension by its discriminator
+pub fn is_pool_stats_account(account_info: &AccountInfo) -> bool {
+    account_info.owner == &crate::id()
+        && account_info.try_borrow_data().map_or(false, |data| {
+            data.len() >= 8 && data[..8] == PoolStatsState::discriminator()
+        })
+}
+
+#[cfg(test)]
+mod pool_stats_test {
+    use super::*;
+
+    #[test]
+    fn record_swap_test() {
+        let pool_id = Pubkey::new_unique();
+        let mut pool_stats = PoolStatsState::default();
+        pool_stats.initialize(255, pool_id);
+
+        let hour = 1_000 * STATS_BUCKET_SECONDS;
+        pool_stats
+            .record_swap(pool_id, hour + 10, 100, 200, 1, 0)
+            .unwrap();
+        pool_stats
+            .record_swap(pool_id, hour + 20, 50, 25, 0, 2)
+            .unwrap();
+        pool_stats
+            .record_swap(pool_id, hour + STATS_BUCKET_SECONDS, 10, 10, 1, 0)
+            .unwrap();
+        let total = pool_stats.total;
+        assert_eq!(
+            total,
+            StatsBucket {
+                start_time: hour,
+                volume_0: 160,
+                volume_1: 235,
+                fees_0: 2,
+                fees_1: 2,
+                swap_count: 3,
+            }
+        );
+        let updated_at = pool_stats.updated_at;
+        assert_eq!(updated_at, hour + STATS_BUCKET_SECONDS);
+
+        // a day later the bucket of the first hour is reused, the second hour is still in
+        let next_day = hour + STATS_WINDOW_SECONDS + 30;
+        pool_stats
+            .record_swap(pool_id, next_day, 7, 8, 0, 1)
+            .unwrap();
+        let total = pool_stats.total;
+        assert_eq!(
+            (
+                total.start_time,
+                total.volume_0,
+                total.volume_1,
+                total.swap_count
+            ),
+            (hour + STATS_BUCKET_SECONDS, 17, 18, 2)
+        );
+
+        // the stats of another pool are rejected
+        assert_eq!(
+            pool_stats
+                .record_swap(Pubkey::new_unique(), next_day, 1, 1, 0, 0)
+                .unwrap_err(),
+            ErrorCode::PoolStatsMismatch.into()
+        );
+    }
+
+    #[test]
+    fn roll_test() {
+        let pool_id = Pubkey::new_unique();
+        let mut pool_stats = PoolStatsState::default();
+        pool_stats.initialize(255, pool_id);
+        let hour = 1_000 * STATS_BUCKET_SECONDS;
+        pool_stats
+            .record_swap(pool_id, hour, 100, 200, 1, 1)
+            .unwrap();
+
+        // still in the window until a full day has passed
+        pool_stats.roll(hour + STATS_WINDOW_SECONDS - 1).unwrap();
+        let total = pool_stats.total;
+        assert_eq!(total.swap_count, 1);
+
+        pool_stats.roll(hour + STATS_WINDOW_SECONDS).unwrap();
+        let total = pool_stats.total;
+        assert_eq!(total, StatsBucket::default());
+        let bucket = pool_stats.buckets[1_000 % STATS_BUCKET_NUM];
+        assert_eq!(bucket, StatsBucket::default());
+    }
+}
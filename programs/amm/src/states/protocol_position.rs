@@ -7,12 +7,17 @@ use crate::{
     libraries::{fixed_point_64, liquidity_math},
 };
 use anchor_lang::prelude::*;
+use std::cell::RefMut;
 
 /// Seed to derive account address and signature
 pub const POSITION_SEED: &str = "position";
 
 /// Info stored for each user's position
-#[account]
+///
+/// Zero copy with the field order of the borsh layout it replaced, the accounts created before
+/// are loaded as they are
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
 #[derive(Default, Debug)]
 pub struct ProtocolPositionState {
     /// Bump to identify PDA
@@ -53,6 +58,19 @@ pub struct ProtocolPositionState {
 impl ProtocolPositionState {
     pub const LEN: usize = 8 + 1 + 32 + 4 + 4 + 16 + 16 + 16 + 8 + 8 + 16 * REWARD_NUM + 64;
 
+    /// Load the protocol position of an `init_if_needed` constraint, initialized by its first
+    /// load when it was just created
+    pub fn load_or_init<'a, 'info>(
+        loader: &'a AccountLoader<'info, ProtocolPositionState>,
+    ) -> Result<RefMut<'a, ProtocolPositionState>> {
+        let uninitialized = loader.as_ref().try_borrow_data()?[..8] == [0u8; 8];
+        if uninitialized {
+            loader.load_init()
+        } else {
+            loader.load_mut()
+        }
+    }
+
     pub fn update(
         &mut self,
         tick_lower_index: i32,
@@ -110,3 +128,16 @@ impl ProtocolPositionState {
         self.reward_growth_inside = reward_growths_inside;
     }
 }
+
+#[cfg(test)]
+mod protocol_position_test {
+    use super::*;
+
+    #[test]
+    fn layout_len_unchanged_test() {
+        assert_eq!(
+            std::mem::size_of::<ProtocolPositionState>() + 8,
+            ProtocolPositionState::LEN
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use anchor_lang::prelude::*;
+
+/// Prefix of the messages the position owner signs, so a relay message can't be mistaken for
+/// any other message signed by the same key
+pub const RELAY_MESSAGE_PREFIX: &[u8] = b"raydium_clmm_relay";
+
+/// The position actions a relayer can submit for the owner, none of them withdraws liquidity
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RelayAction {
+    /// Collect the fees and rewards of the position to the owner's token accounts
+    Collect,
+    /// Increase the liquidity of the position out of the owner's token accounts
+    IncreaseLiquidity,
+}
+
+/// The message the position owner signs to let `relayer` submit `action` on the position
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct RelayedPositionMessage {
+    pub action: RelayAction,
+    /// The personal position acted on
+    pub personal_position: Pubkey,
+    /// The only signer that can submit the action
+    pub relayer: Pubkey,
+    /// Must equal the `relay_nonce` of the position, which is bumped once the action is submitted
+    pub nonce: u64,
+    /// The message can't be submitted after this timestamp
+    pub expires_at: u64,
+    /// The liquidity to add, zero to collect
+    pub liquidity: u128,
+    /// The max amount of token_0 to spend, zero to collect
+    pub amount_0_max: u64,
+    /// The max amount of token_1 to spend, zero to collect
+    pub amount_1_max: u64,
+    /// See `increase_liquidity_v2`, none to collect
+    pub base_flag: Option<bool>,
+}
+
+impl RelayedPositionMessage {
+    /// The bytes the owner signs
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut message = RELAY_MESSAGE_PREFIX.to_vec();
+        message.extend_from_slice(&self.try_to_vec().unwrap());
+        message
+    }
+}
+
+/// Emitted when a relayer submits a position action signed by the owner
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct RelayedPositionActionEvent {
+    /// The personal position acted on
+    #[index]
+    pub personal_position: Pubkey,
+    /// The position owner who signed the action
+    pub owner: Pubkey,
+    /// The signer who submitted the action and paid for the transaction
+    pub relayer: Pubkey,
+    pub action: RelayAction,
+    /// The nonce consumed by the action
+    pub nonce: u64,
+}
+
+#[cfg(test)]
+mod relay_test {
+    use super::*;
+
+    #[test]
+    fn message_bytes_test() {
+        let message = RelayedPositionMessage {
+            action: RelayAction::Collect,
+            personal_position: Pubkey::new_unique(),
+            relayer: Pubkey::new_unique(),
+            nonce: 3,
+            expires_at: 1000,
+            liquidity: 0,
+            amount_0_max: 0,
+            amount_1_max: 0,
+            base_flag: None,
+        };
+        let bytes = message.to_bytes();
+        assert!(bytes.starts_with(RELAY_MESSAGE_PREFIX));
+        assert_eq!(
+            RelayedPositionMessage::try_from_slice(&bytes[RELAY_MESSAGE_PREFIX.len()..]).unwrap(),
+            message
+        );
+
+        // every field is signed
+        let other_nonce = RelayedPositionMessage {
+            nonce: 4,
+            ..message.clone()
+        };
+        assert_ne!(other_nonce.to_bytes(), bytes);
+        let other_action = RelayedPositionMessage {
+            action: RelayAction::IncreaseLiquidity,
+            ..message
+        };
+        assert_ne!(other_action.to_bytes(), bytes);
+    }
+}
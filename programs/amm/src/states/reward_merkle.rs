@@ -0,0 +1,189 @@
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::keccak;
+
+pub const REWARD_MERKLE_SEED: &str = "reward_merkle";
+pub const REWARD_MERKLE_CLAIM_SEED: &str = "reward_merkle_claim";
+
+/// Snapshot of the owed rewards of an ended reward campaign, users claim against the merkle root
+/// instead of the per-position reward accounting of the pool.
+///
+/// PDA of `[REWARD_MERKLE_SEED, pool_id, reward_index]`
+///
+#[account]
+#[derive(Default, Debug)]
+pub struct RewardMerkleState {
+    /// Bump to identify PDA
+    pub bump: u8,
+    /// The pool the reward belongs to
+    pub pool_id: Pubkey,
+    /// The index of reward info in the pool
+    pub reward_index: u8,
+    /// Reward token mint
+    pub reward_mint: Pubkey,
+    /// Reward vault token account
+    pub reward_vault: Pubkey,
+    /// Root of the merkle tree, leaf is `keccak(claimant, amount)`
+    pub merkle_root: [u8; 32],
+    /// The total amount of reward in the snapshot
+    pub total_amount: u64,
+    /// The total amount of reward claimed against the snapshot
+    pub claimed_amount: u64,
+    /// Number of claims made against the snapshot
+    pub claimed_count: u64,
+    // Unused bytes for future upgrades.
+    pub padding: [u64; 8],
+}
+
+impl RewardMerkleState {
+    pub const LEN: usize = 8 + 1 + 32 + 1 + 32 + 32 + 32 + 8 + 8 + 8 + 8 * 8;
+
+    /// Leaf of the merkle tree for the claimant and the owed amount
+    pub fn leaf(claimant: &Pubkey, amount: u64) -> [u8; 32] {
+        keccak::hashv(&[claimant.as_ref(), &amount.to_le_bytes()]).0
+    }
+
+    /// Hash of two nodes, sorted so that a proof doesn't need to carry the position of the node
+    pub fn hash_pair(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+        if a <= b {
+            keccak::hashv(&[a, b]).0
+        } else {
+            keccak::hashv(&[b, a]).0
+        }
+    }
+
+    /// Returns true if the proof links the leaf to the root
+    pub fn verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+        let mut computed = leaf;
+        for node in proof {
+            computed = RewardMerkleState::hash_pair(&computed, node);
+        }
+        computed == *root
+    }
+
+    /// Count a claim of `amount` against the snapshot, the claims can't exceed its total
+    pub fn record_claim(&mut self, amount: u64) -> Result<()> {
+        self.claimed_amount = self
+            .claimed_amount
+            .checked_add(amount)
+            .ok_or(ErrorCode::MathOverflow)?;
+        require_gte!(
+            self.total_amount,
+            self.claimed_amount,
+            ErrorCode::MerkleClaimExceedsTotal
+        );
+        self.claimed_count = self
+            .claimed_count
+            .checked_add(1)
+            .ok_or(ErrorCode::MathOverflow)?;
+        Ok(())
+    }
+}
+
+/// Marks that a claimant has claimed against the snapshot
+///
+/// PDA of `[REWARD_MERKLE_CLAIM_SEED, reward_merkle_state, claimant]`
+///
+#[account]
+#[derive(Default, Debug)]
+pub struct RewardMerkleClaimState {
+    /// Bump to identify PDA
+    pub bump: u8,
+    /// The snapshot claimed against
+    pub reward_merkle: Pubkey,
+    /// The claimant wallet
+    pub claimant: Pubkey,
+    /// The claimed amount
+    pub amount: u64,
+}
+
+impl RewardMerkleClaimState {
+    pub const LEN: usize = 8 + 1 + 32 + 32 + 8;
+}
+
+/// Emitted when the owed rewards of an ended campaign are consolidated into a merkle root
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct RewardMerkleRootEvent {
+    #[index]
+    pub pool_state: Pubkey,
+    pub reward_index: u8,
+    pub merkle_root: [u8; 32],
+    pub total_amount: u64,
+}
+
+/// Emitted when a reward is claimed against a merkle snapshot
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct ClaimMerkleRewardEvent {
+    #[index]
+    pub pool_state: Pubkey,
+    pub reward_index: u8,
+    #[index]
+    pub claimant: Pubkey,
+    pub amount: u64,
+}
+
+#[cfg(test)]
+mod reward_merkle_test {
+    use super::*;
+
+    #[test]
+    fn verify_proof_test() {
+        let claimants = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        let leaves: Vec<[u8; 32]> = claimants
+            .iter()
+            .enumerate()
+            .map(|(i, c)| RewardMerkleState::leaf(c, 100 * (i as u64 + 1)))
+            .collect();
+        let node_01 = RewardMerkleState::hash_pair(&leaves[0], &leaves[1]);
+        let root = RewardMerkleState::hash_pair(&node_01, &leaves[2]);
+
+        assert!(RewardMerkleState::verify(
+            &[leaves[1], leaves[2]],
+            &root,
+            leaves[0]
+        ));
+        assert!(RewardMerkleState::verify(&[node_01], &root, leaves[2]));
+        // wrong amount
+        assert!(!RewardMerkleState::verify(
+            &[leaves[1], leaves[2]],
+            &root,
+            RewardMerkleState::leaf(&claimants[0], 101)
+        ));
+        // wrong claimant
+        assert!(!RewardMerkleState::verify(
+            &[node_01],
+            &root,
+            RewardMerkleState::leaf(&claimants[0], 300)
+        ));
+    }
+
+    #[test]
+    fn record_claim_test() {
+        let mut reward_merkle_state = RewardMerkleState {
+            total_amount: 300,
+            ..Default::default()
+        };
+        reward_merkle_state.record_claim(100).unwrap();
+        reward_merkle_state.record_claim(200).unwrap();
+        assert_eq!(reward_merkle_state.claimed_amount, 300);
+        assert_eq!(reward_merkle_state.claimed_count, 2);
+
+        // the snapshot is fully claimed
+        assert_eq!(
+            reward_merkle_state.record_claim(1).unwrap_err(),
+            error!(ErrorCode::MerkleClaimExceedsTotal)
+        );
+
+        reward_merkle_state.total_amount = u64::MAX;
+        assert_eq!(
+            reward_merkle_state.record_claim(u64::MAX).unwrap_err(),
+            error!(ErrorCode::MathOverflow)
+        );
+    }
+}
@@ -142,8 +142,30 @@ impl TickArrayState {
         Ok(())
     }
 
+    /// Reset the ticks that no position references, returns the number of ticks reset.
+    ///
+    /// The outside values of an uninitialized tick are never read while it stays uninitialized,
+    /// but `TickState::update` keeps them when the tick is initialized again above the current
+    /// tick, expecting zero. Values left on an uninitialized tick are therefore normalized to zero,
+    /// the same as `TickState::clear` does when the last liquidity of a tick is removed.
+    /// Initialized ticks are not touched, so the fees and rewards owed to positions don't change.
+    pub fn compact(&mut self) -> Result<u8> {
+        let mut cleared_tick_count = 0;
+        for tick_state in self.ticks.iter_mut() {
+            if tick_state.is_stale() {
+                tick_state.clear();
+                tick_state.tick = 0;
+                cleared_tick_count += 1;
+            }
+        }
+        if cleared_tick_count > 0 {
+            self.recent_epoch = get_recent_epoch()?;
+        }
+        Ok(cleared_tick_count)
+    }
+
     /// Get tick's offset in current tick array, tick must be include in tick array， otherwise throw an error
-    fn get_tick_offset_in_array(self, tick_index: i32, tick_spacing: u16) -> Result<usize> {
+    pub fn get_tick_offset_in_array(self, tick_index: i32, tick_spacing: u16) -> Result<usize> {
         let start_tick_index = TickArrayState::get_array_start_index(tick_index, tick_spacing);
         require_eq!(
             start_tick_index,
@@ -334,7 +356,7 @@ impl TickState {
         } else {
             self.liquidity_net.checked_add(liquidity_delta)
         }
-        .unwrap();
+        .ok_or(ErrorCode::MathOverflow)?;
         Ok(flipped)
     }
 
@@ -345,13 +367,13 @@ impl TickState {
         fee_growth_global_0_x64: u128,
         fee_growth_global_1_x64: u128,
         reward_infos: &[RewardInfo; REWARD_NUM],
-    ) -> i128 {
+    ) -> Result<i128> {
         self.fee_growth_outside_0_x64 = fee_growth_global_0_x64
             .checked_sub(self.fee_growth_outside_0_x64)
-            .unwrap();
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
         self.fee_growth_outside_1_x64 = fee_growth_global_1_x64
             .checked_sub(self.fee_growth_outside_1_x64)
-            .unwrap();
+            .ok_or(ErrorCode::FeeAccumulatorOverflow)?;
 
         for i in 0..REWARD_NUM {
             if !reward_infos[i].initialized() {
@@ -361,10 +383,10 @@ impl TickState {
             self.reward_growths_outside_x64[i] = reward_infos[i]
                 .reward_growth_global_x64
                 .checked_sub(self.reward_growths_outside_x64[i])
-                .unwrap();
+                .ok_or(ErrorCode::MathOverflow)?;
         }
 
-        self.liquidity_net
+        Ok(self.liquidity_net)
     }
 
     pub fn clear(&mut self) {
@@ -379,6 +401,16 @@ impl TickState {
         self.liquidity_gross != 0
     }
 
+    /// Whether the tick is uninitialized but still holds values of its last use
+    pub fn is_stale(self) -> bool {
+        !self.is_initialized()
+            && (self.tick != 0
+                || self.liquidity_net != 0
+                || self.fee_growth_outside_0_x64 != 0
+                || self.fee_growth_outside_1_x64 != 0
+                || self.reward_growths_outside_x64 != [0; REWARD_NUM])
+    }
+
     /// Common checks for a valid tick input.
     /// A tick is valid if it lies within tick boundaries
     pub fn check_is_out_of_boundary(tick: i32) -> bool {
@@ -386,6 +418,18 @@ impl TickState {
     }
 }
 
+/// Emitted when the uninitialized ticks of a tick array are reset
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct TickArrayCompactedEvent {
+    #[index]
+    pub pool_state: Pubkey,
+    pub tick_array: Pubkey,
+    pub start_tick_index: i32,
+    /// The number of ticks reset
+    pub cleared_tick_count: u8,
+}
+
 // Calculates the fee growths inside of tick_lower and tick_upper based on their positions relative to tick_current.
 /// `fee_growth_inside = fee_growth_global - fee_growth_below(lower) - fee_growth_above(upper)`
 ///
@@ -395,7 +439,7 @@ pub fn get_fee_growth_inside(
     tick_current: i32,
     fee_growth_global_0_x64: u128,
     fee_growth_global_1_x64: u128,
-) -> (u128, u128) {
+) -> Result<(u128, u128)> {
     // calculate fee growth below
     let (fee_growth_below_0_x64, fee_growth_below_1_x64) = if tick_current >= tick_lower.tick {
         (
@@ -406,10 +450,10 @@ pub fn get_fee_growth_inside(
         (
             fee_growth_global_0_x64
                 .checked_sub(tick_lower.fee_growth_outside_0_x64)
-                .unwrap(),
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?,
             fee_growth_global_1_x64
                 .checked_sub(tick_lower.fee_growth_outside_1_x64)
-                .unwrap(),
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?,
         )
     };
 
@@ -423,10 +467,10 @@ pub fn get_fee_growth_inside(
         (
             fee_growth_global_0_x64
                 .checked_sub(tick_upper.fee_growth_outside_0_x64)
-                .unwrap(),
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?,
             fee_growth_global_1_x64
                 .checked_sub(tick_upper.fee_growth_outside_1_x64)
-                .unwrap(),
+                .ok_or(ErrorCode::FeeAccumulatorOverflow)?,
         )
     };
     let fee_growth_inside_0_x64 = fee_growth_global_0_x64
@@ -436,7 +480,7 @@ pub fn get_fee_growth_inside(
         .wrapping_sub(fee_growth_below_1_x64)
         .wrapping_sub(fee_growth_above_1_x64);
 
-    (fee_growth_inside_0_x64, fee_growth_inside_1_x64)
+    Ok((fee_growth_inside_0_x64, fee_growth_inside_1_x64))
 }
 
 // Calculates the reward growths inside of tick_lower and tick_upper based on their positions relative to tick_current.
@@ -445,7 +489,7 @@ pub fn get_reward_growths_inside(
     tick_upper: &TickState,
     tick_current_index: i32,
     reward_infos: &[RewardInfo; REWARD_NUM],
-) -> [u128; REWARD_NUM] {
+) -> Result<[u128; REWARD_NUM]> {
     let mut reward_growths_inside = [0; REWARD_NUM];
 
     for i in 0..REWARD_NUM {
@@ -459,7 +503,7 @@ pub fn get_reward_growths_inside(
             reward_infos[i]
                 .reward_growth_global_x64
                 .checked_sub(tick_lower.reward_growths_outside_x64[i])
-                .unwrap()
+                .ok_or(ErrorCode::MathOverflow)?
         };
 
         let reward_growths_above = if tick_current_index < tick_upper.tick {
@@ -468,7 +512,7 @@ pub fn get_reward_growths_inside(
             reward_infos[i]
                 .reward_growth_global_x64
                 .checked_sub(tick_upper.reward_growths_outside_x64[i])
-                .unwrap()
+                .ok_or(ErrorCode::MathOverflow)?
         };
         reward_growths_inside[i] = reward_infos[i]
             .reward_growth_global_x64
@@ -485,7 +529,7 @@ pub fn get_reward_growths_inside(
         );
     }
 
-    reward_growths_inside
+    Ok(reward_growths_inside)
 }
 
 pub fn check_tick_array_start_index(
@@ -507,6 +551,47 @@ pub fn check_tick_array_start_index(
     Ok(())
 }
 
+/// The address of the tick array of the pool starting at `start_tick_index`
+pub fn tick_array_key(program_id: &Pubkey, pool_id: &Pubkey, start_tick_index: i32) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            TICK_ARRAY_SEED.as_bytes(),
+            pool_id.as_ref(),
+            &start_tick_index.to_be_bytes(),
+        ],
+        program_id,
+    )
+    .0
+}
+
+/// The start indexes and addresses of every tick array holding a tick from `tick_lower` to
+/// `tick_upper`, in ascending order. The range is clamped to the tick bounds and the tick arrays
+/// are listed whether they are initialized or not.
+pub fn tick_array_keys_for_range(
+    program_id: &Pubkey,
+    pool_id: &Pubkey,
+    tick_spacing: u16,
+    tick_lower: i32,
+    tick_upper: i32,
+) -> Vec<(i32, Pubkey)> {
+    let mut keys = Vec::new();
+    if tick_lower > tick_upper {
+        return keys;
+    }
+    let mut start_index =
+        TickArrayState::get_array_start_index(tick_lower.max(tick_math::MIN_TICK), tick_spacing);
+    let last_start_index =
+        TickArrayState::get_array_start_index(tick_upper.min(tick_math::MAX_TICK), tick_spacing);
+    while start_index <= last_start_index {
+        keys.push((
+            start_index,
+            tick_array_key(program_id, pool_id, start_index),
+        ));
+        start_index += TickArrayState::tick_count(tick_spacing);
+    }
+    keys
+}
+
 /// Common checks for valid tick inputs.
 ///
 pub fn check_ticks_order(tick_lower_index: i32, tick_upper_index: i32) -> Result<()> {
@@ -810,7 +895,8 @@ pub mod tick_array_test {
                 tick_current,
                 fee_growth_global_0_x64,
                 fee_growth_global_1_x64,
-            );
+            )
+            .unwrap();
 
             if fee_growth_global_0_x64 != 0 {
                 fee_growth_global_0_x64 = fee_growth_global_0_x64 + fee_growth_global_delta;
@@ -819,17 +905,21 @@ pub mod tick_array_test {
                 fee_growth_global_1_x64 = fee_growth_global_1_x64 + fee_growth_global_delta;
             }
             if cross_tick_lower {
-                tick_lower.cross(
-                    fee_growth_global_0_x64,
-                    fee_growth_global_1_x64,
-                    &[RewardInfo::default(); 3],
-                );
+                tick_lower
+                    .cross(
+                        fee_growth_global_0_x64,
+                        fee_growth_global_1_x64,
+                        &[RewardInfo::default(); 3],
+                    )
+                    .unwrap();
             } else {
-                tick_upper.cross(
-                    fee_growth_global_0_x64,
-                    fee_growth_global_1_x64,
-                    &[RewardInfo::default(); 3],
-                );
+                tick_upper
+                    .cross(
+                        fee_growth_global_0_x64,
+                        fee_growth_global_1_x64,
+                        &[RewardInfo::default(); 3],
+                    )
+                    .unwrap();
             }
 
             tick_current = target_tick_current;
@@ -839,7 +929,8 @@ pub mod tick_array_test {
                 tick_current,
                 fee_growth_global_0_x64,
                 fee_growth_global_1_x64,
-            );
+            )
+            .unwrap();
 
             println!(
                 "inside_delta_0:{},fee_growth_inside_0_after:{},fee_growth_inside_0_before:{}",
@@ -1118,6 +1209,42 @@ pub mod tick_array_test {
             assert_eq!(fee_growth_inside_delta_0, 0);
             assert_eq!(fee_growth_inside_delta_1, 0);
         }
+
+        #[test]
+        fn cross_overflow_test() {
+            // the fee growth outside can't exceed the global one, crossing fails with an error
+            // instead of a panic
+            let mut tick = TickState {
+                tick: 10,
+                liquidity_net: 100,
+                fee_growth_outside_0_x64: 1000,
+                ..Default::default()
+            };
+            assert_eq!(
+                tick.cross(999, 0, &[RewardInfo::default(); 3]).unwrap_err(),
+                ErrorCode::FeeAccumulatorOverflow.into()
+            );
+            assert_eq!(
+                tick.cross(1000, 0, &[RewardInfo::default(); 3]).unwrap(),
+                100
+            );
+            let fee_growth_outside_0_x64 = tick.fee_growth_outside_0_x64;
+            assert_eq!(fee_growth_outside_0_x64, 0);
+        }
+
+        #[test]
+        fn update_liquidity_net_overflow_test() {
+            let mut tick = TickState {
+                tick: 10,
+                liquidity_net: i128::MAX,
+                ..Default::default()
+            };
+            assert_eq!(
+                tick.update(0, 1, 0, 0, false, &[RewardInfo::default(); 3])
+                    .unwrap_err(),
+                ErrorCode::MathOverflow.into()
+            );
+        }
     }
 
     mod get_reward_growths_inside_test {
@@ -1155,13 +1282,18 @@ pub mod tick_array_test {
                 tick_upper,
                 tick_current,
                 &build_reward_infos(reward_growth_global_x64),
-            )[0];
+            )
+            .unwrap()[0];
 
             reward_growth_global_x64 = reward_growth_global_x64 + reward_growth_global_delta;
             if cross_tick_lower {
-                tick_lower.cross(0, 0, &build_reward_infos(reward_growth_global_x64));
+                tick_lower
+                    .cross(0, 0, &build_reward_infos(reward_growth_global_x64))
+                    .unwrap();
             } else {
-                tick_upper.cross(0, 0, &build_reward_infos(reward_growth_global_x64));
+                tick_upper
+                    .cross(0, 0, &build_reward_infos(reward_growth_global_x64))
+                    .unwrap();
             }
 
             tick_current = target_tick_current;
@@ -1170,7 +1302,8 @@ pub mod tick_array_test {
                 tick_upper,
                 tick_current,
                 &build_reward_infos(reward_growth_global_x64),
-            )[0];
+            )
+            .unwrap()[0];
 
             println!(
                 "inside_delta:{}, reward_growth_inside_after:{}, reward_growth_inside_before:{}",
@@ -1199,7 +1332,8 @@ pub mod tick_array_test {
 
             let reward_infos = &[RewardInfo::default(); 3];
             let reward_inside =
-                get_reward_growths_inside(tick_lower, tick_upper, tick_current, reward_infos);
+                get_reward_growths_inside(tick_lower, tick_upper, tick_current, reward_infos)
+                    .unwrap();
             assert_eq!(reward_inside, [0; 3]);
         }
 
@@ -1307,6 +1441,249 @@ pub mod tick_array_test {
             assert_eq!(reward_frowth_inside_delta, 500);
         }
     }
+    mod compact_test {
+        use super::*;
+        use std::convert::identity;
+
+        const TICK_SPACING: u16 = 10;
+        const START_INDEX: i32 = -300;
+
+        fn reward_infos() -> [RewardInfo; REWARD_NUM] {
+            let mut reward_infos = [RewardInfo::default(); REWARD_NUM];
+            for (i, reward_info) in reward_infos.iter_mut().enumerate() {
+                reward_info.token_mint = Pubkey::new_unique();
+                reward_info.reward_growth_global_x64 = 1_000_000 * (i as u128 + 1);
+            }
+            reward_infos
+        }
+
+        fn build_initialized_tick(tick: i32, seed: u128) -> TickState {
+            let mut tick_state = build_tick(tick, seed, seed as i128).take();
+            tick_state.fee_growth_outside_0_x64 = 100 * seed;
+            tick_state.fee_growth_outside_1_x64 = 200 * seed;
+            tick_state.reward_growths_outside_x64 = [300 * seed, 400 * seed, 500 * seed];
+            tick_state
+        }
+
+        fn build_stale_tick(tick: i32, seed: u128) -> TickState {
+            let mut tick_state = build_initialized_tick(tick, seed);
+            tick_state.liquidity_gross = 0;
+            tick_state.liquidity_net = 0;
+            tick_state
+        }
+
+        /// Initialized ticks on every third slot, stale ticks on the others but the last slots
+        fn build_mixed_tick_array() -> TickArrayState {
+            let mut tick_states = Vec::new();
+            for offset in 0..50 {
+                let tick = START_INDEX + offset * i32::from(TICK_SPACING);
+                if tick == 0 {
+                    continue;
+                }
+                let seed = offset as u128 + 1;
+                if offset % 3 == 0 {
+                    tick_states.push(build_initialized_tick(tick, seed));
+                } else {
+                    tick_states.push(build_stale_tick(tick, seed));
+                }
+            }
+            build_tick_array_with_tick_states(
+                Pubkey::new_unique(),
+                START_INDEX,
+                TICK_SPACING,
+                tick_states,
+            )
+            .take()
+        }
+
+        fn growths_inside(
+            tick_array: &TickArrayState,
+            tick_lower: i32,
+            tick_upper: i32,
+            tick_current: i32,
+            reward_infos: &[RewardInfo; REWARD_NUM],
+        ) -> (u128, u128, [u128; REWARD_NUM]) {
+            let tick_lower_state = tick_array.ticks[tick_array
+                .get_tick_offset_in_array(tick_lower, TICK_SPACING)
+                .unwrap()];
+            let tick_upper_state = tick_array.ticks[tick_array
+                .get_tick_offset_in_array(tick_upper, TICK_SPACING)
+                .unwrap()];
+            let (fee_growth_inside_0_x64, fee_growth_inside_1_x64) = get_fee_growth_inside(
+                &tick_lower_state,
+                &tick_upper_state,
+                tick_current,
+                1_000_000,
+                2_000_000,
+            )
+            .unwrap();
+            let reward_growths_inside = get_reward_growths_inside(
+                &tick_lower_state,
+                &tick_upper_state,
+                tick_current,
+                reward_infos,
+            )
+            .unwrap();
+            (
+                fee_growth_inside_0_x64,
+                fee_growth_inside_1_x64,
+                reward_growths_inside,
+            )
+        }
+
+        #[test]
+        fn compact_clears_only_uninitialized_ticks_test() {
+            let mut tick_array = build_mixed_tick_array();
+            let before = tick_array;
+
+            let cleared_tick_count = tick_array.compact().unwrap();
+            let stale_count = before.ticks.iter().filter(|t| t.is_stale()).count();
+            assert!(stale_count > 0);
+            assert_eq!(usize::from(cleared_tick_count), stale_count);
+
+            for (tick_before, tick_after) in before.ticks.iter().zip(tick_array.ticks.iter()) {
+                if tick_before.is_initialized() {
+                    assert_eq!(
+                        bytemuck::bytes_of(tick_before),
+                        bytemuck::bytes_of(tick_after)
+                    );
+                } else {
+                    assert!(!tick_after.is_stale());
+                    assert_eq!(
+                        bytemuck::bytes_of(&TickState::default()),
+                        bytemuck::bytes_of(tick_after)
+                    );
+                }
+            }
+            assert_eq!(
+                identity(before.initialized_tick_count),
+                identity(tick_array.initialized_tick_count)
+            );
+
+            // nothing left to clear
+            let after = tick_array;
+            assert_eq!(tick_array.compact().unwrap(), 0);
+            assert_eq!(bytemuck::bytes_of(&after), bytemuck::bytes_of(&tick_array));
+        }
+
+        #[test]
+        fn compact_keeps_position_growths_inside_test() {
+            let reward_infos = reward_infos();
+            let before = build_mixed_tick_array();
+            let mut tick_array = before;
+            tick_array.compact().unwrap();
+
+            let initialized_ticks: Vec<i32> = before
+                .ticks
+                .iter()
+                .filter(|t| t.is_initialized())
+                .map(|t| t.tick)
+                .collect();
+            // every range between initialized ticks, with the current tick below, inside, on the
+            // boundaries of and above the range
+            for (i, &tick_lower) in initialized_ticks.iter().enumerate() {
+                for &tick_upper in initialized_ticks[i + 1..].iter() {
+                    for tick_current in (START_INDEX - 20..START_INDEX + 520).step_by(5) {
+                        assert_eq!(
+                            growths_inside(
+                                &before,
+                                tick_lower,
+                                tick_upper,
+                                tick_current,
+                                &reward_infos
+                            ),
+                            growths_inside(
+                                &tick_array,
+                                tick_lower,
+                                tick_upper,
+                                tick_current,
+                                &reward_infos
+                            ),
+                        );
+                    }
+                }
+            }
+        }
+
+        #[test]
+        fn compact_normalizes_reinitialized_tick_test() {
+            let reward_infos = reward_infos();
+            let tick_current = -200;
+            let fee_growth_global_0_x64 = 1_000_000;
+            let fee_growth_global_1_x64 = 2_000_000;
+            // a position above the current tick, its ticks hold stale values
+            let open_position = |tick_array: &mut TickArrayState| {
+                for (tick, upper) in [(-100, false), (-50, true)] {
+                    let tick_state = tick_array.get_tick_state_mut(tick, TICK_SPACING).unwrap();
+                    tick_state.tick = tick;
+                    tick_state
+                        .update(
+                            tick_current,
+                            1000,
+                            fee_growth_global_0_x64,
+                            fee_growth_global_1_x64,
+                            upper,
+                            &reward_infos,
+                        )
+                        .unwrap();
+                }
+                growths_inside(tick_array, -100, -50, tick_current, &reward_infos)
+            };
+
+            let mut tick_array = build_tick_array_with_tick_states(
+                Pubkey::new_unique(),
+                START_INDEX,
+                TICK_SPACING,
+                vec![build_stale_tick(-100, 3), build_stale_tick(-50, 5)],
+            )
+            .take();
+            let mut stale_tick_array = tick_array;
+            assert_eq!(tick_array.compact().unwrap(), 2);
+
+            // no growth happened inside a range above the current tick
+            assert_eq!(open_position(&mut tick_array), (0, 0, [0; REWARD_NUM]));
+            assert_ne!(
+                open_position(&mut stale_tick_array),
+                (0, 0, [0; REWARD_NUM])
+            );
+        }
+    }
+
+    #[test]
+    fn tick_array_keys_for_range_test() {
+        let pool_id = Pubkey::new_unique();
+        let tick_count = TickArrayState::tick_count(10);
+        let keys = tick_array_keys_for_range(&crate::id(), &pool_id, 10, -1, tick_count);
+        assert_eq!(
+            keys.iter()
+                .map(|(start_index, _)| *start_index)
+                .collect::<Vec<i32>>(),
+            vec![-tick_count, 0, tick_count]
+        );
+        for (start_index, key) in keys {
+            let mut tick_array = TickArrayState::default();
+            tick_array.pool_id = pool_id;
+            tick_array.start_tick_index = start_index;
+            assert_eq!(key, tick_array.key());
+        }
+        // a single tick array, and none for an inverted range
+        assert_eq!(
+            tick_array_keys_for_range(&crate::id(), &pool_id, 10, 5, 5).len(),
+            1
+        );
+        assert!(tick_array_keys_for_range(&crate::id(), &pool_id, 10, 5, 4).is_empty());
+        // clamped to the tick bounds
+        let keys = tick_array_keys_for_range(&crate::id(), &pool_id, 1, i32::MIN, i32::MAX);
+        assert_eq!(
+            keys[0].0,
+            TickArrayState::get_array_start_index(tick_math::MIN_TICK, 1)
+        );
+        assert_eq!(
+            keys[keys.len() - 1].0,
+            TickArrayState::get_array_start_index(tick_math::MAX_TICK, 1)
+        );
+    }
+
     mod tick_array_layout_test {
         use super::*;
         use anchor_lang::Discriminator;
@@ -0,0 +1,165 @@
+use crate::pool::REWARD_NUM;
+use anchor_lang::prelude::*;
+
+pub const VAULT_RECONCILIATION_SEED: &str = "vault_reconciliation";
+
+/// Report of the last reconciliation of the pool vaults, compares the vault balances with the
+/// amounts the pool owes out of them: the liquidity and owed fees of the sampled positions, the
+/// protocol and fund fees, and the rewards paid out of the pool vaults.
+///
+/// Positions not sampled are not counted, so the surplus is an upper bound unless every position
+/// of the pool is sampled.
+///
+/// PDA of `[VAULT_RECONCILIATION_SEED, pool_id]`
+///
+#[account]
+#[derive(Default, Debug)]
+pub struct VaultReconciliationState {
+    /// Bump to identify PDA
+    pub bump: u8,
+    /// The reconciled pool
+    pub pool_id: Pubkey,
+    /// The slot of the reconciliation
+    pub slot: u64,
+    /// The timestamp of the reconciliation
+    pub timestamp: u64,
+    /// Number of personal positions sampled
+    pub position_count: u32,
+    /// The balance of token_vault_0
+    pub vault_amount_0: u64,
+    /// The balance of token_vault_1
+    pub vault_amount_1: u64,
+    /// The token_0 withdrawable by the liquidity of the sampled positions
+    pub liquidity_amount_0: u64,
+    /// The token_1 withdrawable by the liquidity of the sampled positions
+    pub liquidity_amount_1: u64,
+    /// The token_0 fees owed to the sampled positions, including the fees accrued since their last update
+    pub fees_owed_0: u64,
+    /// The token_1 fees owed to the sampled positions, including the fees accrued since their last update
+    pub fees_owed_1: u64,
+    /// The protocol fees of token_0 not collected
+    pub protocol_fees_0: u64,
+    /// The protocol fees of token_1 not collected
+    pub protocol_fees_1: u64,
+    /// The fund fees of token_0 not collected
+    pub fund_fees_0: u64,
+    /// The fund fees of token_1 not collected
+    pub fund_fees_1: u64,
+    /// The rewards owed to the sampled positions by reward index, including the rewards accrued
+    /// since their last update
+    pub rewards_owed: [u64; REWARD_NUM],
+    /// The rewards owed paid out of token_vault_0
+    pub vault_rewards_owed_0: u64,
+    /// The rewards owed paid out of token_vault_1
+    pub vault_rewards_owed_1: u64,
+    /// `vault_amount_0` minus the owed token_0, negative if the vault can't pay what it owes
+    pub surplus_0: i128,
+    /// `vault_amount_1` minus the owed token_1, negative if the vault can't pay what it owes
+    pub surplus_1: i128,
+    // Unused bytes for future upgrades.
+    pub padding: [u64; 8],
+}
+
+impl VaultReconciliationState {
+    pub const LEN: usize = 8
+        + 1
+        + 32
+        + 8
+        + 8
+        + 4
+        + 8 * 2
+        + 8 * 2
+        + 8 * 2
+        + 8 * 2
+        + 8 * 2
+        + 8 * REWARD_NUM
+        + 8 * 2
+        + 16 * 2
+        + 8 * 8;
+
+    /// Clear the report of the previous reconciliation
+    pub fn reset(&mut self, bump: u8, pool_id: Pubkey, slot: u64, timestamp: u64) {
+        *self = Self {
+            bump,
+            pool_id,
+            slot,
+            timestamp,
+            ..Default::default()
+        };
+    }
+
+    pub fn owed_0(&self) -> u128 {
+        u128::from(self.liquidity_amount_0)
+            + u128::from(self.fees_owed_0)
+            + u128::from(self.protocol_fees_0)
+            + u128::from(self.fund_fees_0)
+            + u128::from(self.vault_rewards_owed_0)
+    }
+
+    pub fn owed_1(&self) -> u128 {
+        u128::from(self.liquidity_amount_1)
+            + u128::from(self.fees_owed_1)
+            + u128::from(self.protocol_fees_1)
+            + u128::from(self.fund_fees_1)
+            + u128::from(self.vault_rewards_owed_1)
+    }
+
+    /// Record the vault balances and compute the surplus of the vaults over the owed amounts
+    pub fn settle(&mut self, vault_amount_0: u64, vault_amount_1: u64) {
+        self.vault_amount_0 = vault_amount_0;
+        self.vault_amount_1 = vault_amount_1;
+        self.surplus_0 = i128::from(vault_amount_0) - self.owed_0() as i128;
+        self.surplus_1 = i128::from(vault_amount_1) - self.owed_1() as i128;
+    }
+
+    /// Whether both vaults hold at least the owed amounts
+    pub fn is_solvent(&self) -> bool {
+        self.surplus_0 >= 0 && self.surplus_1 >= 0
+    }
+}
+
+/// Emitted when the vaults of a pool are reconciled
+#[event]
+#[cfg_attr(feature = "client", derive(Debug))]
+pub struct VaultReconciliationEvent {
+    #[index]
+    pub pool_state: Pubkey,
+    pub position_count: u32,
+    pub vault_amount_0: u64,
+    pub vault_amount_1: u64,
+    pub surplus_0: i128,
+    pub surplus_1: i128,
+}
+
+#[cfg(test)]
+mod vault_reconciliation_test {
+    use super::*;
+
+    #[test]
+    fn settle_test() {
+        let mut reconciliation_state = VaultReconciliationState {
+            liquidity_amount_0: 1000,
+            fees_owed_0: 100,
+            protocol_fees_0: 10,
+            fund_fees_0: 5,
+            vault_rewards_owed_0: 50,
+            liquidity_amount_1: 2000,
+            fees_owed_1: 200,
+            ..Default::default()
+        };
+        reconciliation_state.settle(1200, 2200);
+        assert_eq!(reconciliation_state.surplus_0, 35);
+        assert_eq!(reconciliation_state.surplus_1, 0);
+        assert!(reconciliation_state.is_solvent());
+
+        // the vault can't pay the rewards owed out of it
+        reconciliation_state.vault_rewards_owed_1 = 1;
+        reconciliation_state.settle(1200, 2200);
+        assert_eq!(reconciliation_state.surplus_1, -1);
+        assert!(!reconciliation_state.is_solvent());
+
+        reconciliation_state.reset(1, Pubkey::new_unique(), 10, 20);
+        assert_eq!(reconciliation_state.owed_0(), 0);
+        assert_eq!(reconciliation_state.owed_1(), 0);
+    }
+}
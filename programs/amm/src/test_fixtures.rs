@@ -0,0 +1,276 @@
+//! Builders of program states for unit tests.
+//!
+//! Enabled by the `test-fixtures` feature so that integrators can construct realistic states
+//! without copying the private test helpers of the program.
+//!
+use crate::libraries::tick_math;
+use crate::states::*;
+use anchor_lang::prelude::*;
+use std::cell::{RefCell, RefMut};
+use std::collections::VecDeque;
+
+/// Build an amm config
+pub fn build_amm_config(index: u16, tick_spacing: u16, trade_fee_rate: u32) -> AmmConfig {
+    let mut amm_config = AmmConfig {
+        index,
+        owner: crate::admin::id(),
+        trade_fee_rate,
+        tick_spacing,
+        ..Default::default()
+    };
+    amm_config.bump = Pubkey::find_program_address(
+        &[AMM_CONFIG_SEED.as_bytes(), &index.to_be_bytes()],
+        &crate::id(),
+    )
+    .1;
+    amm_config
+}
+
+/// Build a tick with the liquidity, a tick is initialized when `liquidity_gross` is not zero
+pub fn build_tick_state(tick: i32, liquidity_gross: u128, liquidity_net: i128) -> TickState {
+    TickState {
+        tick,
+        liquidity_gross,
+        liquidity_net,
+        ..Default::default()
+    }
+}
+
+/// Build a tick array of the pool, ticks are placed by their tick index
+pub fn build_tick_array_state(
+    pool_id: Pubkey,
+    start_index: i32,
+    tick_spacing: u16,
+    tick_states: Vec<TickState>,
+) -> TickArrayState {
+    let mut tick_array = TickArrayState::default();
+    tick_array
+        .initialize(start_index, tick_spacing, pool_id)
+        .unwrap();
+    for tick_state in tick_states {
+        let offset = tick_array
+            .get_tick_offset_in_array(tick_state.tick, tick_spacing)
+            .unwrap();
+        tick_array.ticks[offset] = tick_state;
+    }
+    tick_array
+}
+
+/// Build an observation account of the pool
+pub fn build_observation_state(pool_id: Pubkey) -> ObservationState {
+    ObservationState {
+        initialized: true,
+        pool_id,
+        ..Default::default()
+    }
+}
+
+/// Build a personal position of the pool, owned through a new nft mint
+pub fn build_personal_position_state(
+    pool_state: &PoolState,
+    tick_lower_index: i32,
+    tick_upper_index: i32,
+    liquidity: u128,
+) -> PersonalPositionState {
+    let nft_mint = Pubkey::new_unique();
+    let mut personal_position = PersonalPositionState {
+        nft_mint,
+        pool_id: pool_state.key(),
+        tick_lower_index,
+        tick_upper_index,
+        liquidity,
+        ..Default::default()
+    };
+    personal_position.bump = [Pubkey::find_program_address(
+        &[POSITION_SEED.as_bytes(), nft_mint.as_ref()],
+        &crate::id(),
+    )
+    .1];
+    personal_position
+}
+
+/// The states of a pool built by [`PoolBuilder`]
+pub struct PoolFixture {
+    pub amm_config: AmmConfig,
+    pub pool_state: RefCell<PoolState>,
+    /// Tick arrays in the order they were added to the builder
+    pub tick_array_states: VecDeque<RefCell<TickArrayState>>,
+    pub observation_state: RefCell<ObservationState>,
+}
+
+impl PoolFixture {
+    /// Borrow the tick arrays mutably, as expected by `swap_internal`
+    pub fn tick_array_states_mut(&self) -> VecDeque<RefMut<TickArrayState>> {
+        self.tick_array_states
+            .iter()
+            .map(|tick_array_state| tick_array_state.borrow_mut())
+            .collect()
+    }
+}
+
+/// Builder of a pool with its amm config, observation and tick arrays
+pub struct PoolBuilder {
+    amm_config: AmmConfig,
+    tick_current: i32,
+    sqrt_price_x64: u128,
+    liquidity: u128,
+    token_mint_0: Pubkey,
+    token_mint_1: Pubkey,
+    tick_arrays: Vec<(i32, Vec<TickState>)>,
+}
+
+impl PoolBuilder {
+    pub fn new(tick_spacing: u16) -> Self {
+        let mut token_mint_0 = Pubkey::new_unique();
+        let mut token_mint_1 = Pubkey::new_unique();
+        if token_mint_0 > token_mint_1 {
+            std::mem::swap(&mut token_mint_0, &mut token_mint_1);
+        }
+        Self {
+            amm_config: build_amm_config(0, tick_spacing, 2500),
+            tick_current: 0,
+            sqrt_price_x64: tick_math::get_sqrt_price_at_tick(0).unwrap(),
+            liquidity: 0,
+            token_mint_0,
+            token_mint_1,
+            tick_arrays: Vec::new(),
+        }
+    }
+
+    pub fn amm_config(mut self, amm_config: AmmConfig) -> Self {
+        self.amm_config = amm_config;
+        self
+    }
+
+    /// Set the current tick, the price is set to the price of the tick
+    pub fn tick_current(mut self, tick_current: i32) -> Self {
+        self.tick_current = tick_current;
+        self.sqrt_price_x64 = tick_math::get_sqrt_price_at_tick(tick_current).unwrap();
+        self
+    }
+
+    /// Set the price, the current tick is derived from the price
+    pub fn sqrt_price_x64(mut self, sqrt_price_x64: u128) -> Self {
+        self.sqrt_price_x64 = sqrt_price_x64;
+        self.tick_current = tick_math::get_tick_at_sqrt_price(sqrt_price_x64).unwrap();
+        self
+    }
+
+    pub fn liquidity(mut self, liquidity: u128) -> Self {
+        self.liquidity = liquidity;
+        self
+    }
+
+    pub fn token_mints(mut self, token_mint_0: Pubkey, token_mint_1: Pubkey) -> Self {
+        self.token_mint_0 = token_mint_0;
+        self.token_mint_1 = token_mint_1;
+        self
+    }
+
+    /// Add an initialized tick array, the array must be covered by the bitmap of the pool
+    pub fn tick_array(mut self, start_index: i32, tick_states: Vec<TickState>) -> Self {
+        self.tick_arrays.push((start_index, tick_states));
+        self
+    }
+
+    pub fn build(self) -> PoolFixture {
+        let amm_config_key = Pubkey::find_program_address(
+            &[
+                AMM_CONFIG_SEED.as_bytes(),
+                &self.amm_config.index.to_be_bytes(),
+            ],
+            &crate::id(),
+        )
+        .0;
+        let tick_spacing = self.amm_config.tick_spacing;
+
+        let mut pool_state = PoolState::default();
+        pool_state.amm_config = amm_config_key;
+        pool_state.token_mint_0 = self.token_mint_0;
+        pool_state.token_mint_1 = self.token_mint_1;
+        pool_state.tick_spacing = tick_spacing;
+        pool_state.tick_current = self.tick_current;
+        pool_state.sqrt_price_x64 = self.sqrt_price_x64;
+        pool_state.liquidity = self.liquidity;
+        pool_state.bump = [Pubkey::find_program_address(
+            &[
+                POOL_SEED.as_bytes(),
+                amm_config_key.as_ref(),
+                self.token_mint_0.as_ref(),
+                self.token_mint_1.as_ref(),
+            ],
+            &crate::id(),
+        )
+        .1];
+        let pool_id = pool_state.key();
+        pool_state.observation_key = Pubkey::find_program_address(
+            &[OBSERVATION_SEED.as_bytes(), pool_id.as_ref()],
+            &crate::id(),
+        )
+        .0;
+
+        let mut tick_array_states = VecDeque::new();
+        for (start_index, tick_states) in self.tick_arrays {
+            tick_array_states.push_back(RefCell::new(build_tick_array_state(
+                pool_id,
+                start_index,
+                tick_spacing,
+                tick_states,
+            )));
+            pool_state.flip_tick_array_bit(None, start_index).unwrap();
+        }
+
+        PoolFixture {
+            amm_config: self.amm_config,
+            pool_state: RefCell::new(pool_state),
+            tick_array_states,
+            observation_state: RefCell::new(build_observation_state(pool_id)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_fixtures_test {
+    use super::*;
+
+    #[test]
+    fn pool_builder_test() {
+        let fixture = PoolBuilder::new(10)
+            .tick_current(-10)
+            .liquidity(1000)
+            .tick_array(-600, vec![build_tick_state(-100, 1000, 1000)])
+            .tick_array(0, vec![build_tick_state(100, 1000, -1000)])
+            .build();
+        let pool_state = fixture.pool_state.borrow();
+        assert_eq!(pool_state.tick_current, -10);
+        assert_eq!(pool_state.liquidity, 1000);
+        assert_eq!(fixture.observation_state.borrow().pool_id, pool_state.key());
+
+        let (is_initialized, start_index) = pool_state
+            .get_first_initialized_tick_array(&None, true)
+            .unwrap();
+        assert!(is_initialized);
+        assert_eq!(start_index, -600);
+        assert_eq!(
+            pool_state
+                .next_initialized_tick_array_start_index(&None, -600, false)
+                .unwrap(),
+            Some(0)
+        );
+
+        let tick_array_states = fixture.tick_array_states_mut();
+        assert_eq!(tick_array_states.len(), 2);
+        assert_eq!(tick_array_states[0].pool_id, pool_state.key());
+        assert!(tick_array_states[1].ticks[10].is_initialized());
+    }
+
+    #[test]
+    fn personal_position_test() {
+        let fixture = PoolBuilder::new(60).build();
+        let pool_state = fixture.pool_state.borrow();
+        let position = build_personal_position_state(&pool_state, -120, 120, 5000);
+        assert_eq!(position.pool_id, pool_state.key());
+        let liquidity = position.liquidity;
+        assert_eq!(liquidity, 5000);
+    }
+}
@@ -0,0 +1,164 @@
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    ed25519_program,
+    instruction::Instruction,
+    sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+};
+
+/// The offsets of a signature in the ed25519 program instruction data
+const SIGNATURE_OFFSETS_START: usize = 2;
+const SIGNATURE_OFFSETS_LEN: usize = 14;
+const SIGNATURE_LEN: usize = 64;
+const PUBKEY_LEN: usize = 32;
+/// The instruction index the ed25519 program reads as the verify instruction itself
+const CURRENT_INSTRUCTION_INDEX: u16 = u16::MAX;
+
+/// Check the instruction right before the current one is an ed25519 program instruction
+/// verifying the signature of `signer` over `message`. The ed25519 program fails the transaction
+/// when the signature doesn't verify, so only the signer and the message are checked here.
+pub fn verify_ed25519_signature(
+    instructions_sysvar: &AccountInfo,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let signed_message = load_ed25519_message(instructions_sysvar, signer)?;
+    require!(
+        signed_message == message,
+        ErrorCode::InvalidSignatureInstruction
+    );
+    Ok(())
+}
+
+/// The message `signer` signed in the ed25519 program instruction right before the current one
+pub fn load_ed25519_message(instructions_sysvar: &AccountInfo, signer: &Pubkey) -> Result<Vec<u8>> {
+    let current_index = load_current_index_checked(instructions_sysvar)?;
+    require_gt!(current_index, 0, ErrorCode::InvalidSignatureInstruction);
+    let verify_instruction =
+        load_instruction_at_checked(usize::from(current_index - 1), instructions_sysvar)?;
+    Ok(get_ed25519_message(&verify_instruction, signer)?.to_vec())
+}
+
+pub fn check_ed25519_instruction(
+    instruction: &Instruction,
+    signer: &Pubkey,
+    message: &[u8],
+) -> Result<()> {
+    let signed_message = get_ed25519_message(instruction, signer)?;
+    require!(
+        signed_message == message,
+        ErrorCode::InvalidSignatureInstruction
+    );
+    Ok(())
+}
+
+/// The message of an ed25519 program instruction verifying a single signature of `signer`
+pub fn get_ed25519_message<'a>(instruction: &'a Instruction, signer: &Pubkey) -> Result<&'a [u8]> {
+    require_keys_eq!(
+        instruction.program_id,
+        ed25519_program::ID,
+        ErrorCode::InvalidSignatureInstruction
+    );
+    require!(
+        instruction.accounts.is_empty(),
+        ErrorCode::InvalidSignatureInstruction
+    );
+    let data = &instruction.data;
+    // exactly one signature, so a second one can't be passed off as the signer's
+    require!(
+        data.len() >= SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN && data[0] == 1,
+        ErrorCode::InvalidSignatureInstruction
+    );
+    let offsets = &data[SIGNATURE_OFFSETS_START..SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN];
+    let read_u16 = |i: usize| u16::from_le_bytes([offsets[i * 2], offsets[i * 2 + 1]]);
+    let signature_offset = usize::from(read_u16(0));
+    let signature_instruction_index = read_u16(1);
+    let public_key_offset = usize::from(read_u16(2));
+    let public_key_instruction_index = read_u16(3);
+    let message_offset = usize::from(read_u16(4));
+    let message_size = usize::from(read_u16(5));
+    let message_instruction_index = read_u16(6);
+    // the signature, key and message must be read from the verify instruction itself
+    require!(
+        signature_instruction_index == CURRENT_INSTRUCTION_INDEX
+            && public_key_instruction_index == CURRENT_INSTRUCTION_INDEX
+            && message_instruction_index == CURRENT_INSTRUCTION_INDEX,
+        ErrorCode::InvalidSignatureInstruction
+    );
+    require!(
+        signature_offset + SIGNATURE_LEN <= data.len(),
+        ErrorCode::InvalidSignatureInstruction
+    );
+
+    let public_key = data
+        .get(public_key_offset..public_key_offset + PUBKEY_LEN)
+        .ok_or(ErrorCode::InvalidSignatureInstruction)?;
+    require!(
+        public_key == signer.as_ref(),
+        ErrorCode::InvalidSignatureInstruction
+    );
+    let message = data
+        .get(message_offset..message_offset + message_size)
+        .ok_or(ErrorCode::InvalidSignatureInstruction)?;
+    Ok(message)
+}
+
+#[cfg(test)]
+mod ed25519_test {
+    use super::*;
+
+    /// Lay out the instruction data the way the ed25519 program expects it
+    fn build_verify_instruction(signer: &Pubkey, message: &[u8]) -> Instruction {
+        let public_key_offset = SIGNATURE_OFFSETS_START + SIGNATURE_OFFSETS_LEN;
+        let signature_offset = public_key_offset + PUBKEY_LEN;
+        let message_offset = signature_offset + SIGNATURE_LEN;
+        let mut data = vec![1u8, 0];
+        for value in [
+            signature_offset as u16,
+            CURRENT_INSTRUCTION_INDEX,
+            public_key_offset as u16,
+            CURRENT_INSTRUCTION_INDEX,
+            message_offset as u16,
+            message.len() as u16,
+            CURRENT_INSTRUCTION_INDEX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(signer.as_ref());
+        data.extend_from_slice(&[7u8; SIGNATURE_LEN]);
+        data.extend_from_slice(message);
+        Instruction {
+            program_id: ed25519_program::ID,
+            accounts: vec![],
+            data,
+        }
+    }
+
+    #[test]
+    fn check_ed25519_instruction_test() {
+        let signer = Pubkey::new_unique();
+        let message = b"collect fees".to_vec();
+        let instruction = build_verify_instruction(&signer, &message);
+        assert!(check_ed25519_instruction(&instruction, &signer, &message).is_ok());
+
+        // another signer or message
+        assert!(check_ed25519_instruction(&instruction, &Pubkey::new_unique(), &message).is_err());
+        assert!(check_ed25519_instruction(&instruction, &signer, b"collect fee").is_err());
+
+        // not the ed25519 program
+        let mut other_program = instruction.clone();
+        other_program.program_id = Pubkey::new_unique();
+        assert!(check_ed25519_instruction(&other_program, &signer, &message).is_err());
+
+        // the message read from another instruction of the transaction
+        let mut other_instruction = instruction.clone();
+        other_instruction.data[14] = 0;
+        other_instruction.data[15] = 0;
+        assert!(check_ed25519_instruction(&other_instruction, &signer, &message).is_err());
+
+        // truncated data
+        let mut truncated = instruction;
+        truncated.data.truncate(truncated.data.len() - 1);
+        assert!(check_ed25519_instruction(&truncated, &signer, &message).is_err());
+    }
+}
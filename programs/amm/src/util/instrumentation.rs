@@ -0,0 +1,15 @@
+/// Log the compute units remaining at a phase of an instruction, to profile the hot paths on
+/// devnet. Expands to nothing unless the `instrumentation` feature is enabled.
+///
+/// `sol_remaining_compute_units` is not exposed by solana-program < 1.17, the remaining units are
+/// logged by `sol_log_compute_units` right after the phase name instead.
+#[macro_export]
+macro_rules! log_compute_units {
+    ($phase:expr) => {
+        #[cfg(feature = "instrumentation")]
+        {
+            anchor_lang::prelude::msg!("compute units at {}", $phase);
+            anchor_lang::solana_program::log::sol_log_compute_units();
+        }
+    };
+}
@@ -6,3 +6,8 @@ pub use system::*;
 
 pub mod account_load;
 pub use account_load::*;
+
+pub mod instrumentation;
+
+pub mod ed25519;
+pub use ed25519::*;
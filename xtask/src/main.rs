@@ -0,0 +1,184 @@
+use anyhow::{format_err, Result};
+use clap::Parser;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A program of the workspace: the artifact `cargo build-sbf` writes, its manifest and its key
+/// under `[programs.localnet]` of Anchor.toml
+struct WorkspaceProgram {
+    artifact: &'static str,
+    manifest: &'static str,
+    anchor_name: &'static str,
+}
+
+const AMM_PROGRAM: WorkspaceProgram = WorkspaceProgram {
+    artifact: "raydium_amm_v3",
+    manifest: "programs/amm/Cargo.toml",
+    anchor_name: "amm_v3",
+};
+const CPI_CONSUMER_PROGRAM: WorkspaceProgram = WorkspaceProgram {
+    artifact: "cpi_consumer",
+    manifest: "examples/cpi-consumer/Cargo.toml",
+    anchor_name: "cpi_consumer",
+};
+const DEPLOY_DIR: &str = "target/deploy";
+const IDL_DIR: &str = "target/idl";
+
+#[derive(Debug, Parser)]
+#[command(bin_name = "cargo xtask")]
+enum Task {
+    /// Build the clmm program with `cargo build-sbf`
+    BuildProgram {
+        /// Features of the program, e.g. devnet or enable-log
+        #[arg(long)]
+        features: Vec<String>,
+        /// Also build the cpi-consumer example program
+        #[arg(long)]
+        all: bool,
+    },
+    /// Build the programs and run a local validator with them deployed at their Anchor.toml ids
+    Localnet {
+        /// Run with the programs already in target/deploy
+        #[arg(long)]
+        skip_build: bool,
+        #[arg(long, default_value = "test-ledger")]
+        ledger: String,
+    },
+    /// Write the IDL of the clmm program to target/idl
+    Idl,
+    /// Run the program and client tests and the solana-program-test integration tests of the
+    /// cpi-consumer example
+    IntegrationTest {
+        /// Also build the programs, catching code that only fails to build for sbf
+        #[arg(long)]
+        build_program: bool,
+    },
+}
+
+fn main() -> Result<()> {
+    let root = workspace_root();
+    match Task::parse() {
+        Task::BuildProgram { features, all } => {
+            build_program(&root, &AMM_PROGRAM, &features)?;
+            if all {
+                build_program(&root, &CPI_CONSUMER_PROGRAM, &[])?;
+            }
+        }
+        Task::Localnet { skip_build, ledger } => {
+            if !skip_build {
+                build_program(&root, &AMM_PROGRAM, &[])?;
+                build_program(&root, &CPI_CONSUMER_PROGRAM, &[])?;
+            }
+            let anchor_toml = std::fs::read_to_string(root.join("Anchor.toml"))?;
+            let mut validator = Command::new("solana-test-validator");
+            validator
+                .current_dir(&root)
+                .args(["--reset", "--ledger", &ledger]);
+            for program in [&AMM_PROGRAM, &CPI_CONSUMER_PROGRAM] {
+                let program_id = localnet_program_id(&anchor_toml, program.anchor_name)?;
+                let artifact = root
+                    .join(DEPLOY_DIR)
+                    .join(format!("{}.so", program.artifact));
+                if !artifact.exists() {
+                    return Err(format_err!(
+                        "{} is not built, run `cargo xtask build-program --all`",
+                        artifact.display()
+                    ));
+                }
+                println!("deploying {} at {}", program.artifact, program_id);
+                validator.arg("--bpf-program").arg(program_id).arg(artifact);
+            }
+            println!("point http_url and ws_url of client_config.ini to http://127.0.0.1:8899 and ws://127.0.0.1:8900");
+            run(&mut validator)?;
+        }
+        Task::Idl => {
+            std::fs::create_dir_all(root.join(IDL_DIR))?;
+            let out_file = Path::new(IDL_DIR).join(format!("{}.json", AMM_PROGRAM.artifact));
+            run(Command::new("anchor").current_dir(&root).args([
+                "idl",
+                "parse",
+                "--file",
+                "programs/amm/src/lib.rs",
+                "--out",
+                out_file.to_str().unwrap(),
+            ]))?;
+            println!("idl written to {}", out_file.display());
+        }
+        Task::IntegrationTest {
+            build_program: build,
+        } => {
+            if build {
+                build_program(&root, &AMM_PROGRAM, &[])?;
+                build_program(&root, &CPI_CONSUMER_PROGRAM, &[])?;
+            }
+            run(Command::new("cargo")
+                .current_dir(&root)
+                .args(["test", "-p", "raydium-amm-v3"]))?;
+            run(Command::new("cargo")
+                .current_dir(&root)
+                .args(["test", "-p", "cpi-consumer"]))?;
+            run(Command::new("cargo")
+                .current_dir(&root)
+                .args(["test", "-p", "client"]))?;
+        }
+    }
+    Ok(())
+}
+
+/// The workspace root, the parent of the xtask crate
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .unwrap()
+        .to_path_buf()
+}
+
+fn build_program(root: &Path, program: &WorkspaceProgram, features: &[String]) -> Result<()> {
+    let mut command = Command::new("cargo");
+    command
+        .current_dir(root)
+        .args(["build-sbf", "--manifest-path", program.manifest]);
+    if !features.is_empty() {
+        command.args(["--features", &features.join(",")]);
+    }
+    run(&mut command)?;
+    println!(
+        "built {}",
+        Path::new(DEPLOY_DIR)
+            .join(format!("{}.so", program.artifact))
+            .display()
+    );
+    Ok(())
+}
+
+/// The id of the program under `[programs.localnet]` of Anchor.toml
+fn localnet_program_id(anchor_toml: &str, anchor_name: &str) -> Result<String> {
+    let mut in_localnet = false;
+    for line in anchor_toml.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_localnet = line == "[programs.localnet]";
+            continue;
+        }
+        if !in_localnet {
+            continue;
+        }
+        if let Some((name, program_id)) = line.split_once('=') {
+            if name.trim() == anchor_name {
+                return Ok(program_id.trim().trim_matches('"').to_string());
+            }
+        }
+    }
+    Err(format_err!(
+        "{} is not listed under [programs.localnet] of Anchor.toml",
+        anchor_name
+    ))
+}
+
+fn run(command: &mut Command) -> Result<()> {
+    println!("running {:?}", command);
+    let status = command.status()?;
+    if !status.success() {
+        return Err(format_err!("{:?} failed with {}", command, status));
+    }
+    Ok(())
+}